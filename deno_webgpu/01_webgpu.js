@@ -2343,8 +2343,26 @@
       webidl.illegalConstructor();
     }
 
+    /**
+     * @returns {Promise<GPUCompilationInfo>}
+     */
     compilationInfo() {
-      throw new Error("Not yet implemented");
+      webidl.assertBranded(this, GPUShaderModule);
+      const prefix =
+        "Failed to execute 'compilationInfo' on 'GPUShaderModule'";
+      const shaderModuleRid = assertResource(this, {
+        prefix,
+        context: "this",
+      });
+      const messages = core.opSync(
+        "op_webgpu_shader_module_compilation_info",
+        { shaderModuleRid },
+      );
+      return PromiseResolve(ObjectFreeze({
+        messages: ObjectFreeze(
+          ArrayPrototypeMap(messages, (message) => ObjectFreeze(message)),
+        ),
+      }));
     }
 
     [SymbolFor("Deno.privateCustomInspect")](inspect) {
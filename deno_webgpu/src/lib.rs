@@ -884,5 +884,9 @@ fn declare_webgpu_ops() -> Vec<(&'static str, Box<OpFn>)> {
             "op_webgpu_create_shader_module",
             op_sync(shader::op_webgpu_create_shader_module),
         ),
+        (
+            "op_webgpu_shader_module_compilation_info",
+            op_sync(shader::op_webgpu_shader_module_compilation_info),
+        ),
     ]
 }
@@ -81,6 +81,7 @@ pub fn op_webgpu_create_compute_pipeline(
             entry_point: Cow::from(args.compute.entry_point),
             // TODO(lucacasonato): support args.compute.constants
         },
+        cache: None,
     };
     let implicit_pipelines = match args.layout {
         Some(_) => None,
@@ -359,6 +360,7 @@ pub fn op_webgpu_create_render_pipeline(
         multisample: args.multisample.into(),
         fragment,
         multiview: None,
+        cache: None,
     };
 
     let implicit_pipelines = match args.layout {
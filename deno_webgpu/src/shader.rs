@@ -4,11 +4,16 @@ use deno_core::error::AnyError;
 use deno_core::ResourceId;
 use deno_core::{OpState, Resource};
 use serde::Deserialize;
+use serde::Serialize;
 use std::borrow::Cow;
 
+use super::error::WebGpuError;
 use super::error::WebGpuResult;
 
-pub(crate) struct WebGpuShaderModule(pub(crate) wgpu_core::id::ShaderModuleId);
+pub(crate) struct WebGpuShaderModule(
+    pub(crate) wgpu_core::id::ShaderModuleId,
+    wgpu_types::CompilationInfo,
+);
 impl Resource for WebGpuShaderModule {
     fn name(&self) -> Cow<str> {
         "webGPUShaderModule".into()
@@ -42,10 +47,57 @@ pub fn op_webgpu_create_shader_module(
         shader_bound_checks: wgpu_types::ShaderBoundChecks::default(),
     };
 
-    gfx_put!(device => instance.device_create_shader_module(
-    device,
-    &descriptor,
-    source,
-    std::marker::PhantomData
-  ) => state, WebGpuShaderModule)
+    let (shader_module, maybe_err) = gfx_select!(device => instance.device_create_shader_module(device, &descriptor, source, std::marker::PhantomData));
+    let compilation_info = maybe_err
+        .as_ref()
+        .map(|err| err.compilation_info())
+        .unwrap_or_default();
+
+    let rid = state
+        .resource_table
+        .add(WebGpuShaderModule(shader_module, compilation_info));
+
+    Ok(WebGpuResult::rid_err(rid, maybe_err.map(WebGpuError::from)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShaderModuleCompilationInfoArgs {
+    shader_module_rid: ResourceId,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuCompilationMessage {
+    message: String,
+    #[serde(rename = "type")]
+    message_type: wgpu_types::CompilationMessageType,
+    line_num: u64,
+    line_pos: u64,
+    offset: u64,
+    length: u64,
+}
+
+pub fn op_webgpu_shader_module_compilation_info(
+    state: &mut OpState,
+    args: ShaderModuleCompilationInfoArgs,
+    _: (),
+) -> Result<Vec<GpuCompilationMessage>, AnyError> {
+    let shader_module_resource = state
+        .resource_table
+        .get::<WebGpuShaderModule>(args.shader_module_rid)?;
+
+    Ok(shader_module_resource
+        .1
+        .messages
+        .iter()
+        .map(|message| GpuCompilationMessage {
+            message: message.message.clone(),
+            message_type: message.message_type,
+            line_num: message.line_num,
+            line_pos: message.line_pos,
+            offset: message.offset,
+            length: message.length,
+        })
+        .collect())
 }
@@ -0,0 +1,275 @@
+/*! This is a minimizer of WebGPU traces.
+ *  It replays smaller and smaller parts of a trace, each in a separate process,
+ *  and keeps the smallest one that still fails the same way.
+ */
+
+use player::{minimize, DataSource as _, GlobalPlay as _, IdentityPassThroughFactory, TraceReader};
+use wgc::{device::trace, gfx_select};
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+const USAGE: &str = "\
+Usage: minimize [options] <trace-dir> <output-dir>
+
+Options:
+    --backend <name>    Replay on vulkan, metal, dx12, dx11, gl or cpu,
+                        instead of the backend of the trace
+    --expect <text>     Keep the parts of the trace that panic with a message
+                        containing <text>, instead of the exact message of the
+                        original trace
+    -h, --help          Print this message
+";
+
+/// File that a replay writes its panic message into.
+const PANIC_FILE_NAME: &str = "panic.txt";
+
+struct Options {
+    input: PathBuf,
+    output: PathBuf,
+    backend: Option<String>,
+    expect: Option<String>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        let mut backend = None;
+        let mut expect = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => backend = args.next(),
+                "--expect" => expect = args.next(),
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+        let output = paths.pop();
+        let input = paths.pop();
+        match (input, output) {
+            (Some(input), Some(output)) if paths.is_empty() => Ok(Self {
+                input,
+                output,
+                backend,
+                expect,
+            }),
+            _ => Err("Provide the trace and output dir paths as the parameters".to_string()),
+        }
+    }
+}
+
+/// How a replay ended.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Success,
+    Panic(String),
+    /// Failed without a panic, e.g. killed by a signal.
+    Crash,
+}
+
+/// Replay `trace.ron` in the directory, writing the panic message next to it.
+fn replay(dir: &Path, backend: Option<wgt::Backend>) {
+    let panic_path = dir.join(PANIC_FILE_NAME);
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "Box<dyn Any>".to_string(),
+            },
+        };
+        let _ = fs::write(&panic_path, message);
+    }));
+
+    let mut trace = TraceReader::open(dir);
+    let global = wgc::hub::Global::new("player", IdentityPassThroughFactory, wgt::Backends::all());
+    let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
+
+    let device = match trace.next() {
+        Some(trace::Action::Init {
+            desc,
+            backend: trace_backend,
+        }) => {
+            let backend = backend.unwrap_or(trace_backend);
+            let adapter = global
+                .request_adapter(
+                    &wgc::instance::RequestAdapterOptions {
+                        power_preference: wgt::PowerPreference::LowPower,
+                        force_fallback_adapter: false,
+                        compatible_surface: None,
+                    },
+                    wgc::instance::AdapterInputs::IdSet(
+                        &[wgc::id::TypedId::zip(0, 0, backend)],
+                        |id| id.backend(),
+                    ),
+                )
+                .expect("Unable to find an adapter for selected backend");
+            let id = wgc::id::TypedId::zip(1, 0, backend);
+            let (_, error) = gfx_select!(adapter => global.adapter_request_device(
+                adapter,
+                &desc,
+                None,
+                id
+            ));
+            if let Some(e) = error {
+                panic!("{:?}", e);
+            }
+            id
+        }
+        _ => panic!("Expected Action::Init"),
+    };
+
+    let mut data_source = dir;
+    for action in trace {
+        gfx_select!(device => global.process(device, action, &mut data_source, &mut command_buffer_id_manager));
+    }
+    gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
+}
+
+/// Replay `trace.ron` in the directory in a child process.
+fn run(dir: &Path, backend: Option<&str>) -> Outcome {
+    let panic_path = dir.join(PANIC_FILE_NAME);
+    let _ = fs::remove_file(&panic_path);
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command.arg("--replay").arg(dir);
+    if let Some(backend) = backend {
+        command.arg("--backend").arg(backend);
+    }
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("Unable to launch the replay");
+    if status.success() {
+        Outcome::Success
+    } else {
+        match fs::read_to_string(&panic_path) {
+            Ok(message) => Outcome::Panic(message),
+            Err(_) => Outcome::Crash,
+        }
+    }
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("--replay") {
+        let dir = PathBuf::from(args.nth(1).expect("Missing the replay dir"));
+        let backend = match (args.next().as_deref(), args.next()) {
+            (Some("--backend"), Some(name)) => Some(player::parse_backend(&name).unwrap()),
+            _ => None,
+        };
+        replay(&dir, backend);
+        return;
+    }
+
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(1);
+        }
+    };
+    if let Some(ref name) = options.backend {
+        if let Err(message) = player::parse_backend(name) {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+    }
+
+    log::info!("Loading trace '{:?}'", options.input);
+    let mut reader = TraceReader::open(&options.input);
+    let actions: Vec<_> = reader.by_ref().collect();
+    if actions.iter().any(|action| {
+        matches!(
+            *action,
+            trace::Action::ConfigureSurface(..)
+                | trace::Action::GetSurfaceTexture { .. }
+                | trace::Action::Present(_)
+                | trace::Action::DiscardSurfaceTexture(_)
+        )
+    }) {
+        eprintln!("Traces that use a surface can't be minimized");
+        std::process::exit(1);
+    }
+
+    let dir = options.output.as_path();
+    fs::create_dir_all(dir).unwrap();
+    if dir.join(trace::BINARY_FILE_NAME).exists() {
+        eprintln!("The output dir already has a binary trace");
+        std::process::exit(1);
+    }
+    for name in actions.iter().filter_map(minimize::data_file) {
+        fs::write(dir.join(name), reader.load(name)).unwrap();
+    }
+
+    let trace_path = dir.join(trace::FILE_NAME);
+    let backend = options.backend.as_deref();
+    let selection = minimize::Selection::all(&actions);
+    minimize::write_ron(&actions, &selection, &trace_path).unwrap();
+    let expected = run(dir, backend);
+    let (total_actions, total_commands) = selection.count();
+    match expected {
+        Outcome::Success => {
+            eprintln!("The trace replays without issues");
+            std::process::exit(1);
+        }
+        Outcome::Panic(ref message) => println!("The trace panics with: {}", message),
+        Outcome::Crash => println!("The trace crashes"),
+    }
+    if let (Some(text), Outcome::Panic(message)) = (options.expect.as_ref(), &expected) {
+        if !message.contains(text.as_str()) {
+            eprintln!("The panic message doesn't contain '{}'", text);
+            std::process::exit(1);
+        }
+    }
+
+    let mut attempts = 0;
+    let selection = minimize::minimize(&actions, &mut |selection| {
+        minimize::write_ron(&actions, selection, &trace_path).unwrap();
+        attempts += 1;
+        let outcome = run(dir, backend);
+        let reproduced = match (outcome, &expected, options.expect.as_ref()) {
+            (Outcome::Panic(message), _, Some(text)) => message.contains(text.as_str()),
+            (outcome, expected, _) => outcome == *expected,
+        };
+        if reproduced {
+            let (kept_actions, kept_commands) = selection.count();
+            println!(
+                "Attempt {}: reproduced with {} actions and {} commands",
+                attempts, kept_actions, kept_commands
+            );
+        }
+        reproduced
+    });
+
+    minimize::write_ron(&actions, &selection, &trace_path).unwrap();
+    let _ = fs::remove_file(dir.join(PANIC_FILE_NAME));
+    let used = actions
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| selection.contains(index))
+        .filter_map(|(_, action)| minimize::data_file(action))
+        .collect::<HashSet<_>>();
+    for name in actions.iter().filter_map(minimize::data_file) {
+        if !used.contains(name) {
+            let _ = fs::remove_file(dir.join(name));
+        }
+    }
+
+    let (kept_actions, kept_commands) = selection.count();
+    println!(
+        "Reduced {} actions and {} commands to {} actions and {} commands in {:?}",
+        total_actions, total_commands, kept_actions, kept_commands, trace_path
+    );
+}
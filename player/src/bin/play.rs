@@ -1,33 +1,310 @@
 /*! This is a player for WebGPU traces.
 !*/
 
-use player::{GlobalPlay as _, IdentityPassThroughFactory};
+use player::{GlobalPlay as _, IdentityPassThroughFactory, TraceReader};
 use wgc::{device::trace, gfx_select};
 
 use std::{
-    fs,
+    collections::HashMap,
     path::{Path, PathBuf},
 };
 
+const USAGE: &str = "\
+Usage: play [options] <trace-dir>
+       play stats [--top <n>] <trace-dir>
+
+Options:
+    --backend <name>    Replay on vulkan, metal, dx12, dx11, gl or cpu,
+                        instead of the backend of the trace
+    --frame <n>         Stop at frame <n>, counted from 0, before it's presented
+    --step              Pause after every submission
+    --dump <path>       Write a texture to <path> whenever the replay stops or pauses,
+                        as PNG if the path ends with `.png`, or as raw texels otherwise
+    --texture <label>   Dump the texture with the given label instead of the surface
+    -h, --help          Print this message
+
+The `stats` command reports the resources and the work in the trace instead,
+listing the <n> largest buffers and textures, 5 by default.
+";
+
+struct Options {
+    dir: PathBuf,
+    backend: Option<wgt::Backend>,
+    frame: Option<usize>,
+    step: bool,
+    dump: Option<PathBuf>,
+    texture: Option<String>,
+}
+
+impl Options {
+    fn parse() -> Result<Self, String> {
+        let mut dir = None;
+        let mut options = Options {
+            dir: PathBuf::new(),
+            backend: None,
+            frame: None,
+            step: false,
+            dump: None,
+            texture: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing the value of {}", arg))
+            };
+            match arg.as_str() {
+                "--backend" => options.backend = Some(player::parse_backend(&value()?)?),
+                "--frame" => {
+                    let frame = value()?;
+                    options.frame = Some(
+                        frame
+                            .parse()
+                            .map_err(|_| format!("Invalid frame {}", frame))?,
+                    );
+                }
+                "--step" => options.step = true,
+                "--dump" => options.dump = Some(PathBuf::from(value()?)),
+                "--texture" => options.texture = Some(value()?),
+                "-h" | "--help" => {
+                    print!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if dir.is_none() && Path::new(&arg).is_dir() => dir = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+        options.dir = dir.ok_or_else(|| "Provide the dir path as the parameter".to_string())?;
+        Ok(options)
+    }
+}
+
+/// State of the replay that the controls look at.
+struct Session {
+    options: Options,
+    /// Number of frames presented so far.
+    frame: usize,
+    /// Number of submissions so far.
+    submission: usize,
+    /// Textures created with a label.
+    textures: HashMap<String, (wgc::id::TextureId, wgt::Extent3d, wgt::TextureFormat)>,
+    surface_config: Option<wgt::SurfaceConfiguration>,
+    surface_texture: Option<wgc::id::TextureId>,
+    /// Readback buffers are allocated past the buffers of the whole trace.
+    next_buffer_index: u32,
+    /// True if the dumped texture was released before the end of the trace.
+    released: bool,
+}
+
+impl Session {
+    fn new(options: Options, next_buffer_index: u32) -> Self {
+        Self {
+            options,
+            frame: 0,
+            submission: 0,
+            textures: HashMap::new(),
+            surface_config: None,
+            surface_texture: None,
+            next_buffer_index,
+            released: false,
+        }
+    }
+
+    /// Keep track of the resources that may be dumped, before an action is processed.
+    fn observe(&mut self, action: &mut trace::Action) {
+        match *action {
+            trace::Action::CreateTexture(id, ref mut desc) => {
+                if let Some(ref label) = desc.label {
+                    // The selected texture needs to be copied to get dumped.
+                    if self.options.dump.is_some()
+                        && self.options.texture.as_deref() == Some(label.as_ref())
+                    {
+                        desc.usage |= wgt::TextureUsages::COPY_SRC;
+                    }
+                    self.textures
+                        .insert(label.to_string(), (id, desc.size, desc.format));
+                }
+            }
+            trace::Action::DestroyTexture(id) | trace::Action::FreeTexture(id) => {
+                self.textures.retain(|_, &mut (texture, ..)| texture != id);
+            }
+            trace::Action::ConfigureSurface(_, ref mut config) => {
+                // The surface texture needs to be copied to get dumped.
+                if self.options.dump.is_some() && self.options.texture.is_none() {
+                    config.usage |= wgt::TextureUsages::COPY_SRC;
+                }
+                self.surface_config = Some(config.clone());
+            }
+            trace::Action::GetSurfaceTexture { id, .. } => {
+                self.surface_texture = Some(id);
+            }
+            trace::Action::Present(_) | trace::Action::DiscardSurfaceTexture(_) => {
+                self.surface_texture = None;
+            }
+            trace::Action::Submit(..) => {
+                self.submission += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if the replay stops at this action, before processing it.
+    fn stops_at(&self, action: &trace::Action) -> bool {
+        match *action {
+            trace::Action::Present(_) => self.options.frame == Some(self.frame),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the action releases the texture that is dumped at the end of the trace.
+    /// It gets dumped right before that instead.
+    fn releases_dumped(&self, action: &trace::Action) -> bool {
+        match *action {
+            trace::Action::DestroyTexture(id) | trace::Action::FreeTexture(id)
+                if self.options.frame.is_none() && !self.options.step && !self.released =>
+            {
+                self.options
+                    .texture
+                    .as_ref()
+                    .and_then(|label| self.textures.get(label))
+                    .map(|&(texture, ..)| texture)
+                    == Some(id)
+            }
+            _ => false,
+        }
+    }
+
+    /// Write the selected texture to disk, if requested.
+    fn dump(
+        &mut self,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) {
+        let path = match self.options.dump {
+            Some(ref path) => path.clone(),
+            None => return,
+        };
+        let (texture, size, format) = match self.options.texture {
+            Some(ref label) => match self.textures.get(label) {
+                Some(&entry) => entry,
+                None => {
+                    log::error!("No texture is labeled '{}'", label);
+                    return;
+                }
+            },
+            None => match (self.surface_texture, self.surface_config.as_ref()) {
+                (Some(id), Some(config)) => (
+                    id,
+                    wgt::Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    config.format,
+                ),
+                _ => {
+                    log::error!("There is no surface texture to dump");
+                    return;
+                }
+            },
+        };
+
+        let buffer = wgc::id::TypedId::zip(self.next_buffer_index, 1, device.backend());
+        self.next_buffer_index += 1;
+        let data = gfx_select!(device => global.read_texture(device, texture, size, format, buffer, comb_manager));
+
+        let swizzle = match format {
+            wgt::TextureFormat::Rgba8Unorm | wgt::TextureFormat::Rgba8UnormSrgb => Some(false),
+            wgt::TextureFormat::Bgra8Unorm | wgt::TextureFormat::Bgra8UnormSrgb => Some(true),
+            _ => None,
+        };
+        let result = match (path.extension(), swizzle) {
+            (Some(ext), Some(swizzle)) if ext == "png" => write_png(&path, size, data, swizzle),
+            (Some(ext), None) if ext == "png" => {
+                log::error!("Unable to write a texture of format {:?} as PNG", format);
+                return;
+            }
+            _ => std::fs::write(&path, data),
+        };
+        match result {
+            Ok(()) => println!("Dumped the texture into {:?}", path),
+            Err(e) => log::error!("Unable to write {:?}: {:?}", path, e),
+        }
+    }
+}
+
+fn write_png(
+    path: &Path,
+    size: wgt::Extent3d,
+    mut data: Vec<u8>,
+    swizzle: bool,
+) -> Result<(), std::io::Error> {
+    if swizzle {
+        for texel in data.chunks_exact_mut(4) {
+            texel.swap(0, 2);
+        }
+    }
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, size.width, size.height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+fn print_stats(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let mut dir = None;
+    let mut top = 5;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => {
+                let value = args.next().unwrap_or_default();
+                top = value
+                    .parse()
+                    .map_err(|_| format!("Invalid number of resources {}", value))?;
+            }
+            _ if dir.is_none() && Path::new(&arg).is_dir() => dir = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+    let dir = dir.ok_or_else(|| "Provide the dir path as the parameter".to_string())?;
+
+    let mut trace = TraceReader::open(&dir);
+    let mut stats = player::stats::Stats::new(top);
+    while let Some(action) = trace.next() {
+        stats.add(&action, &mut trace);
+    }
+    print!("{}", stats);
+    Ok(())
+}
+
 fn main() {
     #[cfg(feature = "winit")]
     use winit::{event_loop::EventLoop, window::WindowBuilder};
 
     env_logger::init();
 
-    //TODO: setting for the backend bits
-    //TODO: setting for the target frame, or controls
+    if std::env::args().nth(1).as_deref() == Some("stats") {
+        if let Err(message) = print_stats(std::env::args().skip(2)) {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let dir = match std::env::args().nth(1) {
-        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
-        _ => panic!("Provide the dir path as the parameter"),
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(1);
+        }
     };
 
-    log::info!("Loading trace '{:?}'", dir);
-    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
-    let mut actions: Vec<trace::Action> = ron::de::from_reader(file).unwrap();
-    actions.reverse(); // allows us to pop from the top
-    log::info!("Found {} actions", actions.len());
+    log::info!("Loading trace '{:?}'", options.dir);
+    let mut trace = TraceReader::open(&options.dir);
 
     #[cfg(feature = "winit")]
     let event_loop = {
@@ -48,8 +325,9 @@ fn main() {
     let surface =
         global.instance_create_surface(&window, wgc::id::TypedId::zip(0, 1, wgt::Backend::Empty));
 
-    let device = match actions.pop() {
+    let device = match trace.next() {
         Some(trace::Action::Init { desc, backend }) => {
+            let backend = options.backend.unwrap_or(backend);
             log::info!("Initializing the device for backend: {:?}", backend);
             let adapter = global
                 .request_adapter(
@@ -85,17 +363,64 @@ fn main() {
         _ => panic!("Expected Action::Init"),
     };
 
+    // A texture can be dumped before the trace creates all of its buffers,
+    // so the readback buffers can't reuse any index of the trace.
+    let next_buffer_index = if options.dump.is_some() {
+        TraceReader::open(&options.dir)
+            .filter_map(|action| match action {
+                trace::Action::CreateBuffer(id, _) => Some(wgc::id::TypedId::unzip(id).0 + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    let mut session = Session::new(options, next_buffer_index);
+
     log::info!("Executing actions");
     #[cfg(not(feature = "winit"))]
     {
+        use std::io::BufRead as _;
+
         gfx_select!(device => global.device_start_capture(device));
 
-        while let Some(action) = actions.pop() {
-            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+        let stdin = std::io::stdin();
+        let mut stopped = false;
+        while let Some(mut action) = trace.next() {
+            if session.stops_at(&action) {
+                session.dump(&global, device, &mut command_buffer_id_manager);
+                println!("Stopped at frame {}", session.frame);
+                stopped = true;
+                break;
+            }
+            if session.releases_dumped(&action) {
+                session.dump(&global, device, &mut command_buffer_id_manager);
+                session.released = true;
+            }
+            session.observe(&mut action);
+            let is_submit = matches!(action, trace::Action::Submit(..));
+            gfx_select!(device => global.process(device, action, &mut trace, &mut command_buffer_id_manager));
+            if is_submit && session.options.step {
+                session.dump(&global, device, &mut command_buffer_id_manager);
+                println!(
+                    "Paused after submission {}, press Enter to continue",
+                    session.submission
+                );
+                let _ = stdin.lock().read_line(&mut String::new());
+            }
+        }
+        if !stopped {
+            if session.options.frame.is_some() {
+                log::warn!("The trace ended at frame {}", session.frame);
+            }
+            if !session.options.step && !session.released {
+                session.dump(&global, device, &mut command_buffer_id_manager);
+            }
         }
 
         gfx_select!(device => global.device_stop_capture(device));
-        gfx_select!(device => global.device_poll(device, true)).unwrap();
+        gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
     }
     #[cfg(feature = "winit")]
     {
@@ -105,17 +430,41 @@ fn main() {
         };
 
         let mut resize_config = None;
-        let mut frame_count = 0;
         let mut done = false;
+        let mut paused = false;
         event_loop.run(move |event, _, control_flow| {
             *control_flow = ControlFlow::Poll;
             match event {
                 Event::MainEventsCleared => {
                     window.request_redraw();
                 }
-                Event::RedrawRequested(_) if resize_config.is_none() => loop {
-                    match actions.pop() {
-                        Some(trace::Action::ConfigureSurface(_device_id, config)) => {
+                Event::RedrawRequested(_) if resize_config.is_none() && !paused && !done => loop {
+                    let mut action = match trace.next() {
+                        Some(action) => action,
+                        None => {
+                            println!("Finished the end at frame {}", session.frame);
+                            if session.options.frame.is_none()
+                                && !session.options.step
+                                && !session.released
+                            {
+                                session.dump(&global, device, &mut command_buffer_id_manager);
+                            }
+                            done = true;
+                            break;
+                        }
+                    };
+                    if session.stops_at(&action) {
+                        session.dump(&global, device, &mut command_buffer_id_manager);
+                        println!("Stopped at frame {}", session.frame);
+                        done = true;
+                    }
+                    if session.releases_dumped(&action) {
+                        session.dump(&global, device, &mut command_buffer_id_manager);
+                        session.released = true;
+                    }
+                    session.observe(&mut action);
+                    match action {
+                        trace::Action::ConfigureSurface(_device_id, config) => {
                             log::info!("Configuring the surface");
                             let current_size: (u32, u32) = window.inner_size().into();
                             let size = (config.width, config.height);
@@ -133,27 +482,30 @@ fn main() {
                                 }
                             }
                         }
-                        Some(trace::Action::Present(id)) => {
-                            frame_count += 1;
-                            log::debug!("Presenting frame {}", frame_count);
+                        trace::Action::Present(id) => {
+                            session.frame += 1;
+                            log::debug!("Presenting frame {}", session.frame);
                             gfx_select!(device => global.surface_present(id)).unwrap();
                             break;
                         }
-                        Some(trace::Action::DiscardSurfaceTexture(id)) => {
-                            log::debug!("Discarding frame {}", frame_count);
+                        trace::Action::DiscardSurfaceTexture(id) => {
+                            log::debug!("Discarding frame {}", session.frame);
                             gfx_select!(device => global.surface_texture_discard(id)).unwrap();
                             break;
                         }
-                        Some(action) => {
-                            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
-                        }
-                        None => {
-                            if !done {
-                                println!("Finished the end at frame {}", frame_count);
-                                done = true;
-                            }
+                        trace::Action::Submit(..) if session.options.step => {
+                            gfx_select!(device => global.process(device, action, &mut trace, &mut command_buffer_id_manager));
+                            session.dump(&global, device, &mut command_buffer_id_manager);
+                            println!(
+                                "Paused after submission {}, press Space to continue",
+                                session.submission
+                            );
+                            paused = true;
                             break;
                         }
+                        action => {
+                            gfx_select!(device => global.process(device, action, &mut trace, &mut command_buffer_id_manager));
+                        }
                     }
                 },
                 Event::WindowEvent { event, .. } => match event {
@@ -165,6 +517,17 @@ fn main() {
                             }
                         }
                     }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(VirtualKeyCode::Space),
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    } => {
+                        paused = false;
+                    }
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
@@ -181,7 +544,7 @@ fn main() {
                 },
                 Event::LoopDestroyed => {
                     log::info!("Closing");
-                    gfx_select!(device => global.device_poll(device, true)).unwrap();
+                    gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
                 }
                 _ => {}
             }
@@ -6,9 +6,18 @@
  *   so that we don't accidentally try to use the same ID.
 !*/
 
+pub mod minimize;
+pub mod stats;
+
 use wgc::device::trace;
 
-use std::{borrow::Cow, fmt::Debug, fs, marker::PhantomData, path::Path};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    fs, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug)]
 pub struct IdentityPassThrough<I>(PhantomData<I>);
@@ -34,6 +43,93 @@ impl<I: Clone + Debug + wgc::id::TypedId> wgc::hub::IdentityHandlerFactory<I>
 }
 impl wgc::hub::GlobalIdentityHandlerFactory for IdentityPassThroughFactory {}
 
+/// Parse the name of a backend, as given on the command line.
+pub fn parse_backend(name: &str) -> Result<wgt::Backend, String> {
+    Ok(match name.to_lowercase().as_str() {
+        "vulkan" => wgt::Backend::Vulkan,
+        "metal" => wgt::Backend::Metal,
+        "dx12" => wgt::Backend::Dx12,
+        "dx11" => wgt::Backend::Dx11,
+        "gl" => wgt::Backend::Gl,
+        "cpu" => wgt::Backend::Cpu,
+        other => return Err(format!("Unknown backend {}", other)),
+    })
+}
+
+/// Source of the data that actions refer to by name.
+pub trait DataSource {
+    fn load(&mut self, name: &str) -> Vec<u8>;
+    fn size(&mut self, name: &str) -> u64 {
+        self.load(name).len() as u64
+    }
+}
+
+/// Data files in a directory.
+impl DataSource for &Path {
+    fn load(&mut self, name: &str) -> Vec<u8> {
+        fs::read(self.join(name)).unwrap()
+    }
+    fn size(&mut self, name: &str) -> u64 {
+        fs::metadata(self.join(name)).unwrap().len()
+    }
+}
+
+/// Trace recorded either in RON or in the binary format.
+pub enum TraceReader {
+    Ron {
+        dir: PathBuf,
+        actions: std::vec::IntoIter<trace::Action<'static>>,
+    },
+    Binary(trace::BinaryReader),
+}
+
+impl TraceReader {
+    /// Open the trace in a directory, preferring the binary format.
+    pub fn open(dir: &Path) -> Self {
+        let binary_path = dir.join(trace::BINARY_FILE_NAME);
+        if binary_path.exists() {
+            let file = io::BufReader::new(fs::File::open(binary_path).unwrap());
+            Self::Binary(trace::BinaryReader::new(file).unwrap())
+        } else {
+            let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
+            let actions: Vec<trace::Action<'static>> = ron::de::from_reader(file).unwrap();
+            Self::Ron {
+                dir: dir.to_path_buf(),
+                actions: actions.into_iter(),
+            }
+        }
+    }
+}
+
+impl Iterator for TraceReader {
+    type Item = trace::Action<'static>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            Self::Ron {
+                ref mut actions, ..
+            } => actions.next(),
+            Self::Binary(ref mut reader) => reader.next_action().unwrap(),
+        }
+    }
+}
+
+impl DataSource for TraceReader {
+    fn load(&mut self, name: &str) -> Vec<u8> {
+        match *self {
+            Self::Ron { ref dir, .. } => fs::read(dir.join(name)).unwrap(),
+            Self::Binary(ref mut reader) => reader
+                .take_blob(name)
+                .unwrap_or_else(|| panic!("Missing blob {}", name)),
+        }
+    }
+    fn size(&mut self, name: &str) -> u64 {
+        match *self {
+            Self::Ron { ref dir, .. } => fs::metadata(dir.join(name)).unwrap().len(),
+            Self::Binary(..) => self.load(name).len() as u64,
+        }
+    }
+}
+
 pub trait GlobalPlay {
     fn encode_commands<A: wgc::hub::HalApi>(
         &self,
@@ -44,9 +140,25 @@ pub trait GlobalPlay {
         &self,
         device: wgc::id::DeviceId,
         action: trace::Action,
-        dir: &Path,
+        data_source: &mut dyn DataSource,
         comb_manager: &mut wgc::hub::IdentityManager,
     );
+    fn read_texture<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        texture: wgc::id::TextureId,
+        size: wgt::Extent3d,
+        format: wgt::TextureFormat,
+        buffer: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Vec<u8>;
+}
+
+extern "C" fn map_callback(status: wgc::resource::BufferMapAsyncStatus, _user_data: *mut u8) {
+    match status {
+        wgc::resource::BufferMapAsyncStatus::Success => (),
+        _ => panic!("Unable to map"),
+    }
 }
 
 impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
@@ -148,7 +260,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
         &self,
         device: wgc::id::DeviceId,
         action: trace::Action,
-        dir: &Path,
+        data_source: &mut dyn DataSource,
         comb_manager: &mut wgc::hub::IdentityManager,
     ) {
         use wgc::device::trace::Action;
@@ -251,7 +363,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             }
             Action::CreateShaderModule { id, desc, data } => {
                 log::info!("Creating shader from {}", data);
-                let code = fs::read_to_string(dir.join(&data)).unwrap();
+                let code = String::from_utf8(data_source.load(&data)).unwrap();
                 let source = if data.ends_with(".wgsl") {
                     wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code))
                 } else if data.ends_with(".ron") {
@@ -268,6 +380,16 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             Action::DestroyShaderModule(id) => {
                 self.shader_module_drop::<A>(id);
             }
+            Action::CreatePipelineCache { id, desc } => {
+                let (_, error) =
+                    unsafe { self.device_create_pipeline_cache::<A>(device, &desc, id) };
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+            }
+            Action::DestroyPipelineCache(id) => {
+                self.pipeline_cache_drop::<A>(id);
+            }
             Action::CreateComputePipeline {
                 id,
                 desc,
@@ -343,7 +465,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 range,
                 queued,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
+                let bin = data_source.load(&data);
                 let size = (range.end - range.start) as usize;
                 if queued {
                     self.queue_write_buffer::<A>(device, id, range.start, &bin)
@@ -360,7 +482,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 layout,
                 size,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
+                let bin = data_source.load(&data);
                 self.queue_write_texture::<A>(device, &to, &bin, &layout, &size)
                     .unwrap();
             }
@@ -381,4 +503,102 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             }
         }
     }
+
+    /// Copy the first layer of a texture into a temporary buffer with the given ID,
+    /// and return its rows of texel blocks without padding.
+    fn read_texture<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        texture: wgc::id::TextureId,
+        size: wgt::Extent3d,
+        format: wgt::TextureFormat,
+        buffer: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Vec<u8> {
+        let info = format.describe();
+        let (block_width, block_height) = (
+            info.block_dimensions.0 as u32,
+            info.block_dimensions.1 as u32,
+        );
+        let row_size = (size.width + block_width - 1) / block_width * info.block_size as u32;
+        let row_count = (size.height + block_height - 1) / block_height;
+        let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let bytes_per_row = (row_size + align - 1) / align * align;
+        let buffer_size = bytes_per_row as wgt::BufferAddress * row_count as wgt::BufferAddress;
+
+        self.device_maintain_ids::<A>(device).unwrap();
+        let (_, error) = self.device_create_buffer::<A>(
+            device,
+            &wgc::resource::BufferDescriptor {
+                label: Some(Cow::Borrowed("readback")),
+                size: buffer_size,
+                usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+            buffer,
+        );
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+
+        let (encoder, error) = self.device_create_command_encoder::<A>(
+            device,
+            &wgt::CommandEncoderDescriptor { label: None },
+            comb_manager.alloc(device.backend()),
+        );
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        self.command_encoder_copy_texture_to_buffer::<A>(
+            encoder,
+            &wgt::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgt::Origin3d::ZERO,
+                aspect: wgt::TextureAspect::All,
+            },
+            &wgt::ImageCopyBuffer {
+                buffer,
+                layout: wgt::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            &wgt::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        )
+        .unwrap();
+        let (cmdbuf, error) = self
+            .command_encoder_finish::<A>(encoder, &wgt::CommandBufferDescriptor { label: None });
+        if let Some(e) = error {
+            panic!("{:?}", e);
+        }
+        self.queue_submit::<A>(device, &[cmdbuf]).unwrap();
+
+        self.buffer_map_async::<A>(
+            buffer,
+            0..buffer_size,
+            wgc::resource::BufferMapOperation {
+                host: wgc::device::HostMap::Read,
+                callback: map_callback,
+                user_data: std::ptr::null_mut(),
+            },
+        )
+        .unwrap();
+        self.device_poll::<A>(device, wgt::Maintain::Wait).unwrap();
+        let (ptr, _) = self
+            .buffer_get_mapped_range::<A>(buffer, 0, Some(buffer_size))
+            .unwrap();
+        let contents = unsafe { std::slice::from_raw_parts(ptr, buffer_size as usize) };
+        let mut data = Vec::with_capacity((row_size * row_count) as usize);
+        for row in contents.chunks(bytes_per_row as usize) {
+            data.extend_from_slice(&row[..row_size as usize]);
+        }
+        self.buffer_unmap::<A>(buffer).unwrap();
+        self.buffer_drop::<A>(buffer, false);
+        data
+    }
 }
@@ -0,0 +1,548 @@
+/*! Reduction of traces to the smallest part that still reproduces an issue.
+ *  Actions are dropped together with everything that depends on the resources
+ *  they create, so the remaining trace stays valid. Then the commands inside
+ *  `Action::Submit` are dropped one chunk at a time in the same way.
+ */
+
+use wgc::device::trace::{Action, Command};
+
+use std::{collections::HashSet, fs, io, io::Write as _, path::Path};
+
+/// A resource that actions create and refer to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Resource {
+    Buffer(wgc::id::BufferId),
+    Texture(wgc::id::TextureId),
+    TextureView(wgc::id::TextureViewId),
+    Sampler(wgc::id::SamplerId),
+    BindGroupLayout(wgc::id::BindGroupLayoutId),
+    PipelineLayout(wgc::id::PipelineLayoutId),
+    BindGroup(wgc::id::BindGroupId),
+    ShaderModule(wgc::id::ShaderModuleId),
+    PipelineCache(wgc::id::PipelineCacheId),
+    ComputePipeline(wgc::id::ComputePipelineId),
+    RenderPipeline(wgc::id::RenderPipelineId),
+    RenderBundle(wgc::id::RenderBundleId),
+    QuerySet(wgc::id::QuerySetId),
+}
+
+/// Resources created and used by an action or a command.
+#[derive(Default)]
+struct Dependencies {
+    creates: Vec<Resource>,
+    uses: Vec<Resource>,
+}
+
+impl Dependencies {
+    fn of_action(action: &Action) -> Self {
+        let mut deps = Self::default();
+        match *action {
+            Action::Init { .. }
+            | Action::ConfigureSurface(..)
+            | Action::Present(_)
+            | Action::DiscardSurfaceTexture(_) => {}
+            Action::CreateBuffer(id, _) => deps.creates.push(Resource::Buffer(id)),
+            Action::FreeBuffer(id) | Action::DestroyBuffer(id) => {
+                deps.uses.push(Resource::Buffer(id))
+            }
+            Action::CreateTexture(id, _) | Action::GetSurfaceTexture { id, .. } => {
+                deps.creates.push(Resource::Texture(id))
+            }
+            Action::FreeTexture(id) | Action::DestroyTexture(id) => {
+                deps.uses.push(Resource::Texture(id))
+            }
+            Action::CreateTextureView { id, parent_id, .. } => {
+                deps.creates.push(Resource::TextureView(id));
+                deps.uses.push(Resource::Texture(parent_id));
+            }
+            Action::DestroyTextureView(id) => deps.uses.push(Resource::TextureView(id)),
+            Action::CreateSampler(id, _) => deps.creates.push(Resource::Sampler(id)),
+            Action::DestroySampler(id) => deps.uses.push(Resource::Sampler(id)),
+            Action::CreateBindGroupLayout(id, _) => {
+                deps.creates.push(Resource::BindGroupLayout(id))
+            }
+            Action::DestroyBindGroupLayout(id) => deps.uses.push(Resource::BindGroupLayout(id)),
+            Action::CreatePipelineLayout(id, ref desc) => {
+                deps.creates.push(Resource::PipelineLayout(id));
+                deps.uses.extend(
+                    desc.bind_group_layouts
+                        .iter()
+                        .map(|&id| Resource::BindGroupLayout(id)),
+                );
+            }
+            Action::DestroyPipelineLayout(id) => deps.uses.push(Resource::PipelineLayout(id)),
+            Action::CreateBindGroup(id, ref desc) => {
+                use wgc::binding_model::BindingResource as Br;
+                deps.creates.push(Resource::BindGroup(id));
+                deps.uses.push(Resource::BindGroupLayout(desc.layout));
+                for entry in desc.entries.iter() {
+                    match entry.resource {
+                        Br::Buffer(ref binding) => {
+                            deps.uses.push(Resource::Buffer(binding.buffer_id))
+                        }
+                        Br::BufferArray(ref bindings) => deps.uses.extend(
+                            bindings
+                                .iter()
+                                .map(|binding| Resource::Buffer(binding.buffer_id)),
+                        ),
+                        Br::Sampler(id) => deps.uses.push(Resource::Sampler(id)),
+                        Br::SamplerArray(ref ids) => deps
+                            .uses
+                            .extend(ids.iter().map(|&id| Resource::Sampler(id))),
+                        Br::TextureView(id) => deps.uses.push(Resource::TextureView(id)),
+                        Br::TextureViewArray(ref ids) => deps
+                            .uses
+                            .extend(ids.iter().map(|&id| Resource::TextureView(id))),
+                    }
+                }
+            }
+            Action::DestroyBindGroup(id) => deps.uses.push(Resource::BindGroup(id)),
+            Action::CreateShaderModule { id, .. } => deps.creates.push(Resource::ShaderModule(id)),
+            Action::DestroyShaderModule(id) => deps.uses.push(Resource::ShaderModule(id)),
+            Action::CreatePipelineCache { id, .. } => {
+                deps.creates.push(Resource::PipelineCache(id))
+            }
+            Action::DestroyPipelineCache(id) => deps.uses.push(Resource::PipelineCache(id)),
+            Action::CreateComputePipeline {
+                id,
+                ref desc,
+                ref implicit_context,
+            } => {
+                deps.creates.push(Resource::ComputePipeline(id));
+                deps.add_implicit_context(implicit_context);
+                deps.uses.extend(desc.layout.map(Resource::PipelineLayout));
+                deps.uses.push(Resource::ShaderModule(desc.stage.module));
+                deps.uses.extend(desc.cache.map(Resource::PipelineCache));
+            }
+            Action::DestroyComputePipeline(id) => deps.uses.push(Resource::ComputePipeline(id)),
+            Action::CreateRenderPipeline {
+                id,
+                ref desc,
+                ref implicit_context,
+            } => {
+                deps.creates.push(Resource::RenderPipeline(id));
+                deps.add_implicit_context(implicit_context);
+                deps.uses.extend(desc.layout.map(Resource::PipelineLayout));
+                deps.uses
+                    .push(Resource::ShaderModule(desc.vertex.stage.module));
+                if let Some(ref fragment) = desc.fragment {
+                    deps.uses
+                        .push(Resource::ShaderModule(fragment.stage.module));
+                }
+                deps.uses.extend(desc.cache.map(Resource::PipelineCache));
+            }
+            Action::DestroyRenderPipeline(id) => deps.uses.push(Resource::RenderPipeline(id)),
+            Action::CreateRenderBundle { id, ref base, .. } => {
+                deps.creates.push(Resource::RenderBundle(id));
+                for command in base.commands.iter() {
+                    deps.add_render_command(command);
+                }
+            }
+            Action::DestroyRenderBundle(id) => deps.uses.push(Resource::RenderBundle(id)),
+            Action::CreateQuerySet { id, .. } => deps.creates.push(Resource::QuerySet(id)),
+            Action::DestroyQuerySet(id) => deps.uses.push(Resource::QuerySet(id)),
+            Action::WriteBuffer { id, .. } => deps.uses.push(Resource::Buffer(id)),
+            Action::WriteTexture { ref to, .. } => deps.uses.push(Resource::Texture(to.texture)),
+            // The commands are tracked on their own.
+            Action::Submit(..) => {}
+        }
+        deps
+    }
+
+    fn of_command(command: &Command) -> Self {
+        let mut deps = Self::default();
+        match *command {
+            Command::CopyBufferToBuffer { src, dst, .. } => {
+                deps.uses.push(Resource::Buffer(src));
+                deps.uses.push(Resource::Buffer(dst));
+            }
+            Command::CopyBufferToTexture {
+                ref src, ref dst, ..
+            } => {
+                deps.uses.push(Resource::Buffer(src.buffer));
+                deps.uses.push(Resource::Texture(dst.texture));
+            }
+            Command::CopyTextureToBuffer {
+                ref src, ref dst, ..
+            } => {
+                deps.uses.push(Resource::Texture(src.texture));
+                deps.uses.push(Resource::Buffer(dst.buffer));
+            }
+            Command::CopyTextureToTexture {
+                ref src, ref dst, ..
+            } => {
+                deps.uses.push(Resource::Texture(src.texture));
+                deps.uses.push(Resource::Texture(dst.texture));
+            }
+            Command::ClearBuffer { dst, .. } => deps.uses.push(Resource::Buffer(dst)),
+            Command::ClearTexture { dst, .. } => deps.uses.push(Resource::Texture(dst)),
+            Command::WriteTimestamp { query_set_id, .. } => {
+                deps.uses.push(Resource::QuerySet(query_set_id))
+            }
+            Command::ResolveQuerySet {
+                query_set_id,
+                destination,
+                ..
+            } => {
+                deps.uses.push(Resource::QuerySet(query_set_id));
+                deps.uses.push(Resource::Buffer(destination));
+            }
+            Command::PushDebugGroup(_) | Command::PopDebugGroup | Command::InsertDebugMarker(_) => {
+            }
+            Command::RunComputePass { ref base } => {
+                use wgc::command::ComputeCommand as Cc;
+                for command in base.commands.iter() {
+                    match *command {
+                        Cc::SetBindGroup { bind_group_id, .. } => {
+                            deps.uses.push(Resource::BindGroup(bind_group_id))
+                        }
+                        Cc::SetPipeline(id) => deps.uses.push(Resource::ComputePipeline(id)),
+                        Cc::DispatchIndirect { buffer_id, .. } => {
+                            deps.uses.push(Resource::Buffer(buffer_id))
+                        }
+                        Cc::WriteTimestamp { query_set_id, .. }
+                        | Cc::BeginPipelineStatisticsQuery { query_set_id, .. } => {
+                            deps.uses.push(Resource::QuerySet(query_set_id))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Command::RunRenderPass {
+                ref base,
+                ref target_colors,
+                ref target_depth_stencil,
+            } => {
+                for at in target_colors.iter() {
+                    deps.uses.push(Resource::TextureView(at.view));
+                    deps.uses
+                        .extend(at.resolve_target.map(Resource::TextureView));
+                }
+                if let Some(ref at) = *target_depth_stencil {
+                    deps.uses.push(Resource::TextureView(at.view));
+                }
+                for command in base.commands.iter() {
+                    deps.add_render_command(command);
+                }
+            }
+        }
+        deps
+    }
+
+    fn add_implicit_context(&mut self, context: &Option<wgc::device::ImplicitPipelineContext>) {
+        if let Some(ref context) = *context {
+            self.creates.push(Resource::PipelineLayout(context.root_id));
+            self.creates.extend(
+                context
+                    .group_ids
+                    .iter()
+                    .map(|&id| Resource::BindGroupLayout(id)),
+            );
+        }
+    }
+
+    fn add_render_command(&mut self, command: &wgc::command::RenderCommand) {
+        use wgc::command::RenderCommand as Rc;
+        match *command {
+            Rc::SetBindGroup { bind_group_id, .. } => {
+                self.uses.push(Resource::BindGroup(bind_group_id))
+            }
+            Rc::SetPipeline(id) => self.uses.push(Resource::RenderPipeline(id)),
+            Rc::SetIndexBuffer { buffer_id, .. }
+            | Rc::SetVertexBuffer { buffer_id, .. }
+            | Rc::MultiDrawIndirect { buffer_id, .. } => {
+                self.uses.push(Resource::Buffer(buffer_id))
+            }
+            Rc::MultiDrawIndirectCount {
+                buffer_id,
+                count_buffer_id,
+                ..
+            } => {
+                self.uses.push(Resource::Buffer(buffer_id));
+                self.uses.push(Resource::Buffer(count_buffer_id));
+            }
+            Rc::WriteTimestamp { query_set_id, .. }
+            | Rc::BeginPipelineStatisticsQuery { query_set_id, .. } => {
+                self.uses.push(Resource::QuerySet(query_set_id))
+            }
+            Rc::ExecuteBundle(id) => self.uses.push(Resource::RenderBundle(id)),
+            _ => {}
+        }
+    }
+}
+
+/// Part of a trace that is kept.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    actions: Vec<bool>,
+    /// Kept commands of each action, empty for anything but `Action::Submit`.
+    commands: Vec<Vec<bool>>,
+}
+
+impl Selection {
+    /// Select the whole trace.
+    pub fn all(actions: &[Action]) -> Self {
+        Self {
+            actions: vec![true; actions.len()],
+            commands: actions
+                .iter()
+                .map(|action| match *action {
+                    Action::Submit(_, ref commands) => vec![true; commands.len()],
+                    _ => Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Number of the kept actions and commands.
+    pub fn count(&self) -> (usize, usize) {
+        let actions = self.actions.iter().filter(|&&keep| keep).count();
+        let commands = self
+            .commands
+            .iter()
+            .zip(self.actions.iter())
+            .filter(|&(_, &keep)| keep)
+            .map(|(commands, _)| commands.iter().filter(|&&keep| keep).count())
+            .sum();
+        (actions, commands)
+    }
+
+    /// Returns true if the action at `index` is kept.
+    pub fn contains(&self, index: usize) -> bool {
+        self.actions[index]
+    }
+}
+
+/// Dependencies of every action and command of a trace.
+struct Graph {
+    actions: Vec<Dependencies>,
+    commands: Vec<Vec<Dependencies>>,
+}
+
+impl Graph {
+    fn new(actions: &[Action]) -> Self {
+        Self {
+            actions: actions.iter().map(Dependencies::of_action).collect(),
+            commands: actions
+                .iter()
+                .map(|action| match *action {
+                    Action::Submit(_, ref commands) => {
+                        commands.iter().map(Dependencies::of_command).collect()
+                    }
+                    _ => Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Drop the actions, and everything that uses the resources they create.
+    fn drop_actions(&self, selection: &Selection, dropped: &[usize]) -> Selection {
+        let mut selection = selection.clone();
+        let mut lost = HashSet::new();
+        for &index in dropped {
+            selection.actions[index] = false;
+        }
+        // Resources are always used after they are created, so a single pass in order is enough.
+        for (index, deps) in self.actions.iter().enumerate() {
+            if selection.actions[index] && deps.uses.iter().any(|res| lost.contains(res)) {
+                selection.actions[index] = false;
+            }
+            if !selection.actions[index] {
+                lost.extend(deps.creates.iter().cloned());
+                continue;
+            }
+            for (keep, deps) in selection.commands[index]
+                .iter_mut()
+                .zip(self.commands[index].iter())
+            {
+                if deps.uses.iter().any(|res| lost.contains(res)) {
+                    *keep = false;
+                }
+            }
+        }
+        selection
+    }
+
+    /// Drop the commands, which nothing else depends on.
+    fn drop_commands(&self, selection: &Selection, dropped: &[(usize, usize)]) -> Selection {
+        let mut selection = selection.clone();
+        for &(action, command) in dropped {
+            selection.commands[action][command] = false;
+        }
+        selection
+    }
+}
+
+/// Remove chunks of units while the test passes, halving the chunks when none can be removed.
+fn reduce<U: Copy>(
+    mut selection: Selection,
+    units: impl Fn(&Selection) -> Vec<U>,
+    remove: impl Fn(&Selection, &[U]) -> Selection,
+    test: &mut dyn FnMut(&Selection) -> bool,
+) -> Selection {
+    let mut granularity = 2;
+    loop {
+        let units = units(&selection);
+        if units.is_empty() {
+            break;
+        }
+        let chunk_size = (units.len() / granularity).max(1);
+        let reduced = units.chunks(chunk_size).any(|chunk| {
+            let candidate = remove(&selection, chunk);
+            if test(&candidate) {
+                selection = candidate;
+                true
+            } else {
+                false
+            }
+        });
+        if reduced {
+            granularity = (granularity - 1).max(2);
+        } else if chunk_size == 1 {
+            break;
+        } else {
+            granularity = (granularity * 2).min(units.len());
+        }
+    }
+    selection
+}
+
+/// Find a small part of the trace for which `test` still returns true.
+///
+/// The first action, `Action::Init`, is always kept.
+pub fn minimize(actions: &[Action], test: &mut dyn FnMut(&Selection) -> bool) -> Selection {
+    let graph = Graph::new(actions);
+    let mut selection = Selection::all(actions);
+    loop {
+        let start = selection.clone();
+        selection = reduce(
+            selection,
+            |selection| {
+                (1..actions.len())
+                    .filter(|&i| selection.actions[i])
+                    .collect()
+            },
+            |selection, dropped| graph.drop_actions(selection, dropped),
+            test,
+        );
+        selection = reduce(
+            selection,
+            |selection| {
+                let mut units = Vec::new();
+                for (action, commands) in selection.commands.iter().enumerate() {
+                    if selection.actions[action] {
+                        units.extend(
+                            commands
+                                .iter()
+                                .enumerate()
+                                .filter(|&(_, &keep)| keep)
+                                .map(|(command, _)| (action, command)),
+                        );
+                    }
+                }
+                units
+            },
+            |selection, dropped| graph.drop_commands(selection, dropped),
+            test,
+        );
+        // Dropping commands may leave resources unused, which lets more actions go.
+        if selection == start {
+            break;
+        }
+    }
+    selection
+}
+
+/// Stand-in for `Action::Submit` with some of the commands dropped.
+#[derive(serde::Serialize)]
+enum PartialAction<'a> {
+    Submit(u64, Vec<&'a Command>),
+}
+
+/// Write the selected part of the trace in RON.
+pub fn write_ron(actions: &[Action], selection: &Selection, path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    file.write_all(b"[\n")?;
+    let config = ron::ser::PrettyConfig::default();
+    for (index, action) in actions.iter().enumerate() {
+        if !selection.actions[index] {
+            continue;
+        }
+        let result = match *action {
+            Action::Submit(submit_index, ref commands) => {
+                let partial = PartialAction::Submit(
+                    submit_index,
+                    commands
+                        .iter()
+                        .zip(selection.commands[index].iter())
+                        .filter(|&(_, &keep)| keep)
+                        .map(|(command, _)| command)
+                        .collect(),
+                );
+                ron::ser::to_string_pretty(&partial, config.clone())
+            }
+            _ => ron::ser::to_string_pretty(action, config.clone()),
+        };
+        let string = result.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        writeln!(file, "{},", string)?;
+    }
+    file.write_all(b"]")?;
+    file.flush()
+}
+
+/// Name of the data file that the action refers to, if any.
+pub fn data_file<'a>(action: &'a Action) -> Option<&'a str> {
+    match *action {
+        Action::CreateShaderModule { ref data, .. }
+        | Action::WriteBuffer { ref data, .. }
+        | Action::WriteTexture { ref data, .. } => Some(data.as_str()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_drop_dependents() {
+    let buffer = |index| wgc::id::TypedId::zip(index, 1, wgt::Backend::Empty);
+    let create = |index| {
+        Action::CreateBuffer(
+            buffer(index),
+            wgc::resource::BufferDescriptor {
+                label: None,
+                size: 16,
+                usage: wgt::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        )
+    };
+    let clear = |index| Command::ClearBuffer {
+        dst: buffer(index),
+        offset: 0,
+        size: None,
+    };
+    let actions = vec![
+        Action::Init {
+            desc: wgt::DeviceDescriptor::default(),
+            backend: wgt::Backend::Empty,
+        },
+        create(0),
+        create(1),
+        Action::WriteBuffer {
+            id: buffer(0),
+            data: "data1.bin".to_string(),
+            range: 0..16,
+            queued: true,
+        },
+        Action::Submit(1, vec![clear(0), clear(1)]),
+        Action::DestroyBuffer(buffer(0)),
+    ];
+
+    // The issue is reproduced by clearing the second buffer.
+    let selection = minimize(&actions, &mut |selection| {
+        selection.actions[2] && selection.actions[4] && selection.commands[4][1]
+    });
+    assert_eq!(
+        selection.actions,
+        vec![true, false, true, false, true, false]
+    );
+    assert_eq!(selection.commands[4], vec![false, true]);
+}
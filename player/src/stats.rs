@@ -0,0 +1,359 @@
+/*! Statistics of the resources and the work in a trace.
+ *  Frames are delimited by `Action::Present`, so a trace without a surface
+ *  is reported as a single frame.
+ */
+
+use crate::DataSource;
+use wgc::{
+    device::trace::{Action, Command},
+    id::TypedId,
+};
+
+use std::{collections::HashMap, fmt};
+
+/// A buffer or a texture, with its size in bytes.
+#[derive(Debug)]
+struct Allocation {
+    id: (u32, u32),
+    label: Option<String>,
+    description: String,
+    bytes: u64,
+}
+
+#[derive(Debug, Default)]
+struct Frame {
+    pipelines: usize,
+    submissions: usize,
+    commands: usize,
+}
+
+/// A resource that is still alive.
+#[derive(Debug)]
+struct Created {
+    /// Index of the action that created it.
+    order: usize,
+    label: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Upload {
+    count: usize,
+    bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct Stats {
+    /// Number of the largest buffers and textures to report.
+    top: usize,
+    actions: usize,
+    created: HashMap<&'static str, usize>,
+    buffers: Vec<Allocation>,
+    textures: Vec<Allocation>,
+    frames: Vec<Frame>,
+    write_buffer: Upload,
+    write_texture: Upload,
+    /// Resources that are still alive, by kind and id.
+    live: HashMap<(&'static str, (u32, u32)), Created>,
+}
+
+fn key<I: TypedId>(id: I) -> (u32, u32) {
+    let (index, epoch, _) = id.unzip();
+    (index, epoch)
+}
+
+impl Stats {
+    pub fn new(top: usize) -> Self {
+        Self {
+            top,
+            actions: 0,
+            created: HashMap::new(),
+            buffers: Vec::new(),
+            textures: Vec::new(),
+            frames: vec![Frame::default()],
+            write_buffer: Upload::default(),
+            write_texture: Upload::default(),
+            live: HashMap::new(),
+        }
+    }
+
+    fn create(&mut self, kind: &'static str, id: (u32, u32), label: Option<String>) {
+        *self.created.entry(kind).or_insert(0) += 1;
+        let order = self.actions;
+        self.live.insert((kind, id), Created { order, label });
+    }
+
+    fn destroy(&mut self, kind: &'static str, id: (u32, u32)) {
+        self.live.remove(&(kind, id));
+    }
+
+    /// Account for the next action of the trace.
+    ///
+    /// The data source is only asked for the sizes of the data.
+    pub fn add(&mut self, action: &Action, data_source: &mut dyn DataSource) {
+        fn label(label: &wgc::Label) -> Option<String> {
+            label.as_ref().map(|label| label.to_string())
+        }
+
+        self.actions += 1;
+        let frame = self.frames.len() - 1;
+        match *action {
+            Action::Init { .. }
+            | Action::ConfigureSurface(..)
+            | Action::DiscardSurfaceTexture(_) => {}
+            Action::Present(_) => self.frames.push(Frame::default()),
+            Action::CreateBuffer(id, ref desc) => {
+                self.buffers.push(Allocation {
+                    id: key(id),
+                    label: label(&desc.label),
+                    description: format!("{:?}", desc.usage),
+                    bytes: desc.size,
+                });
+                self.create("Buffer", key(id), label(&desc.label));
+            }
+            Action::FreeBuffer(id) | Action::DestroyBuffer(id) => self.destroy("Buffer", key(id)),
+            Action::CreateTexture(id, ref desc) => {
+                let info = desc.format.describe();
+                let bytes = (0..desc.mip_level_count)
+                    .filter_map(|level| desc.mip_level_size(level))
+                    .map(|size| {
+                        let size = size.physical_size(desc.format);
+                        let blocks = (size.width / info.block_dimensions.0 as u32)
+                            * (size.height / info.block_dimensions.1 as u32)
+                            * size.depth_or_array_layers;
+                        blocks as u64 * info.block_size as u64 * desc.sample_count as u64
+                    })
+                    .sum();
+                self.textures.push(Allocation {
+                    id: key(id),
+                    label: label(&desc.label),
+                    description: format!(
+                        "{}x{}x{} {:?}, {} mips",
+                        desc.size.width,
+                        desc.size.height,
+                        desc.size.depth_or_array_layers,
+                        desc.format,
+                        desc.mip_level_count
+                    ),
+                    bytes,
+                });
+                self.create("Texture", key(id), label(&desc.label));
+            }
+            Action::FreeTexture(id) | Action::DestroyTexture(id) => {
+                self.destroy("Texture", key(id))
+            }
+            // Surface textures are owned by the surface.
+            Action::GetSurfaceTexture { .. } => {}
+            Action::CreateTextureView { id, ref desc, .. } => {
+                self.create("TextureView", key(id), label(&desc.label))
+            }
+            Action::DestroyTextureView(id) => self.destroy("TextureView", key(id)),
+            Action::CreateSampler(id, ref desc) => {
+                self.create("Sampler", key(id), label(&desc.label))
+            }
+            Action::DestroySampler(id) => self.destroy("Sampler", key(id)),
+            Action::CreateBindGroupLayout(id, ref desc) => {
+                self.create("BindGroupLayout", key(id), label(&desc.label))
+            }
+            Action::DestroyBindGroupLayout(id) => self.destroy("BindGroupLayout", key(id)),
+            Action::CreatePipelineLayout(id, ref desc) => {
+                self.create("PipelineLayout", key(id), label(&desc.label))
+            }
+            Action::DestroyPipelineLayout(id) => self.destroy("PipelineLayout", key(id)),
+            Action::CreateBindGroup(id, ref desc) => {
+                self.create("BindGroup", key(id), label(&desc.label))
+            }
+            Action::DestroyBindGroup(id) => self.destroy("BindGroup", key(id)),
+            Action::CreateShaderModule { id, ref desc, .. } => {
+                self.create("ShaderModule", key(id), label(&desc.label))
+            }
+            Action::DestroyShaderModule(id) => self.destroy("ShaderModule", key(id)),
+            Action::CreatePipelineCache { id, ref desc } => {
+                self.create("PipelineCache", key(id), label(&desc.label))
+            }
+            Action::DestroyPipelineCache(id) => self.destroy("PipelineCache", key(id)),
+            Action::CreateComputePipeline { id, ref desc, .. } => {
+                self.frames[frame].pipelines += 1;
+                self.create("ComputePipeline", key(id), label(&desc.label))
+            }
+            Action::DestroyComputePipeline(id) => self.destroy("ComputePipeline", key(id)),
+            Action::CreateRenderPipeline { id, ref desc, .. } => {
+                self.frames[frame].pipelines += 1;
+                self.create("RenderPipeline", key(id), label(&desc.label))
+            }
+            Action::DestroyRenderPipeline(id) => self.destroy("RenderPipeline", key(id)),
+            Action::CreateRenderBundle { id, ref desc, .. } => {
+                self.create("RenderBundle", key(id), label(&desc.label))
+            }
+            Action::DestroyRenderBundle(id) => self.destroy("RenderBundle", key(id)),
+            Action::CreateQuerySet { id, ref desc } => {
+                self.create("QuerySet", key(id), label(&desc.label))
+            }
+            Action::DestroyQuerySet(id) => self.destroy("QuerySet", key(id)),
+            Action::WriteBuffer { ref range, .. } => {
+                self.write_buffer.count += 1;
+                self.write_buffer.bytes += range.end - range.start;
+            }
+            Action::WriteTexture { ref data, .. } => {
+                self.write_texture.count += 1;
+                self.write_texture.bytes += data_source.size(data);
+            }
+            Action::Submit(_, ref commands) => {
+                self.frames[frame].submissions += 1;
+                self.frames[frame].commands += commands
+                    .iter()
+                    .filter(|command| {
+                        !matches!(
+                            **command,
+                            Command::PushDebugGroup(_)
+                                | Command::PopDebugGroup
+                                | Command::InsertDebugMarker(_)
+                        )
+                    })
+                    .count();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_label(f: &mut fmt::Formatter, label: &Option<String>) -> fmt::Result {
+            match *label {
+                Some(ref label) => write!(f, " {:?}", label),
+                None => Ok(()),
+            }
+        }
+
+        writeln!(f, "Actions: {}", self.actions)?;
+
+        writeln!(f, "Resources created:")?;
+        let mut created = self.created.iter().collect::<Vec<_>>();
+        created.sort();
+        for (kind, count) in created {
+            writeln!(f, "\t{}: {}", kind, count)?;
+        }
+
+        for &(name, allocations) in &[("buffers", &self.buffers), ("textures", &self.textures)] {
+            let total: u64 = allocations.iter().map(|a| a.bytes).sum();
+            writeln!(
+                f,
+                "Largest {} of {}, {} bytes in total:",
+                name,
+                allocations.len(),
+                total
+            )?;
+            let mut sorted = allocations.iter().collect::<Vec<_>>();
+            sorted.sort_by_key(|a| std::cmp::Reverse(a.bytes));
+            for a in sorted.into_iter().take(self.top) {
+                write!(f, "\t{:?}", a.id)?;
+                write_label(f, &a.label)?;
+                writeln!(f, " {}: {} bytes", a.description, a.bytes)?;
+            }
+        }
+
+        // The last frame is only reported if it wasn't ended by the last action.
+        let frames = match self.frames.split_last() {
+            Some((last, rest))
+                if self.frames.len() > 1 && last.pipelines == 0 && last.submissions == 0 =>
+            {
+                rest
+            }
+            _ => &self.frames[..],
+        };
+        writeln!(f, "Frames: {}", frames.len())?;
+        for (index, frame) in frames.iter().enumerate() {
+            writeln!(
+                f,
+                "\t{}: {} pipelines created, {} submissions of {} commands",
+                index, frame.pipelines, frame.submissions, frame.commands
+            )?;
+        }
+
+        writeln!(f, "Uploads:")?;
+        for &(name, upload) in &[
+            ("WriteBuffer", &self.write_buffer),
+            ("WriteTexture", &self.write_texture),
+        ] {
+            writeln!(
+                f,
+                "\t{}: {} calls, {} bytes",
+                name, upload.count, upload.bytes
+            )?;
+        }
+
+        let mut live = self.live.iter().collect::<Vec<_>>();
+        live.sort_by_key(|&(_, created)| created.order);
+        writeln!(f, "Never destroyed: {}", live.len())?;
+        for (&(kind, id), created) in live {
+            write!(f, "\t{} {:?}", kind, id)?;
+            write_label(f, &created.label)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_stats() {
+    struct NoData;
+    impl DataSource for NoData {
+        fn load(&mut self, _name: &str) -> Vec<u8> {
+            vec![0; 64]
+        }
+    }
+
+    fn id<I: TypedId>(index: u32) -> I {
+        I::zip(index, 1, wgt::Backend::Empty)
+    }
+    let actions = [
+        Action::CreateBuffer(
+            id(0),
+            wgc::resource::BufferDescriptor {
+                label: Some("kept".into()),
+                size: 256,
+                usage: wgt::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        ),
+        Action::CreateTexture(
+            id(0),
+            wgc::resource::TextureDescriptor {
+                label: None,
+                size: wgt::Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 2,
+                sample_count: 1,
+                dimension: wgt::TextureDimension::D2,
+                format: wgt::TextureFormat::Rgba8Unorm,
+                usage: wgt::TextureUsages::COPY_DST,
+            },
+        ),
+        Action::WriteBuffer {
+            id: id(0),
+            data: "data1.bin".to_string(),
+            range: 0..16,
+            queued: true,
+        },
+        Action::Submit(1, Vec::new()),
+        Action::Present(id(0)),
+        Action::DestroyTexture(id(0)),
+        Action::Submit(2, Vec::new()),
+    ];
+
+    let mut stats = Stats::new(5);
+    for action in actions.iter() {
+        stats.add(action, &mut NoData);
+    }
+    assert_eq!(stats.created["Buffer"], 1);
+    assert_eq!(stats.textures[0].bytes, (16 + 4) * 4);
+    assert_eq!(stats.write_buffer.bytes, 16);
+    assert_eq!(stats.frames.len(), 2);
+    assert_eq!(stats.frames[1].submissions, 1);
+    assert_eq!(
+        stats.live.keys().collect::<Vec<_>>(),
+        vec![&("Buffer", (0, 1))]
+    );
+}
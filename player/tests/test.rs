@@ -5,14 +5,22 @@
  *  Test requirements:
  *    - all IDs have the backend `Empty`
  *    - all expected buffers have `MAP_READ` usage
+ *    - all expected textures have `COPY_SRC` usage
  *    - last action is `Submit`
  *    - no swapchain use
+ *
+ *  Expected textures are compared against a reference PNG.
+ *  A missing reference is a failure. Set `WGPU_UPDATE_REFERENCES=1` to write
+ *  the references from the actual contents instead of comparing them.
+ *  On mismatch, the actual contents and the difference are written
+ *  next to the reference, as `<name>-actual.png` and `<name>-difference.png`.
 !*/
 
 use player::{GlobalPlay, IdentityPassThroughFactory};
 use std::{
+    ffi::OsString,
     fs::{read_to_string, File},
-    io::{Read, Seek, SeekFrom},
+    io::{BufWriter, Cursor, Read, Seek, SeekFrom},
     path::{Path, PathBuf},
     ptr, slice,
 };
@@ -48,10 +56,59 @@ struct Expectation {
     data: ExpectedData,
 }
 
+#[derive(serde::Deserialize)]
+struct TextureExpectation {
+    name: String,
+    texture: RawId,
+    #[serde(default)]
+    mip_level: u32,
+    #[serde(default)]
+    array_layer: u32,
+    /// Path to the reference PNG, relative to the test.
+    image: String,
+    /// Largest difference of a color channel that is still considered equal.
+    #[serde(default)]
+    tolerance: u8,
+    /// Number of color channels allowed to exceed the tolerance.
+    #[serde(default)]
+    max_outliers: usize,
+}
+
+/// Buffer that a texture is copied into at the end of the test.
+struct Readback {
+    buffer: wgc::id::BufferId,
+    size: wgt::Extent3d,
+    bytes_per_row: u32,
+    swizzle: bool,
+}
+
+impl Readback {
+    fn buffer_size(&self) -> wgt::BufferAddress {
+        self.bytes_per_row as wgt::BufferAddress * self.size.height as wgt::BufferAddress
+    }
+
+    /// Returns the tightly packed RGBA8 rows of the texture.
+    fn rgba(&self, contents: &[u8]) -> Vec<u8> {
+        let row_size = self.size.width as usize * 4;
+        let mut data = Vec::with_capacity(row_size * self.size.height as usize);
+        for row in contents.chunks(self.bytes_per_row as usize) {
+            data.extend_from_slice(&row[..row_size]);
+        }
+        if self.swizzle {
+            for texel in data.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+        }
+        data
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct Test<'a> {
     features: wgt::Features,
     expectations: Vec<Expectation>,
+    #[serde(default)]
+    texture_expectations: Vec<TextureExpectation>,
     actions: Vec<wgc::device::trace::Action<'a>>,
 }
 
@@ -70,6 +127,7 @@ impl Test<'_> {
             wgt::Backend::Dx12 => "Dx12",
             wgt::Backend::Dx11 => "Dx11",
             wgt::Backend::Gl => "Gl",
+            wgt::Backend::Cpu => "Cpu",
             _ => unreachable!(),
         };
         let string = read_to_string(path).unwrap().replace("Empty", backend_name);
@@ -99,11 +157,112 @@ impl Test<'_> {
             panic!("{:?}", e);
         }
 
+        // Readback buffers are allocated past the ones of the trace.
+        let mut readback_index = 0;
+        let mut texture_descs = Vec::new();
+        for action in self.actions.iter() {
+            match *action {
+                wgc::device::trace::Action::CreateBuffer(id, _) => {
+                    readback_index = readback_index.max(wgc::id::TypedId::unzip(id).0 + 1);
+                }
+                wgc::device::trace::Action::CreateTexture(id, ref desc) => {
+                    texture_descs.push((id, desc.clone()));
+                }
+                _ => {}
+            }
+        }
+
         let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
         println!("\t\t\tRunning...");
+        let mut data_source = dir;
         for action in self.actions {
-            wgc::gfx_select!(device => global.process(device, action, dir, &mut command_buffer_id_manager));
+            wgc::gfx_select!(device => global.process(device, action, &mut data_source, &mut command_buffer_id_manager));
+        }
+
+        let mut readbacks = Vec::with_capacity(self.texture_expectations.len());
+        if !self.texture_expectations.is_empty() {
+            println!("\t\t\tCopying...");
+            let (encoder, error) = wgc::gfx_select!(device => global.device_create_command_encoder(
+                device,
+                &wgt::CommandEncoderDescriptor { label: None },
+                command_buffer_id_manager.alloc(backend)
+            ));
+            if let Some(e) = error {
+                panic!("{:?}", e);
+            }
+            for expect in &self.texture_expectations {
+                let texture =
+                    wgc::id::TypedId::zip(expect.texture.index, expect.texture.epoch, backend);
+                let desc = match texture_descs.iter().find(|&&(id, _)| id == texture) {
+                    Some(&(_, ref desc)) => desc,
+                    None => panic!("Texture of {} is not created by the test", expect.name),
+                };
+                let swizzle = match desc.format {
+                    wgt::TextureFormat::Rgba8Unorm | wgt::TextureFormat::Rgba8UnormSrgb => false,
+                    wgt::TextureFormat::Bgra8Unorm | wgt::TextureFormat::Bgra8UnormSrgb => true,
+                    other => panic!("Unable to compare {} of format {:?}", expect.name, other),
+                };
+                let size = wgt::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..desc.mip_level_size(expect.mip_level).unwrap()
+                };
+                let align = wgt::COPY_BYTES_PER_ROW_ALIGNMENT;
+                let readback = Readback {
+                    buffer: wgc::id::TypedId::zip(readback_index, 1, backend),
+                    size,
+                    bytes_per_row: (size.width * 4 + align - 1) / align * align,
+                    swizzle,
+                };
+                readback_index += 1;
+
+                let (_, error) = wgc::gfx_select!(device => global.device_create_buffer(
+                    device,
+                    &wgc::resource::BufferDescriptor {
+                        label: None,
+                        size: readback.buffer_size(),
+                        usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    },
+                    readback.buffer
+                ));
+                if let Some(e) = error {
+                    panic!("{:?}", e);
+                }
+                wgc::gfx_select!(device => global.command_encoder_copy_texture_to_buffer(
+                    encoder,
+                    &wgt::ImageCopyTexture {
+                        texture,
+                        mip_level: expect.mip_level,
+                        origin: wgt::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: expect.array_layer,
+                        },
+                        aspect: wgt::TextureAspect::All,
+                    },
+                    &wgt::ImageCopyBuffer {
+                        buffer: readback.buffer,
+                        layout: wgt::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: std::num::NonZeroU32::new(readback.bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    &size
+                ))
+                .unwrap();
+                readbacks.push(readback);
+            }
+            let (cmdbuf, error) = wgc::gfx_select!(device => global.command_encoder_finish(
+                encoder,
+                &wgt::CommandBufferDescriptor { label: None }
+            ));
+            if let Some(e) = error {
+                panic!("{:?}", e);
+            }
+            wgc::gfx_select!(device => global.queue_submit(device, &[cmdbuf])).unwrap();
         }
+
         println!("\t\t\tMapping...");
         for expect in &self.expectations {
             let buffer = wgc::id::TypedId::zip(expect.buffer.index, expect.buffer.epoch, backend);
@@ -118,9 +277,21 @@ impl Test<'_> {
             ))
             .unwrap();
         }
+        for readback in &readbacks {
+            wgc::gfx_select!(device => global.buffer_map_async(
+                readback.buffer,
+                0 .. readback.buffer_size(),
+                wgc::resource::BufferMapOperation {
+                    host: wgc::device::HostMap::Read,
+                    callback: map_callback,
+                    user_data: ptr::null_mut(),
+                }
+            ))
+            .unwrap();
+        }
 
         println!("\t\t\tWaiting...");
-        wgc::gfx_select!(device => global.device_poll(device, true)).unwrap();
+        wgc::gfx_select!(device => global.device_poll(device, wgt::Maintain::Wait)).unwrap();
 
         for expect in self.expectations {
             println!("\t\t\tChecking {}", expect.name);
@@ -154,10 +325,112 @@ impl Test<'_> {
             }
         }
 
+        for (expect, readback) in self.texture_expectations.iter().zip(readbacks.iter()) {
+            println!("\t\t\tComparing {}", expect.name);
+            let (ptr, size) = wgc::gfx_select!(device => global.buffer_get_mapped_range(
+                readback.buffer,
+                0,
+                Some(readback.buffer_size())
+            ))
+            .unwrap();
+            let contents = unsafe { slice::from_raw_parts(ptr, size as usize) };
+            compare_image(
+                dir.join(&expect.image),
+                expect,
+                readback,
+                &readback.rgba(contents),
+            );
+        }
+
         wgc::gfx_select!(device => global.clear_backend(()));
     }
 }
 
+fn read_png(path: &Path, size: wgt::Extent3d) -> Vec<u8> {
+    let bytes = std::fs::read(path).unwrap();
+    let decoder = png::Decoder::new(Cursor::new(bytes));
+    let (info, mut reader) = decoder.read_info().unwrap();
+    assert_eq!(
+        (info.width, info.height),
+        (size.width, size.height),
+        "Size of {:?} doesn't match the texture",
+        path
+    );
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::RGBA, png::BitDepth::Eight),
+        "{:?} is not an 8-bit RGBA image",
+        path
+    );
+    let mut data = vec![0; info.buffer_size()];
+    reader.next_frame(&mut data).unwrap();
+    data
+}
+
+fn write_png(path: &Path, size: wgt::Extent3d, data: &[u8]) {
+    let file = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, size.width, size.height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+}
+
+/// Returns the path of a file written next to the reference image.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.file_stem().unwrap());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Environment variable that makes the tests write the reference images.
+const UPDATE_REFERENCES_VAR: &str = "WGPU_UPDATE_REFERENCES";
+
+fn compare_image(path: PathBuf, expect: &TextureExpectation, readback: &Readback, data: &[u8]) {
+    if matches!(std::env::var_os(UPDATE_REFERENCES_VAR), Some(value) if value != "0") {
+        log::warn!("Writing the reference {:?}", path);
+        write_png(&path, readback.size, data);
+        return;
+    }
+    assert!(
+        path.exists(),
+        "Reference {:?} of image {} is missing, run with {}=1 to write it",
+        path,
+        expect.name,
+        UPDATE_REFERENCES_VAR
+    );
+
+    let reference = read_png(&path, readback.size);
+    let difference = reference
+        .chunks_exact(4)
+        .zip(data.chunks_exact(4))
+        .flat_map(|(expected, actual)| {
+            let diff = |i: usize| (expected[i] as i16 - actual[i] as i16).unsigned_abs() as u8;
+            vec![diff(0), diff(1), diff(2), diff(3)]
+        })
+        .collect::<Vec<u8>>();
+    let outliers = difference.iter().filter(|&&d| d > expect.tolerance).count();
+    let max_difference = difference.iter().cloned().max().unwrap_or(0);
+
+    if outliers > expect.max_outliers {
+        // Show the channels that differ on an opaque background.
+        let visible = difference
+            .chunks_exact(4)
+            .flat_map(|d| vec![d[0].max(d[3]), d[1].max(d[3]), d[2].max(d[3]), 255])
+            .collect::<Vec<u8>>();
+        write_png(&sibling_path(&path, "-actual.png"), readback.size, data);
+        write_png(
+            &sibling_path(&path, "-difference.png"),
+            readback.size,
+            &visible,
+        );
+        panic!(
+            "Image {} doesn't match {:?}: {} outliers over the limit of {}, max difference {}",
+            expect.name, path, outliers, expect.max_outliers, max_difference
+        );
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct Corpus {
     backends: wgt::Backends,
@@ -170,6 +443,7 @@ const BACKENDS: &[wgt::Backend] = &[
     wgt::Backend::Dx12,
     wgt::Backend::Dx11,
     wgt::Backend::Gl,
+    wgt::Backend::Cpu,
 ];
 
 impl Corpus {
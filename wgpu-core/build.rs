@@ -18,5 +18,6 @@ fn main() {
                 wasm
             )
         },
+        cpu: { all(not(wasm), feature = "cpu") },
     }
 }
@@ -155,6 +155,26 @@ impl RenderBundleEncoder {
         self.parent_id
     }
 
+    #[cfg(feature = "trace")]
+    pub fn into_action(self, id: id::RenderBundleId, label: Label) -> crate::device::trace::Action {
+        let desc = crate::device::trace::new_render_bundle_encoder_descriptor(
+            None,
+            &self.context,
+            self.is_ds_read_only,
+        );
+        crate::device::trace::Action::CreateRenderBundle {
+            id,
+            desc: RenderBundleEncoderDescriptor {
+                label,
+                color_formats: Cow::Owned(desc.color_formats.into_owned()),
+                depth_stencil: desc.depth_stencil,
+                sample_count: desc.sample_count,
+                multiview: desc.multiview,
+            },
+            base: self.base,
+        }
+    }
+
     pub(crate) fn finish<A: hal::Api, G: GlobalIdentityHandlerFactory>(
         self,
         desc: &RenderBundleDescriptor,
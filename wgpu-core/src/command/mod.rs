@@ -277,6 +277,39 @@ pub enum CommandEncoderError {
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
+    /// Calls `hal_command_encoder_callback` with the raw encoder, opened for recording, or
+    /// `None` if the encoder is invalid or finished.
+    ///
+    /// # Safety
+    ///
+    /// - The raw command encoder must not be finished or reset
+    /// - The commands recorded through it aren't tracked: the resources they use must be
+    ///   transitioned by the caller, and kept alive until the command buffer is done executing
+    pub unsafe fn command_encoder_as_hal_mut<
+        A: HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &self,
+        id: id::CommandEncoderId,
+        hal_command_encoder_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal_mut", "CommandEncoder");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (mut guard, _) = hub.command_buffers.write(&mut token);
+        let hal_command_encoder = match guard.get_mut(id) {
+            Ok(cmd_buf) => match cmd_buf.status {
+                CommandEncoderStatus::Recording => Some(cmd_buf.encoder.open()),
+                _ => None,
+            },
+            Err(_) => None,
+        };
+
+        hal_command_encoder_callback(hal_command_encoder)
+    }
+
     pub fn command_encoder_finish<A: HalApi>(
         &self,
         encoder_id: id::CommandEncoderId,
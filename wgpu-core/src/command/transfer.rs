@@ -95,6 +95,10 @@ pub enum TransferError {
     MismatchedAspects,
     #[error("copying from textures with format {0:?} is forbidden")]
     CopyFromForbiddenTextureFormat(wgt::TextureFormat),
+    #[error(
+        "copying the stencil aspect of textures to buffers is not supported by the {0:?} backend"
+    )]
+    UnsupportedStencilCopy(wgt::Backend),
     #[error("copying to textures with format {0:?} is forbidden")]
     CopyToForbiddenTextureFormat(wgt::TextureFormat),
     #[error("the entire texture must be copied when copying from depth texture")]
@@ -311,10 +315,10 @@ pub(crate) fn validate_texture_copy_range(
     match desc.format {
         wgt::TextureFormat::Depth32Float
         | wgt::TextureFormat::Depth24Plus
-        | wgt::TextureFormat::Depth24PlusStencil8 => {
-            if *copy_size != extent {
-                return Err(TransferError::InvalidDepthTextureExtent);
-            }
+        | wgt::TextureFormat::Depth24PlusStencil8
+            if copy_size.width != extent.width || copy_size.height != extent.height =>
+        {
+            return Err(TransferError::InvalidDepthTextureExtent);
         }
         _ => {}
     }
@@ -820,7 +824,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         let dst_barriers = dst_pending.map(|pending| pending.into_hal(dst_buffer));
 
-        let format_desc = src_texture.desc.format.describe();
         let (hal_copy_size, array_layer_count) =
             validate_texture_copy_range(source, &src_texture.desc, CopySide::Source, copy_size)?;
         let (required_buffer_bytes_in_copy, bytes_per_array_layer) = validate_linear_texture_data(
@@ -828,16 +831,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             src_texture.desc.format,
             dst_buffer.size,
             CopySide::Destination,
-            format_desc.block_size as BufferAddress,
+            conv::copy_block_size(src_texture.desc.format, source.aspect) as BufferAddress,
             copy_size,
             true,
         )?;
 
-        if !conv::is_valid_copy_src_texture_format(src_texture.desc.format) {
+        if !conv::is_valid_copy_src_texture_format(src_texture.desc.format, source.aspect) {
             return Err(
                 TransferError::CopyFromForbiddenTextureFormat(src_texture.desc.format).into(),
             );
         }
+        // The GL backend can't read stencil values back yet.
+        if A::VARIANT == wgt::Backend::Gl && source.aspect == wgt::TextureAspect::StencilOnly {
+            return Err(TransferError::UnsupportedStencilCopy(A::VARIANT).into());
+        }
 
         cmd_buf
             .buffer_memory_init_actions
@@ -4,14 +4,27 @@ pub fn is_power_of_two(val: u32) -> bool {
     val != 0 && (val & (val - 1)) == 0
 }
 
-pub fn is_valid_copy_src_texture_format(format: wgt::TextureFormat) -> bool {
+pub fn is_valid_copy_src_texture_format(
+    format: wgt::TextureFormat,
+    aspect: wgt::TextureAspect,
+) -> bool {
+    use wgt::TextureAspect as Ta;
     use wgt::TextureFormat as Tf;
-    match format {
-        Tf::Depth24Plus | Tf::Depth24PlusStencil8 => false,
+    match (format, aspect) {
+        (Tf::Depth24PlusStencil8, Ta::StencilOnly) => true,
+        (Tf::Depth24Plus, _) | (Tf::Depth24PlusStencil8, _) => false,
         _ => true,
     }
 }
 
+/// Returns the size of a texel block of the `aspect` of `format` in buffers.
+pub fn copy_block_size(format: wgt::TextureFormat, aspect: wgt::TextureAspect) -> u32 {
+    match (format, aspect) {
+        (wgt::TextureFormat::Depth24PlusStencil8, wgt::TextureAspect::StencilOnly) => 1,
+        _ => format.describe().block_size as u32,
+    }
+}
+
 pub fn is_valid_copy_dst_texture_format(format: wgt::TextureFormat) -> bool {
     use wgt::TextureFormat as Tf;
     match format {
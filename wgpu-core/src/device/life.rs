@@ -203,6 +203,10 @@ pub enum WaitIdleError {
     Device(#[from] DeviceError),
     #[error("GPU got stuck :(")]
     StuckGpu,
+    #[error("timed out waiting for the GPU")]
+    Timeout,
+    #[error("Submission index is from queue {0:?}, but the device {1:?} is polled")]
+    WrongSubmissionIndex(id::QueueId, id::DeviceId),
 }
 
 /// A struct responsible for tracking resource lifetimes.
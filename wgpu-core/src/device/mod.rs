@@ -1,6 +1,5 @@
 use crate::{
     binding_model, command, conv,
-    device::life::WaitIdleError,
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Hub, Input, InvalidId, Storage, Token},
     id,
     init_tracker::{
@@ -17,24 +16,44 @@ use crate::{
 use arrayvec::ArrayVec;
 use copyless::VecHelper as _;
 use hal::{CommandEncoder as _, Device as _};
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::{Mutex, MutexGuard, RwLockWriteGuard};
 use smallvec::SmallVec;
 use thiserror::Error;
 use wgt::{BufferAddress, TextureFormat, TextureViewDimension};
 
-use std::{borrow::Cow, iter, marker::PhantomData, mem, num::NonZeroU32, ops::Range, ptr};
+use std::{
+    borrow::Cow,
+    hash::{Hash as _, Hasher as _},
+    iter,
+    marker::PhantomData,
+    mem,
+    num::NonZeroU32,
+    ops::Range,
+    ptr,
+};
 
 mod life;
 pub mod queue;
 #[cfg(any(feature = "trace", feature = "replay"))]
 pub mod trace;
 
+pub use life::WaitIdleError;
+
 pub const SHADER_STAGE_COUNT: usize = 3;
 // Should be large enough for the largest possible texture row. This value is enough for a 16k texture with float4 format.
 pub(crate) const ZERO_BUFFER_SIZE: BufferAddress = 512 << 10;
 
 const CLEANUP_WAIT_MS: u32 = 5000;
 
+/// Limit of a wait for the GPU.
+#[derive(Clone, Copy, Debug)]
+enum WaitLimit {
+    /// Timeout given by the user, after which the GPU may still finish the work.
+    Timeout(u32),
+    /// Time after which a GPU that didn't finish the work is stuck.
+    Stuck(u32),
+}
+
 const IMPLICIT_FAILURE: &str = "failed implicit";
 const EP_FAILURE: &str = "EP is invalid";
 
@@ -127,16 +146,21 @@ impl RenderPassContext {
 
 pub type BufferMapPendingClosure = (resource::BufferMapOperation, resource::BufferMapAsyncStatus);
 
+/// Called once the device is lost, with the reason and a message describing it.
+pub type DeviceLostClosure = Box<dyn FnOnce(wgt::DeviceLostReason, String) + Send + 'static>;
+
 #[derive(Default)]
 pub struct UserClosures {
     pub mappings: Vec<BufferMapPendingClosure>,
     pub submissions: SmallVec<[queue::SubmittedWorkDoneClosure; 1]>,
+    pub device_lost: Vec<(DeviceLostClosure, wgt::DeviceLostReason, String)>,
 }
 
 impl UserClosures {
     fn extend(&mut self, other: Self) {
         self.mappings.extend(other.mappings);
         self.submissions.extend(other.submissions);
+        self.device_lost.extend(other.device_lost);
     }
 
     unsafe fn fire(self) {
@@ -148,6 +172,9 @@ impl UserClosures {
         for closure in self.submissions {
             (closure.callback)(closure.user_data);
         }
+        for (closure, reason, message) in self.device_lost {
+            closure(reason, message);
+        }
     }
 }
 
@@ -193,7 +220,7 @@ fn map_buffer<A: hal::Api>(
                 mapping
                     .ptr
                     .as_ptr()
-                    .offset(uninitialized_range.start as isize),
+                    .offset((uninitialized_range.start - offset) as isize),
                 0,
                 num_bytes as usize,
             )
@@ -279,6 +306,9 @@ pub struct Device<A: hal::Api> {
     //TODO: move this behind another mutex. This would allow several methods to switch
     // to borrow Device immutably, such as `write_buffer`, `write_texture`, and `buffer_unmap`.
     pending_writes: queue::PendingWrites<A>,
+    /// Reason of the loss, once the device is lost.
+    lost_reason: Mutex<Option<wgt::DeviceLostReason>>,
+    lost_closure: Mutex<Option<DeviceLostClosure>>,
     #[cfg(feature = "trace")]
     pub(crate) trace: Option<Mutex<trace::Trace>>,
 }
@@ -310,6 +340,36 @@ impl<A: hal::Api> Device<A> {
             Err(MissingDownlevelFlags(flags))
         }
     }
+
+    pub(crate) fn check_lost(&self) -> Result<(), DeviceError> {
+        match *self.lost_reason.lock() {
+            Some(_) => Err(DeviceError::Lost),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark the device as lost, returning the lost closure to call once nothing is locked.
+    pub(crate) fn lose(&self, reason: wgt::DeviceLostReason, message: String) -> UserClosures {
+        let mut lost_reason = self.lost_reason.lock();
+        if lost_reason.is_some() {
+            return UserClosures::default();
+        }
+        match reason {
+            wgt::DeviceLostReason::Destroyed => log::info!("Device is lost: {}", message),
+            wgt::DeviceLostReason::Unknown => log::error!("Device is lost: {}", message),
+        }
+        *lost_reason = Some(reason);
+        UserClosures {
+            device_lost: self
+                .lost_closure
+                .lock()
+                .take()
+                .map(|closure| (closure, reason, message))
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+    }
 }
 
 impl<A: HalApi> Device<A> {
@@ -398,9 +458,37 @@ impl<A: HalApi> Device<A> {
             features: desc.features,
             downlevel,
             pending_writes,
+            lost_reason: Mutex::new(None),
+            lost_closure: Mutex::new(None),
         })
     }
 
+    /// Maintain the device, turning it into a lost one if the driver reports it lost,
+    /// or if the GPU is stuck.
+    ///
+    /// A user timeout only makes the wait fail, as the GPU may still finish its work.
+    fn maintain_or_lose<'this, 'token: 'this, G: GlobalIdentityHandlerFactory>(
+        &'this self,
+        hub: &Hub<A, G>,
+        maintain: wgt::Maintain<queue::WrappedSubmissionIndex>,
+        limit: WaitLimit,
+        token: &mut Token<'token, Self>,
+    ) -> (UserClosures, Result<(), WaitIdleError>) {
+        if let Err(error) = self.check_lost() {
+            return (UserClosures::default(), Err(error.into()));
+        }
+        let message = match self.maintain(hub, maintain, limit, token) {
+            Ok(closures) => return (closures, Ok(())),
+            Err(WaitIdleError::Device(DeviceError::Lost)) => {
+                "the driver reported the device as lost"
+            }
+            Err(WaitIdleError::StuckGpu) => "the GPU got stuck",
+            Err(error) => return (UserClosures::default(), Err(error)),
+        };
+        let closures = self.lose(wgt::DeviceLostReason::Unknown, message.to_string());
+        (closures, Err(DeviceError::Lost.into()))
+    }
+
     fn lock_life<'this, 'token: 'this>(
         &'this self,
         //TODO: fix this - the token has to be borrowed for the lock
@@ -412,7 +500,8 @@ impl<A: HalApi> Device<A> {
     fn maintain<'this, 'token: 'this, G: GlobalIdentityHandlerFactory>(
         &'this self,
         hub: &Hub<A, G>,
-        force_wait: bool,
+        maintain: wgt::Maintain<queue::WrappedSubmissionIndex>,
+        limit: WaitLimit,
         token: &mut Token<'token, Self>,
     ) -> Result<UserClosures, WaitIdleError> {
         profiling::scope!("maintain", "Device");
@@ -431,14 +520,24 @@ impl<A: HalApi> Device<A> {
         );
         life_tracker.triage_mapped(hub, token);
 
-        let last_done_index = if force_wait {
-            let current_index = self.active_submission_index;
-            unsafe {
+        let last_done_index = if maintain.is_wait() {
+            let index_to_wait_for = match maintain {
+                wgt::Maintain::WaitForSubmissionIndex(submission_index) => submission_index.index,
+                _ => self.active_submission_index,
+            };
+            let (timeout_ms, error) = match limit {
+                WaitLimit::Timeout(ms) => (ms, WaitIdleError::Timeout),
+                WaitLimit::Stuck(ms) => (ms, WaitIdleError::StuckGpu),
+            };
+            let signaled = unsafe {
                 self.raw
-                    .wait(&self.fence, current_index, CLEANUP_WAIT_MS)
+                    .wait(&self.fence, index_to_wait_for, timeout_ms)
                     .map_err(DeviceError::from)?
             };
-            current_index
+            if !signaled {
+                return Err(error);
+            }
+            index_to_wait_for
         } else {
             unsafe {
                 self.raw
@@ -455,6 +554,7 @@ impl<A: HalApi> Device<A> {
         Ok(UserClosures {
             mappings: mapping_closures,
             submissions: submission_closures,
+            device_lost: Vec::new(),
         })
     }
 
@@ -594,6 +694,30 @@ impl<A: HalApi> Device<A> {
         })
     }
 
+    fn create_buffer_from_hal(
+        &self,
+        hal_buffer: A::Buffer,
+        self_id: id::DeviceId,
+        desc: &resource::BufferDescriptor,
+    ) -> resource::Buffer<A> {
+        debug_assert_eq!(self_id.backend(), A::VARIANT);
+
+        resource::Buffer {
+            raw: Some(hal_buffer),
+            device_id: Stored {
+                value: id::Valid(self_id),
+                ref_count: self.life_guard.add_ref(),
+            },
+            usage: desc.usage,
+            size: desc.size,
+            // The contents of the buffer are provided by the application.
+            initialization_status: BufferInitTracker::new(0),
+            sync_mapped_writes: None,
+            map_state: resource::BufferMapState::Idle,
+            life_guard: LifeGuard::new(desc.label.borrow_or_default()),
+        }
+    }
+
     fn create_texture_from_hal(
         &self,
         hal_texture: A::Texture,
@@ -1057,7 +1181,7 @@ impl<A: HalApi> Device<A> {
         desc: &pipeline::ShaderModuleDescriptor<'a>,
         source: pipeline::ShaderModuleSource<'a>,
     ) -> Result<pipeline::ShaderModule<A>, pipeline::CreateShaderModuleError> {
-        let (module, source) = match source {
+        let (module, source, source_hash) = match source {
             pipeline::ShaderModuleSource::Wgsl(code) => {
                 profiling::scope!("naga::wgsl::parse_str");
                 let module = naga::front::wgsl::parse_str(&code).map_err(|inner| {
@@ -1067,9 +1191,13 @@ impl<A: HalApi> Device<A> {
                         inner,
                     })
                 })?;
-                (module, code.into_owned())
+                // The hash keys the persistent pipeline caches, so it has to be stable across runs.
+                let mut hasher = fxhash::FxHasher64::default();
+                code.hash(&mut hasher);
+                (module, code.into_owned(), Some(hasher.finish()))
             }
-            pipeline::ShaderModuleSource::Naga(module) => (module, String::new()),
+            // Modules can't be hashed, so pipelines made from them are never cached.
+            pipeline::ShaderModuleSource::Naga(module) => (module, String::new(), None),
         };
 
         use naga::valid::Capabilities as Caps;
@@ -1100,7 +1228,11 @@ impl<A: HalApi> Device<A> {
             })?;
         let interface =
             validation::Interface::new(&module, &info, self.features, self.limits.clone());
-        let hal_shader = hal::ShaderInput::Naga(hal::NagaShader { module, info });
+        let hal_shader = hal::ShaderInput::Naga(hal::NagaShader {
+            module,
+            info,
+            source_hash,
+        });
 
         let hal_desc = hal::ShaderModuleDescriptor {
             label: desc.label.borrow_option(),
@@ -1173,6 +1305,36 @@ impl<A: HalApi> Device<A> {
         })
     }
 
+    fn create_pipeline_cache(
+        &self,
+        self_id: id::DeviceId,
+        desc: &pipeline::PipelineCacheDescriptor,
+    ) -> Result<pipeline::PipelineCache<A>, pipeline::CreatePipelineCacheError> {
+        let mut hal_desc = hal::PipelineCacheDescriptor {
+            label: desc.label.borrow_option(),
+            data: desc.data.as_deref(),
+        };
+        let raw = match unsafe { self.raw.create_pipeline_cache(&hal_desc) } {
+            Ok(raw) => raw,
+            Err(hal::PipelineCacheError::Validation) if desc.fallback => {
+                log::warn!("Pipeline cache data is incompatible, starting with an empty cache");
+                hal_desc.data = None;
+                unsafe { self.raw.create_pipeline_cache(&hal_desc) }?
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(pipeline::PipelineCache {
+            raw,
+            device_id: Stored {
+                value: id::Valid(self_id),
+                ref_count: self.life_guard.add_ref(),
+            },
+            #[cfg(debug_assertions)]
+            label: desc.label.borrow_or_default().to_string(),
+        })
+    }
+
     fn deduplicate_bind_group_layout(
         self_id: id::DeviceId,
         entry_map: &binding_model::BindEntryMap,
@@ -2148,7 +2310,7 @@ impl<A: HalApi> Device<A> {
         hub: &Hub<A, G>,
         token: &mut Token<Self>,
     ) -> Result<pipeline::ComputePipeline<A>, pipeline::CreateComputePipelineError> {
-        //TODO: only lock mutable if the layout is derived
+        // Locked mutably for the derived layouts, and downgraded before the shaders are compiled.
         let (mut pipeline_layout_guard, mut token) = hub.pipeline_layouts.write(token);
         let (mut bgl_guard, mut token) = hub.bind_group_layouts.write(&mut token);
 
@@ -2168,11 +2330,21 @@ impl<A: HalApi> Device<A> {
         let mut shader_binding_sizes = FastHashMap::default();
 
         let io = validation::StageIo::default();
-        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let (shader_module_guard, mut token) = hub.shader_modules.read(&mut token);
+        let (cache_guard, _) = hub.pipeline_caches.read(&mut token);
 
         let shader_module = shader_module_guard
             .get(desc.stage.module)
             .map_err(|_| validation::StageError::InvalidModule)?;
+        let cache = match desc.cache {
+            Some(cache_id) => Some(
+                &cache_guard
+                    .get(cache_id)
+                    .map_err(|_| pipeline::CreateComputePipelineError::InvalidCache)?
+                    .raw,
+            ),
+            None => None,
+        };
 
         {
             let flag = wgt::ShaderStages::COMPUTE;
@@ -2212,6 +2384,10 @@ impl<A: HalApi> Device<A> {
                 &mut *pipeline_layout_guard,
             )?,
         };
+        // Compiling may take a while, possibly on a worker thread, so let the other
+        // threads use the layouts in the meantime.
+        let pipeline_layout_guard = RwLockWriteGuard::downgrade(pipeline_layout_guard);
+        let bgl_guard = RwLockWriteGuard::downgrade(bgl_guard);
         let layout = pipeline_layout_guard
             .get(pipeline_layout_id)
             .map_err(|_| pipeline::CreateComputePipelineError::InvalidLayout)?;
@@ -2226,6 +2402,7 @@ impl<A: HalApi> Device<A> {
                 entry_point: desc.stage.entry_point.as_ref(),
                 module: &shader_module.raw,
             },
+            cache,
         };
 
         let raw =
@@ -2270,7 +2447,7 @@ impl<A: HalApi> Device<A> {
     ) -> Result<pipeline::RenderPipeline<A>, pipeline::CreateRenderPipelineError> {
         use wgt::TextureFormatFeatureFlags as Tfff;
 
-        //TODO: only lock mutable if the layout is derived
+        // Locked mutably for the derived layouts, and downgraded before the shaders are compiled.
         let (mut pipeline_layout_guard, mut token) = hub.pipeline_layouts.write(token);
         let (mut bgl_guard, mut token) = hub.bind_group_layouts.write(&mut token);
 
@@ -2482,7 +2659,17 @@ impl<A: HalApi> Device<A> {
             sc
         };
 
-        let (shader_module_guard, _) = hub.shader_modules.read(&mut token);
+        let (shader_module_guard, mut token) = hub.shader_modules.read(&mut token);
+        let (cache_guard, _) = hub.pipeline_caches.read(&mut token);
+        let cache = match desc.cache {
+            Some(cache_id) => Some(
+                &cache_guard
+                    .get(cache_id)
+                    .map_err(|_| pipeline::CreateRenderPipelineError::InvalidCache)?
+                    .raw,
+            ),
+            None => None,
+        };
 
         let vertex_stage = {
             let stage = &desc.vertex.stage;
@@ -2625,6 +2812,10 @@ impl<A: HalApi> Device<A> {
                 &mut *pipeline_layout_guard,
             )?,
         };
+        // Compiling may take a while, possibly on a worker thread, so let the other
+        // threads use the layouts in the meantime.
+        let pipeline_layout_guard = RwLockWriteGuard::downgrade(pipeline_layout_guard);
+        let bgl_guard = RwLockWriteGuard::downgrade(bgl_guard);
         let layout = pipeline_layout_guard
             .get(pipeline_layout_id)
             .map_err(|_| pipeline::CreateRenderPipelineError::InvalidLayout)?;
@@ -2648,6 +2839,7 @@ impl<A: HalApi> Device<A> {
             fragment_stage,
             color_targets,
             multiview: desc.multiview,
+            cache,
         };
         let raw =
             unsafe { self.raw.create_render_pipeline(&pipeline_desc) }.map_err(
@@ -2820,13 +3012,42 @@ impl<A: hal::Api> Device<A> {
         }
     }
 
+    /// Wait for the submitted work to be done, and free the resources waiting for it.
+    fn release_resources(&mut self) -> SmallVec<[queue::SubmittedWorkDoneClosure; 1]> {
+        self.pending_writes.deactivate();
+        let current_index = self.active_submission_index;
+        match unsafe { self.raw.wait(&self.fence, current_index, CLEANUP_WAIT_MS) } {
+            Ok(true) => {}
+            Ok(false) => {
+                log::error!("timed out waiting for the device, its resources are leaked");
+                return SmallVec::new();
+            }
+            Err(error) => {
+                log::error!("failed to wait for the device: {:?}", error);
+                return SmallVec::new();
+            }
+        }
+        let mut life_tracker = self.life_tracker.lock();
+        for resource in self.pending_writes.temp_resources.drain(..) {
+            life_tracker.schedule_resource_destruction(resource, current_index);
+        }
+        let closures = life_tracker.triage_submissions(current_index, &self.command_allocator);
+        life_tracker.cleanup(&self.raw);
+        closures
+    }
+
     /// Wait for idle and remove resources that we can, before we die.
     pub(crate) fn prepare_to_die(&mut self) {
         self.pending_writes.deactivate();
         let mut life_tracker = self.life_tracker.lock();
         let current_index = self.active_submission_index;
-        if let Err(error) = unsafe { self.raw.wait(&self.fence, current_index, CLEANUP_WAIT_MS) } {
-            log::error!("failed to wait for the device: {:?}", error);
+        // A lost device isn't going to finish its work.
+        if self.check_lost().is_ok() {
+            if let Err(error) =
+                unsafe { self.raw.wait(&self.fence, current_index, CLEANUP_WAIT_MS) }
+            {
+                log::error!("failed to wait for the device: {:?}", error);
+            }
         }
         let _ = life_tracker.triage_submissions(current_index, &self.command_allocator);
         life_tracker.cleanup(&self.raw);
@@ -2991,6 +3212,42 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(device.downlevel.clone())
     }
 
+    /// Returns the features of `format` that can be used on the device.
+    ///
+    /// Formats requiring features that aren't enabled have no features.
+    pub fn device_texture_format_features<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        format: TextureFormat,
+    ) -> Result<wgt::TextureFormatFeatures, InvalidDevice> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (adapter_guard, mut token) = hub.adapters.read(&mut token);
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+        let adapter = &adapter_guard[device.adapter_id.value];
+
+        Ok(device
+            .describe_format_features(adapter, format)
+            .unwrap_or(wgt::TextureFormatFeatures {
+                allowed_usages: wgt::TextureUsages::empty(),
+                flags: wgt::TextureFormatFeatureFlags::empty(),
+            }))
+    }
+
+    /// Returns the memory allocated by the device for its resources, per heap.
+    pub fn device_memory_report<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<wgt::MemoryReport, InvalidDevice> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+
+        Ok(unsafe { device.raw.memory_report() })
+    }
+
     pub fn device_create_buffer<A: HalApi>(
         &self,
         device_id: id::DeviceId,
@@ -3009,6 +3266,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 let mut desc = desc.clone();
@@ -3113,6 +3373,83 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// # Safety
+    ///
+    /// - `hal_buffer` must be created from `device_id` corresponding raw handle.
+    /// - `hal_buffer` must be created respecting `desc`
+    /// - `hal_buffer` must be initialized
+    ///
+    /// `hal_buffer` is destroyed if the creation fails, unless `device_id` is invalid.
+    pub unsafe fn create_buffer_from_hal<A: HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        device_id: id::DeviceId,
+        desc: &resource::BufferDescriptor,
+        id_in: Input<G, id::BufferId>,
+    ) -> (id::BufferId, Option<resource::CreateBufferError>) {
+        profiling::scope!("create_buffer", "Device");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let fid = hub.buffers.prepare(id_in);
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = match device_guard.get(device_id) {
+                Ok(device) => device,
+                Err(_) => break DeviceError::Invalid.into(),
+            };
+            let error = if let Err(error) = device.check_lost() {
+                Some(error.into())
+            } else if desc.usage.is_empty() {
+                Some(resource::CreateBufferError::EmptyUsage)
+            } else if desc.mapped_at_creation {
+                Some(resource::CreateBufferError::MappedFromHal)
+            } else if desc
+                .usage
+                .intersects(wgt::BufferUsages::MAP_READ | wgt::BufferUsages::MAP_WRITE)
+            {
+                // Mapping needs the memory properties that `create_buffer` picks for these usages.
+                Some(resource::CreateBufferError::MapUsageFromHal(desc.usage))
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                device.raw.destroy_buffer(hal_buffer);
+                break error;
+            }
+
+            // NB: Any change done through the raw buffer handle will not be recorded in the replay
+            #[cfg(feature = "trace")]
+            if let Some(ref trace) = device.trace {
+                trace
+                    .lock()
+                    .add(trace::Action::CreateBuffer(fid.id(), desc.clone()));
+            }
+
+            let buffer = device.create_buffer_from_hal(hal_buffer, device_id, desc);
+            let ref_count = buffer.life_guard.add_ref();
+
+            let id = fid.assign(buffer, &mut token);
+            log::info!("Created buffer {:?} with {:?}", id, desc);
+
+            device
+                .trackers
+                .lock()
+                .buffers
+                .init(
+                    id,
+                    ref_count,
+                    BufferState::with_usage(hal::BufferUses::empty()),
+                )
+                .unwrap();
+            return (id.0, None);
+        };
+
+        let id = fid.assign_error(desc.label.borrow_or_default(), &mut token);
+        (id, Some(error))
+    }
+
     #[cfg(feature = "replay")]
     pub fn device_wait_for_buffer<A: HalApi>(
         &self,
@@ -3354,6 +3691,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -3411,6 +3751,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
 
             // NB: Any change done through the raw texture handle will not be recorded in the replay
             #[cfg(feature = "trace")]
@@ -3696,6 +4039,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -3777,6 +4123,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -3872,6 +4221,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -3950,6 +4302,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -4044,6 +4399,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 let mut trace = trace.lock();
@@ -4104,6 +4462,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 let mut trace = trace.lock();
@@ -4133,6 +4494,24 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         A::hub(self).shader_modules.label_for_resource(id)
     }
 
+    /// Returns the interface of the shader module.
+    ///
+    /// Returns `None` if the module is invalid, or was created from SPIR-V passed through.
+    pub fn shader_module_reflect<A: HalApi>(
+        &self,
+        shader_module_id: id::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (_, mut token) = hub.devices.read(&mut token);
+        let (module_guard, _) = hub.shader_modules.read(&mut token);
+        let module = module_guard.get(shader_module_id).ok()?;
+        module
+            .interface
+            .as_ref()
+            .map(|interface| interface.reflect())
+    }
+
     pub fn shader_module_drop<A: HalApi>(&self, shader_module_id: id::ShaderModuleId) {
         profiling::scope!("drop", "ShaderModule");
 
@@ -4154,6 +4533,101 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
     }
 
+    /// # Safety
+    ///
+    /// The data must have been returned by [`Global::pipeline_cache_get_data`].
+    /// The data of the native cache is passed to the driver, and not every driver
+    /// validates it properly.
+    pub unsafe fn device_create_pipeline_cache<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        desc: &pipeline::PipelineCacheDescriptor,
+        id_in: Input<G, id::PipelineCacheId>,
+    ) -> (
+        id::PipelineCacheId,
+        Option<pipeline::CreatePipelineCacheError>,
+    ) {
+        profiling::scope!("create_pipeline_cache", "Device");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let fid = hub.pipeline_caches.prepare(id_in);
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = match device_guard.get(device_id) {
+                Ok(device) => device,
+                Err(_) => break DeviceError::Invalid.into(),
+            };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
+            #[cfg(feature = "trace")]
+            if let Some(ref trace) = device.trace {
+                trace.lock().add(trace::Action::CreatePipelineCache {
+                    id: fid.id(),
+                    desc: desc.clone(),
+                });
+            }
+
+            let cache = match device.create_pipeline_cache(device_id, desc) {
+                Ok(cache) => cache,
+                Err(e) => break e,
+            };
+            let id = fid.assign(cache, &mut token);
+            return (id.0, None);
+        };
+
+        let id = fid.assign_error(desc.label.borrow_or_default(), &mut token);
+        (id, Some(error))
+    }
+
+    /// Serialize the contents of the cache, to be passed to
+    /// [`Global::device_create_pipeline_cache`] on the next run.
+    ///
+    /// Returns `None` if the cache is invalid.
+    pub fn pipeline_cache_get_data<A: HalApi>(
+        &self,
+        pipeline_cache_id: id::PipelineCacheId,
+    ) -> Option<Vec<u8>> {
+        profiling::scope!("get_data", "PipelineCache");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (cache_guard, _) = hub.pipeline_caches.read(&mut token);
+        let cache = cache_guard.get(pipeline_cache_id).ok()?;
+        let device = &device_guard[cache.device_id.value];
+        Some(unsafe { device.raw.pipeline_cache_get_data(&cache.raw) })
+    }
+
+    pub fn pipeline_cache_label<A: HalApi>(&self, id: id::PipelineCacheId) -> String {
+        A::hub(self).pipeline_caches.label_for_resource(id)
+    }
+
+    pub fn pipeline_cache_drop<A: HalApi>(&self, pipeline_cache_id: id::PipelineCacheId) {
+        profiling::scope!("drop", "PipelineCache");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let (cache, _) = hub
+            .pipeline_caches
+            .unregister(pipeline_cache_id, &mut token);
+        if let Some(cache) = cache {
+            let device = &device_guard[cache.device_id.value];
+            #[cfg(feature = "trace")]
+            if let Some(ref trace) = device.trace {
+                trace
+                    .lock()
+                    .add(trace::Action::DestroyPipelineCache(pipeline_cache_id));
+            }
+            unsafe {
+                device.raw.destroy_pipeline_cache(cache.raw);
+            }
+        }
+    }
+
     pub fn device_create_command_encoder<A: HalApi>(
         &self,
         device_id: id::DeviceId,
@@ -4172,6 +4646,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid,
             };
+            if let Err(error) = device.check_lost() {
+                break error;
+            }
             let dev_stored = Stored {
                 value: id::Valid(device_id),
                 ref_count: device.life_guard.add_ref(),
@@ -4347,6 +4824,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace.lock().add(trace::Action::CreateQuerySet {
@@ -4432,6 +4912,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             let adapter = &adapter_guard[device.adapter_id.value];
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
@@ -4565,6 +5048,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace.lock().add(trace::Action::CreateComputePipeline {
@@ -4735,6 +5221,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(device) => device,
                 Err(_) => break DeviceError::Invalid.into(),
             };
+            if let Err(error) = device.check_lost() {
+                break error.into();
+            }
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 trace
@@ -4837,23 +5326,162 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(())
     }
 
+    /// Poll the device.
+    ///
+    /// A GPU that doesn't finish the work it's waited for in a few seconds is stuck,
+    /// and makes the device lost.
     pub fn device_poll<A: HalApi>(
         &self,
         device_id: id::DeviceId,
-        force_wait: bool,
+        maintain: wgt::Maintain<queue::WrappedSubmissionIndex>,
     ) -> Result<(), WaitIdleError> {
-        let closures = {
+        self.poll_device::<A>(device_id, maintain, WaitLimit::Stuck(CLEANUP_WAIT_MS))
+    }
+
+    /// Poll the device, giving up on waiting after `timeout_ms` with [`WaitIdleError::Timeout`].
+    ///
+    /// The device is kept, so it can be polled again later.
+    pub fn device_poll_timeout<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        maintain: wgt::Maintain<queue::WrappedSubmissionIndex>,
+        timeout_ms: u32,
+    ) -> Result<(), WaitIdleError> {
+        self.poll_device::<A>(device_id, maintain, WaitLimit::Timeout(timeout_ms))
+    }
+
+    fn poll_device<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        maintain: wgt::Maintain<queue::WrappedSubmissionIndex>,
+        limit: WaitLimit,
+    ) -> Result<(), WaitIdleError> {
+        if let wgt::Maintain::WaitForSubmissionIndex(submission_index) = maintain {
+            if submission_index.queue_id != device_id {
+                return Err(WaitIdleError::WrongSubmissionIndex(
+                    submission_index.queue_id,
+                    device_id,
+                ));
+            }
+        }
+
+        let (closures, result) = {
             let hub = A::hub(self);
             let mut token = Token::root();
             let (device_guard, mut token) = hub.devices.read(&mut token);
             device_guard
                 .get(device_id)
                 .map_err(|_| DeviceError::Invalid)?
-                .maintain(hub, force_wait, &mut token)?
+                .maintain_or_lose(hub, maintain, limit, &mut token)
         };
         unsafe {
             closures.fire();
         }
+        result
+    }
+
+    /// Set the closure to call once the device is lost.
+    ///
+    /// If the device is already lost, the closure is called right away.
+    pub fn device_set_lost_closure<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        closure: DeviceLostClosure,
+    ) -> Result<(), InvalidDevice> {
+        let lost_reason = {
+            let hub = A::hub(self);
+            let mut token = Token::root();
+            let (device_guard, _) = hub.devices.read(&mut token);
+            let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+            let lost_reason = *device.lost_reason.lock();
+            match lost_reason {
+                Some(reason) => reason,
+                None => {
+                    *device.lost_closure.lock() = Some(closure);
+                    return Ok(());
+                }
+            }
+        };
+        closure(lost_reason, "the device was already lost".to_string());
+        Ok(())
+    }
+
+    /// Destroy the device, freeing its buffers and textures.
+    ///
+    /// The device is lost with [`wgt::DeviceLostReason::Destroyed`], and all the pending
+    /// mappings are cancelled. Buffers that are mapped are only freed once dropped.
+    pub fn device_destroy<A: HalApi>(&self, device_id: id::DeviceId) -> Result<(), InvalidDevice> {
+        profiling::scope!("destroy", "Device");
+
+        let hub = A::hub(self);
+
+        log::info!("Device {:?} is destroyed", device_id);
+        let mut closures;
+        let mut buffer_ids = Vec::new();
+        let mut texture_ids = Vec::new();
+        {
+            let mut token = Token::root();
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+            // A device that is already lost may never finish its work.
+            if device.check_lost().is_err() {
+                return Ok(());
+            }
+            closures = device.lose(
+                wgt::DeviceLostReason::Destroyed,
+                "the device was destroyed".to_string(),
+            );
+
+            let (mut buffer_guard, mut token) = hub.buffers.write(&mut token);
+            for (id, buffer) in buffer_guard.iter(A::VARIANT) {
+                if buffer.device_id.value.0 == device_id && buffer.raw.is_some() {
+                    buffer_ids.push(id);
+                }
+            }
+            buffer_ids.retain(|&id| {
+                let buffer = buffer_guard.get_mut(id).unwrap();
+                match mem::replace(&mut buffer.map_state, resource::BufferMapState::Idle) {
+                    resource::BufferMapState::Waiting(pending_mapping) => {
+                        closures.mappings.push((
+                            pending_mapping.op,
+                            resource::BufferMapAsyncStatus::ContextLost,
+                        ));
+                        true
+                    }
+                    resource::BufferMapState::Idle => true,
+                    mapped => {
+                        buffer.map_state = mapped;
+                        false
+                    }
+                }
+            });
+
+            let (texture_guard, _) = hub.textures.read(&mut token);
+            for (id, texture) in texture_guard.iter(A::VARIANT) {
+                if texture.device_id.value.0 == device_id {
+                    if let resource::TextureInner::Native { raw: Some(_) } = texture.inner {
+                        texture_ids.push(id);
+                    }
+                }
+            }
+        }
+
+        for buffer_id in buffer_ids {
+            let _ = self.buffer_destroy::<A>(buffer_id);
+        }
+        for texture_id in texture_ids {
+            let _ = self.texture_destroy::<A>(texture_id);
+        }
+
+        {
+            let mut token = Token::root();
+            let (mut device_guard, _) = hub.devices.write(&mut token);
+            let device = device_guard.get_mut(device_id).map_err(|_| InvalidDevice)?;
+            closures.submissions = device.release_resources();
+        }
+        unsafe {
+            closures.fire();
+        }
         Ok(())
     }
 
@@ -4868,8 +5496,23 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let mut token = Token::root();
         let (device_guard, mut token) = hub.devices.read(&mut token);
         for (_, device) in device_guard.iter(A::VARIANT) {
-            let cbs = device.maintain(hub, force_wait, &mut token)?;
+            let maintain = if force_wait {
+                wgt::Maintain::Wait
+            } else {
+                wgt::Maintain::Poll
+            };
+            let (cbs, result) = device.maintain_or_lose(
+                hub,
+                maintain,
+                WaitLimit::Stuck(CLEANUP_WAIT_MS),
+                &mut token,
+            );
             closures.extend(cbs);
+            match result {
+                // Lost devices don't prevent the other ones from being polled.
+                Ok(()) | Err(WaitIdleError::Device(DeviceError::Lost)) => {}
+                Err(error) => return Err(error),
+            }
         }
         Ok(())
     }
@@ -4897,6 +5540,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         {
             self.poll_devices::<hal::api::Gles>(force_wait, &mut closures)?;
         }
+        #[cfg(cpu)]
+        {
+            self.poll_devices::<hal::api::Cpu>(force_wait, &mut closures)?;
+        }
 
         unsafe {
             closures.fire();
@@ -4975,6 +5622,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 .map_err(|_| resource::BufferAccessError::Invalid)?;
 
             check_buffer_usage(buffer.usage, pub_usage)?;
+            // A lost device would never get to the mapping, so it fails right away,
+            // with nothing locked by the time the callback runs.
+            if device_guard[buffer.device_id.value].check_lost().is_err() {
+                drop(buffer_guard);
+                drop(device_guard);
+                let closures = UserClosures {
+                    mappings: vec![(op, resource::BufferMapAsyncStatus::ContextLost)],
+                    ..UserClosures::default()
+                };
+                unsafe {
+                    closures.fire();
+                }
+                return Ok(());
+            }
             buffer.map_state = match buffer.map_state {
                 resource::BufferMapState::Init { .. } | resource::BufferMapState::Active { .. } => {
                     return Err(resource::BufferAccessError::AlreadyMapped);
@@ -5065,7 +5726,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         max: range.end,
                     });
                 }
-                unsafe { Ok((ptr.as_ptr().offset(offset as isize), range_size)) }
+                // The mapping starts at `range.start`, not at the start of the buffer.
+                unsafe {
+                    Ok((
+                        ptr.as_ptr().offset((offset - range.start) as isize),
+                        range_size,
+                    ))
+                }
             }
             resource::BufferMapState::Idle | resource::BufferMapState::Waiting(_) => {
                 Err(resource::BufferAccessError::NotMapped)
@@ -5200,3 +5867,147 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(())
     }
 }
+
+#[cfg(all(test, cpu))]
+fn create_test_device(global: &Global<crate::hub::IdentityManagerFactory>) -> id::DeviceId {
+    let adapter = global
+        .request_adapter(
+            &instance::RequestAdapterOptions::default(),
+            instance::AdapterInputs::Mask(wgt::Backends::CPU, |_| PhantomData),
+        )
+        .unwrap();
+    let (device, error) = global.adapter_request_device::<hal::api::Cpu>(
+        adapter,
+        &DeviceDescriptor::default(),
+        None,
+        PhantomData,
+    );
+    assert!(error.is_none());
+    device
+}
+
+#[cfg(all(test, cpu))]
+fn count_lost(
+    global: &Global<crate::hub::IdentityManagerFactory>,
+    device: id::DeviceId,
+    counter: &std::sync::Arc<std::sync::atomic::AtomicUsize>,
+) {
+    let counter = std::sync::Arc::clone(counter);
+    let closure = Box::new(move |_, _| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    });
+    global
+        .device_set_lost_closure::<hal::api::Cpu>(device, closure)
+        .unwrap();
+}
+
+#[cfg(cpu)]
+#[test]
+fn test_poll_timeout_keeps_device() {
+    use std::sync::{atomic, Arc};
+
+    let global = Global::new(
+        "test",
+        crate::hub::IdentityManagerFactory,
+        wgt::Backends::CPU,
+    );
+    let device = create_test_device(&global);
+    let lost = Arc::new(atomic::AtomicUsize::new(0));
+    count_lost(&global, device, &lost);
+
+    // Nothing is ever going to reach this submission.
+    let maintain = wgt::Maintain::WaitForSubmissionIndex(queue::WrappedSubmissionIndex {
+        queue_id: device,
+        index: 10,
+    });
+    for _ in 0..2 {
+        assert!(matches!(
+            global.device_poll_timeout::<hal::api::Cpu>(device, maintain, 1),
+            Err(WaitIdleError::Timeout)
+        ));
+    }
+    assert_eq!(lost.load(atomic::Ordering::SeqCst), 0);
+    global
+        .device_poll::<hal::api::Cpu>(device, wgt::Maintain::Wait)
+        .unwrap();
+}
+
+#[cfg(cpu)]
+#[test]
+fn test_stuck_gpu_loses_device() {
+    use std::sync::{atomic, Arc};
+
+    let global = Global::new(
+        "test",
+        crate::hub::IdentityManagerFactory,
+        wgt::Backends::CPU,
+    );
+    let device = create_test_device(&global);
+    let lost = Arc::new(atomic::AtomicUsize::new(0));
+    count_lost(&global, device, &lost);
+
+    let maintain = wgt::Maintain::WaitForSubmissionIndex(queue::WrappedSubmissionIndex {
+        queue_id: device,
+        index: 10,
+    });
+    let (closures, result) = {
+        let hub = <hal::api::Cpu as HalApi>::hub(&global);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        device_guard.get(device).unwrap().maintain_or_lose(
+            hub,
+            maintain,
+            WaitLimit::Stuck(1),
+            &mut token,
+        )
+    };
+    unsafe {
+        closures.fire();
+    }
+    assert!(matches!(
+        result,
+        Err(WaitIdleError::Device(DeviceError::Lost))
+    ));
+    assert_eq!(lost.load(atomic::Ordering::SeqCst), 1);
+    assert!(matches!(
+        global.device_poll::<hal::api::Cpu>(device, wgt::Maintain::Wait),
+        Err(WaitIdleError::Device(DeviceError::Lost))
+    ));
+}
+
+#[cfg(cpu)]
+#[test]
+fn test_lost_closures_of_all_devices() {
+    use std::sync::{atomic, Arc};
+
+    let global = Global::new(
+        "test",
+        crate::hub::IdentityManagerFactory,
+        wgt::Backends::CPU,
+    );
+    let devices = [create_test_device(&global), create_test_device(&global)];
+    let lost = Arc::new(atomic::AtomicUsize::new(0));
+    for &device in devices.iter() {
+        count_lost(&global, device, &lost);
+    }
+
+    // Devices lost in the same pass have their closures collected together.
+    let mut closures = UserClosures::default();
+    {
+        let hub = <hal::api::Cpu as HalApi>::hub(&global);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        for &device in devices.iter() {
+            closures.extend(
+                device_guard
+                    .get(device)
+                    .unwrap()
+                    .lose(wgt::DeviceLostReason::Unknown, "test".to_string()),
+            );
+        }
+    }
+    unsafe {
+        closures.fire();
+    }
+    assert_eq!(lost.load(atomic::Ordering::SeqCst), 2);
+}
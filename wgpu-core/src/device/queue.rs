@@ -9,11 +9,11 @@ use crate::{
     conv,
     device::{DeviceError, WaitIdleError},
     get_lowest_common_denom,
-    hub::{Global, GlobalIdentityHandlerFactory, HalApi, Token},
+    hub::{Global, GlobalIdentityHandlerFactory, HalApi, Input, Token},
     id,
     init_tracker::{has_copy_partial_init_tracker_coverage, TextureInitRange},
-    resource::{BufferAccessError, BufferMapState, TextureInner},
-    track, FastHashSet,
+    resource::{self, BufferAccessError, BufferMapState, TextureInner},
+    track, FastHashSet, LifeGuard, Stored, SubmissionIndex,
 };
 
 use hal::{CommandEncoder as _, Device as _, Queue as _};
@@ -29,6 +29,14 @@ use thiserror::Error;
 /// without a concrete moment of when it can be cleared.
 const WRITE_COMMAND_BUFFERS_PER_POOL: usize = 64;
 
+/// Index of a submission, along with the queue it was made on.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct WrappedSubmissionIndex {
+    pub queue_id: id::QueueId,
+    pub index: SubmissionIndex,
+}
+
 pub type OnSubmittedWorkDoneCallback = unsafe extern "C" fn(user_data: *mut u8);
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -193,6 +201,38 @@ impl<A: hal::Api> PendingWrites<A> {
     }
 }
 
+fn validate_write_buffer<A: hal::Api>(
+    dst: &resource::Buffer<A>,
+    buffer_id: id::BufferId,
+    buffer_offset: wgt::BufferAddress,
+    data_size: wgt::BufferAddress,
+) -> Result<(), TransferError> {
+    if dst.raw.is_none() {
+        return Err(TransferError::InvalidBuffer(buffer_id));
+    }
+    if !dst.usage.contains(wgt::BufferUsages::COPY_DST) {
+        return Err(TransferError::MissingCopyDstUsageFlag(
+            Some(buffer_id),
+            None,
+        ));
+    }
+    if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(TransferError::UnalignedCopySize(data_size));
+    }
+    if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(TransferError::UnalignedBufferOffset(buffer_offset));
+    }
+    if buffer_offset + data_size > dst.size {
+        return Err(TransferError::BufferOverrun {
+            start_offset: buffer_offset,
+            end_offset: buffer_offset + data_size,
+            buffer_size: dst.size,
+            side: CopySide::Destination,
+        });
+    }
+    Ok(())
+}
+
 impl<A: hal::Api> super::Device<A> {
     fn prepare_stage(&mut self, size: wgt::BufferAddress) -> Result<StagingData<A>, DeviceError> {
         profiling::scope!("prepare_stage");
@@ -219,6 +259,8 @@ pub enum QueueWriteError {
     Transfer(#[from] TransferError),
     #[error(transparent)]
     MemoryInitFailure(#[from] ClearError),
+    #[error("staging buffer {0:?} was created on another device")]
+    StagingBufferDeviceMismatch(id::StagingBufferId),
 }
 
 #[derive(Clone, Debug, Error)]
@@ -257,7 +299,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let device = device_guard
             .get_mut(queue_id)
             .map_err(|_| DeviceError::Invalid)?;
-        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        device.check_lost()?;
 
         #[cfg(feature = "trace")]
         if let Some(ref trace) = device.trace {
@@ -284,6 +326,185 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         .map_err(DeviceError::from)?;
 
+        self.queue_write_staging_buffer_impl(
+            device,
+            &mut token,
+            stage,
+            data_size,
+            buffer_id,
+            buffer_offset,
+        )
+    }
+
+    /// Create a staging buffer of `buffer_size` bytes, to be filled through the returned
+    /// pointer, and then given to [`Global::queue_write_staging_buffer`].
+    pub fn queue_create_staging_buffer<A: HalApi>(
+        &self,
+        queue_id: id::QueueId,
+        buffer_size: wgt::BufferSize,
+        id_in: Input<G, id::StagingBufferId>,
+    ) -> Result<(id::StagingBufferId, *mut u8), QueueWriteError> {
+        profiling::scope!("create_staging_buffer", "Queue");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let device = device_guard
+            .get_mut(queue_id)
+            .map_err(|_| DeviceError::Invalid)?;
+        device.check_lost()?;
+
+        let stage = device.prepare_stage(buffer_size.get())?;
+        let mapping = match unsafe { device.raw.map_buffer(&stage.buffer, 0..buffer_size.get()) } {
+            Ok(mapping) => mapping,
+            Err(error) => {
+                unsafe { device.raw.destroy_buffer(stage.buffer) };
+                return Err(DeviceError::from(error).into());
+            }
+        };
+        // The memory is handed out as a slice, so it can't be left uninitialized.
+        unsafe {
+            ptr::write_bytes(mapping.ptr.as_ptr(), 0, buffer_size.get() as usize);
+        }
+        let staging_buffer = resource::StagingBuffer {
+            raw: stage.buffer,
+            device_id: Stored {
+                value: id::Valid(queue_id),
+                ref_count: device.life_guard.add_ref(),
+            },
+            size: buffer_size.get(),
+            life_guard: LifeGuard::new("<StagingBuffer>"),
+            #[cfg(feature = "trace")]
+            ptr: mapping.ptr,
+            is_coherent: mapping.is_coherent,
+        };
+
+        let fid = hub.staging_buffers.prepare(id_in);
+        let id = fid.assign(staging_buffer, &mut token);
+        Ok((id.0, mapping.ptr.as_ptr()))
+    }
+
+    /// Copy the contents of a staging buffer into `buffer_id` at `buffer_offset`,
+    /// consuming the staging buffer.
+    pub fn queue_write_staging_buffer<A: HalApi>(
+        &self,
+        queue_id: id::QueueId,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        staging_buffer_id: id::StagingBufferId,
+    ) -> Result<(), QueueWriteError> {
+        profiling::scope!("write_staging_buffer", "Queue");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let staging_buffer = hub
+            .staging_buffers
+            .unregister(staging_buffer_id, &mut token)
+            .0
+            .ok_or(TransferError::InvalidBuffer(buffer_id))?;
+        if staging_buffer.device_id.value.0 != queue_id {
+            // The GPU has never seen the buffer, so it can be freed right away.
+            if let Ok(owner) = device_guard.get(staging_buffer.device_id.value.0) {
+                unsafe {
+                    let _ = owner.raw.unmap_buffer(&staging_buffer.raw);
+                    owner.raw.destroy_buffer(staging_buffer.raw);
+                }
+            }
+            return Err(QueueWriteError::StagingBufferDeviceMismatch(
+                staging_buffer_id,
+            ));
+        }
+        let device = device_guard
+            .get_mut(queue_id)
+            .map_err(|_| DeviceError::Invalid)?;
+        let data_size = staging_buffer.size;
+
+        #[cfg(feature = "trace")]
+        if let Some(ref trace) = device.trace {
+            let mut trace = trace.lock();
+            let data = unsafe {
+                std::slice::from_raw_parts(staging_buffer.ptr.as_ptr(), data_size as usize)
+            };
+            let data_path = trace.make_binary("bin", data);
+            trace.add(Action::WriteBuffer {
+                id: buffer_id,
+                data: data_path,
+                range: buffer_offset..buffer_offset + data_size,
+                queued: true,
+            });
+        }
+
+        let stage = StagingData {
+            buffer: staging_buffer.raw,
+        };
+        let result = unsafe {
+            if !staging_buffer.is_coherent {
+                device
+                    .raw
+                    .flush_mapped_ranges(&stage.buffer, iter::once(0..data_size));
+            }
+            device.raw.unmap_buffer(&stage.buffer)
+        };
+        if let Err(error) = result {
+            device.pending_writes.consume(stage);
+            return Err(DeviceError::from(error).into());
+        }
+        if let Err(error) = device.check_lost() {
+            device.pending_writes.consume(stage);
+            return Err(error.into());
+        }
+
+        self.queue_write_staging_buffer_impl(
+            device,
+            &mut token,
+            stage,
+            data_size,
+            buffer_id,
+            buffer_offset,
+        )
+    }
+
+    /// Check that a write of `buffer_size` bytes into `buffer_id` at `buffer_offset` is valid,
+    /// so that a staging buffer isn't filled for nothing.
+    pub fn queue_validate_write_buffer<A: HalApi>(
+        &self,
+        _queue_id: id::QueueId,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        buffer_size: wgt::BufferAddress,
+    ) -> Result<(), QueueWriteError> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+        let buffer = buffer_guard
+            .get(buffer_id)
+            .map_err(|_| TransferError::InvalidBuffer(buffer_id))?;
+        validate_write_buffer(buffer, buffer_id, buffer_offset, buffer_size)?;
+        Ok(())
+    }
+
+    fn queue_write_staging_buffer_impl<A: HalApi>(
+        &self,
+        device: &mut super::Device<A>,
+        device_token: &mut Token<super::Device<A>>,
+        stage: StagingData<A>,
+        data_size: wgt::BufferAddress,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+    ) -> Result<(), QueueWriteError> {
+        let hub = A::hub(self);
+        let (buffer_guard, _) = hub.buffers.read(device_token);
+
+        let validation = buffer_guard
+            .get(buffer_id)
+            .map_err(|_| TransferError::InvalidBuffer(buffer_id))
+            .and_then(|dst| validate_write_buffer(dst, buffer_id, buffer_offset, data_size));
+        if let Err(error) = validation {
+            device.pending_writes.consume(stage);
+            return Err(error.into());
+        }
+
         let mut trackers = device.trackers.lock();
         let (dst, transition) = trackers
             .buffers
@@ -293,28 +514,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .raw
             .as_ref()
             .ok_or(TransferError::InvalidBuffer(buffer_id))?;
-        if !dst.usage.contains(wgt::BufferUsages::COPY_DST) {
-            return Err(TransferError::MissingCopyDstUsageFlag(Some(buffer_id), None).into());
-        }
         dst.life_guard.use_at(device.active_submission_index + 1);
 
-        if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            return Err(TransferError::UnalignedCopySize(data_size).into());
-        }
-        if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            return Err(TransferError::UnalignedBufferOffset(buffer_offset).into());
-        }
-        if buffer_offset + data_size > dst.size {
-            return Err(TransferError::BufferOverrun {
-                start_offset: buffer_offset,
-                end_offset: buffer_offset + data_size,
-                buffer_size: dst.size,
-                side: CopySide::Destination,
-            }
-            .into());
-        }
-
-        let region = wgt::BufferSize::new(data.len() as u64).map(|size| hal::BufferCopy {
+        let region = wgt::BufferSize::new(data_size).map(|size| hal::BufferCopy {
             src_offset: 0,
             dst_offset: buffer_offset,
             size,
@@ -336,7 +538,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         // Ensure the overwritten bytes are marked as initialized so they don't need to be nulled prior to mapping or binding.
         {
             drop(buffer_guard);
-            let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+            let (mut buffer_guard, _) = hub.buffers.write(device_token);
 
             let dst = buffer_guard.get_mut(buffer_id).unwrap();
             dst.initialization_status
@@ -362,6 +564,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let device = device_guard
             .get_mut(queue_id)
             .map_err(|_| DeviceError::Invalid)?;
+        device.check_lost()?;
 
         #[cfg(feature = "trace")]
         if let Some(ref trace) = device.trace {
@@ -580,10 +783,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         queue_id: id::QueueId,
         command_buffer_ids: &[id::CommandBufferId],
-    ) -> Result<(), QueueSubmitError> {
+    ) -> Result<WrappedSubmissionIndex, QueueSubmitError> {
         profiling::scope!("submit", "Queue");
 
-        let callbacks = {
+        let (submit_index, callbacks) = {
             let hub = A::hub(self);
             let mut token = Token::root();
 
@@ -591,6 +794,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let device = device_guard
                 .get_mut(queue_id)
                 .map_err(|_| DeviceError::Invalid)?;
+            device.check_lost()?;
             device.temp_suspected.clear();
             device.active_submission_index += 1;
             let submit_index = device.active_submission_index;
@@ -875,24 +1079,35 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
             // This will schedule destruction of all resources that are no longer needed
             // by the user but used in the command stream, among other things.
-            let closures = match device.maintain(hub, false, &mut token) {
+            let closures = match device.maintain(
+                hub,
+                wgt::Maintain::Poll,
+                super::WaitLimit::Stuck(super::CLEANUP_WAIT_MS),
+                &mut token,
+            ) {
                 Ok(closures) => closures,
                 Err(WaitIdleError::Device(err)) => return Err(QueueSubmitError::Queue(err)),
                 Err(WaitIdleError::StuckGpu) => return Err(QueueSubmitError::StuckGpu),
+                Err(WaitIdleError::Timeout) | Err(WaitIdleError::WrongSubmissionIndex(..)) => {
+                    unreachable!()
+                }
             };
 
             device.pending_writes.temp_resources = pending_write_resources;
             device.temp_suspected.clear();
             device.lock_life(&mut token).post_submit();
 
-            closures
+            (submit_index, closures)
         };
 
         // the closures should execute with nothing locked!
         unsafe {
             callbacks.fire();
         }
-        Ok(())
+        Ok(WrappedSubmissionIndex {
+            queue_id,
+            index: submit_index,
+        })
     }
 
     pub fn queue_get_timestamp_period<A: HalApi>(
@@ -931,3 +1146,40 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(())
     }
 }
+
+#[cfg(cpu)]
+#[test]
+fn test_staging_buffer_device_mismatch() {
+    use std::marker::PhantomData;
+
+    let global = Global::new(
+        "test",
+        crate::hub::IdentityManagerFactory,
+        wgt::Backends::CPU,
+    );
+    let first = super::create_test_device(&global);
+    let second = super::create_test_device(&global);
+    let (buffer, error) = global.device_create_buffer::<hal::api::Cpu>(
+        second,
+        &resource::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgt::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+        PhantomData,
+    );
+    assert!(error.is_none());
+
+    let size = wgt::BufferSize::new(16).unwrap();
+    let (staging_buffer, ptr) = global
+        .queue_create_staging_buffer::<hal::api::Cpu>(first, size, PhantomData)
+        .unwrap();
+    assert!(unsafe { std::slice::from_raw_parts(ptr, 16) }
+        .iter()
+        .all(|&byte| byte == 0));
+    assert!(matches!(
+        global.queue_write_staging_buffer::<hal::api::Cpu>(second, buffer, 0, staging_buffer),
+        Err(QueueWriteError::StagingBufferDeviceMismatch(id)) if id == staging_buffer
+    ));
+}
@@ -1,4 +1,6 @@
 use crate::id;
+#[cfg(feature = "replay")]
+use std::io::Read as _;
 use std::ops::Range;
 #[cfg(feature = "trace")]
 use std::{borrow::Cow, io::Write as _};
@@ -8,6 +10,26 @@ use std::{borrow::Cow, io::Write as _};
 type FileName = String;
 
 pub const FILE_NAME: &str = "trace.ron";
+pub const BINARY_FILE_NAME: &str = "trace.bin";
+
+/// Binary traces start with this signature, followed by the format version
+/// and the compression method, one byte each.
+///
+/// The rest of the file is a stream of records, deflated if the compression
+/// method is `COMPRESSION_DEFLATE`. Each record starts with a tag byte:
+///   - `RECORD_ACTION` is followed by the size of the action as `u64`,
+///     and the action encoded with `bincode`.
+///   - `RECORD_BLOB` is followed by the size of the name as `u32`, the name,
+///     the size of the data as `u64`, and the data. Blobs take the place
+///     of the data files of RON traces, and precede the actions using them.
+///
+/// All the sizes are little-endian.
+pub const BINARY_SIGNATURE: [u8; 8] = *b"WGPUTRCE";
+pub const BINARY_VERSION: u8 = 1;
+pub const COMPRESSION_NONE: u8 = 0;
+pub const COMPRESSION_DEFLATE: u8 = 1;
+pub const RECORD_ACTION: u8 = 0;
+pub const RECORD_BLOB: u8 = 1;
 
 #[cfg(feature = "trace")]
 pub(crate) fn new_render_bundle_encoder_descriptor<'a>(
@@ -82,6 +104,11 @@ pub enum Action<'a> {
         data: FileName,
     },
     DestroyShaderModule(id::ShaderModuleId),
+    CreatePipelineCache {
+        id: id::PipelineCacheId,
+        desc: crate::pipeline::PipelineCacheDescriptor<'a>,
+    },
+    DestroyPipelineCache(id::PipelineCacheId),
     CreateComputePipeline {
         id: id::ComputePipelineId,
         desc: crate::pipeline::ComputePipelineDescriptor<'a>,
@@ -181,25 +208,103 @@ pub enum Command {
     },
 }
 
+/// Format of the trace written by a device.
+///
+/// Picked with the `WGPU_TRACE_FORMAT` environment variable, which can be
+/// `ron` (the default), `binary`, or `deflate` for the compressed binary format.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    Ron,
+    Binary,
+    Deflate,
+}
+
+impl TraceFormat {
+    pub fn from_env() -> Self {
+        match std::env::var("WGPU_TRACE_FORMAT") {
+            Ok(value) => match value.to_lowercase().as_str() {
+                "ron" => Self::Ron,
+                "binary" => Self::Binary,
+                "deflate" => Self::Deflate,
+                _ => {
+                    log::warn!("Unknown trace format {:?}, using RON", value);
+                    Self::Ron
+                }
+            },
+            Err(_) => Self::Ron,
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+enum TraceStream {
+    Ron {
+        path: std::path::PathBuf,
+        file: std::fs::File,
+        config: ron::ser::PrettyConfig,
+    },
+    Binary(std::io::BufWriter<std::fs::File>),
+    Deflate(flate2::write::DeflateEncoder<std::io::BufWriter<std::fs::File>>),
+}
+
+#[cfg(feature = "trace")]
+impl TraceStream {
+    fn binary(&mut self) -> Option<&mut dyn std::io::Write> {
+        match *self {
+            Self::Ron { .. } => None,
+            Self::Binary(ref mut stream) => Some(stream),
+            Self::Deflate(ref mut stream) => Some(stream),
+        }
+    }
+}
+
 #[cfg(feature = "trace")]
 #[derive(Debug)]
 pub struct Trace {
-    path: std::path::PathBuf,
-    file: std::fs::File,
-    config: ron::ser::PrettyConfig,
+    stream: TraceStream,
     binary_id: usize,
 }
 
 #[cfg(feature = "trace")]
 impl Trace {
     pub fn new(path: &std::path::Path) -> Result<Self, std::io::Error> {
-        log::info!("Tracing into '{:?}'", path);
-        let mut file = std::fs::File::create(path.join(FILE_NAME))?;
-        file.write_all(b"[\n")?;
+        Self::with_format(path, TraceFormat::from_env())
+    }
+
+    pub fn with_format(
+        path: &std::path::Path,
+        format: TraceFormat,
+    ) -> Result<Self, std::io::Error> {
+        log::info!("Tracing into '{:?}' as {:?}", path, format);
+        let stream = match format {
+            TraceFormat::Ron => {
+                let mut file = std::fs::File::create(path.join(FILE_NAME))?;
+                file.write_all(b"[\n")?;
+                TraceStream::Ron {
+                    path: path.to_path_buf(),
+                    file,
+                    config: ron::ser::PrettyConfig::default(),
+                }
+            }
+            TraceFormat::Binary | TraceFormat::Deflate => {
+                let mut file =
+                    std::io::BufWriter::new(std::fs::File::create(path.join(BINARY_FILE_NAME))?);
+                file.write_all(&BINARY_SIGNATURE)?;
+                if format == TraceFormat::Deflate {
+                    file.write_all(&[BINARY_VERSION, COMPRESSION_DEFLATE])?;
+                    TraceStream::Deflate(flate2::write::DeflateEncoder::new(
+                        file,
+                        flate2::Compression::fast(),
+                    ))
+                } else {
+                    file.write_all(&[BINARY_VERSION, COMPRESSION_NONE])?;
+                    TraceStream::Binary(file)
+                }
+            }
+        };
         Ok(Self {
-            path: path.to_path_buf(),
-            file,
-            config: ron::ser::PrettyConfig::default(),
+            stream,
             binary_id: 0,
         })
     }
@@ -207,25 +312,260 @@ impl Trace {
     pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> String {
         self.binary_id += 1;
         let name = format!("data{}.{}", self.binary_id, kind);
-        let _ = std::fs::write(self.path.join(&name), data);
+        match self.stream {
+            TraceStream::Ron { ref path, .. } => {
+                let _ = std::fs::write(path.join(&name), data);
+            }
+            _ => {
+                if let Err(e) = self.write_blob(&name, data) {
+                    log::warn!("Unable to write a trace blob: {:?}", e);
+                }
+            }
+        }
         name
     }
 
     pub(crate) fn add(&mut self, action: Action) {
-        match ron::ser::to_string_pretty(&action, self.config.clone()) {
-            Ok(string) => {
-                let _ = writeln!(self.file, "{},", string);
+        // Submissions and presentations are where the application is most
+        // likely to crash, so the binary stream is flushed after them.
+        let flush = matches!(action, Action::Submit(..) | Action::Present(_));
+        let stream: &mut dyn std::io::Write = match self.stream {
+            TraceStream::Ron {
+                ref mut file,
+                ref config,
+                ..
+            } => {
+                match ron::ser::to_string_pretty(&action, config.clone()) {
+                    Ok(string) => {
+                        let _ = writeln!(file, "{},", string);
+                    }
+                    Err(e) => {
+                        log::warn!("RON serialization failure: {:?}", e);
+                    }
+                }
+                return;
+            }
+            TraceStream::Binary(ref mut stream) => stream,
+            TraceStream::Deflate(ref mut stream) => stream,
+        };
+        match bincode::serialize(&action) {
+            Ok(bytes) => {
+                let header = (bytes.len() as u64).to_le_bytes();
+                let _ = stream
+                    .write_all(&[RECORD_ACTION])
+                    .and_then(|()| stream.write_all(&header))
+                    .and_then(|()| stream.write_all(&bytes));
             }
             Err(e) => {
-                log::warn!("RON serialization failure: {:?}", e);
+                log::warn!("Binary serialization failure: {:?}", e);
             }
         }
+        if flush {
+            let _ = stream.flush();
+        }
+    }
+
+    fn write_blob(&mut self, name: &str, data: &[u8]) -> Result<(), std::io::Error> {
+        let stream = match self.stream.binary() {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        stream.write_all(&[RECORD_BLOB])?;
+        stream.write_all(&(name.len() as u32).to_le_bytes())?;
+        stream.write_all(name.as_bytes())?;
+        stream.write_all(&(data.len() as u64).to_le_bytes())?;
+        stream.write_all(data)
     }
 }
 
 #[cfg(feature = "trace")]
 impl Drop for Trace {
     fn drop(&mut self) {
-        let _ = self.file.write_all(b"]");
+        match self.stream {
+            TraceStream::Ron { ref mut file, .. } => {
+                let _ = file.write_all(b"]");
+            }
+            TraceStream::Binary(ref mut stream) => {
+                let _ = stream.flush();
+            }
+            TraceStream::Deflate(ref mut stream) => {
+                let _ = stream.try_finish();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "replay")]
+#[derive(Debug, thiserror::Error)]
+pub enum BinaryTraceError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("not a binary trace")]
+    InvalidSignature,
+    #[error("unsupported format version {0}")]
+    UnsupportedVersion(u8),
+    #[error("unknown compression method {0}")]
+    UnknownCompression(u8),
+    #[error("unknown record tag {0}")]
+    UnknownRecord(u8),
+    #[error("blob name is not valid UTF-8")]
+    InvalidBlobName,
+    #[error(transparent)]
+    Decode(#[from] bincode::Error),
+}
+
+/// Streaming reader of the binary trace format.
+#[cfg(feature = "replay")]
+pub struct BinaryReader {
+    stream: Box<dyn std::io::Read>,
+    blobs: crate::FastHashMap<String, Vec<u8>>,
+}
+
+#[cfg(feature = "replay")]
+impl BinaryReader {
+    pub fn new<R: std::io::Read + 'static>(mut reader: R) -> Result<Self, BinaryTraceError> {
+        let mut header = [0; 10];
+        reader.read_exact(&mut header)?;
+        if header[..8] != BINARY_SIGNATURE {
+            return Err(BinaryTraceError::InvalidSignature);
+        }
+        if header[8] != BINARY_VERSION {
+            return Err(BinaryTraceError::UnsupportedVersion(header[8]));
+        }
+        let stream: Box<dyn std::io::Read> = match header[9] {
+            COMPRESSION_NONE => Box::new(reader),
+            COMPRESSION_DEFLATE => Box::new(flate2::read::DeflateDecoder::new(reader)),
+            other => return Err(BinaryTraceError::UnknownCompression(other)),
+        };
+        Ok(Self {
+            stream,
+            blobs: crate::FastHashMap::default(),
+        })
+    }
+
+    /// Read the next action, collecting the blobs on the way.
+    ///
+    /// Returns `None` at the end of the trace.
+    pub fn next_action(&mut self) -> Result<Option<Action<'static>>, BinaryTraceError> {
+        loop {
+            let mut tag = [0];
+            if self.stream.read(&mut tag)? == 0 {
+                return Ok(None);
+            }
+            match tag[0] {
+                RECORD_ACTION => {
+                    let size = self.read_size()?;
+                    let bytes = self.read_bytes(size)?;
+                    return Ok(Some(bincode::deserialize(&bytes)?));
+                }
+                RECORD_BLOB => {
+                    let mut name_size = [0; 4];
+                    self.stream.read_exact(&mut name_size)?;
+                    let name = self.read_bytes(u32::from_le_bytes(name_size) as u64)?;
+                    let name =
+                        String::from_utf8(name).map_err(|_| BinaryTraceError::InvalidBlobName)?;
+                    let size = self.read_size()?;
+                    let data = self.read_bytes(size)?;
+                    self.blobs.insert(name, data);
+                }
+                other => return Err(BinaryTraceError::UnknownRecord(other)),
+            }
+        }
+    }
+
+    /// Take the blob with the given name, if it was read already.
+    pub fn take_blob(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.blobs.remove(name)
+    }
+
+    fn read_size(&mut self) -> Result<u64, std::io::Error> {
+        let mut size = [0; 8];
+        self.stream.read_exact(&mut size)?;
+        Ok(u64::from_le_bytes(size))
+    }
+
+    fn read_bytes(&mut self, size: u64) -> Result<Vec<u8>, std::io::Error> {
+        let mut bytes = Vec::new();
+        self.stream.by_ref().take(size).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != size {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(all(feature = "trace", feature = "replay"))]
+#[test]
+fn test_binary_round_trip() {
+    for &format in &[TraceFormat::Binary, TraceFormat::Deflate] {
+        check_binary_round_trip(format);
+    }
+}
+
+#[cfg(all(feature = "trace", feature = "replay"))]
+#[cfg(test)]
+fn check_binary_round_trip(format: TraceFormat) {
+    use id::TypedId as _;
+
+    let dir = std::env::temp_dir().join(format!("wgpu-trace-{}-{:?}", std::process::id(), format));
+    std::fs::create_dir_all(&dir).unwrap();
+    let buffer = id::BufferId::zip(1, 1, wgt::Backend::Empty);
+    {
+        let mut trace = Trace::with_format(&dir, format).unwrap();
+        let data = trace.make_binary("bin", &[1, 2, 3, 4]);
+        trace.add(Action::WriteBuffer {
+            id: buffer,
+            data,
+            range: 0..4,
+            queued: true,
+        });
+        trace.add(Action::DestroyBuffer(buffer));
+    }
+
+    let file = std::fs::File::open(dir.join(BINARY_FILE_NAME)).unwrap();
+    let mut reader = BinaryReader::new(file).unwrap();
+    match reader.next_action().unwrap() {
+        Some(Action::WriteBuffer {
+            id, data, range, ..
+        }) => {
+            assert_eq!(id, buffer);
+            assert_eq!(range, 0..4);
+            assert_eq!(reader.take_blob(&data), Some(vec![1, 2, 3, 4]));
+        }
+        other => panic!("Unexpected action {:?}", other),
+    }
+    match reader.next_action().unwrap() {
+        Some(Action::DestroyBuffer(id)) => assert_eq!(id, buffer),
+        other => panic!("Unexpected action {:?}", other),
+    }
+    assert!(reader.next_action().unwrap().is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(all(feature = "trace", feature = "replay"))]
+#[test]
+fn test_binary_flush_on_submit() {
+    for &format in &[TraceFormat::Binary, TraceFormat::Deflate] {
+        let dir = std::env::temp_dir().join(format!(
+            "wgpu-trace-flush-{}-{:?}",
+            std::process::id(),
+            format
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut trace = Trace::with_format(&dir, format).unwrap();
+        trace.add(Action::Submit(1, Vec::new()));
+
+        // The trace is still alive, so only the flushed part is on disk.
+        let file = std::fs::File::open(dir.join(BINARY_FILE_NAME)).unwrap();
+        let mut reader = BinaryReader::new(file).unwrap();
+        match reader.next_action().unwrap() {
+            Some(Action::Submit(index, commands)) => {
+                assert_eq!(index, 1);
+                assert!(commands.is_empty());
+            }
+            other => panic!("Unexpected action {:?}", other),
+        }
+        drop(trace);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -4,8 +4,8 @@ use crate::{
     device::Device,
     id,
     instance::{Adapter, HalSurface, Instance, Surface},
-    pipeline::{ComputePipeline, RenderPipeline, ShaderModule},
-    resource::{Buffer, QuerySet, Sampler, Texture, TextureClearMode, TextureView},
+    pipeline::{ComputePipeline, PipelineCache, RenderPipeline, ShaderModule},
+    resource::{Buffer, QuerySet, Sampler, StagingBuffer, Texture, TextureClearMode, TextureView},
     Epoch, Index,
 };
 
@@ -278,6 +278,9 @@ impl<A: hal::Api> Access<QuerySet<A>> for ComputePipeline<A> {}
 impl<A: hal::Api> Access<QuerySet<A>> for Sampler<A> {}
 impl<A: hal::Api> Access<ShaderModule<A>> for Device<A> {}
 impl<A: hal::Api> Access<ShaderModule<A>> for BindGroupLayout<A> {}
+impl<A: hal::Api> Access<PipelineCache<A>> for Device<A> {}
+impl<A: hal::Api> Access<PipelineCache<A>> for ShaderModule<A> {}
+impl<A: hal::Api> Access<StagingBuffer<A>> for Device<A> {}
 impl<A: hal::Api> Access<Buffer<A>> for Root {}
 impl<A: hal::Api> Access<Buffer<A>> for Device<A> {}
 impl<A: hal::Api> Access<Buffer<A>> for BindGroupLayout<A> {}
@@ -286,6 +289,7 @@ impl<A: hal::Api> Access<Buffer<A>> for CommandBuffer<A> {}
 impl<A: hal::Api> Access<Buffer<A>> for ComputePipeline<A> {}
 impl<A: hal::Api> Access<Buffer<A>> for RenderPipeline<A> {}
 impl<A: hal::Api> Access<Buffer<A>> for QuerySet<A> {}
+impl<A: hal::Api> Access<Buffer<A>> for StagingBuffer<A> {}
 impl<A: hal::Api> Access<Texture<A>> for Root {}
 impl<A: hal::Api> Access<Texture<A>> for Device<A> {}
 impl<A: hal::Api> Access<Texture<A>> for Buffer<A> {}
@@ -385,8 +389,10 @@ pub trait GlobalIdentityHandlerFactory:
     + IdentityHandlerFactory<id::RenderBundleId>
     + IdentityHandlerFactory<id::RenderPipelineId>
     + IdentityHandlerFactory<id::ComputePipelineId>
+    + IdentityHandlerFactory<id::PipelineCacheId>
     + IdentityHandlerFactory<id::QuerySetId>
     + IdentityHandlerFactory<id::BufferId>
+    + IdentityHandlerFactory<id::StagingBufferId>
     + IdentityHandlerFactory<id::TextureId>
     + IdentityHandlerFactory<id::TextureViewId>
     + IdentityHandlerFactory<id::SamplerId>
@@ -548,8 +554,10 @@ pub struct HubReport {
     pub render_bundles: StorageReport,
     pub render_pipelines: StorageReport,
     pub compute_pipelines: StorageReport,
+    pub pipeline_caches: StorageReport,
     pub query_sets: StorageReport,
     pub buffers: StorageReport,
+    pub staging_buffers: StorageReport,
     pub textures: StorageReport,
     pub texture_views: StorageReport,
     pub samplers: StorageReport,
@@ -572,8 +580,10 @@ pub struct Hub<A: hal::Api, F: GlobalIdentityHandlerFactory> {
     pub render_bundles: Registry<RenderBundle, id::RenderBundleId, F>,
     pub render_pipelines: Registry<RenderPipeline<A>, id::RenderPipelineId, F>,
     pub compute_pipelines: Registry<ComputePipeline<A>, id::ComputePipelineId, F>,
+    pub pipeline_caches: Registry<PipelineCache<A>, id::PipelineCacheId, F>,
     pub query_sets: Registry<QuerySet<A>, id::QuerySetId, F>,
     pub buffers: Registry<Buffer<A>, id::BufferId, F>,
+    pub staging_buffers: Registry<StagingBuffer<A>, id::StagingBufferId, F>,
     pub textures: Registry<Texture<A>, id::TextureId, F>,
     pub texture_views: Registry<TextureView<A>, id::TextureViewId, F>,
     pub samplers: Registry<Sampler<A>, id::SamplerId, F>,
@@ -592,8 +602,10 @@ impl<A: HalApi, F: GlobalIdentityHandlerFactory> Hub<A, F> {
             render_bundles: Registry::new(A::VARIANT, factory),
             render_pipelines: Registry::new(A::VARIANT, factory),
             compute_pipelines: Registry::new(A::VARIANT, factory),
+            pipeline_caches: Registry::new(A::VARIANT, factory),
             query_sets: Registry::new(A::VARIANT, factory),
             buffers: Registry::new(A::VARIANT, factory),
+            staging_buffers: Registry::new(A::VARIANT, factory),
             textures: Registry::new(A::VARIANT, factory),
             texture_views: Registry::new(A::VARIANT, factory),
             samplers: Registry::new(A::VARIANT, factory),
@@ -664,6 +676,15 @@ impl<A: HalApi, F: GlobalIdentityHandlerFactory> Hub<A, F> {
                 devices[buffer.device_id.value].destroy_buffer(buffer);
             }
         }
+        for element in self.staging_buffers.data.write().map.drain(..) {
+            if let Element::Occupied(staging_buffer, _) = element {
+                let device = &devices[staging_buffer.device_id.value];
+                unsafe {
+                    let _ = device.raw.unmap_buffer(&staging_buffer.raw);
+                    device.raw.destroy_buffer(staging_buffer.raw);
+                }
+            }
+        }
         for element in self.bind_groups.data.write().map.drain(..) {
             if let Element::Occupied(bind_group, _) = element {
                 let device = &devices[bind_group.device_id.value];
@@ -713,6 +734,14 @@ impl<A: HalApi, F: GlobalIdentityHandlerFactory> Hub<A, F> {
                 }
             }
         }
+        for element in self.pipeline_caches.data.write().map.drain(..) {
+            if let Element::Occupied(cache, _) = element {
+                let device = &devices[cache.device_id.value];
+                unsafe {
+                    device.raw.destroy_pipeline_cache(cache.raw);
+                }
+            }
+        }
 
         for element in surface_guard.map.iter_mut() {
             if let Element::Occupied(ref mut surface, _epoch) = *element {
@@ -768,8 +797,10 @@ impl<A: HalApi, F: GlobalIdentityHandlerFactory> Hub<A, F> {
             render_bundles: self.render_bundles.data.read().generate_report(),
             render_pipelines: self.render_pipelines.data.read().generate_report(),
             compute_pipelines: self.compute_pipelines.data.read().generate_report(),
+            pipeline_caches: self.pipeline_caches.data.read().generate_report(),
             query_sets: self.query_sets.data.read().generate_report(),
             buffers: self.buffers.data.read().generate_report(),
+            staging_buffers: self.staging_buffers.data.read().generate_report(),
             textures: self.textures.data.read().generate_report(),
             texture_views: self.texture_views.data.read().generate_report(),
             samplers: self.samplers.data.read().generate_report(),
@@ -788,6 +819,8 @@ pub struct Hubs<F: GlobalIdentityHandlerFactory> {
     dx11: Hub<hal::api::Dx11, F>,
     #[cfg(gl)]
     gl: Hub<hal::api::Gles, F>,
+    #[cfg(cpu)]
+    cpu: Hub<hal::api::Cpu, F>,
 }
 
 impl<F: GlobalIdentityHandlerFactory> Hubs<F> {
@@ -803,6 +836,8 @@ impl<F: GlobalIdentityHandlerFactory> Hubs<F> {
             dx11: Hub::new(factory),
             #[cfg(gl)]
             gl: Hub::new(factory),
+            #[cfg(cpu)]
+            cpu: Hub::new(factory),
         }
     }
 }
@@ -820,6 +855,8 @@ pub struct GlobalReport {
     pub dx11: Option<HubReport>,
     #[cfg(gl)]
     pub gl: Option<HubReport>,
+    #[cfg(cpu)]
+    pub cpu: Option<HubReport>,
 }
 
 pub struct Global<G: GlobalIdentityHandlerFactory> {
@@ -894,6 +931,12 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             } else {
                 None
             },
+            #[cfg(cpu)]
+            cpu: if self.instance.cpu.is_some() {
+                Some(self.hubs.cpu.generate_report())
+            } else {
+                None
+            },
         }
     }
 }
@@ -925,6 +968,10 @@ impl<G: GlobalIdentityHandlerFactory> Drop for Global<G> {
         {
             self.hubs.gl.clear(&mut *surface_guard, true);
         }
+        #[cfg(cpu)]
+        {
+            self.hubs.cpu.clear(&mut *surface_guard, true);
+        }
 
         // destroy surfaces
         for element in surface_guard.map.drain(..) {
@@ -1044,6 +1091,27 @@ impl HalApi for hal::api::Gles {
     }
 }
 
+#[cfg(cpu)]
+impl HalApi for hal::api::Cpu {
+    const VARIANT: Backend = Backend::Cpu;
+    fn create_instance_from_hal(name: &str, hal_instance: Self::Instance) -> Instance {
+        Instance {
+            name: name.to_owned(),
+            cpu: Some(hal_instance),
+            ..Default::default()
+        }
+    }
+    fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
+        &global.hubs.cpu
+    }
+    fn get_surface(surface: &Surface) -> &HalSurface<Self> {
+        surface.cpu.as_ref().unwrap()
+    }
+    fn get_surface_mut(surface: &mut Surface) -> &mut HalSurface<Self> {
+        surface.cpu.as_mut().unwrap()
+    }
+}
+
 #[cfg(test)]
 fn _test_send_sync(global: &Global<IdentityManagerFactory>) {
     fn test_internal<T: Send + Sync>(_: T) {}
@@ -11,7 +11,7 @@ type Dummy = hal::api::Empty;
 #[cfg_attr(
     feature = "replay",
     derive(serde::Deserialize),
-    serde(from = "SerialId")
+    serde(try_from = "SerialId")
 )]
 #[cfg_attr(
     all(feature = "serde", not(feature = "trace")),
@@ -39,10 +39,19 @@ impl<T> From<Id<T>> for SerialId {
     }
 }
 #[cfg(feature = "replay")]
-impl<T> From<SerialId> for Id<T> {
-    fn from(id: SerialId) -> Self {
+impl<T> std::convert::TryFrom<SerialId> for Id<T> {
+    type Error = String;
+    fn try_from(id: SerialId) -> Result<Self, Self::Error> {
         match id {
-            SerialId::Id(index, epoch, backend) => TypedId::zip(index, epoch, backend),
+            SerialId::Id(index, epoch, backend) => {
+                if epoch > EPOCH_MASK {
+                    Err(format!("epoch {} does not fit in an ID", epoch))
+                } else if (index, epoch, backend) == (0, 0, Backend::Empty) {
+                    Err("ID is zero".to_string())
+                } else {
+                    Ok(TypedId::zip(index, epoch, backend))
+                }
+            }
         }
     }
 }
@@ -61,6 +70,8 @@ impl<T> Id<T> {
             3 => Backend::Dx12,
             4 => Backend::Dx11,
             5 => Backend::Gl,
+            6 => Backend::BrowserWebGpu,
+            7 => Backend::Cpu,
             _ => unreachable!(),
         }
     }
@@ -142,6 +153,7 @@ pub type DeviceId = Id<crate::device::Device<Dummy>>;
 pub type QueueId = DeviceId;
 // Resource
 pub type BufferId = Id<crate::resource::Buffer<Dummy>>;
+pub type StagingBufferId = Id<crate::resource::StagingBuffer<Dummy>>;
 pub type TextureViewId = Id<crate::resource::TextureView<Dummy>>;
 pub type TextureId = Id<crate::resource::Texture<Dummy>>;
 pub type SamplerId = Id<crate::resource::Sampler<Dummy>>;
@@ -153,6 +165,7 @@ pub type BindGroupId = Id<crate::binding_model::BindGroup<Dummy>>;
 pub type ShaderModuleId = Id<crate::pipeline::ShaderModule<Dummy>>;
 pub type RenderPipelineId = Id<crate::pipeline::RenderPipeline<Dummy>>;
 pub type ComputePipelineId = Id<crate::pipeline::ComputePipeline<Dummy>>;
+pub type PipelineCacheId = Id<crate::pipeline::PipelineCache<Dummy>>;
 // Command
 pub type CommandEncoderId = CommandBufferId;
 pub type CommandBufferId = Id<crate::command::CommandBuffer<Dummy>>;
@@ -171,6 +184,7 @@ fn test_id_backend() {
         Backend::Dx12,
         Backend::Dx11,
         Backend::Gl,
+        Backend::Cpu,
     ] {
         let id: Id<()> = Id::zip(1, 0, b);
         assert_eq!(id.backend(), b);
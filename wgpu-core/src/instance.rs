@@ -97,6 +97,8 @@ pub struct Instance {
     pub dx11: Option<HalInstance<hal::api::Dx11>>,
     #[cfg(gl)]
     pub gl: Option<HalInstance<hal::api::Gles>>,
+    #[cfg(cpu)]
+    pub cpu: Option<HalInstance<hal::api::Cpu>>,
 }
 
 impl Instance {
@@ -130,6 +132,8 @@ impl Instance {
             dx11: init(hal::api::Dx11, backends),
             #[cfg(gl)]
             gl: init(hal::api::Gles, backends),
+            #[cfg(cpu)]
+            cpu: init(hal::api::Cpu, backends),
         }
     }
 
@@ -155,6 +159,8 @@ impl Instance {
         destroy(hal::api::Dx11, &self.dx11, surface.dx11);
         #[cfg(gl)]
         destroy(hal::api::Gles, &self.gl, surface.gl);
+        #[cfg(cpu)]
+        destroy(hal::api::Cpu, &self.cpu, surface.cpu);
     }
 }
 
@@ -170,6 +176,8 @@ pub struct Surface {
     pub dx11: Option<HalSurface<hal::api::Dx11>>,
     #[cfg(gl)]
     pub gl: Option<HalSurface<hal::api::Gles>>,
+    #[cfg(cpu)]
+    pub cpu: Option<HalSurface<hal::api::Cpu>>,
 }
 
 impl crate::hub::Resource for Surface {
@@ -489,6 +497,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             dx11: init(hal::api::Dx11, &self.instance.dx11, handle),
             #[cfg(gl)]
             gl: init(hal::api::Gles, &self.instance.gl, handle),
+            #[cfg(cpu)]
+            cpu: init(hal::api::Cpu, &self.instance.cpu, handle),
         };
 
         let mut token = Token::root();
@@ -516,6 +526,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             }),
             #[cfg(gl)]
             gl: None,
+            #[cfg(cpu)]
+            cpu: None,
         };
 
         let mut token = Token::root();
@@ -587,6 +599,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         self.enumerate(hal::api::Dx11, &self.instance.dx11, &inputs, &mut adapters);
         #[cfg(gl)]
         self.enumerate(hal::api::Gles, &self.instance.gl, &inputs, &mut adapters);
+        #[cfg(cpu)]
+        self.enumerate(hal::api::Cpu, &self.instance.cpu, &inputs, &mut adapters);
 
         adapters
     }
@@ -707,6 +721,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             desc.force_fallback_adapter,
             &mut device_types,
         );
+        #[cfg(cpu)]
+        let (id_cpu, adapters_cpu) = gather(
+            hal::api::Cpu,
+            self.instance.cpu.as_ref(),
+            &inputs,
+            compatible_surface,
+            desc.force_fallback_adapter,
+            &mut device_types,
+        );
 
         // need to free the token to be used by `select`
         drop(surface_guard);
@@ -764,6 +787,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         if let Some(id) = self.select(&mut selected, id_gl, adapters_gl) {
             return Ok(id);
         }
+        #[cfg(cpu)]
+        if let Some(id) = self.select(&mut selected, id_cpu, adapters_cpu) {
+            return Ok(id);
+        }
         let _ = selected;
 
         log::warn!("Some adapters are present, but enumerating them failed!");
@@ -794,6 +821,8 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             Backend::Dx11 => fid.assign(Adapter::new(hal_adapter), &mut token).0,
             #[cfg(gl)]
             Backend::Gl => fid.assign(Adapter::new(hal_adapter), &mut token).0,
+            #[cfg(cpu)]
+            Backend::Cpu => fid.assign(Adapter::new(hal_adapter), &mut token).0,
             _ => unreachable!(),
         }
     }
@@ -965,6 +994,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 /// - metal  = "metal" or "mtl"
 /// - gles   = "opengl" or "gles" or "gl"
 /// - webgpu = "webgpu"
+/// - cpu    = "cpu"
 pub fn parse_backends_from_comma_list(string: &str) -> Backends {
     let mut backends = Backends::empty();
     for backend in string.to_lowercase().split(',') {
@@ -975,6 +1005,7 @@ pub fn parse_backends_from_comma_list(string: &str) -> Backends {
             "metal" | "mtl" => Backends::METAL,
             "opengl" | "gles" | "gl" => Backends::GL,
             "webgpu" => Backends::BROWSER_WEBGPU,
+            "cpu" => Backends::CPU,
             b => {
                 log::warn!("unknown backend string '{}'", b);
                 continue;
@@ -216,6 +216,8 @@ macro_rules! gfx_select {
                 target_arch = "wasm32"
             ))]
             wgt::Backend::Gl => $global.$method::<$crate::api::Gles>( $($param),+ ),
+            #[cfg(all(not(target_arch = "wasm32"), feature = "cpu"))]
+            wgt::Backend::Cpu => $global.$method::<$crate::api::Cpu>( $($param),* ),
             other => panic!("Unexpected backend {:?}", other),
 
         }
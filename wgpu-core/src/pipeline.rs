@@ -2,7 +2,7 @@ use crate::{
     binding_model::{CreateBindGroupLayoutError, CreatePipelineLayoutError},
     device::{DeviceError, MissingDownlevelFlags, MissingFeatures, RenderPassContext},
     hub::Resource,
-    id::{DeviceId, PipelineLayoutId, ShaderModuleId},
+    id::{DeviceId, PipelineCacheId, PipelineLayoutId, ShaderModuleId},
     validation, Label, LifeGuard, Stored,
 };
 use arrayvec::ArrayVec;
@@ -126,6 +126,121 @@ pub enum CreateShaderModuleError {
     MissingFeatures(#[from] MissingFeatures),
 }
 
+impl CreateShaderModuleError {
+    /// Describe the error as compilation messages, located in the shader source when possible.
+    pub fn compilation_info(&self) -> wgt::CompilationInfo {
+        use wgt::{CompilationMessage as Message, CompilationMessageType as Type};
+
+        let messages = match *self {
+            Self::Parsing(ref error) => {
+                // The parser only exposes the line and column of the error.
+                let (line, column) = error.inner.location(&error.source);
+                let line_start = error
+                    .source
+                    .split_inclusive('\n')
+                    .take(line - 1)
+                    .map(str::len)
+                    .sum::<usize>();
+                let offset = error.source[line_start..]
+                    .char_indices()
+                    .nth(column - 1)
+                    .map_or(error.source.len(), |(i, _)| line_start + i);
+                vec![Message::with_range(
+                    error.inner.to_string(),
+                    Type::Error,
+                    &error.source,
+                    offset..offset,
+                )]
+            }
+            Self::Validation(ref error) => {
+                let mut message = error.inner.to_string();
+                let mut source = error.inner.source();
+                while let Some(cause) = source {
+                    message = format!("{}: {}", message, cause);
+                    source = cause.source();
+                }
+                // Modules given as `naga::Module` have no source to point at.
+                let ranges = error
+                    .inner
+                    .spans()
+                    .filter(|_| !error.source.is_empty())
+                    .filter_map(|&(span, ref description)| Some((span.to_range()?, description)))
+                    .collect::<Vec<_>>();
+                // Spans go from the outermost part of the module to the innermost one,
+                // so the error is located at the last one.
+                let mut messages = vec![match ranges.last().map(|last| last.0.clone()) {
+                    Some(range) => Message::with_range(message, Type::Error, &error.source, range),
+                    None => Message::new(message, Type::Error),
+                }];
+                messages.extend(ranges.into_iter().map(|(range, description)| {
+                    Message::with_range(description.clone(), Type::Info, &error.source, range)
+                }));
+                messages
+            }
+            _ => vec![Message::new(self.to_string(), Type::Error)],
+        };
+        wgt::CompilationInfo { messages }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "trace", derive(serde::Serialize))]
+#[cfg_attr(feature = "replay", derive(serde::Deserialize))]
+pub struct PipelineCacheDescriptor<'a> {
+    pub label: Label<'a>,
+    /// Data previously returned by `pipeline_cache_get_data`, if any.
+    ///
+    /// It isn't recorded in traces, so a replayed cache always starts empty.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(skip))]
+    pub data: Option<Cow<'a, [u8]>>,
+    /// Start with an empty cache if the data is incompatible, instead of failing.
+    pub fallback: bool,
+}
+
+/// Storage of translated shaders and of the native pipeline cache of the backend.
+///
+/// Like shader modules, caches are only used while creating pipelines,
+/// so they aren't tracked and are destroyed as soon as they are dropped.
+#[derive(Debug)]
+pub struct PipelineCache<A: hal::Api> {
+    pub(crate) raw: A::PipelineCache,
+    pub(crate) device_id: Stored<DeviceId>,
+    #[cfg(debug_assertions)]
+    pub(crate) label: String,
+}
+
+impl<A: hal::Api> Resource for PipelineCache<A> {
+    const TYPE: &'static str = "PipelineCache";
+
+    fn life_guard(&self) -> &LifeGuard {
+        unreachable!()
+    }
+
+    fn label(&self) -> &str {
+        #[cfg(debug_assertions)]
+        return &self.label;
+        #[cfg(not(debug_assertions))]
+        return "";
+    }
+}
+
+#[derive(Clone, Debug, Error)]
+pub enum CreatePipelineCacheError {
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+    #[error("pipeline cache data is corrupted, or was produced by another backend or version")]
+    Validation,
+}
+
+impl From<hal::PipelineCacheError> for CreatePipelineCacheError {
+    fn from(error: hal::PipelineCacheError) -> Self {
+        match error {
+            hal::PipelineCacheError::Validation => Self::Validation,
+            hal::PipelineCacheError::Device(error) => Self::Device(error.into()),
+        }
+    }
+}
+
 /// Describes a programmable pipeline stage.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
@@ -163,6 +278,9 @@ pub struct ComputePipelineDescriptor<'a> {
     pub layout: Option<PipelineLayoutId>,
     /// The compiled compute stage and its entry point.
     pub stage: ProgrammableStageDescriptor<'a>,
+    /// The cache to look up and store the compiled shaders in, if any.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub cache: Option<PipelineCacheId>,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -171,6 +289,8 @@ pub enum CreateComputePipelineError {
     Device(#[from] DeviceError),
     #[error("pipeline layout is invalid")]
     InvalidLayout,
+    #[error("pipeline cache is invalid")]
+    InvalidCache,
     #[error("unable to derive an implicit layout")]
     Implicit(#[from] ImplicitLayoutError),
     #[error("error matching shader requirements against the pipeline")]
@@ -258,6 +378,9 @@ pub struct RenderPipelineDescriptor<'a> {
     /// If the pipeline will be used with a multiview render pass, this indicates how many array
     /// layers the attachments will have.
     pub multiview: Option<NonZeroU32>,
+    /// The cache to look up and store the compiled shaders in, if any.
+    #[cfg_attr(any(feature = "replay", feature = "trace"), serde(default))]
+    pub cache: Option<PipelineCacheId>,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -299,6 +422,8 @@ pub enum CreateRenderPipelineError {
     Device(#[from] DeviceError),
     #[error("pipeline layout is invalid")]
     InvalidLayout,
+    #[error("pipeline cache is invalid")]
+    InvalidCache,
     #[error("unable to derive an implicit layout")]
     Implicit(#[from] ImplicitLayoutError),
     #[error("color state [{0}] is invalid")]
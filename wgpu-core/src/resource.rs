@@ -1,7 +1,7 @@
 use crate::{
     device::{DeviceError, HostMap, MissingFeatures},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Resource, Token},
-    id::{DeviceId, SurfaceId, TextureId, Valid},
+    id::{BufferId, DeviceId, SurfaceId, TextureId, TextureViewId, Valid},
     init_tracker::{BufferInitTracker, TextureInitTracker},
     track::{TextureSelector, DUMMY_SELECTOR},
     validation::MissingBufferUsageError,
@@ -139,6 +139,12 @@ pub enum CreateBufferError {
     EmptyUsage,
     #[error("`MAP` usage can only be combined with the opposite `COPY`, requested {0:?}")]
     UsageMismatch(wgt::BufferUsages),
+    #[error("buffers created from hal can't be mapped at creation")]
+    MappedFromHal,
+    #[error(
+        "buffers created from hal can't have `MAP_READ` or `MAP_WRITE` usage, requested {0:?}"
+    )]
+    MapUsageFromHal(wgt::BufferUsages),
 }
 
 impl<A: hal::Api> Resource for Buffer<A> {
@@ -149,6 +155,36 @@ impl<A: hal::Api> Resource for Buffer<A> {
     }
 }
 
+/// A buffer that the user writes to directly, before it is copied into another buffer.
+///
+/// It is mapped from the start, zeroed, and consumed by the copy, so it's never tracked.
+#[derive(Debug)]
+pub struct StagingBuffer<A: hal::Api> {
+    pub(crate) raw: A::Buffer,
+    pub(crate) device_id: Stored<DeviceId>,
+    pub(crate) size: wgt::BufferAddress,
+    pub(crate) life_guard: LifeGuard,
+    /// Mapped memory, only read back for the trace.
+    #[cfg(feature = "trace")]
+    pub(crate) ptr: NonNull<u8>,
+    pub(crate) is_coherent: bool,
+}
+
+unsafe impl<A: hal::Api> Send for StagingBuffer<A> {}
+unsafe impl<A: hal::Api> Sync for StagingBuffer<A> {}
+
+impl<A: hal::Api> Resource for StagingBuffer<A> {
+    const TYPE: &'static str = "StagingBuffer";
+
+    fn life_guard(&self) -> &LifeGuard {
+        &self.life_guard
+    }
+
+    fn label(&self) -> &str {
+        "<StagingBuffer>"
+    }
+}
+
 impl<A: hal::Api> Borrow<()> for Buffer<A> {
     fn borrow(&self) -> &() {
         &DUMMY_SELECTOR
@@ -231,6 +267,25 @@ impl<A: hal::Api> Texture<A> {
 }
 
 impl<G: GlobalIdentityHandlerFactory> Global<G> {
+    /// # Safety
+    ///
+    /// - The raw buffer handle must not be manually destroyed
+    pub unsafe fn buffer_as_hal<A: HalApi, F: FnOnce(Option<&A::Buffer>)>(
+        &self,
+        id: BufferId,
+        hal_buffer_callback: F,
+    ) {
+        profiling::scope!("as_hal", "Buffer");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.buffers.read(&mut token);
+        let buffer = guard.get(id).ok();
+        let hal_buffer = buffer.and_then(|buffer| buffer.raw.as_ref());
+
+        hal_buffer_callback(hal_buffer);
+    }
+
     /// # Safety
     ///
     /// - The raw texture handle must not be manually destroyed
@@ -250,6 +305,25 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         hal_texture_callback(hal_texture);
     }
 
+    /// # Safety
+    ///
+    /// - The raw texture view handle must not be manually destroyed
+    pub unsafe fn texture_view_as_hal<A: HalApi, F: FnOnce(Option<&A::TextureView>)>(
+        &self,
+        id: TextureViewId,
+        hal_texture_view_callback: F,
+    ) {
+        profiling::scope!("as_hal", "TextureView");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.texture_views.read(&mut token);
+        let texture_view = guard.get(id).ok();
+        let hal_texture_view = texture_view.map(|view| &view.raw);
+
+        hal_texture_view_callback(hal_texture_view);
+    }
+
     /// # Safety
     ///
     /// - The raw device handle must not be manually destroyed
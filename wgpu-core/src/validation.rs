@@ -766,6 +766,46 @@ impl NumericType {
         }
     }
 
+    fn to_vertex_format(self) -> Option<wgt::VertexFormat> {
+        use naga::{ScalarKind as Sk, VectorSize as Vs};
+        use wgt::VertexFormat as Vf;
+
+        Some(match (self.dim, self.kind, self.width) {
+            (NumericDimension::Scalar, Sk::Uint, 4) => Vf::Uint32,
+            (NumericDimension::Vector(Vs::Bi), Sk::Uint, 4) => Vf::Uint32x2,
+            (NumericDimension::Vector(Vs::Tri), Sk::Uint, 4) => Vf::Uint32x3,
+            (NumericDimension::Vector(Vs::Quad), Sk::Uint, 4) => Vf::Uint32x4,
+            (NumericDimension::Scalar, Sk::Sint, 4) => Vf::Sint32,
+            (NumericDimension::Vector(Vs::Bi), Sk::Sint, 4) => Vf::Sint32x2,
+            (NumericDimension::Vector(Vs::Tri), Sk::Sint, 4) => Vf::Sint32x3,
+            (NumericDimension::Vector(Vs::Quad), Sk::Sint, 4) => Vf::Sint32x4,
+            (NumericDimension::Scalar, Sk::Float, 4) => Vf::Float32,
+            (NumericDimension::Vector(Vs::Bi), Sk::Float, 4) => Vf::Float32x2,
+            (NumericDimension::Vector(Vs::Tri), Sk::Float, 4) => Vf::Float32x3,
+            (NumericDimension::Vector(Vs::Quad), Sk::Float, 4) => Vf::Float32x4,
+            (NumericDimension::Scalar, Sk::Float, 8) => Vf::Float64,
+            (NumericDimension::Vector(Vs::Bi), Sk::Float, 8) => Vf::Float64x2,
+            (NumericDimension::Vector(Vs::Tri), Sk::Float, 8) => Vf::Float64x3,
+            (NumericDimension::Vector(Vs::Quad), Sk::Float, 8) => Vf::Float64x4,
+            _ => return None,
+        })
+    }
+
+    fn to_scalar_kind_and_components(self) -> Option<(wgt::ShaderScalarKind, u8)> {
+        let kind = match self.kind {
+            naga::ScalarKind::Float => wgt::ShaderScalarKind::Float,
+            naga::ScalarKind::Sint => wgt::ShaderScalarKind::Sint,
+            naga::ScalarKind::Uint => wgt::ShaderScalarKind::Uint,
+            naga::ScalarKind::Bool => return None,
+        };
+        let components = match self.dim {
+            NumericDimension::Scalar => 1,
+            NumericDimension::Vector(size) => size as u8,
+            NumericDimension::Matrix(..) => return None,
+        };
+        Some((kind, components))
+    }
+
     fn is_subtype_of(&self, other: &NumericType) -> bool {
         if self.width > other.width {
             return false;
@@ -967,6 +1007,89 @@ impl Interface {
         }
     }
 
+    /// Describe the entry points of the module.
+    pub fn reflect(&self) -> wgt::ShaderReflection {
+        // Validation only lets scalars and vectors of 32-bit numbers through locations,
+        // except for 64-bit floats in vertex inputs, so they all have a reflected type.
+        let locations = |varyings: &[Varying]| {
+            let mut list = varyings
+                .iter()
+                .filter_map(|varying| match *varying {
+                    Varying::Local { location, ref iv } => Some((location, iv.ty)),
+                    Varying::BuiltIn(_) => None,
+                })
+                .collect::<Vec<_>>();
+            list.sort_by_key(|&(location, _)| location);
+            list
+        };
+
+        let mut entry_points = self
+            .entry_points
+            .iter()
+            .map(|(&(shader_stage, ref name), entry_point)| {
+                let stage = match shader_stage {
+                    naga::ShaderStage::Vertex => wgt::ShaderStages::VERTEX,
+                    naga::ShaderStage::Fragment => wgt::ShaderStages::FRAGMENT,
+                    naga::ShaderStage::Compute => wgt::ShaderStages::COMPUTE,
+                };
+                let mut bindings = entry_point
+                    .resources
+                    .iter()
+                    .map(|&(handle, usage)| {
+                        let res = &self.resources[handle];
+                        // Report the access the shader needs, regardless of what the device supports.
+                        let features = wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+                        wgt::BindingReflection {
+                            group: res.bind.group,
+                            binding: res.bind.binding,
+                            ty: res.derive_binding_type(usage, features).ok(),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                bindings.sort_by_key(|b| (b.group, b.binding));
+
+                wgt::EntryPointReflection {
+                    name: name.clone(),
+                    stage,
+                    bindings,
+                    vertex_inputs: match shader_stage {
+                        naga::ShaderStage::Vertex => locations(&entry_point.inputs)
+                            .into_iter()
+                            .filter_map(|(location, ty)| {
+                                Some(wgt::VertexInputReflection {
+                                    location,
+                                    format: ty.to_vertex_format()?,
+                                })
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    },
+                    fragment_outputs: match shader_stage {
+                        naga::ShaderStage::Fragment => locations(&entry_point.outputs)
+                            .into_iter()
+                            .filter_map(|(location, ty)| {
+                                let (kind, components) = ty.to_scalar_kind_and_components()?;
+                                Some(wgt::FragmentOutputReflection {
+                                    location,
+                                    kind,
+                                    components,
+                                })
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    },
+                    workgroup_size: match shader_stage {
+                        naga::ShaderStage::Compute => entry_point.workgroup_size,
+                        _ => [0; 3],
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        entry_points.sort_by(|a, b| (&a.name, a.stage.bits()).cmp(&(&b.name, b.stage.bits())));
+
+        wgt::ShaderReflection { entry_points }
+    }
+
     pub fn check_stage(
         &self,
         given_layouts: Option<&[&BindEntryMap]>,
@@ -0,0 +1,23 @@
+fn main() {
+    // The pipeline cache is tagged with the naga revision, taken from the manifest:
+    // the `rev` of the git dependency, or its `version` once published.
+    let manifest = std::fs::read_to_string("Cargo.toml").unwrap();
+    let revision = manifest
+        .split("[dependencies.naga]")
+        .nth(1)
+        .and_then(|section| {
+            section
+                .lines()
+                .take_while(|line| !line.starts_with('['))
+                .find_map(|line| {
+                    let (key, value) = line.split_once('=')?;
+                    match key.trim() {
+                        "rev" | "version" => Some(value.trim().trim_matches('"').to_string()),
+                        _ => None,
+                    }
+                })
+        })
+        .unwrap_or_default();
+    println!("cargo:rustc-env=WGPU_HAL_NAGA_REVISION={}", revision);
+    println!("cargo:rerun-if-changed=Cargo.toml");
+}
@@ -143,7 +143,11 @@ impl<A: hal::Api> Example<A> {
             )
             .validate(&module)
             .unwrap();
-            hal::NagaShader { module, info }
+            hal::NagaShader {
+                module,
+                info,
+                source_hash: None,
+            }
         };
         let shader_desc = hal::ShaderModuleDescriptor {
             label: None,
@@ -244,6 +248,7 @@ impl<A: hal::Api> Example<A> {
                 write_mask: wgt::ColorWrites::default(),
             }],
             multiview: None,
+            cache: None,
         };
         let pipeline = unsafe { device.create_render_pipeline(&pipeline_desc).unwrap() };
 
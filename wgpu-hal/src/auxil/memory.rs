@@ -0,0 +1,77 @@
+//! Memory accounting for the backends that allocate every resource on its own.
+
+use parking_lot::Mutex;
+
+/// Memory used by the buffers and textures of a device, each of them being its own block
+/// of a single heap.
+#[derive(Debug, Default)]
+pub struct ResourceMemory {
+    heap: Mutex<wgt::MemoryHeapReport>,
+}
+
+impl ResourceMemory {
+    pub fn new(size: u64, device_local: bool) -> Self {
+        Self {
+            heap: Mutex::new(wgt::MemoryHeapReport {
+                size,
+                device_local,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn add_buffer(&self, size: wgt::BufferAddress) {
+        let mut heap = self.heap.lock();
+        heap.buffer_bytes += size;
+        heap.block_count += 1;
+        heap.block_bytes += size;
+    }
+
+    pub fn remove_buffer(&self, size: wgt::BufferAddress) {
+        let mut heap = self.heap.lock();
+        heap.buffer_bytes -= size;
+        heap.block_count -= 1;
+        heap.block_bytes -= size;
+    }
+
+    pub fn add_texture(&self, size: wgt::BufferAddress) {
+        let mut heap = self.heap.lock();
+        heap.texture_bytes += size;
+        heap.block_count += 1;
+        heap.block_bytes += size;
+    }
+
+    pub fn remove_texture(&self, size: wgt::BufferAddress) {
+        let mut heap = self.heap.lock();
+        heap.texture_bytes -= size;
+        heap.block_count -= 1;
+        heap.block_bytes -= size;
+    }
+
+    pub fn heap(&self) -> wgt::MemoryHeapReport {
+        *self.heap.lock()
+    }
+}
+
+/// Estimate the memory used by a texture, assuming its texel blocks are tightly packed.
+pub fn texture_memory_size(
+    format: wgt::TextureFormat,
+    dimension: wgt::TextureDimension,
+    size: wgt::Extent3d,
+    mip_level_count: u32,
+    sample_count: u32,
+) -> wgt::BufferAddress {
+    let format_info = format.describe();
+    let (block_width, block_height) = format_info.block_dimensions;
+    let is_3d = dimension == wgt::TextureDimension::D3;
+    let mip_bytes = (0..mip_level_count)
+        .map(|level| {
+            let extent = size.mip_level_size(level, is_3d).physical_size(format);
+            (extent.width / block_width as u32) as u64
+                * (extent.height / block_height as u32) as u64
+                * extent.depth_or_array_layers as u64
+                * format_info.block_size as u64
+        })
+        .sum::<u64>();
+    mip_bytes * sample_count as u64
+}
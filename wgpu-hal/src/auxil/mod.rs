@@ -1,6 +1,9 @@
 #[cfg(feature = "renderdoc")]
 pub(super) mod renderdoc;
 
+pub mod memory;
+pub mod pipeline_cache;
+
 pub mod db {
     pub mod intel {
         pub const VENDOR: u32 = 0x8086;
@@ -0,0 +1,326 @@
+//! Storage of the translated shaders, shared by the backends.
+//!
+//! Serialized data starts with a header that identifies the versions of this crate
+//! and naga, the backend and the adapter, followed by the native cache blob of the
+//! backend, if any, and the translated shaders.
+
+use parking_lot::Mutex;
+use std::hash::{Hash, Hasher};
+
+const MAGIC: [u8; 8] = *b"WGPUPIPE";
+/// Revision of naga, which is a git dependency, so its output may change
+/// without a version bump of this crate.
+const NAGA_REVISION: &str = env!("WGPU_HAL_NAGA_REVISION");
+
+/// What the translated shaders depend on, besides the modules and the options.
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub backend: wgt::Backend,
+    pub vendor: u32,
+    pub device: u32,
+    /// Version of the driver, in whatever form the backend reports it.
+    pub driver: String,
+}
+
+impl Header {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        env!("CARGO_PKG_VERSION").to_string().encode(out);
+        NAGA_REVISION.to_string().encode(out);
+        (self.backend as u8).encode(out);
+        self.vendor.encode(out);
+        self.device.encode(out);
+        self.driver.encode(out);
+    }
+}
+
+/// Translation options of a naga backend.
+///
+/// They are all keyed the same way, by hashing everything that affects the output.
+pub trait TranslationOptions {
+    fn hash_key(&self, state: &mut fxhash::FxHasher64);
+}
+
+#[cfg(feature = "vulkan")]
+impl TranslationOptions for naga::back::spv::Options {
+    fn hash_key(&self, state: &mut fxhash::FxHasher64) {
+        // The capabilities are in a hash set, which has no stable order.
+        let capabilities = self.capabilities.as_ref().map(|caps| {
+            let mut caps = caps.iter().map(|&cap| cap as u32).collect::<Vec<_>>();
+            caps.sort_unstable();
+            caps
+        });
+        (
+            self.lang_version,
+            self.flags.bits(),
+            capabilities,
+            self.bounds_check_policies,
+        )
+            .hash(state);
+    }
+}
+
+#[cfg(feature = "gles")]
+impl TranslationOptions for naga::back::glsl::Options {
+    fn hash_key(&self, state: &mut fxhash::FxHasher64) {
+        let version = match self.version {
+            naga::back::glsl::Version::Desktop(v) => (false, v),
+            naga::back::glsl::Version::Embedded(v) => (true, v),
+        };
+        (version, self.writer_flags.bits(), &self.binding_map).hash(state);
+    }
+}
+
+#[cfg(feature = "metal")]
+impl TranslationOptions for naga::back::msl::Options {
+    fn hash_key(&self, state: &mut fxhash::FxHasher64) {
+        self.hash(state);
+    }
+}
+
+#[cfg(feature = "dx12")]
+impl TranslationOptions for naga::back::hlsl::Options {
+    fn hash_key(&self, state: &mut fxhash::FxHasher64) {
+        self.hash(state);
+    }
+}
+
+#[cfg(test)]
+impl TranslationOptions for () {
+    fn hash_key(&self, _state: &mut fxhash::FxHasher64) {}
+}
+
+/// Reader of the serialized data.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        if self.data.len() < count {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(count);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Some(u64::from_le_bytes(bytes))
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        let len = self.u64()?;
+        // Guard the allocations against corrupted lengths.
+        if len > self.data.len() as u64 {
+            return None;
+        }
+        Some(len as usize)
+    }
+}
+
+fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Value that can be stored in the cache.
+pub trait CacheEntry: Clone + Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(reader: &mut Reader) -> Option<Self>;
+}
+
+impl CacheEntry for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some(reader.bytes(1)?[0])
+    }
+}
+
+impl CacheEntry for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(reader.bytes(4)?);
+        Some(u32::from_le_bytes(bytes))
+    }
+}
+
+impl CacheEntry for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_u64(out, self.len() as u64);
+        out.extend_from_slice(self.as_bytes());
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let len = reader.len()?;
+        String::from_utf8(reader.bytes(len)?.to_vec()).ok()
+    }
+}
+
+impl<T: CacheEntry> CacheEntry for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_u64(out, self.len() as u64);
+        for item in self.iter() {
+            item.encode(out);
+        }
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        let len = reader.len()?;
+        (0..len).map(|_| T::decode(reader)).collect()
+    }
+}
+
+impl<A: CacheEntry, B: CacheEntry> CacheEntry for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+impl<A: CacheEntry, B: CacheEntry, C: CacheEntry> CacheEntry for (A, B, C) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+        self.2.encode(out);
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some((A::decode(reader)?, B::decode(reader)?, C::decode(reader)?))
+    }
+}
+
+/// Translated shaders, keyed by the hash of the source module and the translation options.
+#[derive(Debug)]
+pub struct ShaderCache<T> {
+    header: Header,
+    entries: Mutex<fxhash::FxHashMap<u64, T>>,
+}
+
+impl<T: CacheEntry> ShaderCache<T> {
+    /// Create a cache from the serialized data, returning the native blob along with it.
+    ///
+    /// Data serialized with a different header is rejected.
+    pub fn new(
+        header: Header,
+        data: Option<&[u8]>,
+    ) -> Result<(Self, Vec<u8>), crate::PipelineCacheError> {
+        let mut entries = fxhash::FxHashMap::default();
+        let mut native = Vec::new();
+        if let Some(data) = data {
+            let mut expected = Vec::new();
+            header.encode(&mut expected);
+            let mut reader = Reader { data };
+            let valid = (|| {
+                if reader.bytes(expected.len())? != &expected[..] {
+                    return None;
+                }
+                let native_len = reader.len()?;
+                native = reader.bytes(native_len)?.to_vec();
+                let count = reader.len()?;
+                for _ in 0..count {
+                    let key = reader.u64()?;
+                    entries.insert(key, T::decode(&mut reader)?);
+                }
+                Some(())
+            })()
+            .is_some();
+            if !valid {
+                return Err(crate::PipelineCacheError::Validation);
+            }
+        }
+        Ok((
+            Self {
+                header,
+                entries: Mutex::new(entries),
+            },
+            native,
+        ))
+    }
+
+    /// Serialize the cache along with the native blob.
+    pub fn serialize(&self, native: &[u8]) -> Vec<u8> {
+        let entries = self.entries.lock();
+        let mut out = Vec::new();
+        self.header.encode(&mut out);
+        write_u64(&mut out, native.len() as u64);
+        out.extend_from_slice(native);
+        write_u64(&mut out, entries.len() as u64);
+        for (&key, entry) in entries.iter() {
+            write_u64(&mut out, key);
+            entry.encode(&mut out);
+        }
+        out
+    }
+
+    /// Get the translation of a shader, producing it if it's not cached yet.
+    ///
+    /// `stage` identifies the entry point and any per-pipeline options.
+    /// Without a source hash, the shader can't be identified, and `translate` is always called.
+    pub fn get_or_translate<E>(
+        cache: Option<&Self>,
+        source_hash: Option<u64>,
+        stage: impl Hash,
+        options: &impl TranslationOptions,
+        translate: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let (cache, source_hash) = match (cache, source_hash) {
+            (Some(cache), Some(hash)) => (cache, hash),
+            _ => return translate(),
+        };
+        // The keys are serialized, so the hasher has to give the same result on every run.
+        let mut hasher = fxhash::FxHasher64::default();
+        source_hash.hash(&mut hasher);
+        stage.hash(&mut hasher);
+        options.hash_key(&mut hasher);
+        let key = hasher.finish();
+
+        if let Some(entry) = cache.entries.lock().get(&key) {
+            return Ok(entry.clone());
+        }
+        let entry = translate()?;
+        cache.entries.lock().insert(key, entry.clone());
+        Ok(entry)
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let header = Header {
+        backend: wgt::Backend::Empty,
+        vendor: 1,
+        device: 2,
+        driver: "1.0".to_string(),
+    };
+    let (cache, _) = ShaderCache::<(String, Vec<u32>)>::new(header.clone(), None).unwrap();
+    let translate = || Ok::<_, ()>(("main".to_string(), vec![1, 2, 3]));
+    let entry = ShaderCache::get_or_translate(Some(&cache), Some(7), "vs", &(), translate).unwrap();
+    let data = cache.serialize(&[4, 5]);
+
+    let (loaded, native) =
+        ShaderCache::<(String, Vec<u32>)>::new(header.clone(), Some(&data)).unwrap();
+    assert_eq!(native, vec![4, 5]);
+    let cached =
+        ShaderCache::get_or_translate(Some(&loaded), Some(7), "vs", &(), || Err(())).unwrap();
+    assert_eq!(cached, entry);
+    // A different stage misses the cache.
+    assert!(ShaderCache::get_or_translate(Some(&loaded), Some(7), "fs", &(), || Err(())).is_err());
+
+    let other_backend = Header {
+        backend: wgt::Backend::Vulkan,
+        ..header.clone()
+    };
+    assert!(ShaderCache::<(String, Vec<u32>)>::new(other_backend, Some(&data)).is_err());
+    let other_driver = Header {
+        driver: "1.1".to_string(),
+        ..header.clone()
+    };
+    assert!(ShaderCache::<(String, Vec<u32>)>::new(other_driver, Some(&data)).is_err());
+    assert!(ShaderCache::<(String, Vec<u32>)>::new(header, Some(&data[..9])).is_err());
+}
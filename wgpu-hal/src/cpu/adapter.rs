@@ -0,0 +1,202 @@
+use super::format;
+
+/// Largest texture dimension we expose.
+///
+/// Textures are host allocations, so this is mostly about keeping
+/// the memory footprint of a single texture reasonable.
+const MAX_TEXTURE_DIMENSION: u32 = 8192;
+
+impl crate::Instance<super::Api> for super::Instance {
+    unsafe fn init(desc: &crate::InstanceDescriptor) -> Result<Self, crate::InstanceError> {
+        Ok(Self { _flags: desc.flags })
+    }
+
+    unsafe fn create_surface(
+        &self,
+        _rwh: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Result<super::Surface, crate::InstanceError> {
+        Ok(super::Surface {
+            config: None,
+            presented: None,
+        })
+    }
+
+    unsafe fn destroy_surface(&self, _surface: super::Surface) {}
+
+    unsafe fn enumerate_adapters(&self) -> Vec<crate::ExposedAdapter<super::Api>> {
+        let limits = wgt::Limits {
+            max_texture_dimension_1d: MAX_TEXTURE_DIMENSION,
+            max_texture_dimension_2d: MAX_TEXTURE_DIMENSION,
+            ..wgt::Limits::default()
+        };
+
+        let features = wgt::Features::DEPTH_CLIP_CONTROL
+            | wgt::Features::INDIRECT_FIRST_INSTANCE
+            | wgt::Features::TIMESTAMP_QUERY
+            | wgt::Features::MAPPABLE_PRIMARY_BUFFERS
+            | wgt::Features::MULTI_DRAW_INDIRECT
+            | wgt::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+            | wgt::Features::POLYGON_MODE_LINE
+            | wgt::Features::POLYGON_MODE_POINT
+            | wgt::Features::VERTEX_WRITABLE_STORAGE
+            | wgt::Features::CLEAR_TEXTURE
+            | wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            | wgt::Features::TEXTURE_FORMAT_16BIT_NORM;
+
+        let downlevel = wgt::DownlevelCapabilities {
+            flags: wgt::DownlevelFlags::all(),
+            limits: wgt::DownlevelLimits {},
+            shader_model: wgt::ShaderModel::Sm5,
+        };
+
+        vec![crate::ExposedAdapter {
+            adapter: super::Adapter { _private: () },
+            info: wgt::AdapterInfo {
+                name: "CPU reference".to_string(),
+                vendor: 0,
+                device: 0,
+                device_type: wgt::DeviceType::Cpu,
+                backend: wgt::Backend::Cpu,
+            },
+            features,
+            capabilities: crate::Capabilities {
+                limits,
+                alignments: crate::Alignments {
+                    buffer_copy_offset: wgt::BufferSize::new(4).unwrap(),
+                    buffer_copy_pitch: wgt::BufferSize::new(4).unwrap(),
+                },
+                downlevel,
+            },
+        }]
+    }
+}
+
+impl crate::Adapter<super::Api> for super::Adapter {
+    unsafe fn open(
+        &self,
+        _features: wgt::Features,
+        _limits: &wgt::Limits,
+    ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
+        Ok(crate::OpenDevice {
+            device: super::Device {
+                memory: crate::auxil::memory::ResourceMemory::new(0, true),
+            },
+            queue: super::Queue {
+                epoch: std::time::Instant::now(),
+            },
+        })
+    }
+
+    unsafe fn texture_format_capabilities(
+        &self,
+        format: wgt::TextureFormat,
+    ) -> crate::TextureFormatCapabilities {
+        use crate::TextureFormatCapabilities as Tfc;
+        use wgt::TextureFormat as Tf;
+
+        match format {
+            Tf::Depth32Float | Tf::Depth24Plus | Tf::Depth24PlusStencil8 => {
+                Tfc::SAMPLED
+                    | Tfc::DEPTH_STENCIL_ATTACHMENT
+                    | Tfc::MULTISAMPLE
+                    | Tfc::COPY_SRC
+                    | Tfc::COPY_DST
+            }
+            _ if format::is_supported(format) => {
+                let is_filterable = match format.describe().sample_type {
+                    wgt::TextureSampleType::Float { filterable } => filterable,
+                    _ => false,
+                };
+                let mut caps = Tfc::SAMPLED
+                    | Tfc::STORAGE
+                    | Tfc::STORAGE_READ_WRITE
+                    | Tfc::COLOR_ATTACHMENT
+                    | Tfc::MULTISAMPLE
+                    | Tfc::COPY_SRC
+                    | Tfc::COPY_DST;
+                if is_filterable {
+                    caps |= Tfc::SAMPLED_LINEAR
+                        | Tfc::COLOR_ATTACHMENT_BLEND
+                        | Tfc::MULTISAMPLE_RESOLVE;
+                }
+                caps
+            }
+            // Compressed formats are neither decoded nor exposed.
+            _ => Tfc::empty(),
+        }
+    }
+
+    unsafe fn surface_capabilities(
+        &self,
+        _surface: &super::Surface,
+    ) -> Option<crate::SurfaceCapabilities> {
+        Some(crate::SurfaceCapabilities {
+            formats: vec![
+                wgt::TextureFormat::Rgba8UnormSrgb,
+                wgt::TextureFormat::Bgra8UnormSrgb,
+                wgt::TextureFormat::Rgba8Unorm,
+                wgt::TextureFormat::Bgra8Unorm,
+            ],
+            swap_chain_sizes: 1..=3,
+            current_extent: None,
+            extents: wgt::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            }..=wgt::Extent3d {
+                width: MAX_TEXTURE_DIMENSION,
+                height: MAX_TEXTURE_DIMENSION,
+                depth_or_array_layers: 1,
+            },
+            usage: crate::TextureUses::COLOR_TARGET
+                | crate::TextureUses::COPY_SRC
+                | crate::TextureUses::COPY_DST,
+            present_modes: vec![
+                wgt::PresentMode::Fifo,
+                wgt::PresentMode::Mailbox,
+                wgt::PresentMode::Immediate,
+            ],
+            composite_alpha_modes: vec![crate::CompositeAlphaMode::Opaque],
+        })
+    }
+}
+
+impl crate::Surface<super::Api> for super::Surface {
+    unsafe fn configure(
+        &mut self,
+        _device: &super::Device,
+        config: &crate::SurfaceConfiguration,
+    ) -> Result<(), crate::SurfaceError> {
+        self.config = Some(config.clone());
+        Ok(())
+    }
+
+    unsafe fn unconfigure(&mut self, _device: &super::Device) {
+        self.config = None;
+        self.presented = None;
+    }
+
+    unsafe fn acquire_texture(
+        &mut self,
+        _timeout_ms: u32,
+    ) -> Result<Option<crate::AcquiredSurfaceTexture<super::Api>>, crate::SurfaceError> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or(crate::SurfaceError::Other("surface is not configured"))?;
+        let desc = super::TextureDesc {
+            format: config.format,
+            dimension: wgt::TextureDimension::D2,
+            size: config.extent,
+            mip_level_count: 1,
+            sample_count: 1,
+        };
+        let texture = super::Texture::new(desc)?;
+        Ok(Some(crate::AcquiredSurfaceTexture {
+            texture,
+            suboptimal: false,
+        }))
+    }
+
+    unsafe fn discard_texture(&mut self, _texture: super::Texture) {}
+}
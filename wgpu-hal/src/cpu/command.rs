@@ -0,0 +1,444 @@
+use super::Command as C;
+use std::ops::Range;
+
+impl super::CommandBuffer {
+    fn clear(&mut self) {
+        self.label = None;
+        self.commands.clear();
+    }
+}
+
+impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
+    unsafe fn begin_encoding(&mut self, label: crate::Label) -> Result<(), crate::DeviceError> {
+        self.cmd_buffer.clear();
+        self.cmd_buffer.label = label.map(str::to_string);
+        Ok(())
+    }
+    unsafe fn discard_encoding(&mut self) {
+        self.cmd_buffer.clear();
+    }
+    unsafe fn end_encoding(&mut self) -> Result<super::CommandBuffer, crate::DeviceError> {
+        Ok(std::mem::take(&mut self.cmd_buffer))
+    }
+    unsafe fn reset_all<I>(&mut self, _command_buffers: I) {
+        //TODO: could re-use the allocations in all these command buffers
+    }
+
+    unsafe fn transition_buffers<'a, T>(&mut self, _barriers: T)
+    where
+        T: Iterator<Item = crate::BufferBarrier<'a, super::Api>>,
+    {
+    }
+
+    unsafe fn transition_textures<'a, T>(&mut self, _barriers: T)
+    where
+        T: Iterator<Item = crate::TextureBarrier<'a, super::Api>>,
+    {
+    }
+
+    unsafe fn clear_buffer(&mut self, buffer: &super::Buffer, range: crate::MemoryRange) {
+        self.cmd_buffer.commands.push(C::ClearBuffer {
+            dst: buffer.clone(),
+            range,
+        });
+    }
+
+    unsafe fn copy_buffer_to_buffer<T>(
+        &mut self,
+        src: &super::Buffer,
+        dst: &super::Buffer,
+        regions: T,
+    ) where
+        T: Iterator<Item = crate::BufferCopy>,
+    {
+        for copy in regions {
+            self.cmd_buffer.commands.push(C::CopyBufferToBuffer {
+                src: src.clone(),
+                dst: dst.clone(),
+                copy,
+            })
+        }
+    }
+
+    unsafe fn copy_texture_to_texture<T>(
+        &mut self,
+        src: &super::Texture,
+        _src_usage: crate::TextureUses,
+        dst: &super::Texture,
+        regions: T,
+    ) where
+        T: Iterator<Item = crate::TextureCopy>,
+    {
+        let src_size = src.desc.mip_extent(0);
+        let dst_size = dst.desc.mip_extent(0);
+        for mut copy in regions {
+            copy.clamp_size_to_virtual(&src_size, &dst_size);
+            self.cmd_buffer.commands.push(C::CopyTextureToTexture {
+                src: src.clone(),
+                dst: dst.clone(),
+                copy,
+            })
+        }
+    }
+
+    unsafe fn copy_buffer_to_texture<T>(
+        &mut self,
+        src: &super::Buffer,
+        dst: &super::Texture,
+        regions: T,
+    ) where
+        T: Iterator<Item = crate::BufferTextureCopy>,
+    {
+        let dst_size = dst.desc.mip_extent(0);
+        for mut copy in regions {
+            copy.clamp_size_to_virtual(&dst_size);
+            self.cmd_buffer.commands.push(C::CopyBufferToTexture {
+                src: src.clone(),
+                dst: dst.clone(),
+                copy,
+            })
+        }
+    }
+
+    unsafe fn copy_texture_to_buffer<T>(
+        &mut self,
+        src: &super::Texture,
+        _src_usage: crate::TextureUses,
+        dst: &super::Buffer,
+        regions: T,
+    ) where
+        T: Iterator<Item = crate::BufferTextureCopy>,
+    {
+        let src_size = src.desc.mip_extent(0);
+        for mut copy in regions {
+            copy.clamp_size_to_virtual(&src_size);
+            self.cmd_buffer.commands.push(C::CopyTextureToBuffer {
+                src: src.clone(),
+                dst: dst.clone(),
+                copy,
+            })
+        }
+    }
+
+    unsafe fn begin_query(&mut self, set: &super::QuerySet, index: u32) {
+        self.cmd_buffer
+            .commands
+            .push(C::BeginQuery(set.clone(), index));
+    }
+    unsafe fn end_query(&mut self, _set: &super::QuerySet, _index: u32) {}
+    unsafe fn write_timestamp(&mut self, set: &super::QuerySet, index: u32) {
+        self.cmd_buffer
+            .commands
+            .push(C::WriteTimestamp(set.clone(), index));
+    }
+    unsafe fn reset_queries(&mut self, set: &super::QuerySet, range: Range<u32>) {
+        self.cmd_buffer
+            .commands
+            .push(C::ResetQueries(set.clone(), range));
+    }
+    unsafe fn copy_query_results(
+        &mut self,
+        set: &super::QuerySet,
+        range: Range<u32>,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        stride: wgt::BufferSize,
+    ) {
+        self.cmd_buffer.commands.push(C::CopyQueryResults {
+            set: set.clone(),
+            range,
+            dst: buffer.clone(),
+            offset,
+            stride: stride.get(),
+        });
+    }
+
+    // render
+
+    unsafe fn begin_render_pass(&mut self, desc: &crate::RenderPassDescriptor<super::Api>) {
+        let color_targets = desc
+            .color_attachments
+            .iter()
+            .map(|cat| super::ColorTarget {
+                view: cat.target.view.clone(),
+                resolve_target: cat.resolve_target.as_ref().map(|rat| rat.view.clone()),
+                ops: cat.ops,
+                clear_value: cat.clear_value,
+            })
+            .collect();
+        let depth_stencil_target =
+            desc.depth_stencil_attachment
+                .as_ref()
+                .map(|dsat| super::DepthStencilTarget {
+                    view: dsat.target.view.clone(),
+                    depth_ops: dsat.depth_ops,
+                    stencil_ops: dsat.stencil_ops,
+                    clear_value: dsat.clear_value,
+                });
+        self.cmd_buffer.commands.push(C::BeginRenderPass {
+            color_targets,
+            depth_stencil_target,
+        });
+
+        // The viewport and the scissor rectangle cover the whole targets by default.
+        self.state.viewport = crate::Rect {
+            x: 0.0,
+            y: 0.0,
+            w: desc.extent.width as f32,
+            h: desc.extent.height as f32,
+        };
+        self.state.depth_range = 0.0..1.0;
+        self.state.scissor = crate::Rect {
+            x: 0,
+            y: 0,
+            w: desc.extent.width,
+            h: desc.extent.height,
+        };
+    }
+    unsafe fn end_render_pass(&mut self) {
+        self.cmd_buffer.commands.push(C::EndRenderPass);
+    }
+
+    unsafe fn set_bind_group(
+        &mut self,
+        _layout: &super::PipelineLayout,
+        index: u32,
+        group: &super::BindGroup,
+        dynamic_offsets: &[wgt::DynamicOffset],
+    ) {
+        let mut dynamic_offsets = dynamic_offsets.iter();
+        let bindings = &mut self.state.bind_groups[index as usize];
+        bindings.clear();
+        for entry in group.entries.iter() {
+            let mut resource = entry.resource.clone();
+            if let super::Binding::Buffer(ref mut range) = resource {
+                if entry.has_dynamic_offset {
+                    range.offset += *dynamic_offsets.next().unwrap() as wgt::BufferAddress;
+                }
+            }
+            bindings.push((entry.binding, resource));
+        }
+    }
+    unsafe fn set_push_constants(
+        &mut self,
+        _layout: &super::PipelineLayout,
+        _stages: wgt::ShaderStages,
+        _offset: u32,
+        _data: &[u32],
+    ) {
+    }
+
+    unsafe fn insert_debug_marker(&mut self, _label: &str) {}
+    unsafe fn begin_debug_marker(&mut self, _group_label: &str) {}
+    unsafe fn end_debug_marker(&mut self) {}
+
+    unsafe fn set_render_pipeline(&mut self, pipeline: &super::RenderPipeline) {
+        self.state.render_pipeline = Some(pipeline.clone());
+    }
+
+    unsafe fn set_index_buffer<'a>(
+        &mut self,
+        binding: crate::BufferBinding<'a, super::Api>,
+        format: wgt::IndexFormat,
+    ) {
+        self.state.index_buffer = Some((super::BufferBinding::new(&binding), format));
+    }
+    unsafe fn set_vertex_buffer<'a>(
+        &mut self,
+        index: u32,
+        binding: crate::BufferBinding<'a, super::Api>,
+    ) {
+        let index = index as usize;
+        if self.state.vertex_buffers.len() <= index {
+            self.state.vertex_buffers.resize(index + 1, None);
+        }
+        self.state.vertex_buffers[index] = Some(super::BufferBinding::new(&binding));
+    }
+    unsafe fn set_viewport(&mut self, rect: &crate::Rect<f32>, depth_range: Range<f32>) {
+        self.state.viewport = rect.clone();
+        self.state.depth_range = depth_range;
+    }
+    unsafe fn set_scissor_rect(&mut self, rect: &crate::Rect<u32>) {
+        self.state.scissor = rect.clone();
+    }
+    unsafe fn set_stencil_reference(&mut self, value: u32) {
+        self.state.stencil_reference = value;
+    }
+    unsafe fn set_blend_constants(&mut self, color: &[f32; 4]) {
+        self.state.blend_constant = *color;
+    }
+
+    unsafe fn draw(
+        &mut self,
+        start_vertex: u32,
+        vertex_count: u32,
+        start_instance: u32,
+        instance_count: u32,
+    ) {
+        self.push_draw(super::DrawSize::Direct(super::DrawCall {
+            indexed: false,
+            elements: start_vertex..start_vertex + vertex_count,
+            base_vertex: 0,
+            instances: start_instance..start_instance + instance_count,
+        }));
+    }
+    unsafe fn draw_indexed(
+        &mut self,
+        start_index: u32,
+        index_count: u32,
+        base_vertex: i32,
+        start_instance: u32,
+        instance_count: u32,
+    ) {
+        self.push_draw(super::DrawSize::Direct(super::DrawCall {
+            indexed: true,
+            elements: start_index..start_index + index_count,
+            base_vertex,
+            instances: start_instance..start_instance + instance_count,
+        }));
+    }
+    unsafe fn draw_indirect(
+        &mut self,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        draw_count: u32,
+    ) {
+        for draw in 0..draw_count as wgt::BufferAddress {
+            self.push_draw(super::DrawSize::Indirect {
+                indexed: false,
+                buffer: buffer.clone(),
+                offset: offset + draw * 16,
+            });
+        }
+    }
+    unsafe fn draw_indexed_indirect(
+        &mut self,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        draw_count: u32,
+    ) {
+        for draw in 0..draw_count as wgt::BufferAddress {
+            self.push_draw(super::DrawSize::Indirect {
+                indexed: true,
+                buffer: buffer.clone(),
+                offset: offset + draw * 20,
+            });
+        }
+    }
+
+    // `MULTI_DRAW_INDIRECT_COUNT` is not exposed.
+
+    unsafe fn draw_indirect_count(
+        &mut self,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+        _count_buffer: &super::Buffer,
+        _count_offset: wgt::BufferAddress,
+        _max_count: u32,
+    ) {
+    }
+    unsafe fn draw_indexed_indirect_count(
+        &mut self,
+        _buffer: &super::Buffer,
+        _offset: wgt::BufferAddress,
+        _count_buffer: &super::Buffer,
+        _count_offset: wgt::BufferAddress,
+        _max_count: u32,
+    ) {
+    }
+
+    // compute
+
+    unsafe fn begin_compute_pass(&mut self, _desc: &crate::ComputePassDescriptor) {}
+    unsafe fn end_compute_pass(&mut self) {}
+
+    unsafe fn set_compute_pipeline(&mut self, pipeline: &super::ComputePipeline) {
+        self.state.compute_pipeline = Some(pipeline.clone());
+    }
+
+    unsafe fn dispatch(&mut self, count: [u32; 3]) {
+        self.push_dispatch(super::DispatchSize::Direct(count));
+    }
+    unsafe fn dispatch_indirect(&mut self, buffer: &super::Buffer, offset: wgt::BufferAddress) {
+        self.push_dispatch(super::DispatchSize::Indirect {
+            buffer: buffer.clone(),
+            offset,
+        });
+    }
+}
+
+impl super::CommandEncoder {
+    /// Resolve the resources used by an entry point out of the bound groups.
+    fn bind(
+        &self,
+        program: &super::shader::Program,
+        entry_point: usize,
+    ) -> Result<Vec<Option<super::Binding>>, super::shader::Error> {
+        let bind_groups = &self.state.bind_groups;
+        program.bind(entry_point, |rb| {
+            bind_groups
+                .get(rb.group as usize)?
+                .iter()
+                .find(|&&(binding, _)| binding == rb.binding)
+                .map(|&(_, ref resource)| resource.clone())
+        })
+    }
+
+    fn push_dispatch(&mut self, size: super::DispatchSize) {
+        let pipeline = match self.state.compute_pipeline {
+            Some(ref pipeline) => pipeline.clone(),
+            None => return,
+        };
+        match self.bind(&pipeline.program, pipeline.entry_point) {
+            Ok(resources) => self.cmd_buffer.commands.push(C::Dispatch {
+                pipeline,
+                resources,
+                size,
+            }),
+            Err(e) => log::error!("Skipping dispatch: {}", e),
+        }
+    }
+
+    fn push_draw(&mut self, size: super::DrawSize) {
+        let pipeline = match self.state.render_pipeline {
+            Some(ref pipeline) => pipeline.clone(),
+            None => return,
+        };
+        let vertex_stage = &pipeline.vertex_stage;
+        let resources = self
+            .bind(&vertex_stage.program, vertex_stage.index)
+            .and_then(|vertex_resources| {
+                let fragment_resources = match pipeline.fragment_stage {
+                    Some(ref stage) => {
+                        let entry_point = &stage.entry_point;
+                        self.bind(&entry_point.program, entry_point.index)?
+                    }
+                    None => Vec::new(),
+                };
+                Ok((vertex_resources, fragment_resources))
+            });
+        let (vertex_resources, fragment_resources) = match resources {
+            Ok(resources) => resources,
+            Err(e) => {
+                log::error!("Skipping draw: {}", e);
+                return;
+            }
+        };
+        let state = &self.state;
+        self.cmd_buffer.commands.push(C::Draw {
+            state: Box::new(super::DrawState {
+                pipeline,
+                vertex_resources,
+                fragment_resources,
+                vertex_buffers: state.vertex_buffers.clone(),
+                index_buffer: state.index_buffer.clone(),
+                viewport: state.viewport.clone(),
+                depth_range: state.depth_range.clone(),
+                scissor: state.scissor.clone(),
+                stencil_reference: state.stencil_reference,
+                blend_constant: state.blend_constant,
+            }),
+            size,
+        });
+    }
+}
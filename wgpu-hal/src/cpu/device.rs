@@ -0,0 +1,323 @@
+use std::{ptr::NonNull, sync::Arc};
+
+use parking_lot::Mutex;
+
+type DeviceResult<T> = Result<T, crate::DeviceError>;
+
+fn find_entry_point(
+    stage: &crate::ProgrammableStage<super::Api>,
+    naga_stage: naga::ShaderStage,
+) -> Result<usize, crate::PipelineError> {
+    stage
+        .module
+        .program
+        .entry_point_index(naga_stage, stage.entry_point)
+        .ok_or(crate::PipelineError::EntryPoint(naga_stage))
+}
+
+/// Finds the entry point of a stage and checks that it can be executed.
+fn prepare_stage(
+    stage: &crate::ProgrammableStage<super::Api>,
+    naga_stage: naga::ShaderStage,
+) -> Result<super::EntryPoint, crate::PipelineError> {
+    let index = find_entry_point(stage, naga_stage)?;
+    let program = &stage.module.program;
+    program.check_entry_point(index).map_err(|e| {
+        let stages = match naga_stage {
+            naga::ShaderStage::Vertex => wgt::ShaderStages::VERTEX,
+            naga::ShaderStage::Fragment => wgt::ShaderStages::FRAGMENT,
+            naga::ShaderStage::Compute => wgt::ShaderStages::COMPUTE,
+        };
+        crate::PipelineError::Linkage(stages, e.to_string())
+    })?;
+    Ok(super::EntryPoint {
+        program: Arc::clone(program),
+        index,
+    })
+}
+
+impl crate::Device<super::Api> for super::Device {
+    unsafe fn exit(self, _queue: super::Queue) {}
+
+    unsafe fn create_buffer(&self, desc: &crate::BufferDescriptor) -> DeviceResult<super::Buffer> {
+        let memory = super::Memory::new(desc.size)?;
+        self.memory.add_buffer(memory.size());
+        Ok(super::Buffer {
+            memory,
+            size: desc.size,
+        })
+    }
+    unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
+        // The memory itself is freed once no command buffer references it anymore.
+        self.memory.remove_buffer(buffer.memory.size());
+    }
+
+    unsafe fn map_buffer(
+        &self,
+        buffer: &super::Buffer,
+        range: crate::MemoryRange,
+    ) -> DeviceResult<crate::BufferMapping> {
+        debug_assert!(range.end <= buffer.size);
+        let ptr = buffer.memory.ptr().add(range.start as usize);
+        Ok(crate::BufferMapping {
+            ptr: NonNull::new_unchecked(ptr),
+            is_coherent: true,
+        })
+    }
+    unsafe fn unmap_buffer(&self, _buffer: &super::Buffer) -> DeviceResult<()> {
+        Ok(())
+    }
+    unsafe fn flush_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
+    unsafe fn invalidate_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {}
+
+    unsafe fn create_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+    ) -> DeviceResult<super::Texture> {
+        let texture = super::Texture::new(super::TextureDesc {
+            format: desc.format,
+            dimension: desc.dimension,
+            size: desc.size,
+            mip_level_count: desc.mip_level_count,
+            sample_count: desc.sample_count,
+        })?;
+        self.memory.add_texture(texture.memory.size());
+        Ok(texture)
+    }
+    unsafe fn destroy_texture(&self, texture: super::Texture) {
+        self.memory.remove_texture(texture.memory.size());
+    }
+
+    unsafe fn create_texture_view(
+        &self,
+        texture: &super::Texture,
+        desc: &crate::TextureViewDescriptor,
+    ) -> DeviceResult<super::TextureView> {
+        let range = &desc.range;
+        let mip_end = match range.mip_level_count {
+            Some(count) => range.base_mip_level + count.get(),
+            None => texture.desc.mip_level_count,
+        };
+        let layer_end = match range.array_layer_count {
+            Some(count) => range.base_array_layer + count.get(),
+            None => texture.desc.array_layer_count(),
+        };
+        Ok(super::TextureView {
+            texture: texture.clone(),
+            format: desc.format,
+            aspects: crate::FormatAspects::from(texture.desc.format)
+                & crate::FormatAspects::from(range.aspect),
+            mip_levels: range.base_mip_level..mip_end,
+            array_layers: range.base_array_layer..layer_end,
+        })
+    }
+    unsafe fn destroy_texture_view(&self, _view: super::TextureView) {}
+
+    unsafe fn create_sampler(
+        &self,
+        desc: &crate::SamplerDescriptor,
+    ) -> DeviceResult<super::Sampler> {
+        Ok(super::Sampler {
+            address_modes: desc.address_modes,
+            mag_filter: desc.mag_filter,
+            min_filter: desc.min_filter,
+            mipmap_filter: desc.mipmap_filter,
+            lod_clamp: desc.lod_clamp.clone().unwrap_or(0.0..32.0),
+            compare: desc.compare,
+            border_color: desc.border_color,
+        })
+    }
+    unsafe fn destroy_sampler(&self, _sampler: super::Sampler) {}
+
+    unsafe fn create_command_encoder(
+        &self,
+        _desc: &crate::CommandEncoderDescriptor<super::Api>,
+    ) -> DeviceResult<super::CommandEncoder> {
+        Ok(super::CommandEncoder {
+            cmd_buffer: super::CommandBuffer::default(),
+            state: super::State::default(),
+        })
+    }
+    unsafe fn destroy_command_encoder(&self, _encoder: super::CommandEncoder) {}
+
+    unsafe fn create_bind_group_layout(
+        &self,
+        desc: &crate::BindGroupLayoutDescriptor,
+    ) -> DeviceResult<super::BindGroupLayout> {
+        Ok(super::BindGroupLayout {
+            entries: Arc::from(desc.entries),
+        })
+    }
+    unsafe fn destroy_bind_group_layout(&self, _bg_layout: super::BindGroupLayout) {}
+
+    unsafe fn create_pipeline_layout(
+        &self,
+        _desc: &crate::PipelineLayoutDescriptor<super::Api>,
+    ) -> DeviceResult<super::PipelineLayout> {
+        Ok(super::PipelineLayout)
+    }
+    unsafe fn destroy_pipeline_layout(&self, _pipeline_layout: super::PipelineLayout) {}
+
+    unsafe fn create_bind_group(
+        &self,
+        desc: &crate::BindGroupDescriptor<super::Api>,
+    ) -> DeviceResult<super::BindGroup> {
+        let mut entries = Vec::with_capacity(desc.entries.len());
+        for entry in desc.entries.iter() {
+            let layout = match desc
+                .layout
+                .entries
+                .iter()
+                .find(|layout| layout.binding == entry.binding)
+            {
+                Some(layout) => layout,
+                None => continue,
+            };
+            let index = entry.resource_index as usize;
+            let (has_dynamic_offset, resource) = match layout.ty {
+                wgt::BindingType::Buffer {
+                    has_dynamic_offset, ..
+                } => {
+                    let range = super::BufferBinding::new(&desc.buffers[index]);
+                    (has_dynamic_offset, super::Binding::Buffer(range))
+                }
+                wgt::BindingType::Sampler(_) => {
+                    (false, super::Binding::Sampler(desc.samplers[index].clone()))
+                }
+                wgt::BindingType::Texture { .. } | wgt::BindingType::StorageTexture { .. } => (
+                    false,
+                    super::Binding::Texture(desc.textures[index].view.clone()),
+                ),
+            };
+            entries.push(super::BindGroupEntry {
+                binding: entry.binding,
+                has_dynamic_offset,
+                resource,
+            });
+        }
+        Ok(super::BindGroup { entries })
+    }
+    unsafe fn destroy_bind_group(&self, _group: super::BindGroup) {}
+
+    unsafe fn create_shader_module(
+        &self,
+        _desc: &crate::ShaderModuleDescriptor,
+        shader: crate::ShaderInput,
+    ) -> Result<super::ShaderModule, crate::ShaderError> {
+        match shader {
+            crate::ShaderInput::Naga(naga) => {
+                let program = super::shader::Program::new(Arc::new(naga))
+                    .map_err(|e| crate::ShaderError::Compilation(e.to_string()))?;
+                Ok(super::ShaderModule {
+                    program: Arc::new(program),
+                })
+            }
+            crate::ShaderInput::SpirV(_) => Err(crate::ShaderError::Compilation(
+                "SPIR-V input is not supported".to_string(),
+            )),
+        }
+    }
+    unsafe fn destroy_shader_module(&self, _module: super::ShaderModule) {}
+
+    unsafe fn create_render_pipeline(
+        &self,
+        desc: &crate::RenderPipelineDescriptor<super::Api>,
+    ) -> Result<super::RenderPipeline, crate::PipelineError> {
+        let vertex_stage = prepare_stage(&desc.vertex_stage, naga::ShaderStage::Vertex)?;
+        let fragment_stage = match desc.fragment_stage {
+            Some(ref stage) => {
+                let entry_point = prepare_stage(stage, naga::ShaderStage::Fragment)?;
+                Some(super::FragmentStage {
+                    uses_derivatives: entry_point.program.uses_derivatives(entry_point.index),
+                    entry_point,
+                })
+            }
+            None => None,
+        };
+        Ok(super::RenderPipeline {
+            vertex_stage,
+            fragment_stage,
+            vertex_buffers: desc
+                .vertex_buffers
+                .iter()
+                .map(|vb| super::VertexBufferLayout {
+                    stride: vb.array_stride,
+                    step_mode: vb.step_mode,
+                    attributes: vb.attributes.to_vec(),
+                })
+                .collect(),
+            primitive: desc.primitive,
+            depth_stencil: desc.depth_stencil.clone(),
+            multisample: desc.multisample,
+            color_targets: Arc::from(desc.color_targets),
+        })
+    }
+    unsafe fn destroy_render_pipeline(&self, _pipeline: super::RenderPipeline) {}
+
+    unsafe fn create_compute_pipeline(
+        &self,
+        desc: &crate::ComputePipelineDescriptor<super::Api>,
+    ) -> Result<super::ComputePipeline, crate::PipelineError> {
+        let entry_point = prepare_stage(&desc.stage, naga::ShaderStage::Compute)?;
+        Ok(super::ComputePipeline {
+            program: entry_point.program,
+            entry_point: entry_point.index,
+        })
+    }
+    unsafe fn destroy_compute_pipeline(&self, _pipeline: super::ComputePipeline) {}
+
+    unsafe fn create_pipeline_cache(
+        &self,
+        _desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<super::PipelineCache, crate::PipelineCacheError> {
+        Ok(super::PipelineCache)
+    }
+    unsafe fn destroy_pipeline_cache(&self, _cache: super::PipelineCache) {}
+    unsafe fn pipeline_cache_get_data(&self, _cache: &super::PipelineCache) -> Vec<u8> {
+        Vec::new()
+    }
+
+    unsafe fn create_query_set(
+        &self,
+        desc: &wgt::QuerySetDescriptor<crate::Label>,
+    ) -> DeviceResult<super::QuerySet> {
+        let stride = match desc.ty {
+            wgt::QueryType::Occlusion | wgt::QueryType::Timestamp => 1,
+            wgt::QueryType::PipelineStatistics(types) => types.bits().count_ones(),
+        };
+        Ok(super::QuerySet {
+            values: Arc::new(Mutex::new(vec![0; (desc.count * stride) as usize])),
+            stride,
+        })
+    }
+    unsafe fn destroy_query_set(&self, _set: super::QuerySet) {}
+
+    unsafe fn create_fence(&self) -> DeviceResult<super::Fence> {
+        Ok(super::Fence { value: 0 })
+    }
+    unsafe fn destroy_fence(&self, _fence: super::Fence) {}
+    unsafe fn get_fence_value(&self, fence: &super::Fence) -> DeviceResult<crate::FenceValue> {
+        Ok(fence.value)
+    }
+    unsafe fn wait(
+        &self,
+        fence: &super::Fence,
+        wait_value: crate::FenceValue,
+        _timeout_ms: u32,
+    ) -> DeviceResult<bool> {
+        // Submissions are executed synchronously, so there is no pending work
+        // to wait for: either the value is reached, or it never will be.
+        Ok(fence.value >= wait_value)
+    }
+
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        wgt::MemoryReport {
+            heaps: vec![self.memory.heap()],
+        }
+    }
+
+    unsafe fn start_capture(&self) -> bool {
+        false
+    }
+    unsafe fn stop_capture(&self) {}
+}
@@ -0,0 +1,555 @@
+//! Conversion of texels between their memory representation and
+//! the values seen by the shaders.
+
+use wgt::TextureFormat as Tf;
+
+/// Texel value, as seen by the shaders.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Texel {
+    Float([f32; 4]),
+    Sint([i32; 4]),
+    Uint([u32; 4]),
+}
+
+impl Texel {
+    /// Builds a texel of the kind that `format` expects out of a clear color.
+    pub fn from_color(format: Tf, color: wgt::Color) -> Self {
+        match format.describe().sample_type {
+            wgt::TextureSampleType::Sint => Self::Sint([
+                color.r as i32,
+                color.g as i32,
+                color.b as i32,
+                color.a as i32,
+            ]),
+            wgt::TextureSampleType::Uint => Self::Uint([
+                color.r as u32,
+                color.g as u32,
+                color.b as u32,
+                color.a as u32,
+            ]),
+            _ => Self::Float([
+                color.r as f32,
+                color.g as f32,
+                color.b as f32,
+                color.a as f32,
+            ]),
+        }
+    }
+
+    pub fn to_float(self) -> [f32; 4] {
+        match self {
+            Self::Float(v) => v,
+            Self::Sint(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+            Self::Uint(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+        }
+    }
+}
+
+pub(super) fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal, renormalize it
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3FF;
+            sign | ((113 - shift) << 23) | (mantissa << 13)
+        }
+        0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+pub(super) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+    if exponent == 0xFF {
+        let nan_bit = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7C00 | nan_bit;
+    }
+    let half_exponent = exponent - 112;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = remainder > halfway || (remainder == halfway && (half_mantissa & 1) != 0);
+        return sign | (half_mantissa + round_up as u32) as u16;
+    }
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1FFF;
+    let round_up = remainder > 0x1000 || (remainder == 0x1000 && (half & 1) != 0);
+    // a carry out of the mantissa correctly bumps the exponent
+    sign | (half + round_up as u32) as u16
+}
+
+/// Decodes an unsigned float with a 5-bit exponent and no sign,
+/// as used by `Rg11b10Float`.
+fn small_ufloat_to_f32(bits: u32, mantissa_bits: u32) -> f32 {
+    let exponent = bits >> mantissa_bits;
+    let mantissa = bits & ((1 << mantissa_bits) - 1);
+    // reuse the f16 path, which has the same exponent layout
+    f16_to_f32(((exponent << 10) | (mantissa << (10 - mantissa_bits))) as u16)
+}
+
+fn f32_to_small_ufloat(value: f32, mantissa_bits: u32) -> u32 {
+    if value.is_nan() {
+        return (0x1F << mantissa_bits) | 1;
+    }
+    if value <= 0.0 {
+        return 0;
+    }
+    let half = f32_to_f16(value) as u32;
+    let max = (0x1E << mantissa_bits) | ((1 << mantissa_bits) - 1);
+    (half >> (10 - mantissa_bits)).min(max)
+}
+
+fn rgb9e5_to_f32(packed: u32) -> [f32; 4] {
+    let exponent = (packed >> 27) as i32 - 15 - 9;
+    let scale = 2f32.powi(exponent);
+    [
+        (packed & 0x1FF) as f32 * scale,
+        ((packed >> 9) & 0x1FF) as f32 * scale,
+        ((packed >> 18) & 0x1FF) as f32 * scale,
+        1.0,
+    ]
+}
+
+fn f32_to_rgb9e5(value: [f32; 4]) -> u32 {
+    const MAX: f32 = 65408.0;
+    let clamp = |v: f32| if v > 0.0 { v.min(MAX) } else { 0.0 };
+    let (r, g, b) = (clamp(value[0]), clamp(value[1]), clamp(value[2]));
+    let max_channel = r.max(g).max(b);
+    if max_channel == 0.0 {
+        return 0;
+    }
+    let mut exponent = (max_channel.log2().floor() as i32).max(-16) + 1 + 15;
+    let mut scale = 2f32.powi(exponent - 15 - 9);
+    if (max_channel / scale + 0.5).floor() as u32 == 512 {
+        exponent += 1;
+        scale *= 2.0;
+    }
+    let quantize = |v: f32| ((v / scale + 0.5).floor() as u32).min(0x1FF);
+    ((exponent as u32) << 27) | (quantize(b) << 18) | (quantize(g) << 9) | quantize(r)
+}
+
+pub(super) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(super) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_unorm(value: f32, max: f32) -> u32 {
+    let value = if value.is_nan() { 0.0 } else { value };
+    (value.clamp(0.0, 1.0) * max + 0.5) as u32
+}
+
+fn to_snorm(value: f32, max: f32) -> i32 {
+    let value = if value.is_nan() { 0.0 } else { value };
+    let scaled = value.clamp(-1.0, 1.0) * max;
+    (if scaled < 0.0 {
+        scaled - 0.5
+    } else {
+        scaled + 0.5
+    }) as i32
+}
+
+fn read_u16(bytes: &[u8], index: usize) -> u16 {
+    u16::from_ne_bytes([bytes[index * 2], bytes[index * 2 + 1]])
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    let mut raw = [0; 4];
+    raw.copy_from_slice(&bytes[index * 4..index * 4 + 4]);
+    u32::from_ne_bytes(raw)
+}
+
+fn write_u16(bytes: &mut [u8], index: usize, value: u16) {
+    bytes[index * 2..index * 2 + 2].copy_from_slice(&value.to_ne_bytes());
+}
+
+fn write_u32(bytes: &mut [u8], index: usize, value: u32) {
+    bytes[index * 4..index * 4 + 4].copy_from_slice(&value.to_ne_bytes());
+}
+
+/// Number of components stored in memory for a format,
+/// and the kind of per-component encoding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Layout {
+    Unorm8,
+    Snorm8,
+    Uint8,
+    Sint8,
+    Unorm16,
+    Snorm16,
+    Uint16,
+    Sint16,
+    Float16,
+    Uint32,
+    Sint32,
+    Float32,
+}
+
+fn plain_layout(format: Tf) -> Option<(Layout, usize)> {
+    Some(match format {
+        Tf::R8Unorm => (Layout::Unorm8, 1),
+        Tf::R8Snorm => (Layout::Snorm8, 1),
+        Tf::R8Uint => (Layout::Uint8, 1),
+        Tf::R8Sint => (Layout::Sint8, 1),
+        Tf::R16Uint => (Layout::Uint16, 1),
+        Tf::R16Sint => (Layout::Sint16, 1),
+        Tf::R16Unorm => (Layout::Unorm16, 1),
+        Tf::R16Snorm => (Layout::Snorm16, 1),
+        Tf::R16Float => (Layout::Float16, 1),
+        Tf::Rg8Unorm => (Layout::Unorm8, 2),
+        Tf::Rg8Snorm => (Layout::Snorm8, 2),
+        Tf::Rg8Uint => (Layout::Uint8, 2),
+        Tf::Rg8Sint => (Layout::Sint8, 2),
+        Tf::R32Uint => (Layout::Uint32, 1),
+        Tf::R32Sint => (Layout::Sint32, 1),
+        Tf::R32Float => (Layout::Float32, 1),
+        Tf::Rg16Uint => (Layout::Uint16, 2),
+        Tf::Rg16Sint => (Layout::Sint16, 2),
+        Tf::Rg16Unorm => (Layout::Unorm16, 2),
+        Tf::Rg16Snorm => (Layout::Snorm16, 2),
+        Tf::Rg16Float => (Layout::Float16, 2),
+        Tf::Rgba8Unorm | Tf::Rgba8UnormSrgb | Tf::Bgra8Unorm | Tf::Bgra8UnormSrgb => {
+            (Layout::Unorm8, 4)
+        }
+        Tf::Rgba8Snorm => (Layout::Snorm8, 4),
+        Tf::Rgba8Uint => (Layout::Uint8, 4),
+        Tf::Rgba8Sint => (Layout::Sint8, 4),
+        Tf::Rg32Uint => (Layout::Uint32, 2),
+        Tf::Rg32Sint => (Layout::Sint32, 2),
+        Tf::Rg32Float => (Layout::Float32, 2),
+        Tf::Rgba16Uint => (Layout::Uint16, 4),
+        Tf::Rgba16Sint => (Layout::Sint16, 4),
+        Tf::Rgba16Unorm => (Layout::Unorm16, 4),
+        Tf::Rgba16Snorm => (Layout::Snorm16, 4),
+        Tf::Rgba16Float => (Layout::Float16, 4),
+        Tf::Rgba32Uint => (Layout::Uint32, 4),
+        Tf::Rgba32Sint => (Layout::Sint32, 4),
+        Tf::Rgba32Float => (Layout::Float32, 4),
+        _ => return None,
+    })
+}
+
+/// Returns true if texels of this format can be decoded and encoded.
+///
+/// This covers all the uncompressed color formats.
+pub(super) fn is_supported(format: Tf) -> bool {
+    match format {
+        Tf::Rgb10a2Unorm | Tf::Rg11b10Float | Tf::Rgb9e5Ufloat => true,
+        _ => plain_layout(format).is_some(),
+    }
+}
+
+/// Returns the range of values of a normalized format.
+pub(super) fn normalized_range(format: Tf) -> Option<(f32, f32)> {
+    match plain_layout(format) {
+        Some((Layout::Unorm8, _)) | Some((Layout::Unorm16, _)) => Some((0.0, 1.0)),
+        Some((Layout::Snorm8, _)) | Some((Layout::Snorm16, _)) => Some((-1.0, 1.0)),
+        None if format == Tf::Rgb10a2Unorm => Some((0.0, 1.0)),
+        _ => None,
+    }
+}
+
+fn is_bgra(format: Tf) -> bool {
+    match format {
+        Tf::Bgra8Unorm | Tf::Bgra8UnormSrgb => true,
+        _ => false,
+    }
+}
+
+/// Reads a single texel of a color format.
+pub(super) fn decode(format: Tf, bytes: &[u8]) -> Texel {
+    let (layout, count) = match plain_layout(format) {
+        Some(pair) => pair,
+        None => {
+            return match format {
+                Tf::Rgb10a2Unorm => {
+                    let packed = read_u32(bytes, 0);
+                    Texel::Float([
+                        (packed & 0x3FF) as f32 / 1023.0,
+                        ((packed >> 10) & 0x3FF) as f32 / 1023.0,
+                        ((packed >> 20) & 0x3FF) as f32 / 1023.0,
+                        (packed >> 30) as f32 / 3.0,
+                    ])
+                }
+                Tf::Rg11b10Float => {
+                    let packed = read_u32(bytes, 0);
+                    Texel::Float([
+                        small_ufloat_to_f32(packed & 0x7FF, 6),
+                        small_ufloat_to_f32((packed >> 11) & 0x7FF, 6),
+                        small_ufloat_to_f32(packed >> 22, 5),
+                        1.0,
+                    ])
+                }
+                Tf::Rgb9e5Ufloat => Texel::Float(rgb9e5_to_f32(read_u32(bytes, 0))),
+                _ => panic!("Unable to decode texels of {:?}", format),
+            };
+        }
+    };
+
+    match decode_components(layout, count, bytes) {
+        Texel::Float(mut v) => {
+            if is_bgra(format) {
+                v.swap(0, 2);
+            }
+            if format.describe().srgb {
+                for c in v[..3].iter_mut() {
+                    *c = srgb_to_linear(*c);
+                }
+            }
+            Texel::Float(v)
+        }
+        texel => texel,
+    }
+}
+
+/// Reads `count` components of the given layout, filling the rest with `(0, 0, 0, 1)`.
+fn decode_components(layout: Layout, count: usize, bytes: &[u8]) -> Texel {
+    match layout {
+        Layout::Uint8 | Layout::Uint16 | Layout::Uint32 => {
+            let mut v = [0, 0, 0, 1];
+            for (i, c) in v.iter_mut().enumerate().take(count) {
+                *c = match layout {
+                    Layout::Uint8 => bytes[i] as u32,
+                    Layout::Uint16 => read_u16(bytes, i) as u32,
+                    _ => read_u32(bytes, i),
+                };
+            }
+            Texel::Uint(v)
+        }
+        Layout::Sint8 | Layout::Sint16 | Layout::Sint32 => {
+            let mut v = [0, 0, 0, 1];
+            for (i, c) in v.iter_mut().enumerate().take(count) {
+                *c = match layout {
+                    Layout::Sint8 => bytes[i] as i8 as i32,
+                    Layout::Sint16 => read_u16(bytes, i) as i16 as i32,
+                    _ => read_u32(bytes, i) as i32,
+                };
+            }
+            Texel::Sint(v)
+        }
+        _ => {
+            let mut v = [0.0, 0.0, 0.0, 1.0];
+            for (i, c) in v.iter_mut().enumerate().take(count) {
+                *c = match layout {
+                    Layout::Unorm8 => bytes[i] as f32 / 255.0,
+                    Layout::Snorm8 => (bytes[i] as i8 as f32 / 127.0).max(-1.0),
+                    Layout::Unorm16 => read_u16(bytes, i) as f32 / 65535.0,
+                    Layout::Snorm16 => (read_u16(bytes, i) as i16 as f32 / 32767.0).max(-1.0),
+                    Layout::Float16 => f16_to_f32(read_u16(bytes, i)),
+                    _ => f32::from_bits(read_u32(bytes, i)),
+                };
+            }
+            Texel::Float(v)
+        }
+    }
+}
+
+/// Reads a vertex attribute, or returns `None` for the 64-bit formats.
+pub(super) fn decode_vertex(format: wgt::VertexFormat, bytes: &[u8]) -> Option<Texel> {
+    use wgt::VertexFormat as Vf;
+    let (layout, count) = match format {
+        Vf::Uint8x2 => (Layout::Uint8, 2),
+        Vf::Uint8x4 => (Layout::Uint8, 4),
+        Vf::Sint8x2 => (Layout::Sint8, 2),
+        Vf::Sint8x4 => (Layout::Sint8, 4),
+        Vf::Unorm8x2 => (Layout::Unorm8, 2),
+        Vf::Unorm8x4 => (Layout::Unorm8, 4),
+        Vf::Snorm8x2 => (Layout::Snorm8, 2),
+        Vf::Snorm8x4 => (Layout::Snorm8, 4),
+        Vf::Uint16x2 => (Layout::Uint16, 2),
+        Vf::Uint16x4 => (Layout::Uint16, 4),
+        Vf::Sint16x2 => (Layout::Sint16, 2),
+        Vf::Sint16x4 => (Layout::Sint16, 4),
+        Vf::Unorm16x2 => (Layout::Unorm16, 2),
+        Vf::Unorm16x4 => (Layout::Unorm16, 4),
+        Vf::Snorm16x2 => (Layout::Snorm16, 2),
+        Vf::Snorm16x4 => (Layout::Snorm16, 4),
+        Vf::Float16x2 => (Layout::Float16, 2),
+        Vf::Float16x4 => (Layout::Float16, 4),
+        Vf::Float32 => (Layout::Float32, 1),
+        Vf::Float32x2 => (Layout::Float32, 2),
+        Vf::Float32x3 => (Layout::Float32, 3),
+        Vf::Float32x4 => (Layout::Float32, 4),
+        Vf::Uint32 => (Layout::Uint32, 1),
+        Vf::Uint32x2 => (Layout::Uint32, 2),
+        Vf::Uint32x3 => (Layout::Uint32, 3),
+        Vf::Uint32x4 => (Layout::Uint32, 4),
+        Vf::Sint32 => (Layout::Sint32, 1),
+        Vf::Sint32x2 => (Layout::Sint32, 2),
+        Vf::Sint32x3 => (Layout::Sint32, 3),
+        Vf::Sint32x4 => (Layout::Sint32, 4),
+        Vf::Float64 | Vf::Float64x2 | Vf::Float64x3 | Vf::Float64x4 => return None,
+    };
+    Some(decode_components(layout, count, bytes))
+}
+
+/// Writes a single texel of a color format.
+///
+/// Values are converted into the kind that the format expects, if needed.
+pub(super) fn encode(format: Tf, texel: Texel, bytes: &mut [u8]) {
+    let (layout, count) = match plain_layout(format) {
+        Some(pair) => pair,
+        None => {
+            let v = texel.to_float();
+            let packed = match format {
+                Tf::Rgb10a2Unorm => {
+                    to_unorm(v[0], 1023.0)
+                        | (to_unorm(v[1], 1023.0) << 10)
+                        | (to_unorm(v[2], 1023.0) << 20)
+                        | (to_unorm(v[3], 3.0) << 30)
+                }
+                Tf::Rg11b10Float => {
+                    f32_to_small_ufloat(v[0], 6)
+                        | (f32_to_small_ufloat(v[1], 6) << 11)
+                        | (f32_to_small_ufloat(v[2], 5) << 22)
+                }
+                Tf::Rgb9e5Ufloat => f32_to_rgb9e5(v),
+                _ => panic!("Unable to encode texels of {:?}", format),
+            };
+            write_u32(bytes, 0, packed);
+            return;
+        }
+    };
+
+    match layout {
+        Layout::Uint8 | Layout::Uint16 | Layout::Uint32 => {
+            let v = match texel {
+                Texel::Uint(v) => v,
+                Texel::Sint(v) => [v[0] as u32, v[1] as u32, v[2] as u32, v[3] as u32],
+                Texel::Float(v) => [v[0] as u32, v[1] as u32, v[2] as u32, v[3] as u32],
+            };
+            for (i, &c) in v.iter().enumerate().take(count) {
+                match layout {
+                    Layout::Uint8 => bytes[i] = c as u8,
+                    Layout::Uint16 => write_u16(bytes, i, c as u16),
+                    _ => write_u32(bytes, i, c),
+                }
+            }
+        }
+        Layout::Sint8 | Layout::Sint16 | Layout::Sint32 => {
+            let v = match texel {
+                Texel::Sint(v) => v,
+                Texel::Uint(v) => [v[0] as i32, v[1] as i32, v[2] as i32, v[3] as i32],
+                Texel::Float(v) => [v[0] as i32, v[1] as i32, v[2] as i32, v[3] as i32],
+            };
+            for (i, &c) in v.iter().enumerate().take(count) {
+                match layout {
+                    Layout::Sint8 => bytes[i] = c as i8 as u8,
+                    Layout::Sint16 => write_u16(bytes, i, c as i16 as u16),
+                    _ => write_u32(bytes, i, c as u32),
+                }
+            }
+        }
+        _ => {
+            let mut v = texel.to_float();
+            if format.describe().srgb {
+                for c in v[..3].iter_mut() {
+                    *c = linear_to_srgb(*c);
+                }
+            }
+            if is_bgra(format) {
+                v.swap(0, 2);
+            }
+            for (i, &c) in v.iter().enumerate().take(count) {
+                match layout {
+                    Layout::Unorm8 => bytes[i] = to_unorm(c, 255.0) as u8,
+                    Layout::Snorm8 => bytes[i] = to_snorm(c, 127.0) as i8 as u8,
+                    Layout::Unorm16 => write_u16(bytes, i, to_unorm(c, 65535.0) as u16),
+                    Layout::Snorm16 => write_u16(bytes, i, to_snorm(c, 32767.0) as i16 as u16),
+                    Layout::Float16 => write_u16(bytes, i, f32_to_f16(c)),
+                    _ => write_u32(bytes, i, c.to_bits()),
+                }
+            }
+        }
+    }
+}
+
+/// Reads the depth value of a texel, as laid out in a buffer copy.
+pub(super) fn decode_depth(format: Tf, bytes: &[u8]) -> f32 {
+    match format {
+        Tf::Depth32Float => f32::from_bits(read_u32(bytes, 0)),
+        _ => (read_u32(bytes, 0) & 0xFF_FFFF) as f32 / 16_777_215.0,
+    }
+}
+
+/// Writes the depth value of a texel, as laid out in a buffer copy.
+///
+/// For `Depth24PlusStencil8`, the stencil bits are left untouched.
+pub(super) fn encode_depth(format: Tf, depth: f32, bytes: &mut [u8]) {
+    match format {
+        Tf::Depth32Float => write_u32(bytes, 0, depth.to_bits()),
+        _ => {
+            let old = read_u32(bytes, 0);
+            let value = to_unorm(depth, 16_777_215.0);
+            write_u32(bytes, 0, (old & 0xFF00_0000) | value);
+        }
+    }
+}
+
+#[test]
+fn test_f16_round_trip() {
+    for &value in &[0.0, -0.0, 1.0, -2.5, 0.333_333, 65504.0, 6.1e-5, 1.0e-7] {
+        let half = f32_to_f16(value);
+        let back = f16_to_f32(half);
+        assert!((back - value).abs() <= value.abs() * 1.0e-3 + 6.0e-8);
+        assert_eq!(f32_to_f16(back), half);
+    }
+    assert_eq!(f32_to_f16(1.0e6), 0x7C00);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+}
+
+#[test]
+fn test_texel_round_trip() {
+    let texel = Texel::Float([0.25, 0.5, 1.0, 0.0]);
+    for &format in &[
+        Tf::Rgba8Unorm,
+        Tf::Bgra8UnormSrgb,
+        Tf::Rgba16Float,
+        Tf::Rgba32Float,
+        Tf::Rgb10a2Unorm,
+    ] {
+        let mut bytes = [0u8; 16];
+        encode(format, texel, &mut bytes);
+        let decoded = decode(format, &bytes).to_float();
+        for (a, b) in decoded.iter().zip(texel.to_float().iter()) {
+            assert!((a - b).abs() < 0.01, "{:?}: {:?}", format, decoded);
+        }
+    }
+    let mut bytes = [0u8; 4];
+    encode(
+        Tf::Rgb9e5Ufloat,
+        Texel::Float([1.0, 2.0, 0.5, 1.0]),
+        &mut bytes,
+    );
+    assert_eq!(
+        decode(Tf::Rgb9e5Ufloat, &bytes),
+        Texel::Float([1.0, 2.0, 0.5, 1.0])
+    );
+}
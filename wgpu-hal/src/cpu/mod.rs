@@ -0,0 +1,637 @@
+/*!
+# CPU reference backend.
+
+Executes all the work on the host, without any graphics API underneath.
+It's meant for testing and for running on machines without a GPU, and it
+values simplicity and correctness over speed.
+
+## Memory
+
+Buffers and textures are plain host allocations, shared by `Arc` between the
+resources and the command buffers that reference them. Buffer mapping is
+persistent and coherent: the mapped pointer points straight into the storage.
+
+Textures are stored subresource after subresource: first by aspect plane,
+then by array layer, then by mip level. Within a subresource, rows of texel
+blocks are tightly packed, and the samples of a texel are stored next to
+each other. Depth is always stored as `f32` and stencil as `u8`, in separate
+planes, regardless of the format. Conversion to the buffer representation of
+the format happens at copy time.
+
+## Execution
+
+Like the GLES backend, the command encoder records a list of `Command`s,
+which the queue replays at submission time. Execution is synchronous:
+by the time `Queue::submit` returns, the work is done and the fence is signaled.
+
+Shaders are interpreted by the `shader` module. Draws are rasterized by the
+`raster` module, one primitive at a time, in submission order.
+
+## Presentation
+
+Surfaces are headless. They hand out host textures and keep the last
+presented one around, but never show anything on screen.
+*/
+
+mod adapter;
+mod command;
+mod device;
+mod format;
+mod queue;
+mod raster;
+mod shader;
+
+use arrayvec::ArrayVec;
+use parking_lot::Mutex;
+
+use std::{alloc, ops::Range, ptr::NonNull, sync::Arc};
+
+#[derive(Clone)]
+pub struct Api;
+
+/// Alignment of all host allocations, suitable for any scalar type.
+const MEMORY_ALIGNMENT: usize = 16;
+
+impl crate::Api for Api {
+    type Instance = Instance;
+    type Surface = Surface;
+    type Adapter = Adapter;
+    type Device = Device;
+
+    type Queue = Queue;
+    type CommandEncoder = CommandEncoder;
+    type CommandBuffer = CommandBuffer;
+
+    type Buffer = Buffer;
+    type Texture = Texture;
+    type SurfaceTexture = Texture;
+    type TextureView = TextureView;
+    type Sampler = Sampler;
+    type QuerySet = QuerySet;
+    type Fence = Fence;
+
+    type BindGroupLayout = BindGroupLayout;
+    type BindGroup = BindGroup;
+    type PipelineLayout = PipelineLayout;
+    type ShaderModule = ShaderModule;
+    type RenderPipeline = RenderPipeline;
+    type ComputePipeline = ComputePipeline;
+    type PipelineCache = PipelineCache;
+}
+
+pub struct Instance {
+    _flags: crate::InstanceFlags,
+}
+
+#[derive(Debug)]
+pub struct Surface {
+    config: Option<crate::SurfaceConfiguration>,
+    presented: Option<Texture>,
+}
+
+impl Surface {
+    /// Returns the texture that was presented last, if any.
+    pub fn presented_texture(&self) -> Option<&Texture> {
+        self.presented.as_ref()
+    }
+}
+
+pub struct Adapter {
+    _private: (),
+}
+
+pub struct Device {
+    memory: crate::auxil::memory::ResourceMemory,
+}
+
+pub struct Queue {
+    /// Origin of the timestamp queries.
+    epoch: std::time::Instant,
+}
+
+/// Zero-initialized host allocation.
+#[derive(Debug)]
+struct Memory {
+    ptr: NonNull<u8>,
+    layout: alloc::Layout,
+}
+
+// Access to the contents is synchronized by the users of the memory,
+// just like it is with the GPU memory of other backends.
+unsafe impl Send for Memory {}
+unsafe impl Sync for Memory {}
+
+impl Memory {
+    fn new(size: wgt::BufferAddress) -> Result<Arc<Self>, crate::DeviceError> {
+        use std::convert::TryFrom as _;
+
+        let size = usize::try_from(size).map_err(|_| crate::DeviceError::OutOfMemory)?;
+        let layout = alloc::Layout::from_size_align(size.max(1), MEMORY_ALIGNMENT)
+            .map_err(|_| crate::DeviceError::OutOfMemory)?;
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        match NonNull::new(ptr) {
+            Some(ptr) => Ok(Arc::new(Self { ptr, layout })),
+            None => Err(crate::DeviceError::OutOfMemory),
+        }
+    }
+
+    fn ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    fn size(&self) -> u64 {
+        self.layout.size() as u64
+    }
+
+    /// Returns the byte slice for `range`.
+    ///
+    /// The caller must make sure no one writes to this range for the duration of the borrow.
+    unsafe fn slice(&self, range: Range<usize>) -> &[u8] {
+        debug_assert!(range.start <= range.end && range.end <= self.layout.size());
+        std::slice::from_raw_parts(self.ptr().add(range.start), range.end - range.start)
+    }
+
+    /// Returns the mutable byte slice for `range`.
+    ///
+    /// The caller must make sure no one else accesses this range for the duration of the borrow.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slice_mut(&self, range: Range<usize>) -> &mut [u8] {
+        debug_assert!(range.start <= range.end && range.end <= self.layout.size());
+        std::slice::from_raw_parts_mut(self.ptr().add(range.start), range.end - range.start)
+    }
+}
+
+impl Drop for Memory {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Buffer {
+    memory: Arc<Memory>,
+    size: wgt::BufferAddress,
+}
+
+impl Buffer {
+    unsafe fn slice(&self, range: Range<wgt::BufferAddress>) -> &[u8] {
+        self.memory.slice(range.start as usize..range.end as usize)
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slice_mut(&self, range: Range<wgt::BufferAddress>) -> &mut [u8] {
+        self.memory
+            .slice_mut(range.start as usize..range.end as usize)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TextureDesc {
+    format: wgt::TextureFormat,
+    dimension: wgt::TextureDimension,
+    size: wgt::Extent3d,
+    mip_level_count: u32,
+    sample_count: u32,
+}
+
+/// Location of a single subresource (an aspect of a mip level of an array layer)
+/// in the texture memory.
+#[derive(Clone, Copy, Debug)]
+struct Subresource {
+    offset: usize,
+    /// Size of a single sample of a texel block, in bytes.
+    block_size: usize,
+    sample_count: usize,
+    row_pitch: usize,
+    slice_pitch: usize,
+    /// Size, in texel blocks.
+    extent: crate::CopyExtent,
+}
+
+impl Subresource {
+    fn size(&self) -> usize {
+        self.slice_pitch * self.extent.depth as usize
+    }
+
+    /// Returns the memory range of a block at the given block coordinates.
+    fn block_range(&self, x: u32, y: u32, z: u32, sample: u32) -> Range<usize> {
+        let start = self.offset
+            + z as usize * self.slice_pitch
+            + y as usize * self.row_pitch
+            + (x as usize * self.sample_count + sample as usize) * self.block_size;
+        start..start + self.block_size
+    }
+}
+
+impl TextureDesc {
+    fn array_layer_count(&self) -> u32 {
+        match self.dimension {
+            wgt::TextureDimension::D3 => 1,
+            _ => self.size.depth_or_array_layers,
+        }
+    }
+
+    fn aspects(&self) -> crate::FormatAspects {
+        crate::FormatAspects::from(self.format)
+    }
+
+    /// Returns the virtual size of a mip level, in texels.
+    fn mip_extent(&self, level: u32) -> crate::CopyExtent {
+        crate::CopyExtent {
+            width: self.size.width,
+            height: self.size.height,
+            depth: match self.dimension {
+                wgt::TextureDimension::D3 => self.size.depth_or_array_layers,
+                _ => 1,
+            },
+        }
+        .at_mip_level(level)
+    }
+
+    /// Returns the texel block dimensions and the size of a block in
+    /// the storage plane of a single `aspect`.
+    fn plane_block(&self, aspect: crate::FormatAspects) -> ((u32, u32), usize) {
+        if aspect == crate::FormatAspects::DEPTH {
+            ((1, 1), 4)
+        } else if aspect == crate::FormatAspects::STENCIL {
+            ((1, 1), 1)
+        } else {
+            let desc = self.format.describe();
+            let (w, h) = desc.block_dimensions;
+            ((w as u32, h as u32), desc.block_size as usize)
+        }
+    }
+
+    fn subresource_at(
+        &self,
+        aspect: crate::FormatAspects,
+        level: u32,
+        offset: usize,
+    ) -> Subresource {
+        let ((block_width, block_height), block_size) = self.plane_block(aspect);
+        let mip = self.mip_extent(level);
+        let extent = crate::CopyExtent {
+            width: (mip.width + block_width - 1) / block_width,
+            height: (mip.height + block_height - 1) / block_height,
+            depth: mip.depth,
+        };
+        let sample_count = self.sample_count as usize;
+        let row_pitch = extent.width as usize * sample_count * block_size;
+        Subresource {
+            offset,
+            block_size,
+            sample_count,
+            row_pitch,
+            slice_pitch: row_pitch * extent.height as usize,
+            extent,
+        }
+    }
+
+    fn layer_size(&self, aspect: crate::FormatAspects) -> usize {
+        (0..self.mip_level_count)
+            .map(|level| self.subresource_at(aspect, level, 0).size())
+            .sum()
+    }
+
+    fn plane_size(&self, aspect: crate::FormatAspects) -> usize {
+        self.layer_size(aspect) * self.array_layer_count() as usize
+    }
+
+    fn total_size(&self) -> usize {
+        let aspects = self.aspects();
+        [
+            crate::FormatAspects::COLOR,
+            crate::FormatAspects::DEPTH,
+            crate::FormatAspects::STENCIL,
+        ]
+        .iter()
+        .filter(|&&aspect| aspects.contains(aspect))
+        .map(|&aspect| self.plane_size(aspect))
+        .sum()
+    }
+
+    /// Returns the location of a subresource. `aspect` has to be a single aspect.
+    fn subresource(&self, aspect: crate::FormatAspects, level: u32, layer: u32) -> Subresource {
+        let mut offset = 0;
+        if aspect == crate::FormatAspects::STENCIL
+            && self.aspects().contains(crate::FormatAspects::DEPTH)
+        {
+            offset += self.plane_size(crate::FormatAspects::DEPTH);
+        }
+        offset += layer as usize * self.layer_size(aspect);
+        offset += (0..level)
+            .map(|l| self.subresource_at(aspect, l, 0).size())
+            .sum::<usize>();
+        self.subresource_at(aspect, level, offset)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Texture {
+    memory: Arc<Memory>,
+    desc: TextureDesc,
+}
+
+impl Texture {
+    fn new(desc: TextureDesc) -> Result<Self, crate::DeviceError> {
+        Ok(Self {
+            memory: Memory::new(desc.total_size() as wgt::BufferAddress)?,
+            desc,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TextureView {
+    texture: Texture,
+    format: wgt::TextureFormat,
+    aspects: crate::FormatAspects,
+    mip_levels: Range<u32>,
+    array_layers: Range<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    address_modes: [wgt::AddressMode; 3],
+    mag_filter: wgt::FilterMode,
+    min_filter: wgt::FilterMode,
+    mipmap_filter: wgt::FilterMode,
+    lod_clamp: Range<f32>,
+    compare: Option<wgt::CompareFunction>,
+    border_color: Option<wgt::SamplerBorderColor>,
+}
+
+#[derive(Clone, Debug)]
+pub struct QuerySet {
+    /// Results of all the queries, `stride` values per query.
+    values: Arc<Mutex<Vec<u64>>>,
+    stride: u32,
+}
+
+#[derive(Debug)]
+pub struct Fence {
+    value: crate::FenceValue,
+}
+
+pub struct BindGroupLayout {
+    entries: Arc<[wgt::BindGroupLayoutEntry]>,
+}
+
+pub struct PipelineLayout;
+
+/// Range of a buffer bound to a shader.
+#[derive(Clone, Debug)]
+struct BufferBinding {
+    buffer: Buffer,
+    offset: wgt::BufferAddress,
+    size: wgt::BufferAddress,
+}
+
+impl BufferBinding {
+    fn new(binding: &crate::BufferBinding<Api>) -> Self {
+        Self {
+            buffer: binding.buffer.clone(),
+            offset: binding.offset,
+            size: match binding.size {
+                Some(size) => size.get(),
+                None => binding.buffer.size - binding.offset,
+            },
+        }
+    }
+}
+
+/// Resource bound to a shader.
+#[derive(Clone, Debug)]
+enum Binding {
+    Buffer(BufferBinding),
+    Texture(TextureView),
+    Sampler(Sampler),
+}
+
+#[derive(Debug)]
+struct BindGroupEntry {
+    binding: u32,
+    has_dynamic_offset: bool,
+    resource: Binding,
+}
+
+#[derive(Debug)]
+pub struct BindGroup {
+    entries: Vec<BindGroupEntry>,
+}
+
+#[derive(Debug)]
+pub struct ShaderModule {
+    program: Arc<shader::Program>,
+}
+
+/// Entry point of a shader module, prepared for execution.
+#[derive(Clone, Debug)]
+struct EntryPoint {
+    program: Arc<shader::Program>,
+    index: usize,
+}
+
+#[derive(Clone, Debug)]
+struct VertexBufferLayout {
+    stride: wgt::BufferAddress,
+    step_mode: wgt::VertexStepMode,
+    attributes: Vec<wgt::VertexAttribute>,
+}
+
+#[derive(Clone, Debug)]
+struct FragmentStage {
+    entry_point: EntryPoint,
+    /// Whether the fragments have to be shaded in quads, for the derivatives.
+    uses_derivatives: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderPipeline {
+    vertex_stage: EntryPoint,
+    fragment_stage: Option<FragmentStage>,
+    vertex_buffers: Arc<[VertexBufferLayout]>,
+    primitive: wgt::PrimitiveState,
+    depth_stencil: Option<wgt::DepthStencilState>,
+    multisample: wgt::MultisampleState,
+    color_targets: Arc<[wgt::ColorTargetState]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ComputePipeline {
+    program: Arc<shader::Program>,
+    entry_point: usize,
+}
+
+/// Shaders are interpreted directly, so there is nothing to cache.
+#[derive(Debug)]
+pub struct PipelineCache;
+
+#[derive(Debug)]
+enum DispatchSize {
+    Direct([u32; 3]),
+    Indirect {
+        buffer: Buffer,
+        offset: wgt::BufferAddress,
+    },
+}
+
+/// Render state captured by a draw call.
+#[derive(Debug)]
+struct DrawState {
+    pipeline: RenderPipeline,
+    /// Resources bound to the global variables of the vertex shader module.
+    vertex_resources: Vec<Option<Binding>>,
+    /// Resources bound to the global variables of the fragment shader module.
+    fragment_resources: Vec<Option<Binding>>,
+    vertex_buffers: Vec<Option<BufferBinding>>,
+    index_buffer: Option<(BufferBinding, wgt::IndexFormat)>,
+    viewport: crate::Rect<f32>,
+    depth_range: Range<f32>,
+    scissor: crate::Rect<u32>,
+    stencil_reference: u32,
+    blend_constant: [f32; 4],
+}
+
+/// Range of vertices or indices, and of instances, to draw.
+#[derive(Clone, Debug)]
+struct DrawCall {
+    indexed: bool,
+    /// Range of vertices, or of indices if `indexed` is set.
+    elements: Range<u32>,
+    base_vertex: i32,
+    instances: Range<u32>,
+}
+
+#[derive(Debug)]
+enum DrawSize {
+    Direct(DrawCall),
+    Indirect {
+        indexed: bool,
+        buffer: Buffer,
+        offset: wgt::BufferAddress,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct ColorTarget {
+    view: TextureView,
+    resolve_target: Option<TextureView>,
+    ops: crate::AttachmentOps,
+    clear_value: wgt::Color,
+}
+
+#[derive(Clone, Debug)]
+struct DepthStencilTarget {
+    view: TextureView,
+    depth_ops: crate::AttachmentOps,
+    stencil_ops: crate::AttachmentOps,
+    clear_value: (f32, u32),
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    ClearBuffer {
+        dst: Buffer,
+        range: crate::MemoryRange,
+    },
+    CopyBufferToBuffer {
+        src: Buffer,
+        dst: Buffer,
+        copy: crate::BufferCopy,
+    },
+    CopyTextureToTexture {
+        src: Texture,
+        dst: Texture,
+        copy: crate::TextureCopy,
+    },
+    CopyBufferToTexture {
+        src: Buffer,
+        dst: Texture,
+        copy: crate::BufferTextureCopy,
+    },
+    CopyTextureToBuffer {
+        src: Texture,
+        dst: Buffer,
+        copy: crate::BufferTextureCopy,
+    },
+    BeginQuery(QuerySet, u32),
+    WriteTimestamp(QuerySet, u32),
+    ResetQueries(QuerySet, Range<u32>),
+    CopyQueryResults {
+        set: QuerySet,
+        range: Range<u32>,
+        dst: Buffer,
+        offset: wgt::BufferAddress,
+        stride: wgt::BufferAddress,
+    },
+    BeginRenderPass {
+        color_targets: ArrayVec<ColorTarget, { crate::MAX_COLOR_TARGETS }>,
+        depth_stencil_target: Option<DepthStencilTarget>,
+    },
+    EndRenderPass,
+    Dispatch {
+        pipeline: ComputePipeline,
+        /// Resources bound to the global variables of the shader module.
+        resources: Vec<Option<Binding>>,
+        size: DispatchSize,
+    },
+    Draw {
+        state: Box<DrawState>,
+        size: DrawSize,
+    },
+}
+
+#[derive(Default)]
+pub struct CommandBuffer {
+    label: Option<String>,
+    commands: Vec<Command>,
+}
+
+/// Resources bound to the encoder.
+struct State {
+    compute_pipeline: Option<ComputePipeline>,
+    render_pipeline: Option<RenderPipeline>,
+    /// Resources of each bind group, by binding, with the dynamic offsets applied.
+    bind_groups: [Vec<(u32, Binding)>; crate::MAX_BIND_GROUPS],
+    vertex_buffers: Vec<Option<BufferBinding>>,
+    index_buffer: Option<(BufferBinding, wgt::IndexFormat)>,
+    viewport: crate::Rect<f32>,
+    depth_range: Range<f32>,
+    scissor: crate::Rect<u32>,
+    stencil_reference: u32,
+    blend_constant: [f32; 4],
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            compute_pipeline: None,
+            render_pipeline: None,
+            bind_groups: Default::default(),
+            vertex_buffers: Vec::new(),
+            index_buffer: None,
+            viewport: crate::Rect {
+                x: 0.0,
+                y: 0.0,
+                w: 0.0,
+                h: 0.0,
+            },
+            depth_range: 0.0..1.0,
+            scissor: crate::Rect {
+                x: 0,
+                y: 0,
+                w: 0,
+                h: 0,
+            },
+            stencil_reference: 0,
+            blend_constant: [0.0; 4],
+        }
+    }
+}
+
+pub struct CommandEncoder {
+    cmd_buffer: CommandBuffer,
+    state: State,
+}
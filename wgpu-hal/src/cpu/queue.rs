@@ -0,0 +1,447 @@
+use super::{format, raster, Command as C};
+use crate::FormatAspects;
+use std::{ptr, slice};
+
+/// State of the execution within a submission.
+#[derive(Default)]
+struct State {
+    color_targets: Vec<super::ColorTarget>,
+    depth_stencil_target: Option<super::DepthStencilTarget>,
+}
+
+/// Calls `f` with the buffer offset and the relative block coordinates
+/// of each texel block in a buffer-texture copy.
+fn for_each_copy_block(
+    copy: &crate::BufferTextureCopy,
+    block_dimensions: (u32, u32),
+    block_size: usize,
+    mut f: impl FnMut(usize, u32, u32, u32),
+) {
+    let (block_width, block_height) = block_dimensions;
+    let width_blocks = (copy.size.width + block_width - 1) / block_width;
+    let height_blocks = (copy.size.height + block_height - 1) / block_height;
+    let bytes_per_row = copy
+        .buffer_layout
+        .bytes_per_row
+        .map_or(width_blocks as usize * block_size, |bpr| bpr.get() as usize);
+    let rows_per_image = copy
+        .buffer_layout
+        .rows_per_image
+        .map_or(height_blocks, |rpi| rpi.get()) as usize;
+
+    for z in 0..copy.size.depth {
+        for y in 0..height_blocks {
+            let row_offset = copy.buffer_layout.offset as usize
+                + (z as usize * rows_per_image + y as usize) * bytes_per_row;
+            for x in 0..width_blocks {
+                f(row_offset + x as usize * block_size, x, y, z);
+            }
+        }
+    }
+}
+
+impl super::Queue {
+    unsafe fn copy_buffer_texture(
+        buffer: &super::Buffer,
+        texture: &super::Texture,
+        copy: &crate::BufferTextureCopy,
+        to_texture: bool,
+    ) {
+        let desc = &texture.desc;
+        let base = &copy.texture_base;
+        let aspects = base.aspect & desc.aspects();
+        let (block_dimensions, block_size) = if aspects.contains(FormatAspects::DEPTH) {
+            // depth is copied as 32-bit values, optionally packed with stencil
+            ((1, 1), 4)
+        } else {
+            desc.plane_block(aspects)
+        };
+        let (x0, y0) = (
+            base.origin.x / block_dimensions.0,
+            base.origin.y / block_dimensions.1,
+        );
+        let z0 = base.origin.z;
+        let memory = &texture.memory;
+
+        if aspects.contains(FormatAspects::DEPTH) {
+            let depth_sub =
+                desc.subresource(FormatAspects::DEPTH, base.mip_level, base.array_layer);
+            let stencil_sub = if aspects.contains(FormatAspects::STENCIL) {
+                Some(desc.subresource(FormatAspects::STENCIL, base.mip_level, base.array_layer))
+            } else {
+                None
+            };
+            for_each_copy_block(copy, block_dimensions, block_size, |offset, x, y, z| {
+                let depth_range = depth_sub.block_range(x0 + x, y0 + y, z0 + z, 0);
+                let stencil_range =
+                    stencil_sub.map(|sub| sub.block_range(x0 + x, y0 + y, z0 + z, 0));
+                let buffer_range = offset as u64..(offset + block_size) as u64;
+                if to_texture {
+                    let bytes = buffer.slice(buffer_range);
+                    let depth = format::decode_depth(desc.format, bytes);
+                    memory
+                        .slice_mut(depth_range)
+                        .copy_from_slice(&depth.to_ne_bytes());
+                    if let Some(range) = stencil_range {
+                        memory.slice_mut(range)[0] = (read_packed(bytes) >> 24) as u8;
+                    }
+                } else {
+                    let bytes = buffer.slice_mut(buffer_range);
+                    let mut raw = [0; 4];
+                    raw.copy_from_slice(memory.slice(depth_range));
+                    format::encode_depth(desc.format, f32::from_ne_bytes(raw), bytes);
+                    if let Some(range) = stencil_range {
+                        let stencil = memory.slice(range)[0] as u32;
+                        let packed = (read_packed(bytes) & 0xFF_FFFF) | (stencil << 24);
+                        bytes.copy_from_slice(&packed.to_ne_bytes());
+                    }
+                }
+            });
+        } else {
+            let sub = desc.subresource(aspects, base.mip_level, base.array_layer);
+            for_each_copy_block(copy, block_dimensions, block_size, |offset, x, y, z| {
+                let texture_range = sub.block_range(x0 + x, y0 + y, z0 + z, 0);
+                let buffer_range = offset as u64..(offset + block_size) as u64;
+                if to_texture {
+                    memory
+                        .slice_mut(texture_range)
+                        .copy_from_slice(buffer.slice(buffer_range));
+                } else {
+                    buffer
+                        .slice_mut(buffer_range)
+                        .copy_from_slice(memory.slice(texture_range));
+                }
+            });
+        }
+    }
+
+    unsafe fn copy_texture_to_texture(
+        src: &super::Texture,
+        dst: &super::Texture,
+        copy: &crate::TextureCopy,
+    ) {
+        let aspects = copy.src_base.aspect & src.desc.aspects();
+        for &aspect in [
+            FormatAspects::COLOR,
+            FormatAspects::DEPTH,
+            FormatAspects::STENCIL,
+        ]
+        .iter()
+        {
+            if !aspects.contains(aspect) {
+                continue;
+            }
+            let ((block_width, block_height), _) = src.desc.plane_block(aspect);
+            let src_sub =
+                src.desc
+                    .subresource(aspect, copy.src_base.mip_level, copy.src_base.array_layer);
+            let dst_sub =
+                dst.desc
+                    .subresource(aspect, copy.dst_base.mip_level, copy.dst_base.array_layer);
+            let width_blocks = (copy.size.width + block_width - 1) / block_width;
+            let height_blocks = (copy.size.height + block_height - 1) / block_height;
+            let row_size = width_blocks as usize * src_sub.sample_count * src_sub.block_size;
+            let (src_origin, dst_origin) = (&copy.src_base.origin, &copy.dst_base.origin);
+
+            for z in 0..copy.size.depth {
+                for y in 0..height_blocks {
+                    let src_start = src_sub
+                        .block_range(
+                            src_origin.x / block_width,
+                            src_origin.y / block_height + y,
+                            src_origin.z + z,
+                            0,
+                        )
+                        .start;
+                    let dst_start = dst_sub
+                        .block_range(
+                            dst_origin.x / block_width,
+                            dst_origin.y / block_height + y,
+                            dst_origin.z + z,
+                            0,
+                        )
+                        .start;
+                    ptr::copy(
+                        src.memory.ptr().add(src_start),
+                        dst.memory.ptr().add(dst_start),
+                        row_size,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fills the first mip level of all the array layers of a view with a texel pattern.
+    unsafe fn fill_view(view: &super::TextureView, aspect: FormatAspects, pattern: &[u8]) {
+        let level = view.mip_levels.start;
+        for layer in view.array_layers.clone() {
+            let sub = view.texture.desc.subresource(aspect, level, layer);
+            let bytes = view
+                .texture
+                .memory
+                .slice_mut(sub.offset..sub.offset + sub.size());
+            for chunk in bytes.chunks_exact_mut(pattern.len()) {
+                chunk.copy_from_slice(pattern);
+            }
+        }
+    }
+
+    /// Averages all the samples of `src` into `dst`.
+    unsafe fn resolve(src: &super::TextureView, dst: &super::TextureView) {
+        let layers = src.array_layers.clone().zip(dst.array_layers.clone());
+        for (src_layer, dst_layer) in layers {
+            let src_sub =
+                src.texture
+                    .desc
+                    .subresource(FormatAspects::COLOR, src.mip_levels.start, src_layer);
+            let dst_sub =
+                dst.texture
+                    .desc
+                    .subresource(FormatAspects::COLOR, dst.mip_levels.start, dst_layer);
+            let extent = src_sub.extent.min(&dst_sub.extent);
+            for y in 0..extent.height {
+                for x in 0..extent.width {
+                    let mut sum = [0.0; 4];
+                    for sample in 0..src_sub.sample_count as u32 {
+                        let range = src_sub.block_range(x, y, 0, sample);
+                        let texel = format::decode(src.format, src.texture.memory.slice(range));
+                        for (s, v) in sum.iter_mut().zip(texel.to_float().iter()) {
+                            *s += v;
+                        }
+                    }
+                    for s in sum.iter_mut() {
+                        *s /= src_sub.sample_count as f32;
+                    }
+                    let range = dst_sub.block_range(x, y, 0, 0);
+                    format::encode(
+                        dst.format,
+                        format::Texel::Float(sum),
+                        dst.texture.memory.slice_mut(range),
+                    );
+                }
+            }
+        }
+    }
+
+    unsafe fn begin_render_pass(
+        &self,
+        color_targets: &[super::ColorTarget],
+        depth_stencil_target: Option<&super::DepthStencilTarget>,
+    ) {
+        for cat in color_targets {
+            if !cat.ops.contains(crate::AttachmentOps::LOAD) {
+                let mut pattern =
+                    vec![0; cat.view.texture.desc.plane_block(FormatAspects::COLOR).1];
+                let texel = format::Texel::from_color(cat.view.format, cat.clear_value);
+                format::encode(cat.view.format, texel, &mut pattern);
+                Self::fill_view(&cat.view, FormatAspects::COLOR, &pattern);
+            }
+        }
+        if let Some(dsat) = depth_stencil_target {
+            if dsat.view.aspects.contains(FormatAspects::DEPTH)
+                && !dsat.depth_ops.contains(crate::AttachmentOps::LOAD)
+            {
+                let pattern = dsat.clear_value.0.to_ne_bytes();
+                Self::fill_view(&dsat.view, FormatAspects::DEPTH, &pattern);
+            }
+            if dsat.view.aspects.contains(FormatAspects::STENCIL)
+                && !dsat.stencil_ops.contains(crate::AttachmentOps::LOAD)
+            {
+                let pattern = [dsat.clear_value.1 as u8];
+                Self::fill_view(&dsat.view, FormatAspects::STENCIL, &pattern);
+            }
+        }
+    }
+
+    unsafe fn process(&self, command: &C, state: &mut State) {
+        match *command {
+            C::ClearBuffer { ref dst, ref range } => {
+                dst.slice_mut(range.clone()).fill(0);
+            }
+            C::CopyBufferToBuffer {
+                ref src,
+                ref dst,
+                copy,
+            } => {
+                ptr::copy(
+                    src.memory.ptr().add(copy.src_offset as usize),
+                    dst.memory.ptr().add(copy.dst_offset as usize),
+                    copy.size.get() as usize,
+                );
+            }
+            C::CopyTextureToTexture {
+                ref src,
+                ref dst,
+                ref copy,
+            } => {
+                Self::copy_texture_to_texture(src, dst, copy);
+            }
+            C::CopyBufferToTexture {
+                ref src,
+                ref dst,
+                ref copy,
+            } => {
+                Self::copy_buffer_texture(src, dst, copy, true);
+            }
+            C::CopyTextureToBuffer {
+                ref src,
+                ref dst,
+                ref copy,
+            } => {
+                Self::copy_buffer_texture(dst, src, copy, false);
+            }
+            C::BeginQuery(ref set, index) => {
+                //TODO: count samples and pipeline statistics,
+                // once the draws and dispatches are executed.
+                let start = (index * set.stride) as usize;
+                set.values.lock()[start..start + set.stride as usize].fill(0);
+            }
+            C::WriteTimestamp(ref set, index) => {
+                let nanos = self.epoch.elapsed().as_nanos() as u64;
+                set.values.lock()[index as usize] = nanos;
+            }
+            C::ResetQueries(ref set, ref range) => {
+                let stride = set.stride as usize;
+                set.values.lock()[range.start as usize * stride..range.end as usize * stride]
+                    .fill(0);
+            }
+            C::CopyQueryResults {
+                ref set,
+                ref range,
+                ref dst,
+                offset,
+                stride,
+            } => {
+                let values = set.values.lock();
+                let count = set.stride as usize;
+                for (i, query) in range.clone().enumerate() {
+                    let start = query as usize * count;
+                    let bytes = slice::from_raw_parts(
+                        values[start..start + count].as_ptr() as *const u8,
+                        count * crate::QUERY_SIZE as usize,
+                    );
+                    let dst_offset = offset + i as wgt::BufferAddress * stride;
+                    dst.slice_mut(dst_offset..dst_offset + bytes.len() as wgt::BufferAddress)
+                        .copy_from_slice(bytes);
+                }
+            }
+            C::BeginRenderPass {
+                ref color_targets,
+                ref depth_stencil_target,
+            } => {
+                self.begin_render_pass(color_targets, depth_stencil_target.as_ref());
+                state.color_targets.clear();
+                state.color_targets.extend(color_targets.iter().cloned());
+                state.depth_stencil_target = depth_stencil_target.clone();
+            }
+            C::EndRenderPass => {
+                state.depth_stencil_target = None;
+                for cat in state.color_targets.drain(..) {
+                    if let Some(ref resolve_target) = cat.resolve_target {
+                        Self::resolve(&cat.view, resolve_target);
+                    }
+                }
+            }
+            C::Dispatch {
+                ref pipeline,
+                ref resources,
+                ref size,
+            } => {
+                let group_count = match *size {
+                    super::DispatchSize::Direct(count) => count,
+                    super::DispatchSize::Indirect { ref buffer, offset } => {
+                        let bytes = buffer.slice(offset..offset + 12);
+                        [
+                            read_packed(&bytes[0..]),
+                            read_packed(&bytes[4..]),
+                            read_packed(&bytes[8..]),
+                        ]
+                    }
+                };
+                let resources = super::shader::Resource::from_bindings(resources);
+                if let Err(e) =
+                    pipeline
+                        .program
+                        .dispatch(pipeline.entry_point, &resources, group_count)
+                {
+                    log::error!("Compute shader failed: {}", e);
+                }
+            }
+            C::Draw {
+                state: ref draw,
+                ref size,
+            } => {
+                let call = match *size {
+                    super::DrawSize::Direct(ref call) => call.clone(),
+                    super::DrawSize::Indirect {
+                        indexed,
+                        ref buffer,
+                        offset,
+                    } => {
+                        let bytes = buffer.slice(offset..offset + if indexed { 20 } else { 16 });
+                        let args = |i: usize| read_packed(&bytes[i * 4..]);
+                        let (base_vertex, first_instance) = if indexed {
+                            (args(3) as i32, args(4))
+                        } else {
+                            (0, args(3))
+                        };
+                        super::DrawCall {
+                            indexed,
+                            elements: args(2)..args(2) + args(0),
+                            base_vertex,
+                            instances: first_instance..first_instance + args(1),
+                        }
+                    }
+                };
+                if let Err(e) = raster::draw(
+                    draw,
+                    &call,
+                    &state.color_targets,
+                    state.depth_stencil_target.as_ref(),
+                ) {
+                    log::error!("Draw failed: {}", e);
+                }
+            }
+        }
+    }
+}
+
+fn read_packed(bytes: &[u8]) -> u32 {
+    let mut raw = [0; 4];
+    raw.copy_from_slice(&bytes[..4]);
+    u32::from_ne_bytes(raw)
+}
+
+impl crate::Queue<super::Api> for super::Queue {
+    unsafe fn submit(
+        &mut self,
+        command_buffers: &[&super::CommandBuffer],
+        signal_fence: Option<(&mut super::Fence, crate::FenceValue)>,
+    ) -> Result<(), crate::DeviceError> {
+        let mut state = State::default();
+        for cmd_buf in command_buffers.iter() {
+            log::trace!("Executing command buffer {:?}", cmd_buf.label);
+            for command in cmd_buf.commands.iter() {
+                self.process(command, &mut state);
+            }
+        }
+
+        if let Some((fence, value)) = signal_fence {
+            fence.value = value;
+        }
+        Ok(())
+    }
+
+    unsafe fn present(
+        &mut self,
+        surface: &mut super::Surface,
+        texture: super::Texture,
+    ) -> Result<(), crate::SurfaceError> {
+        surface.presented = Some(texture);
+        Ok(())
+    }
+
+    unsafe fn get_timestamp_period(&self) -> f32 {
+        1.0
+    }
+}
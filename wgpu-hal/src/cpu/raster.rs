@@ -0,0 +1,1269 @@
+//! Rasterization of the draw calls.
+//!
+//! Primitives are clipped in clip space against the depth planes and a guard
+//! band, which keeps the window coordinates small enough for the fixed-point
+//! edge functions. The rest of the viewport clipping is done by the scissor.
+//! Coverage is evaluated at the standard sample positions, with 8 bits of
+//! subpixel precision and the top-left fill rule. Everything else, including
+//! the shaders, runs in `f32`, one primitive and one pixel after the other,
+//! so the results are fully deterministic.
+
+use super::{
+    format,
+    shader::{self, Error, FunctionRef, Invocation, Resource, Scalar, Status, Value},
+};
+use crate::FormatAspects;
+use arrayvec::ArrayVec;
+
+use std::collections::{hash_map::Entry, HashMap};
+
+/// Subpixel precision of the window coordinates, in bits.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
+/// Extent of the guard band, relative to the viewport.
+const GUARD_BAND: f32 = 8.0;
+
+type Components = ArrayVec<Scalar, 4>;
+
+/// Output of the vertex shader.
+#[derive(Clone, Debug)]
+struct Vertex {
+    /// Position in clip space.
+    position: [f32; 4],
+    /// User-defined outputs, by location.
+    varyings: Vec<(u32, Components)>,
+}
+
+impl Vertex {
+    /// Weighted sum of vertices. Non-float components are taken from the first one.
+    fn mix(parts: &[(f32, &Vertex)]) -> Self {
+        let mut position = [0.0; 4];
+        for &(weight, vertex) in parts {
+            for (p, v) in position.iter_mut().zip(vertex.position.iter()) {
+                *p += weight * v;
+            }
+        }
+        let varyings = parts[0]
+            .1
+            .varyings
+            .iter()
+            .enumerate()
+            .map(|(index, &(location, _))| {
+                let components = parts
+                    .iter()
+                    .map(|&(weight, vertex)| (weight, &vertex.varyings[index].1));
+                (location, mix_components(components))
+            })
+            .collect();
+        Self { position, varyings }
+    }
+
+    fn varying(&self, location: u32) -> Option<&Components> {
+        self.varyings
+            .iter()
+            .find(|&&(l, _)| l == location)
+            .map(|&(_, ref components)| components)
+    }
+}
+
+fn mix_components<'a>(parts: impl Iterator<Item = (f32, &'a Components)> + Clone) -> Components {
+    let mut result = parts.clone().next().unwrap().1.clone();
+    for (index, component) in result.iter_mut().enumerate() {
+        if let Scalar::Float(_) = *component {
+            let sum = parts
+                .clone()
+                .map(|(weight, components)| match components.get(index) {
+                    Some(&Scalar::Float(value)) => weight * value,
+                    _ => 0.0,
+                })
+                .sum();
+            *component = Scalar::Float(sum);
+        }
+    }
+    result
+}
+
+/// Vertex in window coordinates.
+struct WindowVertex<'v> {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    vertex: &'v Vertex,
+}
+
+/// A pixel covered by a primitive.
+struct Fragment<'v> {
+    x: u32,
+    y: u32,
+    /// Mask of the covered samples.
+    coverage: u32,
+    front_facing: bool,
+    /// Depth at the pixel center, and its slope along X and Y.
+    depth: f32,
+    depth_slope: [f32; 2],
+    inv_w: f32,
+    vertices: [&'v Vertex; 3],
+    /// Weights of the vertices for screen space interpolation.
+    linear: [f32; 3],
+    /// Weights of the vertices for perspective-correct interpolation.
+    perspective: [f32; 3],
+    /// Vertex providing the flat-interpolated values.
+    provoking: &'v Vertex,
+}
+
+impl<'v> Fragment<'v> {
+    /// Builds a fragment out of the linear weights of up to 3 vertices.
+    fn new(
+        (x, y): (u32, u32),
+        coverage: u32,
+        front_facing: bool,
+        vertices: [&WindowVertex<'v>; 3],
+        linear: [f32; 3],
+        provoking: &'v Vertex,
+    ) -> Self {
+        let mut perspective = [0.0; 3];
+        let mut inv_w = 0.0;
+        let mut depth = 0.0;
+        for i in 0..3 {
+            perspective[i] = linear[i] * vertices[i].inv_w;
+            inv_w += perspective[i];
+            depth += linear[i] * vertices[i].z;
+        }
+        if inv_w != 0.0 {
+            for weight in perspective.iter_mut() {
+                *weight /= inv_w;
+            }
+        }
+        Self {
+            x,
+            y,
+            coverage,
+            front_facing,
+            depth,
+            depth_slope: [0.0; 2],
+            inv_w,
+            vertices: [vertices[0].vertex, vertices[1].vertex, vertices[2].vertex],
+            linear,
+            perspective,
+            provoking,
+        }
+    }
+
+    fn interpolate(&self, location: u32, weights: &[f32; 3]) -> Option<Components> {
+        let mut parts = ArrayVec::<(f32, &Components), 3>::new();
+        for (&weight, vertex) in weights.iter().zip(self.vertices.iter()) {
+            parts.push((weight, vertex.varying(location)?));
+        }
+        Some(mix_components(parts.into_iter()))
+    }
+}
+
+/// Values written by the fragment shader.
+#[derive(Debug, Default)]
+struct FragmentOutput {
+    colors: Vec<(u32, Components)>,
+    depth: Option<f32>,
+    sample_mask: Option<u32>,
+}
+
+struct FragmentShader<'a> {
+    env: shader::Environment<'a>,
+    entry_point: usize,
+    uses_derivatives: bool,
+}
+
+/// Edge function of a triangle, in fixed point.
+#[derive(Clone, Copy)]
+struct Edge {
+    origin: (i64, i64),
+    delta: (i64, i64),
+    /// Subtracted on the edges that aren't top or left edges,
+    /// so that they don't own the samples exactly on them.
+    bias: i64,
+}
+
+impl Edge {
+    fn new(a: (i64, i64), b: (i64, i64)) -> Self {
+        let delta = (b.0 - a.0, b.1 - a.1);
+        let top_left = delta.1 < 0 || (delta.1 == 0 && delta.0 > 0);
+        Self {
+            origin: a,
+            delta,
+            bias: if top_left { 0 } else { 1 },
+        }
+    }
+
+    fn eval(&self, (x, y): (i64, i64)) -> i64 {
+        self.delta.0 * (y - self.origin.1) - self.delta.1 * (x - self.origin.0)
+    }
+
+    fn contains(&self, point: (i64, i64)) -> bool {
+        self.eval(point) - self.bias >= 0
+    }
+}
+
+/// Returns the standard sample positions, in fixed point relative to the pixel corner.
+fn sample_positions(count: u32) -> Vec<(i64, i64)> {
+    let sixteenths: &[(i64, i64)] = match count {
+        2 => &[(12, 12), (4, 4)],
+        4 => &[(6, 2), (14, 6), (2, 10), (10, 14)],
+        8 => &[
+            (9, 5),
+            (7, 11),
+            (13, 9),
+            (5, 3),
+            (3, 13),
+            (1, 7),
+            (11, 15),
+            (15, 1),
+        ],
+        _ => &[(8, 8)],
+    };
+    (0..count as usize)
+        .map(|i| {
+            let (x, y) = sixteenths[i % sixteenths.len()];
+            (x * SUBPIXEL_ONE / 16, y * SUBPIXEL_ONE / 16)
+        })
+        .collect()
+}
+
+fn texel_of(components: &Components) -> format::Texel {
+    let mut texel = match components.first() {
+        Some(&Scalar::Sint(_)) => format::Texel::Sint([0, 0, 0, 1]),
+        Some(&Scalar::Uint(_)) => format::Texel::Uint([0, 0, 0, 1]),
+        _ => format::Texel::Float([0.0, 0.0, 0.0, 1.0]),
+    };
+    for (i, component) in components.iter().enumerate() {
+        match texel {
+            format::Texel::Sint(ref mut v) => v[i] = component.to_bits() as i32,
+            format::Texel::Uint(ref mut v) => v[i] = component.to_bits(),
+            format::Texel::Float(ref mut v) => match *component {
+                Scalar::Float(value) => v[i] = value,
+                _ => v[i] = 0.0,
+            },
+        }
+    }
+    texel
+}
+
+fn blend_factor(
+    factor: wgt::BlendFactor,
+    channel: usize,
+    src: &[f32; 4],
+    dst: &[f32; 4],
+    constant: &[f32; 4],
+) -> f32 {
+    use wgt::BlendFactor as Bf;
+    match factor {
+        Bf::Zero => 0.0,
+        Bf::One => 1.0,
+        Bf::Src => src[channel],
+        Bf::OneMinusSrc => 1.0 - src[channel],
+        Bf::SrcAlpha => src[3],
+        Bf::OneMinusSrcAlpha => 1.0 - src[3],
+        Bf::Dst => dst[channel],
+        Bf::OneMinusDst => 1.0 - dst[channel],
+        Bf::DstAlpha => dst[3],
+        Bf::OneMinusDstAlpha => 1.0 - dst[3],
+        Bf::SrcAlphaSaturated if channel == 3 => 1.0,
+        Bf::SrcAlphaSaturated => src[3].min(1.0 - dst[3]),
+        Bf::Constant => constant[channel],
+        Bf::OneMinusConstant => 1.0 - constant[channel],
+    }
+}
+
+fn blend(state: &wgt::BlendState, src: [f32; 4], dst: [f32; 4], constant: &[f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for (channel, value) in result.iter_mut().enumerate() {
+        let component = if channel == 3 {
+            &state.alpha
+        } else {
+            &state.color
+        };
+        let s = src[channel] * blend_factor(component.src_factor, channel, &src, &dst, constant);
+        let d = dst[channel] * blend_factor(component.dst_factor, channel, &src, &dst, constant);
+        *value = match component.operation {
+            wgt::BlendOperation::Add => s + d,
+            wgt::BlendOperation::Subtract => s - d,
+            wgt::BlendOperation::ReverseSubtract => d - s,
+            wgt::BlendOperation::Min => src[channel].min(dst[channel]),
+            wgt::BlendOperation::Max => src[channel].max(dst[channel]),
+        };
+    }
+    result
+}
+
+fn stencil_op(op: wgt::StencilOperation, value: u8, reference: u8) -> u8 {
+    use wgt::StencilOperation as So;
+    match op {
+        So::Keep => value,
+        So::Zero => 0,
+        So::Replace => reference,
+        So::Invert => !value,
+        So::IncrementClamp => value.saturating_add(1),
+        So::DecrementClamp => value.saturating_sub(1),
+        So::IncrementWrap => value.wrapping_add(1),
+        So::DecrementWrap => value.wrapping_sub(1),
+    }
+}
+
+struct Rasterizer<'a> {
+    state: &'a super::DrawState,
+    pipeline: &'a super::RenderPipeline,
+    vertex_env: shader::Environment<'a>,
+    fragment_shader: Option<FragmentShader<'a>>,
+    color_targets: &'a [super::ColorTarget],
+    depth_stencil_target: Option<&'a super::DepthStencilTarget>,
+    /// Intersection of the scissor rectangle and the targets, as `[x0, y0, x1, y1]`.
+    clip_rect: [u32; 4],
+    samples: Vec<(i64, i64)>,
+}
+
+impl<'a> Rasterizer<'a> {
+    fn read_index(&self, element: u32) -> Option<u32> {
+        let &(ref binding, format) = self.state.index_buffer.as_ref()?;
+        let size = match format {
+            wgt::IndexFormat::Uint16 => 2,
+            wgt::IndexFormat::Uint32 => 4,
+        };
+        let offset = element as wgt::BufferAddress * size;
+        if offset + size > binding.size {
+            return None;
+        }
+        let start = binding.offset + offset;
+        let bytes = unsafe { binding.buffer.slice(start..start + size) };
+        let (index, restart) = match format {
+            wgt::IndexFormat::Uint16 => (
+                u16::from_ne_bytes([bytes[0], bytes[1]]) as u32,
+                u16::MAX as u32,
+            ),
+            wgt::IndexFormat::Uint32 => (
+                u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                u32::MAX,
+            ),
+        };
+        if index == restart && self.pipeline.primitive.topology.is_strip() {
+            None
+        } else {
+            Some(index)
+        }
+    }
+
+    fn vertex_input(
+        &self,
+        vertex_index: u32,
+        instance_index: u32,
+        binding: &naga::Binding,
+        inner: &naga::TypeInner,
+    ) -> Result<Value, Error> {
+        let location = match *binding {
+            naga::Binding::BuiltIn(naga::BuiltIn::VertexIndex) => {
+                return Ok(Value::Scalar(Scalar::Uint(vertex_index)))
+            }
+            naga::Binding::BuiltIn(naga::BuiltIn::InstanceIndex) => {
+                return Ok(Value::Scalar(Scalar::Uint(instance_index)))
+            }
+            naga::Binding::BuiltIn(_) => return Err(Error::Unsupported("vertex shader inputs")),
+            naga::Binding::Location { location, .. } => location,
+        };
+        let size = match *inner {
+            naga::TypeInner::Vector { size, .. } => size as usize,
+            _ => 1,
+        };
+
+        for (buffer_index, layout) in self.pipeline.vertex_buffers.iter().enumerate() {
+            let attribute = match layout
+                .attributes
+                .iter()
+                .find(|attribute| attribute.shader_location == location)
+            {
+                Some(attribute) => attribute,
+                None => continue,
+            };
+            let index = match layout.step_mode {
+                wgt::VertexStepMode::Vertex => vertex_index,
+                wgt::VertexStepMode::Instance => instance_index,
+            };
+            let attribute_size = attribute.format.size();
+            let offset = index as wgt::BufferAddress * layout.stride + attribute.offset;
+            let texel = match self.state.vertex_buffers.get(buffer_index) {
+                Some(&Some(ref vb)) if offset + attribute_size <= vb.size => {
+                    let start = vb.offset + offset;
+                    let bytes = unsafe { vb.buffer.slice(start..start + attribute_size) };
+                    format::decode_vertex(attribute.format, bytes)
+                }
+                // Out-of-bounds fetches return zeros.
+                _ => format::decode_vertex(attribute.format, &[0; 32]),
+            };
+            let texel = texel.ok_or(Error::Unsupported("64-bit vertex formats"))?;
+            let components = shader::texel_scalars(texel)
+                .iter()
+                .cloned()
+                .take(size)
+                .collect();
+            return Ok(Value::from_components(components));
+        }
+        Err(Error::InvalidOperand("a vertex attribute"))
+    }
+
+    fn shade_vertex(&self, vertex_index: u32, instance_index: u32) -> Result<Vertex, Error> {
+        let stage = &self.pipeline.vertex_stage;
+        let arguments = self
+            .vertex_env
+            .entry_arguments(stage.index, &mut |binding, inner| {
+                self.vertex_input(vertex_index, instance_index, binding, inner)
+            })?;
+        let mut invocation = Invocation::new(
+            &self.vertex_env,
+            FunctionRef::EntryPoint(stage.index),
+            arguments,
+        );
+        let result = match invocation.run(&mut [])? {
+            Status::Return(result) => result,
+            _ => return Err(Error::InvalidOperand("a vertex shader result")),
+        };
+
+        let mut vertex = Vertex {
+            position: [0.0; 4],
+            varyings: Vec::new(),
+        };
+        for (binding, value) in self.vertex_env.entry_outputs(stage.index, result)? {
+            match *binding {
+                naga::Binding::BuiltIn(naga::BuiltIn::Position) => {
+                    for (p, c) in vertex.position.iter_mut().zip(value.components()?) {
+                        *p = c.as_float()?;
+                    }
+                }
+                naga::Binding::BuiltIn(_) => {}
+                naga::Binding::Location { location, .. } => {
+                    vertex.varyings.push((location, value.components()?));
+                }
+            }
+        }
+        Ok(vertex)
+    }
+
+    /// Clip planes, as coefficients of the clip space position, positive inside.
+    fn clip_planes(&self) -> ArrayVec<[f32; 4], 6> {
+        let mut planes = ArrayVec::new();
+        planes.push([1.0, 0.0, 0.0, GUARD_BAND]);
+        planes.push([-1.0, 0.0, 0.0, GUARD_BAND]);
+        planes.push([0.0, 1.0, 0.0, GUARD_BAND]);
+        planes.push([0.0, -1.0, 0.0, GUARD_BAND]);
+        if !self.pipeline.primitive.unclipped_depth {
+            planes.push([0.0, 0.0, 1.0, 0.0]);
+            planes.push([0.0, 0.0, -1.0, 1.0]);
+        }
+        planes
+    }
+
+    fn to_window<'v>(&self, vertex: &'v Vertex) -> WindowVertex<'v> {
+        let [x, y, z, w] = vertex.position;
+        let inv_w = 1.0 / w;
+        let viewport = &self.state.viewport;
+        let depth = &self.state.depth_range;
+        WindowVertex {
+            x: viewport.x + (x * inv_w + 1.0) * 0.5 * viewport.w,
+            y: viewport.y + (1.0 - y * inv_w) * 0.5 * viewport.h,
+            z: depth.start + z * inv_w * (depth.end - depth.start),
+            inv_w,
+            vertex,
+        }
+    }
+
+    fn contains_pixel(&self, x: i64, y: i64) -> bool {
+        let [x0, y0, x1, y1] = self.clip_rect;
+        x >= x0 as i64 && x < x1 as i64 && y >= y0 as i64 && y < y1 as i64
+    }
+
+    fn all_samples(&self) -> u32 {
+        !0 >> (32 - self.samples.len() as u32)
+    }
+
+    fn draw_point(&self, vertex: &Vertex) -> Result<(), Error> {
+        let inside = self.clip_planes().iter().all(|plane| {
+            plane
+                .iter()
+                .zip(vertex.position.iter())
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                >= 0.0
+        });
+        if inside {
+            let window = self.to_window(vertex);
+            self.point(&window, vertex, true)?;
+        }
+        Ok(())
+    }
+
+    fn point(&self, v: &WindowVertex, provoking: &Vertex, front_facing: bool) -> Result<(), Error> {
+        let (x, y) = (v.x.floor() as i64, v.y.floor() as i64);
+        if !self.contains_pixel(x, y) {
+            return Ok(());
+        }
+        let fragment = Fragment::new(
+            (x as u32, y as u32),
+            self.all_samples(),
+            front_facing,
+            [v, v, v],
+            [1.0, 0.0, 0.0],
+            provoking,
+        );
+        self.process(&[fragment])
+    }
+
+    fn draw_line(&self, a: &Vertex, b: &Vertex) -> Result<(), Error> {
+        let (mut t0, mut t1) = (0.0f32, 1.0f32);
+        for plane in self.clip_planes() {
+            let distance = |v: &Vertex| -> f32 {
+                plane
+                    .iter()
+                    .zip(v.position.iter())
+                    .map(|(p, c)| p * c)
+                    .sum()
+            };
+            let (da, db) = (distance(a), distance(b));
+            if da < 0.0 && db < 0.0 {
+                return Ok(());
+            }
+            if da < 0.0 {
+                t0 = t0.max(da / (da - db));
+            } else if db < 0.0 {
+                t1 = t1.min(da / (da - db));
+            }
+        }
+        if t0 >= t1 {
+            return Ok(());
+        }
+        let start = Vertex::mix(&[(1.0 - t0, a), (t0, b)]);
+        let end = Vertex::mix(&[(1.0 - t1, a), (t1, b)]);
+        self.line(&self.to_window(&start), &self.to_window(&end), a, true)
+    }
+
+    /// Rasterizes a line, lighting up one pixel per column or row along its major axis.
+    fn line(
+        &self,
+        a: &WindowVertex,
+        b: &WindowVertex,
+        provoking: &Vertex,
+        front_facing: bool,
+    ) -> Result<(), Error> {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let x_major = dx.abs() >= dy.abs();
+        let (major_start, length) = if x_major { (a.x, dx) } else { (a.y, dy) };
+        if length == 0.0 {
+            return Ok(());
+        }
+        let (low, high) = if length > 0.0 {
+            (major_start, major_start + length)
+        } else {
+            (major_start + length, major_start)
+        };
+        // Pixels with their center in `[low, high)`.
+        let first = (low - 0.5).ceil() as i64;
+        let last = (high - 0.5).ceil() as i64;
+        for major in first..last {
+            let t = (major as f32 + 0.5 - major_start) / length;
+            let (x, y) = if x_major {
+                (major, (a.y + t * dy).floor() as i64)
+            } else {
+                ((a.x + t * dx).floor() as i64, major)
+            };
+            if !self.contains_pixel(x, y) {
+                continue;
+            }
+            let fragment = Fragment::new(
+                (x as u32, y as u32),
+                self.all_samples(),
+                front_facing,
+                [a, b, b],
+                [1.0 - t, t, 0.0],
+                provoking,
+            );
+            self.process(&[fragment])?;
+        }
+        Ok(())
+    }
+
+    fn draw_triangle(&self, vertices: [&Vertex; 3]) -> Result<(), Error> {
+        let mut polygon = vertices.iter().map(|&v| v.clone()).collect::<Vec<_>>();
+        for plane in self.clip_planes() {
+            let distance = |v: &Vertex| -> f32 {
+                plane
+                    .iter()
+                    .zip(v.position.iter())
+                    .map(|(p, c)| p * c)
+                    .sum()
+            };
+            if polygon.iter().all(|v| distance(v) >= 0.0) {
+                continue;
+            }
+            let mut clipped = Vec::with_capacity(polygon.len() + 1);
+            for (i, a) in polygon.iter().enumerate() {
+                let b = &polygon[(i + 1) % polygon.len()];
+                let (da, db) = (distance(a), distance(b));
+                if da >= 0.0 {
+                    clipped.push(a.clone());
+                }
+                if (da >= 0.0) != (db >= 0.0) {
+                    let t = da / (da - db);
+                    clipped.push(Vertex::mix(&[(1.0 - t, a), (t, b)]));
+                }
+            }
+            polygon = clipped;
+        }
+        if polygon.len() < 3 {
+            return Ok(());
+        }
+
+        let window = polygon
+            .iter()
+            .map(|v| self.to_window(v))
+            .collect::<Vec<_>>();
+        // Twice the signed area, positive if the polygon is counter-clockwise
+        // in NDC, since window space is upside down.
+        let area = (0..window.len())
+            .map(|i| {
+                let (a, b) = (&window[i], &window[(i + 1) % window.len()]);
+                (b.x - a.x) * (b.y + a.y)
+            })
+            .sum::<f32>();
+        let ccw = area > 0.0;
+        let primitive = &self.pipeline.primitive;
+        let front_facing = ccw == (primitive.front_face == wgt::FrontFace::Ccw);
+        match primitive.cull_mode {
+            Some(wgt::Face::Front) if front_facing => return Ok(()),
+            Some(wgt::Face::Back) if !front_facing => return Ok(()),
+            _ => {}
+        }
+
+        let provoking = vertices[0];
+        match primitive.polygon_mode {
+            wgt::PolygonMode::Fill => {
+                for i in 1..window.len() - 1 {
+                    self.fill_triangle(
+                        [&window[0], &window[i], &window[i + 1]],
+                        provoking,
+                        front_facing,
+                    )?;
+                }
+            }
+            wgt::PolygonMode::Line => {
+                for (i, a) in window.iter().enumerate() {
+                    let b = &window[(i + 1) % window.len()];
+                    self.line(a, b, provoking, front_facing)?;
+                }
+            }
+            wgt::PolygonMode::Point => {
+                for v in window.iter() {
+                    self.point(v, provoking, front_facing)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the constant depth bias of a triangle with the given depth slope.
+    fn depth_bias(&self, max_depth: f32, max_slope: f32) -> f32 {
+        let (state, target) = match (&self.pipeline.depth_stencil, self.depth_stencil_target) {
+            (&Some(ref state), Some(target)) if state.bias.is_enabled() => (state, target),
+            _ => return 0.0,
+        };
+        // Minimum resolvable difference of the depth format.
+        let unit = match target.view.texture.desc.format {
+            wgt::TextureFormat::Depth32Float => {
+                let exponent = ((max_depth.to_bits() >> 23) & 0xFF) as i32 - 127;
+                2f32.powi(exponent - 23)
+            }
+            _ => 1.0 / (1 << 24) as f32,
+        };
+        let bias = &state.bias;
+        let value = bias.constant as f32 * unit + bias.slope_scale * max_slope;
+        if bias.clamp > 0.0 {
+            value.min(bias.clamp)
+        } else if bias.clamp < 0.0 {
+            value.max(bias.clamp)
+        } else {
+            value
+        }
+    }
+
+    fn fill_triangle(
+        &self,
+        mut vertices: [&WindowVertex; 3],
+        provoking: &Vertex,
+        front_facing: bool,
+    ) -> Result<(), Error> {
+        let fixed = |v: &WindowVertex| {
+            (
+                (v.x * SUBPIXEL_ONE as f32).round() as i64,
+                (v.y * SUBPIXEL_ONE as f32).round() as i64,
+            )
+        };
+        let mut points = [fixed(vertices[0]), fixed(vertices[1]), fixed(vertices[2])];
+        let mut area = Edge::new(points[0], points[1]).eval(points[2]);
+        if area == 0 {
+            return Ok(());
+        }
+        // Make the edge functions positive inside.
+        if area < 0 {
+            vertices.swap(1, 2);
+            points.swap(1, 2);
+            area = -area;
+        }
+        let edges = [
+            Edge::new(points[1], points[2]),
+            Edge::new(points[2], points[0]),
+            Edge::new(points[0], points[1]),
+        ];
+
+        // Depth plane, in window coordinates.
+        let area_f = area as f32 / (SUBPIXEL_ONE * SUBPIXEL_ONE) as f32;
+        let delta = |i: usize| {
+            (
+                (points[i].0 - points[0].0) as f32 / SUBPIXEL_ONE as f32,
+                (points[i].1 - points[0].1) as f32 / SUBPIXEL_ONE as f32,
+                vertices[i].z - vertices[0].z,
+            )
+        };
+        let ((x1, y1, z1), (x2, y2, z2)) = (delta(1), delta(2));
+        let depth_slope = [(z1 * y2 - z2 * y1) / area_f, (z2 * x1 - z1 * x2) / area_f];
+        let max_depth = vertices.iter().fold(0.0f32, |max, v| max.max(v.z.abs()));
+        let bias = self.depth_bias(max_depth, depth_slope[0].abs().max(depth_slope[1].abs()));
+
+        let [clip_x0, clip_y0, clip_x1, clip_y1] = self.clip_rect;
+        let min = |i: usize| points.iter().map(|p| [p.0, p.1][i]).min().unwrap();
+        let max = |i: usize| points.iter().map(|p| [p.0, p.1][i]).max().unwrap();
+        let x0 = (min(0) >> SUBPIXEL_BITS).max(clip_x0 as i64);
+        let y0 = (min(1) >> SUBPIXEL_BITS).max(clip_y0 as i64);
+        let x1 = ((max(0) >> SUBPIXEL_BITS) + 1).min(clip_x1 as i64);
+        let y1 = ((max(1) >> SUBPIXEL_BITS) + 1).min(clip_y1 as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return Ok(());
+        }
+
+        // Shading in quads, for the derivatives, covers whole 2x2 blocks.
+        let quads = match self.fragment_shader {
+            Some(ref shader) => shader.uses_derivatives,
+            None => false,
+        };
+        let (step, x_start, y_start) = if quads {
+            (2, x0 & !1, y0 & !1)
+        } else {
+            (1, x0, y0)
+        };
+
+        let mut fragments = Vec::with_capacity(4);
+        for y_block in (y_start..y1).step_by(step) {
+            for x_block in (x_start..x1).step_by(step) {
+                fragments.clear();
+                for lane in 0..step * step {
+                    let (x, y) = (x_block + (lane & 1) as i64, y_block + (lane >> 1) as i64);
+                    let mut coverage = 0;
+                    if self.contains_pixel(x, y) {
+                        for (index, &(sx, sy)) in self.samples.iter().enumerate() {
+                            let sample = (x * SUBPIXEL_ONE + sx, y * SUBPIXEL_ONE + sy);
+                            if edges.iter().all(|edge| edge.contains(sample)) {
+                                coverage |= 1 << index;
+                            }
+                        }
+                    }
+                    let center = (
+                        x * SUBPIXEL_ONE + SUBPIXEL_ONE / 2,
+                        y * SUBPIXEL_ONE + SUBPIXEL_ONE / 2,
+                    );
+                    let mut linear = [0.0; 3];
+                    for (weight, edge) in linear.iter_mut().zip(edges.iter()) {
+                        *weight = edge.eval(center) as f32 / area as f32;
+                    }
+                    let mut fragment = Fragment::new(
+                        (x.max(0) as u32, y.max(0) as u32),
+                        coverage,
+                        front_facing,
+                        vertices,
+                        linear,
+                        provoking,
+                    );
+                    fragment.depth += bias;
+                    fragment.depth_slope = depth_slope;
+                    fragments.push(fragment);
+                }
+                if fragments.iter().any(|f| f.coverage != 0) {
+                    self.process(&fragments)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn fragment_input(&self, fragment: &Fragment, binding: &naga::Binding) -> Result<Value, Error> {
+        let (location, interpolation) = match *binding {
+            naga::Binding::BuiltIn(naga::BuiltIn::Position) => {
+                let position = [
+                    fragment.x as f32 + 0.5,
+                    fragment.y as f32 + 0.5,
+                    fragment.depth,
+                    fragment.inv_w,
+                ];
+                return Ok(Value::Vector(
+                    position.iter().map(|&c| Scalar::Float(c)).collect(),
+                ));
+            }
+            naga::Binding::BuiltIn(naga::BuiltIn::FrontFacing) => {
+                return Ok(Value::Scalar(Scalar::Bool(fragment.front_facing)))
+            }
+            naga::Binding::BuiltIn(naga::BuiltIn::SampleIndex) => {
+                return Ok(Value::Scalar(Scalar::Uint(0)))
+            }
+            naga::Binding::BuiltIn(naga::BuiltIn::SampleMask) => {
+                return Ok(Value::Scalar(Scalar::Uint(fragment.coverage)))
+            }
+            naga::Binding::BuiltIn(_) => return Err(Error::Unsupported("fragment shader inputs")),
+            naga::Binding::Location {
+                location,
+                interpolation,
+                ..
+            } => (location, interpolation),
+        };
+        let components = match interpolation {
+            Some(naga::Interpolation::Flat) => fragment.provoking.varying(location).cloned(),
+            Some(naga::Interpolation::Linear) => fragment.interpolate(location, &fragment.linear),
+            _ => fragment.interpolate(location, &fragment.perspective),
+        };
+        components
+            .map(Value::from_components)
+            .ok_or(Error::InvalidOperand("a matching vertex output"))
+    }
+
+    fn fragment_output(
+        &self,
+        shader: &FragmentShader,
+        status: Option<Status>,
+    ) -> Result<Option<FragmentOutput>, Error> {
+        let result = match status {
+            Some(Status::Return(result)) => result,
+            Some(Status::Kill) => return Ok(None),
+            _ => return Err(Error::InvalidOperand("a fragment shader result")),
+        };
+        let mut output = FragmentOutput::default();
+        for (binding, value) in shader.env.entry_outputs(shader.entry_point, result)? {
+            match *binding {
+                naga::Binding::BuiltIn(naga::BuiltIn::FragDepth) => {
+                    output.depth = Some(value.scalar()?.as_float()?);
+                }
+                naga::Binding::BuiltIn(naga::BuiltIn::SampleMask) => {
+                    output.sample_mask = Some(value.scalar()?.to_bits());
+                }
+                naga::Binding::BuiltIn(_) => {}
+                naga::Binding::Location { location, .. } => {
+                    output.colors.push((location, value.components()?));
+                }
+            }
+        }
+        Ok(Some(output))
+    }
+
+    /// Shades a pixel or a 2x2 quad of pixels, and writes the results to the targets.
+    fn process(&self, fragments: &[Fragment]) -> Result<(), Error> {
+        let shader = match self.fragment_shader {
+            Some(ref shader) => shader,
+            None => {
+                for fragment in fragments.iter().filter(|f| f.coverage != 0) {
+                    self.merge(fragment, &FragmentOutput::default());
+                }
+                return Ok(());
+            }
+        };
+        let function = FunctionRef::EntryPoint(shader.entry_point);
+        let mut invocations = Vec::with_capacity(fragments.len());
+        for (lane, fragment) in fragments.iter().enumerate() {
+            let arguments = shader
+                .env
+                .entry_arguments(shader.entry_point, &mut |binding, _| {
+                    self.fragment_input(fragment, binding)
+                })?;
+            invocations.push(
+                Invocation::new(&shader.env, function, arguments)
+                    .with_lane(lane, fragment.coverage == 0),
+            );
+        }
+
+        if shader.uses_derivatives && fragments.len() == 4 {
+            Invocation::run_quad(&mut invocations)?;
+        } else {
+            for (invocation, fragment) in invocations.iter_mut().zip(fragments.iter()) {
+                if fragment.coverage != 0 {
+                    invocation.run(&mut [])?;
+                }
+            }
+        }
+
+        for (invocation, fragment) in invocations.into_iter().zip(fragments.iter()) {
+            if fragment.coverage == 0 {
+                continue;
+            }
+            if let Some(output) = self.fragment_output(shader, invocation.into_status())? {
+                self.merge(fragment, &output);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the depth and stencil tests of a sample, and updates the depth-stencil target.
+    fn depth_stencil_test(&self, fragment: &Fragment, depth: f32, sample: u32) -> bool {
+        let (state, target) = match (&self.pipeline.depth_stencil, self.depth_stencil_target) {
+            (&Some(ref state), Some(target)) => (state, target),
+            _ => return true,
+        };
+        let view = &target.view;
+        let desc = &view.texture.desc;
+        let (level, layer) = (view.mip_levels.start, view.array_layers.start);
+        let memory = &view.texture.memory;
+
+        let stencil = if view.aspects.contains(FormatAspects::STENCIL) && state.stencil.is_enabled()
+        {
+            let sub = desc.subresource(FormatAspects::STENCIL, level, layer);
+            let range = sub.block_range(fragment.x, fragment.y, 0, sample);
+            let face = if fragment.front_facing {
+                &state.stencil.front
+            } else {
+                &state.stencil.back
+            };
+            Some((range, face))
+        } else {
+            None
+        };
+        let depth_range = if view.aspects.contains(FormatAspects::DEPTH) {
+            let sub = desc.subresource(FormatAspects::DEPTH, level, layer);
+            Some(sub.block_range(fragment.x, fragment.y, 0, sample))
+        } else {
+            None
+        };
+
+        let reference = self.state.stencil_reference as u8;
+        let update_stencil = |op: wgt::StencilOperation| {
+            if let Some((ref range, _)) = stencil {
+                let stored = unsafe { &mut memory.slice_mut(range.clone())[0] };
+                let write_mask = state.stencil.write_mask as u8;
+                let value = stencil_op(op, *stored, reference);
+                *stored = (*stored & !write_mask) | (value & write_mask);
+            }
+        };
+
+        if let Some((ref range, face)) = stencil {
+            let read_mask = state.stencil.read_mask as u8;
+            let stored = unsafe { memory.slice(range.clone())[0] };
+            let passed = shader::compare(
+                face.compare,
+                (reference & read_mask) as f32,
+                (stored & read_mask) as f32,
+            );
+            if !passed {
+                update_stencil(face.fail_op);
+                return false;
+            }
+        }
+
+        if let Some(range) = depth_range {
+            // The depth is converted to the format of the target before the test.
+            let depth = match desc.format {
+                wgt::TextureFormat::Depth32Float => depth,
+                _ => (depth.clamp(0.0, 1.0) * 16_777_215.0).round() / 16_777_215.0,
+            };
+            let bytes = unsafe { memory.slice_mut(range) };
+            let mut raw = [0; 4];
+            raw.copy_from_slice(bytes);
+            if !shader::compare(state.depth_compare, depth, f32::from_ne_bytes(raw)) {
+                if let Some((_, face)) = stencil {
+                    update_stencil(face.depth_fail_op);
+                }
+                return false;
+            }
+            if state.depth_write_enabled {
+                bytes.copy_from_slice(&depth.to_ne_bytes());
+            }
+        }
+
+        if let Some((_, face)) = stencil {
+            update_stencil(face.pass_op);
+        }
+        true
+    }
+
+    /// Runs the per-sample tests of a shaded fragment, and writes its colors.
+    fn merge(&self, fragment: &Fragment, output: &FragmentOutput) {
+        let multisample = &self.pipeline.multisample;
+        let mut mask =
+            fragment.coverage & multisample.mask as u32 & output.sample_mask.unwrap_or(!0);
+        let color = |location: u32| {
+            output
+                .colors
+                .iter()
+                .find(|&&(l, _)| l == location)
+                .map(|&(_, ref components)| texel_of(components))
+        };
+        if multisample.alpha_to_coverage_enabled {
+            let alpha = color(0).map_or(1.0, |texel| texel.to_float()[3]);
+            let count = self.samples.len() as f32;
+            let covered = (alpha.clamp(0.0, 1.0) * count + 0.5) as u32;
+            mask &= !(!0u64 << covered) as u32;
+        }
+        if mask == 0 {
+            return;
+        }
+
+        let colors = (0..self.color_targets.len() as u32)
+            .map(color)
+            .collect::<ArrayVec<_, { crate::MAX_COLOR_TARGETS }>>();
+        let (min_depth, max_depth) = {
+            let range = &self.state.depth_range;
+            (range.start.min(range.end), range.start.max(range.end))
+        };
+
+        for (sample, &(sx, sy)) in self.samples.iter().enumerate() {
+            if mask & (1 << sample) == 0 {
+                continue;
+            }
+            let depth = output.depth.unwrap_or_else(|| {
+                let offset = |s: i64| (s - SUBPIXEL_ONE / 2) as f32 / SUBPIXEL_ONE as f32;
+                fragment.depth
+                    + fragment.depth_slope[0] * offset(sx)
+                    + fragment.depth_slope[1] * offset(sy)
+            });
+            if !self.depth_stencil_test(fragment, depth.clamp(min_depth, max_depth), sample as u32)
+            {
+                continue;
+            }
+
+            let targets = self
+                .color_targets
+                .iter()
+                .zip(self.pipeline.color_targets.iter());
+            for ((target, state), texel) in targets.zip(colors.iter()) {
+                let texel = match *texel {
+                    Some(texel) if !state.write_mask.is_empty() => texel,
+                    _ => continue,
+                };
+                self.write_color(target, state, fragment, sample as u32, texel);
+            }
+        }
+    }
+
+    fn write_color(
+        &self,
+        target: &super::ColorTarget,
+        state: &wgt::ColorTargetState,
+        fragment: &Fragment,
+        sample: u32,
+        texel: format::Texel,
+    ) {
+        let view = &target.view;
+        if !format::is_supported(view.format) {
+            return;
+        }
+        let sub = view.texture.desc.subresource(
+            FormatAspects::COLOR,
+            view.mip_levels.start,
+            view.array_layers.start,
+        );
+        let bytes = unsafe {
+            view.texture
+                .memory
+                .slice_mut(sub.block_range(fragment.x, fragment.y, 0, sample))
+        };
+        let stored = format::decode(view.format, bytes);
+        let mut value = match (texel, stored) {
+            (format::Texel::Float(src), format::Texel::Float(dst)) => match state.blend {
+                Some(ref blend_state) => {
+                    let clamp = |mut color: [f32; 4]| {
+                        if let Some((min, max)) = format::normalized_range(view.format) {
+                            for c in color.iter_mut() {
+                                *c = c.clamp(min, max);
+                            }
+                        }
+                        color
+                    };
+                    let constant = clamp(self.state.blend_constant);
+                    format::Texel::Float(blend(blend_state, clamp(src), dst, &constant))
+                }
+                None => texel,
+            },
+            _ => texel,
+        };
+
+        if state.write_mask != wgt::ColorWrites::ALL {
+            let enabled = |channel: usize| state.write_mask.bits() & (1 << channel) != 0;
+            match (&mut value, stored) {
+                (&mut format::Texel::Float(ref mut v), format::Texel::Float(old)) => {
+                    for c in 0..4 {
+                        if !enabled(c) {
+                            v[c] = old[c];
+                        }
+                    }
+                }
+                (&mut format::Texel::Sint(ref mut v), format::Texel::Sint(old)) => {
+                    for c in 0..4 {
+                        if !enabled(c) {
+                            v[c] = old[c];
+                        }
+                    }
+                }
+                (&mut format::Texel::Uint(ref mut v), format::Texel::Uint(old)) => {
+                    for c in 0..4 {
+                        if !enabled(c) {
+                            v[c] = old[c];
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        format::encode(view.format, value, bytes);
+    }
+
+    /// Assembles the primitives of an instance out of its vertices.
+    ///
+    /// `elements` refers to `vertices` by index, with `None` restarting a strip.
+    fn draw_primitives(
+        &self,
+        vertices: &[Vertex],
+        elements: &[Option<usize>],
+    ) -> Result<(), Error> {
+        let vertex = |element: Option<usize>| element.map(|index| &vertices[index]);
+        match self.pipeline.primitive.topology {
+            wgt::PrimitiveTopology::PointList => {
+                for &element in elements {
+                    if let Some(v) = vertex(element) {
+                        self.draw_point(v)?;
+                    }
+                }
+            }
+            wgt::PrimitiveTopology::LineList => {
+                for pair in elements.chunks_exact(2) {
+                    if let (Some(a), Some(b)) = (vertex(pair[0]), vertex(pair[1])) {
+                        self.draw_line(a, b)?;
+                    }
+                }
+            }
+            wgt::PrimitiveTopology::LineStrip => {
+                for pair in elements.windows(2) {
+                    if let (Some(a), Some(b)) = (vertex(pair[0]), vertex(pair[1])) {
+                        self.draw_line(a, b)?;
+                    }
+                }
+            }
+            wgt::PrimitiveTopology::TriangleList => {
+                for triple in elements.chunks_exact(3) {
+                    if let (Some(a), Some(b), Some(c)) =
+                        (vertex(triple[0]), vertex(triple[1]), vertex(triple[2]))
+                    {
+                        self.draw_triangle([a, b, c])?;
+                    }
+                }
+            }
+            wgt::PrimitiveTopology::TriangleStrip => {
+                for strip in elements.split(Option::is_none) {
+                    for k in 0..strip.len().saturating_sub(2) {
+                        // Every other triangle is flipped, to keep the winding consistent.
+                        let (b, c) = if k % 2 == 0 {
+                            (k + 1, k + 2)
+                        } else {
+                            (k + 2, k + 1)
+                        };
+                        if let (Some(a), Some(b), Some(c)) =
+                            (vertex(strip[k]), vertex(strip[b]), vertex(strip[c]))
+                        {
+                            self.draw_triangle([a, b, c])?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Executes a draw call into the targets of the current render pass.
+///
+/// # Safety
+///
+/// The resources used by the draw, and the targets, have to be alive.
+pub(super) unsafe fn draw(
+    state: &super::DrawState,
+    call: &super::DrawCall,
+    color_targets: &[super::ColorTarget],
+    depth_stencil_target: Option<&super::DepthStencilTarget>,
+) -> Result<(), Error> {
+    let vertex_resources = Resource::from_bindings(&state.vertex_resources);
+    let fragment_resources = Resource::from_bindings(&state.fragment_resources);
+
+    let pipeline = &state.pipeline;
+    let fragment_shader = pipeline
+        .fragment_stage
+        .as_ref()
+        .map(|stage| FragmentShader {
+            env: shader::Environment::new(&stage.entry_point.program, &fragment_resources),
+            entry_point: stage.entry_point.index,
+            uses_derivatives: stage.uses_derivatives,
+        });
+
+    let target_views = color_targets
+        .iter()
+        .map(|target| &target.view)
+        .chain(depth_stencil_target.map(|target| &target.view));
+    let mut clip_rect = [
+        state.scissor.x,
+        state.scissor.y,
+        state.scissor.x + state.scissor.w,
+        state.scissor.y + state.scissor.h,
+    ];
+    for view in target_views {
+        let extent = view.texture.desc.mip_extent(view.mip_levels.start);
+        clip_rect[2] = clip_rect[2].min(extent.width);
+        clip_rect[3] = clip_rect[3].min(extent.height);
+    }
+
+    let rasterizer = Rasterizer {
+        state,
+        pipeline,
+        vertex_env: shader::Environment::new(&pipeline.vertex_stage.program, &vertex_resources),
+        fragment_shader,
+        color_targets,
+        depth_stencil_target,
+        clip_rect,
+        samples: sample_positions(pipeline.multisample.count.clamp(1, 32)),
+    };
+
+    let mut vertices = Vec::new();
+    let mut elements = Vec::with_capacity(call.elements.len());
+    let mut cache = HashMap::new();
+    for instance in call.instances.clone() {
+        vertices.clear();
+        elements.clear();
+        cache.clear();
+        for element in call.elements.clone() {
+            let vertex_index = if call.indexed {
+                match rasterizer.read_index(element) {
+                    Some(index) => (index as i32).wrapping_add(call.base_vertex) as u32,
+                    None => {
+                        elements.push(None);
+                        continue;
+                    }
+                }
+            } else {
+                element
+            };
+            let slot = match cache.entry(vertex_index) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    vertices.push(rasterizer.shade_vertex(vertex_index, instance)?);
+                    *entry.insert(vertices.len() - 1)
+                }
+            };
+            elements.push(Some(slot));
+        }
+        rasterizer.draw_primitives(&vertices, &elements)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,849 @@
+//! Execution of a single invocation.
+
+use super::{
+    image, math,
+    value::{column_stride, Pointer, Scalar, Space, Value},
+    Environment, Error, FunctionRef, VariableLayout,
+};
+use arrayvec::ArrayVec;
+use naga::{Expression as E, Handle, Statement as S, TypeInner};
+
+#[derive(Clone, Debug)]
+pub enum Status {
+    /// Suspended at a barrier, waiting for the rest of the workgroup.
+    Barrier,
+    /// Returned from the entry point, with the given result.
+    Return(Option<Value>),
+    /// Discarded by a `Kill` statement.
+    Kill,
+}
+
+enum CursorKind<'a> {
+    Block,
+    Loop {
+        body: &'a naga::Block,
+        continuing: &'a naga::Block,
+        in_continuing: bool,
+    },
+    Switch {
+        cases: &'a [naga::SwitchCase],
+        case: usize,
+    },
+}
+
+/// Position of the execution within a block.
+struct Cursor<'a> {
+    block: &'a naga::Block,
+    index: usize,
+    kind: CursorKind<'a>,
+}
+
+struct Frame<'a> {
+    function: &'a naga::Function,
+    info: &'a naga::valid::FunctionInfo,
+    layout: &'a VariableLayout,
+    arguments: Vec<Value>,
+    locals: Vec<u8>,
+    /// Values of the evaluated expressions.
+    expressions: Vec<Option<Value>>,
+    cursors: Vec<Cursor<'a>>,
+    /// Expression of the caller receiving the returned value.
+    result: Option<Handle<naga::Expression>>,
+}
+
+/// Outcome of a single step of execution.
+enum Step<'a> {
+    Continue,
+    /// Reached an `Emit` statement, which is left to the caller in lockstep mode.
+    Emit(&'a naga::Range<naga::Expression>),
+    Stop(Status),
+}
+
+pub struct Invocation<'a> {
+    env: &'a Environment<'a>,
+    frames: Vec<Frame<'a>>,
+    private: Vec<u8>,
+    finished: Option<Status>,
+    /// Position in the fragment quad: bit 0 is the column, bit 1 the row.
+    lane: usize,
+    /// Helper invocations only run for the sake of derivatives,
+    /// their side effects are discarded.
+    helper: bool,
+}
+
+impl<'a> Invocation<'a> {
+    pub fn new(env: &'a Environment<'a>, function: FunctionRef, arguments: Vec<Value>) -> Self {
+        let mut invocation = Self {
+            env,
+            frames: Vec::new(),
+            private: env.program.private.memory.clone(),
+            finished: None,
+            lane: 0,
+            helper: false,
+        };
+        invocation.push_frame(function, arguments, None);
+        invocation
+    }
+
+    /// Place the invocation in a fragment quad.
+    pub fn with_lane(mut self, lane: usize, helper: bool) -> Self {
+        self.lane = lane;
+        self.helper = helper;
+        self
+    }
+
+    /// Run until the invocation finishes or reaches a barrier.
+    pub fn run(&mut self, workgroup: &mut [u8]) -> Result<Status, Error> {
+        if let Some(ref status) = self.finished {
+            return Ok(status.clone());
+        }
+        loop {
+            match self.step(workgroup, false)? {
+                Step::Continue | Step::Emit(_) => {}
+                Step::Stop(status) => {
+                    if !matches!(status, Status::Barrier) {
+                        self.finished = Some(status.clone());
+                    }
+                    return Ok(status);
+                }
+            }
+        }
+    }
+
+    /// Run the invocations of a fragment quad to completion.
+    ///
+    /// The expressions are evaluated in lockstep, so that derivatives
+    /// can look at the values of the neighbors.
+    pub fn run_quad(lanes: &mut [Self]) -> Result<(), Error> {
+        let mut emits = Vec::with_capacity(lanes.len());
+        loop {
+            emits.clear();
+            for lane in lanes.iter_mut() {
+                let range = lane.run_to_emit()?;
+                if let Some(range) = range {
+                    for handle in range.clone() {
+                        lane.frame_mut().expressions[handle.index()] = None;
+                    }
+                }
+                emits.push(range.cloned());
+            }
+            if emits.iter().all(Option::is_none) {
+                return Ok(());
+            }
+
+            let mut pending = true;
+            while pending {
+                pending = false;
+                for (index, range) in emits.iter_mut().enumerate() {
+                    if let Some(handle) = range.as_mut().and_then(Iterator::next) {
+                        pending = true;
+                        let value = lanes[index].eval_in(handle, &[], Some(lanes))?;
+                        lanes[index].frame_mut().expressions[handle.index()] = Some(value);
+                        lanes[index].store_outputs(handle, &mut [])?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run until the next `Emit` statement, which is returned, or until the end.
+    fn run_to_emit(&mut self) -> Result<Option<&'a naga::Range<naga::Expression>>, Error> {
+        if self.finished.is_some() {
+            return Ok(None);
+        }
+        loop {
+            match self.step(&mut [], true)? {
+                Step::Continue => {}
+                Step::Emit(range) => return Ok(Some(range)),
+                Step::Stop(status) => {
+                    self.finished = Some(status);
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Returns how the invocation finished, if it did.
+    pub fn into_status(self) -> Option<Status> {
+        self.finished
+    }
+
+    fn module(&self) -> &'a naga::Module {
+        self.env.program.module()
+    }
+
+    fn frame(&self) -> &Frame<'a> {
+        self.frames.last().unwrap()
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame<'a> {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn push_frame(
+        &mut self,
+        function_ref: FunctionRef,
+        arguments: Vec<Value>,
+        result: Option<Handle<naga::Expression>>,
+    ) {
+        let (function, info, layout) = self.env.program.function(function_ref);
+        self.frames.push(Frame {
+            function,
+            info,
+            layout,
+            arguments,
+            locals: layout.memory.clone(),
+            expressions: vec![None; function.expressions.len()],
+            cursors: vec![Cursor {
+                block: &function.body,
+                index: 0,
+                kind: CursorKind::Block,
+            }],
+            result,
+        });
+    }
+
+    fn push_cursor(&mut self, block: &'a naga::Block, kind: CursorKind<'a>) {
+        self.frame_mut().cursors.push(Cursor {
+            block,
+            index: 0,
+            kind,
+        });
+    }
+
+    fn return_value(&mut self, value: Option<Value>) -> Result<Option<Status>, Error> {
+        let frame = self.frames.pop().unwrap();
+        match self.frames.last_mut() {
+            Some(caller) => {
+                if let Some(result) = frame.result {
+                    caller.expressions[result.index()] = value;
+                }
+                Ok(None)
+            }
+            None => Ok(Some(Status::Return(value))),
+        }
+    }
+
+    /// Execute the next statement, or leave the current block if it's over.
+    fn step(&mut self, workgroup: &mut [u8], lockstep: bool) -> Result<Step<'a>, Error> {
+        let frame = self.frames.last_mut().unwrap();
+        let cursor = match frame.cursors.last_mut() {
+            Some(cursor) => cursor,
+            // Reached the end of a function without a result.
+            None => return Ok(self.return_value(None)?.map_or(Step::Continue, Step::Stop)),
+        };
+
+        if cursor.index < cursor.block.len() {
+            let block = cursor.block;
+            let statement = &block[cursor.index];
+            cursor.index += 1;
+            if let S::Emit(ref range) = *statement {
+                if lockstep {
+                    return Ok(Step::Emit(range));
+                }
+            }
+            return Ok(self
+                .execute(statement, workgroup)?
+                .map_or(Step::Continue, Step::Stop));
+        }
+
+        cursor.index = 0;
+        match cursor.kind {
+            CursorKind::Block => {
+                frame.cursors.pop();
+            }
+            CursorKind::Loop {
+                body,
+                continuing,
+                ref mut in_continuing,
+            } => {
+                *in_continuing = !*in_continuing;
+                cursor.block = if *in_continuing { continuing } else { body };
+            }
+            CursorKind::Switch {
+                cases,
+                ref mut case,
+            } => {
+                if cases[*case].fall_through && *case + 1 < cases.len() {
+                    *case += 1;
+                    cursor.block = &cases[*case].body;
+                } else {
+                    frame.cursors.pop();
+                }
+            }
+        }
+        Ok(Step::Continue)
+    }
+
+    fn execute(
+        &mut self,
+        statement: &'a naga::Statement,
+        workgroup: &mut [u8],
+    ) -> Result<Option<Status>, Error> {
+        match *statement {
+            S::Emit(ref range) => {
+                // Expressions are re-evaluated on each iteration of a loop.
+                for handle in range.clone() {
+                    self.frame_mut().expressions[handle.index()] = None;
+                }
+                for handle in range.clone() {
+                    let value = self.eval(handle, workgroup)?;
+                    self.frame_mut().expressions[handle.index()] = Some(value);
+                    self.store_outputs(handle, workgroup)?;
+                }
+            }
+            S::Block(ref block) => self.push_cursor(block, CursorKind::Block),
+            S::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                let condition = self.eval(condition, workgroup)?.scalar()?.as_bool()?;
+                self.push_cursor(if condition { accept } else { reject }, CursorKind::Block);
+            }
+            S::Switch {
+                selector,
+                ref cases,
+            } => {
+                let selector = self.eval(selector, workgroup)?.scalar()?.to_bits() as i32;
+                let is_selected = |case: &naga::SwitchCase| match case.value {
+                    naga::SwitchValue::Integer(value) => value == selector,
+                    naga::SwitchValue::Default => false,
+                };
+                let case = cases.iter().position(is_selected).or_else(|| {
+                    cases
+                        .iter()
+                        .position(|case| matches!(case.value, naga::SwitchValue::Default))
+                });
+                if let Some(case) = case {
+                    self.push_cursor(&cases[case].body, CursorKind::Switch { cases, case });
+                }
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => self.push_cursor(
+                body,
+                CursorKind::Loop {
+                    body,
+                    continuing,
+                    in_continuing: false,
+                },
+            ),
+            S::Break => {
+                let cursors = &mut self.frame_mut().cursors;
+                while let Some(cursor) = cursors.pop() {
+                    if let CursorKind::Block = cursor.kind {
+                        continue;
+                    }
+                    break;
+                }
+            }
+            S::Continue => {
+                let cursors = &mut self.frame_mut().cursors;
+                while let Some(cursor) = cursors.last_mut() {
+                    if let CursorKind::Loop {
+                        continuing,
+                        ref mut in_continuing,
+                        ..
+                    } = cursor.kind
+                    {
+                        *in_continuing = true;
+                        cursor.block = continuing;
+                        cursor.index = 0;
+                        break;
+                    }
+                    cursors.pop();
+                }
+            }
+            S::Return { value } => {
+                let value = match value {
+                    Some(handle) => Some(self.eval(handle, workgroup)?),
+                    None => None,
+                };
+                return self.return_value(value);
+            }
+            S::Kill => return Ok(Some(Status::Kill)),
+            S::Barrier(_) => return Ok(Some(Status::Barrier)),
+            S::Store { pointer, value } => {
+                let pointer = self.eval(pointer, workgroup)?.pointer()?;
+                let inner = self.type_of(value);
+                let value = self.eval(value, workgroup)?;
+                self.write(pointer, inner, &value, workgroup)?;
+            }
+            S::ImageStore {
+                image,
+                coordinate,
+                array_index,
+                value,
+            } => {
+                let view = self.env.texture(self.eval(image, workgroup)?.handle()?)?;
+                let coordinate = self.eval(coordinate, workgroup)?;
+                let array_index = match array_index {
+                    Some(index) => Some(self.eval(index, workgroup)?),
+                    None => None,
+                };
+                let value = self.eval(value, workgroup)?;
+                if !self.helper {
+                    image::store(
+                        view,
+                        self.image_type(image)?,
+                        &coordinate,
+                        array_index.as_ref(),
+                        &value,
+                    )?;
+                }
+            }
+            S::Atomic {
+                pointer,
+                ref fun,
+                value,
+                result,
+            } => {
+                let pointer = self.eval(pointer, workgroup)?.pointer()?;
+                let inner = self.type_of(value);
+                let operand = self.eval(value, workgroup)?;
+                let old = self.read(pointer, inner, workgroup)?;
+                let binary = |op| math::binary(op, &old, &operand);
+                let (new, result_value) = match *fun {
+                    naga::AtomicFunction::Add => (binary(naga::BinaryOperator::Add)?, None),
+                    naga::AtomicFunction::Subtract => {
+                        (binary(naga::BinaryOperator::Subtract)?, None)
+                    }
+                    naga::AtomicFunction::And => (binary(naga::BinaryOperator::And)?, None),
+                    naga::AtomicFunction::ExclusiveOr => {
+                        (binary(naga::BinaryOperator::ExclusiveOr)?, None)
+                    }
+                    naga::AtomicFunction::InclusiveOr => {
+                        (binary(naga::BinaryOperator::InclusiveOr)?, None)
+                    }
+                    naga::AtomicFunction::Min => (
+                        math::math(naga::MathFunction::Min, &[old.clone(), operand.clone()])?,
+                        None,
+                    ),
+                    naga::AtomicFunction::Max => (
+                        math::math(naga::MathFunction::Max, &[old.clone(), operand.clone()])?,
+                        None,
+                    ),
+                    naga::AtomicFunction::Exchange { compare: None } => (operand.clone(), None),
+                    naga::AtomicFunction::Exchange {
+                        compare: Some(compare),
+                    } => {
+                        let old = old.scalar()?;
+                        let exchanged = old == self.eval(compare, workgroup)?.scalar()?;
+                        let result = Value::Vector(
+                            [old, Scalar::from_bits(old.kind(), exchanged as u32)]
+                                .iter()
+                                .cloned()
+                                .collect(),
+                        );
+                        let new = if exchanged {
+                            operand.clone()
+                        } else {
+                            Value::Scalar(old)
+                        };
+                        (new, Some(result))
+                    }
+                };
+                self.write(pointer, inner, &new, workgroup)?;
+                self.frame_mut().expressions[result.index()] = Some(result_value.unwrap_or(old));
+            }
+            S::Call {
+                function,
+                ref arguments,
+                result,
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|&argument| self.eval(argument, workgroup))
+                    .collect::<Result<_, _>>()?;
+                self.push_frame(FunctionRef::Function(function), arguments, result);
+            }
+        }
+        Ok(None)
+    }
+
+    fn type_of(&self, handle: Handle<naga::Expression>) -> &'a TypeInner {
+        let info: &'a naga::valid::FunctionInfo = self.frame().info;
+        info[handle].ty.inner_with(&self.module().types)
+    }
+
+    fn memory<'m>(&'m self, space: Space, workgroup: &'m [u8]) -> Result<&'m [u8], Error> {
+        Ok(match space {
+            Space::Function { frame } => &self.frames[frame as usize].locals,
+            Space::Private => &self.private,
+            Space::WorkGroup => workgroup,
+            Space::Buffer(handle) => self.env.buffer(handle)?.bytes(),
+        })
+    }
+
+    fn read(&self, pointer: Pointer, inner: &TypeInner, workgroup: &[u8]) -> Result<Value, Error> {
+        let memory = self.memory(pointer.space, workgroup)?;
+        Value::read(self.module(), inner, memory, pointer.offset)
+    }
+
+    fn write(
+        &mut self,
+        pointer: Pointer,
+        inner: &TypeInner,
+        value: &Value,
+        workgroup: &mut [u8],
+    ) -> Result<(), Error> {
+        let module = self.module();
+        let memory = match pointer.space {
+            Space::Function { frame } => &mut self.frames[frame as usize].locals,
+            Space::Private => &mut self.private,
+            Space::WorkGroup => workgroup,
+            Space::Buffer(_) if self.helper => return Ok(()),
+            Space::Buffer(handle) => self.env.buffer(handle)?.bytes_mut(),
+        };
+        value.write(module, inner, memory, pointer.offset)
+    }
+
+    /// Advance a pointer to a member of the value it points to.
+    fn access_pointer(
+        &self,
+        base: Handle<naga::Expression>,
+        pointer: Pointer,
+        index: Option<u32>,
+    ) -> Result<Value, Error> {
+        let module = self.module();
+        // Negative indices are out of bounds.
+        let index = index.unwrap_or(u32::MAX);
+        let offset = match *self.type_of(base) {
+            TypeInner::Pointer { base, .. } => match module.types[base].inner {
+                TypeInner::Vector { width, .. } => index.checked_mul(width as u32),
+                TypeInner::Matrix { rows, .. } => index.checked_mul(column_stride(rows)),
+                TypeInner::Array { stride, .. } => index.checked_mul(stride),
+                TypeInner::Struct { ref members, .. } => {
+                    members.get(index as usize).map(|member| member.offset)
+                }
+                _ => return Err(Error::InvalidOperand("a pointer to a composite")),
+            },
+            TypeInner::ValuePointer {
+                size: Some(_),
+                width,
+                ..
+            } => index.checked_mul(width as u32),
+            _ => return Err(Error::InvalidOperand("a pointer to a composite")),
+        };
+        Ok(Value::Pointer(Pointer {
+            space: pointer.space,
+            offset: offset.map_or(u32::MAX, |offset| pointer.offset.saturating_add(offset)),
+        }))
+    }
+
+    /// Write the outputs of an emitted expression that go through a pointer.
+    fn store_outputs(
+        &mut self,
+        handle: Handle<naga::Expression>,
+        workgroup: &mut [u8],
+    ) -> Result<(), Error> {
+        if let E::Math {
+            fun: fun @ naga::MathFunction::Modf,
+            arg,
+            arg1: Some(pointer),
+            ..
+        }
+        | E::Math {
+            fun: fun @ naga::MathFunction::Frexp,
+            arg,
+            arg1: Some(pointer),
+            ..
+        } = self.frame().function.expressions[handle]
+        {
+            let (_, output) = math::split(fun, &self.eval(arg, workgroup)?)?;
+            let pointer = self.eval(pointer, workgroup)?.pointer()?;
+            self.write(pointer, self.type_of(arg), &output, workgroup)?;
+        }
+        Ok(())
+    }
+
+    fn image_type(&self, image: Handle<naga::Expression>) -> Result<image::ImageType, Error> {
+        match *self.type_of(image) {
+            TypeInner::Image { dim, class, .. } => Ok(image::ImageType { dim, class }),
+            _ => Err(Error::InvalidOperand("an image")),
+        }
+    }
+
+    /// Returns the value of an expression in the same function of another invocation.
+    fn neighbor_value(
+        &self,
+        lanes: &[Self],
+        lane: usize,
+        handle: Handle<naga::Expression>,
+    ) -> Option<Value> {
+        let neighbor = lanes.get(lane)?;
+        let frame = neighbor.frames.last()?;
+        if !std::ptr::eq(frame.function, self.frame().function) {
+            return None;
+        }
+        match frame.expressions[handle.index()] {
+            Some(ref value) => Some(value.clone()),
+            // Arguments and constants are not emitted, evaluate them in place.
+            None => neighbor.eval_in(handle, &[], None).ok(),
+        }
+    }
+
+    /// Returns the derivatives of an expression along X and Y.
+    ///
+    /// Outside of a quad, derivatives are zero.
+    fn derivatives(
+        &self,
+        handle: Handle<naga::Expression>,
+        value: &Value,
+        quad: Option<&[Self]>,
+    ) -> Result<(Value, Value), Error> {
+        let lanes = match quad {
+            Some(lanes) => lanes,
+            None => {
+                let zero = math::binary(naga::BinaryOperator::Subtract, value, value)?;
+                return Ok((zero.clone(), zero));
+            }
+        };
+        let at = |lane: usize| {
+            self.neighbor_value(lanes, lane, handle)
+                .unwrap_or_else(|| value.clone())
+        };
+        let (row, column) = (self.lane & 2, self.lane & 1);
+        let dx = math::binary(naga::BinaryOperator::Subtract, &at(row | 1), &at(row))?;
+        let dy = math::binary(naga::BinaryOperator::Subtract, &at(2 | column), &at(column))?;
+        Ok((dx, dy))
+    }
+
+    fn eval(&self, handle: Handle<naga::Expression>, workgroup: &[u8]) -> Result<Value, Error> {
+        self.eval_in(handle, workgroup, None)
+    }
+
+    /// Evaluate an expression, possibly as part of a quad.
+    fn eval_in(
+        &self,
+        handle: Handle<naga::Expression>,
+        workgroup: &[u8],
+        quad: Option<&[Self]>,
+    ) -> Result<Value, Error> {
+        let frame = self.frame();
+        if let Some(ref value) = frame.expressions[handle.index()] {
+            return Ok(value.clone());
+        }
+        let module = self.module();
+        let eval = |handle| self.eval_in(handle, workgroup, quad);
+
+        Ok(match frame.function.expressions[handle] {
+            E::Access { base, index } => {
+                let index = eval(index)?.scalar()?.as_index()?;
+                match eval(base)? {
+                    Value::Pointer(pointer) => self.access_pointer(base, pointer, index)?,
+                    value => value.member(index.unwrap_or(0))?,
+                }
+            }
+            E::AccessIndex { base, index } => match eval(base)? {
+                Value::Pointer(pointer) => self.access_pointer(base, pointer, Some(index))?,
+                value => value.member(index)?,
+            },
+            E::Constant(constant) => Value::constant(module, constant)?,
+            E::Splat { size, value } => {
+                let scalar = eval(value)?.scalar()?;
+                Value::Vector((0..size as u8).map(|_| scalar).collect())
+            }
+            E::Swizzle {
+                size,
+                vector,
+                pattern,
+            } => {
+                let components = eval(vector)?.components()?;
+                Value::Vector(
+                    pattern[..size as usize]
+                        .iter()
+                        .map(|&component| components[component as usize])
+                        .collect(),
+                )
+            }
+            E::Compose { ty, ref components } => {
+                let values = components
+                    .iter()
+                    .map(|&component| eval(component))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match module.types[ty].inner {
+                    TypeInner::Vector { .. } => {
+                        let mut scalars = ArrayVec::new();
+                        for value in values {
+                            scalars.extend(value.components()?);
+                        }
+                        Value::Vector(scalars)
+                    }
+                    _ => Value::Composite(values),
+                }
+            }
+            E::FunctionArgument(index) => frame.arguments[index as usize].clone(),
+            E::GlobalVariable(global) => {
+                let offset = self.env.program.global_offsets[global.index()];
+                let space = match module.global_variables[global].class {
+                    naga::StorageClass::Handle => return Ok(Value::Handle(global)),
+                    naga::StorageClass::Uniform | naga::StorageClass::Storage { .. } => {
+                        Space::Buffer(global)
+                    }
+                    naga::StorageClass::Private => Space::Private,
+                    naga::StorageClass::WorkGroup => Space::WorkGroup,
+                    naga::StorageClass::Function | naga::StorageClass::PushConstant => {
+                        return Err(Error::Unsupported("push constants"))
+                    }
+                };
+                Value::Pointer(Pointer { space, offset })
+            }
+            E::LocalVariable(local) => Value::Pointer(Pointer {
+                space: Space::Function {
+                    frame: self.frames.len() as u32 - 1,
+                },
+                offset: frame.layout.offsets[local.index()],
+            }),
+            E::Load { pointer } => {
+                let pointer = eval(pointer)?.pointer()?;
+                self.read(pointer, self.type_of(handle), workgroup)?
+            }
+            E::ImageSample {
+                image,
+                sampler,
+                gather,
+                coordinate,
+                array_index,
+                offset,
+                level,
+                depth_ref,
+            } => {
+                let view = self.env.texture(eval(image)?.handle()?)?;
+                let ty = self.image_type(image)?;
+                let coordinate_value = eval(coordinate)?;
+                let array_index = match array_index {
+                    Some(index) => Some(eval(index)?),
+                    None => None,
+                };
+                let depth_ref = match depth_ref {
+                    Some(depth_ref) => Some(eval(depth_ref)?),
+                    None => None,
+                };
+                let lod = |dx: &Value, dy: &Value| {
+                    image::lod_from_gradients(view, ty, &coordinate_value, dx, dy)
+                };
+                let level = match level {
+                    naga::SampleLevel::Zero => image::Level::Zero,
+                    naga::SampleLevel::Exact(lod) => {
+                        image::Level::Lod(eval(lod)?.scalar()?.as_float()?)
+                    }
+                    naga::SampleLevel::Auto => {
+                        let (dx, dy) = self.derivatives(coordinate, &coordinate_value, quad)?;
+                        image::Level::Lod(lod(&dx, &dy)?)
+                    }
+                    naga::SampleLevel::Bias(bias) => {
+                        let (dx, dy) = self.derivatives(coordinate, &coordinate_value, quad)?;
+                        let bias = eval(bias)?.scalar()?.as_float()?;
+                        image::Level::Lod(lod(&dx, &dy)? + bias)
+                    }
+                    naga::SampleLevel::Gradient { x, y } => {
+                        image::Level::Lod(lod(&eval(x)?, &eval(y)?)?)
+                    }
+                };
+                image::sample(image::Sample {
+                    view,
+                    sampler: self.env.sampler(eval(sampler)?.handle()?)?,
+                    ty,
+                    coordinate: &coordinate_value,
+                    array_index: array_index.as_ref(),
+                    offset: match offset {
+                        Some(constant) => Some(Value::constant(module, constant)?),
+                        None => None,
+                    },
+                    level,
+                    depth_ref: depth_ref.as_ref(),
+                    gather,
+                })?
+            }
+            E::ImageLoad {
+                image,
+                coordinate,
+                array_index,
+                index,
+            } => {
+                let view = self.env.texture(eval(image)?.handle()?)?;
+                let array_index = match array_index {
+                    Some(index) => Some(eval(index)?),
+                    None => None,
+                };
+                let index = match index {
+                    Some(index) => Some(eval(index)?),
+                    None => None,
+                };
+                image::load(
+                    view,
+                    self.image_type(image)?,
+                    &eval(coordinate)?,
+                    array_index.as_ref(),
+                    index.as_ref(),
+                )?
+            }
+            E::ImageQuery { image, query } => {
+                let view = self.env.texture(eval(image)?.handle()?)?;
+                let level = match query {
+                    naga::ImageQuery::Size { level: Some(level) } => Some(eval(level)?),
+                    _ => None,
+                };
+                image::query(view, self.image_type(image)?, query, level.as_ref())?
+            }
+            E::Derivative { axis, expr } => {
+                let (dx, dy) = self.derivatives(expr, &eval(expr)?, quad)?;
+                match axis {
+                    naga::DerivativeAxis::X => dx,
+                    naga::DerivativeAxis::Y => dy,
+                    naga::DerivativeAxis::Width => {
+                        let abs = |value| math::math(naga::MathFunction::Abs, &[value]);
+                        math::binary(naga::BinaryOperator::Add, &abs(dx)?, &abs(dy)?)?
+                    }
+                }
+            }
+            E::Unary { op, expr } => math::unary(op, &eval(expr)?)?,
+            E::Binary { op, left, right } => math::binary(op, &eval(left)?, &eval(right)?)?,
+            E::Select {
+                condition,
+                accept,
+                reject,
+            } => math::select(&eval(condition)?, &eval(accept)?, &eval(reject)?)?,
+            E::Relational { fun, argument } => math::relational(fun, &eval(argument)?)?,
+            E::Math {
+                fun,
+                arg,
+                arg1,
+                arg2,
+                arg3,
+            } => {
+                let mut args = vec![eval(arg)?];
+                for &extra in [arg1, arg2, arg3].iter().flatten() {
+                    args.push(eval(extra)?);
+                }
+                math::math(fun, &args)?
+            }
+            E::As {
+                expr,
+                kind,
+                convert,
+            } => math::cast(&eval(expr)?, kind, convert.is_some())?,
+            E::CallResult(_) | E::AtomicResult { .. } => {
+                return Err(Error::InvalidOperand("an evaluated expression"))
+            }
+            E::ArrayLength(array) => {
+                let pointer = eval(array)?.pointer()?;
+                let stride = match *self.type_of(array) {
+                    TypeInner::Pointer { base, .. } => match module.types[base].inner {
+                        TypeInner::Array { stride, .. } => stride,
+                        _ => return Err(Error::InvalidOperand("a pointer to an array")),
+                    },
+                    _ => return Err(Error::InvalidOperand("a pointer to an array")),
+                };
+                let size = match pointer.space {
+                    Space::Buffer(global) => self.env.buffer(global)?.bytes().len() as u32,
+                    _ => return Err(Error::InvalidOperand("a runtime-sized array")),
+                };
+                Value::Scalar(Scalar::Uint(size.saturating_sub(pointer.offset) / stride))
+            }
+        })
+    }
+}
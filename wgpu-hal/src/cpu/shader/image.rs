@@ -0,0 +1,655 @@
+//! Texture access: loads, stores, queries and filtered sampling.
+
+use super::{
+    super::{format, Sampler, TextureView},
+    Error, Scalar, Value,
+};
+use crate::FormatAspects;
+use naga::{ImageClass, ImageDimension, ScalarKind};
+
+/// Image type of a texture expression.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageType {
+    pub dim: ImageDimension,
+    pub class: ImageClass,
+}
+
+impl ImageType {
+    fn is_depth(&self) -> bool {
+        matches!(self.class, ImageClass::Depth { .. })
+    }
+
+    fn result(&self, texel: [Scalar; 4]) -> Value {
+        if self.is_depth() {
+            Value::Scalar(texel[0])
+        } else {
+            Value::Vector(texel.iter().cloned().collect())
+        }
+    }
+}
+
+/// Level of detail selection of a sampling operation.
+#[derive(Clone, Copy, Debug)]
+pub enum Level {
+    /// Computed from the derivatives of the coordinates.
+    Lod(f32),
+    /// The base level, without any filtering between levels.
+    Zero,
+}
+
+pub fn texel_scalars(texel: format::Texel) -> [Scalar; 4] {
+    match texel {
+        format::Texel::Float(v) => [
+            Scalar::Float(v[0]),
+            Scalar::Float(v[1]),
+            Scalar::Float(v[2]),
+            Scalar::Float(v[3]),
+        ],
+        format::Texel::Sint(v) => [
+            Scalar::Sint(v[0]),
+            Scalar::Sint(v[1]),
+            Scalar::Sint(v[2]),
+            Scalar::Sint(v[3]),
+        ],
+        format::Texel::Uint(v) => [
+            Scalar::Uint(v[0]),
+            Scalar::Uint(v[1]),
+            Scalar::Uint(v[2]),
+            Scalar::Uint(v[3]),
+        ],
+    }
+}
+
+/// Location of a texel within a view.
+#[derive(Clone, Copy, Debug)]
+struct TexelAddress {
+    level: u32,
+    layer: u32,
+    coords: [i32; 3],
+    sample: u32,
+}
+
+struct Image<'a> {
+    view: &'a TextureView,
+    ty: ImageType,
+    aspect: FormatAspects,
+}
+
+impl<'a> Image<'a> {
+    fn new(view: &'a TextureView, ty: ImageType) -> Self {
+        let aspect = if view.aspects.contains(FormatAspects::DEPTH) {
+            FormatAspects::DEPTH
+        } else if view.aspects.contains(FormatAspects::STENCIL) {
+            FormatAspects::STENCIL
+        } else {
+            FormatAspects::COLOR
+        };
+        Self { view, ty, aspect }
+    }
+
+    fn level_count(&self) -> u32 {
+        self.view.mip_levels.end - self.view.mip_levels.start
+    }
+
+    fn layer_count(&self) -> u32 {
+        let layers = self.view.array_layers.end - self.view.array_layers.start;
+        match self.ty.dim {
+            ImageDimension::Cube => layers / 6,
+            _ => layers,
+        }
+    }
+
+    /// Size of a level of the view, in texels.
+    fn size(&self, level: u32) -> [u32; 3] {
+        let extent = self
+            .view
+            .texture
+            .desc
+            .mip_extent(self.view.mip_levels.start + level);
+        match self.ty.dim {
+            ImageDimension::D1 => [extent.width, 1, 1],
+            ImageDimension::D2 | ImageDimension::Cube => [extent.width, extent.height, 1],
+            ImageDimension::D3 => [extent.width, extent.height, extent.depth],
+        }
+    }
+
+    /// Reads a texel, or returns `None` if it's out of bounds.
+    fn fetch(&self, address: TexelAddress) -> Option<[Scalar; 4]> {
+        let size = self.size(address.level.min(self.level_count().saturating_sub(1)));
+        let layers = self.view.array_layers.end - self.view.array_layers.start;
+        let in_bounds = address.level < self.level_count()
+            && address.layer < layers
+            && address.sample < self.view.texture.desc.sample_count
+            && address
+                .coords
+                .iter()
+                .zip(size.iter())
+                .all(|(&c, &s)| c >= 0 && (c as u32) < s);
+        if !in_bounds {
+            return None;
+        }
+
+        let desc = &self.view.texture.desc;
+        let (layer, z) = match self.ty.dim {
+            ImageDimension::D3 => (0, address.coords[2] as u32),
+            _ => (self.view.array_layers.start + address.layer, 0),
+        };
+        let sub = desc.subresource(
+            self.aspect,
+            self.view.mip_levels.start + address.level,
+            layer,
+        );
+        let range = sub.block_range(
+            address.coords[0] as u32,
+            address.coords[1] as u32,
+            z,
+            address.sample,
+        );
+        // Shaders run while the queue replays the command buffers,
+        // so nothing else can access the texture memory.
+        let bytes = unsafe { self.view.texture.memory.slice(range) };
+        Some(if self.aspect == FormatAspects::DEPTH {
+            let mut raw = [0; 4];
+            raw.copy_from_slice(bytes);
+            let depth = Scalar::Float(f32::from_ne_bytes(raw));
+            [
+                depth,
+                Scalar::Float(0.0),
+                Scalar::Float(0.0),
+                Scalar::Float(1.0),
+            ]
+        } else if self.aspect == FormatAspects::STENCIL {
+            let stencil = Scalar::Uint(bytes[0] as u32);
+            [stencil, Scalar::Uint(0), Scalar::Uint(0), Scalar::Uint(1)]
+        } else {
+            texel_scalars(format::decode(self.view.format, bytes))
+        })
+    }
+
+    fn write(&self, address: TexelAddress, texel: format::Texel) {
+        if self.fetch(address).is_none() || self.aspect != FormatAspects::COLOR {
+            return;
+        }
+        let desc = &self.view.texture.desc;
+        let (layer, z) = match self.ty.dim {
+            ImageDimension::D3 => (0, address.coords[2] as u32),
+            _ => (self.view.array_layers.start + address.layer, 0),
+        };
+        let sub = desc.subresource(
+            self.aspect,
+            self.view.mip_levels.start + address.level,
+            layer,
+        );
+        let range = sub.block_range(address.coords[0] as u32, address.coords[1] as u32, z, 0);
+        let bytes = unsafe { self.view.texture.memory.slice_mut(range) };
+        format::encode(self.view.format, texel, bytes);
+    }
+
+    fn zero(&self) -> [Scalar; 4] {
+        let kind = match self.ty.class {
+            ImageClass::Sampled { kind, .. } => kind,
+            ImageClass::Depth { .. } => ScalarKind::Float,
+            ImageClass::Storage { format, .. } => format.into(),
+        };
+        [Scalar::zero(kind); 4]
+    }
+}
+
+/// Integer coordinates of an image operation.
+fn integer_coords(coordinate: &Value) -> Result<[i32; 3], Error> {
+    let mut coords = [0; 3];
+    for (c, scalar) in coords.iter_mut().zip(coordinate.components()?) {
+        *c = match scalar {
+            Scalar::Sint(v) => v,
+            Scalar::Uint(v) => v as i32,
+            _ => return Err(Error::InvalidOperand("integer coordinates")),
+        };
+    }
+    Ok(coords)
+}
+
+fn index(value: Option<&Value>) -> Result<u32, Error> {
+    Ok(match value {
+        Some(value) => match value.scalar()? {
+            Scalar::Sint(v) if v < 0 => u32::MAX,
+            scalar => scalar.to_bits(),
+        },
+        None => 0,
+    })
+}
+
+pub fn load(
+    view: &TextureView,
+    ty: ImageType,
+    coordinate: &Value,
+    array_index: Option<&Value>,
+    index_value: Option<&Value>,
+) -> Result<Value, Error> {
+    let image = Image::new(view, ty);
+    let multi = match ty.class {
+        ImageClass::Sampled { multi, .. } | ImageClass::Depth { multi } => multi,
+        ImageClass::Storage { .. } => false,
+    };
+    let index_value = index(index_value)?;
+    let address = TexelAddress {
+        level: if multi { 0 } else { index_value },
+        layer: index(array_index)?,
+        coords: integer_coords(coordinate)?,
+        sample: if multi { index_value } else { 0 },
+    };
+    // Out-of-bounds loads return zero, like with robust buffer access.
+    Ok(ty.result(image.fetch(address).unwrap_or_else(|| image.zero())))
+}
+
+pub fn store(
+    view: &TextureView,
+    ty: ImageType,
+    coordinate: &Value,
+    array_index: Option<&Value>,
+    value: &Value,
+) -> Result<(), Error> {
+    let image = Image::new(view, ty);
+    let address = TexelAddress {
+        level: 0,
+        layer: index(array_index)?,
+        coords: integer_coords(coordinate)?,
+        sample: 0,
+    };
+    let components = value.components()?;
+    let texel = match components[0] {
+        Scalar::Float(_) => {
+            let mut v = [0.0; 4];
+            for (c, s) in v.iter_mut().zip(components.iter()) {
+                *c = s.as_float()?;
+            }
+            format::Texel::Float(v)
+        }
+        Scalar::Sint(_) => {
+            let mut v = [0; 4];
+            for (c, s) in v.iter_mut().zip(components.iter()) {
+                *c = s.to_bits() as i32;
+            }
+            format::Texel::Sint(v)
+        }
+        _ => {
+            let mut v = [0; 4];
+            for (c, s) in v.iter_mut().zip(components.iter()) {
+                *c = s.to_bits();
+            }
+            format::Texel::Uint(v)
+        }
+    };
+    image.write(address, texel);
+    Ok(())
+}
+
+pub fn query(
+    view: &TextureView,
+    ty: ImageType,
+    query: naga::ImageQuery,
+    level: Option<&Value>,
+) -> Result<Value, Error> {
+    let image = Image::new(view, ty);
+    let sint = |v: u32| Scalar::Sint(v as i32);
+    Ok(match query {
+        naga::ImageQuery::Size { .. } => {
+            let size = image.size(index(level)?.min(image.level_count() - 1));
+            match ty.dim {
+                ImageDimension::D1 => Value::Scalar(sint(size[0])),
+                ImageDimension::D2 | ImageDimension::Cube => {
+                    Value::Vector(size[..2].iter().map(|&s| sint(s)).collect())
+                }
+                ImageDimension::D3 => Value::Vector(size.iter().map(|&s| sint(s)).collect()),
+            }
+        }
+        naga::ImageQuery::NumLevels => Value::Scalar(sint(image.level_count())),
+        naga::ImageQuery::NumLayers => Value::Scalar(sint(image.layer_count())),
+        naga::ImageQuery::NumSamples => Value::Scalar(sint(view.texture.desc.sample_count)),
+    })
+}
+
+/// Selects the cube face a direction points to, and returns it along with
+/// the normalized coordinates on that face.
+fn cube_face(dir: [f32; 3]) -> (u32, [f32; 2]) {
+    let [x, y, z] = dir;
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+        if x >= 0.0 {
+            (0, -z, -y, ax)
+        } else {
+            (1, z, -y, ax)
+        }
+    } else if ay >= az {
+        if y >= 0.0 {
+            (2, x, z, ay)
+        } else {
+            (3, x, -z, ay)
+        }
+    } else if z >= 0.0 {
+        (4, x, -y, az)
+    } else {
+        (5, -x, -y, az)
+    };
+    let ma = if ma == 0.0 { 1.0 } else { ma };
+    (face, [0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0)])
+}
+
+fn floats(value: &Value) -> Result<[f32; 3], Error> {
+    let mut v = [0.0; 3];
+    for (c, scalar) in v.iter_mut().zip(value.components()?) {
+        *c = scalar.as_float()?;
+    }
+    Ok(v)
+}
+
+/// Computes the level of detail out of the screen-space derivatives
+/// of the sampling coordinates.
+pub fn lod_from_gradients(
+    view: &TextureView,
+    ty: ImageType,
+    coordinate: &Value,
+    dx: &Value,
+    dy: &Value,
+) -> Result<f32, Error> {
+    let image = Image::new(view, ty);
+    let size = image.size(0);
+    let (dx, dy) = match ty.dim {
+        ImageDimension::Cube => {
+            // Project the neighboring directions onto the face of this one.
+            let dir = floats(coordinate)?;
+            let (face, uv) = cube_face(dir);
+            let project = |d: [f32; 3]| {
+                let moved = [dir[0] + d[0], dir[1] + d[1], dir[2] + d[2]];
+                let moved_uv = cube_face_with(moved, face);
+                [moved_uv[0] - uv[0], moved_uv[1] - uv[1], 0.0]
+            };
+            (project(floats(dx)?), project(floats(dy)?))
+        }
+        _ => (floats(dx)?, floats(dy)?),
+    };
+    let length = |d: [f32; 3]| {
+        d.iter()
+            .zip(size.iter())
+            .map(|(&c, &s)| (c * s as f32) * (c * s as f32))
+            .sum::<f32>()
+            .sqrt()
+    };
+    let rho = length(dx).max(length(dy));
+    Ok(if rho > 0.0 {
+        rho.log2()
+    } else {
+        f32::NEG_INFINITY
+    })
+}
+
+/// Like `cube_face`, but with a fixed face.
+fn cube_face_with(dir: [f32; 3], face: u32) -> [f32; 2] {
+    let [x, y, z] = dir;
+    let (sc, tc, ma) = match face {
+        0 => (-z, -y, x),
+        1 => (z, -y, -x),
+        2 => (x, z, y),
+        3 => (x, -z, -y),
+        4 => (x, -y, z),
+        _ => (-x, -y, -z),
+    };
+    let ma = ma.abs().max(f32::EPSILON);
+    [0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0)]
+}
+
+fn wrap(coord: i32, size: u32, mode: wgt::AddressMode) -> Option<i32> {
+    let size = size as i32;
+    match mode {
+        wgt::AddressMode::ClampToEdge => Some(coord.clamp(0, size - 1)),
+        wgt::AddressMode::Repeat => Some(coord.rem_euclid(size)),
+        wgt::AddressMode::MirrorRepeat => {
+            let period = coord.rem_euclid(2 * size);
+            Some(if period < size {
+                period
+            } else {
+                2 * size - 1 - period
+            })
+        }
+        wgt::AddressMode::ClampToBorder => {
+            if coord >= 0 && coord < size {
+                Some(coord)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Compares a reference value with a stored value.
+pub fn compare(function: wgt::CompareFunction, reference: f32, value: f32) -> bool {
+    use wgt::CompareFunction as Cf;
+    match function {
+        Cf::Never => false,
+        Cf::Less => reference < value,
+        Cf::Equal => reference == value,
+        Cf::LessEqual => reference <= value,
+        Cf::Greater => reference > value,
+        Cf::NotEqual => reference != value,
+        Cf::GreaterEqual => reference >= value,
+        Cf::Always => true,
+    }
+}
+
+/// Everything about a sampling operation, except for the level.
+struct SampleOp<'a> {
+    image: Image<'a>,
+    sampler: &'a Sampler,
+    layer: u32,
+    coords: [f32; 3],
+    offset: [i32; 3],
+    depth_ref: Option<f32>,
+}
+
+impl SampleOp<'_> {
+    fn border(&self) -> [f32; 4] {
+        match self.sampler.border_color {
+            Some(wgt::SamplerBorderColor::OpaqueBlack) => [0.0, 0.0, 0.0, 1.0],
+            Some(wgt::SamplerBorderColor::OpaqueWhite) => [1.0; 4],
+            _ => [0.0; 4],
+        }
+    }
+
+    /// Reads a texel for filtering, applying the address modes and the comparison.
+    fn texel(&self, level: u32, coords: [i32; 3]) -> [f32; 4] {
+        let size = self.image.size(level);
+        let mut wrapped = [0; 3];
+        for i in 0..3 {
+            wrapped[i] = match wrap(
+                coords[i] + self.offset[i],
+                size[i],
+                self.sampler.address_modes[i],
+            ) {
+                Some(c) => c,
+                None => return self.compared(self.border()),
+            };
+        }
+        let texel = self
+            .image
+            .fetch(TexelAddress {
+                level,
+                layer: self.layer,
+                coords: wrapped,
+                sample: 0,
+            })
+            .unwrap_or_else(|| self.image.zero());
+        let mut v = [0.0; 4];
+        for (c, s) in v.iter_mut().zip(texel.iter()) {
+            *c = match *s {
+                Scalar::Float(f) => f,
+                Scalar::Sint(i) => i as f32,
+                Scalar::Uint(u) => u as f32,
+                Scalar::Bool(b) => b as u32 as f32,
+            };
+        }
+        self.compared(v)
+    }
+
+    fn compared(&self, texel: [f32; 4]) -> [f32; 4] {
+        match (self.depth_ref, self.sampler.compare) {
+            (Some(reference), Some(function)) => {
+                let passed = compare(function, reference, texel[0]) as u32 as f32;
+                [passed, 0.0, 0.0, 1.0]
+            }
+            _ => texel,
+        }
+    }
+
+    /// Returns the texel coordinates and the weights of the footprint along one axis.
+    fn footprint(&self, level: u32, axis: usize, filter: wgt::FilterMode) -> ([i32; 2], f32) {
+        let size = self.image.size(level)[axis] as f32;
+        match filter {
+            wgt::FilterMode::Nearest => {
+                let c = (self.coords[axis] * size).floor() as i32;
+                ([c, c], 0.0)
+            }
+            wgt::FilterMode::Linear => {
+                let x = self.coords[axis] * size - 0.5;
+                let c = x.floor();
+                ([c as i32, c as i32 + 1], x - c)
+            }
+        }
+    }
+
+    fn dimensions(&self) -> usize {
+        match self.image.ty.dim {
+            ImageDimension::D1 => 1,
+            ImageDimension::D2 | ImageDimension::Cube => 2,
+            ImageDimension::D3 => 3,
+        }
+    }
+
+    fn sample_level(&self, level: u32, filter: wgt::FilterMode) -> [f32; 4] {
+        let dims = self.dimensions();
+        let mut axes = [([0, 0], 0.0); 3];
+        for (axis, slot) in axes.iter_mut().enumerate().take(dims) {
+            *slot = self.footprint(level, axis, filter);
+        }
+        let corners = 1 << dims;
+        let mut result = [0.0; 4];
+        for corner in 0..corners {
+            let mut coords = [0; 3];
+            let mut weight = 1.0;
+            for (axis, &(texels, frac)) in axes.iter().enumerate().take(dims) {
+                let upper = (corner >> axis) & 1 == 1;
+                coords[axis] = texels[upper as usize];
+                weight *= if upper { frac } else { 1.0 - frac };
+            }
+            if weight == 0.0 {
+                continue;
+            }
+            let texel = self.texel(level, coords);
+            for (r, t) in result.iter_mut().zip(texel.iter()) {
+                *r += weight * t;
+            }
+        }
+        result
+    }
+
+    fn gather(&self, component: naga::SwizzleComponent) -> [f32; 4] {
+        let ([x0, x1], _) = self.footprint(0, 0, wgt::FilterMode::Linear);
+        let ([y0, y1], _) = self.footprint(0, 1, wgt::FilterMode::Linear);
+        let mut result = [0.0; 4];
+        let corners = [[x0, y1], [x1, y1], [x1, y0], [x0, y0]];
+        for (r, &[x, y]) in result.iter_mut().zip(corners.iter()) {
+            *r = self.texel(0, [x, y, 0])[component as usize];
+        }
+        result
+    }
+}
+
+pub struct Sample<'a> {
+    pub view: &'a TextureView,
+    pub sampler: &'a Sampler,
+    pub ty: ImageType,
+    pub coordinate: &'a Value,
+    pub array_index: Option<&'a Value>,
+    pub offset: Option<Value>,
+    pub level: Level,
+    pub depth_ref: Option<&'a Value>,
+    pub gather: Option<naga::SwizzleComponent>,
+}
+
+pub fn sample(op: Sample) -> Result<Value, Error> {
+    let image = Image::new(op.view, op.ty);
+    let mut layer = index(op.array_index)?;
+    let mut coords = floats(op.coordinate)?;
+    if let ImageDimension::Cube = op.ty.dim {
+        let (face, uv) = cube_face(coords);
+        coords = [uv[0], uv[1], 0.0];
+        layer = layer * 6 + face;
+    }
+    let mut offset = [0; 3];
+    if let Some(ref value) = op.offset {
+        for (o, scalar) in offset.iter_mut().zip(value.components()?) {
+            *o = scalar.to_bits() as i32;
+        }
+    }
+    let depth_ref = match op.depth_ref {
+        Some(value) => Some(value.scalar()?.as_float()?),
+        None => None,
+    };
+    let level_count = image.level_count();
+    let sampler = op.sampler;
+    let sample_op = SampleOp {
+        image,
+        sampler,
+        layer,
+        coords,
+        offset,
+        depth_ref,
+    };
+
+    let texel = if let Some(component) = op.gather {
+        sample_op.gather(component)
+    } else {
+        match op.level {
+            Level::Zero => sample_op.sample_level(0, sampler.mag_filter),
+            Level::Lod(lod) => {
+                let lod = lod.max(sampler.lod_clamp.start).min(sampler.lod_clamp.end);
+                // The filter is chosen before clamping to the levels of the view.
+                let filter = if lod <= 0.0 {
+                    sampler.mag_filter
+                } else {
+                    sampler.min_filter
+                };
+                let lod = lod.max(0.0).min((level_count - 1) as f32);
+                match sampler.mipmap_filter {
+                    wgt::FilterMode::Nearest => {
+                        sample_op.sample_level((lod + 0.5).floor() as u32, filter)
+                    }
+                    wgt::FilterMode::Linear => {
+                        let base = lod.floor();
+                        let frac = lod - base;
+                        let lower = sample_op.sample_level(base as u32, filter);
+                        if frac == 0.0 {
+                            lower
+                        } else {
+                            let upper = sample_op.sample_level(base as u32 + 1, filter);
+                            let mut v = [0.0; 4];
+                            for ((v, l), u) in v.iter_mut().zip(lower.iter()).zip(upper.iter()) {
+                                *v = l + (u - l) * frac;
+                            }
+                            v
+                        }
+                    }
+                }
+            }
+        }
+    };
+
+    let scalars = [
+        Scalar::Float(texel[0]),
+        Scalar::Float(texel[1]),
+        Scalar::Float(texel[2]),
+        Scalar::Float(texel[3]),
+    ];
+    Ok(match op.gather {
+        Some(_) => Value::Vector(scalars.iter().cloned().collect()),
+        None => op.ty.result(scalars),
+    })
+}
@@ -0,0 +1,680 @@
+//! Operators and built-in functions.
+
+use super::{
+    value::{Scalar, Value},
+    Error,
+};
+use arrayvec::ArrayVec;
+use naga::{BinaryOperator as Bo, MathFunction as Mf, RelationalFunction as Rf};
+
+type Components = ArrayVec<Scalar, 4>;
+
+/// Apply `fun` to every component of a scalar, vector or matrix.
+fn map(value: &Value, fun: &dyn Fn(Scalar) -> Result<Scalar, Error>) -> Result<Value, Error> {
+    match *value {
+        Value::Scalar(scalar) => Ok(Value::Scalar(fun(scalar)?)),
+        Value::Vector(ref components) => Ok(Value::Vector(
+            components
+                .iter()
+                .map(|&component| fun(component))
+                .collect::<Result<_, _>>()?,
+        )),
+        Value::Composite(ref columns) => Ok(Value::Composite(
+            columns
+                .iter()
+                .map(|column| map(column, fun))
+                .collect::<Result<_, _>>()?,
+        )),
+        _ => Err(Error::InvalidOperand("a numeric value")),
+    }
+}
+
+/// Make the operands have the same number of components, splatting scalars.
+fn broadcast(values: &[&Value]) -> Result<ArrayVec<Components, 3>, Error> {
+    let mut all = values
+        .iter()
+        .map(|value| value.components())
+        .collect::<Result<ArrayVec<_, 3>, _>>()?;
+    let count = all.iter().map(ArrayVec::len).max().unwrap_or(1);
+    for components in all.iter_mut() {
+        if components.len() == 1 && count > 1 {
+            let scalar = components[0];
+            components.extend((1..count).map(|_| scalar));
+        } else if components.len() != count {
+            return Err(Error::InvalidOperand("operands of matching sizes"));
+        }
+    }
+    Ok(all)
+}
+
+/// Apply `fun` to the components of two operands, pairwise.
+fn zip(
+    a: &Value,
+    b: &Value,
+    fun: &dyn Fn(Scalar, Scalar) -> Result<Scalar, Error>,
+) -> Result<Value, Error> {
+    match (a, b) {
+        (&Value::Composite(ref a_columns), &Value::Composite(ref b_columns)) => {
+            Ok(Value::Composite(
+                a_columns
+                    .iter()
+                    .zip(b_columns)
+                    .map(|(a, b)| zip(a, b, fun))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        (&Value::Composite(_), &Value::Scalar(b)) => map(a, &|a| fun(a, b)),
+        (&Value::Scalar(a), &Value::Composite(_)) => map(b, &|b| fun(a, b)),
+        _ => {
+            let operands = broadcast(&[a, b])?;
+            Ok(Value::from_components(
+                operands[0]
+                    .iter()
+                    .zip(&operands[1])
+                    .map(|(&a, &b)| fun(a, b))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+    }
+}
+
+/// Apply `fun` to the components of three operands.
+fn zip3(
+    a: &Value,
+    b: &Value,
+    c: &Value,
+    fun: &dyn Fn(Scalar, Scalar, Scalar) -> Result<Scalar, Error>,
+) -> Result<Value, Error> {
+    let operands = broadcast(&[a, b, c])?;
+    Ok(Value::from_components(
+        (0..operands[0].len())
+            .map(|i| fun(operands[0][i], operands[1][i], operands[2][i]))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+fn float(fun: fn(f32) -> f32) -> impl Fn(Scalar) -> Result<Scalar, Error> {
+    move |scalar| Ok(Scalar::Float(fun(scalar.as_float()?)))
+}
+
+fn float2(fun: fn(f32, f32) -> f32) -> impl Fn(Scalar, Scalar) -> Result<Scalar, Error> {
+    move |a, b| Ok(Scalar::Float(fun(a.as_float()?, b.as_float()?)))
+}
+
+fn floats(value: &Value) -> Result<ArrayVec<f32, 4>, Error> {
+    value
+        .components()?
+        .into_iter()
+        .map(Scalar::as_float)
+        .collect()
+}
+
+fn from_floats(values: impl IntoIterator<Item = f32>) -> Value {
+    Value::from_components(values.into_iter().map(Scalar::Float).collect())
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn columns(matrix: &Value) -> Result<Vec<ArrayVec<f32, 4>>, Error> {
+    match *matrix {
+        Value::Composite(ref columns) => columns.iter().map(floats).collect(),
+        _ => Err(Error::InvalidOperand("a matrix")),
+    }
+}
+
+fn from_columns(columns: impl IntoIterator<Item = ArrayVec<f32, 4>>) -> Value {
+    Value::Composite(columns.into_iter().map(from_floats).collect())
+}
+
+/// Round half to even, like `roundEven` in GLSL.
+fn round_even(value: f32) -> f32 {
+    let rounded = value.round();
+    if (value - value.trunc()).abs() == 0.5 {
+        2.0 * (value * 0.5).round()
+    } else {
+        rounded
+    }
+}
+
+fn determinant(columns: &[ArrayVec<f32, 4>]) -> f32 {
+    match columns.len() {
+        1 => columns[0][0],
+        n => (0..n)
+            .map(|c| {
+                // Laplace expansion along the first row.
+                let minor = columns
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != c)
+                    .map(|(_, column)| column.iter().skip(1).cloned().collect())
+                    .collect::<Vec<_>>();
+                let sign = if c % 2 == 0 { 1.0 } else { -1.0 };
+                sign * columns[c][0] * determinant(&minor)
+            })
+            .sum(),
+    }
+}
+
+fn extract_bits(scalar: Scalar, offset: u32, count: u32) -> Result<Scalar, Error> {
+    let offset = offset.min(32);
+    let count = count.min(32 - offset);
+    if count == 0 {
+        return Ok(Scalar::zero(scalar.kind()));
+    }
+    let shift = 32 - count;
+    Ok(match scalar {
+        // Shifting left then right sign-extends the extracted bits.
+        Scalar::Sint(value) => Scalar::Sint((value << (shift - offset)) >> shift),
+        Scalar::Uint(value) => Scalar::Uint((value << (shift - offset)) >> shift),
+        _ => return Err(Error::InvalidOperand("an integer")),
+    })
+}
+
+fn insert_bits(scalar: Scalar, new_bits: Scalar, offset: u32, count: u32) -> Result<Scalar, Error> {
+    let offset = offset.min(32);
+    let count = count.min(32 - offset);
+    let mask = if count == 32 {
+        !0
+    } else {
+        ((1u32 << count) - 1) << offset
+    };
+    let bits = (scalar.to_bits() & !mask) | ((new_bits.to_bits() << offset) & mask);
+    Ok(Scalar::from_bits(scalar.kind(), bits))
+}
+
+fn pack(value: &Value, bits: u32, fun: fn(f32) -> u32) -> Result<Value, Error> {
+    let packed = floats(value)?
+        .iter()
+        .enumerate()
+        .fold(0, |packed, (i, &x)| packed | (fun(x) << (i as u32 * bits)));
+    Ok(Value::Scalar(Scalar::Uint(packed)))
+}
+
+fn unpack(value: &Value, count: u32, fun: fn(u32) -> f32) -> Result<Value, Error> {
+    let bits = 32 / count;
+    let packed = value.scalar()?.to_bits();
+    let mask = (1u64 << bits) as u32 - 1;
+    Ok(from_floats(
+        (0..count).map(|i| fun((packed >> (i * bits)) & mask)),
+    ))
+}
+
+pub fn unary(op: naga::UnaryOperator, value: &Value) -> Result<Value, Error> {
+    map(value, &|scalar| {
+        Ok(match (op, scalar) {
+            (naga::UnaryOperator::Negate, Scalar::Float(value)) => Scalar::Float(-value),
+            (naga::UnaryOperator::Negate, Scalar::Sint(value)) => {
+                Scalar::Sint(value.wrapping_neg())
+            }
+            (naga::UnaryOperator::Not, Scalar::Bool(value)) => Scalar::Bool(!value),
+            (naga::UnaryOperator::Not, Scalar::Sint(value)) => Scalar::Sint(!value),
+            (naga::UnaryOperator::Not, Scalar::Uint(value)) => Scalar::Uint(!value),
+            _ => return Err(Error::InvalidOperand("an operand of the unary operator")),
+        })
+    })
+}
+
+fn binary_scalar(op: Bo, a: Scalar, b: Scalar) -> Result<Scalar, Error> {
+    use std::cmp::Ordering;
+
+    let ordering = match (a, b) {
+        (Scalar::Float(a), Scalar::Float(b)) => a.partial_cmp(&b),
+        (Scalar::Sint(a), Scalar::Sint(b)) => Some(a.cmp(&b)),
+        (Scalar::Uint(a), Scalar::Uint(b)) => Some(a.cmp(&b)),
+        (Scalar::Bool(a), Scalar::Bool(b)) => Some(a.cmp(&b)),
+        _ => None,
+    };
+    let comparison = match op {
+        Bo::Equal => Some(ordering == Some(Ordering::Equal)),
+        Bo::NotEqual => Some(ordering != Some(Ordering::Equal)),
+        Bo::Less => Some(ordering == Some(Ordering::Less)),
+        Bo::LessEqual => Some(matches!(ordering, Some(o) if o != Ordering::Greater)),
+        Bo::Greater => Some(ordering == Some(Ordering::Greater)),
+        Bo::GreaterEqual => Some(matches!(ordering, Some(o) if o != Ordering::Less)),
+        _ => None,
+    };
+    if let Some(result) = comparison {
+        return Ok(Scalar::Bool(result));
+    }
+
+    Ok(match (a, b) {
+        (Scalar::Float(a), Scalar::Float(b)) => Scalar::Float(match op {
+            Bo::Add => a + b,
+            Bo::Subtract => a - b,
+            Bo::Multiply => a * b,
+            Bo::Divide => a / b,
+            Bo::Modulo => a - b * (a / b).trunc(),
+            _ => return Err(Error::InvalidOperand("float operands")),
+        }),
+        // Integer division by zero and overflow are defined to return the dividend.
+        (Scalar::Sint(a), Scalar::Sint(b)) => Scalar::Sint(match op {
+            Bo::Add => a.wrapping_add(b),
+            Bo::Subtract => a.wrapping_sub(b),
+            Bo::Multiply => a.wrapping_mul(b),
+            Bo::Divide => a.checked_div(b).unwrap_or(a),
+            Bo::Modulo => a.checked_rem(b).unwrap_or(0),
+            Bo::And => a & b,
+            Bo::ExclusiveOr => a ^ b,
+            Bo::InclusiveOr => a | b,
+            _ => return Err(Error::InvalidOperand("signed integer operands")),
+        }),
+        (Scalar::Uint(a), Scalar::Uint(b)) => Scalar::Uint(match op {
+            Bo::Add => a.wrapping_add(b),
+            Bo::Subtract => a.wrapping_sub(b),
+            Bo::Multiply => a.wrapping_mul(b),
+            Bo::Divide => a.checked_div(b).unwrap_or(a),
+            Bo::Modulo => a.checked_rem(b).unwrap_or(0),
+            Bo::And => a & b,
+            Bo::ExclusiveOr => a ^ b,
+            Bo::InclusiveOr => a | b,
+            Bo::ShiftLeft => a.wrapping_shl(b),
+            Bo::ShiftRight => a.wrapping_shr(b),
+            _ => return Err(Error::InvalidOperand("unsigned integer operands")),
+        }),
+        (Scalar::Sint(a), Scalar::Uint(b)) => Scalar::Sint(match op {
+            Bo::ShiftLeft => a.wrapping_shl(b),
+            // Arithmetic shift, since `a` is signed.
+            Bo::ShiftRight => a.wrapping_shr(b),
+            _ => return Err(Error::InvalidOperand("a shift of a signed integer")),
+        }),
+        (Scalar::Bool(a), Scalar::Bool(b)) => Scalar::Bool(match op {
+            Bo::And | Bo::LogicalAnd => a & b,
+            Bo::InclusiveOr | Bo::LogicalOr => a | b,
+            Bo::ExclusiveOr => a ^ b,
+            _ => return Err(Error::InvalidOperand("boolean operands")),
+        }),
+        _ => return Err(Error::InvalidOperand("operands of the same kind")),
+    })
+}
+
+pub fn binary(op: Bo, left: &Value, right: &Value) -> Result<Value, Error> {
+    if op == Bo::Multiply {
+        match (left, right) {
+            // matrix * vector
+            (&Value::Composite(_), &Value::Vector(_)) => {
+                let vector = floats(right)?;
+                let columns = columns(left)?;
+                let rows = columns.first().map_or(0, ArrayVec::len);
+                return Ok(from_floats((0..rows).map(|r| {
+                    columns
+                        .iter()
+                        .zip(&vector)
+                        .map(|(column, v)| column[r] * v)
+                        .sum()
+                })));
+            }
+            // vector * matrix
+            (&Value::Vector(_), &Value::Composite(_)) => {
+                let vector = floats(left)?;
+                let columns = columns(right)?;
+                return Ok(from_floats(
+                    columns.iter().map(|column| dot(&vector, column)),
+                ));
+            }
+            // matrix * matrix
+            (&Value::Composite(_), &Value::Composite(_)) => {
+                let a = columns(left)?;
+                let b = columns(right)?;
+                let rows = a.first().map_or(0, ArrayVec::len);
+                return Ok(from_columns(b.iter().map(|b_column| {
+                    (0..rows)
+                        .map(|r| {
+                            a.iter()
+                                .zip(b_column)
+                                .map(|(a_column, v)| a_column[r] * v)
+                                .sum()
+                        })
+                        .collect()
+                })));
+            }
+            _ => {}
+        }
+    }
+    zip(left, right, &|a, b| binary_scalar(op, a, b))
+}
+
+pub fn select(condition: &Value, accept: &Value, reject: &Value) -> Result<Value, Error> {
+    match *condition {
+        Value::Scalar(scalar) => Ok(if scalar.as_bool()? {
+            accept.clone()
+        } else {
+            reject.clone()
+        }),
+        Value::Vector(_) => zip3(condition, accept, reject, &|c, a, r| {
+            Ok(if c.as_bool()? { a } else { r })
+        }),
+        _ => Err(Error::InvalidOperand("a boolean condition")),
+    }
+}
+
+pub fn relational(fun: Rf, argument: &Value) -> Result<Value, Error> {
+    let components = argument.components()?;
+    Ok(match fun {
+        Rf::All | Rf::Any => {
+            let bools = components
+                .into_iter()
+                .map(Scalar::as_bool)
+                .collect::<Result<ArrayVec<_, 4>, _>>()?;
+            Value::Scalar(Scalar::Bool(if fun == Rf::All {
+                bools.iter().all(|&b| b)
+            } else {
+                bools.iter().any(|&b| b)
+            }))
+        }
+        Rf::IsNan | Rf::IsInf | Rf::IsFinite | Rf::IsNormal => {
+            let test: fn(f32) -> bool = match fun {
+                Rf::IsNan => f32::is_nan,
+                Rf::IsInf => f32::is_infinite,
+                Rf::IsFinite => f32::is_finite,
+                _ => f32::is_normal,
+            };
+            map(argument, &|scalar| {
+                Ok(Scalar::Bool(test(scalar.as_float()?)))
+            })?
+        }
+    })
+}
+
+/// Splits the argument of `modf` or `frexp` into the result
+/// and the part that is stored through the pointer argument.
+pub fn split(fun: Mf, arg: &Value) -> Result<(Value, Value), Error> {
+    let split_scalar = |scalar: Scalar| -> Result<(Scalar, Scalar), Error> {
+        let x = scalar.as_float()?;
+        Ok(match fun {
+            Mf::Modf => (Scalar::Float(x - x.trunc()), Scalar::Float(x.trunc())),
+            _ if x == 0.0 || !x.is_finite() => (Scalar::Float(x), Scalar::Sint(0)),
+            _ => {
+                let exponent = x.abs().log2().floor() as i32 + 1;
+                let mantissa = x / 2f32.powi(exponent);
+                // Correct the rounding of the logarithm near powers of two.
+                if mantissa.abs() >= 1.0 {
+                    (Scalar::Float(mantissa / 2.0), Scalar::Sint(exponent + 1))
+                } else if mantissa.abs() < 0.5 {
+                    (Scalar::Float(mantissa * 2.0), Scalar::Sint(exponent - 1))
+                } else {
+                    (Scalar::Float(mantissa), Scalar::Sint(exponent))
+                }
+            }
+        })
+    };
+    let result = map(arg, &|scalar| Ok(split_scalar(scalar)?.0))?;
+    let output = map(arg, &|scalar| Ok(split_scalar(scalar)?.1))?;
+    Ok((result, output))
+}
+
+pub fn math(fun: Mf, args: &[Value]) -> Result<Value, Error> {
+    let arg = &args[0];
+    let arg1 = || {
+        args.get(1)
+            .ok_or(Error::InvalidOperand("a second argument"))
+    };
+    let arg2 = || args.get(2).ok_or(Error::InvalidOperand("a third argument"));
+    let arg3 = || {
+        args.get(3)
+            .ok_or(Error::InvalidOperand("a fourth argument"))
+    };
+
+    Ok(match fun {
+        Mf::Abs => map(arg, &|scalar| {
+            Ok(match scalar {
+                Scalar::Float(value) => Scalar::Float(value.abs()),
+                Scalar::Sint(value) => Scalar::Sint(value.wrapping_abs()),
+                other => other,
+            })
+        })?,
+        Mf::Min | Mf::Max => zip(arg, arg1()?, &|a, b| {
+            let less = binary_scalar(Bo::Less, a, b)?.as_bool()?;
+            Ok(match (a, b) {
+                (Scalar::Float(a), Scalar::Float(b)) if fun == Mf::Min => Scalar::Float(a.min(b)),
+                (Scalar::Float(a), Scalar::Float(b)) => Scalar::Float(a.max(b)),
+                _ if less == (fun == Mf::Min) => a,
+                _ => b,
+            })
+        })?,
+        Mf::Clamp => {
+            let lower = math(Mf::Max, &[arg.clone(), arg1()?.clone()])?;
+            math(Mf::Min, &[lower, arg2()?.clone()])?
+        }
+        Mf::Cos => map(arg, &float(f32::cos))?,
+        Mf::Cosh => map(arg, &float(f32::cosh))?,
+        Mf::Sin => map(arg, &float(f32::sin))?,
+        Mf::Sinh => map(arg, &float(f32::sinh))?,
+        Mf::Tan => map(arg, &float(f32::tan))?,
+        Mf::Tanh => map(arg, &float(f32::tanh))?,
+        Mf::Acos => map(arg, &float(f32::acos))?,
+        Mf::Asin => map(arg, &float(f32::asin))?,
+        Mf::Atan => map(arg, &float(f32::atan))?,
+        Mf::Atan2 => zip(arg, arg1()?, &float2(f32::atan2))?,
+        Mf::Asinh => map(arg, &float(f32::asinh))?,
+        Mf::Acosh => map(arg, &float(f32::acosh))?,
+        Mf::Atanh => map(arg, &float(f32::atanh))?,
+        Mf::Ceil => map(arg, &float(f32::ceil))?,
+        Mf::Floor => map(arg, &float(f32::floor))?,
+        Mf::Round => map(arg, &float(round_even))?,
+        Mf::Fract => map(arg, &float(|x| x - x.floor()))?,
+        Mf::Trunc => map(arg, &float(f32::trunc))?,
+        Mf::Ldexp => zip(arg, arg1()?, &|x, exp| match exp {
+            Scalar::Sint(exp) => Ok(Scalar::Float(x.as_float()? * 2f32.powi(exp))),
+            _ => Err(Error::InvalidOperand("an integer exponent")),
+        })?,
+        Mf::Exp => map(arg, &float(f32::exp))?,
+        Mf::Exp2 => map(arg, &float(f32::exp2))?,
+        Mf::Log => map(arg, &float(f32::ln))?,
+        Mf::Log2 => map(arg, &float(f32::log2))?,
+        Mf::Pow => zip(arg, arg1()?, &float2(f32::powf))?,
+        Mf::Dot => {
+            let products = binary(Bo::Multiply, arg, arg1()?)?.components()?;
+            let mut sum = products[0];
+            for &product in products.iter().skip(1) {
+                sum = binary_scalar(Bo::Add, sum, product)?;
+            }
+            Value::Scalar(sum)
+        }
+        Mf::Outer => {
+            let a = floats(arg)?;
+            let b = floats(arg1()?)?;
+            from_columns(b.iter().map(|&b| a.iter().map(|&a| a * b).collect()))
+        }
+        Mf::Cross => {
+            let a = floats(arg)?;
+            let b = floats(arg1()?)?;
+            if a.len() != 3 || b.len() != 3 {
+                return Err(Error::InvalidOperand("3-component vectors"));
+            }
+            from_floats(vec![
+                a[1] * b[2] - a[2] * b[1],
+                a[2] * b[0] - a[0] * b[2],
+                a[0] * b[1] - a[1] * b[0],
+            ])
+        }
+        Mf::Distance => {
+            let difference = binary(Bo::Subtract, arg, arg1()?)?;
+            math(Mf::Length, &[difference])?
+        }
+        Mf::Length => {
+            let a = floats(arg)?;
+            Value::Scalar(Scalar::Float(dot(&a, &a).sqrt()))
+        }
+        Mf::Normalize => {
+            let a = floats(arg)?;
+            let length = dot(&a, &a).sqrt();
+            from_floats(a.iter().map(|x| x / length))
+        }
+        Mf::FaceForward => {
+            let n = floats(arg)?;
+            let i = floats(arg1()?)?;
+            let n_ref = floats(arg2()?)?;
+            let sign = if dot(&n_ref, &i) < 0.0 { 1.0 } else { -1.0 };
+            from_floats(n.iter().map(|x| sign * x))
+        }
+        Mf::Reflect => {
+            let i = floats(arg)?;
+            let n = floats(arg1()?)?;
+            let d = dot(&n, &i);
+            from_floats(i.iter().zip(&n).map(|(i, n)| i - 2.0 * d * n))
+        }
+        Mf::Refract => {
+            let i = floats(arg)?;
+            let n = floats(arg1()?)?;
+            let eta = arg2()?.scalar()?.as_float()?;
+            let d = dot(&n, &i);
+            let k = 1.0 - eta * eta * (1.0 - d * d);
+            if k < 0.0 {
+                from_floats(i.iter().map(|_| 0.0))
+            } else {
+                from_floats(
+                    i.iter()
+                        .zip(&n)
+                        .map(|(i, n)| eta * i - (eta * d + k.sqrt()) * n),
+                )
+            }
+        }
+        Mf::Sign => map(arg, &|scalar| {
+            Ok(match scalar {
+                Scalar::Float(value) if value == 0.0 || value.is_nan() => Scalar::Float(0.0),
+                Scalar::Float(value) => Scalar::Float(value.signum()),
+                Scalar::Sint(value) => Scalar::Sint(value.signum()),
+                _ => return Err(Error::InvalidOperand("a signed value")),
+            })
+        })?,
+        Mf::Fma => zip3(arg, arg1()?, arg2()?, &|a, b, c| {
+            Ok(Scalar::Float(
+                a.as_float()?.mul_add(b.as_float()?, c.as_float()?),
+            ))
+        })?,
+        Mf::Mix => zip3(arg, arg1()?, arg2()?, &|x, y, a| {
+            let (x, y, a) = (x.as_float()?, y.as_float()?, a.as_float()?);
+            Ok(Scalar::Float(x * (1.0 - a) + y * a))
+        })?,
+        Mf::Step => zip(
+            arg,
+            arg1()?,
+            &float2(|edge, x| if x < edge { 0.0 } else { 1.0 }),
+        )?,
+        Mf::SmoothStep => zip3(arg, arg1()?, arg2()?, &|low, high, x| {
+            let (low, high, x) = (low.as_float()?, high.as_float()?, x.as_float()?);
+            let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
+            Ok(Scalar::Float(t * t * (3.0 - 2.0 * t)))
+        })?,
+        Mf::Sqrt => map(arg, &float(f32::sqrt))?,
+        Mf::InverseSqrt => map(arg, &float(|x| 1.0 / x.sqrt()))?,
+        Mf::Transpose => {
+            let columns = columns(arg)?;
+            let rows = columns.first().map_or(0, ArrayVec::len);
+            from_columns((0..rows).map(|r| columns.iter().map(|column| column[r]).collect()))
+        }
+        Mf::Determinant => Value::Scalar(Scalar::Float(determinant(&columns(arg)?))),
+        Mf::CountOneBits => map(arg, &|scalar| {
+            Ok(Scalar::from_bits(
+                scalar.kind(),
+                scalar.to_bits().count_ones(),
+            ))
+        })?,
+        Mf::ReverseBits => map(arg, &|scalar| {
+            Ok(Scalar::from_bits(
+                scalar.kind(),
+                scalar.to_bits().reverse_bits(),
+            ))
+        })?,
+        Mf::ExtractBits => {
+            let offset = arg1()?.scalar()?.to_bits();
+            let count = arg2()?.scalar()?.to_bits();
+            map(arg, &|scalar| extract_bits(scalar, offset, count))?
+        }
+        Mf::InsertBits => {
+            let offset = arg2()?.scalar()?.to_bits();
+            let count = arg3()?.scalar()?.to_bits();
+            zip(arg, arg1()?, &|scalar, new_bits| {
+                insert_bits(scalar, new_bits, offset, count)
+            })?
+        }
+        Mf::Pack4x8snorm => pack(arg, 8, |x| {
+            ((x.clamp(-1.0, 1.0) * 127.0).round() as i32 as u32) & 0xFF
+        })?,
+        Mf::Pack4x8unorm => pack(arg, 8, |x| (x.clamp(0.0, 1.0) * 255.0).round() as u32)?,
+        Mf::Pack2x16snorm => pack(arg, 16, |x| {
+            ((x.clamp(-1.0, 1.0) * 32767.0).round() as i32 as u32) & 0xFFFF
+        })?,
+        Mf::Pack2x16unorm => pack(arg, 16, |x| (x.clamp(0.0, 1.0) * 65535.0).round() as u32)?,
+        Mf::Pack2x16float => pack(arg, 16, |x| crate::cpu::format::f32_to_f16(x) as u32)?,
+        Mf::Unpack4x8snorm => unpack(arg, 4, |bits| (bits as u8 as i8 as f32 / 127.0).max(-1.0))?,
+        Mf::Unpack4x8unorm => unpack(arg, 4, |bits| bits as f32 / 255.0)?,
+        Mf::Unpack2x16snorm => unpack(arg, 2, |bits| {
+            (bits as u16 as i16 as f32 / 32767.0).max(-1.0)
+        })?,
+        Mf::Unpack2x16unorm => unpack(arg, 2, |bits| bits as f32 / 65535.0)?,
+        Mf::Unpack2x16float => unpack(arg, 2, |bits| crate::cpu::format::f16_to_f32(bits as u16))?,
+        Mf::Modf | Mf::Frexp => split(fun, arg)?.0,
+        Mf::Inverse => return Err(Error::Unsupported("matrix inverse")),
+    })
+}
+
+/// Convert or reinterpret every component of a value.
+pub fn cast(value: &Value, kind: naga::ScalarKind, convert: bool) -> Result<Value, Error> {
+    map(value, &|scalar| {
+        Ok(if convert {
+            scalar.convert(kind)
+        } else {
+            Scalar::from_bits(kind, scalar.to_bits())
+        })
+    })
+}
+
+#[test]
+fn test_math() {
+    let vector = |values: &[f32]| from_floats(values.iter().cloned());
+    let check = |value: Value, expected: &[f32]| {
+        assert_eq!(floats(&value).unwrap().as_slice(), expected);
+    };
+
+    let matrix = from_columns(vec![
+        [1.0, 2.0].iter().cloned().collect(),
+        [3.0, 4.0].iter().cloned().collect(),
+    ]);
+    check(
+        binary(Bo::Multiply, &matrix, &vector(&[1.0, 1.0])).unwrap(),
+        &[4.0, 6.0],
+    );
+    check(
+        binary(Bo::Multiply, &vector(&[1.0, 1.0]), &matrix).unwrap(),
+        &[3.0, 7.0],
+    );
+    check(math(Mf::Determinant, &[matrix]).unwrap(), &[-2.0]);
+    check(
+        binary(
+            Bo::Multiply,
+            &vector(&[1.0, 2.0]),
+            &Value::Scalar(Scalar::Float(2.0)),
+        )
+        .unwrap(),
+        &[2.0, 4.0],
+    );
+    check(
+        math(Mf::Round, &[vector(&[0.5, 1.5, -2.5])]).unwrap(),
+        &[0.0, 2.0, -2.0],
+    );
+    check(
+        math(
+            Mf::Unpack4x8unorm,
+            &[Value::Scalar(Scalar::Uint(0xFF00_FF00))],
+        )
+        .unwrap(),
+        &[0.0, 1.0, 0.0, 1.0],
+    );
+
+    let int = |value| Value::Scalar(Scalar::Sint(value));
+    let result = binary(Bo::Divide, &int(i32::MIN), &int(-1)).unwrap();
+    assert_eq!(result.scalar().unwrap(), Scalar::Sint(i32::MIN));
+    let result = math(
+        Mf::ExtractBits,
+        &[
+            int(-8),
+            Value::Scalar(Scalar::Uint(2)),
+            Value::Scalar(Scalar::Uint(2)),
+        ],
+    )
+    .unwrap();
+    assert_eq!(result.scalar().unwrap(), Scalar::Sint(-2));
+}
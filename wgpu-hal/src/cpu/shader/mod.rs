@@ -0,0 +1,536 @@
+/*!
+Interpreter of the naga IR.
+
+Shaders are executed straight from the validated module, one invocation at a time.
+The expression types come from the validator's `ModuleInfo`, so they don't
+need to be resolved again.
+
+## Memory
+
+Everything addressable lives in byte arrays laid out exactly like naga lays
+out the types: function locals, private and workgroup variables, as well as
+the uniform and storage buffers. Pointers are byte offsets into one of those,
+which keeps `Access` chains, `ArrayLength` and atomics straightforward.
+Out-of-bounds reads return zeros, and out-of-bounds writes are discarded.
+
+## Scheduling
+
+Instead of recursing over blocks, an invocation keeps an explicit stack of
+call frames and block cursors, so that it can be suspended at any statement.
+Invocations of a workgroup take turns running until their next barrier,
+which gives barriers and workgroup memory their expected semantics.
+
+Fragment invocations that need derivatives run as quads of 2x2 pixels,
+evaluating the expressions of each `Emit` in lockstep, so that
+a derivative can look at the values of the neighbors.
+*/
+
+mod exec;
+mod image;
+mod math;
+mod value;
+
+use std::sync::Arc;
+
+use naga::Handle;
+
+pub(super) use exec::{Invocation, Status};
+pub(super) use image::{compare, texel_scalars};
+pub(super) use value::{Scalar, Value};
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub(super) enum Error {
+    #[error("{0} are not supported")]
+    Unsupported(&'static str),
+    #[error("expected {0}")]
+    InvalidOperand(&'static str),
+    #[error("nothing is bound to {0:?}")]
+    MissingBinding(naga::ResourceBinding),
+}
+
+/// Round an offset up to the alignment of any type.
+fn align(offset: u32) -> u32 {
+    (offset + 15) & !15
+}
+
+/// Initial contents of a set of variables, laid out one after another.
+#[derive(Debug, Default)]
+struct VariableLayout {
+    offsets: Vec<u32>,
+    memory: Vec<u8>,
+}
+
+impl VariableLayout {
+    fn add(
+        &mut self,
+        module: &naga::Module,
+        ty: Handle<naga::Type>,
+        init: Option<Handle<naga::Constant>>,
+    ) -> Result<(), Error> {
+        let offset = align(self.memory.len() as u32);
+        let inner = &module.types[ty].inner;
+        self.offsets.push(offset);
+        self.memory
+            .resize((offset + inner.span(&module.constants)) as usize, 0);
+        if let Some(constant) = init {
+            Value::constant(module, constant)?.write(module, inner, &mut self.memory, offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reference to a function of a module.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum FunctionRef {
+    Function(Handle<naga::Function>),
+    EntryPoint(usize),
+}
+
+/// A shader module, prepared for execution.
+#[derive(Debug)]
+pub(super) struct Program {
+    shader: Arc<crate::NagaShader>,
+    /// Offsets of the private and workgroup variables, indexed by global handle.
+    global_offsets: Vec<u32>,
+    private: VariableLayout,
+    workgroup_memory_size: u32,
+    functions: Vec<VariableLayout>,
+    entry_points: Vec<VariableLayout>,
+}
+
+impl Program {
+    pub fn new(shader: Arc<crate::NagaShader>) -> Result<Self, Error> {
+        let module = &shader.module;
+
+        let mut global_offsets = Vec::with_capacity(module.global_variables.len());
+        let mut private = VariableLayout::default();
+        let mut workgroup = VariableLayout::default();
+        for (_, var) in module.global_variables.iter() {
+            let offset = match var.class {
+                naga::StorageClass::Private => {
+                    private.add(module, var.ty, var.init)?;
+                    private.offsets.last().cloned()
+                }
+                naga::StorageClass::WorkGroup => {
+                    // Workgroup memory is zero-initialized.
+                    workgroup.add(module, var.ty, None)?;
+                    workgroup.offsets.last().cloned()
+                }
+                _ => None,
+            };
+            global_offsets.push(offset.unwrap_or(0));
+        }
+
+        let locals = |function: &naga::Function| -> Result<VariableLayout, Error> {
+            let mut layout = VariableLayout::default();
+            for (_, local) in function.local_variables.iter() {
+                layout.add(module, local.ty, local.init)?;
+            }
+            Ok(layout)
+        };
+        let functions = module
+            .functions
+            .iter()
+            .map(|(_, function)| locals(function))
+            .collect::<Result<_, _>>()?;
+        let entry_points = module
+            .entry_points
+            .iter()
+            .map(|ep| locals(&ep.function))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            global_offsets,
+            private,
+            workgroup_memory_size: workgroup.memory.len() as u32,
+            functions,
+            entry_points,
+            shader,
+        })
+    }
+
+    pub fn module(&self) -> &naga::Module {
+        &self.shader.module
+    }
+
+    pub fn entry_point_index(&self, stage: naga::ShaderStage, name: &str) -> Option<usize> {
+        self.module()
+            .entry_points
+            .iter()
+            .position(|ep| ep.stage == stage && ep.name == name)
+    }
+
+    fn function(
+        &self,
+        function: FunctionRef,
+    ) -> (&naga::Function, &naga::valid::FunctionInfo, &VariableLayout) {
+        match function {
+            FunctionRef::Function(handle) => (
+                &self.module().functions[handle],
+                &self.shader.info[handle],
+                &self.functions[handle.index()],
+            ),
+            FunctionRef::EntryPoint(index) => (
+                &self.module().entry_points[index].function,
+                self.shader.info.get_entry_point(index),
+                &self.entry_points[index],
+            ),
+        }
+    }
+
+    /// Check that everything used by an entry point can be executed.
+    pub fn check_entry_point(&self, index: usize) -> Result<(), Error> {
+        let module = self.module();
+        let info = self.shader.info.get_entry_point(index);
+        for (handle, var) in module.global_variables.iter() {
+            if !info[handle].is_empty() && var.class == naga::StorageClass::PushConstant {
+                return Err(Error::Unsupported("push constants"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the functions called by an entry point, directly or not.
+    fn called_functions(&self, entry_point: usize) -> Vec<Handle<naga::Function>> {
+        let mut called = Vec::new();
+        collect_calls(
+            &self.module().entry_points[entry_point].function.body,
+            &mut called,
+        );
+        let mut index = 0;
+        while index < called.len() {
+            let mut calls = Vec::new();
+            collect_calls(&self.module().functions[called[index]].body, &mut calls);
+            for callee in calls {
+                if !called.contains(&callee) {
+                    called.push(callee);
+                }
+            }
+            index += 1;
+        }
+        called
+    }
+
+    /// Whether an entry point takes derivatives, explicitly or to select a mip level.
+    pub fn uses_derivatives(&self, entry_point: usize) -> bool {
+        let module = self.module();
+        std::iter::once(&module.entry_points[entry_point].function)
+            .chain(
+                self.called_functions(entry_point)
+                    .into_iter()
+                    .map(|handle| &module.functions[handle]),
+            )
+            .any(|function| {
+                function.expressions.iter().any(|(_, expression)| {
+                    matches!(
+                        *expression,
+                        naga::Expression::Derivative { .. }
+                            | naga::Expression::ImageSample {
+                                level: naga::SampleLevel::Auto | naga::SampleLevel::Bias(_),
+                                ..
+                            }
+                    )
+                })
+            })
+    }
+
+    /// Resolve the resources used by an entry point, indexed by global variable.
+    pub fn bind<R>(
+        &self,
+        entry_point: usize,
+        lookup: impl Fn(&naga::ResourceBinding) -> Option<R>,
+    ) -> Result<Vec<Option<R>>, Error> {
+        let info = self.shader.info.get_entry_point(entry_point);
+        self.module()
+            .global_variables
+            .iter()
+            .map(|(handle, var)| match var.binding {
+                Some(ref binding) if !info[handle].is_empty() => lookup(binding)
+                    .map(Some)
+                    .ok_or_else(|| Error::MissingBinding(binding.clone())),
+                _ => Ok(None),
+            })
+            .collect()
+    }
+
+    /// Run a compute entry point over a grid of workgroups.
+    pub fn dispatch(
+        &self,
+        entry_point: usize,
+        resources: &[Option<Resource>],
+        group_count: [u32; 3],
+    ) -> Result<(), Error> {
+        let env = Environment::new(self, resources);
+        let [size_x, size_y, size_z] = self.module().entry_points[entry_point].workgroup_size;
+        let u32x3 = |v: [u32; 3]| Value::Vector(v.iter().map(|&c| Scalar::Uint(c)).collect());
+
+        let mut workgroup = vec![0; self.workgroup_memory_size as usize];
+        let mut invocations = Vec::new();
+        for group_z in 0..group_count[2] {
+            for group_y in 0..group_count[1] {
+                for group_x in 0..group_count[0] {
+                    let group_id = [group_x, group_y, group_z];
+                    workgroup.iter_mut().for_each(|byte| *byte = 0);
+                    invocations.clear();
+                    for index in 0..size_x * size_y * size_z {
+                        let local_id = [
+                            index % size_x,
+                            index / size_x % size_y,
+                            index / (size_x * size_y),
+                        ];
+                        let mut builtin = |binding: &naga::Binding, _: &naga::TypeInner| {
+                            Ok(match *binding {
+                                naga::Binding::BuiltIn(naga::BuiltIn::GlobalInvocationId) => {
+                                    u32x3([
+                                        group_x * size_x + local_id[0],
+                                        group_y * size_y + local_id[1],
+                                        group_z * size_z + local_id[2],
+                                    ])
+                                }
+                                naga::Binding::BuiltIn(naga::BuiltIn::LocalInvocationId) => {
+                                    u32x3(local_id)
+                                }
+                                naga::Binding::BuiltIn(naga::BuiltIn::LocalInvocationIndex) => {
+                                    Value::Scalar(Scalar::Uint(index))
+                                }
+                                naga::Binding::BuiltIn(naga::BuiltIn::WorkGroupId) => {
+                                    u32x3(group_id)
+                                }
+                                naga::Binding::BuiltIn(naga::BuiltIn::WorkGroupSize) => {
+                                    u32x3([size_x, size_y, size_z])
+                                }
+                                naga::Binding::BuiltIn(naga::BuiltIn::NumWorkGroups) => {
+                                    u32x3(group_count)
+                                }
+                                _ => return Err(Error::Unsupported("compute shader inputs")),
+                            })
+                        };
+                        let arguments = env.entry_arguments(entry_point, &mut builtin)?;
+                        invocations.push(Invocation::new(
+                            &env,
+                            FunctionRef::EntryPoint(entry_point),
+                            arguments,
+                        ));
+                    }
+
+                    // Run the invocations in turns, from one barrier to the next.
+                    loop {
+                        let mut running = false;
+                        for invocation in invocations.iter_mut() {
+                            if let Status::Barrier = invocation.run(&mut workgroup)? {
+                                running = true;
+                            }
+                        }
+                        if !running {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn collect_calls(block: &naga::Block, calls: &mut Vec<Handle<naga::Function>>) {
+    for statement in block.iter() {
+        match *statement {
+            naga::Statement::Block(ref block) => collect_calls(block, calls),
+            naga::Statement::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_calls(accept, calls);
+                collect_calls(reject, calls);
+            }
+            naga::Statement::Switch { ref cases, .. } => {
+                for case in cases {
+                    collect_calls(&case.body, calls);
+                }
+            }
+            naga::Statement::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                collect_calls(body, calls);
+                collect_calls(continuing, calls);
+            }
+            naga::Statement::Call { function, .. } => calls.push(function),
+            _ => {}
+        }
+    }
+}
+
+/// Range of a buffer bound to a global variable.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct BufferSlice {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl BufferSlice {
+    /// Create a slice of a buffer.
+    ///
+    /// # Safety
+    ///
+    /// The buffer has to outlive the slice.
+    pub unsafe fn new(
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferAddress,
+    ) -> Self {
+        Self {
+            ptr: buffer.memory.ptr().add(offset as usize),
+            len: size as usize,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn bytes_mut(&self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+/// Resource bound to a global variable.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Resource<'a> {
+    Buffer(BufferSlice),
+    Texture(&'a super::TextureView),
+    Sampler(&'a super::Sampler),
+}
+
+impl<'a> Resource<'a> {
+    /// Create the resource seen by the shaders for a binding.
+    ///
+    /// # Safety
+    ///
+    /// The buffers have to outlive the resource.
+    pub unsafe fn new(binding: &'a super::Binding) -> Self {
+        match *binding {
+            super::Binding::Buffer(ref bb) => {
+                Self::Buffer(BufferSlice::new(&bb.buffer, bb.offset, bb.size))
+            }
+            super::Binding::Texture(ref view) => Self::Texture(view),
+            super::Binding::Sampler(ref sampler) => Self::Sampler(sampler),
+        }
+    }
+
+    /// Create the resources of a list of bindings, indexed by global variable.
+    ///
+    /// # Safety
+    ///
+    /// The buffers have to outlive the resources.
+    pub unsafe fn from_bindings(bindings: &'a [Option<super::Binding>]) -> Vec<Option<Self>> {
+        bindings
+            .iter()
+            .map(|binding| binding.as_ref().map(|b| Self::new(b)))
+            .collect()
+    }
+}
+
+/// Everything an invocation can see beside its own memory.
+pub(super) struct Environment<'a> {
+    program: &'a Program,
+    resources: &'a [Option<Resource<'a>>],
+}
+
+impl<'a> Environment<'a> {
+    pub fn new(program: &'a Program, resources: &'a [Option<Resource<'a>>]) -> Self {
+        Self { program, resources }
+    }
+
+    fn buffer(&self, handle: Handle<naga::GlobalVariable>) -> Result<&'a BufferSlice, Error> {
+        match self.resources.get(handle.index()) {
+            Some(&Some(Resource::Buffer(ref slice))) => Ok(slice),
+            _ => Err(Error::InvalidOperand("a bound buffer")),
+        }
+    }
+
+    fn texture(
+        &self,
+        handle: Handle<naga::GlobalVariable>,
+    ) -> Result<&'a super::TextureView, Error> {
+        match self.resources.get(handle.index()) {
+            Some(&Some(Resource::Texture(view))) => Ok(view),
+            _ => Err(Error::InvalidOperand("a bound texture")),
+        }
+    }
+
+    fn sampler(&self, handle: Handle<naga::GlobalVariable>) -> Result<&'a super::Sampler, Error> {
+        match self.resources.get(handle.index()) {
+            Some(&Some(Resource::Sampler(sampler))) => Ok(sampler),
+            _ => Err(Error::InvalidOperand("a bound sampler")),
+        }
+    }
+
+    /// Flatten the result of an entry point into values for each binding.
+    pub fn entry_outputs(
+        &self,
+        entry_point: usize,
+        result: Option<Value>,
+    ) -> Result<Vec<(&'a naga::Binding, Value)>, Error> {
+        let module = self.program.module();
+        let function_result = match module.entry_points[entry_point].function.result {
+            Some(ref function_result) => function_result,
+            None => return Ok(Vec::new()),
+        };
+        let result = result.ok_or(Error::InvalidOperand("a returned value"))?;
+        match (
+            function_result.binding.as_ref(),
+            &module.types[function_result.ty].inner,
+        ) {
+            (Some(binding), _) => Ok(vec![(binding, result)]),
+            (None, &naga::TypeInner::Struct { ref members, .. }) => members
+                .iter()
+                .enumerate()
+                .map(|(index, member)| {
+                    let binding = member
+                        .binding
+                        .as_ref()
+                        .ok_or(Error::InvalidOperand("a member binding"))?;
+                    Ok((binding, result.member(index as u32)?))
+                })
+                .collect(),
+            (None, _) => Err(Error::InvalidOperand("a result binding")),
+        }
+    }
+
+    /// Build the arguments of an entry point, out of values for each binding.
+    pub fn entry_arguments(
+        &self,
+        entry_point: usize,
+        input: &mut dyn FnMut(&naga::Binding, &naga::TypeInner) -> Result<Value, Error>,
+    ) -> Result<Vec<Value>, Error> {
+        let module = self.program.module();
+        module.entry_points[entry_point]
+            .function
+            .arguments
+            .iter()
+            .map(|arg| {
+                let inner = &module.types[arg.ty].inner;
+                match (arg.binding.as_ref(), inner) {
+                    (Some(binding), _) => input(binding, inner),
+                    (None, &naga::TypeInner::Struct { ref members, .. }) => Ok(Value::Composite(
+                        members
+                            .iter()
+                            .map(|member| {
+                                let binding = member
+                                    .binding
+                                    .as_ref()
+                                    .ok_or(Error::InvalidOperand("a member binding"))?;
+                                input(binding, &module.types[member.ty].inner)
+                            })
+                            .collect::<Result<_, _>>()?,
+                    )),
+                    (None, _) => Err(Error::InvalidOperand("an argument binding")),
+                }
+            })
+            .collect()
+    }
+}
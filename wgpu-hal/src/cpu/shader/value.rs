@@ -0,0 +1,383 @@
+//! Values manipulated by the interpreter, and their representation in memory.
+
+use super::Error;
+use arrayvec::ArrayVec;
+use naga::{Handle, ScalarKind, TypeInner};
+
+use std::convert::TryInto;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scalar {
+    Bool(bool),
+    Sint(i32),
+    Uint(u32),
+    Float(f32),
+}
+
+impl Scalar {
+    pub fn zero(kind: ScalarKind) -> Self {
+        Self::from_bits(kind, 0)
+    }
+
+    pub fn from_bits(kind: ScalarKind, bits: u32) -> Self {
+        match kind {
+            ScalarKind::Bool => Self::Bool(bits != 0),
+            ScalarKind::Sint => Self::Sint(bits as i32),
+            ScalarKind::Uint => Self::Uint(bits),
+            ScalarKind::Float => Self::Float(f32::from_bits(bits)),
+        }
+    }
+
+    pub fn to_bits(self) -> u32 {
+        match self {
+            Self::Bool(value) => value as u32,
+            Self::Sint(value) => value as u32,
+            Self::Uint(value) => value,
+            Self::Float(value) => value.to_bits(),
+        }
+    }
+
+    pub fn kind(self) -> ScalarKind {
+        match self {
+            Self::Bool(_) => ScalarKind::Bool,
+            Self::Sint(_) => ScalarKind::Sint,
+            Self::Uint(_) => ScalarKind::Uint,
+            Self::Float(_) => ScalarKind::Float,
+        }
+    }
+
+    /// Convert the value to another kind, like the `as` operator of Rust.
+    pub fn convert(self, kind: ScalarKind) -> Self {
+        match (self, kind) {
+            (Self::Float(value), ScalarKind::Bool) => Self::Bool(value != 0.0),
+            (_, ScalarKind::Bool) => Self::Bool(self.to_bits() != 0),
+            (Self::Bool(value), _) => Self::Uint(value as u32).convert(kind),
+            (Self::Sint(value), ScalarKind::Sint) => Self::Sint(value),
+            (Self::Sint(value), ScalarKind::Uint) => Self::Uint(value as u32),
+            (Self::Sint(value), ScalarKind::Float) => Self::Float(value as f32),
+            (Self::Uint(value), ScalarKind::Sint) => Self::Sint(value as i32),
+            (Self::Uint(value), ScalarKind::Uint) => Self::Uint(value),
+            (Self::Uint(value), ScalarKind::Float) => Self::Float(value as f32),
+            // Rust float to integer casts saturate, and map NaN to zero.
+            (Self::Float(value), ScalarKind::Sint) => Self::Sint(value as i32),
+            (Self::Float(value), ScalarKind::Uint) => Self::Uint(value as u32),
+            (Self::Float(value), ScalarKind::Float) => Self::Float(value),
+        }
+    }
+
+    pub fn as_bool(self) -> Result<bool, Error> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            _ => Err(Error::InvalidOperand("a boolean")),
+        }
+    }
+
+    pub fn as_float(self) -> Result<f32, Error> {
+        match self {
+            Self::Float(value) => Ok(value),
+            _ => Err(Error::InvalidOperand("a float")),
+        }
+    }
+
+    /// Interpret an integer as an index, mapping negative values to `None`.
+    pub fn as_index(self) -> Result<Option<u32>, Error> {
+        match self {
+            Self::Sint(value) => Ok(value.try_into().ok()),
+            Self::Uint(value) => Ok(Some(value)),
+            _ => Err(Error::InvalidOperand("an index")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space {
+    /// Local variables of the call frame at the given depth.
+    Function {
+        frame: u32,
+    },
+    Private,
+    WorkGroup,
+    /// Uniform or storage buffer bound to a global variable.
+    Buffer(Handle<naga::GlobalVariable>),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Pointer {
+    pub space: Space,
+    /// Byte offset within the space.
+    pub offset: u32,
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Scalar(Scalar),
+    Vector(ArrayVec<Scalar, 4>),
+    /// Matrix (as a list of columns), array or structure.
+    Composite(Vec<Value>),
+    Pointer(Pointer),
+    /// Texture or sampler, referred to by the global variable it's bound to.
+    Handle(Handle<naga::GlobalVariable>),
+}
+
+impl Value {
+    pub fn scalar(&self) -> Result<Scalar, Error> {
+        match *self {
+            Self::Scalar(scalar) => Ok(scalar),
+            _ => Err(Error::InvalidOperand("a scalar")),
+        }
+    }
+
+    pub fn pointer(&self) -> Result<Pointer, Error> {
+        match *self {
+            Self::Pointer(pointer) => Ok(pointer),
+            _ => Err(Error::InvalidOperand("a pointer")),
+        }
+    }
+
+    pub fn handle(&self) -> Result<Handle<naga::GlobalVariable>, Error> {
+        match *self {
+            Self::Handle(handle) => Ok(handle),
+            _ => Err(Error::InvalidOperand("a texture or a sampler")),
+        }
+    }
+
+    /// Return the components of a vector, or the single component of a scalar.
+    pub fn components(&self) -> Result<ArrayVec<Scalar, 4>, Error> {
+        match *self {
+            Self::Scalar(scalar) => Ok(Some(scalar).into_iter().collect()),
+            Self::Vector(ref components) => Ok(components.clone()),
+            _ => Err(Error::InvalidOperand("a scalar or a vector")),
+        }
+    }
+
+    /// Build a scalar out of a single component, and a vector otherwise.
+    pub fn from_components(components: ArrayVec<Scalar, 4>) -> Self {
+        if components.len() == 1 {
+            Self::Scalar(components[0])
+        } else {
+            Self::Vector(components)
+        }
+    }
+
+    /// Return the value of a composite member, clamping the index.
+    pub fn member(&self, index: u32) -> Result<Value, Error> {
+        match *self {
+            Self::Vector(ref components) => {
+                let index = (index as usize).min(components.len() - 1);
+                Ok(Self::Scalar(components[index]))
+            }
+            Self::Composite(ref members) if !members.is_empty() => {
+                let index = (index as usize).min(members.len() - 1);
+                Ok(members[index].clone())
+            }
+            _ => Err(Error::InvalidOperand("a composite")),
+        }
+    }
+
+    /// Value of a module constant.
+    pub fn constant(module: &naga::Module, handle: Handle<naga::Constant>) -> Result<Self, Error> {
+        Ok(match module.constants[handle].inner {
+            naga::ConstantInner::Scalar { value, .. } => Self::Scalar(match value {
+                naga::ScalarValue::Bool(value) => Scalar::Bool(value),
+                naga::ScalarValue::Sint(value) => Scalar::Sint(value as i32),
+                naga::ScalarValue::Uint(value) => Scalar::Uint(value as u32),
+                naga::ScalarValue::Float(value) => Scalar::Float(value as f32),
+            }),
+            naga::ConstantInner::Composite { ty, ref components } => {
+                let values = components
+                    .iter()
+                    .map(|&component| Self::constant(module, component))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match module.types[ty].inner {
+                    TypeInner::Vector { .. } => {
+                        Self::Vector(values.iter().map(Self::scalar).collect::<Result<_, _>>()?)
+                    }
+                    _ => Self::Composite(values),
+                }
+            }
+        })
+    }
+
+    /// Read a value of the given type from memory.
+    ///
+    /// Bytes outside of `memory` read as zeros.
+    pub fn read(
+        module: &naga::Module,
+        inner: &TypeInner,
+        memory: &[u8],
+        offset: u32,
+    ) -> Result<Self, Error> {
+        let read_scalar = |kind, offset: u32| {
+            let start = offset as usize;
+            let bits = match memory.get(start..start + 4) {
+                Some(bytes) => u32::from_le_bytes(bytes.try_into().unwrap()),
+                None => 0,
+            };
+            Scalar::from_bits(kind, bits)
+        };
+        Ok(match *inner {
+            TypeInner::Scalar { kind, width } | TypeInner::Atomic { kind, width } => {
+                check_width(width)?;
+                Self::Scalar(read_scalar(kind, offset))
+            }
+            TypeInner::Vector { size, kind, width } => {
+                check_width(width)?;
+                Self::Vector(
+                    (0..size as u32)
+                        .map(|i| read_scalar(kind, offset.saturating_add(i * 4)))
+                        .collect(),
+                )
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                width,
+            } => {
+                check_width(width)?;
+                let stride = column_stride(rows);
+                Self::Composite(
+                    (0..columns as u32)
+                        .map(|c| {
+                            Self::Vector(
+                                (0..rows as u32)
+                                    .map(|r| {
+                                        read_scalar(
+                                            ScalarKind::Float,
+                                            offset.saturating_add(c * stride + r * 4),
+                                        )
+                                    })
+                                    .collect(),
+                            )
+                        })
+                        .collect(),
+                )
+            }
+            TypeInner::Array { base, size, stride } => {
+                let base_inner = &module.types[base].inner;
+                let count = array_length(module, size)?;
+                Self::Composite(
+                    (0..count)
+                        .map(|i| {
+                            Self::read(
+                                module,
+                                base_inner,
+                                memory,
+                                offset.saturating_add(i * stride),
+                            )
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            TypeInner::Struct { ref members, .. } => Self::Composite(
+                members
+                    .iter()
+                    .map(|member| {
+                        let member_inner = &module.types[member.ty].inner;
+                        Self::read(
+                            module,
+                            member_inner,
+                            memory,
+                            offset.saturating_add(member.offset),
+                        )
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => return Err(Error::InvalidOperand("a type stored in memory")),
+        })
+    }
+
+    /// Write a value of the given type to memory.
+    ///
+    /// Bytes outside of `memory` are discarded.
+    pub fn write(
+        &self,
+        module: &naga::Module,
+        inner: &TypeInner,
+        memory: &mut [u8],
+        offset: u32,
+    ) -> Result<(), Error> {
+        let mut write_scalar = |scalar: Scalar, offset: u32| {
+            let start = offset as usize;
+            if let Some(bytes) = memory.get_mut(start..start + 4) {
+                bytes.copy_from_slice(&scalar.to_bits().to_le_bytes());
+            }
+        };
+        match (inner, self) {
+            (&TypeInner::Scalar { width, .. }, &Self::Scalar(scalar))
+            | (&TypeInner::Atomic { width, .. }, &Self::Scalar(scalar)) => {
+                check_width(width)?;
+                write_scalar(scalar, offset);
+            }
+            (&TypeInner::Vector { width, .. }, &Self::Vector(ref components)) => {
+                check_width(width)?;
+                for (i, &component) in components.iter().enumerate() {
+                    write_scalar(component, offset.saturating_add(i as u32 * 4));
+                }
+            }
+            (&TypeInner::Matrix { rows, width, .. }, &Self::Composite(ref columns)) => {
+                check_width(width)?;
+                let stride = column_stride(rows);
+                for (c, column) in columns.iter().enumerate() {
+                    for (r, component) in column.components()?.into_iter().enumerate() {
+                        write_scalar(
+                            component,
+                            offset.saturating_add(c as u32 * stride + r as u32 * 4),
+                        );
+                    }
+                }
+            }
+            (&TypeInner::Array { base, stride, .. }, &Self::Composite(ref elements)) => {
+                let base_inner = &module.types[base].inner;
+                for (i, element) in elements.iter().enumerate() {
+                    element.write(
+                        module,
+                        base_inner,
+                        memory,
+                        offset.saturating_add(i as u32 * stride),
+                    )?;
+                }
+            }
+            (&TypeInner::Struct { ref members, .. }, &Self::Composite(ref values)) => {
+                for (member, value) in members.iter().zip(values) {
+                    let member_inner = &module.types[member.ty].inner;
+                    value.write(
+                        module,
+                        member_inner,
+                        memory,
+                        offset.saturating_add(member.offset),
+                    )?;
+                }
+            }
+            _ => return Err(Error::InvalidOperand("a value matching the stored type")),
+        }
+        Ok(())
+    }
+}
+
+fn check_width(width: naga::Bytes) -> Result<(), Error> {
+    if width == 4 {
+        Ok(())
+    } else {
+        Err(Error::Unsupported("non 32-bit scalars"))
+    }
+}
+
+/// Distance in bytes between matrix columns, which are aligned like vectors.
+pub fn column_stride(rows: naga::VectorSize) -> u32 {
+    match rows {
+        naga::VectorSize::Bi => 8,
+        naga::VectorSize::Tri | naga::VectorSize::Quad => 16,
+    }
+}
+
+pub fn array_length(module: &naga::Module, size: naga::ArraySize) -> Result<u32, Error> {
+    match size {
+        naga::ArraySize::Constant(handle) => match Value::constant(module, handle)? {
+            Value::Scalar(scalar) => scalar
+                .as_index()?
+                .ok_or(Error::InvalidOperand("an array length")),
+            _ => Err(Error::InvalidOperand("an array length")),
+        },
+        naga::ArraySize::Dynamic => Err(Error::Unsupported("loading runtime-sized arrays")),
+    }
+}
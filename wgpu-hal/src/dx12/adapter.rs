@@ -1,8 +1,9 @@
 use super::{conv, HResult as _};
 use std::{mem, sync::Arc, thread};
 use winapi::{
-    shared::{dxgi, dxgi1_2, dxgi1_5, minwindef, windef, winerror},
-    um::{d3d12, d3d12sdklayers, winuser},
+    shared::{dxgi, dxgi1_2, dxgi1_4, dxgi1_5, minwindef, windef, winerror},
+    um::{d3d12, d3d12sdklayers, winnt, winuser},
+    Interface as _,
 };
 
 impl Drop for super::Adapter {
@@ -293,12 +294,40 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 .into_device_result("Queue creation")?
         };
 
+        let adapter = match self.raw.cast::<dxgi1_4::IDXGIAdapter3>().into_result() {
+            Ok(adapter) => Some(adapter),
+            Err(err) => {
+                log::info!("Memory budget is not available: {}", err);
+                None
+            }
+        };
+        let cache_header = {
+            let mut desc: dxgi1_2::DXGI_ADAPTER_DESC2 = mem::zeroed();
+            self.raw.GetDesc2(&mut desc);
+            // This gives the version of the user mode driver.
+            let mut umd_version: winnt::LARGE_INTEGER = mem::zeroed();
+            let hr = self
+                .raw
+                .CheckInterfaceSupport(&dxgi::IDXGIDevice::uuidof(), &mut umd_version);
+            crate::auxil::pipeline_cache::Header {
+                backend: wgt::Backend::Dx12,
+                vendor: desc.VendorId,
+                device: desc.DeviceId,
+                driver: if winerror::SUCCEEDED(hr) {
+                    umd_version.QuadPart().to_string()
+                } else {
+                    String::new()
+                },
+            }
+        };
         let device = super::Device::new(
             self.device,
             queue,
             features,
             self.private_caps,
             &self.library,
+            adapter,
+            cache_header,
         )?;
         Ok(crate::OpenDevice {
             device,
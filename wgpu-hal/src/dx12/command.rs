@@ -512,6 +512,8 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             u: mem::zeroed(),
         };
         let raw_format = conv::map_texture_format(src.format);
+        // The stencil plane is copied as one byte per texel.
+        let stencil_format = conv::map_texture_format(wgt::TextureFormat::R8Uint);
 
         let block_size = src.format.describe().block_dimensions.0 as u32;
         for r in regions {
@@ -520,7 +522,11 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
             *dst_location.u.PlacedFootprint_mut() = d3d12::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
                 Offset: r.buffer_layout.offset,
                 Footprint: d3d12::D3D12_SUBRESOURCE_FOOTPRINT {
-                    Format: raw_format,
+                    Format: if r.texture_base.aspect == crate::FormatAspects::STENCIL {
+                        stencil_format
+                    } else {
+                        raw_format
+                    },
                     Width: r.size.width,
                     Height: r
                         .buffer_layout
@@ -1,10 +1,10 @@
-use crate::FormatAspects;
+use crate::{auxil::pipeline_cache::ShaderCache, FormatAspects};
 
 use super::{conv, descriptor, view, HResult as _};
 use parking_lot::Mutex;
 use std::{ffi, mem, num::NonZeroU32, ptr, slice, sync::Arc};
 use winapi::{
-    shared::{dxgiformat, dxgitype, winerror},
+    shared::{dxgi1_4, dxgiformat, dxgitype, winerror},
     um::{d3d12, d3dcompiler, synchapi, winbase},
     Interface,
 };
@@ -21,6 +21,8 @@ impl super::Device {
         features: wgt::Features,
         private_caps: super::PrivateCapabilities,
         library: &Arc<native::D3D12Lib>,
+        adapter: Option<native::WeakPtr<dxgi1_4::IDXGIAdapter3>>,
+        cache_header: crate::auxil::pipeline_cache::Header,
     ) -> Result<Self, crate::DeviceError> {
         let mut idle_fence = native::Fence::null();
         let hr = unsafe {
@@ -150,6 +152,15 @@ impl super::Device {
                 native::DescriptorHeapType::Sampler,
             )),
             library: Arc::clone(library),
+            adapter,
+            cache_header,
+            memory: crate::auxil::memory::ResourceMemory::new(
+                0,
+                match private_caps.memory_architecture {
+                    super::MemoryArchitecture::Unified { .. } => false,
+                    super::MemoryArchitecture::NonUnified => true,
+                },
+            ),
             #[cfg(feature = "renderdoc")]
             render_doc: Default::default(),
         })
@@ -178,35 +189,47 @@ impl super::Device {
         stage: &crate::ProgrammableStage<super::Api>,
         layout: &super::PipelineLayout,
         naga_stage: naga::ShaderStage,
+        cache: Option<&super::PipelineCache>,
     ) -> Result<native::Blob, crate::PipelineError> {
         use naga::back::hlsl;
 
         let stage_bit = crate::auxil::map_naga_stage(naga_stage);
         let module = &stage.module.naga.module;
-        //TODO: reuse the writer
-        let mut source = String::new();
-        let mut writer = hlsl::Writer::new(&mut source, &layout.naga_options);
-        let reflection_info = {
-            profiling::scope!("naga::back::hlsl::write");
-            writer
-                .write(module, &stage.module.naga.info)
-                .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("HLSL: {:?}", e)))?
-        };
+        let ep_index = module
+            .entry_points
+            .iter()
+            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
+            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+
+        let (source, ep_name) = ShaderCache::get_or_translate(
+            cache.map(|cache| &cache.shaders),
+            stage.module.naga.source_hash,
+            (stage.entry_point, naga_stage),
+            &layout.naga_options,
+            || -> Result<_, crate::PipelineError> {
+                //TODO: reuse the writer
+                let mut source = String::new();
+                let mut writer = hlsl::Writer::new(&mut source, &layout.naga_options);
+                let reflection_info = {
+                    profiling::scope!("naga::back::hlsl::write");
+                    writer.write(module, &stage.module.naga.info).map_err(|e| {
+                        crate::PipelineError::Linkage(stage_bit, format!("HLSL: {:?}", e))
+                    })?
+                };
+                let ep_name = reflection_info.entry_point_names[ep_index]
+                    .as_ref()
+                    .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?
+                    .clone();
+                Ok((source, ep_name))
+            },
+        )?;
 
         let full_stage = format!(
             "{}_{}\0",
             naga_stage.to_hlsl_str(),
             layout.naga_options.shader_model.to_str()
         );
-        let ep_index = module
-            .entry_points
-            .iter()
-            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
-            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
-        let raw_ep = reflection_info.entry_point_names[ep_index]
-            .as_ref()
-            .map(|name| ffi::CString::new(name.as_str()).unwrap())
-            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
+        let raw_ep = ffi::CString::new(ep_name).unwrap();
 
         let mut shader_data = native::Blob::null();
         let mut error = native::Blob::null();
@@ -302,6 +325,18 @@ impl super::Device {
             size,
             mip_level_count,
             sample_count,
+            memory_size: 0,
+        }
+    }
+
+    pub unsafe fn buffer_from_raw(
+        resource: native::Resource,
+        size: wgt::BufferAddress,
+    ) -> super::Buffer {
+        super::Buffer {
+            resource,
+            size,
+            memory_size: 0,
         }
     }
 }
@@ -314,6 +349,9 @@ impl crate::Device<super::Api> for super::Device {
         self.sampler_pool.into_inner().destroy();
         self.shared.destroy();
         self.idler.destroy();
+        if let Some(adapter) = self.adapter {
+            adapter.destroy();
+        }
         queue.raw.destroy();
     }
 
@@ -386,9 +424,17 @@ impl crate::Device<super::Api> for super::Device {
             resource.SetName(cwstr.as_ptr());
         }
 
-        Ok(super::Buffer { resource, size })
+        self.memory.add_buffer(size);
+        Ok(super::Buffer {
+            resource,
+            size,
+            memory_size: size,
+        })
     }
     unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
+        if buffer.memory_size != 0 {
+            self.memory.remove_buffer(buffer.memory_size);
+        }
         buffer.resource.destroy();
     }
     unsafe fn map_buffer(
@@ -479,6 +525,15 @@ impl crate::Device<super::Api> for super::Device {
             resource.SetName(cwstr.as_ptr());
         }
 
+        let memory_size = crate::auxil::memory::texture_memory_size(
+            desc.format,
+            desc.dimension,
+            desc.size,
+            desc.mip_level_count,
+            desc.sample_count,
+        );
+        self.memory.add_texture(memory_size);
+
         Ok(super::Texture {
             resource,
             format: desc.format,
@@ -486,9 +541,13 @@ impl crate::Device<super::Api> for super::Device {
             size: desc.size,
             mip_level_count: desc.mip_level_count,
             sample_count: desc.sample_count,
+            memory_size,
         })
     }
     unsafe fn destroy_texture(&self, texture: super::Texture) {
+        if texture.memory_size != 0 {
+            self.memory.remove_texture(texture.memory_size);
+        }
         texture.resource.destroy();
     }
 
@@ -1217,12 +1276,16 @@ impl crate::Device<super::Api> for super::Device {
         let (topology_class, topology) = conv::map_topology(desc.primitive.topology);
         let mut shader_stages = wgt::ShaderStages::VERTEX;
 
-        let blob_vs =
-            self.load_shader(&desc.vertex_stage, desc.layout, naga::ShaderStage::Vertex)?;
+        let blob_vs = self.load_shader(
+            &desc.vertex_stage,
+            desc.layout,
+            naga::ShaderStage::Vertex,
+            desc.cache,
+        )?;
         let blob_fs = match desc.fragment_stage {
             Some(ref stage) => {
                 shader_stages |= wgt::ShaderStages::FRAGMENT;
-                self.load_shader(stage, desc.layout, naga::ShaderStage::Fragment)?
+                self.load_shader(stage, desc.layout, naga::ShaderStage::Fragment, desc.cache)?
             }
             None => native::Blob::null(),
         };
@@ -1400,7 +1463,12 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::ComputePipelineDescriptor<super::Api>,
     ) -> Result<super::ComputePipeline, crate::PipelineError> {
-        let blob_cs = self.load_shader(&desc.stage, desc.layout, naga::ShaderStage::Compute)?;
+        let blob_cs = self.load_shader(
+            &desc.stage,
+            desc.layout,
+            naga::ShaderStage::Compute,
+            desc.cache,
+        )?;
 
         let pair = {
             profiling::scope!("ID3D12Device::CreateComputePipelineState");
@@ -1433,6 +1501,18 @@ impl crate::Device<super::Api> for super::Device {
         pipeline.raw.destroy();
     }
 
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<super::PipelineCache, crate::PipelineCacheError> {
+        let (shaders, _) = ShaderCache::new(self.cache_header.clone(), desc.data)?;
+        Ok(super::PipelineCache { shaders })
+    }
+    unsafe fn destroy_pipeline_cache(&self, _cache: super::PipelineCache) {}
+    unsafe fn pipeline_cache_get_data(&self, cache: &super::PipelineCache) -> Vec<u8> {
+        cache.shaders.serialize(&[])
+    }
+
     unsafe fn create_query_set(
         &self,
         desc: &wgt::QuerySetDescriptor<crate::Label>,
@@ -1511,6 +1591,28 @@ impl crate::Device<super::Api> for super::Device {
         }
     }
 
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        let mut heap = self.memory.heap();
+        if let Some(adapter) = self.adapter {
+            // The local segment group is the dedicated memory on discrete adapters,
+            // and all the memory on integrated ones.
+            let mut info: dxgi1_4::DXGI_QUERY_VIDEO_MEMORY_INFO = mem::zeroed();
+            let hr = adapter.QueryVideoMemoryInfo(
+                0,
+                dxgi1_4::DXGI_MEMORY_SEGMENT_GROUP_LOCAL,
+                &mut info,
+            );
+            match hr.into_result() {
+                Ok(()) => {
+                    heap.budget = Some(info.Budget);
+                    heap.driver_usage = Some(info.CurrentUsage);
+                }
+                Err(err) => log::warn!("Unable to query video memory info: {}", err),
+            }
+        }
+        wgt::MemoryReport { heaps: vec![heap] }
+    }
+
     unsafe fn start_capture(&self) -> bool {
         #[cfg(feature = "renderdoc")]
         {
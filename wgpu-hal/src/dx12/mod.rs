@@ -77,6 +77,7 @@ impl crate::Api for Api {
     type ShaderModule = ShaderModule;
     type RenderPipeline = RenderPipeline;
     type ComputePipeline = ComputePipeline;
+    type PipelineCache = PipelineCache;
 }
 
 trait HResult<O> {
@@ -247,6 +248,10 @@ pub struct Device {
     sampler_pool: Mutex<descriptor::CpuPool>,
     // library
     library: Arc<native::D3D12Lib>,
+    /// Adapter used to query the memory budget, if it supports `IDXGIAdapter3`.
+    adapter: Option<native::WeakPtr<dxgi1_4::IDXGIAdapter3>>,
+    cache_header: crate::auxil::pipeline_cache::Header,
+    memory: crate::auxil::memory::ResourceMemory,
     #[cfg(feature = "renderdoc")]
     render_doc: crate::auxil::renderdoc::RenderDoc,
 }
@@ -369,6 +374,8 @@ unsafe impl Sync for CommandBuffer {}
 pub struct Buffer {
     resource: native::Resource,
     size: wgt::BufferAddress,
+    /// Size of the buffer in memory, 0 for buffers that weren't created by the device.
+    memory_size: wgt::BufferAddress,
 }
 
 unsafe impl Send for Buffer {}
@@ -395,6 +402,9 @@ pub struct Texture {
     size: wgt::Extent3d,
     mip_level_count: u32,
     sample_count: u32,
+    /// Estimated size of the texture in memory, 0 for textures that weren't created by the
+    /// device.
+    memory_size: wgt::BufferAddress,
 }
 
 unsafe impl Send for Texture {}
@@ -415,7 +425,13 @@ impl Texture {
     }
 
     fn calc_subresource_for_copy(&self, base: &crate::TextureCopyBase) -> u32 {
-        self.calc_subresource(base.mip_level, base.array_layer, 0)
+        // The stencil aspect lives in the second plane of depth-stencil formats.
+        let plane = if base.aspect == crate::FormatAspects::STENCIL {
+            1
+        } else {
+            0
+        };
+        self.calc_subresource(base.mip_level, base.array_layer, plane)
     }
 }
 
@@ -531,6 +547,12 @@ pub struct RenderPipeline {
 unsafe impl Send for RenderPipeline {}
 unsafe impl Sync for RenderPipeline {}
 
+/// Compiled shader blobs aren't stored, only the generated HLSL is cached.
+#[derive(Debug)]
+pub struct PipelineCache {
+    shaders: crate::auxil::pipeline_cache::ShaderCache<(String, String)>,
+}
+
 pub struct ComputePipeline {
     raw: native::PipelineState,
     layout: PipelineLayoutShared,
@@ -705,6 +727,7 @@ impl crate::Surface<Api> for Surface {
             size: sc.size,
             mip_level_count: 1,
             sample_count: 1,
+            memory_size: 0,
         };
         Ok(Some(crate::AcquiredSurfaceTexture {
             texture,
@@ -35,6 +35,7 @@ impl crate::Api for Api {
     type ShaderModule = Resource;
     type RenderPipeline = Resource;
     type ComputePipeline = Resource;
+    type PipelineCache = Resource;
 }
 
 impl crate::Instance<Api> for Context {
@@ -201,6 +202,16 @@ impl crate::Device<Api> for Context {
         Ok(Resource)
     }
     unsafe fn destroy_compute_pipeline(&self, pipeline: Resource) {}
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<Resource, crate::PipelineCacheError> {
+        Ok(Resource)
+    }
+    unsafe fn destroy_pipeline_cache(&self, cache: Resource) {}
+    unsafe fn pipeline_cache_get_data(&self, cache: &Resource) -> Vec<u8> {
+        Vec::new()
+    }
 
     unsafe fn create_query_set(
         &self,
@@ -225,6 +236,10 @@ impl crate::Device<Api> for Context {
         Ok(true)
     }
 
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        wgt::MemoryReport::default()
+    }
+
     unsafe fn start_capture(&self) -> bool {
         false
     }
@@ -463,6 +463,15 @@ impl super::Adapter {
         #[allow(clippy::drop_ref)]
         drop(gl);
 
+        let info = Self::make_info(vendor, renderer.clone());
+        // The vendor is only known through the renderer string on some platforms.
+        let cache_header = crate::auxil::pipeline_cache::Header {
+            backend: wgt::Backend::Gl,
+            vendor: info.vendor as u32,
+            device: info.device as u32,
+            driver: format!("{} {}", renderer, version),
+        };
+
         Some(crate::ExposedAdapter {
             adapter: super::Adapter {
                 shared: Arc::new(super::AdapterShared {
@@ -470,9 +479,10 @@ impl super::Adapter {
                     private_caps,
                     workarounds,
                     shading_language_version,
+                    cache_header,
                 }),
             },
-            info: Self::make_info(vendor, renderer),
+            info,
             features,
             capabilities: crate::Capabilities {
                 limits,
@@ -548,6 +558,7 @@ impl crate::Adapter<super::Api> for super::Adapter {
             device: super::Device {
                 shared: Arc::clone(&self.shared),
                 main_vao,
+                memory: crate::auxil::memory::ResourceMemory::new(0, true),
                 #[cfg(feature = "renderdoc")]
                 render_doc: Default::default(),
             },
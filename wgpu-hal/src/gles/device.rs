@@ -1,5 +1,5 @@
 use super::conv;
-use crate::auxil::map_naga_stage;
+use crate::auxil::{map_naga_stage, pipeline_cache::ShaderCache};
 use glow::HasContext;
 use std::{
     convert::TryInto,
@@ -23,12 +23,15 @@ struct CompilationContext<'a> {
 }
 
 impl CompilationContext<'_> {
-    fn consume_reflection(
-        self,
+    /// Resolve the bindings of the generated shader into the slots of the layout.
+    fn reflect(
+        &self,
         module: &naga::Module,
         ep_info: &naga::valid::FunctionInfo,
         reflection_info: naga::back::glsl::ReflectionInfo,
-    ) {
+    ) -> (Vec<(String, super::BindingRegister, u8)>, Vec<(u8, u8)>) {
+        let mut bindings = Vec::new();
+        let mut samplers = Vec::new();
         for (handle, var) in module.global_variables.iter() {
             if ep_info[handle].is_empty() {
                 continue;
@@ -53,7 +56,7 @@ impl CompilationContext<'_> {
                 register,
                 slot
             );
-            self.name_binding_map.insert(name, (register, slot));
+            bindings.push((name, register, slot));
         }
 
         for (name, mapping) in reflection_info.texture_mapping {
@@ -69,17 +72,30 @@ impl CompilationContext<'_> {
             let tex_br = var.binding.as_ref().unwrap();
             let texture_linear_index = self.layout.get_slot(tex_br);
 
-            self.name_binding_map
-                .insert(name, (register, texture_linear_index));
+            bindings.push((name, register, texture_linear_index));
             if let Some(sampler_handle) = mapping.sampler {
                 let sam_br = module.global_variables[sampler_handle]
                     .binding
                     .as_ref()
                     .unwrap();
                 let sampler_linear_index = self.layout.get_slot(sam_br);
-                self.sampler_map[texture_linear_index as usize] = Some(sampler_linear_index);
+                samplers.push((texture_linear_index, sampler_linear_index));
             }
         }
+        (bindings, samplers)
+    }
+
+    fn consume_reflection(
+        self,
+        bindings: Vec<(String, super::BindingRegister, u8)>,
+        samplers: Vec<(u8, u8)>,
+    ) {
+        for (name, register, slot) in bindings {
+            self.name_binding_map.insert(name, (register, slot));
+        }
+        for (texture_linear_index, sampler_linear_index) in samplers {
+            self.sampler_map[texture_linear_index as usize] = Some(sampler_linear_index);
+        }
     }
 }
 
@@ -129,46 +145,58 @@ impl super::Device {
         naga_stage: naga::ShaderStage,
         stage: &crate::ProgrammableStage<super::Api>,
         context: CompilationContext,
+        cache: Option<&super::PipelineCache>,
     ) -> Result<glow::Shader, crate::PipelineError> {
         use naga::back::glsl;
-        let pipeline_options = glsl::PipelineOptions {
-            shader_stage: naga_stage,
-            entry_point: stage.entry_point.to_string(),
-        };
-
         let shader = &stage.module.naga;
-        let entry_point_index = shader
-            .module
-            .entry_points
-            .iter()
-            .position(|ep| ep.name.as_str() == stage.entry_point)
-            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
-
-        let mut output = String::new();
-        let mut writer = glsl::Writer::new(
-            &mut output,
-            &shader.module,
-            &shader.info,
+        let (output, bindings, samplers) = ShaderCache::get_or_translate(
+            cache.map(|cache| &cache.shaders),
+            shader.source_hash,
+            (stage.entry_point, naga_stage),
             &context.layout.naga_options,
-            &pipeline_options,
-        )
-        .map_err(|e| {
-            let msg = format!("{}", e);
-            crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
-        })?;
-
-        let reflection_info = writer.write().map_err(|e| {
-            let msg = format!("{}", e);
-            crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
-        })?;
-
-        log::debug!("Naga generated shader:\n{}", output);
-
-        context.consume_reflection(
-            &shader.module,
-            shader.info.get_entry_point(entry_point_index),
-            reflection_info,
-        );
+            || -> Result<_, crate::PipelineError> {
+                let pipeline_options = glsl::PipelineOptions {
+                    shader_stage: naga_stage,
+                    entry_point: stage.entry_point.to_string(),
+                };
+
+                let entry_point_index = shader
+                    .module
+                    .entry_points
+                    .iter()
+                    .position(|ep| ep.name.as_str() == stage.entry_point)
+                    .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+
+                let mut output = String::new();
+                let mut writer = glsl::Writer::new(
+                    &mut output,
+                    &shader.module,
+                    &shader.info,
+                    &context.layout.naga_options,
+                    &pipeline_options,
+                )
+                .map_err(|e| {
+                    let msg = format!("{}", e);
+                    crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
+                })?;
+
+                let reflection_info = writer.write().map_err(|e| {
+                    let msg = format!("{}", e);
+                    crate::PipelineError::Linkage(map_naga_stage(naga_stage), msg)
+                })?;
+
+                log::debug!("Naga generated shader:\n{}", output);
+
+                let (bindings, samplers) = context.reflect(
+                    &shader.module,
+                    shader.info.get_entry_point(entry_point_index),
+                    reflection_info,
+                );
+                Ok((output, bindings, samplers))
+            },
+        )?;
+
+        context.consume_reflection(bindings, samplers);
 
         unsafe { Self::compile_shader(gl, &output, naga_stage, stage.module.label.as_deref()) }
     }
@@ -179,6 +207,7 @@ impl super::Device {
         shaders: I,
         layout: &super::PipelineLayout,
         #[cfg_attr(target_arch = "wasm32", allow(unused))] label: Option<&str>,
+        cache: Option<&super::PipelineCache>,
     ) -> Result<super::PipelineInner, crate::PipelineError> {
         let program = gl.create_program().unwrap();
         #[cfg(not(target_arch = "wasm32"))]
@@ -201,7 +230,7 @@ impl super::Device {
                 name_binding_map: &mut name_binding_map,
             };
 
-            let shader = Self::create_shader(gl, naga_stage, stage, context)?;
+            let shader = Self::create_shader(gl, naga_stage, stage, context, cache)?;
             shaders_to_delete.push(shader);
         }
 
@@ -418,6 +447,7 @@ impl crate::Device<super::Api> for super::Device {
         } else {
             None
         };
+        self.memory.add_buffer(desc.size);
 
         Ok(super::Buffer {
             raw,
@@ -428,6 +458,7 @@ impl crate::Device<super::Api> for super::Device {
         })
     }
     unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
+        self.memory.remove_buffer(buffer.size);
         if let Some(raw) = buffer.raw {
             let gl = &self.shared.context.lock();
             gl.delete_buffer(raw);
@@ -637,6 +668,16 @@ impl crate::Device<super::Api> for super::Device {
             super::TextureInner::Texture { raw, target }
         };
 
+        // GL doesn't tell how much memory the driver actually allocated.
+        let memory_size = crate::auxil::memory::texture_memory_size(
+            desc.format,
+            desc.dimension,
+            desc.size,
+            desc.mip_level_count,
+            desc.sample_count,
+        );
+        self.memory.add_texture(memory_size);
+
         Ok(super::Texture {
             inner,
             mip_level_count: desc.mip_level_count,
@@ -648,9 +689,13 @@ impl crate::Device<super::Api> for super::Device {
             format: desc.format,
             format_desc,
             copy_size,
+            memory_size,
         })
     }
     unsafe fn destroy_texture(&self, texture: super::Texture) {
+        if texture.memory_size != 0 {
+            self.memory.remove_texture(texture.memory_size);
+        }
         let gl = &self.shared.context.lock();
         match texture.inner {
             super::TextureInner::Renderbuffer { raw, .. } => {
@@ -950,7 +995,7 @@ impl crate::Device<super::Api> for super::Device {
                 .as_ref()
                 .map(|fs| (naga::ShaderStage::Fragment, fs)),
         );
-        let inner = self.create_pipeline(gl, shaders, desc.layout, desc.label)?;
+        let inner = self.create_pipeline(gl, shaders, desc.layout, desc.label, desc.cache)?;
 
         let (vertex_buffers, vertex_attributes) = {
             let mut buffers = Vec::new();
@@ -1018,7 +1063,7 @@ impl crate::Device<super::Api> for super::Device {
     ) -> Result<super::ComputePipeline, crate::PipelineError> {
         let gl = &self.shared.context.lock();
         let shaders = iter::once((naga::ShaderStage::Compute, &desc.stage));
-        let inner = self.create_pipeline(gl, shaders, desc.layout, desc.label)?;
+        let inner = self.create_pipeline(gl, shaders, desc.layout, desc.label, desc.cache)?;
 
         Ok(super::ComputePipeline { inner })
     }
@@ -1027,6 +1072,18 @@ impl crate::Device<super::Api> for super::Device {
         gl.delete_program(pipeline.inner.program);
     }
 
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<super::PipelineCache, crate::PipelineCacheError> {
+        let (shaders, _) = ShaderCache::new(self.shared.cache_header.clone(), desc.data)?;
+        Ok(super::PipelineCache { shaders })
+    }
+    unsafe fn destroy_pipeline_cache(&self, _cache: super::PipelineCache) {}
+    unsafe fn pipeline_cache_get_data(&self, cache: &super::PipelineCache) -> Vec<u8> {
+        cache.shaders.serialize(&[])
+    }
+
     #[cfg_attr(target_arch = "wasm32", allow(unused))]
     unsafe fn create_query_set(
         &self,
@@ -1120,6 +1177,12 @@ impl crate::Device<super::Api> for super::Device {
         }
     }
 
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        wgt::MemoryReport {
+            heaps: vec![self.memory.heap()],
+        }
+    }
+
     unsafe fn start_capture(&self) -> bool {
         #[cfg(feature = "renderdoc")]
         return self
@@ -73,6 +73,7 @@ use self::egl::{AdapterContext, Instance, Surface};
 #[cfg(target_arch = "wasm32")]
 use self::web::{AdapterContext, Instance, Surface};
 
+use crate::auxil::pipeline_cache::{CacheEntry, Reader, ShaderCache};
 use arrayvec::ArrayVec;
 
 use glow::HasContext;
@@ -113,6 +114,7 @@ impl crate::Api for Api {
     type ShaderModule = ShaderModule;
     type RenderPipeline = RenderPipeline;
     type ComputePipeline = ComputePipeline;
+    type PipelineCache = PipelineCache;
 }
 
 bitflags::bitflags! {
@@ -179,6 +181,7 @@ struct AdapterShared {
     private_caps: PrivateCapabilities,
     workarounds: Workarounds,
     shading_language_version: naga::back::glsl::Version,
+    cache_header: crate::auxil::pipeline_cache::Header,
 }
 
 pub struct Adapter {
@@ -188,6 +191,7 @@ pub struct Adapter {
 pub struct Device {
     shared: Arc<AdapterShared>,
     main_vao: glow::VertexArray,
+    memory: crate::auxil::memory::ResourceMemory,
     #[cfg(feature = "renderdoc")]
     render_doc: crate::auxil::renderdoc::RenderDoc,
 }
@@ -257,6 +261,8 @@ pub struct Texture {
     #[allow(unused)]
     format_desc: TextureFormatDesc,
     copy_size: crate::CopyExtent,
+    /// Estimated size of the texture in memory, 0 for the textures of surfaces.
+    memory_size: wgt::BufferAddress,
 }
 
 impl Texture {
@@ -276,6 +282,7 @@ impl Texture {
                 height: 0,
                 depth: 0,
             },
+            memory_size: 0,
         }
     }
 }
@@ -321,7 +328,7 @@ impl PipelineLayout {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 enum BindingRegister {
     UniformBuffers,
     StorageBuffers,
@@ -329,6 +336,21 @@ enum BindingRegister {
     Images,
 }
 
+impl CacheEntry for BindingRegister {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+    fn decode(reader: &mut Reader) -> Option<Self> {
+        Some(match u8::decode(reader)? {
+            0 => Self::UniformBuffers,
+            1 => Self::StorageBuffers,
+            2 => Self::Textures,
+            3 => Self::Images,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug)]
 enum RawBinding {
     Buffer {
@@ -356,6 +378,16 @@ pub struct ShaderModule {
     label: Option<String>,
 }
 
+/// Generated GLSL of a shader stage, along with the reflected bindings
+/// as `(name, register, slot)`, and the samplers as `(texture slot, sampler slot)`.
+type CachedShader = (String, Vec<(String, BindingRegister, u8)>, Vec<(u8, u8)>);
+
+/// Program binaries aren't used, only the generated GLSL is cached.
+#[derive(Debug)]
+pub struct PipelineCache {
+    shaders: ShaderCache<CachedShader>,
+}
+
 #[derive(Clone, Debug, Default)]
 struct VertexFormatDesc {
     element_count: i32,
@@ -550,6 +550,10 @@ impl super::Queue {
                     log::error!("Not implemented yet: cubemap texture copy to buffer");
                     return;
                 }
+                if copy.texture_base.aspect == crate::FormatAspects::STENCIL {
+                    log::error!("Not implemented yet: stencil texture copy to buffer");
+                    return;
+                }
                 let format_desc = self.shared.describe_texture_format(src_format);
                 let row_texels = copy
                     .buffer_layout
@@ -270,6 +270,7 @@ impl crate::Surface<super::Api> for Surface {
                 height: sc.extent.height,
                 depth: 1,
             },
+            memory_size: 0,
         };
         Ok(Some(crate::AcquiredSurfaceTexture {
             texture,
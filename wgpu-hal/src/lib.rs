@@ -52,6 +52,8 @@ compile_error!("Metal API enabled on non-Apple OS. If your project is not using
 #[cfg(all(feature = "dx12", not(windows)))]
 compile_error!("DX12 API enabled on non-Windows OS. If your project is not using resolver=\"2\" in Cargo.toml, it should.");
 
+#[cfg(feature = "cpu")]
+mod cpu;
 #[cfg(all(feature = "dx12", windows))]
 mod dx12;
 mod empty;
@@ -64,6 +66,8 @@ mod vulkan;
 
 pub mod auxil;
 pub mod api {
+    #[cfg(feature = "cpu")]
+    pub use super::cpu::Api as Cpu;
     #[cfg(feature = "dx12")]
     pub use super::dx12::Api as Dx12;
     pub use super::empty::Api as Empty;
@@ -77,6 +81,8 @@ pub mod api {
 
 #[cfg(feature = "vulkan")]
 pub use vulkan::UpdateAfterBindTypes;
+#[cfg(all(feature = "vulkan", unix))]
+pub use vulkan::{ExternalMemoryError, ExternalMemoryType};
 
 use std::{
     borrow::Borrow,
@@ -127,6 +133,14 @@ pub enum PipelineError {
     Device(#[from] DeviceError),
 }
 
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum PipelineCacheError {
+    #[error("cache data is invalid or was made by a different version")]
+    Validation,
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+}
+
 #[derive(Clone, Debug, PartialEq, Error)]
 pub enum SurfaceError {
     #[error("surface is lost")]
@@ -167,6 +181,7 @@ pub trait Api: Clone + Sized {
     type ShaderModule: fmt::Debug + Send + Sync;
     type RenderPipeline: Send + Sync;
     type ComputePipeline: Send + Sync;
+    type PipelineCache: fmt::Debug + Send + Sync;
 }
 
 pub trait Instance<A: Api>: Sized + Send + Sync {
@@ -290,6 +305,13 @@ pub trait Device<A: Api>: Send + Sync {
         desc: &ComputePipelineDescriptor<A>,
     ) -> Result<A::ComputePipeline, PipelineError>;
     unsafe fn destroy_compute_pipeline(&self, pipeline: A::ComputePipeline);
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &PipelineCacheDescriptor,
+    ) -> Result<A::PipelineCache, PipelineCacheError>;
+    unsafe fn destroy_pipeline_cache(&self, cache: A::PipelineCache);
+    /// Serialize the cache, so that it can be passed to `create_pipeline_cache` next time.
+    unsafe fn pipeline_cache_get_data(&self, cache: &A::PipelineCache) -> Vec<u8>;
 
     unsafe fn create_query_set(
         &self,
@@ -306,6 +328,9 @@ pub trait Device<A: Api>: Send + Sync {
         timeout_ms: u32,
     ) -> Result<bool, DeviceError>;
 
+    /// Report the memory allocated for buffers and textures, and the budget of the driver.
+    unsafe fn memory_report(&self) -> wgt::MemoryReport;
+
     unsafe fn start_capture(&self) -> bool;
     unsafe fn stop_capture(&self);
 }
@@ -901,6 +926,9 @@ pub struct NagaShader {
     pub module: naga::Module,
     /// Analysis information of the module.
     pub info: naga::valid::ModuleInfo,
+    /// Hash of the source the module was made from, if known.
+    /// Pipeline caches keep the translated shaders under it.
+    pub source_hash: Option<u64>,
 }
 
 // Custom implementation avoids the need to generate Debug impl code
@@ -951,6 +979,16 @@ pub struct ComputePipelineDescriptor<'a, A: Api> {
     pub layout: &'a A::PipelineLayout,
     /// The compiled compute stage and its entry point.
     pub stage: ProgrammableStage<'a, A>,
+    /// The cache to look up and store the compiled stage in.
+    pub cache: Option<&'a A::PipelineCache>,
+}
+
+/// Describes a pipeline cache.
+#[derive(Clone, Debug)]
+pub struct PipelineCacheDescriptor<'a> {
+    pub label: Label<'a>,
+    /// Data previously returned by `pipeline_cache_get_data`.
+    pub data: Option<&'a [u8]>,
 }
 
 /// Describes how the vertex buffer is interpreted.
@@ -987,6 +1025,8 @@ pub struct RenderPipelineDescriptor<'a, A: Api> {
     /// If the pipeline will be used with a multiview render pass, this indicates how many array
     /// layers the attachments will have.
     pub multiview: Option<NonZeroU32>,
+    /// The cache to look up and store the compiled stages in.
+    pub cache: Option<&'a A::PipelineCache>,
 }
 
 /// Specifies how the alpha channel of the textures should be handled during (martin mouv i step)
@@ -21,15 +21,18 @@ impl crate::Adapter<super::Api> for super::Adapter {
         features: wgt::Features,
         _limits: &wgt::Limits,
     ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
-        let queue = self
-            .shared
-            .device
-            .lock()
-            .new_command_queue_with_max_command_buffer_count(MAX_COMMAND_BUFFERS);
+        let device = self.shared.device.lock();
+        let queue = device.new_command_queue_with_max_command_buffer_count(MAX_COMMAND_BUFFERS);
+        let working_set_size = if self.shared.private_caps.working_set_size {
+            device.recommended_max_working_set_size()
+        } else {
+            0
+        };
         Ok(crate::OpenDevice {
             device: super::Device {
                 shared: Arc::clone(&self.shared),
                 features,
+                memory: crate::auxil::memory::ResourceMemory::new(working_set_size, true),
             },
             queue: super::Queue {
                 raw: Arc::new(Mutex::new(queue)),
@@ -643,6 +646,17 @@ impl super::PrivateCapabilities {
 
         Self {
             family_check,
+            os_version: (major, minor),
+            allocated_size: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 13)
+            } else {
+                Self::version_at_least(major, minor, 11, 0)
+            },
+            working_set_size: if os_is_mac {
+                Self::version_at_least(major, minor, 10, 12)
+            } else {
+                Self::version_at_least(major, minor, 16, 0)
+            },
             msl_version: if os_is_mac {
                 if Self::version_at_least(major, minor, 10, 15) {
                     MTLLanguageVersion::V2_2
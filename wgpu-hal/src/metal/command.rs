@@ -262,6 +262,11 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 .buffer_layout
                 .rows_per_image
                 .map_or(0, |v| v.get() as u64 * bytes_per_row);
+            let options = if copy.texture_base.aspect == crate::FormatAspects::STENCIL {
+                mtl::MTLBlitOption::StencilFromDepthStencil
+            } else {
+                mtl::MTLBlitOption::empty()
+            };
             encoder.copy_from_texture_to_buffer(
                 &src.raw,
                 copy.texture_base.array_layer as u64,
@@ -272,7 +277,7 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
                 copy.buffer_layout.offset,
                 bytes_per_row,
                 bytes_per_image,
-                mtl::MTLBlitOption::empty(),
+                options,
             );
         }
     }
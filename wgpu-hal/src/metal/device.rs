@@ -6,7 +6,7 @@ use std::{
 };
 
 use super::conv;
-use crate::auxil::map_naga_stage;
+use crate::auxil::{map_naga_stage, pipeline_cache::ShaderCache};
 
 type DeviceResult<T> = Result<T, crate::DeviceError>;
 
@@ -55,6 +55,7 @@ impl super::Device {
         layout: &super::PipelineLayout,
         primitive_class: mtl::MTLPrimitiveTopologyClass,
         naga_stage: naga::ShaderStage,
+        cache: Option<&super::PipelineCache>,
     ) -> Result<CompiledShader, crate::PipelineError> {
         let stage_bit = map_naga_stage(naga_stage);
         let pipeline_options = naga::back::msl::PipelineOptions {
@@ -65,13 +66,36 @@ impl super::Device {
         };
 
         let module = &stage.module.naga.module;
-        let (source, info) = naga::back::msl::write_string(
-            module,
-            &stage.module.naga.info,
+        let ep_index = module
+            .entry_points
+            .iter()
+            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
+            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
+        let ep = &module.entry_points[ep_index];
+
+        let (source, name) = ShaderCache::get_or_translate(
+            cache.map(|cache| &cache.shaders),
+            stage.module.naga.source_hash,
+            (
+                stage.entry_point,
+                naga_stage,
+                pipeline_options.allow_point_size,
+            ),
             &layout.naga_options,
-            &pipeline_options,
-        )
-        .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?;
+            || -> Result<_, crate::PipelineError> {
+                let (source, info) = naga::back::msl::write_string(
+                    module,
+                    &stage.module.naga.info,
+                    &layout.naga_options,
+                    &pipeline_options,
+                )
+                .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("MSL: {:?}", e)))?;
+                let name = info.entry_point_names[ep_index]
+                    .clone()
+                    .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
+                Ok((source, name))
+            },
+        )?;
 
         let options = mtl::CompileOptions::new();
         options.set_language_version(self.shared.private_caps.msl_version);
@@ -86,22 +110,13 @@ impl super::Device {
                 crate::PipelineError::Linkage(stage_bit, format!("Metal: {}", err))
             })?;
 
-        let ep_index = module
-            .entry_points
-            .iter()
-            .position(|ep| ep.stage == naga_stage && ep.name == stage.entry_point)
-            .ok_or(crate::PipelineError::EntryPoint(naga_stage))?;
-        let ep = &module.entry_points[ep_index];
-        let name = info.entry_point_names[ep_index]
-            .as_ref()
-            .map_err(|e| crate::PipelineError::Linkage(stage_bit, format!("{}", e)))?;
         let wg_size = mtl::MTLSize {
             width: ep.workgroup_size[0] as _,
             height: ep.workgroup_size[1] as _,
             depth: ep.workgroup_size[2] as _,
         };
 
-        let function = library.get_function(name, None).map_err(|e| {
+        let function = library.get_function(&name, None).map_err(|e| {
             log::error!("get_function: {:?}", e);
             crate::PipelineError::EntryPoint(naga_stage)
         })?;
@@ -191,6 +206,15 @@ impl super::Device {
             array_layers,
             mip_levels,
             copy_size,
+            memory_size: 0,
+        }
+    }
+
+    pub unsafe fn buffer_from_raw(raw: mtl::Buffer, size: wgt::BufferAddress) -> super::Buffer {
+        super::Buffer {
+            raw,
+            size,
+            memory_size: 0,
         }
     }
 
@@ -224,12 +248,23 @@ impl crate::Device<super::Api> for super::Device {
         if let Some(label) = desc.label {
             raw.set_label(label);
         }
+        let memory_size = if self.shared.private_caps.allocated_size {
+            raw.allocated_size()
+        } else {
+            desc.size
+        };
+        self.memory.add_buffer(memory_size);
         Ok(super::Buffer {
             raw,
             size: desc.size,
+            memory_size,
         })
     }
-    unsafe fn destroy_buffer(&self, _buffer: super::Buffer) {}
+    unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
+        if buffer.memory_size != 0 {
+            self.memory.remove_buffer(buffer.memory_size);
+        }
+    }
 
     unsafe fn map_buffer(
         &self,
@@ -304,6 +339,19 @@ impl crate::Device<super::Api> for super::Device {
             raw.set_label(label);
         }
 
+        let memory_size = if self.shared.private_caps.allocated_size {
+            raw.allocated_size()
+        } else {
+            crate::auxil::memory::texture_memory_size(
+                desc.format,
+                desc.dimension,
+                desc.size,
+                desc.mip_level_count,
+                desc.sample_count,
+            )
+        };
+        self.memory.add_texture(memory_size);
+
         Ok(super::Texture {
             raw,
             raw_format: mtl_format,
@@ -311,10 +359,15 @@ impl crate::Device<super::Api> for super::Device {
             mip_levels: desc.mip_level_count,
             array_layers,
             copy_size,
+            memory_size,
         })
     }
 
-    unsafe fn destroy_texture(&self, _texture: super::Texture) {}
+    unsafe fn destroy_texture(&self, texture: super::Texture) {
+        if texture.memory_size != 0 {
+            self.memory.remove_texture(texture.memory_size);
+        }
+    }
 
     unsafe fn create_texture_view(
         &self,
@@ -761,6 +814,7 @@ impl crate::Device<super::Api> for super::Device {
             desc.layout,
             primitive_class,
             naga::ShaderStage::Vertex,
+            desc.cache,
         )?;
 
         descriptor.set_vertex_function(Some(&vs.function));
@@ -779,6 +833,7 @@ impl crate::Device<super::Api> for super::Device {
                     desc.layout,
                     primitive_class,
                     naga::ShaderStage::Fragment,
+                    desc.cache,
                 )?;
                 descriptor.set_fragment_function(Some(&fs.function));
                 if self.shared.private_caps.supports_mutability {
@@ -946,6 +1001,7 @@ impl crate::Device<super::Api> for super::Device {
             desc.layout,
             mtl::MTLPrimitiveTopologyClass::Unspecified,
             naga::ShaderStage::Compute,
+            desc.cache,
         )?;
         descriptor.set_compute_function(Some(&cs.function));
 
@@ -983,6 +1039,30 @@ impl crate::Device<super::Api> for super::Device {
     }
     unsafe fn destroy_compute_pipeline(&self, _pipeline: super::ComputePipeline) {}
 
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<super::PipelineCache, crate::PipelineCacheError> {
+        let header = crate::auxil::pipeline_cache::Header {
+            backend: wgt::Backend::Metal,
+            vendor: 0,
+            device: 0,
+            // The driver comes with the OS.
+            driver: format!(
+                "{} {}.{}",
+                self.shared.device.lock().name(),
+                self.shared.private_caps.os_version.0,
+                self.shared.private_caps.os_version.1
+            ),
+        };
+        let (shaders, _) = ShaderCache::new(header, desc.data)?;
+        Ok(super::PipelineCache { shaders })
+    }
+    unsafe fn destroy_pipeline_cache(&self, _cache: super::PipelineCache) {}
+    unsafe fn pipeline_cache_get_data(&self, cache: &super::PipelineCache) -> Vec<u8> {
+        cache.shaders.serialize(&[])
+    }
+
     unsafe fn create_query_set(
         &self,
         desc: &wgt::QuerySetDescriptor<crate::Label>,
@@ -1058,6 +1138,17 @@ impl crate::Device<super::Api> for super::Device {
         }
     }
 
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        let mut heap = self.memory.heap();
+        if self.shared.private_caps.working_set_size {
+            heap.budget = Some(heap.size);
+        }
+        if self.shared.private_caps.allocated_size {
+            heap.driver_usage = Some(self.shared.device.lock().current_allocated_size());
+        }
+        wgt::MemoryReport { heaps: vec![heap] }
+    }
+
     unsafe fn start_capture(&self) -> bool {
         if !self.shared.private_caps.supports_capture_manager {
             return false;
@@ -59,6 +59,7 @@ impl crate::Api for Api {
     type ShaderModule = ShaderModule;
     type RenderPipeline = RenderPipeline;
     type ComputePipeline = ComputePipeline;
+    type PipelineCache = PipelineCache;
 }
 
 pub struct Instance {
@@ -141,6 +142,12 @@ impl crate::Instance<Api> for Instance {
 #[derive(Clone, Debug)]
 struct PrivateCapabilities {
     family_check: bool,
+    /// Major and minor version of the OS.
+    os_version: (u32, u32),
+    /// `MTLResource.allocatedSize` and `MTLDevice.currentAllocatedSize` are available.
+    allocated_size: bool,
+    /// `MTLDevice.recommendedMaxWorkingSetSize` is available.
+    working_set_size: bool,
     msl_version: mtl::MTLLanguageVersion,
     fragment_rw_storage: bool,
     read_write_texture_tier: mtl::MTLReadWriteTextureTier,
@@ -283,6 +290,7 @@ unsafe impl Sync for Queue {}
 pub struct Device {
     shared: Arc<AdapterShared>,
     features: wgt::Features,
+    memory: crate::auxil::memory::ResourceMemory,
 }
 
 pub struct Surface {
@@ -398,6 +406,8 @@ impl crate::Queue<Api> for Queue {
 pub struct Buffer {
     raw: mtl::Buffer,
     size: wgt::BufferAddress,
+    /// Size of the buffer in memory, 0 for buffers that weren't created by the device.
+    memory_size: wgt::BufferAddress,
 }
 
 unsafe impl Send for Buffer {}
@@ -417,6 +427,8 @@ pub struct Texture {
     array_layers: u32,
     mip_levels: u32,
     copy_size: crate::CopyExtent,
+    /// Size of the texture in memory, 0 for textures that weren't created by the device.
+    memory_size: wgt::BufferAddress,
 }
 
 unsafe impl Send for Texture {}
@@ -642,6 +654,12 @@ pub struct RenderPipeline {
 unsafe impl Send for RenderPipeline {}
 unsafe impl Sync for RenderPipeline {}
 
+/// Compiled libraries aren't stored, only the generated MSL is cached.
+#[derive(Debug)]
+pub struct PipelineCache {
+    shaders: crate::auxil::pipeline_cache::ShaderCache<(String, String)>,
+}
+
 pub struct ComputePipeline {
     raw: mtl::ComputePipelineState,
     #[allow(dead_code)]
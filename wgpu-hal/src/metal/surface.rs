@@ -287,6 +287,7 @@ impl crate::Surface<super::Api> for super::Surface {
                     height: self.extent.height,
                     depth: 1,
                 },
+                memory_size: 0,
             },
             drawable,
             present_with_transaction: self.present_with_transaction,
@@ -618,6 +618,21 @@ impl PhysicalDeviceCapabilities {
             }
         }
 
+        if self.supports_extension(vk::ExtMemoryBudgetFn::name()) {
+            extensions.push(vk::ExtMemoryBudgetFn::name());
+        }
+
+        // External memory and dedicated allocations are core in Vulkan 1.1
+        #[cfg(unix)]
+        if self.properties.api_version >= vk::API_VERSION_1_1
+            && self.supports_extension(vk::KhrExternalMemoryFdFn::name())
+        {
+            extensions.push(vk::KhrExternalMemoryFdFn::name());
+            if self.supports_extension(vk::ExtExternalMemoryDmaBufFn::name()) {
+                extensions.push(vk::ExtExternalMemoryDmaBufFn::name());
+            }
+        }
+
         if requested_features.contains(wgt::Features::CONSERVATIVE_RASTERIZATION) {
             extensions.push(vk::ExtConservativeRasterizationFn::name());
         }
@@ -1129,6 +1144,24 @@ impl super::Adapter {
             None
         };
 
+        #[cfg(unix)]
+        let external_memory_fd_fn = if enabled_extensions.contains(&khr::ExternalMemoryFd::name()) {
+            Some(khr::ExternalMemoryFd::new(&self.instance.raw, &raw_device))
+        } else {
+            None
+        };
+        #[cfg(unix)]
+        let external_memory_types = {
+            let mut types = vk::ExternalMemoryHandleTypeFlags::empty();
+            if external_memory_fd_fn.is_some() {
+                types |= vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD;
+                if enabled_extensions.contains(&vk::ExtExternalMemoryDmaBufFn::name()) {
+                    types |= vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT;
+                }
+            }
+            types
+        };
+
         let naga_options = {
             use naga::back::spv;
 
@@ -1194,13 +1227,32 @@ impl super::Adapter {
             raw_device.get_device_queue(family_index, queue_index)
         };
 
+        let memory_stats = super::MemoryStats {
+            heaps: mem_properties.memory_heaps[..mem_properties.memory_heap_count as usize]
+                .iter()
+                .map(|heap| wgt::MemoryHeapReport {
+                    size: heap.size,
+                    device_local: heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                    ..Default::default()
+                })
+                .collect(),
+            type_heaps: memory_types
+                .iter()
+                .map(|memory_type| memory_type.heap_index as usize)
+                .collect(),
+            blocks: Default::default(),
+        };
+
         let shared = Arc::new(super::DeviceShared {
             raw: raw_device,
+            physical_device: self.raw,
             handle_is_owned,
             instance: Arc::clone(&self.instance),
             extension_fns: super::DeviceExtensionFunctions {
                 draw_indirect_count: indirect_count_fn,
                 timeline_semaphore: timeline_semaphore_fn,
+                #[cfg(unix)]
+                external_memory_fd: external_memory_fd_fn,
             },
             vendor_id: self.phd_capabilities.properties.vendor_id,
             timestamp_period: self.phd_capabilities.properties.limits.timestamp_period,
@@ -1210,6 +1262,11 @@ impl super::Adapter {
             workarounds: self.workarounds,
             render_passes: Mutex::new(Default::default()),
             framebuffers: Mutex::new(Default::default()),
+            memory_budget: self.instance.get_physical_device_properties.is_some()
+                && enabled_extensions.contains(&vk::ExtMemoryBudgetFn::name()),
+            memory: Mutex::new(memory_stats),
+            #[cfg(unix)]
+            external_memory_types,
         });
         let mut relay_semaphores = [vk::Semaphore::null(); 2];
         for sem in relay_semaphores.iter_mut() {
@@ -1269,6 +1326,12 @@ impl super::Adapter {
             desc_allocator: Mutex::new(desc_allocator),
             valid_ash_memory_types,
             naga_options,
+            cache_header: crate::auxil::pipeline_cache::Header {
+                backend: wgt::Backend::Vulkan,
+                vendor: self.phd_capabilities.properties.vendor_id,
+                device: self.phd_capabilities.properties.device_id,
+                driver: self.phd_capabilities.properties.driver_version.to_string(),
+            },
             #[cfg(feature = "renderdoc")]
             render_doc: Default::default(),
         };
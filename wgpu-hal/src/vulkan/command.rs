@@ -21,10 +21,16 @@ impl super::Texture {
             let extent = r.texture_base.max_copy_size(&copy_size).min(&r.size);
             let (image_subresource, image_offset) =
                 conv::map_subresource_layers(&r.texture_base, aspects);
+            // The stencil aspect is copied as one byte per texel.
+            let block_size = if r.texture_base.aspect == crate::FormatAspects::STENCIL {
+                1
+            } else {
+                fi.block_size as u32
+            };
             vk::BufferImageCopy {
                 buffer_offset: r.buffer_layout.offset,
                 buffer_row_length: r.buffer_layout.bytes_per_row.map_or(0, |bpr| {
-                    fi.block_dimensions.0 as u32 * (bpr.get() / fi.block_size as u32)
+                    fi.block_dimensions.0 as u32 * (bpr.get() / block_size)
                 }),
                 buffer_image_height: r
                     .buffer_layout
@@ -1,4 +1,5 @@
 use super::conv;
+use crate::auxil::pipeline_cache::ShaderCache;
 
 use arrayvec::ArrayVec;
 use ash::{extensions::khr, vk};
@@ -254,16 +255,16 @@ impl super::DeviceShared {
         &self,
         buffer: &'a super::Buffer,
         ranges: I,
-    ) -> impl 'a + Iterator<Item = vk::MappedMemoryRange> {
-        let block = buffer.block.lock();
+    ) -> Option<impl 'a + Iterator<Item = vk::MappedMemoryRange>> {
+        let block = buffer.block.as_ref()?.lock();
         let mask = self.private_caps.non_coherent_map_mask;
-        ranges.map(move |range| {
+        Some(ranges.map(move |range| {
             vk::MappedMemoryRange::builder()
                 .memory(*block.memory())
                 .offset((block.offset() + range.start) & !mask)
                 .size((range.end - range.start + mask) & !mask)
                 .build()
-        })
+        }))
     }
 
     unsafe fn free_resources(&self) {
@@ -279,6 +280,27 @@ impl super::DeviceShared {
     }
 }
 
+impl super::MemoryStats {
+    pub(super) fn heap_mut(&mut self, memory_type: u32) -> &mut wgt::MemoryHeapReport {
+        let heap = self.type_heaps[memory_type as usize];
+        &mut self.heaps[heap]
+    }
+
+    pub(super) fn add_block(&mut self, memory: vk::DeviceMemory, memory_type: u32, size: u64) {
+        let heap = self.type_heaps[memory_type as usize];
+        self.heaps[heap].block_count += 1;
+        self.heaps[heap].block_bytes += size;
+        self.blocks.insert(memory, (heap, size));
+    }
+
+    fn remove_block(&mut self, memory: vk::DeviceMemory) {
+        if let Some((heap, size)) = self.blocks.remove(&memory) {
+            self.heaps[heap].block_count -= 1;
+            self.heaps[heap].block_bytes -= size;
+        }
+    }
+}
+
 impl gpu_alloc::MemoryDevice<vk::DeviceMemory> for super::DeviceShared {
     unsafe fn allocate_memory(
         &self,
@@ -299,7 +321,10 @@ impl gpu_alloc::MemoryDevice<vk::DeviceMemory> for super::DeviceShared {
         }
 
         match self.raw.allocate_memory(&info, None) {
-            Ok(memory) => Ok(memory),
+            Ok(memory) => {
+                self.memory.lock().add_block(memory, memory_type, size);
+                Ok(memory)
+            }
             Err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => {
                 Err(gpu_alloc::OutOfMemory::OutOfDeviceMemory)
             }
@@ -312,6 +337,7 @@ impl gpu_alloc::MemoryDevice<vk::DeviceMemory> for super::DeviceShared {
     }
 
     unsafe fn deallocate_memory(&self, memory: vk::DeviceMemory) {
+        self.memory.lock().remove_block(memory);
         self.raw.free_memory(memory, None);
     }
 
@@ -594,6 +620,8 @@ impl super::Device {
             raw: vk_image,
             drop_guard,
             block: None,
+            #[cfg(unix)]
+            external_memory: None,
             usage: desc.usage,
             aspects: crate::FormatAspects::from(desc.format),
             format_info: desc.format.describe(),
@@ -602,6 +630,68 @@ impl super::Device {
         }
     }
 
+    /// # Safety
+    ///
+    /// - `vk_buffer` must be bound to memory that outlives the returned buffer
+    /// - The buffer is destroyed by `destroy_buffer`, but its memory is left to the application,
+    ///   and it can't be mapped
+    pub unsafe fn buffer_from_raw(vk_buffer: vk::Buffer) -> super::Buffer {
+        super::Buffer {
+            raw: vk_buffer,
+            block: None,
+            #[cfg(unix)]
+            external_memory: None,
+        }
+    }
+
+    /// Creates the image of a texture, without any memory bound to it.
+    pub(super) unsafe fn create_image(
+        &self,
+        desc: &crate::TextureDescriptor,
+        tiling: vk::ImageTiling,
+        external_memory_types: vk::ExternalMemoryHandleTypeFlags,
+    ) -> Result<(vk::Image, vk::ImageCreateFlags, crate::CopyExtent), crate::DeviceError> {
+        let array_layer_count = match desc.dimension {
+            wgt::TextureDimension::D3 => 1,
+            _ => desc.size.depth_or_array_layers,
+        };
+        let copy_size = conv::map_extent_to_copy_size(&desc.size, desc.dimension);
+
+        let mut raw_flags = vk::ImageCreateFlags::empty();
+        if desc.dimension == wgt::TextureDimension::D2
+            && desc.size.depth_or_array_layers % 6 == 0
+            && desc.sample_count == 1
+            && desc.size.width == desc.size.height
+        {
+            raw_flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+        }
+
+        let mut external_info =
+            vk::ExternalMemoryImageCreateInfo::builder().handle_types(external_memory_types);
+        let mut vk_info = vk::ImageCreateInfo::builder()
+            .flags(raw_flags)
+            .image_type(conv::map_texture_dimension(desc.dimension))
+            .format(self.shared.private_caps.map_texture_format(desc.format))
+            .extent(vk::Extent3D {
+                width: copy_size.width,
+                height: copy_size.height,
+                depth: copy_size.depth,
+            })
+            .mip_levels(desc.mip_level_count)
+            .array_layers(array_layer_count)
+            .samples(vk::SampleCountFlags::from_raw(desc.sample_count))
+            .tiling(tiling)
+            .usage(conv::map_texture_usage(desc.usage))
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        if !external_memory_types.is_empty() {
+            vk_info = vk_info.push_next(&mut external_info);
+        }
+
+        let raw = self.shared.raw.create_image(&vk_info, None)?;
+        Ok((raw, raw_flags, copy_size))
+    }
+
     fn create_shader_module_impl(
         &self,
         spv: &[u32],
@@ -621,6 +711,7 @@ impl super::Device {
         &self,
         stage: &crate::ProgrammableStage<super::Api>,
         naga_stage: naga::ShaderStage,
+        cache: Option<&super::PipelineCache>,
     ) -> Result<CompiledStage, crate::PipelineError> {
         let stage_flags = crate::auxil::map_naga_stage(naga_stage);
         let vk_module = match *stage.module {
@@ -647,16 +738,22 @@ impl super::Device {
                 } else {
                     &self.naga_options
                 };
-                let spv = {
-                    profiling::scope!("naga::spv::write_vec");
-                    naga::back::spv::write_vec(
-                        &naga_shader.module,
-                        &naga_shader.info,
-                        options,
-                        Some(&pipeline_options),
-                    )
-                }
-                .map_err(|e| crate::PipelineError::Linkage(stage_flags, format!("{}", e)))?;
+                let spv = ShaderCache::get_or_translate(
+                    cache.map(|cache| &cache.shaders),
+                    naga_shader.source_hash,
+                    (stage.entry_point, naga_stage),
+                    options,
+                    || {
+                        profiling::scope!("naga::spv::write_vec");
+                        naga::back::spv::write_vec(
+                            &naga_shader.module,
+                            &naga_shader.info,
+                            options,
+                            Some(&pipeline_options),
+                        )
+                        .map_err(|e| crate::PipelineError::Linkage(stage_flags, format!("{}", e)))
+                    },
+                )?;
                 self.create_shader_module_impl(&spv)?
             }
         };
@@ -741,6 +838,11 @@ impl crate::Device<super::Api> for super::Device {
         self.shared
             .raw
             .bind_buffer_memory(raw, *block.memory(), block.offset())?;
+        self.shared
+            .memory
+            .lock()
+            .heap_mut(block.memory_type())
+            .buffer_bytes += block.size();
 
         if let Some(label) = desc.label {
             self.shared
@@ -749,14 +851,31 @@ impl crate::Device<super::Api> for super::Device {
 
         Ok(super::Buffer {
             raw,
-            block: Mutex::new(block),
+            block: Some(Mutex::new(block)),
+            #[cfg(unix)]
+            external_memory: None,
         })
     }
     unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
         self.shared.raw.destroy_buffer(buffer.raw, None);
-        self.mem_allocator
-            .lock()
-            .dealloc(&*self.shared, buffer.block.into_inner());
+        if let Some(block) = buffer.block {
+            let block = block.into_inner();
+            self.shared
+                .memory
+                .lock()
+                .heap_mut(block.memory_type())
+                .buffer_bytes -= block.size();
+            self.mem_allocator.lock().dealloc(&*self.shared, block);
+        }
+        #[cfg(unix)]
+        if let Some(memory) = buffer.external_memory {
+            self.shared
+                .memory
+                .lock()
+                .heap_mut(memory.memory_type)
+                .buffer_bytes -= memory.size;
+            gpu_alloc::MemoryDevice::deallocate_memory(&*self.shared, memory.raw);
+        }
     }
 
     unsafe fn map_buffer(
@@ -765,7 +884,13 @@ impl crate::Device<super::Api> for super::Device {
         range: crate::MemoryRange,
     ) -> Result<crate::BufferMapping, crate::DeviceError> {
         let size = range.end - range.start;
-        let mut block = buffer.block.lock();
+        let mut block = match buffer.block {
+            Some(ref block) => block.lock(),
+            None => {
+                log::error!("Buffers created from raw handles can't be mapped");
+                return Err(crate::DeviceError::OutOfMemory);
+            }
+        };
         let ptr = block.map(&*self.shared, range.start, size as usize)?;
         let is_coherent = block
             .props()
@@ -773,7 +898,9 @@ impl crate::Device<super::Api> for super::Device {
         Ok(crate::BufferMapping { ptr, is_coherent })
     }
     unsafe fn unmap_buffer(&self, buffer: &super::Buffer) -> Result<(), crate::DeviceError> {
-        buffer.block.lock().unmap(&*self.shared);
+        if let Some(ref block) = buffer.block {
+            block.lock().unmap(&*self.shared);
+        }
         Ok(())
     }
 
@@ -781,61 +908,35 @@ impl crate::Device<super::Api> for super::Device {
     where
         I: Iterator<Item = crate::MemoryRange>,
     {
-        let vk_ranges = self.shared.make_memory_ranges(buffer, ranges);
-        inplace_or_alloc_from_iter(vk_ranges, |array| {
-            self.shared.raw.flush_mapped_memory_ranges(array).unwrap()
-        });
+        if let Some(vk_ranges) = self.shared.make_memory_ranges(buffer, ranges) {
+            inplace_or_alloc_from_iter(vk_ranges, |array| {
+                self.shared.raw.flush_mapped_memory_ranges(array).unwrap()
+            });
+        }
     }
     unsafe fn invalidate_mapped_ranges<I>(&self, buffer: &super::Buffer, ranges: I)
     where
         I: Iterator<Item = crate::MemoryRange>,
     {
-        let vk_ranges = self.shared.make_memory_ranges(buffer, ranges);
-        inplace_or_alloc_from_iter(vk_ranges, |array| {
-            self.shared
-                .raw
-                .invalidate_mapped_memory_ranges(array)
-                .unwrap()
-        });
+        if let Some(vk_ranges) = self.shared.make_memory_ranges(buffer, ranges) {
+            inplace_or_alloc_from_iter(vk_ranges, |array| {
+                self.shared
+                    .raw
+                    .invalidate_mapped_memory_ranges(array)
+                    .unwrap()
+            });
+        }
     }
 
     unsafe fn create_texture(
         &self,
         desc: &crate::TextureDescriptor,
     ) -> Result<super::Texture, crate::DeviceError> {
-        let array_layer_count = match desc.dimension {
-            wgt::TextureDimension::D3 => 1,
-            _ => desc.size.depth_or_array_layers,
-        };
-        let copy_size = conv::map_extent_to_copy_size(&desc.size, desc.dimension);
-
-        let mut raw_flags = vk::ImageCreateFlags::empty();
-        if desc.dimension == wgt::TextureDimension::D2
-            && desc.size.depth_or_array_layers % 6 == 0
-            && desc.sample_count == 1
-            && desc.size.width == desc.size.height
-        {
-            raw_flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
-        }
-
-        let vk_info = vk::ImageCreateInfo::builder()
-            .flags(raw_flags)
-            .image_type(conv::map_texture_dimension(desc.dimension))
-            .format(self.shared.private_caps.map_texture_format(desc.format))
-            .extent(vk::Extent3D {
-                width: copy_size.width,
-                height: copy_size.height,
-                depth: copy_size.depth,
-            })
-            .mip_levels(desc.mip_level_count)
-            .array_layers(array_layer_count)
-            .samples(vk::SampleCountFlags::from_raw(desc.sample_count))
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(conv::map_texture_usage(desc.usage))
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .initial_layout(vk::ImageLayout::UNDEFINED);
-
-        let raw = self.shared.raw.create_image(&vk_info, None)?;
+        let (raw, raw_flags, copy_size) = self.create_image(
+            desc,
+            vk::ImageTiling::OPTIMAL,
+            vk::ExternalMemoryHandleTypeFlags::empty(),
+        )?;
         let req = self.shared.raw.get_image_memory_requirements(raw);
 
         let block = self.mem_allocator.lock().alloc(
@@ -851,6 +952,11 @@ impl crate::Device<super::Api> for super::Device {
         self.shared
             .raw
             .bind_image_memory(raw, *block.memory(), block.offset())?;
+        self.shared
+            .memory
+            .lock()
+            .heap_mut(block.memory_type())
+            .texture_bytes += block.size();
 
         if let Some(label) = desc.label {
             self.shared
@@ -861,6 +967,8 @@ impl crate::Device<super::Api> for super::Device {
             raw,
             drop_guard: None,
             block: Some(block),
+            #[cfg(unix)]
+            external_memory: None,
             usage: desc.usage,
             aspects: crate::FormatAspects::from(desc.format),
             format_info: desc.format.describe(),
@@ -873,8 +981,22 @@ impl crate::Device<super::Api> for super::Device {
             self.shared.raw.destroy_image(texture.raw, None);
         }
         if let Some(block) = texture.block {
+            self.shared
+                .memory
+                .lock()
+                .heap_mut(block.memory_type())
+                .texture_bytes -= block.size();
             self.mem_allocator.lock().dealloc(&*self.shared, block);
         }
+        #[cfg(unix)]
+        if let Some(memory) = texture.external_memory {
+            self.shared
+                .memory
+                .lock()
+                .heap_mut(memory.memory_type)
+                .texture_bytes -= memory.size;
+            gpu_alloc::MemoryDevice::deallocate_memory(&*self.shared, memory.raw);
+        }
     }
 
     unsafe fn create_texture_view(
@@ -1417,11 +1539,13 @@ impl crate::Device<super::Api> for super::Device {
             .primitive_restart_enable(desc.primitive.strip_index_format.is_some())
             .build();
 
-        let compiled_vs = self.compile_stage(&desc.vertex_stage, naga::ShaderStage::Vertex)?;
+        let compiled_vs =
+            self.compile_stage(&desc.vertex_stage, naga::ShaderStage::Vertex, desc.cache)?;
         stages.push(compiled_vs.create_info);
         let compiled_fs = match desc.fragment_stage {
             Some(ref stage) => {
-                let compiled = self.compile_stage(stage, naga::ShaderStage::Fragment)?;
+                let compiled =
+                    self.compile_stage(stage, naga::ShaderStage::Fragment, desc.cache)?;
                 stages.push(compiled.create_info);
                 Some(compiled)
             }
@@ -1544,6 +1668,9 @@ impl crate::Device<super::Api> for super::Device {
             .make_render_pass(compatible_rp_key)
             .map_err(crate::DeviceError::from)?;
 
+        let raw_cache = desc
+            .cache
+            .map_or(vk::PipelineCache::null(), |cache| cache.raw);
         let vk_infos = [{
             vk::GraphicsPipelineCreateInfo::builder()
                 .layout(desc.layout.raw)
@@ -1564,7 +1691,7 @@ impl crate::Device<super::Api> for super::Device {
             profiling::scope!("vkCreateGraphicsPipelines");
             self.shared
                 .raw
-                .create_graphics_pipelines(vk::PipelineCache::null(), &vk_infos, None)
+                .create_graphics_pipelines(raw_cache, &vk_infos, None)
                 .map_err(|(_, e)| crate::DeviceError::from(e))?
         };
 
@@ -1595,7 +1722,10 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::ComputePipelineDescriptor<super::Api>,
     ) -> Result<super::ComputePipeline, crate::PipelineError> {
-        let compiled = self.compile_stage(&desc.stage, naga::ShaderStage::Compute)?;
+        let compiled = self.compile_stage(&desc.stage, naga::ShaderStage::Compute, desc.cache)?;
+        let raw_cache = desc
+            .cache
+            .map_or(vk::PipelineCache::null(), |cache| cache.raw);
 
         let vk_infos = [{
             vk::ComputePipelineCreateInfo::builder()
@@ -1608,7 +1738,7 @@ impl crate::Device<super::Api> for super::Device {
             profiling::scope!("vkCreateComputePipelines");
             self.shared
                 .raw
-                .create_compute_pipelines(vk::PipelineCache::null(), &vk_infos, None)
+                .create_compute_pipelines(raw_cache, &vk_infos, None)
                 .map_err(|(_, e)| crate::DeviceError::from(e))?
         };
 
@@ -1628,6 +1758,38 @@ impl crate::Device<super::Api> for super::Device {
         self.shared.raw.destroy_pipeline(pipeline.raw, None);
     }
 
+    unsafe fn create_pipeline_cache(
+        &self,
+        desc: &crate::PipelineCacheDescriptor,
+    ) -> Result<super::PipelineCache, crate::PipelineCacheError> {
+        let (shaders, native) = ShaderCache::new(self.cache_header.clone(), desc.data)?;
+        // The driver checks the header of the blob, and ignores it if it's incompatible.
+        let vk_info = vk::PipelineCacheCreateInfo::builder().initial_data(&native);
+        let raw = self
+            .shared
+            .raw
+            .create_pipeline_cache(&vk_info, None)
+            .map_err(crate::DeviceError::from)?;
+        if let Some(label) = desc.label {
+            self.shared
+                .set_object_name(vk::ObjectType::PIPELINE_CACHE, raw, label);
+        }
+        Ok(super::PipelineCache { raw, shaders })
+    }
+    unsafe fn destroy_pipeline_cache(&self, cache: super::PipelineCache) {
+        self.shared.raw.destroy_pipeline_cache(cache.raw, None);
+    }
+    unsafe fn pipeline_cache_get_data(&self, cache: &super::PipelineCache) -> Vec<u8> {
+        let native = match self.shared.raw.get_pipeline_cache_data(cache.raw) {
+            Ok(native) => native,
+            Err(e) => {
+                log::warn!("Unable to get the pipeline cache data: {:?}", e);
+                Vec::new()
+            }
+        };
+        cache.shaders.serialize(&native)
+    }
+
     unsafe fn create_query_set(
         &self,
         desc: &wgt::QuerySetDescriptor<crate::Label>,
@@ -1763,6 +1925,28 @@ impl crate::Device<super::Api> for super::Device {
         }
     }
 
+    unsafe fn memory_report(&self) -> wgt::MemoryReport {
+        let mut heaps = self.shared.memory.lock().heaps.clone();
+        if self.shared.memory_budget {
+            if let Some(ref get_device_properties) =
+                self.shared.instance.get_physical_device_properties
+            {
+                let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+                let mut properties2 = vk::PhysicalDeviceMemoryProperties2::builder()
+                    .push_next(&mut budget_properties);
+                get_device_properties.get_physical_device_memory_properties2(
+                    self.shared.physical_device,
+                    &mut properties2,
+                );
+                for (i, heap) in heaps.iter_mut().enumerate() {
+                    heap.budget = Some(budget_properties.heap_budget[i]);
+                    heap.driver_usage = Some(budget_properties.heap_usage[i]);
+                }
+            }
+        }
+        wgt::MemoryReport { heaps }
+    }
+
     unsafe fn start_capture(&self) -> bool {
         #[cfg(feature = "renderdoc")]
         {
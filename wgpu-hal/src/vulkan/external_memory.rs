@@ -0,0 +1,531 @@
+//! Sharing the memory of buffers and textures with other APIs and processes, through the file
+//! descriptors of `VK_KHR_external_memory_fd`.
+//!
+//! External memory is not allocated through `gpu-alloc`: each resource gets a dedicated
+//! allocation, which is what the drivers expect for memory shared with another process.
+
+use super::conv;
+
+use ash::{extensions::khr, vk};
+use std::os::unix::io::RawFd;
+use thiserror::Error;
+
+/// Type of the file descriptors that share the memory of a resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExternalMemoryType {
+    /// Opaque file descriptor, that can only be imported by Vulkan or OpenGL on the same
+    /// physical device and driver.
+    OpaqueFd,
+    /// Linux DMA-BUF, that can be imported by other drivers and APIs, like compositors.
+    ///
+    /// Textures shared as DMA-BUF use linear tiling, so that they can be imported without a
+    /// format modifier. Their layout is given by [`super::Device::external_texture_layout`].
+    DmaBuf,
+}
+
+impl ExternalMemoryType {
+    fn to_vk(self) -> vk::ExternalMemoryHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD,
+            Self::DmaBuf => vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum ExternalMemoryError {
+    #[error("{0:?} memory is not supported by the device")]
+    Unsupported(ExternalMemoryType),
+    #[error("no memory type of the device can hold the resource")]
+    NoMemoryType,
+    #[error("the file descriptor can't be imported")]
+    InvalidHandle,
+    #[error("the resource doesn't have external memory")]
+    NotExternal,
+    #[error(transparent)]
+    Device(#[from] crate::DeviceError),
+}
+
+impl From<vk::Result> for ExternalMemoryError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_INVALID_EXTERNAL_HANDLE => Self::InvalidHandle,
+            other => Self::Device(other.into()),
+        }
+    }
+}
+
+impl super::Device {
+    /// Returns whether resources can share their memory through file descriptors of type `ty`.
+    pub fn supports_external_memory(&self, ty: ExternalMemoryType) -> bool {
+        self.shared.external_memory_types.contains(ty.to_vk())
+    }
+
+    /// Creates a buffer whose memory can be exported with [`Self::export_buffer_memory`].
+    ///
+    /// The buffer can't be mapped.
+    ///
+    /// # Safety
+    ///
+    /// - Same as `create_buffer`
+    pub unsafe fn create_exportable_buffer(
+        &self,
+        desc: &crate::BufferDescriptor,
+        ty: ExternalMemoryType,
+    ) -> Result<super::Buffer, ExternalMemoryError> {
+        self.create_external_buffer(desc, ty, None)
+    }
+
+    /// Creates a buffer bound to the memory shared through `fd`.
+    ///
+    /// The buffer can't be mapped.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must share the memory of a buffer created with the same descriptor, on the same
+    ///   physical device
+    /// - On success, the device owns `fd`, which must not be used or closed by the caller
+    pub unsafe fn import_buffer_memory(
+        &self,
+        desc: &crate::BufferDescriptor,
+        ty: ExternalMemoryType,
+        fd: RawFd,
+    ) -> Result<super::Buffer, ExternalMemoryError> {
+        self.create_external_buffer(desc, ty, Some(fd))
+    }
+
+    /// Creates a texture whose memory can be exported with [`Self::export_texture_memory`].
+    ///
+    /// With [`ExternalMemoryType::DmaBuf`], the texture must be 2D, with a single mip level and
+    /// array layer.
+    ///
+    /// # Safety
+    ///
+    /// - Same as `create_texture`
+    pub unsafe fn create_exportable_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: ExternalMemoryType,
+    ) -> Result<super::Texture, ExternalMemoryError> {
+        self.create_external_texture(desc, ty, None)
+    }
+
+    /// Creates a texture bound to the memory shared through `fd`.
+    ///
+    /// # Safety
+    ///
+    /// - `fd` must share the memory of a texture created with the same descriptor, on the same
+    ///   physical device. For [`ExternalMemoryType::DmaBuf`], the memory must have a linear
+    ///   layout matching [`Self::external_texture_layout`]
+    /// - On success, the device owns `fd`, which must not be used or closed by the caller
+    pub unsafe fn import_texture_memory(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: ExternalMemoryType,
+        fd: RawFd,
+    ) -> Result<super::Texture, ExternalMemoryError> {
+        self.create_external_texture(desc, ty, Some(fd))
+    }
+
+    /// Exports the memory of a buffer with external memory, as a new file descriptor owned by
+    /// the caller.
+    ///
+    /// # Safety
+    ///
+    /// - The buffer must not be destroyed while the memory is used through the file descriptor
+    pub unsafe fn export_buffer_memory(
+        &self,
+        buffer: &super::Buffer,
+    ) -> Result<RawFd, ExternalMemoryError> {
+        self.export_memory(buffer.external_memory.as_ref())
+    }
+
+    /// Exports the memory of a texture with external memory, as a new file descriptor owned by
+    /// the caller.
+    ///
+    /// # Safety
+    ///
+    /// - The texture must not be destroyed while the memory is used through the file descriptor
+    pub unsafe fn export_texture_memory(
+        &self,
+        texture: &super::Texture,
+    ) -> Result<RawFd, ExternalMemoryError> {
+        self.export_memory(texture.external_memory.as_ref())
+    }
+
+    /// Returns the layout of the first mip level and layer of a texture with external memory,
+    /// as other APIs need it to import DMA-BUF memory.
+    ///
+    /// Only meaningful for textures shared as [`ExternalMemoryType::DmaBuf`].
+    ///
+    /// # Safety
+    ///
+    /// - The texture must have linear tiling
+    pub unsafe fn external_texture_layout(
+        &self,
+        texture: &super::Texture,
+    ) -> vk::SubresourceLayout {
+        let subresource = vk::ImageSubresource {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            array_layer: 0,
+        };
+        self.shared
+            .raw
+            .get_image_subresource_layout(texture.raw, subresource)
+    }
+
+    fn external_memory_fd(
+        &self,
+        ty: ExternalMemoryType,
+    ) -> Result<&khr::ExternalMemoryFd, ExternalMemoryError> {
+        match self.shared.extension_fns.external_memory_fd {
+            Some(ref fd_fn) if self.supports_external_memory(ty) => Ok(fd_fn),
+            _ => Err(ExternalMemoryError::Unsupported(ty)),
+        }
+    }
+
+    unsafe fn create_external_buffer(
+        &self,
+        desc: &crate::BufferDescriptor,
+        ty: ExternalMemoryType,
+        fd: Option<RawFd>,
+    ) -> Result<super::Buffer, ExternalMemoryError> {
+        self.external_memory_fd(ty)?;
+
+        let mut external_info =
+            vk::ExternalMemoryBufferCreateInfo::builder().handle_types(ty.to_vk());
+        let vk_info = vk::BufferCreateInfo::builder()
+            .size(desc.size)
+            .usage(conv::map_buffer_usage(desc.usage))
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .push_next(&mut external_info);
+
+        let raw = self
+            .shared
+            .raw
+            .create_buffer(&vk_info, None)
+            .map_err(crate::DeviceError::from)?;
+        let req = self.shared.raw.get_buffer_memory_requirements(raw);
+        let dedicated_info = vk::MemoryDedicatedAllocateInfo::builder()
+            .buffer(raw)
+            .build();
+
+        let memory = match self.allocate_external_memory(req, ty, fd, dedicated_info) {
+            Ok(memory) => memory,
+            Err(err) => {
+                self.shared.raw.destroy_buffer(raw, None);
+                return Err(err);
+            }
+        };
+        if let Err(err) = self.shared.raw.bind_buffer_memory(raw, memory.raw, 0) {
+            self.shared.raw.destroy_buffer(raw, None);
+            gpu_alloc::MemoryDevice::deallocate_memory(&*self.shared, memory.raw);
+            return Err(err.into());
+        }
+        self.shared
+            .memory
+            .lock()
+            .heap_mut(memory.memory_type)
+            .buffer_bytes += memory.size;
+
+        if let Some(label) = desc.label {
+            self.shared
+                .set_object_name(vk::ObjectType::BUFFER, raw, label);
+        }
+
+        Ok(super::Buffer {
+            raw,
+            block: None,
+            external_memory: Some(memory),
+        })
+    }
+
+    unsafe fn create_external_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: ExternalMemoryType,
+        fd: Option<RawFd>,
+    ) -> Result<super::Texture, ExternalMemoryError> {
+        self.external_memory_fd(ty)?;
+
+        let tiling = match ty {
+            ExternalMemoryType::OpaqueFd => vk::ImageTiling::OPTIMAL,
+            ExternalMemoryType::DmaBuf => vk::ImageTiling::LINEAR,
+        };
+        let (raw, raw_flags, copy_size) = self.create_image(desc, tiling, ty.to_vk())?;
+        let req = self.shared.raw.get_image_memory_requirements(raw);
+        let dedicated_info = vk::MemoryDedicatedAllocateInfo::builder()
+            .image(raw)
+            .build();
+
+        let memory = match self.allocate_external_memory(req, ty, fd, dedicated_info) {
+            Ok(memory) => memory,
+            Err(err) => {
+                self.shared.raw.destroy_image(raw, None);
+                return Err(err);
+            }
+        };
+        if let Err(err) = self.shared.raw.bind_image_memory(raw, memory.raw, 0) {
+            self.shared.raw.destroy_image(raw, None);
+            gpu_alloc::MemoryDevice::deallocate_memory(&*self.shared, memory.raw);
+            return Err(err.into());
+        }
+        self.shared
+            .memory
+            .lock()
+            .heap_mut(memory.memory_type)
+            .texture_bytes += memory.size;
+
+        if let Some(label) = desc.label {
+            self.shared
+                .set_object_name(vk::ObjectType::IMAGE, raw, label);
+        }
+
+        Ok(super::Texture {
+            raw,
+            drop_guard: None,
+            block: None,
+            external_memory: Some(memory),
+            usage: desc.usage,
+            aspects: crate::FormatAspects::from(desc.format),
+            format_info: desc.format.describe(),
+            raw_flags,
+            copy_size,
+        })
+    }
+
+    /// Allocates memory dedicated to a resource, exportable if `fd` is `None`, or imported
+    /// from `fd` otherwise.
+    unsafe fn allocate_external_memory(
+        &self,
+        req: vk::MemoryRequirements,
+        ty: ExternalMemoryType,
+        fd: Option<RawFd>,
+        mut dedicated_info: vk::MemoryDedicatedAllocateInfo,
+    ) -> Result<super::ExternalMemory, ExternalMemoryError> {
+        let handle_type = ty.to_vk();
+        let mut memory_type_bits = req.memory_type_bits & self.valid_ash_memory_types;
+        // The memory types of opaque file descriptors can't be queried, they are the ones
+        // the resource requires.
+        if let (Some(fd), ExternalMemoryType::DmaBuf) = (fd, ty) {
+            let fd_fn = self.external_memory_fd(ty)?;
+            let mut fd_properties = vk::MemoryFdPropertiesKHR::default();
+            fd_fn
+                .fp()
+                .get_memory_fd_properties_khr(
+                    self.shared.raw.handle(),
+                    handle_type,
+                    fd,
+                    &mut fd_properties,
+                )
+                .result()?;
+            memory_type_bits &= fd_properties.memory_type_bits;
+        }
+
+        let mem_properties = self
+            .shared
+            .instance
+            .raw
+            .get_physical_device_memory_properties(self.shared.physical_device);
+        let memory_types =
+            &mem_properties.memory_types[..mem_properties.memory_type_count as usize];
+        let is_allowed = |index: &usize| memory_type_bits & (1 << index) != 0;
+        let memory_type = (0..memory_types.len())
+            .filter(is_allowed)
+            .find(|&index| {
+                memory_types[index]
+                    .property_flags
+                    .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            })
+            .or_else(|| (0..memory_types.len()).find(is_allowed))
+            .ok_or(ExternalMemoryError::NoMemoryType)? as u32;
+
+        let mut export_info = vk::ExportMemoryAllocateInfo::builder().handle_types(handle_type);
+        let mut import_info = vk::ImportMemoryFdInfoKHR::builder()
+            .handle_type(handle_type)
+            .fd(fd.unwrap_or(-1));
+        let mut info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(req.size)
+            .memory_type_index(memory_type)
+            .push_next(&mut dedicated_info);
+        info = match fd {
+            Some(_) => info.push_next(&mut import_info),
+            None => info.push_next(&mut export_info),
+        };
+
+        let raw = self.shared.raw.allocate_memory(&info, None)?;
+        self.shared
+            .memory
+            .lock()
+            .add_block(raw, memory_type, req.size);
+        Ok(super::ExternalMemory {
+            raw,
+            handle_type,
+            memory_type,
+            size: req.size,
+        })
+    }
+
+    unsafe fn export_memory(
+        &self,
+        memory: Option<&super::ExternalMemory>,
+    ) -> Result<RawFd, ExternalMemoryError> {
+        let memory = memory.ok_or(ExternalMemoryError::NotExternal)?;
+        let fd_fn = self
+            .shared
+            .extension_fns
+            .external_memory_fd
+            .as_ref()
+            .unwrap();
+        let info = vk::MemoryGetFdInfoKHR::builder()
+            .memory(memory.raw)
+            .handle_type(memory.handle_type);
+        Ok(fd_fn.get_memory_fd(&info)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternalMemoryType;
+    use crate::{Adapter as _, CommandEncoder as _, Device as _, Instance as _, Queue as _};
+    use std::{iter, ptr};
+
+    #[test]
+    fn opaque_fd_buffer_roundtrip() {
+        let desc = crate::InstanceDescriptor {
+            name: "external memory test",
+            flags: crate::InstanceFlags::empty(),
+        };
+        // Skipped without a Vulkan driver.
+        let instance = match unsafe { crate::vulkan::Instance::init(&desc) } {
+            Ok(instance) => instance,
+            Err(_) => return,
+        };
+        let exposed = match unsafe { instance.enumerate_adapters() }.into_iter().next() {
+            Some(exposed) => exposed,
+            None => return,
+        };
+        let crate::OpenDevice { device, mut queue } = unsafe {
+            exposed
+                .adapter
+                .open(wgt::Features::empty(), &wgt::Limits::default())
+                .unwrap()
+        };
+        if !device.supports_external_memory(ExternalMemoryType::OpaqueFd) {
+            return;
+        }
+
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let size = data.len() as wgt::BufferAddress;
+        let staging_desc = |label, usage| crate::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            memory_flags: crate::MemoryFlags::empty(),
+        };
+        let shared_desc = crate::BufferDescriptor {
+            label: Some("shared"),
+            size,
+            usage: crate::BufferUses::COPY_SRC | crate::BufferUses::COPY_DST,
+            memory_flags: crate::MemoryFlags::empty(),
+        };
+        unsafe {
+            let upload = device
+                .create_buffer(&staging_desc(
+                    "upload",
+                    crate::BufferUses::MAP_WRITE | crate::BufferUses::COPY_SRC,
+                ))
+                .unwrap();
+            let download = device
+                .create_buffer(&staging_desc(
+                    "download",
+                    crate::BufferUses::MAP_READ | crate::BufferUses::COPY_DST,
+                ))
+                .unwrap();
+            let exported = device
+                .create_exportable_buffer(&shared_desc, ExternalMemoryType::OpaqueFd)
+                .unwrap();
+            let fd = device.export_buffer_memory(&exported).unwrap();
+            let imported = device
+                .import_buffer_memory(&shared_desc, ExternalMemoryType::OpaqueFd, fd)
+                .unwrap();
+
+            let mapping = device.map_buffer(&upload, 0..size).unwrap();
+            ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.as_ptr(), data.len());
+            if !mapping.is_coherent {
+                device.flush_mapped_ranges(&upload, iter::once(0..size));
+            }
+            device.unmap_buffer(&upload).unwrap();
+
+            // Written through the exported buffer, and read through the imported one.
+            let mut encoder = device
+                .create_command_encoder(&crate::CommandEncoderDescriptor {
+                    label: None,
+                    queue: &queue,
+                })
+                .unwrap();
+            let copy = crate::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size: wgt::BufferSize::new(size).unwrap(),
+            };
+            encoder.begin_encoding(None).unwrap();
+            encoder.transition_buffers(
+                vec![
+                    crate::BufferBarrier {
+                        buffer: &upload,
+                        usage: crate::BufferUses::MAP_WRITE..crate::BufferUses::COPY_SRC,
+                    },
+                    crate::BufferBarrier {
+                        buffer: &exported,
+                        usage: crate::BufferUses::empty()..crate::BufferUses::COPY_DST,
+                    },
+                ]
+                .into_iter(),
+            );
+            encoder.copy_buffer_to_buffer(&upload, &exported, iter::once(copy));
+            encoder.transition_buffers(
+                vec![
+                    crate::BufferBarrier {
+                        buffer: &imported,
+                        usage: crate::BufferUses::COPY_DST..crate::BufferUses::COPY_SRC,
+                    },
+                    crate::BufferBarrier {
+                        buffer: &download,
+                        usage: crate::BufferUses::empty()..crate::BufferUses::COPY_DST,
+                    },
+                ]
+                .into_iter(),
+            );
+            encoder.copy_buffer_to_buffer(&imported, &download, iter::once(copy));
+            encoder.transition_buffers(iter::once(crate::BufferBarrier {
+                buffer: &download,
+                usage: crate::BufferUses::COPY_DST..crate::BufferUses::MAP_READ,
+            }));
+            let cmd_buf = encoder.end_encoding().unwrap();
+
+            let mut fence = device.create_fence().unwrap();
+            queue.submit(&[&cmd_buf], Some((&mut fence, 1))).unwrap();
+            assert!(device.wait(&fence, 1, !0).unwrap());
+
+            let mapping = device.map_buffer(&download, 0..size).unwrap();
+            if !mapping.is_coherent {
+                device.invalidate_mapped_ranges(&download, iter::once(0..size));
+            }
+            let mut contents = [0u8; 16];
+            ptr::copy_nonoverlapping(mapping.ptr.as_ptr(), contents.as_mut_ptr(), data.len());
+            device.unmap_buffer(&download).unwrap();
+            assert_eq!(contents, data);
+
+            encoder.reset_all(iter::once(cmd_buf));
+            device.destroy_command_encoder(encoder);
+            device.destroy_fence(fence);
+            device.destroy_buffer(imported);
+            device.destroy_buffer(exported);
+            device.destroy_buffer(download);
+            device.destroy_buffer(upload);
+            device.exit(queue);
+        }
+    }
+}
@@ -712,6 +712,8 @@ impl crate::Surface<super::Api> for super::Surface {
                 raw: sc.images[index as usize],
                 drop_guard: None,
                 block: None,
+                #[cfg(unix)]
+                external_memory: None,
                 usage: sc.config.usage,
                 aspects: crate::FormatAspects::COLOR,
                 format_info: sc.config.format.describe(),
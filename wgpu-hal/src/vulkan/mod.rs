@@ -29,6 +29,8 @@ mod adapter;
 mod command;
 mod conv;
 mod device;
+#[cfg(unix)]
+mod external_memory;
 mod instance;
 
 use std::{borrow::Borrow, ffi::CStr, num::NonZeroU32, sync::Arc};
@@ -45,6 +47,9 @@ const MAX_TOTAL_ATTACHMENTS: usize = crate::MAX_COLOR_TARGETS * 2 + 1;
 
 pub type DropGuard = Box<dyn std::any::Any + Send + Sync>;
 
+#[cfg(unix)]
+pub use external_memory::{ExternalMemoryError, ExternalMemoryType};
+
 #[derive(Clone)]
 pub struct Api;
 
@@ -72,6 +77,7 @@ impl crate::Api for Api {
     type ShaderModule = ShaderModule;
     type RenderPipeline = RenderPipeline;
     type ComputePipeline = ComputePipeline;
+    type PipelineCache = PipelineCache;
 }
 
 struct DebugUtils {
@@ -145,6 +151,8 @@ enum ExtensionFn<T> {
 struct DeviceExtensionFunctions {
     draw_indirect_count: Option<ExtensionFn<khr::DrawIndirectCount>>,
     timeline_semaphore: Option<ExtensionFn<khr::TimelineSemaphore>>,
+    #[cfg(unix)]
+    external_memory_fd: Option<khr::ExternalMemoryFd>,
 }
 
 /// Set of internal capabilities, which don't show up in the exposed
@@ -308,8 +316,19 @@ impl UpdateAfterBindTypes {
     }
 }
 
+/// Memory allocated by a device, and how much of it the resources use.
+#[derive(Debug)]
+struct MemoryStats {
+    heaps: Vec<wgt::MemoryHeapReport>,
+    /// Heap index of each memory type.
+    type_heaps: Vec<usize>,
+    /// Heap index and size of each memory block allocated through `gpu-alloc`.
+    blocks: fxhash::FxHashMap<vk::DeviceMemory, (usize, u64)>,
+}
+
 struct DeviceShared {
     raw: ash::Device,
+    physical_device: vk::PhysicalDevice,
     handle_is_owned: bool,
     instance: Arc<InstanceShared>,
     extension_fns: DeviceExtensionFunctions,
@@ -321,6 +340,12 @@ struct DeviceShared {
     workarounds: Workarounds,
     render_passes: Mutex<fxhash::FxHashMap<RenderPassKey, vk::RenderPass>>,
     framebuffers: Mutex<fxhash::FxHashMap<FramebufferKey, vk::Framebuffer>>,
+    /// `VK_EXT_memory_budget` is enabled, and the driver can report the budget of each heap.
+    memory_budget: bool,
+    memory: Mutex<MemoryStats>,
+    /// Handle types of the memory that can be shared with other APIs or processes.
+    #[cfg(unix)]
+    external_memory_types: vk::ExternalMemoryHandleTypeFlags,
 }
 
 pub struct Device {
@@ -330,6 +355,7 @@ pub struct Device {
         Mutex<gpu_descriptor::DescriptorAllocator<vk::DescriptorPool, vk::DescriptorSet>>,
     valid_ash_memory_types: u32,
     naga_options: naga::back::spv::Options,
+    cache_header: crate::auxil::pipeline_cache::Header,
     #[cfg(feature = "renderdoc")]
     render_doc: crate::auxil::renderdoc::RenderDoc,
 }
@@ -348,10 +374,24 @@ pub struct Queue {
     relay_index: Option<usize>,
 }
 
+/// Memory dedicated to a single resource, to share it with other APIs or processes.
+#[cfg(unix)]
+#[derive(Debug)]
+struct ExternalMemory {
+    raw: vk::DeviceMemory,
+    handle_type: vk::ExternalMemoryHandleTypeFlags,
+    memory_type: u32,
+    size: u64,
+}
+
 #[derive(Debug)]
 pub struct Buffer {
     raw: vk::Buffer,
-    block: Mutex<gpu_alloc::MemoryBlock<vk::DeviceMemory>>,
+    /// Memory allocated through `gpu-alloc`, `None` for buffers that weren't created by the
+    /// device or that use external memory.
+    block: Option<Mutex<gpu_alloc::MemoryBlock<vk::DeviceMemory>>>,
+    #[cfg(unix)]
+    external_memory: Option<ExternalMemory>,
 }
 
 #[derive(Debug)]
@@ -359,6 +399,8 @@ pub struct Texture {
     raw: vk::Image,
     drop_guard: Option<DropGuard>,
     block: Option<gpu_alloc::MemoryBlock<vk::DeviceMemory>>,
+    #[cfg(unix)]
+    external_memory: Option<ExternalMemory>,
     usage: crate::TextureUses,
     aspects: crate::FormatAspects,
     format_info: wgt::TextureFormatInfo,
@@ -474,6 +516,12 @@ pub struct ComputePipeline {
     raw: vk::Pipeline,
 }
 
+#[derive(Debug)]
+pub struct PipelineCache {
+    raw: vk::PipelineCache,
+    shaders: crate::auxil::pipeline_cache::ShaderCache<Vec<u32>>,
+}
+
 #[derive(Debug)]
 pub struct QuerySet {
     raw: vk::QueryPool,
@@ -144,6 +144,7 @@ fn main() {
                         wgpu::Backend::Dx11 => "dx11",
                         wgpu::Backend::Gl => "gl",
                         wgpu::Backend::BrowserWebGpu => "webgpu",
+                        wgpu::Backend::Cpu => "cpu",
                     },
                 )
                 .spawn()
@@ -0,0 +1,599 @@
+use crate::{
+    protocol::{
+        read_message, write_message, AdapterCapabilities, ClientMessage, DeviceInfo, RemoteId,
+        ResourceKind, ServerMessage,
+    },
+    shm::{recv_fd, SharedMemory},
+    Error,
+};
+use wgc::{
+    command::{
+        ComputePass, ComputePassDescriptor, CreateRenderBundleError, RenderBundleEncoder,
+        RenderBundleEncoderDescriptor, RenderPass, RenderPassDescriptor,
+    },
+    device::{
+        trace::{Action, Command},
+        HostMap,
+    },
+    hub::IdentityManager,
+    id,
+};
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{BufWriter, Write as _},
+    ops::Range,
+    os::unix::net::UnixStream,
+};
+
+/// ID of any kind, used to return the freed IDs to their managers.
+#[derive(Clone, Copy, Debug)]
+struct RawId(u32, u32, wgt::Backend);
+
+impl id::TypedId for RawId {
+    fn zip(index: u32, epoch: u32, backend: wgt::Backend) -> Self {
+        Self(index, epoch, backend)
+    }
+    fn unzip(self) -> (u32, u32, wgt::Backend) {
+        (self.0, self.1, self.2)
+    }
+}
+
+/// Commands recorded on the client, and sent with [`Client::submit`].
+#[derive(Debug, Default)]
+pub struct CommandEncoder {
+    commands: Vec<Command>,
+}
+
+impl CommandEncoder {
+    pub fn copy_buffer_to_buffer(
+        &mut self,
+        src: id::BufferId,
+        src_offset: wgt::BufferAddress,
+        dst: id::BufferId,
+        dst_offset: wgt::BufferAddress,
+        size: wgt::BufferAddress,
+    ) {
+        self.commands.push(Command::CopyBufferToBuffer {
+            src,
+            src_offset,
+            dst,
+            dst_offset,
+            size,
+        });
+    }
+
+    pub fn copy_buffer_to_texture(
+        &mut self,
+        src: &wgc::command::ImageCopyBuffer,
+        dst: &wgc::command::ImageCopyTexture,
+        size: &wgt::Extent3d,
+    ) {
+        self.commands.push(Command::CopyBufferToTexture {
+            src: src.clone(),
+            dst: dst.clone(),
+            size: *size,
+        });
+    }
+
+    pub fn copy_texture_to_buffer(
+        &mut self,
+        src: &wgc::command::ImageCopyTexture,
+        dst: &wgc::command::ImageCopyBuffer,
+        size: &wgt::Extent3d,
+    ) {
+        self.commands.push(Command::CopyTextureToBuffer {
+            src: src.clone(),
+            dst: dst.clone(),
+            size: *size,
+        });
+    }
+
+    pub fn copy_texture_to_texture(
+        &mut self,
+        src: &wgc::command::ImageCopyTexture,
+        dst: &wgc::command::ImageCopyTexture,
+        size: &wgt::Extent3d,
+    ) {
+        self.commands.push(Command::CopyTextureToTexture {
+            src: src.clone(),
+            dst: dst.clone(),
+            size: *size,
+        });
+    }
+
+    pub fn clear_buffer(
+        &mut self,
+        dst: id::BufferId,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        self.commands
+            .push(Command::ClearBuffer { dst, offset, size });
+    }
+
+    pub fn clear_texture(
+        &mut self,
+        dst: id::TextureId,
+        subresource_range: &wgt::ImageSubresourceRange,
+    ) {
+        self.commands.push(Command::ClearTexture {
+            dst,
+            subresource_range: subresource_range.clone(),
+        });
+    }
+
+    pub fn write_timestamp(&mut self, query_set_id: id::QuerySetId, query_index: u32) {
+        self.commands.push(Command::WriteTimestamp {
+            query_set_id,
+            query_index,
+        });
+    }
+
+    pub fn resolve_query_set(
+        &mut self,
+        query_set_id: id::QuerySetId,
+        queries: Range<u32>,
+        destination: id::BufferId,
+        destination_offset: wgt::BufferAddress,
+    ) {
+        self.commands.push(Command::ResolveQuerySet {
+            query_set_id,
+            start_query: queries.start,
+            query_count: queries.end - queries.start,
+            destination,
+            destination_offset,
+        });
+    }
+
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.commands
+            .push(Command::PushDebugGroup(label.to_string()));
+    }
+
+    pub fn pop_debug_group(&mut self) {
+        self.commands.push(Command::PopDebugGroup);
+    }
+
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.commands
+            .push(Command::InsertDebugMarker(label.to_string()));
+    }
+
+    /// Start recording a compute pass, with the functions of `wgc::command::compute_ffi`.
+    pub fn begin_compute_pass(&self, desc: &ComputePassDescriptor) -> ComputePass {
+        // The parent is not sent to the server, which creates its own encoder.
+        ComputePass::new(id::TypedId::zip(0, 1, wgt::Backend::Empty), desc)
+    }
+
+    pub fn end_compute_pass(&mut self, pass: ComputePass) {
+        self.commands.push(pass.into_command());
+    }
+
+    /// Start recording a render pass, with the functions of `wgc::command::render_ffi`.
+    pub fn begin_render_pass(&self, desc: &RenderPassDescriptor) -> RenderPass {
+        RenderPass::new(id::TypedId::zip(0, 1, wgt::Backend::Empty), desc)
+    }
+
+    pub fn end_render_pass(&mut self, pass: RenderPass) {
+        self.commands.push(pass.into_command());
+    }
+}
+
+/// Buffer range mapped into the memory of the client.
+///
+/// The contents written into a buffer mapped for writing reach the buffer
+/// when it's passed to [`Client::unmap_buffer`].
+#[derive(Debug)]
+pub struct BufferMapping {
+    buffer: id::BufferId,
+    memory: SharedMemory,
+}
+
+impl BufferMapping {
+    pub fn buffer(&self) -> id::BufferId {
+        self.buffer
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self.memory.as_slice()
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.memory.as_mut_slice()
+    }
+}
+
+/// Client side of a connection, on the untrusted process.
+///
+/// Everything except mapping and [`Client::sync`] is sent without waiting for the server.
+/// Messages are buffered until a submission, a round trip, or [`Client::flush`].
+#[derive(Debug)]
+pub struct Client {
+    stream: UnixStream,
+    writer: BufWriter<UnixStream>,
+    device: DeviceInfo,
+    identities: HashMap<ResourceKind, IdentityManager>,
+    submission_count: u64,
+}
+
+impl Client {
+    /// Ask the server on the other end of the socket about the adapter it picks.
+    ///
+    /// This is optional, and has to happen before [`Client::connect`] on the same socket.
+    pub fn request_adapter(stream: &mut UnixStream) -> Result<AdapterCapabilities, Error> {
+        write_message(stream, &ClientMessage::RequestAdapter)?;
+        match read_message(stream)?.ok_or(Error::Disconnected)? {
+            ServerMessage::Adapter(Ok(adapter)) => Ok(adapter),
+            ServerMessage::Adapter(Err(error)) => Err(Error::Refused(error)),
+            _ => Err(Error::UnexpectedMessage),
+        }
+    }
+
+    /// Connect to the server on the other end of the socket, and request the device.
+    pub fn connect(
+        mut stream: UnixStream,
+        desc: &wgc::device::DeviceDescriptor,
+    ) -> Result<Self, Error> {
+        write_message(&mut stream, &ClientMessage::RequestDevice(desc.clone()))?;
+        let device = match read_message(&mut stream)?.ok_or(Error::Disconnected)? {
+            ServerMessage::Device(Ok(device)) => device,
+            ServerMessage::Device(Err(error)) => return Err(Error::Refused(error)),
+            _ => return Err(Error::UnexpectedMessage),
+        };
+        Ok(Self {
+            writer: BufWriter::new(stream.try_clone()?),
+            stream,
+            device,
+            identities: HashMap::new(),
+            submission_count: 0,
+        })
+    }
+
+    /// Get the device of the connection, which is also its queue.
+    pub fn device(&self) -> &DeviceInfo {
+        &self.device
+    }
+
+    fn alloc<I: RemoteId>(&mut self) -> I {
+        let backend = self.device.id.backend();
+        self.identities.entry(I::KIND).or_default().alloc(backend)
+    }
+
+    fn send(&mut self, message: &ClientMessage) -> Result<(), Error> {
+        write_message(&mut self.writer, message)
+    }
+
+    fn action(&mut self, action: Action) -> Result<(), Error> {
+        self.send(&ClientMessage::Action(action))
+    }
+
+    /// Send the data for the next action, returning its name.
+    fn blob(&mut self, name: &str, data: &[u8]) -> Result<String, Error> {
+        self.send(&ClientMessage::Blob {
+            name: name.to_string(),
+            data: Cow::Borrowed(data),
+        })?;
+        Ok(name.to_string())
+    }
+
+    fn receive(&mut self) -> Result<ServerMessage, Error> {
+        read_message(&mut self.stream)?.ok_or(Error::Disconnected)
+    }
+
+    /// Send the buffered messages to the server.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Wait for the server to process everything sent so far.
+    ///
+    /// Returns the validation errors since the last sync, and lets the client
+    /// reuse the IDs of the resources that the server has released.
+    pub fn sync(&mut self) -> Result<Vec<String>, Error> {
+        self.sync_impl(false)
+    }
+
+    /// Same as [`Client::sync`], but also waits for the submitted work to be done.
+    pub fn wait(&mut self) -> Result<Vec<String>, Error> {
+        self.sync_impl(true)
+    }
+
+    fn sync_impl(&mut self, wait: bool) -> Result<Vec<String>, Error> {
+        self.send(&ClientMessage::Sync { wait })?;
+        self.flush()?;
+        match self.receive()? {
+            ServerMessage::Sync { errors, freed } => {
+                let backend = self.device.id.backend();
+                for id in freed {
+                    if let Some(identities) = self.identities.get_mut(&id.kind) {
+                        identities.free(RawId(id.index, id.epoch, backend));
+                    }
+                }
+                Ok(errors)
+            }
+            _ => Err(Error::UnexpectedMessage),
+        }
+    }
+
+    pub fn create_buffer(
+        &mut self,
+        desc: &wgc::resource::BufferDescriptor,
+    ) -> Result<id::BufferId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateBuffer(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    /// Destroy the buffer contents, keeping the ID valid until it's dropped.
+    pub fn destroy_buffer(&mut self, id: id::BufferId) -> Result<(), Error> {
+        self.action(Action::FreeBuffer(id))
+    }
+
+    pub fn drop_buffer(&mut self, id: id::BufferId) -> Result<(), Error> {
+        self.action(Action::DestroyBuffer(id))
+    }
+
+    pub fn create_texture(
+        &mut self,
+        desc: &wgc::resource::TextureDescriptor,
+    ) -> Result<id::TextureId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateTexture(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    /// Destroy the texture contents, keeping the ID valid until it's dropped.
+    pub fn destroy_texture(&mut self, id: id::TextureId) -> Result<(), Error> {
+        self.action(Action::FreeTexture(id))
+    }
+
+    pub fn drop_texture(&mut self, id: id::TextureId) -> Result<(), Error> {
+        self.action(Action::DestroyTexture(id))
+    }
+
+    pub fn create_texture_view(
+        &mut self,
+        texture: id::TextureId,
+        desc: &wgc::resource::TextureViewDescriptor,
+    ) -> Result<id::TextureViewId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateTextureView {
+            id,
+            parent_id: texture,
+            desc: desc.clone(),
+        })?;
+        Ok(id)
+    }
+
+    pub fn drop_texture_view(&mut self, id: id::TextureViewId) -> Result<(), Error> {
+        self.action(Action::DestroyTextureView(id))
+    }
+
+    pub fn create_sampler(
+        &mut self,
+        desc: &wgc::resource::SamplerDescriptor,
+    ) -> Result<id::SamplerId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateSampler(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    pub fn drop_sampler(&mut self, id: id::SamplerId) -> Result<(), Error> {
+        self.action(Action::DestroySampler(id))
+    }
+
+    pub fn create_bind_group_layout(
+        &mut self,
+        desc: &wgc::binding_model::BindGroupLayoutDescriptor,
+    ) -> Result<id::BindGroupLayoutId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateBindGroupLayout(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    pub fn drop_bind_group_layout(&mut self, id: id::BindGroupLayoutId) -> Result<(), Error> {
+        self.action(Action::DestroyBindGroupLayout(id))
+    }
+
+    pub fn create_pipeline_layout(
+        &mut self,
+        desc: &wgc::binding_model::PipelineLayoutDescriptor,
+    ) -> Result<id::PipelineLayoutId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreatePipelineLayout(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    pub fn drop_pipeline_layout(&mut self, id: id::PipelineLayoutId) -> Result<(), Error> {
+        self.action(Action::DestroyPipelineLayout(id))
+    }
+
+    pub fn create_bind_group(
+        &mut self,
+        desc: &wgc::binding_model::BindGroupDescriptor,
+    ) -> Result<id::BindGroupId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateBindGroup(id, desc.clone()))?;
+        Ok(id)
+    }
+
+    pub fn drop_bind_group(&mut self, id: id::BindGroupId) -> Result<(), Error> {
+        self.action(Action::DestroyBindGroup(id))
+    }
+
+    /// Create a shader module from WGSL source.
+    pub fn create_shader_module(
+        &mut self,
+        desc: &wgc::pipeline::ShaderModuleDescriptor,
+        wgsl: &str,
+    ) -> Result<id::ShaderModuleId, Error> {
+        let id = self.alloc();
+        let data = self.blob("shader.wgsl", wgsl.as_bytes())?;
+        self.action(Action::CreateShaderModule {
+            id,
+            desc: desc.clone(),
+            data,
+        })?;
+        Ok(id)
+    }
+
+    pub fn drop_shader_module(&mut self, id: id::ShaderModuleId) -> Result<(), Error> {
+        self.action(Action::DestroyShaderModule(id))
+    }
+
+    /// Create a compute pipeline. The layout has to be explicit.
+    pub fn create_compute_pipeline(
+        &mut self,
+        desc: &wgc::pipeline::ComputePipelineDescriptor,
+    ) -> Result<id::ComputePipelineId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateComputePipeline {
+            id,
+            desc: desc.clone(),
+            implicit_context: None,
+        })?;
+        Ok(id)
+    }
+
+    pub fn drop_compute_pipeline(&mut self, id: id::ComputePipelineId) -> Result<(), Error> {
+        self.action(Action::DestroyComputePipeline(id))
+    }
+
+    /// Create a render pipeline. The layout has to be explicit.
+    pub fn create_render_pipeline(
+        &mut self,
+        desc: &wgc::pipeline::RenderPipelineDescriptor,
+    ) -> Result<id::RenderPipelineId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateRenderPipeline {
+            id,
+            desc: desc.clone(),
+            implicit_context: None,
+        })?;
+        Ok(id)
+    }
+
+    pub fn drop_render_pipeline(&mut self, id: id::RenderPipelineId) -> Result<(), Error> {
+        self.action(Action::DestroyRenderPipeline(id))
+    }
+
+    /// Start recording a render bundle, with the functions of `wgc::command::bundle_ffi`.
+    pub fn create_render_bundle_encoder(
+        &self,
+        desc: &RenderBundleEncoderDescriptor,
+    ) -> Result<RenderBundleEncoder, CreateRenderBundleError> {
+        RenderBundleEncoder::new(desc, self.device.id, None)
+    }
+
+    pub fn create_render_bundle(
+        &mut self,
+        encoder: RenderBundleEncoder,
+        desc: &wgt::RenderBundleDescriptor<wgc::Label>,
+    ) -> Result<id::RenderBundleId, Error> {
+        let id = self.alloc();
+        self.action(encoder.into_action(id, desc.label.clone()))?;
+        Ok(id)
+    }
+
+    pub fn drop_render_bundle(&mut self, id: id::RenderBundleId) -> Result<(), Error> {
+        self.action(Action::DestroyRenderBundle(id))
+    }
+
+    pub fn create_query_set(
+        &mut self,
+        desc: &wgc::resource::QuerySetDescriptor,
+    ) -> Result<id::QuerySetId, Error> {
+        let id = self.alloc();
+        self.action(Action::CreateQuerySet {
+            id,
+            desc: desc.clone(),
+        })?;
+        Ok(id)
+    }
+
+    pub fn drop_query_set(&mut self, id: id::QuerySetId) -> Result<(), Error> {
+        self.action(Action::DestroyQuerySet(id))
+    }
+
+    pub fn write_buffer(
+        &mut self,
+        buffer: id::BufferId,
+        offset: wgt::BufferAddress,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let name = self.blob("data.bin", data)?;
+        self.action(Action::WriteBuffer {
+            id: buffer,
+            data: name,
+            range: offset..offset + data.len() as wgt::BufferAddress,
+            queued: true,
+        })
+    }
+
+    pub fn write_texture(
+        &mut self,
+        texture: &wgc::command::ImageCopyTexture,
+        data: &[u8],
+        layout: &wgt::ImageDataLayout,
+        size: &wgt::Extent3d,
+    ) -> Result<(), Error> {
+        let name = self.blob("data.bin", data)?;
+        self.action(Action::WriteTexture {
+            to: texture.clone(),
+            data: name,
+            layout: *layout,
+            size: *size,
+        })
+    }
+
+    /// Submit the commands recorded by the encoders to the queue, as a single command buffer.
+    pub fn submit<I: IntoIterator<Item = CommandEncoder>>(
+        &mut self,
+        encoders: I,
+    ) -> Result<(), Error> {
+        let commands = encoders
+            .into_iter()
+            .flat_map(|encoder| encoder.commands)
+            .collect();
+        self.submission_count += 1;
+        self.action(Action::Submit(self.submission_count, commands))?;
+        self.flush()
+    }
+
+    /// Map a range of a buffer, waiting for the server to finish using it.
+    ///
+    /// Buffers created with `mapped_at_creation` are mapped for writing with this
+    /// as well, before their first use.
+    pub fn map_buffer(
+        &mut self,
+        buffer: id::BufferId,
+        host: HostMap,
+        range: Range<wgt::BufferAddress>,
+    ) -> Result<BufferMapping, Error> {
+        let size = range.end.saturating_sub(range.start) as usize;
+        self.send(&ClientMessage::MapBuffer {
+            buffer,
+            host,
+            range,
+        })?;
+        self.flush()?;
+        match self.receive()? {
+            ServerMessage::BufferMapped(Ok(())) => {
+                let fd = recv_fd(&self.stream)?;
+                Ok(BufferMapping {
+                    buffer,
+                    memory: SharedMemory::from_fd(fd, size)?,
+                })
+            }
+            ServerMessage::BufferMapped(Err(error)) => Err(Error::Refused(error)),
+            _ => Err(Error::UnexpectedMessage),
+        }
+    }
+
+    pub fn unmap_buffer(&mut self, mapping: BufferMapping) -> Result<(), Error> {
+        self.send(&ClientMessage::UnmapBuffer(mapping.buffer))
+    }
+}
@@ -0,0 +1,65 @@
+/*! Client and server for running WebGPU in a separate process.
+ *
+ * The GPU process owns a `wgpu-core` [`Global`](wgc::hub::Global) per connection
+ * and runs a [`Server`] on its end of a Unix socket. An untrusted process
+ * records the same actions and commands that `wgpu-core` writes into traces,
+ * and sends them over the socket with a [`Client`]. With the `remote` feature, `wgpu`
+ * records them through its own API instead, with `wgpu::Instance::connect`.
+ *
+ * # Notes
+ * - IDs are allocated by the client, so creating resources doesn't need a round trip.
+ *   The server validates them, and reports the released ones back on [`Client::sync`],
+ *   which is the only point where the client recycles IDs.
+ * - Validation errors are collected on the server and returned by [`Client::sync`],
+ *   up to [`MAX_ERRORS`] of them.
+ * - Mapped buffers are shared through memory created by the server and passed
+ *   over the socket, so mapping requires a Unix socket rather than a pipe.
+ * - Shaders are only accepted as WGSL, so everything goes through the naga front-end
+ *   and validator before reaching the backend.
+ * - Misbehaving clients get disconnected, with the server returning [`Error::InvalidAction`].
+ *   This includes IDs nested in descriptors and commands that are not alive, and pass
+ *   commands reaching outside of the data of the pass, since `wgpu-core` panics on those.
+ * - Any remaining panic in `wgpu-core` is caught, and [`Error::Panic`] is returned.
+ *   The server must therefore not be built with `panic = "abort"`. Serve each connection
+ *   on its own thread, so that one client can't take down the others.
+ */
+
+#![cfg(unix)]
+
+mod client;
+mod protocol;
+mod server;
+mod shm;
+mod validate;
+
+pub use client::{BufferMapping, Client, CommandEncoder};
+pub use protocol::{
+    AdapterCapabilities, DeviceInfo, FreedId, RemoteId, ResourceKind, MAX_ERRORS, MAX_MESSAGE_SIZE,
+    MAX_RESOURCES,
+};
+pub use server::Server;
+pub use wgc::device::HostMap;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("failed to encode or decode a message")]
+    Codec(#[from] bincode::Error),
+    #[error("message of {0} bytes is larger than the limit")]
+    MessageTooLarge(usize),
+    #[error("unexpected message")]
+    UnexpectedMessage,
+    #[error("the connection was closed")]
+    Disconnected,
+    #[error("no device was requested on this connection")]
+    NoDevice,
+    #[error("client sent an invalid action: {0}")]
+    InvalidAction(String),
+    #[error("the server refused the request: {0}")]
+    Refused(String),
+    #[error("client caused a panic on the server: {0}")]
+    Panic(String),
+}
@@ -0,0 +1,184 @@
+use crate::Error;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wgc::{
+    device::{trace, HostMap},
+    id,
+};
+
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    ops::Range,
+};
+
+/// Largest message, in bytes, that is accepted from the other side.
+pub const MAX_MESSAGE_SIZE: usize = 256 << 20;
+
+/// Maximum number of live resources of each kind that a client can have.
+pub const MAX_RESOURCES: u32 = 1 << 16;
+
+/// Maximum number of validation errors that are kept between two syncs.
+///
+/// The errors past this limit are only counted.
+pub const MAX_ERRORS: usize = 256;
+
+/// Kind of the resource behind an ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ResourceKind {
+    Adapter,
+    Surface,
+    Device,
+    Buffer,
+    StagingBuffer,
+    Texture,
+    TextureView,
+    Sampler,
+    BindGroupLayout,
+    PipelineLayout,
+    BindGroup,
+    ShaderModule,
+    RenderPipeline,
+    ComputePipeline,
+    PipelineCache,
+    CommandBuffer,
+    RenderBundle,
+    QuerySet,
+}
+
+/// ID of a resource that can be sent over the connection.
+pub trait RemoteId: id::TypedId + Copy + std::fmt::Debug + 'static {
+    const KIND: ResourceKind;
+}
+
+macro_rules! impl_remote_id {
+    ($($id:ident => $kind:ident,)*) => {
+        $(
+            impl RemoteId for id::$id {
+                const KIND: ResourceKind = ResourceKind::$kind;
+            }
+        )*
+    };
+}
+
+impl_remote_id! {
+    AdapterId => Adapter,
+    SurfaceId => Surface,
+    DeviceId => Device,
+    BufferId => Buffer,
+    StagingBufferId => StagingBuffer,
+    TextureId => Texture,
+    TextureViewId => TextureView,
+    SamplerId => Sampler,
+    BindGroupLayoutId => BindGroupLayout,
+    PipelineLayoutId => PipelineLayout,
+    BindGroupId => BindGroup,
+    ShaderModuleId => ShaderModule,
+    RenderPipelineId => RenderPipeline,
+    ComputePipelineId => ComputePipeline,
+    PipelineCacheId => PipelineCache,
+    CommandBufferId => CommandBuffer,
+    RenderBundleId => RenderBundle,
+    QuerySetId => QuerySet,
+}
+
+/// Client ID that the server has released, and that can be allocated again.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FreedId {
+    pub kind: ResourceKind,
+    pub index: u32,
+    pub epoch: u32,
+}
+
+/// Adapter picked by the server for the client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AdapterCapabilities {
+    pub info: wgt::AdapterInfo,
+    pub features: wgt::Features,
+    pub limits: wgt::Limits,
+    pub downlevel: wgt::DownlevelCapabilities,
+}
+
+/// Device created by the server for the client.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub id: id::DeviceId,
+    pub adapter: wgt::AdapterInfo,
+    pub features: wgt::Features,
+    pub limits: wgt::Limits,
+    pub downlevel: wgt::DownlevelCapabilities,
+    pub timestamp_period: f32,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum ClientMessage<'a> {
+    /// Pick the adapter of the connection, answered with `ServerMessage::Adapter`.
+    RequestAdapter,
+    /// Create the device of the connection, picking the adapter if it's not picked yet.
+    /// Has to come before any other message except `RequestAdapter`.
+    RequestDevice(wgc::device::DeviceDescriptor<'a>),
+    /// Data of the next action, which refers to it by name. Only one can be pending.
+    Blob {
+        name: String,
+        data: Cow<'a, [u8]>,
+    },
+    Action(trace::Action<'a>),
+    /// Map a buffer, answered with `ServerMessage::BufferMapped`.
+    MapBuffer {
+        buffer: id::BufferId,
+        host: HostMap,
+        range: Range<wgt::BufferAddress>,
+    },
+    UnmapBuffer(id::BufferId),
+    /// Answered with `ServerMessage::Sync`, once the submitted work is done if `wait` is set.
+    Sync {
+        wait: bool,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum ServerMessage {
+    Adapter(Result<AdapterCapabilities, String>),
+    Device(Result<DeviceInfo, String>),
+    /// On success, the descriptor of the shared memory follows the message.
+    BufferMapped(Result<(), String>),
+    Sync {
+        errors: Vec<String>,
+        freed: Vec<FreedId>,
+    },
+}
+
+/// Write a message prefixed with its size.
+pub(crate) fn write_message<W: Write, T: Serialize>(
+    writer: &mut W,
+    message: &T,
+) -> Result<(), Error> {
+    let data = bincode::serialize(message)?;
+    if data.len() > MAX_MESSAGE_SIZE {
+        return Err(Error::MessageTooLarge(data.len()));
+    }
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+/// Read a message written by `write_message`.
+///
+/// Returns `None` if the other side closed the connection between messages.
+pub(crate) fn read_message<R: Read, T: DeserializeOwned>(
+    reader: &mut R,
+) -> Result<Option<T>, Error> {
+    let mut size = [0; 4];
+    match reader.read_exact(&mut size) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let size = u32::from_le_bytes(size) as usize;
+    if size > MAX_MESSAGE_SIZE {
+        return Err(Error::MessageTooLarge(size));
+    }
+    let mut data = vec![0; size];
+    reader.read_exact(&mut data)?;
+    Ok(Some(bincode::deserialize(&data)?))
+}
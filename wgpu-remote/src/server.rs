@@ -0,0 +1,894 @@
+use crate::{
+    protocol::{
+        read_message, write_message, AdapterCapabilities, ClientMessage, DeviceInfo, FreedId,
+        RemoteId, ResourceKind, ServerMessage, MAX_ERRORS, MAX_RESOURCES,
+    },
+    shm::{send_fd, SharedMemory},
+    validate, Error,
+};
+use parking_lot::Mutex;
+use wgc::{
+    device::{
+        trace::{Action, Command},
+        HostMap,
+    },
+    gfx_select,
+    hub::{
+        Global, GlobalIdentityHandlerFactory, HalApi, IdentityHandler, IdentityHandlerFactory,
+        IdentityManager,
+    },
+    id,
+    resource::{BufferMapAsyncStatus, BufferMapOperation},
+};
+
+use std::{
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    io::BufReader,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    os::unix::{io::RawFd, net::UnixStream},
+    panic,
+    sync::Arc,
+};
+
+#[derive(Debug)]
+struct LiveId {
+    epoch: u32,
+    dropped: bool,
+}
+
+/// Client IDs known to the server.
+#[derive(Debug, Default)]
+pub(crate) struct IdRegistry {
+    live: HashMap<(ResourceKind, u32), LiveId>,
+    /// Client IDs released by `wgpu-core` since the last sync.
+    freed: Vec<FreedId>,
+    /// Command buffers are only ever created by the server.
+    command_buffers: IdentityManager,
+}
+
+impl IdRegistry {
+    fn claim<I: RemoteId>(&mut self, id: I) -> Result<(), String> {
+        let (index, epoch, _) = id.unzip();
+        if index >= MAX_RESOURCES {
+            return Err(format!("{:?} is beyond the resource limit", id));
+        }
+        match self.live.entry((I::KIND, index)) {
+            Entry::Occupied(_) => Err(format!("{:?} is still in use", id)),
+            Entry::Vacant(entry) => {
+                entry.insert(LiveId {
+                    epoch,
+                    dropped: false,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn check<I: RemoteId>(&self, id: I) -> Result<(), String> {
+        let (index, epoch, _) = id.unzip();
+        match self.live.get(&(I::KIND, index)) {
+            Some(live) if live.epoch == epoch && !live.dropped => Ok(()),
+            _ => Err(format!("{:?} is not alive", id)),
+        }
+    }
+
+    fn release<I: RemoteId>(&mut self, id: I) -> Result<(), String> {
+        self.check(id)?;
+        let (index, _, _) = id.unzip();
+        self.live.get_mut(&(I::KIND, index)).unwrap().dropped = true;
+        Ok(())
+    }
+
+    fn free<I: RemoteId>(&mut self, id: I) {
+        let (index, epoch, _) = id.unzip();
+        if I::KIND == ResourceKind::CommandBuffer {
+            self.command_buffers.free(id);
+            return;
+        }
+        match self.live.entry((I::KIND, index)) {
+            Entry::Occupied(entry) if entry.get().epoch == epoch => {
+                entry.remove();
+                self.freed.push(FreedId {
+                    kind: I::KIND,
+                    index,
+                    epoch,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Accepts the IDs chosen by the client, and records the ones that get freed.
+#[derive(Debug)]
+struct IdentityRecycler<I> {
+    registry: Arc<Mutex<IdRegistry>>,
+    _phantom: PhantomData<I>,
+}
+
+impl<I: RemoteId> IdentityHandler<I> for IdentityRecycler<I> {
+    type Input = I;
+    fn process(&self, id: I, backend: wgt::Backend) -> I {
+        let (index, epoch, _backend) = id.unzip();
+        I::zip(index, epoch, backend)
+    }
+    fn free(&self, id: I) {
+        self.registry.lock().free(id);
+    }
+}
+
+struct IdentityRecyclerFactory {
+    registry: Arc<Mutex<IdRegistry>>,
+}
+
+impl<I: RemoteId> IdentityHandlerFactory<I> for IdentityRecyclerFactory {
+    type Filter = IdentityRecycler<I>;
+    fn spawn(&self, _min_index: u32) -> Self::Filter {
+        IdentityRecycler {
+            registry: Arc::clone(&self.registry),
+            _phantom: PhantomData,
+        }
+    }
+}
+impl GlobalIdentityHandlerFactory for IdentityRecyclerFactory {}
+
+struct MappedBuffer {
+    memory: SharedMemory,
+    offset: wgt::BufferAddress,
+    host: HostMap,
+}
+
+extern "C" fn map_callback(status: BufferMapAsyncStatus, user_data: *mut u8) {
+    unsafe { *(user_data as *mut Option<BufferMapAsyncStatus>) = Some(status) };
+}
+
+/// Format an error together with its sources.
+fn format_error(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
+}
+
+/// Server side of a connection, owning the `Global` that the client uses.
+pub struct Server {
+    global: Global<IdentityRecyclerFactory>,
+    registry: Arc<Mutex<IdRegistry>>,
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    backends: wgt::Backends,
+    power_preference: wgt::PowerPreference,
+    adapter: Option<id::AdapterId>,
+    device: Option<id::DeviceId>,
+    errors: Vec<String>,
+    /// Number of errors that didn't fit in `errors`.
+    dropped_errors: usize,
+    blob: Option<(String, Vec<u8>)>,
+    mapped: HashMap<id::BufferId, MappedBuffer>,
+    mapped_at_creation: HashSet<id::BufferId>,
+}
+
+impl Server {
+    /// Create a server on its end of the socket.
+    ///
+    /// The client picks the device features and limits, but the adapter is picked
+    /// by the server, among the given `backends`.
+    pub fn new(
+        stream: UnixStream,
+        backends: wgt::Backends,
+        power_preference: wgt::PowerPreference,
+    ) -> Result<Self, Error> {
+        let registry = Arc::new(Mutex::new(IdRegistry::default()));
+        let factory = IdentityRecyclerFactory {
+            registry: Arc::clone(&registry),
+        };
+        Ok(Self {
+            global: Global::new("wgpu-remote", factory, backends),
+            registry,
+            reader: BufReader::new(stream.try_clone()?),
+            stream,
+            backends,
+            power_preference,
+            adapter: None,
+            device: None,
+            errors: Vec::new(),
+            dropped_errors: 0,
+            blob: None,
+            mapped: HashMap::new(),
+            mapped_at_creation: HashSet::new(),
+        })
+    }
+
+    /// Serve the client until it disconnects.
+    ///
+    /// Returns an error if the client misbehaves or the connection breaks,
+    /// and the connection should be closed.
+    pub fn run(mut self) -> Result<(), Error> {
+        while let Some(message) = read_message(&mut self.reader)? {
+            match panic::catch_unwind(panic::AssertUnwindSafe(|| self.handle(message))) {
+                Ok(result) => result?,
+                Err(payload) => {
+                    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                        message.to_string()
+                    } else if let Some(message) = payload.downcast_ref::<String>() {
+                        message.clone()
+                    } else {
+                        "unknown".to_string()
+                    };
+                    return Err(Error::Panic(message));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: ClientMessage<'static>) -> Result<(), Error> {
+        match message {
+            ClientMessage::RequestAdapter => {
+                let reply = self.request_adapter();
+                write_message(&mut self.stream, &ServerMessage::Adapter(reply))?;
+            }
+            ClientMessage::RequestDevice(desc) => {
+                let reply = self.request_device(&desc);
+                write_message(&mut self.stream, &ServerMessage::Device(reply))?;
+            }
+            ClientMessage::Blob { name, data } => {
+                // Every action takes at most one blob, so there is no need to hold more.
+                if self.blob.is_some() {
+                    return Err(Error::InvalidAction(format!(
+                        "Data {} is sent before the pending data is used",
+                        name
+                    )));
+                }
+                self.blob = Some((name, data.into_owned()));
+            }
+            ClientMessage::Action(action) => {
+                let device = self.device.ok_or(Error::NoDevice)?;
+                validate::check_action(&self.registry.lock(), &action)
+                    .map_err(Error::InvalidAction)?;
+                gfx_select!(device => self.process(device, action))?;
+                // Data is only valid for the action right after it.
+                self.blob = None;
+            }
+            ClientMessage::MapBuffer {
+                buffer,
+                host,
+                range,
+            } => {
+                let device = self.device.ok_or(Error::NoDevice)?;
+                match gfx_select!(device => self.map_buffer(device, buffer, host, range))? {
+                    Ok(fd) => {
+                        write_message(&mut self.stream, &ServerMessage::BufferMapped(Ok(())))?;
+                        send_fd(&self.stream, fd)?;
+                    }
+                    Err(error) => {
+                        write_message(&mut self.stream, &ServerMessage::BufferMapped(Err(error)))?;
+                    }
+                }
+            }
+            ClientMessage::UnmapBuffer(buffer) => {
+                let device = self.device.ok_or(Error::NoDevice)?;
+                gfx_select!(device => self.unmap_buffer(buffer))?;
+            }
+            ClientMessage::Sync { wait } => {
+                let device = self.device.ok_or(Error::NoDevice)?;
+                let global = &self.global;
+                // Polling lets `wgpu-core` free the resources that are no longer used.
+                let maintain = if wait {
+                    wgt::Maintain::Wait
+                } else {
+                    wgt::Maintain::Poll
+                };
+                let result = gfx_select!(device => global.device_poll(device, maintain));
+                self.report(result.err());
+                let mut errors = mem::take(&mut self.errors);
+                if self.dropped_errors != 0 {
+                    errors.push(format!("{} more errors were dropped", self.dropped_errors));
+                    self.dropped_errors = 0;
+                }
+                let freed = mem::take(&mut self.registry.lock().freed);
+                let reply = ServerMessage::Sync { errors, freed };
+                write_message(&mut self.stream, &reply)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn request_adapter(&mut self) -> Result<AdapterCapabilities, String> {
+        let adapter = match self.adapter {
+            Some(adapter) => adapter,
+            None => {
+                let adapter = self
+                    .global
+                    .request_adapter(
+                        &wgc::instance::RequestAdapterOptions {
+                            power_preference: self.power_preference,
+                            force_fallback_adapter: false,
+                            compatible_surface: None,
+                        },
+                        wgc::instance::AdapterInputs::Mask(self.backends, |backend| {
+                            id::TypedId::zip(0, 0, backend)
+                        }),
+                    )
+                    .map_err(|e| format_error(&e))?;
+                self.adapter = Some(adapter);
+                adapter
+            }
+        };
+        let global = &self.global;
+        Ok(AdapterCapabilities {
+            info: gfx_select!(adapter => global.adapter_get_info(adapter))
+                .map_err(|e| format_error(&e))?,
+            features: gfx_select!(adapter => global.adapter_features(adapter))
+                .map_err(|e| format_error(&e))?,
+            limits: gfx_select!(adapter => global.adapter_limits(adapter))
+                .map_err(|e| format_error(&e))?,
+            downlevel: gfx_select!(adapter => global.adapter_downlevel_properties(adapter))
+                .map_err(|e| format_error(&e))?,
+        })
+    }
+
+    fn request_device(
+        &mut self,
+        desc: &wgc::device::DeviceDescriptor,
+    ) -> Result<DeviceInfo, String> {
+        if self.device.is_some() {
+            return Err("The device is already requested".to_string());
+        }
+        let adapter = self.request_adapter()?.info;
+        let adapter_id = self.adapter.unwrap();
+
+        let global = &self.global;
+        let (device, error) = gfx_select!(adapter_id => global.adapter_request_device(
+            adapter_id,
+            desc,
+            None,
+            id::TypedId::zip(0, 0, adapter_id.backend())
+        ));
+        if let Some(e) = error {
+            return Err(format_error(&e));
+        }
+        let info = DeviceInfo {
+            id: device,
+            adapter,
+            features: gfx_select!(device => global.device_features(device))
+                .map_err(|e| format_error(&e))?,
+            limits: gfx_select!(device => global.device_limits(device))
+                .map_err(|e| format_error(&e))?,
+            downlevel: gfx_select!(device => global.device_downlevel_properties(device))
+                .map_err(|e| format_error(&e))?,
+            timestamp_period: gfx_select!(device => global.queue_get_timestamp_period(device))
+                .map_err(|e| format_error(&e))?,
+        };
+        self.device = Some(device);
+        Ok(info)
+    }
+
+    fn report<E: std::error::Error>(&mut self, error: Option<E>) {
+        if let Some(error) = error {
+            if self.errors.len() < MAX_ERRORS {
+                self.errors.push(format_error(&error));
+            } else {
+                self.dropped_errors += 1;
+            }
+        }
+    }
+
+    fn claim<I: RemoteId>(&self, id: I) -> Result<(), Error> {
+        self.registry.lock().claim(id).map_err(Error::InvalidAction)
+    }
+
+    fn check<I: RemoteId>(&self, id: I) -> Result<(), Error> {
+        self.registry.lock().check(id).map_err(Error::InvalidAction)
+    }
+
+    fn release<I: RemoteId>(&self, id: I) -> Result<(), Error> {
+        self.registry
+            .lock()
+            .release(id)
+            .map_err(Error::InvalidAction)
+    }
+
+    fn take_blob(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        match self.blob.take() {
+            Some((blob_name, data)) if blob_name == name => Ok(data),
+            _ => Err(Error::InvalidAction(format!("Missing data {}", name))),
+        }
+    }
+
+    /// Execute an action, collecting the validation errors.
+    ///
+    /// Returns an error only for actions that can't come from a well-behaved client.
+    fn process<A: HalApi>(&mut self, device: id::DeviceId, action: Action) -> Result<(), Error> {
+        log::trace!("action {:?}", action);
+        match action {
+            Action::Init { .. } => {
+                return Err(Error::InvalidAction(
+                    "The device is created with a request".to_string(),
+                ))
+            }
+            Action::ConfigureSurface { .. }
+            | Action::GetSurfaceTexture { .. }
+            | Action::Present(_)
+            | Action::DiscardSurfaceTexture(_) => {
+                return Err(Error::InvalidAction(
+                    "Surfaces are not supported".to_string(),
+                ))
+            }
+            Action::CreatePipelineCache { .. } | Action::DestroyPipelineCache(_) => {
+                // Cache data goes straight to the driver, which can't be trusted with it.
+                return Err(Error::InvalidAction(
+                    "Pipeline caches are not supported".to_string(),
+                ));
+            }
+            Action::CreateBuffer(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self.global.device_create_buffer::<A>(device, &desc, id);
+                if desc.mapped_at_creation && error.is_none() {
+                    self.mapped_at_creation.insert(id);
+                }
+                self.report(error);
+            }
+            Action::FreeBuffer(id) => {
+                self.check(id)?;
+                let result = self.global.buffer_destroy::<A>(id);
+                self.report(result.err());
+            }
+            Action::DestroyBuffer(id) => {
+                self.release(id)?;
+                self.mapped.remove(&id);
+                self.mapped_at_creation.remove(&id);
+                self.global.buffer_drop::<A>(id, false);
+            }
+            Action::CreateTexture(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self.global.device_create_texture::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::FreeTexture(id) => {
+                self.check(id)?;
+                let result = self.global.texture_destroy::<A>(id);
+                self.report(result.err());
+            }
+            Action::DestroyTexture(id) => {
+                self.release(id)?;
+                self.global.texture_drop::<A>(id, false);
+            }
+            Action::CreateTextureView {
+                id,
+                parent_id,
+                desc,
+            } => {
+                self.check(parent_id)?;
+                self.claim(id)?;
+                let (_, error) = self.global.texture_create_view::<A>(parent_id, &desc, id);
+                self.report(error);
+            }
+            Action::DestroyTextureView(id) => {
+                self.release(id)?;
+                let result = self.global.texture_view_drop::<A>(id, false);
+                self.report(result.err());
+            }
+            Action::CreateSampler(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self.global.device_create_sampler::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::DestroySampler(id) => {
+                self.release(id)?;
+                self.global.sampler_drop::<A>(id);
+            }
+            Action::CreateBindGroupLayout(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self
+                    .global
+                    .device_create_bind_group_layout::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::DestroyBindGroupLayout(id) => {
+                self.release(id)?;
+                self.global.bind_group_layout_drop::<A>(id);
+            }
+            Action::CreatePipelineLayout(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self
+                    .global
+                    .device_create_pipeline_layout::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::DestroyPipelineLayout(id) => {
+                self.release(id)?;
+                self.global.pipeline_layout_drop::<A>(id);
+            }
+            Action::CreateBindGroup(id, desc) => {
+                self.claim(id)?;
+                let (_, error) = self.global.device_create_bind_group::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::DestroyBindGroup(id) => {
+                self.release(id)?;
+                self.global.bind_group_drop::<A>(id);
+            }
+            Action::CreateShaderModule { id, mut desc, data } => {
+                if !data.ends_with(".wgsl") {
+                    return Err(Error::InvalidAction(format!("Shader {} is not WGSL", data)));
+                }
+                let code = String::from_utf8_lossy(&self.take_blob(&data)?).into_owned();
+                self.claim(id)?;
+                // Skipping the bound checks is only for trusted shaders.
+                desc.shader_bound_checks = wgt::ShaderBoundChecks::new();
+                let source = wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code));
+                let (_, error) = self
+                    .global
+                    .device_create_shader_module::<A>(device, &desc, source, id);
+                self.report(error);
+            }
+            Action::DestroyShaderModule(id) => {
+                self.release(id)?;
+                self.global.shader_module_drop::<A>(id);
+            }
+            Action::CreateComputePipeline {
+                id,
+                desc,
+                implicit_context,
+            } => {
+                if implicit_context.is_some() {
+                    return Err(Error::InvalidAction(
+                        "Implicit pipeline layouts are not supported".to_string(),
+                    ));
+                }
+                if desc.cache.is_some() {
+                    return Err(Error::InvalidAction(
+                        "Pipeline caches are not supported".to_string(),
+                    ));
+                }
+                self.claim(id)?;
+                let (_, error) = self
+                    .global
+                    .device_create_compute_pipeline::<A>(device, &desc, id, None);
+                self.report(error);
+            }
+            Action::DestroyComputePipeline(id) => {
+                self.release(id)?;
+                self.global.compute_pipeline_drop::<A>(id);
+            }
+            Action::CreateRenderPipeline {
+                id,
+                desc,
+                implicit_context,
+            } => {
+                if implicit_context.is_some() {
+                    return Err(Error::InvalidAction(
+                        "Implicit pipeline layouts are not supported".to_string(),
+                    ));
+                }
+                if desc.cache.is_some() {
+                    return Err(Error::InvalidAction(
+                        "Pipeline caches are not supported".to_string(),
+                    ));
+                }
+                self.claim(id)?;
+                let (_, error) = self
+                    .global
+                    .device_create_render_pipeline::<A>(device, &desc, id, None);
+                self.report(error);
+            }
+            Action::DestroyRenderPipeline(id) => {
+                self.release(id)?;
+                self.global.render_pipeline_drop::<A>(id);
+            }
+            Action::CreateRenderBundle { id, desc, base } => {
+                self.claim(id)?;
+                let bundle = match wgc::command::RenderBundleEncoder::new(&desc, device, Some(base))
+                {
+                    Ok(bundle) => bundle,
+                    Err(e) => {
+                        self.report(Some(e));
+                        wgc::command::RenderBundleEncoder::dummy(device)
+                    }
+                };
+                let (_, error) = self.global.render_bundle_encoder_finish::<A>(
+                    bundle,
+                    &wgt::RenderBundleDescriptor { label: desc.label },
+                    id,
+                );
+                self.report(error);
+            }
+            Action::DestroyRenderBundle(id) => {
+                self.release(id)?;
+                self.global.render_bundle_drop::<A>(id);
+            }
+            Action::CreateQuerySet { id, desc } => {
+                self.claim(id)?;
+                let (_, error) = self.global.device_create_query_set::<A>(device, &desc, id);
+                self.report(error);
+            }
+            Action::DestroyQuerySet(id) => {
+                self.release(id)?;
+                self.global.query_set_drop::<A>(id);
+            }
+            Action::WriteBuffer {
+                id,
+                data,
+                range,
+                queued,
+            } => {
+                if !queued {
+                    return Err(Error::InvalidAction(
+                        "Buffers are written through mapping".to_string(),
+                    ));
+                }
+                self.check(id)?;
+                let bin = self.take_blob(&data)?;
+                let result = self
+                    .global
+                    .queue_write_buffer::<A>(device, id, range.start, &bin);
+                self.report(result.err());
+            }
+            Action::WriteTexture {
+                to,
+                data,
+                layout,
+                size,
+            } => {
+                self.check(to.texture)?;
+                let bin = self.take_blob(&data)?;
+                let result = self
+                    .global
+                    .queue_write_texture::<A>(device, &to, &bin, &layout, &size);
+                self.report(result.err());
+            }
+            Action::Submit(_index, ref commands) if commands.is_empty() => {
+                let result = self.global.queue_submit::<A>(device, &[]);
+                self.report(result.err());
+            }
+            Action::Submit(_index, commands) => {
+                let encoder_id = self.registry.lock().command_buffers.alloc(device.backend());
+                let (encoder, error) = self.global.device_create_command_encoder::<A>(
+                    device,
+                    &wgt::CommandEncoderDescriptor { label: None },
+                    encoder_id,
+                );
+                if let Some(e) = error {
+                    self.report(Some(e));
+                    self.global.command_encoder_drop::<A>(encoder);
+                    return Ok(());
+                }
+                self.encode_commands::<A>(encoder, commands);
+                let (cmd_buf, error) = self.global.command_encoder_finish::<A>(
+                    encoder,
+                    &wgt::CommandBufferDescriptor { label: None },
+                );
+                if let Some(e) = error {
+                    self.report(Some(e));
+                    self.global.command_buffer_drop::<A>(cmd_buf);
+                    return Ok(());
+                }
+                let result = self.global.queue_submit::<A>(device, &[cmd_buf]);
+                self.report(result.err());
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_commands<A: HalApi>(
+        &mut self,
+        encoder: id::CommandEncoderId,
+        commands: Vec<Command>,
+    ) {
+        for command in commands {
+            match command {
+                Command::CopyBufferToBuffer {
+                    src,
+                    src_offset,
+                    dst,
+                    dst_offset,
+                    size,
+                } => {
+                    let result = self.global.command_encoder_copy_buffer_to_buffer::<A>(
+                        encoder, src, src_offset, dst, dst_offset, size,
+                    );
+                    self.report(result.err());
+                }
+                Command::CopyBufferToTexture { src, dst, size } => {
+                    let result = self
+                        .global
+                        .command_encoder_copy_buffer_to_texture::<A>(encoder, &src, &dst, &size);
+                    self.report(result.err());
+                }
+                Command::CopyTextureToBuffer { src, dst, size } => {
+                    let result = self
+                        .global
+                        .command_encoder_copy_texture_to_buffer::<A>(encoder, &src, &dst, &size);
+                    self.report(result.err());
+                }
+                Command::CopyTextureToTexture { src, dst, size } => {
+                    let result = self
+                        .global
+                        .command_encoder_copy_texture_to_texture::<A>(encoder, &src, &dst, &size);
+                    self.report(result.err());
+                }
+                Command::ClearBuffer { dst, offset, size } => {
+                    let result = self
+                        .global
+                        .command_encoder_clear_buffer::<A>(encoder, dst, offset, size);
+                    self.report(result.err());
+                }
+                Command::ClearTexture {
+                    dst,
+                    subresource_range,
+                } => {
+                    let result = self.global.command_encoder_clear_texture::<A>(
+                        encoder,
+                        dst,
+                        &subresource_range,
+                    );
+                    self.report(result.err());
+                }
+                Command::WriteTimestamp {
+                    query_set_id,
+                    query_index,
+                } => {
+                    let result = self.global.command_encoder_write_timestamp::<A>(
+                        encoder,
+                        query_set_id,
+                        query_index,
+                    );
+                    self.report(result.err());
+                }
+                Command::ResolveQuerySet {
+                    query_set_id,
+                    start_query,
+                    query_count,
+                    destination,
+                    destination_offset,
+                } => {
+                    let result = self.global.command_encoder_resolve_query_set::<A>(
+                        encoder,
+                        query_set_id,
+                        start_query,
+                        query_count,
+                        destination,
+                        destination_offset,
+                    );
+                    self.report(result.err());
+                }
+                Command::PushDebugGroup(marker) => {
+                    let result = self
+                        .global
+                        .command_encoder_push_debug_group::<A>(encoder, &marker);
+                    self.report(result.err());
+                }
+                Command::PopDebugGroup => {
+                    let result = self.global.command_encoder_pop_debug_group::<A>(encoder);
+                    self.report(result.err());
+                }
+                Command::InsertDebugMarker(marker) => {
+                    let result = self
+                        .global
+                        .command_encoder_insert_debug_marker::<A>(encoder, &marker);
+                    self.report(result.err());
+                }
+                Command::RunComputePass { base } => {
+                    let result = self
+                        .global
+                        .command_encoder_run_compute_pass_impl::<A>(encoder, base.as_ref());
+                    self.report(result.err());
+                }
+                Command::RunRenderPass {
+                    base,
+                    target_colors,
+                    target_depth_stencil,
+                } => {
+                    let result = self.global.command_encoder_run_render_pass_impl::<A>(
+                        encoder,
+                        base.as_ref(),
+                        &target_colors,
+                        target_depth_stencil.as_ref(),
+                    );
+                    self.report(result.err());
+                }
+            }
+        }
+    }
+
+    /// Map a buffer and copy its contents into new shared memory.
+    ///
+    /// Waits for the device, so that the client gets the memory in the reply.
+    fn map_buffer<A: HalApi>(
+        &mut self,
+        device: id::DeviceId,
+        buffer: id::BufferId,
+        host: HostMap,
+        range: Range<wgt::BufferAddress>,
+    ) -> Result<Result<RawFd, String>, Error> {
+        self.check(buffer)?;
+        if self.mapped.contains_key(&buffer) {
+            return Ok(Err("The buffer is already mapped".to_string()));
+        }
+        if range.start > range.end {
+            return Ok(Err(format!("Invalid range {:?}", range)));
+        }
+        let size = range.end - range.start;
+
+        // Buffers mapped at creation can be accessed right away.
+        if !self.mapped_at_creation.remove(&buffer) {
+            let status = Box::into_raw(Box::new(None::<BufferMapAsyncStatus>));
+            let op = BufferMapOperation {
+                host,
+                callback: map_callback,
+                user_data: status as *mut u8,
+            };
+            if let Err(e) = self.global.buffer_map_async::<A>(buffer, range.clone(), op) {
+                // The callback is not called after an error is returned.
+                unsafe { drop(Box::from_raw(status)) };
+                return Ok(Err(format_error(&e)));
+            }
+            if let Err(e) = self.global.device_poll::<A>(device, wgt::Maintain::Wait) {
+                self.report(Some(e));
+            }
+            match unsafe { (*status).take() } {
+                Some(BufferMapAsyncStatus::Success) => unsafe { drop(Box::from_raw(status)) },
+                Some(status_value) => {
+                    unsafe { drop(Box::from_raw(status)) };
+                    return Ok(Err(format!("Unable to map: {:?}", status_value)));
+                }
+                // The callback may still be called later, so the status is leaked.
+                None => return Ok(Err("The mapping didn't complete".to_string())),
+            }
+        }
+
+        let (ptr, len) =
+            match self
+                .global
+                .buffer_get_mapped_range::<A>(buffer, range.start, Some(size))
+            {
+                Ok(mapping) => mapping,
+                Err(e) => {
+                    let _ = self.global.buffer_unmap::<A>(buffer);
+                    return Ok(Err(format_error(&e)));
+                }
+            };
+        let mut memory = SharedMemory::new(len as usize)?;
+        unsafe { memory.copy_from(ptr, len as usize)? };
+        let fd = memory.fd();
+        self.mapped.insert(
+            buffer,
+            MappedBuffer {
+                memory,
+                offset: range.start,
+                host,
+            },
+        );
+        Ok(Ok(fd))
+    }
+
+    /// Copy the contents written by the client back, and unmap the buffer.
+    fn unmap_buffer<A: HalApi>(&mut self, buffer: id::BufferId) -> Result<(), Error> {
+        self.check(buffer)?;
+        self.mapped_at_creation.remove(&buffer);
+        if let Some(mapped) = self.mapped.remove(&buffer) {
+            if mapped.host == HostMap::Write {
+                let len = mapped.memory.len();
+                match self.global.buffer_get_mapped_range::<A>(
+                    buffer,
+                    mapped.offset,
+                    Some(len as wgt::BufferAddress),
+                ) {
+                    Ok((ptr, _)) => unsafe { mapped.memory.copy_to(ptr, len)? },
+                    Err(e) => self.report(Some(e)),
+                }
+            }
+        }
+        let result = self.global.buffer_unmap::<A>(buffer);
+        self.report(result.err());
+        Ok(())
+    }
+}
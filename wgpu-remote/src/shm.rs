@@ -0,0 +1,272 @@
+use std::{
+    io, mem,
+    os::unix::{
+        io::{AsRawFd, RawFd},
+        net::UnixStream,
+    },
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// Memory mapped in both the client and the server.
+#[derive(Debug)]
+pub(crate) struct SharedMemory {
+    fd: RawFd,
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+unsafe impl Send for SharedMemory {}
+
+impl SharedMemory {
+    /// Create new zeroed memory of the given size.
+    ///
+    /// Where possible, the size is sealed, so the other side can't shrink the memory
+    /// under our feet.
+    pub fn new(len: usize) -> io::Result<Self> {
+        let fd = create_fd()?;
+        if unsafe { libc::ftruncate(fd, len.max(1) as libc::off_t) } != 0 || !seal_size(fd) {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        Self::from_fd(fd, len)
+    }
+
+    /// Map memory created by the other side, taking ownership of the descriptor.
+    pub fn from_fd(fd: RawFd, len: usize) -> io::Result<Self> {
+        // Mapping an empty range is an error, so there is always at least one byte.
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len.max(1),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            let error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(error);
+        }
+        Ok(Self {
+            fd,
+            ptr: NonNull::new(ptr as *mut u8).unwrap(),
+            len,
+        })
+    }
+
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Copy `len` bytes from `src` into the memory.
+    ///
+    /// Fails if the other side shrank the memory.
+    ///
+    /// # Safety
+    /// `src` has to be valid for reading `len` bytes, and `len` can't exceed `self.len()`.
+    pub unsafe fn copy_from(&mut self, src: *const u8, len: usize) -> io::Result<()> {
+        debug_assert!(len <= self.len);
+        self.check_size()?;
+        ptr::copy_nonoverlapping(src, self.ptr.as_ptr(), len);
+        Ok(())
+    }
+
+    /// Copy `len` bytes of the memory into `dst`.
+    ///
+    /// The other side may be writing at the same time, so this never hands out
+    /// a slice of the memory on the server. Fails if the other side shrank the memory.
+    ///
+    /// # Safety
+    /// `dst` has to be valid for writing `len` bytes, and `len` can't exceed `self.len()`.
+    pub unsafe fn copy_to(&self, dst: *mut u8, len: usize) -> io::Result<()> {
+        debug_assert!(len <= self.len);
+        self.check_size()?;
+        ptr::copy_nonoverlapping(self.ptr.as_ptr(), dst, len);
+        Ok(())
+    }
+
+    /// Check that the memory wasn't shrunk, as accessing the pages past its end would
+    /// raise `SIGBUS`.
+    ///
+    /// Sealed memory can't be resized, so there is nothing to check.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn check_size(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Check that the memory wasn't shrunk, as accessing the pages past its end would
+    /// raise `SIGBUS`.
+    ///
+    /// The memory can still be shrunk right after the check, so this only catches
+    /// a misbehaving process, not a malicious one.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn check_size(&self) -> io::Result<()> {
+        let mut stat = mem::MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(self.fd, stat.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let size = unsafe { stat.assume_init() }.st_size;
+        if (size as u64) < self.len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "shared memory was shrunk",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len.max(1));
+            libc::close(self.fd);
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn create_fd() -> io::Result<RawFd> {
+    let fd = unsafe {
+        libc::memfd_create(
+            b"wgpu-remote\0".as_ptr() as *const _,
+            libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fd)
+}
+
+/// Seal the size of the memory, returning false on failure.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn seal_size(fd: RawFd) -> bool {
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_SEAL;
+    unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) == 0 }
+}
+
+/// Shared memory objects can't be sealed, their size is checked on every access instead.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn seal_size(_fd: RawFd) -> bool {
+    true
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn create_fd() -> io::Result<RawFd> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    // The name is only used until the object is unlinked right below.
+    let name = format!(
+        "/wgpu-remote-{}-{}\0",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let fd = unsafe {
+        libc::shm_open(
+            name.as_ptr() as *const _,
+            libc::O_RDWR | libc::O_CREAT | libc::O_EXCL,
+            0o600,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { libc::shm_unlink(name.as_ptr() as *const _) };
+    Ok(fd)
+}
+
+/// Send a file descriptor over the socket, as a single byte of data.
+pub(crate) fn send_fd(socket: &UnixStream, fd: RawFd) -> io::Result<()> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: &mut byte as *mut u8 as *mut libc::c_void,
+        iov_len: 1,
+    };
+    // `u64` keeps the control messages aligned.
+    let mut control = [0u64; 8];
+    unsafe {
+        let space = libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as usize;
+        assert!(space <= mem::size_of_val(&control));
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = space as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+        if libc::sendmsg(socket.as_raw_fd(), &msg, 0) != 1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Receive a file descriptor sent with `send_fd`.
+pub(crate) fn recv_fd(socket: &UnixStream) -> io::Result<RawFd> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: &mut byte as *mut u8 as *mut libc::c_void,
+        iov_len: 1,
+    };
+    let mut control = [0u64; 8];
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let flags = libc::MSG_CMSG_CLOEXEC;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let flags = 0;
+        match libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) {
+            1 => {}
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => return Err(io::Error::last_os_error()),
+        }
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_level != libc::SOL_SOCKET
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a file descriptor",
+            ));
+        }
+        Ok(ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn test_sealed_size() {
+    let memory = SharedMemory::new(16).unwrap();
+    for &len in &[0, 1 << 20] {
+        assert_ne!(unsafe { libc::ftruncate(memory.fd(), len) }, 0);
+    }
+    let mut data = [1u8; 16];
+    unsafe { memory.copy_to(data.as_mut_ptr(), data.len()).unwrap() };
+    assert_eq!(data, [0; 16]);
+}
@@ -0,0 +1,268 @@
+//! Checks of the actions sent by the client, done before they reach `wgpu-core`.
+//!
+//! `wgpu-core` trusts the IDs nested in descriptors and commands to exist, and
+//! the commands of passes to stay within the data of the pass, panicking otherwise.
+
+use crate::{protocol::RemoteId, server::IdRegistry};
+use wgc::{
+    binding_model::BindingResource,
+    command::{BasePass, ComputeCommand, RenderCommand},
+    device::trace::{Action, Command},
+};
+
+use std::str;
+
+/// Check the nested IDs and the pass data of an action.
+pub(crate) fn check_action(registry: &IdRegistry, action: &Action) -> Result<(), String> {
+    match *action {
+        Action::CreatePipelineLayout(_, ref desc) => {
+            check_all(registry, &desc.bind_group_layouts)?;
+        }
+        Action::CreateBindGroup(_, ref desc) => {
+            registry.check(desc.layout)?;
+            for entry in desc.entries.iter() {
+                match entry.resource {
+                    BindingResource::Buffer(ref binding) => registry.check(binding.buffer_id)?,
+                    BindingResource::BufferArray(ref bindings) => {
+                        for binding in bindings.iter() {
+                            registry.check(binding.buffer_id)?;
+                        }
+                    }
+                    BindingResource::Sampler(id) => registry.check(id)?,
+                    BindingResource::SamplerArray(ref ids) => check_all(registry, ids)?,
+                    BindingResource::TextureView(id) => registry.check(id)?,
+                    BindingResource::TextureViewArray(ref ids) => check_all(registry, ids)?,
+                }
+            }
+        }
+        Action::CreateComputePipeline { ref desc, .. } => {
+            if let Some(layout) = desc.layout {
+                registry.check(layout)?;
+            }
+            registry.check(desc.stage.module)?;
+        }
+        Action::CreateRenderPipeline { ref desc, .. } => {
+            if let Some(layout) = desc.layout {
+                registry.check(layout)?;
+            }
+            registry.check(desc.vertex.stage.module)?;
+            if let Some(ref fragment) = desc.fragment {
+                registry.check(fragment.stage.module)?;
+            }
+        }
+        Action::CreateRenderBundle { ref base, .. } => check_render_commands(registry, base, true)?,
+        Action::Submit(_, ref commands) => {
+            for command in commands {
+                check_command(registry, command)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn check_all<I: RemoteId>(registry: &IdRegistry, ids: &[I]) -> Result<(), String> {
+    ids.iter().try_for_each(|&id| registry.check(id))
+}
+
+fn check_command(registry: &IdRegistry, command: &Command) -> Result<(), String> {
+    match *command {
+        Command::CopyBufferToBuffer { src, dst, .. } => {
+            registry.check(src)?;
+            registry.check(dst)?;
+        }
+        Command::CopyBufferToTexture {
+            ref src, ref dst, ..
+        } => {
+            registry.check(src.buffer)?;
+            registry.check(dst.texture)?;
+        }
+        Command::CopyTextureToBuffer {
+            ref src, ref dst, ..
+        } => {
+            registry.check(src.texture)?;
+            registry.check(dst.buffer)?;
+        }
+        Command::CopyTextureToTexture {
+            ref src, ref dst, ..
+        } => {
+            registry.check(src.texture)?;
+            registry.check(dst.texture)?;
+        }
+        Command::ClearBuffer { dst, .. } => registry.check(dst)?,
+        Command::ClearTexture { dst, .. } => registry.check(dst)?,
+        Command::WriteTimestamp { query_set_id, .. } => registry.check(query_set_id)?,
+        Command::ResolveQuerySet {
+            query_set_id,
+            destination,
+            ..
+        } => {
+            registry.check(query_set_id)?;
+            registry.check(destination)?;
+        }
+        Command::PushDebugGroup(_) | Command::PopDebugGroup | Command::InsertDebugMarker(_) => {}
+        Command::RunComputePass { ref base } => check_compute_commands(registry, base)?,
+        Command::RunRenderPass {
+            ref base,
+            ref target_colors,
+            ref target_depth_stencil,
+        } => {
+            for attachment in target_colors {
+                registry.check(attachment.view)?;
+                if let Some(resolve_target) = attachment.resolve_target {
+                    registry.check(resolve_target)?;
+                }
+            }
+            if let Some(ref attachment) = *target_depth_stencil {
+                registry.check(attachment.view)?;
+            }
+            check_render_commands(registry, base, false)?;
+        }
+    }
+    Ok(())
+}
+
+/// Position in the data of a pass, following its commands.
+struct PassData<'a, C> {
+    base: &'a BasePass<C>,
+    dynamic_offset_count: usize,
+    string_offset: usize,
+}
+
+impl<'a, C> PassData<'a, C> {
+    fn new(base: &'a BasePass<C>) -> Self {
+        Self {
+            base,
+            dynamic_offset_count: 0,
+            string_offset: 0,
+        }
+    }
+
+    fn dynamic_offsets(&mut self, count: u8) -> Result<(), String> {
+        self.dynamic_offset_count += count as usize;
+        if self.dynamic_offset_count > self.base.dynamic_offsets.len() {
+            return Err("Dynamic offsets are out of the pass data".to_string());
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, len: usize) -> Result<(), String> {
+        let range = self.string_offset..self.string_offset.saturating_add(len);
+        match self.base.string_data.get(range.clone()).map(str::from_utf8) {
+            Some(Ok(_)) => {
+                self.string_offset = range.end;
+                Ok(())
+            }
+            _ => Err("Debug labels are out of the pass data".to_string()),
+        }
+    }
+
+    fn push_constants(&self, values_offset: u32, size_bytes: u32) -> Result<(), String> {
+        match values_offset.checked_add(size_bytes / wgt::PUSH_CONSTANT_ALIGNMENT) {
+            Some(end) if end as usize <= self.base.push_constant_data.len() => Ok(()),
+            _ => Err("Push constants are out of the pass data".to_string()),
+        }
+    }
+}
+
+fn check_compute_commands(
+    registry: &IdRegistry,
+    base: &BasePass<ComputeCommand>,
+) -> Result<(), String> {
+    let mut data = PassData::new(base);
+    for command in base.commands.iter() {
+        match *command {
+            ComputeCommand::SetBindGroup {
+                num_dynamic_offsets,
+                bind_group_id,
+                ..
+            } => {
+                registry.check(bind_group_id)?;
+                data.dynamic_offsets(num_dynamic_offsets)?;
+            }
+            ComputeCommand::SetPipeline(id) => registry.check(id)?,
+            ComputeCommand::SetPushConstant {
+                size_bytes,
+                values_offset,
+                ..
+            } => data.push_constants(values_offset, size_bytes)?,
+            ComputeCommand::DispatchIndirect { buffer_id, .. } => registry.check(buffer_id)?,
+            ComputeCommand::PushDebugGroup { len, .. }
+            | ComputeCommand::InsertDebugMarker { len, .. } => data.string(len)?,
+            ComputeCommand::WriteTimestamp { query_set_id, .. }
+            | ComputeCommand::BeginPipelineStatisticsQuery { query_set_id, .. } => {
+                registry.check(query_set_id)?
+            }
+            ComputeCommand::Dispatch(_)
+            | ComputeCommand::PopDebugGroup
+            | ComputeCommand::EndPipelineStatisticsQuery => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check the commands of a render pass, or of a render bundle if `bundle` is set.
+fn check_render_commands(
+    registry: &IdRegistry,
+    base: &BasePass<RenderCommand>,
+    bundle: bool,
+) -> Result<(), String> {
+    let mut data = PassData::new(base);
+    for command in base.commands.iter() {
+        match *command {
+            RenderCommand::SetBindGroup {
+                num_dynamic_offsets,
+                bind_group_id,
+                ..
+            } => {
+                registry.check(bind_group_id)?;
+                data.dynamic_offsets(num_dynamic_offsets)?;
+            }
+            RenderCommand::SetPipeline(id) => registry.check(id)?,
+            RenderCommand::SetIndexBuffer { buffer_id, .. }
+            | RenderCommand::SetVertexBuffer { buffer_id, .. } => registry.check(buffer_id)?,
+            RenderCommand::SetPushConstant {
+                size_bytes,
+                values_offset,
+                ..
+            } => {
+                if let Some(values_offset) = values_offset {
+                    data.push_constants(values_offset, size_bytes)?;
+                }
+            }
+            RenderCommand::Draw { .. } | RenderCommand::DrawIndexed { .. } => {}
+            RenderCommand::MultiDrawIndirect {
+                buffer_id,
+                count: None,
+                ..
+            } => registry.check(buffer_id)?,
+            // Render bundles only support the commands above.
+            _ if bundle => {
+                return Err(format!("{:?} is not supported in render bundles", command));
+            }
+            RenderCommand::MultiDrawIndirect { buffer_id, .. } => registry.check(buffer_id)?,
+            RenderCommand::MultiDrawIndirectCount {
+                buffer_id,
+                count_buffer_id,
+                ..
+            } => {
+                registry.check(buffer_id)?;
+                registry.check(count_buffer_id)?;
+            }
+            RenderCommand::PushDebugGroup { len, .. }
+            | RenderCommand::InsertDebugMarker { len, .. } => data.string(len)?,
+            RenderCommand::WriteTimestamp { query_set_id, .. }
+            | RenderCommand::BeginPipelineStatisticsQuery { query_set_id, .. } => {
+                registry.check(query_set_id)?
+            }
+            RenderCommand::ExecuteBundle(id) => registry.check(id)?,
+            RenderCommand::SetBlendConstant(_)
+            | RenderCommand::SetStencilReference(_)
+            | RenderCommand::SetViewport { .. }
+            | RenderCommand::SetScissor(_)
+            | RenderCommand::PopDebugGroup
+            | RenderCommand::EndPipelineStatisticsQuery => {}
+        }
+    }
+    Ok(())
+}
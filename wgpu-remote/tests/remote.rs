@@ -0,0 +1,360 @@
+//! Tests of a client talking to a server on another thread,
+//! through a socket pair, using the CPU backend.
+
+#![cfg(all(unix, feature = "cpu"))]
+
+use std::{borrow::Cow, io::Write as _, os::unix::net::UnixStream, thread};
+use wgpu_remote::{Client, CommandEncoder, Error, HostMap, Server, MAX_ERRORS};
+
+fn connect() -> (Client, thread::JoinHandle<Result<(), Error>>) {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || {
+        Server::new(
+            server_stream,
+            wgt::Backends::CPU,
+            wgt::PowerPreference::default(),
+        )?
+        .run()
+    });
+    let client = Client::connect(
+        client_stream,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::downlevel_defaults(),
+        },
+    )
+    .unwrap();
+    (client, server)
+}
+
+/// Connect a raw socket, for sending messages that the client never sends.
+fn connect_raw() -> (UnixStream, thread::JoinHandle<Result<(), Error>>) {
+    let (client_stream, server_stream) = UnixStream::pair().unwrap();
+    let server = thread::spawn(move || {
+        Server::new(
+            server_stream,
+            wgt::Backends::CPU,
+            wgt::PowerPreference::default(),
+        )?
+        .run()
+    });
+    (client_stream, server)
+}
+
+/// Encode a `Blob` message like `bincode` does, prefixed with its size.
+fn raw_blob(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut message = 2u32.to_le_bytes().to_vec();
+    message.extend_from_slice(&(name.len() as u64).to_le_bytes());
+    message.extend_from_slice(name.as_bytes());
+    message.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    message.extend_from_slice(data);
+    let mut framed = (message.len() as u32).to_le_bytes().to_vec();
+    framed.extend(message);
+    framed
+}
+
+fn buffer_desc(size: u64, usage: wgt::BufferUsages) -> wgc::resource::BufferDescriptor<'static> {
+    wgt::BufferDescriptor {
+        label: None,
+        size,
+        usage,
+        mapped_at_creation: false,
+    }
+}
+
+fn read_buffer(client: &mut Client, buffer: wgc::id::BufferId, size: u64) -> Vec<u8> {
+    let readback = client
+        .create_buffer(&buffer_desc(
+            size,
+            wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+        ))
+        .unwrap();
+    let mut encoder = CommandEncoder::default();
+    encoder.copy_buffer_to_buffer(buffer, 0, readback, 0, size);
+    client.submit(Some(encoder)).unwrap();
+    let mapping = client.map_buffer(readback, HostMap::Read, 0..size).unwrap();
+    let data = mapping.data().to_vec();
+    client.unmap_buffer(mapping).unwrap();
+    client.drop_buffer(readback).unwrap();
+    data
+}
+
+#[test]
+fn write_and_read_back() {
+    let (mut client, server) = connect();
+    assert_eq!(client.device().adapter.backend, wgt::Backend::Cpu);
+
+    let buffer = client
+        .create_buffer(&buffer_desc(
+            16,
+            wgt::BufferUsages::COPY_SRC | wgt::BufferUsages::COPY_DST,
+        ))
+        .unwrap();
+    let data = (0..16).collect::<Vec<u8>>();
+    client.write_buffer(buffer, 0, &data).unwrap();
+    assert_eq!(read_buffer(&mut client, buffer, 16), data);
+    assert_eq!(client.sync().unwrap(), Vec::<String>::new());
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn map_for_writing() {
+    let (mut client, server) = connect();
+
+    let buffer = client
+        .create_buffer(&wgt::BufferDescriptor {
+            label: None,
+            size: 8,
+            usage: wgt::BufferUsages::COPY_SRC,
+            mapped_at_creation: true,
+        })
+        .unwrap();
+    let mut mapping = client.map_buffer(buffer, HostMap::Write, 0..8).unwrap();
+    mapping.data_mut().copy_from_slice(&[1; 8]);
+    client.unmap_buffer(mapping).unwrap();
+    assert_eq!(read_buffer(&mut client, buffer, 8), [1; 8]);
+
+    let buffer = client
+        .create_buffer(&buffer_desc(
+            16,
+            wgt::BufferUsages::MAP_WRITE | wgt::BufferUsages::COPY_SRC,
+        ))
+        .unwrap();
+    let mut mapping = client.map_buffer(buffer, HostMap::Write, 8..16).unwrap();
+    mapping.data_mut().copy_from_slice(&[2; 8]);
+    client.unmap_buffer(mapping).unwrap();
+    assert_eq!(
+        read_buffer(&mut client, buffer, 16),
+        [0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, 2, 2, 2, 2, 2]
+    );
+    assert_eq!(client.sync().unwrap(), Vec::<String>::new());
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn compute_pass() {
+    let (mut client, server) = connect();
+
+    let buffer = client
+        .create_buffer(&buffer_desc(
+            16,
+            wgt::BufferUsages::STORAGE | wgt::BufferUsages::COPY_SRC | wgt::BufferUsages::COPY_DST,
+        ))
+        .unwrap();
+    client
+        .write_buffer(buffer, 0, &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0])
+        .unwrap();
+
+    let module = client
+        .create_shader_module(
+            &wgc::pipeline::ShaderModuleDescriptor {
+                label: None,
+                shader_bound_checks: wgt::ShaderBoundChecks::default(),
+            },
+            "
+            struct Data { values: array<u32, 4>; };
+            [[group(0), binding(0)]] var<storage, read_write> data: Data;
+
+            [[stage(compute), workgroup_size(4)]]
+            fn main([[builtin(local_invocation_index)]] index: u32) {
+                data.values[index] = data.values[index] * 2u;
+            }
+            ",
+        )
+        .unwrap();
+    let bind_group_layout = client
+        .create_bind_group_layout(&wgc::binding_model::BindGroupLayoutDescriptor {
+            label: None,
+            entries: Cow::Owned(vec![wgt::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgt::ShaderStages::COMPUTE,
+                ty: wgt::BindingType::Buffer {
+                    ty: wgt::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }]),
+        })
+        .unwrap();
+    let pipeline_layout = client
+        .create_pipeline_layout(&wgc::binding_model::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: Cow::Owned(vec![bind_group_layout]),
+            push_constant_ranges: Cow::Borrowed(&[]),
+        })
+        .unwrap();
+    let bind_group = client
+        .create_bind_group(&wgc::binding_model::BindGroupDescriptor {
+            label: None,
+            layout: bind_group_layout,
+            entries: Cow::Owned(vec![wgc::binding_model::BindGroupEntry {
+                binding: 0,
+                resource: wgc::binding_model::BindingResource::Buffer(
+                    wgc::binding_model::BufferBinding {
+                        buffer_id: buffer,
+                        offset: 0,
+                        size: None,
+                    },
+                ),
+            }]),
+        })
+        .unwrap();
+    let pipeline = client
+        .create_compute_pipeline(&wgc::pipeline::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(pipeline_layout),
+            stage: wgc::pipeline::ProgrammableStageDescriptor {
+                module,
+                entry_point: Cow::Borrowed("main"),
+            },
+            cache: None,
+        })
+        .unwrap();
+
+    let mut encoder = CommandEncoder::default();
+    let mut pass = encoder.begin_compute_pass(&wgc::command::ComputePassDescriptor::default());
+    wgc::command::compute_ffi::wgpu_compute_pass_set_pipeline(&mut pass, pipeline);
+    unsafe {
+        wgc::command::compute_ffi::wgpu_compute_pass_set_bind_group(
+            &mut pass,
+            0,
+            bind_group,
+            std::ptr::null(),
+            0,
+        );
+    }
+    wgc::command::compute_ffi::wgpu_compute_pass_dispatch(&mut pass, 1, 1, 1);
+    encoder.end_compute_pass(pass);
+    client.submit(Some(encoder)).unwrap();
+
+    assert_eq!(
+        read_buffer(&mut client, buffer, 16),
+        [2, 0, 0, 0, 4, 0, 0, 0, 6, 0, 0, 0, 8, 0, 0, 0]
+    );
+    assert_eq!(client.sync().unwrap(), Vec::<String>::new());
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn errors_and_recycled_ids() {
+    let (mut client, server) = connect();
+
+    // Writing without `COPY_DST` is a validation error, reported on sync.
+    let buffer = client
+        .create_buffer(&buffer_desc(4, wgt::BufferUsages::COPY_SRC))
+        .unwrap();
+    client.write_buffer(buffer, 0, &[0; 4]).unwrap();
+    assert_eq!(client.sync().unwrap().len(), 1);
+    assert!(client.map_buffer(buffer, HostMap::Read, 0..4).is_err());
+
+    // The ID is only reused after the server released it.
+    client.drop_buffer(buffer).unwrap();
+    let other = client
+        .create_buffer(&buffer_desc(4, wgt::BufferUsages::COPY_SRC))
+        .unwrap();
+    assert_ne!(other, buffer);
+    assert!(client.sync().unwrap().is_empty());
+    let reused = client
+        .create_buffer(&buffer_desc(4, wgt::BufferUsages::COPY_SRC))
+        .unwrap();
+    assert_eq!(
+        wgc::id::TypedId::unzip(reused).0,
+        wgc::id::TypedId::unzip(buffer).0
+    );
+    assert!(client.sync().unwrap().is_empty());
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn misbehaving_clients() {
+    // Dropping an ID that was never created.
+    let (mut client, server) = connect();
+    let backend = client.device().id.backend();
+    client
+        .drop_buffer(wgc::id::TypedId::zip(3, 1, backend))
+        .unwrap();
+    client.flush().unwrap();
+    assert!(matches!(
+        server.join().unwrap(),
+        Err(Error::InvalidAction(_))
+    ));
+    assert!(client.sync().is_err());
+
+    // Referencing an ID that was never created in a descriptor.
+    let (mut client, server) = connect();
+    let backend = client.device().id.backend();
+    client
+        .create_pipeline_layout(&wgc::binding_model::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: Cow::Owned(vec![wgc::id::TypedId::zip(3, 1, backend)]),
+            push_constant_ranges: Cow::Borrowed(&[]),
+        })
+        .unwrap();
+    client.flush().unwrap();
+    assert!(matches!(
+        server.join().unwrap(),
+        Err(Error::InvalidAction(_))
+    ));
+
+    // Referencing an ID that was never created in a pass.
+    let (mut client, server) = connect();
+    let backend = client.device().id.backend();
+    let mut encoder = CommandEncoder::default();
+    let mut pass = encoder.begin_compute_pass(&wgc::command::ComputePassDescriptor::default());
+    wgc::command::compute_ffi::wgpu_compute_pass_set_pipeline(
+        &mut pass,
+        wgc::id::TypedId::zip(3, 1, backend),
+    );
+    encoder.end_compute_pass(pass);
+    client.submit(Some(encoder)).unwrap();
+    client.flush().unwrap();
+    assert!(matches!(
+        server.join().unwrap(),
+        Err(Error::InvalidAction(_))
+    ));
+
+    // Sending garbage.
+    let (client_stream, server) = connect_raw();
+    (&client_stream)
+        .write_all(&[4, 0, 0, 0, 0xff, 0xff, 0xff, 0xff])
+        .unwrap();
+    assert!(matches!(server.join().unwrap(), Err(Error::Codec(_))));
+
+    // Piling up data that no action uses.
+    let (client_stream, server) = connect_raw();
+    (&client_stream).write_all(&raw_blob("a", &[0; 4])).unwrap();
+    (&client_stream).write_all(&raw_blob("b", &[0; 4])).unwrap();
+    assert!(matches!(
+        server.join().unwrap(),
+        Err(Error::InvalidAction(_))
+    ));
+}
+
+#[test]
+fn bounded_errors() {
+    let (mut client, server) = connect();
+
+    let buffer = client
+        .create_buffer(&buffer_desc(4, wgt::BufferUsages::COPY_SRC))
+        .unwrap();
+    for _ in 0..MAX_ERRORS + 10 {
+        client.write_buffer(buffer, 0, &[0; 4]).unwrap();
+    }
+    let errors = client.sync().unwrap();
+    assert_eq!(errors.len(), MAX_ERRORS + 1);
+    assert_eq!(errors[MAX_ERRORS], "10 more errors were dropped");
+    assert!(client.sync().unwrap().is_empty());
+
+    drop(client);
+    server.join().unwrap().unwrap();
+}
@@ -62,6 +62,8 @@ pub enum Backend {
     Gl = 5,
     /// WebGPU in the browser
     BrowserWebGpu = 6,
+    /// Reference implementation on the CPU, without a GPU
+    Cpu = 7,
 }
 
 /// Power Preference when choosing a physical adapter.
@@ -99,6 +101,9 @@ bitflags::bitflags! {
         const DX11 = 1 << Backend::Dx11 as u32;
         /// Supported when targeting the web through webassembly
         const BROWSER_WEBGPU = 1 << Backend::BrowserWebGpu as u32;
+        /// Supported on all native platforms (with the CPU feature enabled),
+        /// but slow. Meant for testing.
+        const CPU = 1 << Backend::Cpu as u32;
         /// All the apis that wgpu offers first tier of support for.
         ///
         /// Vulkan + Metal + DX12 + Browser WebGPU
@@ -807,6 +812,8 @@ impl Limits {
 /// Represents the sets of additional limits on an adapter,
 /// which take place when running on downlevel backends.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct DownlevelLimits {}
 
 #[allow(unknown_lints)] // derivable_impls is nightly only currently
@@ -819,6 +826,8 @@ impl Default for DownlevelLimits {
 
 /// Lists various ways the underlying platform does not conform to the WebGPU standard.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
 pub struct DownlevelCapabilities {
     /// Combined boolean flags.
     pub flags: DownlevelFlags,
@@ -915,6 +924,8 @@ impl DownlevelFlags {
 /// Collections of shader features a device supports if they support less than WebGPU normally allows.
 // TODO: Fill out the differences between shader models more completely
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
 pub enum ShaderModel {
     /// Extremely limited shaders, including a total instruction limit.
     Sm2,
@@ -988,6 +999,119 @@ impl<L> DeviceDescriptor<L> {
     }
 }
 
+/// Memory allocated by a device, returned by `Device::memory_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct MemoryReport {
+    /// Memory heaps of the device.
+    ///
+    /// Backends that don't expose their heaps report all of their memory as a single heap.
+    pub heaps: Vec<MemoryHeapReport>,
+}
+
+impl MemoryReport {
+    /// Bytes used by buffers in all the heaps.
+    pub fn buffer_bytes(&self) -> u64 {
+        self.heaps.iter().map(|heap| heap.buffer_bytes).sum()
+    }
+
+    /// Bytes used by textures in all the heaps.
+    pub fn texture_bytes(&self) -> u64 {
+        self.heaps.iter().map(|heap| heap.texture_bytes).sum()
+    }
+
+    /// Bytes of the memory blocks allocated in all the heaps.
+    pub fn block_bytes(&self) -> u64 {
+        self.heaps.iter().map(|heap| heap.block_bytes).sum()
+    }
+}
+
+/// Memory allocated from one heap of a device.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct MemoryHeapReport {
+    /// Size of the heap in bytes, or 0 if unknown.
+    pub size: u64,
+    /// Whether the heap is local to the device, as opposed to system memory visible to it.
+    pub device_local: bool,
+    /// Bytes used by buffers placed in the heap.
+    pub buffer_bytes: u64,
+    /// Bytes used by textures placed in the heap.
+    pub texture_bytes: u64,
+    /// Number of memory blocks allocated from the heap, that resources are placed in.
+    pub block_count: u64,
+    /// Bytes of all the memory blocks allocated from the heap.
+    pub block_bytes: u64,
+    /// Bytes the process can allocate from the heap without degrading performance, as
+    /// reported by the driver, if available.
+    ///
+    /// This changes over time, depending on the other processes using the device.
+    pub budget: Option<u64>,
+    /// Bytes allocated by the process from the heap as reported by the driver, if available.
+    ///
+    /// Includes the memory allocated by the driver itself, in addition to `block_bytes`.
+    pub driver_usage: Option<u64>,
+}
+
+impl MemoryHeapReport {
+    /// Bytes used by resources placed in the heap.
+    pub fn used_bytes(&self) -> u64 {
+        self.buffer_bytes + self.texture_bytes
+    }
+
+    /// Fraction of the bytes of the memory blocks that isn't used by any resource, between 0
+    /// and 1.
+    pub fn fragmentation(&self) -> f32 {
+        if self.block_bytes == 0 {
+            0.0
+        } else {
+            self.block_bytes.saturating_sub(self.used_bytes()) as f32 / self.block_bytes as f32
+        }
+    }
+}
+
+/// Reason for a device to be lost.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceLostReason {
+    /// The driver reported the device as lost, for a reason unknown to wgpu.
+    Unknown = 0,
+    /// The device was destroyed explicitly.
+    Destroyed = 1,
+}
+
+/// Passed to `Device::poll` to control if and how long it should block.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Maintain<T> {
+    /// Block until the given submission has completed execution.
+    WaitForSubmissionIndex(T),
+    /// Block until all the submitted work has completed execution.
+    Wait,
+    /// Check the device a single time without blocking.
+    Poll,
+}
+
+impl<T> Maintain<T> {
+    /// Returns true if the device should block.
+    pub fn is_wait(&self) -> bool {
+        match *self {
+            Self::WaitForSubmissionIndex(..) | Self::Wait => true,
+            Self::Poll => false,
+        }
+    }
+
+    /// Map the submission index, if there is one.
+    pub fn map_index<U>(self, fun: impl FnOnce(T) -> U) -> Maintain<U> {
+        match self {
+            Self::WaitForSubmissionIndex(index) => Maintain::WaitForSubmissionIndex(fun(index)),
+            Self::Wait => Maintain::Wait,
+            Self::Poll => Maintain::Poll,
+        }
+    }
+}
+
 bitflags::bitflags! {
     /// Describes the shader stages that a binding will be visible from.
     ///
@@ -3639,6 +3763,204 @@ pub struct BindGroupLayoutEntry {
     pub count: Option<NonZeroU32>,
 }
 
+/// Interface of a shader module, returned by `ShaderModule::reflect`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct ShaderReflection {
+    /// Entry points of the module, sorted by name.
+    pub entry_points: Vec<EntryPointReflection>,
+}
+
+impl ShaderReflection {
+    /// Returns the entry point with the given name, for the given stage.
+    pub fn entry_point(&self, name: &str, stage: ShaderStages) -> Option<&EntryPointReflection> {
+        self.entry_points
+            .iter()
+            .find(|ep| ep.name == name && ep.stage == stage)
+    }
+}
+
+/// Interface of a single entry point of a shader module.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct EntryPointReflection {
+    /// Name of the entry point.
+    pub name: String,
+    /// Stage of the entry point, with a single bit set.
+    pub stage: ShaderStages,
+    /// Resources used by the entry point, sorted by group and binding.
+    pub bindings: Vec<BindingReflection>,
+    /// Vertex attributes read by a vertex entry point, sorted by location.
+    pub vertex_inputs: Vec<VertexInputReflection>,
+    /// Color attachments written by a fragment entry point, sorted by location.
+    pub fragment_outputs: Vec<FragmentOutputReflection>,
+    /// Workgroup size of a compute entry point, `[0; 3]` for the other stages.
+    pub workgroup_size: [u32; 3],
+}
+
+/// Resource binding used by an entry point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct BindingReflection {
+    /// Index of the bind group.
+    pub group: u32,
+    /// Binding index inside the bind group.
+    pub binding: u32,
+    /// Type of the binding, as it would be derived for an implicit pipeline layout.
+    ///
+    /// Buffers have their `min_binding_size` set to the size of the type in the shader,
+    /// and storage buffers and textures are only writable if the entry point writes to them.
+    ///
+    /// `None` if no bind group layout entry matches the resource, like for a storage
+    /// texture with a format that can't be used for storage.
+    pub ty: Option<BindingType>,
+}
+
+/// Vertex attribute read by a vertex entry point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct VertexInputReflection {
+    /// Location of the attribute.
+    pub location: ShaderLocation,
+    /// Format of the attribute as seen by the shader.
+    ///
+    /// Vertex buffers can provide the attribute in any format with the same kind
+    /// of scalars, for example `Unorm8x4` for a `vec4<f32>` input reported as `Float32x4`.
+    pub format: VertexFormat,
+}
+
+/// Kind of the scalars of a shader variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum ShaderScalarKind {
+    /// Floating point scalars, matching float, unorm and snorm texture formats.
+    Float,
+    /// Signed integers, matching sint texture formats.
+    Sint,
+    /// Unsigned integers, matching uint texture formats.
+    Uint,
+}
+
+/// Color attachment written by a fragment entry point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct FragmentOutputReflection {
+    /// Location of the output, which is the index of the color attachment.
+    pub location: ShaderLocation,
+    /// Kind of the scalars of the output.
+    pub kind: ShaderScalarKind,
+    /// Number of components of the output, 1 for a scalar.
+    ///
+    /// Attachments can have less components, the others being discarded.
+    pub components: u8,
+}
+
+/// Messages produced while compiling a shader module.
+///
+/// Corresponds to [WebGPU `GPUCompilationInfo`](
+/// https://gpuweb.github.io/gpuweb/#gpucompilationinfo).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct CompilationInfo {
+    /// Messages, in the order they were produced.
+    pub messages: Vec<CompilationMessage>,
+}
+
+/// Severity of a [`CompilationMessage`].
+///
+/// Corresponds to [WebGPU `GPUCompilationMessageType`](
+/// https://gpuweb.github.io/gpuweb/#enumdef-gpucompilationmessagetype).
+#[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CompilationMessageType {
+    /// The module is invalid.
+    Error,
+    /// Possible problem that doesn't make the module invalid.
+    Warning,
+    /// Additional information, usually pointing at other parts of the source.
+    Info,
+}
+
+/// Single message produced while compiling a shader module.
+///
+/// Positions are counted in UTF-16 code units, as in JavaScript strings.
+///
+/// Corresponds to [WebGPU `GPUCompilationMessage`](
+/// https://gpuweb.github.io/gpuweb/#gpucompilationmessage).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct CompilationMessage {
+    /// Human-readable message.
+    pub message: String,
+    /// Severity of the message.
+    pub message_type: CompilationMessageType,
+    /// Line of the source the message starts at, starting from 1, or 0 if the message
+    /// isn't about a specific part of the source.
+    pub line_num: u64,
+    /// Position in the line the message starts at, starting from 1, or 0 if the message
+    /// isn't about a specific part of the source.
+    pub line_pos: u64,
+    /// Offset from the start of the source the message starts at.
+    pub offset: u64,
+    /// Length of the part of the source the message is about, which may be 0.
+    pub length: u64,
+}
+
+impl CompilationMessage {
+    /// Create a message that isn't about a specific part of the source.
+    pub fn new(message: String, message_type: CompilationMessageType) -> Self {
+        Self {
+            message,
+            message_type,
+            line_num: 0,
+            line_pos: 0,
+            offset: 0,
+            length: 0,
+        }
+    }
+
+    /// Create a message about the given byte range of the source.
+    ///
+    /// The range is clamped to the source, and to character boundaries.
+    pub fn with_range(
+        message: String,
+        message_type: CompilationMessageType,
+        source: &str,
+        range: Range<usize>,
+    ) -> Self {
+        let floor = |mut index: usize| {
+            index = index.min(source.len());
+            while !source.is_char_boundary(index) {
+                index -= 1;
+            }
+            index
+        };
+        let start = floor(range.start);
+        let end = floor(range.end).max(start);
+        let utf16_len = |s: &str| s.encode_utf16().count() as u64;
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            message,
+            message_type,
+            line_num: source[..start].matches('\n').count() as u64 + 1,
+            line_pos: utf16_len(&source[line_start..start]) + 1,
+            offset: utf16_len(&source[..start]),
+            length: utf16_len(&source[start..end]),
+        }
+    }
+}
+
 /// View of a buffer which can be used to copy to/from a texture.
 #[repr(C)]
 #[derive(Clone, Debug)]
@@ -114,6 +114,7 @@ async fn execute_gpu_inner(
         layout: None,
         module: &cs_module,
         entry_point: "main",
+        cache: None,
     });
 
     // Instantiates the bind group, once again specifying the binding of buffers.
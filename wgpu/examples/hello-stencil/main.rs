@@ -191,6 +191,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             alpha_to_coverage_enabled: false,
                         },
                         multiview: None,
+                        cache: None,
                     }
                 )
             }
@@ -275,6 +276,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                         alpha_to_coverage_enabled: false,
                     },
                     multiview: None,
+                    cache: None,
                 }
             );
 
@@ -1,20 +1,27 @@
+#[cfg(all(unix, feature = "remote"))]
+use super::dispatch::direct_id;
 use crate::{
-    backend::native_gpu_future, AdapterInfo, BindGroupDescriptor, BindGroupLayoutDescriptor,
-    BindingResource, BufferBinding, CommandEncoderDescriptor, ComputePassDescriptor,
-    ComputePipelineDescriptor, DownlevelCapabilities, Features, Label, Limits, LoadOp, MapMode,
-    Operations, PipelineLayoutDescriptor, RenderBundleEncoderDescriptor, RenderPipelineDescriptor,
-    SamplerDescriptor, ShaderModuleDescriptor, ShaderModuleDescriptorSpirV, ShaderSource,
-    SurfaceStatus, TextureDescriptor, TextureFormat, TextureViewDescriptor,
+    backend::{native_gpu_future, worker_pool::WorkerPool},
+    AdapterInfo, BindGroupDescriptor, BindGroupLayoutDescriptor, BindingResource, BufferBinding,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
+    DownlevelCapabilities, Features, Label, Limits, LoadOp, MapMode, Operations,
+    PipelineCacheDescriptor, PipelineLayoutDescriptor, RenderBundleEncoderDescriptor,
+    RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderModuleDescriptorSpirV, ShaderSource, SurfaceStatus, TextureDescriptor, TextureFormat,
+    TextureViewDescriptor,
 };
 
 use arrayvec::ArrayVec;
 use parking_lot::Mutex;
 use smallvec::SmallVec;
 use std::{
-    borrow::Cow::Borrowed,
+    borrow::Cow::{Borrowed, Owned},
+    collections::HashMap,
     error::Error,
     fmt,
     future::{ready, Ready},
+    hash::Hash,
+    iter,
     marker::PhantomData,
     ops::Range,
     slice,
@@ -23,7 +30,68 @@ use std::{
 
 const LABEL: &str = "label";
 
-pub struct Context(wgc::hub::Global<wgc::hub::IdentityManagerFactory>);
+pub struct Context(
+    wgc::hub::Global<wgc::hub::IdentityManagerFactory>,
+    WorkerPool,
+    Mutex<PinnedResources>,
+);
+
+/// Ids pinned by the jobs using them, mapped to the number of jobs,
+/// and whether the resource was dropped in the meantime.
+struct PinSet<I>(HashMap<I, (usize, bool)>);
+
+impl<I> Default for PinSet<I> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<I: Copy + Eq + Hash> PinSet<I> {
+    fn pin(&mut self, id: I) {
+        self.0.entry(id).or_insert((0, false)).0 += 1;
+    }
+
+    /// Returns true if the resource was dropped while pinned, and has to be dropped now.
+    fn unpin(&mut self, id: I) -> bool {
+        let entry = self.0.get_mut(&id).unwrap();
+        entry.0 -= 1;
+        if entry.0 != 0 {
+            return false;
+        }
+        let dropped = entry.1;
+        self.0.remove(&id);
+        dropped
+    }
+
+    /// Returns true if the resource isn't pinned, and can be dropped right away.
+    fn release(&mut self, id: I) -> bool {
+        match self.0.get_mut(&id) {
+            Some(entry) => {
+                entry.1 = true;
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+/// Resources referenced by the pipelines that are created on the worker threads.
+///
+/// Dropping them would free their ids, which may then be reused by the time the
+/// job looks them up. Instead, they are dropped when the last job using them is done.
+#[derive(Default)]
+struct PinnedResources {
+    shader_modules: PinSet<wgc::id::ShaderModuleId>,
+    pipeline_layouts: PinSet<wgc::id::PipelineLayoutId>,
+    pipeline_caches: PinSet<wgc::id::PipelineCacheId>,
+}
+
+/// Resources used by the descriptor of a pipeline that is created on a worker thread.
+struct PipelineResources {
+    shader_modules: ArrayVec<wgc::id::ShaderModuleId, 2>,
+    pipeline_layout: Option<wgc::id::PipelineLayoutId>,
+    pipeline_cache: Option<wgc::id::PipelineCacheId>,
+}
 
 impl Drop for Context {
     fn drop(&mut self) {
@@ -40,17 +108,54 @@ impl fmt::Debug for Context {
 impl Context {
     #[cfg(not(target_arch = "wasm32"))]
     pub unsafe fn from_hal_instance<A: wgc::hub::HalApi>(hal_instance: A::Instance) -> Self {
-        Self(wgc::hub::Global::from_hal_instance::<A>(
-            "wgpu",
-            wgc::hub::IdentityManagerFactory,
-            hal_instance,
-        ))
+        Self(
+            wgc::hub::Global::from_hal_instance::<A>(
+                "wgpu",
+                wgc::hub::IdentityManagerFactory,
+                hal_instance,
+            ),
+            WorkerPool::default(),
+            Mutex::default(),
+        )
     }
 
     pub(crate) fn global(&self) -> &wgc::hub::Global<wgc::hub::IdentityManagerFactory> {
         &self.0
     }
 
+    fn pin_pipeline_resources(&self, resources: &PipelineResources) {
+        let mut pinned = self.2.lock();
+        for &id in resources.shader_modules.iter() {
+            pinned.shader_modules.pin(id);
+        }
+        if let Some(id) = resources.pipeline_layout {
+            pinned.pipeline_layouts.pin(id);
+        }
+        if let Some(id) = resources.pipeline_cache {
+            pinned.pipeline_caches.pin(id);
+        }
+    }
+
+    fn unpin_pipeline_resources(&self, resources: PipelineResources) {
+        let global = &self.0;
+        let mut pinned = self.2.lock();
+        for id in resources.shader_modules {
+            if pinned.shader_modules.unpin(id) {
+                wgc::gfx_select!(id => global.shader_module_drop(id));
+            }
+        }
+        if let Some(id) = resources.pipeline_layout {
+            if pinned.pipeline_layouts.unpin(id) {
+                wgc::gfx_select!(id => global.pipeline_layout_drop(id));
+            }
+        }
+        if let Some(id) = resources.pipeline_cache {
+            if pinned.pipeline_caches.unpin(id) {
+                wgc::gfx_select!(id => global.pipeline_cache_drop(id));
+            }
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn enumerate_adapters(&self, backends: wgt::Backends) -> Vec<wgc::id::AdapterId> {
         self.0
@@ -74,7 +179,7 @@ impl Context {
         hal_device: hal::OpenDevice<A>,
         desc: &crate::DeviceDescriptor,
         trace_dir: Option<&std::path::Path>,
-    ) -> Result<(Device, wgc::id::QueueId), crate::RequestDeviceError> {
+    ) -> Result<(Device, Queue), crate::RequestDeviceError> {
         let global = &self.0;
         let (device_id, error) = global.create_device_from_hal(
             *adapter,
@@ -86,12 +191,18 @@ impl Context {
         if let Some(err) = error {
             self.handle_error_fatal(err, "Adapter::create_device_from_hal");
         }
+        let error_sink = Arc::new(Mutex::new(ErrorSinkRaw::new()));
+        self.set_lost_closure(device_id, &error_sink, None);
         let device = Device {
             id: device_id,
-            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
+            error_sink: error_sink.clone(),
             features: desc.features,
         };
-        Ok((device, device_id))
+        let queue = Queue {
+            id: device_id,
+            error_sink,
+        };
+        Ok((device, queue))
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -123,6 +234,35 @@ impl Context {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn create_buffer_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        device: &Device,
+        desc: &crate::BufferDescriptor,
+    ) -> Buffer {
+        let global = &self.0;
+        let (id, error) = global.create_buffer_from_hal::<A>(
+            hal_buffer,
+            device.id,
+            &desc.map_label(|l| l.map(Borrowed)),
+            PhantomData,
+        );
+        if let Some(cause) = error {
+            self.handle_error(
+                &device.error_sink,
+                cause,
+                LABEL,
+                desc.label,
+                "Device::create_buffer_from_hal",
+            );
+        }
+        Buffer {
+            id,
+            error_sink: Arc::clone(&device.error_sink),
+        }
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub unsafe fn device_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Device>) -> R, R>(
         &self,
@@ -143,6 +283,39 @@ impl Context {
             .texture_as_hal::<A, F>(texture.id, hal_texture_callback)
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn buffer_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Buffer>)>(
+        &self,
+        buffer: &Buffer,
+        hal_buffer_callback: F,
+    ) {
+        self.0.buffer_as_hal::<A, F>(buffer.id, hal_buffer_callback)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn texture_view_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::TextureView>)>(
+        &self,
+        texture_view: &wgc::id::TextureViewId,
+        hal_texture_view_callback: F,
+    ) {
+        self.0
+            .texture_view_as_hal::<A, F>(*texture_view, hal_texture_view_callback)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn command_encoder_as_hal_mut<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &self,
+        encoder: &CommandEncoder,
+        hal_command_encoder_callback: F,
+    ) -> R {
+        self.0
+            .command_encoder_as_hal_mut::<A, F, R>(encoder.id, hal_command_encoder_callback)
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     pub fn generate_report(&self) -> wgc::hub::GlobalReport {
         self.0.generate_report()
@@ -150,51 +323,81 @@ impl Context {
 
     #[cfg(any(target_os = "ios", target_os = "macos"))]
     pub unsafe fn create_surface_from_core_animation_layer(
-        self: &Arc<Self>,
+        &self,
         layer: *mut std::ffi::c_void,
-    ) -> crate::Surface {
+    ) -> Surface {
         let id = self.0.instance_create_surface_metal(layer, PhantomData);
-        crate::Surface {
-            context: Arc::clone(self),
-            id: Surface {
-                id,
-                configured_device: Mutex::default(),
-            },
+        Surface {
+            id,
+            configured_device: Mutex::default(),
         }
     }
 
-    fn handle_error(
+    fn make_error(
         &self,
-        sink_mutex: &Mutex<ErrorSinkRaw>,
         cause: impl Error + Send + Sync + 'static,
         label_key: &'static str,
         label: Label,
         string: &'static str,
-    ) {
+    ) -> crate::Error {
         let error = wgc::error::ContextError {
             string,
             cause: Box::new(cause),
             label: label.unwrap_or_default().to_string(),
             label_key,
         };
-        let mut sink = sink_mutex.lock();
         let mut source_opt: Option<&(dyn Error + 'static)> = Some(&error);
         while let Some(source) = source_opt {
             if let Some(wgc::device::DeviceError::OutOfMemory) =
                 source.downcast_ref::<wgc::device::DeviceError>()
             {
-                return sink.handle_error(crate::Error::OutOfMemory {
+                return crate::Error::OutOfMemory {
                     source: Box::new(error),
-                });
+                };
             }
             source_opt = source.source();
         }
 
         // Otherwise, it is a validation error
-        sink.handle_error(crate::Error::Validation {
+        crate::Error::Validation {
             description: self.format_error(&error),
             source: Box::new(error),
+        }
+    }
+
+    /// Set the closure to call once the device is lost, which marks its error sink
+    /// as lost before calling `callback`.
+    fn set_lost_closure(
+        &self,
+        device: wgc::id::DeviceId,
+        error_sink: &ErrorSink,
+        callback: Option<wgc::device::DeviceLostClosure>,
+    ) {
+        let global = &self.0;
+        let error_sink = Arc::clone(error_sink);
+        let closure = Box::new(move |reason, message| {
+            error_sink.lock().device_lost = true;
+            if let Some(callback) = callback {
+                callback(reason, message);
+            }
         });
+        if let Err(err) =
+            wgc::gfx_select!(device => global.device_set_lost_closure(device, closure))
+        {
+            self.handle_error_fatal(err, "Device::on_device_lost");
+        }
+    }
+
+    fn handle_error(
+        &self,
+        sink_mutex: &Mutex<ErrorSinkRaw>,
+        cause: impl Error + Send + Sync + 'static,
+        label_key: &'static str,
+        label: Label,
+        string: &'static str,
+    ) {
+        let error = self.make_error(cause, label_key, label, string);
+        sink_mutex.lock().handle_error(error);
     }
 
     fn handle_error_nolabel(
@@ -234,430 +437,34 @@ impl Context {
     }
 }
 
-mod pass_impl {
-    use super::Context;
-    use smallvec::SmallVec;
-    use std::convert::TryInto;
-    use std::ops::Range;
-    use wgc::command::{bundle_ffi::*, compute_ffi::*, render_ffi::*};
-
-    impl crate::ComputePassInner<Context> for wgc::command::ComputePass {
-        fn set_pipeline(&mut self, pipeline: &wgc::id::ComputePipelineId) {
-            wgpu_compute_pass_set_pipeline(self, *pipeline)
-        }
-        fn set_bind_group(
-            &mut self,
-            index: u32,
-            bind_group: &wgc::id::BindGroupId,
-            offsets: &[wgt::DynamicOffset],
-        ) {
-            unsafe {
-                wgpu_compute_pass_set_bind_group(
-                    self,
-                    index,
-                    *bind_group,
-                    offsets.as_ptr(),
-                    offsets.len(),
-                )
-            }
-        }
-        fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
-            unsafe {
-                wgpu_compute_pass_set_push_constant(
-                    self,
-                    offset,
-                    data.len().try_into().unwrap(),
-                    data.as_ptr(),
-                )
-            }
-        }
-        fn insert_debug_marker(&mut self, label: &str) {
-            unsafe {
-                let label = std::ffi::CString::new(label).unwrap();
-                wgpu_compute_pass_insert_debug_marker(self, label.as_ptr(), 0);
-            }
-        }
-
-        fn push_debug_group(&mut self, group_label: &str) {
-            unsafe {
-                let label = std::ffi::CString::new(group_label).unwrap();
-                wgpu_compute_pass_push_debug_group(self, label.as_ptr(), 0);
-            }
-        }
-        fn pop_debug_group(&mut self) {
-            wgpu_compute_pass_pop_debug_group(self);
-        }
-
-        fn write_timestamp(&mut self, query_set: &wgc::id::QuerySetId, query_index: u32) {
-            wgpu_compute_pass_write_timestamp(self, *query_set, query_index)
-        }
-
-        fn begin_pipeline_statistics_query(
-            &mut self,
-            query_set: &wgc::id::QuerySetId,
-            query_index: u32,
-        ) {
-            wgpu_compute_pass_begin_pipeline_statistics_query(self, *query_set, query_index)
-        }
-
-        fn end_pipeline_statistics_query(&mut self) {
-            wgpu_compute_pass_end_pipeline_statistics_query(self)
-        }
-
-        fn dispatch(&mut self, x: u32, y: u32, z: u32) {
-            wgpu_compute_pass_dispatch(self, x, y, z)
-        }
-        fn dispatch_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-        ) {
-            wgpu_compute_pass_dispatch_indirect(self, indirect_buffer.id, indirect_offset)
-        }
-    }
-
-    impl crate::RenderInner<Context> for wgc::command::RenderPass {
-        fn set_pipeline(&mut self, pipeline: &wgc::id::RenderPipelineId) {
-            wgpu_render_pass_set_pipeline(self, *pipeline)
-        }
-        fn set_bind_group(
-            &mut self,
-            index: u32,
-            bind_group: &wgc::id::BindGroupId,
-            offsets: &[wgt::DynamicOffset],
-        ) {
-            unsafe {
-                wgpu_render_pass_set_bind_group(
-                    self,
-                    index,
-                    *bind_group,
-                    offsets.as_ptr(),
-                    offsets.len(),
-                )
-            }
-        }
-        fn set_index_buffer(
-            &mut self,
-            buffer: &super::Buffer,
-            index_format: wgt::IndexFormat,
-            offset: wgt::BufferAddress,
-            size: Option<wgt::BufferSize>,
-        ) {
-            self.set_index_buffer(buffer.id, index_format, offset, size)
-        }
-        fn set_vertex_buffer(
-            &mut self,
-            slot: u32,
-            buffer: &super::Buffer,
-            offset: wgt::BufferAddress,
-            size: Option<wgt::BufferSize>,
-        ) {
-            wgpu_render_pass_set_vertex_buffer(self, slot, buffer.id, offset, size)
-        }
-        fn set_push_constants(&mut self, stages: wgt::ShaderStages, offset: u32, data: &[u8]) {
-            unsafe {
-                wgpu_render_pass_set_push_constants(
-                    self,
-                    stages,
-                    offset,
-                    data.len().try_into().unwrap(),
-                    data.as_ptr(),
-                )
-            }
-        }
-        fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
-            wgpu_render_pass_draw(
-                self,
-                vertices.end - vertices.start,
-                instances.end - instances.start,
-                vertices.start,
-                instances.start,
-            )
-        }
-        fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
-            wgpu_render_pass_draw_indexed(
-                self,
-                indices.end - indices.start,
-                instances.end - instances.start,
-                indices.start,
-                base_vertex,
-                instances.start,
-            )
-        }
-        fn draw_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-        ) {
-            wgpu_render_pass_draw_indirect(self, indirect_buffer.id, indirect_offset)
-        }
-        fn draw_indexed_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-        ) {
-            wgpu_render_pass_draw_indexed_indirect(self, indirect_buffer.id, indirect_offset)
-        }
-        fn multi_draw_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-            count: u32,
-        ) {
-            wgpu_render_pass_multi_draw_indirect(self, indirect_buffer.id, indirect_offset, count)
-        }
-        fn multi_draw_indexed_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-            count: u32,
-        ) {
-            wgpu_render_pass_multi_draw_indexed_indirect(
-                self,
-                indirect_buffer.id,
-                indirect_offset,
-                count,
-            )
-        }
-        fn multi_draw_indirect_count(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-            count_buffer: &super::Buffer,
-            count_buffer_offset: wgt::BufferAddress,
-            max_count: u32,
-        ) {
-            wgpu_render_pass_multi_draw_indirect_count(
-                self,
-                indirect_buffer.id,
-                indirect_offset,
-                count_buffer.id,
-                count_buffer_offset,
-                max_count,
-            )
-        }
-        fn multi_draw_indexed_indirect_count(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-            count_buffer: &super::Buffer,
-            count_buffer_offset: wgt::BufferAddress,
-            max_count: u32,
-        ) {
-            wgpu_render_pass_multi_draw_indexed_indirect_count(
-                self,
-                indirect_buffer.id,
-                indirect_offset,
-                count_buffer.id,
-                count_buffer_offset,
-                max_count,
-            )
-        }
-    }
-
-    impl crate::RenderPassInner<Context> for wgc::command::RenderPass {
-        fn set_blend_constant(&mut self, color: wgt::Color) {
-            wgpu_render_pass_set_blend_constant(self, &color)
-        }
-        fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
-            wgpu_render_pass_set_scissor_rect(self, x, y, width, height)
-        }
-        fn set_viewport(
-            &mut self,
-            x: f32,
-            y: f32,
-            width: f32,
-            height: f32,
-            min_depth: f32,
-            max_depth: f32,
-        ) {
-            wgpu_render_pass_set_viewport(self, x, y, width, height, min_depth, max_depth)
-        }
-        fn set_stencil_reference(&mut self, reference: u32) {
-            wgpu_render_pass_set_stencil_reference(self, reference)
-        }
-
-        fn insert_debug_marker(&mut self, label: &str) {
-            unsafe {
-                let label = std::ffi::CString::new(label).unwrap();
-                wgpu_render_pass_insert_debug_marker(self, label.as_ptr(), 0);
-            }
-        }
-
-        fn push_debug_group(&mut self, group_label: &str) {
-            unsafe {
-                let label = std::ffi::CString::new(group_label).unwrap();
-                wgpu_render_pass_push_debug_group(self, label.as_ptr(), 0);
-            }
-        }
-
-        fn pop_debug_group(&mut self) {
-            wgpu_render_pass_pop_debug_group(self);
-        }
-
-        fn write_timestamp(&mut self, query_set: &wgc::id::QuerySetId, query_index: u32) {
-            wgpu_render_pass_write_timestamp(self, *query_set, query_index)
-        }
-
-        fn begin_pipeline_statistics_query(
-            &mut self,
-            query_set: &wgc::id::QuerySetId,
-            query_index: u32,
-        ) {
-            wgpu_render_pass_begin_pipeline_statistics_query(self, *query_set, query_index)
-        }
+#[path = "pass_impl.rs"]
+mod pass_impl;
 
-        fn end_pipeline_statistics_query(&mut self) {
-            wgpu_render_pass_end_pipeline_statistics_query(self)
-        }
+type ComputePass = wgc::command::ComputePass;
+type RenderPass = wgc::command::RenderPass;
+type RenderBundleEncoder = wgc::command::RenderBundleEncoder;
 
-        fn execute_bundles<'a, I: Iterator<Item = &'a wgc::id::RenderBundleId>>(
-            &mut self,
-            render_bundles: I,
-        ) {
-            let temp_render_bundles = render_bundles.cloned().collect::<SmallVec<[_; 4]>>();
-            unsafe {
-                wgpu_render_pass_execute_bundles(
-                    self,
-                    temp_render_bundles.as_ptr(),
-                    temp_render_bundles.len(),
-                )
-            }
-        }
-    }
+pub(super) fn buffer_id(buffer: &Buffer) -> wgc::id::BufferId {
+    buffer.id
+}
 
-    impl crate::RenderInner<Context> for wgc::command::RenderBundleEncoder {
-        fn set_pipeline(&mut self, pipeline: &wgc::id::RenderPipelineId) {
-            wgpu_render_bundle_set_pipeline(self, *pipeline)
-        }
-        fn set_bind_group(
-            &mut self,
-            index: u32,
-            bind_group: &wgc::id::BindGroupId,
-            offsets: &[wgt::DynamicOffset],
-        ) {
-            unsafe {
-                wgpu_render_bundle_set_bind_group(
-                    self,
-                    index,
-                    *bind_group,
-                    offsets.as_ptr(),
-                    offsets.len(),
-                )
-            }
-        }
-        fn set_index_buffer(
-            &mut self,
-            buffer: &super::Buffer,
-            index_format: wgt::IndexFormat,
-            offset: wgt::BufferAddress,
-            size: Option<wgt::BufferSize>,
-        ) {
-            self.set_index_buffer(buffer.id, index_format, offset, size)
-        }
-        fn set_vertex_buffer(
-            &mut self,
-            slot: u32,
-            buffer: &super::Buffer,
-            offset: wgt::BufferAddress,
-            size: Option<wgt::BufferSize>,
-        ) {
-            wgpu_render_bundle_set_vertex_buffer(self, slot, buffer.id, offset, size)
-        }
-
-        fn set_push_constants(&mut self, stages: wgt::ShaderStages, offset: u32, data: &[u8]) {
-            unsafe {
-                wgpu_render_bundle_set_push_constants(
-                    self,
-                    stages,
-                    offset,
-                    data.len().try_into().unwrap(),
-                    data.as_ptr(),
-                )
-            }
-        }
-        fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
-            wgpu_render_bundle_draw(
-                self,
-                vertices.end - vertices.start,
-                instances.end - instances.start,
-                vertices.start,
-                instances.start,
-            )
-        }
-        fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
-            wgpu_render_bundle_draw_indexed(
-                self,
-                indices.end - indices.start,
-                instances.end - instances.start,
-                indices.start,
-                base_vertex,
-                instances.start,
-            )
-        }
-        fn draw_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-        ) {
-            wgpu_render_bundle_draw_indirect(self, indirect_buffer.id, indirect_offset)
-        }
-        fn draw_indexed_indirect(
-            &mut self,
-            indirect_buffer: &super::Buffer,
-            indirect_offset: wgt::BufferAddress,
-        ) {
-            wgpu_render_bundle_draw_indexed_indirect(self, indirect_buffer.id, indirect_offset)
-        }
-        fn multi_draw_indirect(
-            &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count: u32,
-        ) {
-            unimplemented!()
-        }
-        fn multi_draw_indexed_indirect(
-            &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count: u32,
-        ) {
-            unimplemented!()
-        }
-        fn multi_draw_indirect_count(
-            &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count_buffer: &super::Buffer,
-            _count_buffer_offset: wgt::BufferAddress,
-            _max_count: u32,
-        ) {
-            unimplemented!()
-        }
-        fn multi_draw_indexed_indirect_count(
-            &mut self,
-            _indirect_buffer: &super::Buffer,
-            _indirect_offset: wgt::BufferAddress,
-            _count_buffer: &super::Buffer,
-            _count_buffer_offset: wgt::BufferAddress,
-            _max_count: u32,
-        ) {
-            unimplemented!()
-        }
-    }
+/// Object IDs of the crate's context are the direct ones, unless it dispatches
+/// to the remote backend too.
+#[cfg(not(all(unix, feature = "remote")))]
+fn direct_id<T>(id: &T) -> &T {
+    id
 }
 
 fn map_buffer_copy_view(view: crate::ImageCopyBuffer) -> wgc::command::ImageCopyBuffer {
     wgc::command::ImageCopyBuffer {
-        buffer: view.buffer.id.id,
+        buffer: direct_id(&view.buffer.id).id,
         layout: view.layout,
     }
 }
 
 fn map_texture_copy_view(view: crate::ImageCopyTexture) -> wgc::command::ImageCopyTexture {
     wgc::command::ImageCopyTexture {
-        texture: view.texture.id.id,
+        texture: direct_id(&view.texture.id).id,
         mip_level: view.mip_level,
         origin: view.origin,
         aspect: view.aspect,
@@ -718,6 +525,12 @@ pub struct Device {
     features: Features,
 }
 
+#[derive(Debug)]
+pub struct Queue {
+    id: wgc::id::QueueId,
+    error_sink: ErrorSink,
+}
+
 #[derive(Debug)]
 pub(crate) struct Buffer {
     id: wgc::id::BufferId,
@@ -730,6 +543,12 @@ pub struct Texture {
     error_sink: ErrorSink,
 }
 
+#[derive(Debug)]
+pub(crate) struct ShaderModule {
+    id: wgc::id::ShaderModuleId,
+    compilation_info: wgt::CompilationInfo,
+}
+
 #[derive(Debug)]
 pub(crate) struct CommandEncoder {
     id: wgc::id::CommandEncoderId,
@@ -740,8 +559,8 @@ pub(crate) struct CommandEncoder {
 impl crate::Context for Context {
     type AdapterId = wgc::id::AdapterId;
     type DeviceId = Device;
-    type QueueId = wgc::id::QueueId;
-    type ShaderModuleId = wgc::id::ShaderModuleId;
+    type QueueId = Queue;
+    type ShaderModuleId = ShaderModule;
     type BindGroupLayoutId = wgc::id::BindGroupLayoutId;
     type BindGroupId = wgc::id::BindGroupId;
     type TextureViewId = wgc::id::TextureViewId;
@@ -752,6 +571,7 @@ impl crate::Context for Context {
     type PipelineLayoutId = wgc::id::PipelineLayoutId;
     type RenderPipelineId = wgc::id::RenderPipelineId;
     type ComputePipelineId = wgc::id::ComputePipelineId;
+    type PipelineCacheId = wgc::id::PipelineCacheId;
     type CommandEncoderId = CommandEncoder;
     type ComputePassId = wgc::command::ComputePass;
     type RenderPassId = wgc::command::RenderPass;
@@ -759,9 +579,13 @@ impl crate::Context for Context {
     type RenderBundleEncoderId = wgc::command::RenderBundleEncoder;
     type RenderBundleId = wgc::id::RenderBundleId;
     type SurfaceId = Surface;
+    type SubmissionIndex = wgc::device::queue::WrappedSubmissionIndex;
 
     type SurfaceOutputDetail = SurfaceOutputDetail;
 
+    type BufferMappedRange = BufferMappedRange;
+    type QueueWriteBuffer = QueueWriteBuffer;
+
     type RequestAdapterFuture = Ready<Option<Self::AdapterId>>;
     #[allow(clippy::type_complexity)]
     type RequestDeviceFuture =
@@ -769,13 +593,18 @@ impl crate::Context for Context {
     type MapAsyncFuture = native_gpu_future::GpuFuture<Result<(), crate::BufferAsyncError>>;
     type OnSubmittedWorkDoneFuture = native_gpu_future::GpuFuture<()>;
     type PopErrorScopeFuture = Ready<Option<crate::Error>>;
+    type CompilationInfoFuture = Ready<wgt::CompilationInfo>;
+    type CreateRenderPipelineFuture =
+        native_gpu_future::GpuFuture<Result<Self::RenderPipelineId, crate::Error>>;
+    type CreateComputePipelineFuture =
+        native_gpu_future::GpuFuture<Result<Self::ComputePipelineId, crate::Error>>;
 
     fn init(backends: wgt::Backends) -> Self {
-        Self(wgc::hub::Global::new(
-            "wgpu",
-            wgc::hub::IdentityManagerFactory,
-            backends,
-        ))
+        Self(
+            wgc::hub::Global::new("wgpu", wgc::hub::IdentityManagerFactory, backends),
+            WorkerPool::default(),
+            Mutex::default(),
+        )
     }
 
     fn instance_create_surface(
@@ -796,7 +625,9 @@ impl crate::Context for Context {
             &wgc::instance::RequestAdapterOptions {
                 power_preference: options.power_preference,
                 force_fallback_adapter: options.force_fallback_adapter,
-                compatible_surface: options.compatible_surface.map(|surface| surface.id.id),
+                compatible_surface: options
+                    .compatible_surface
+                    .map(|surface| direct_id(&surface.id).id),
             },
             wgc::instance::AdapterInputs::Mask(wgt::Backends::all(), |_| PhantomData),
         );
@@ -828,12 +659,18 @@ impl crate::Context for Context {
             log::error!("Error in Adapter::request_device: {}", err);
             return ready(Err(crate::RequestDeviceError));
         }
+        let error_sink = Arc::new(Mutex::new(ErrorSinkRaw::new()));
+        self.set_lost_closure(device_id, &error_sink, None);
         let device = Device {
             id: device_id,
-            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
+            error_sink: error_sink.clone(),
             features: desc.features,
         };
-        ready(Ok((device, device_id)))
+        let queue = Queue {
+            id: device_id,
+            error_sink,
+        };
+        ready(Ok((device, queue)))
     }
 
     fn adapter_is_surface_supported(
@@ -918,7 +755,7 @@ impl crate::Context for Context {
         let error =
             wgc::gfx_select!(device.id => global.surface_configure(surface.id, device.id, config));
         if let Some(e) = error {
-            self.handle_error_fatal(e, "Surface::configure");
+            self.handle_error_nolabel(&device.error_sink, e, "Surface::configure");
         } else {
             *surface.configured_device.lock() = Some(device.id);
         }
@@ -998,6 +835,27 @@ impl crate::Context for Context {
         }
     }
 
+    fn device_get_texture_format_features(
+        &self,
+        device: &Self::DeviceId,
+        format: wgt::TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        let global = &self.0;
+        match wgc::gfx_select!(device.id => global.device_texture_format_features(device.id, format))
+        {
+            Ok(info) => info,
+            Err(err) => self.handle_error_fatal(err, "Device::get_texture_format_features"),
+        }
+    }
+
+    fn device_memory_report(&self, device: &Self::DeviceId) -> wgt::MemoryReport {
+        let global = &self.0;
+        match wgc::gfx_select!(device.id => global.device_memory_report(device.id)) {
+            Ok(report) => report,
+            Err(err) => self.handle_error_fatal(err, "Device::memory_report"),
+        }
+    }
+
     fn device_create_shader_module(
         &self,
         device: &Self::DeviceId,
@@ -1043,7 +901,9 @@ impl crate::Context for Context {
         let (id, error) = wgc::gfx_select!(
             device.id => global.device_create_shader_module(device.id, &descriptor, source, PhantomData)
         );
+        let mut compilation_info = wgt::CompilationInfo::default();
         if let Some(cause) = error {
+            compilation_info = cause.compilation_info();
             self.handle_error(
                 &device.error_sink,
                 cause,
@@ -1052,7 +912,10 @@ impl crate::Context for Context {
                 "Device::create_shader_module",
             );
         }
-        id
+        ShaderModule {
+            id,
+            compilation_info,
+        }
     }
 
     unsafe fn device_create_shader_module_spirv(
@@ -1070,7 +933,9 @@ impl crate::Context for Context {
         let (id, error) = wgc::gfx_select!(
             device.id => global.device_create_shader_module_spirv(device.id, &descriptor, Borrowed(&desc.source), PhantomData)
         );
+        let mut compilation_info = wgt::CompilationInfo::default();
         if let Some(cause) = error {
+            compilation_info = cause.compilation_info();
             self.handle_error(
                 &device.error_sink,
                 cause,
@@ -1079,7 +944,10 @@ impl crate::Context for Context {
                 "Device::create_shader_module_spirv",
             );
         }
-        id
+        ShaderModule {
+            id,
+            compilation_info,
+        }
     }
 
     fn device_create_bind_group_layout(
@@ -1136,7 +1004,7 @@ impl crate::Context for Context {
             for entry in desc.entries.iter() {
                 if let BindingResource::BufferArray(array) = entry.resource {
                     arrayed_buffer_bindings.extend(array.iter().map(|binding| bm::BufferBinding {
-                        buffer_id: binding.buffer.id.id,
+                        buffer_id: direct_id(&binding.buffer.id).id,
                         offset: binding.offset,
                         size: binding.size,
                     }));
@@ -1156,7 +1024,7 @@ impl crate::Context for Context {
                         offset,
                         size,
                     }) => bm::BindingResource::Buffer(bm::BufferBinding {
-                        buffer_id: buffer.id.id,
+                        buffer_id: direct_id(&buffer.id).id,
                         offset,
                         size,
                     }),
@@ -1281,7 +1149,7 @@ impl crate::Context for Context {
             layout: desc.layout.map(|l| l.id),
             vertex: pipe::VertexState {
                 stage: pipe::ProgrammableStageDescriptor {
-                    module: desc.vertex.module.id,
+                    module: direct_id(&desc.vertex.module.id).id,
                     entry_point: Borrowed(desc.vertex.entry_point),
                 },
                 buffers: Borrowed(&vertex_buffers),
@@ -1291,12 +1159,13 @@ impl crate::Context for Context {
             multisample: desc.multisample,
             fragment: desc.fragment.as_ref().map(|frag| pipe::FragmentState {
                 stage: pipe::ProgrammableStageDescriptor {
-                    module: frag.module.id,
+                    module: direct_id(&frag.module.id).id,
                     entry_point: Borrowed(frag.entry_point),
                 },
                 targets: Borrowed(frag.targets),
             }),
             multiview: desc.multiview,
+            cache: desc.cache.map(|c| *direct_id(&c.id)),
         };
 
         let global = &self.0;
@@ -1340,9 +1209,10 @@ impl crate::Context for Context {
             label: desc.label.map(Borrowed),
             layout: desc.layout.map(|l| l.id),
             stage: pipe::ProgrammableStageDescriptor {
-                module: desc.module.id,
+                module: direct_id(&desc.module.id).id,
                 entry_point: Borrowed(desc.entry_point),
             },
+            cache: desc.cache.map(|c| *direct_id(&c.id)),
         };
 
         let global = &self.0;
@@ -1372,6 +1242,194 @@ impl crate::Context for Context {
         id
     }
 
+    fn device_create_render_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        use wgc::pipeline as pipe;
+
+        // The descriptor is moved to a worker thread, so it can't borrow anything.
+        let descriptor = pipe::RenderPipelineDescriptor {
+            label: desc.label.map(|label| Owned(label.to_string())),
+            layout: desc.layout.map(|l| l.id),
+            vertex: pipe::VertexState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: direct_id(&desc.vertex.module.id).id,
+                    entry_point: Owned(desc.vertex.entry_point.to_string()),
+                },
+                buffers: desc
+                    .vertex
+                    .buffers
+                    .iter()
+                    .map(|vbuf| pipe::VertexBufferLayout {
+                        array_stride: vbuf.array_stride,
+                        step_mode: vbuf.step_mode,
+                        attributes: Owned(vbuf.attributes.to_vec()),
+                    })
+                    .collect(),
+            },
+            primitive: desc.primitive,
+            depth_stencil: desc.depth_stencil.clone(),
+            multisample: desc.multisample,
+            fragment: desc.fragment.as_ref().map(|frag| pipe::FragmentState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: direct_id(&frag.module.id).id,
+                    entry_point: Owned(frag.entry_point.to_string()),
+                },
+                targets: Owned(frag.targets.to_vec()),
+            }),
+            multiview: desc.multiview,
+            cache: desc.cache.map(|c| *direct_id(&c.id)),
+        };
+
+        let resources = PipelineResources {
+            shader_modules: iter::once(descriptor.vertex.stage.module)
+                .chain(descriptor.fragment.as_ref().map(|frag| frag.stage.module))
+                .collect(),
+            pipeline_layout: descriptor.layout,
+            pipeline_cache: descriptor.cache,
+        };
+        self.pin_pipeline_resources(&resources);
+
+        let device_id = device.id;
+        let (future, completion) = native_gpu_future::new_gpu_future();
+        let context = Arc::clone(&self);
+        self.1.execute(move || {
+            let implicit_pipeline_ids = match descriptor.layout {
+                Some(_) => None,
+                None => Some(wgc::device::ImplicitPipelineIds {
+                    root_id: PhantomData,
+                    group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
+                }),
+            };
+            let global = &context.0;
+            let (id, error) = wgc::gfx_select!(device_id => global.device_create_render_pipeline(
+                device_id,
+                &descriptor,
+                PhantomData,
+                implicit_pipeline_ids
+            ));
+            context.unpin_pipeline_resources(resources);
+            completion.complete(match error {
+                Some(cause) => {
+                    if let wgc::pipeline::CreateRenderPipelineError::Internal { stage, ref error } =
+                        cause
+                    {
+                        log::warn!("Shader translation error for stage {:?}: {}", stage, error);
+                        log::warn!("Please report it to https://github.com/gfx-rs/naga");
+                    }
+                    wgc::gfx_select!(id => global.render_pipeline_drop(id));
+                    Err(context.make_error(
+                        cause,
+                        LABEL,
+                        descriptor.label.as_deref(),
+                        "Device::create_render_pipeline_async",
+                    ))
+                }
+                None => Ok(id),
+            });
+        });
+        future
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        use wgc::pipeline as pipe;
+
+        // The descriptor is moved to a worker thread, so it can't borrow anything.
+        let descriptor = pipe::ComputePipelineDescriptor {
+            label: desc.label.map(|label| Owned(label.to_string())),
+            layout: desc.layout.map(|l| l.id),
+            stage: pipe::ProgrammableStageDescriptor {
+                module: direct_id(&desc.module.id).id,
+                entry_point: Owned(desc.entry_point.to_string()),
+            },
+            cache: desc.cache.map(|c| *direct_id(&c.id)),
+        };
+
+        let resources = PipelineResources {
+            shader_modules: iter::once(descriptor.stage.module).collect(),
+            pipeline_layout: descriptor.layout,
+            pipeline_cache: descriptor.cache,
+        };
+        self.pin_pipeline_resources(&resources);
+
+        let device_id = device.id;
+        let (future, completion) = native_gpu_future::new_gpu_future();
+        let context = Arc::clone(&self);
+        self.1.execute(move || {
+            let implicit_pipeline_ids = match descriptor.layout {
+                Some(_) => None,
+                None => Some(wgc::device::ImplicitPipelineIds {
+                    root_id: PhantomData,
+                    group_ids: &[PhantomData; wgc::MAX_BIND_GROUPS],
+                }),
+            };
+            let global = &context.0;
+            let (id, error) = wgc::gfx_select!(device_id => global.device_create_compute_pipeline(
+                device_id,
+                &descriptor,
+                PhantomData,
+                implicit_pipeline_ids
+            ));
+            context.unpin_pipeline_resources(resources);
+            completion.complete(match error {
+                Some(cause) => {
+                    if let wgc::pipeline::CreateComputePipelineError::Internal(ref error) = cause {
+                        log::warn!(
+                            "Shader translation error for stage {:?}: {}",
+                            wgt::ShaderStages::COMPUTE,
+                            error
+                        );
+                        log::warn!("Please report it to https://github.com/gfx-rs/naga");
+                    }
+                    wgc::gfx_select!(id => global.compute_pipeline_drop(id));
+                    Err(context.make_error(
+                        cause,
+                        LABEL,
+                        descriptor.label.as_deref(),
+                        "Device::create_compute_pipeline_async",
+                    ))
+                }
+                None => Ok(id),
+            });
+        });
+        future
+    }
+
+    unsafe fn device_create_pipeline_cache(
+        &self,
+        device: &Self::DeviceId,
+        desc: &PipelineCacheDescriptor,
+    ) -> Self::PipelineCacheId {
+        let descriptor = wgc::pipeline::PipelineCacheDescriptor {
+            label: desc.label.map(Borrowed),
+            data: desc.data.map(Borrowed),
+            fallback: desc.fallback,
+        };
+
+        let global = &self.0;
+        let (id, error) = wgc::gfx_select!(device.id => global.device_create_pipeline_cache(
+            device.id,
+            &descriptor,
+            PhantomData
+        ));
+        if let Some(cause) = error {
+            self.handle_error(
+                &device.error_sink,
+                cause,
+                LABEL,
+                desc.label,
+                "Device::create_pipeline_cache",
+            );
+        }
+        id
+    }
+
     fn device_create_buffer(
         &self,
         device: &Self::DeviceId,
@@ -1526,13 +1584,23 @@ impl crate::Context for Context {
         }
     }
 
+    fn device_destroy(&self, device: &Self::DeviceId) {
+        let global = &self.0;
+        if let Err(err) = wgc::gfx_select!(device.id => global.device_destroy(device.id)) {
+            self.handle_error_nolabel(&device.error_sink, err, "Device::destroy");
+        }
+    }
+
     #[cfg_attr(target_arch = "wasm32", allow(unused))]
     fn device_drop(&self, device: &Self::DeviceId) {
         #[cfg(not(target_arch = "wasm32"))]
         {
             let global = &self.0;
-            match wgc::gfx_select!(device.id => global.device_poll(device.id, true)) {
+            match wgc::gfx_select!(device.id => global.device_poll(device.id, wgt::Maintain::Wait))
+            {
                 Ok(()) => (),
+                // There is nothing to wait for on a lost device.
+                Err(wgc::device::WaitIdleError::Device(wgc::device::DeviceError::Lost)) => (),
                 Err(err) => self.handle_error_fatal(err, "Device::drop"),
             }
         }
@@ -1545,16 +1613,37 @@ impl crate::Context for Context {
         }
     }
 
-    fn device_poll(&self, device: &Self::DeviceId, maintain: crate::Maintain) {
-        let global = &self.0;
-        match wgc::gfx_select!(device.id => global.device_poll(
-            device.id,
-            match maintain {
-                crate::Maintain::Poll => false,
-                crate::Maintain::Wait => true,
+    fn device_poll(
+        &self,
+        device: &Self::DeviceId,
+        maintain: crate::Maintain,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), crate::PollError> {
+        use wgc::device::{DeviceError, WaitIdleError};
+
+        let global = &self.0;
+        let maintain_inner = maintain.map_index(|index| *direct_id(&index.0));
+        let result = match timeout {
+            Some(timeout) => {
+                let timeout_ms = timeout.as_millis().min(u32::MAX as u128) as u32;
+                wgc::gfx_select!(device.id => global.device_poll_timeout(
+                    device.id,
+                    maintain_inner,
+                    timeout_ms
+                ))
+            }
+            None => wgc::gfx_select!(device.id => global.device_poll(device.id, maintain_inner)),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            Err(WaitIdleError::Timeout) => Err(crate::PollError::Timeout),
+            Err(WaitIdleError::StuckGpu) | Err(WaitIdleError::Device(DeviceError::Lost)) => {
+                Err(crate::PollError::DeviceLost)
+            }
+            Err(err @ WaitIdleError::WrongSubmissionIndex(..)) => {
+                self.handle_error_nolabel(&device.error_sink, err, "Device::poll");
+                Ok(())
             }
-        )) {
-            Ok(()) => (),
             Err(err) => self.handle_error_fatal(err, "Device::poll"),
         }
     }
@@ -1568,6 +1657,14 @@ impl crate::Context for Context {
         error_sink.uncaptured_handler = Box::new(handler);
     }
 
+    fn device_on_device_lost(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        self.set_lost_closure(device.id, &device.error_sink, Some(Box::new(callback)));
+    }
+
     fn device_push_error_scope(&self, device: &Self::DeviceId, filter: crate::ErrorFilter) {
         let mut error_sink = device.error_sink.lock();
         error_sink.scopes.push(ErrorScope {
@@ -1696,7 +1793,9 @@ impl crate::Context for Context {
         let global = &self.0;
         match wgc::gfx_select!(buffer.id => global.buffer_destroy(buffer.id)) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Buffer::destroy"),
+            // Destroying twice is fine, the device might have been destroyed already.
+            Err(wgc::resource::DestroyError::AlreadyDestroyed) => (),
+            Err(err) => self.handle_error_nolabel(&buffer.error_sink, err, "Buffer::destroy"),
         }
     }
     fn buffer_drop(&self, buffer: &Self::BufferId) {
@@ -1707,7 +1806,9 @@ impl crate::Context for Context {
         let global = &self.0;
         match wgc::gfx_select!(texture.id => global.texture_destroy(texture.id)) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Texture::destroy"),
+            // Destroying twice is fine, the device might have been destroyed already.
+            Err(wgc::resource::DestroyError::AlreadyDestroyed) => (),
+            Err(err) => self.handle_error_nolabel(&texture.error_sink, err, "Texture::destroy"),
         }
     }
     fn texture_drop(&self, texture: &Self::TextureId) {
@@ -1738,12 +1839,42 @@ impl crate::Context for Context {
         wgc::gfx_select!(*bind_group_layout => global.bind_group_layout_drop(*bind_group_layout))
     }
     fn pipeline_layout_drop(&self, pipeline_layout: &Self::PipelineLayoutId) {
+        if !self.2.lock().pipeline_layouts.release(*pipeline_layout) {
+            return;
+        }
         let global = &self.0;
         wgc::gfx_select!(*pipeline_layout => global.pipeline_layout_drop(*pipeline_layout))
     }
     fn shader_module_drop(&self, shader_module: &Self::ShaderModuleId) {
+        if !self.2.lock().shader_modules.release(shader_module.id) {
+            return;
+        }
+        let global = &self.0;
+        wgc::gfx_select!(shader_module.id => global.shader_module_drop(shader_module.id))
+    }
+    fn pipeline_cache_drop(&self, cache: &Self::PipelineCacheId) {
+        if !self.2.lock().pipeline_caches.release(*cache) {
+            return;
+        }
+        let global = &self.0;
+        wgc::gfx_select!(*cache => global.pipeline_cache_drop(*cache))
+    }
+    fn pipeline_cache_get_data(&self, cache: &Self::PipelineCacheId) -> Option<Vec<u8>> {
+        let global = &self.0;
+        wgc::gfx_select!(*cache => global.pipeline_cache_get_data(*cache))
+    }
+    fn shader_module_reflect(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
         let global = &self.0;
-        wgc::gfx_select!(*shader_module => global.shader_module_drop(*shader_module))
+        wgc::gfx_select!(shader_module.id => global.shader_module_reflect(shader_module.id))
+    }
+    fn shader_module_get_compilation_info(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Self::CompilationInfoFuture {
+        ready(shader_module.compilation_info.clone())
     }
     fn command_encoder_drop(&self, command_encoder: &Self::CommandEncoderId) {
         if command_encoder.open {
@@ -2038,7 +2169,7 @@ impl crate::Context for Context {
         let global = &self.0;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_clear_texture(
             encoder.id,
-            texture.id.id,
+            direct_id(&texture.id).id,
             subresource_range
         )) {
             self.handle_error_nolabel(&encoder.error_sink, cause, "CommandEncoder::clear_texture");
@@ -2055,7 +2186,7 @@ impl crate::Context for Context {
         let global = &self.0;
         if let Err(cause) = wgc::gfx_select!(encoder.id => global.command_encoder_clear_buffer(
             encoder.id,
-            buffer.id.id,
+            direct_id(&buffer.id).id,
             offset, size
         )) {
             self.handle_error_nolabel(&encoder.error_sink, cause, "CommandEncoder::fill_buffer");
@@ -2123,10 +2254,75 @@ impl crate::Context for Context {
     ) {
         let global = &self.0;
         match wgc::gfx_select!(
-            *queue => global.queue_write_buffer(*queue, buffer.id, offset, data)
+            queue.id => global.queue_write_buffer(queue.id, buffer.id, offset, data)
+        ) {
+            Ok(()) => (),
+            Err(err) => self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer"),
+        }
+    }
+
+    fn queue_validate_write_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    ) -> Option<()> {
+        let global = &self.0;
+        match wgc::gfx_select!(
+            queue.id => global.queue_validate_write_buffer(queue.id, buffer.id, offset, size.get())
+        ) {
+            Ok(()) => Some(()),
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer_with");
+                None
+            }
+        }
+    }
+
+    fn queue_create_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        size: wgt::BufferSize,
+    ) -> Option<QueueWriteBuffer> {
+        let global = &self.0;
+        match wgc::gfx_select!(
+            queue.id => global.queue_create_staging_buffer(queue.id, size, PhantomData)
+        ) {
+            Ok((buffer_id, ptr)) => Some(QueueWriteBuffer {
+                buffer_id,
+                mapping: BufferMappedRange {
+                    ptr,
+                    size: size.get() as usize,
+                },
+            }),
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer_with");
+                None
+            }
+        }
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    ) {
+        let global = &self.0;
+        match wgc::gfx_select!(
+            queue.id => global.queue_write_staging_buffer(
+                queue.id,
+                buffer.id,
+                offset,
+                staging_buffer.buffer_id
+            )
         ) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::write_buffer"),
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer_with")
+            }
         }
     }
 
@@ -2139,15 +2335,15 @@ impl crate::Context for Context {
         size: wgt::Extent3d,
     ) {
         let global = &self.0;
-        match wgc::gfx_select!(*queue => global.queue_write_texture(
-            *queue,
+        match wgc::gfx_select!(queue.id => global.queue_write_texture(
+            queue.id,
             &map_texture_copy_view(texture),
             data,
             &data_layout,
             &size
         )) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::write_texture"),
+            Err(err) => self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_texture"),
         }
     }
 
@@ -2155,20 +2351,27 @@ impl crate::Context for Context {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    ) {
+    ) -> Self::SubmissionIndex {
         let temp_command_buffers = command_buffers.collect::<SmallVec<[_; 4]>>();
 
         let global = &self.0;
-        match wgc::gfx_select!(*queue => global.queue_submit(*queue, &temp_command_buffers)) {
-            Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::submit"),
+        match wgc::gfx_select!(queue.id => global.queue_submit(queue.id, &temp_command_buffers)) {
+            Ok(index) => index,
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::submit");
+                // Nothing was submitted, so waiting for the index is the same as waiting for idle.
+                wgc::device::queue::WrappedSubmissionIndex {
+                    queue_id: queue.id,
+                    index: 0,
+                }
+            }
         }
     }
 
     fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32 {
         let global = &self.0;
-        let res = wgc::gfx_select!(queue => global.queue_get_timestamp_period(
-            *queue
+        let res = wgc::gfx_select!(queue.id => global.queue_get_timestamp_period(
+            queue.id
         ));
         match res {
             Ok(v) => v,
@@ -2196,9 +2399,10 @@ impl crate::Context for Context {
         };
 
         let global = &self.0;
-        let res = wgc::gfx_select!(queue => global.queue_on_submitted_work_done(*queue, closure));
+        let res =
+            wgc::gfx_select!(queue.id => global.queue_on_submitted_work_done(queue.id, closure));
         if let Err(cause) = res {
-            self.handle_error_fatal(cause, "Queue::on_submitted_work_done");
+            self.handle_error_nolabel(&queue.error_sink, cause, "Queue::on_submitted_work_done");
         }
         future
     }
@@ -2229,6 +2433,9 @@ struct ErrorScope {
 struct ErrorSinkRaw {
     scopes: Vec<ErrorScope>,
     uncaptured_handler: Box<dyn crate::UncapturedErrorHandler>,
+    /// Set once the device is lost. The lost callback reports the loss, so the
+    /// errors that follow it only reach the error scopes.
+    device_lost: bool,
 }
 
 impl ErrorSinkRaw {
@@ -2236,6 +2443,7 @@ impl ErrorSinkRaw {
         ErrorSinkRaw {
             scopes: Vec::new(),
             uncaptured_handler: Box::from(default_error_handler),
+            device_lost: false,
         }
     }
 
@@ -2255,6 +2463,9 @@ impl ErrorSinkRaw {
                     scope.error = Some(err);
                 }
             }
+            None if self.device_lost => {
+                log::debug!("Error on a lost device: {}", err);
+            }
             None => {
                 (self.uncaptured_handler)(err);
             }
@@ -2298,3 +2509,19 @@ impl Drop for BufferMappedRange {
         // implements `Drop`, to match the web backend
     }
 }
+
+#[derive(Debug)]
+pub struct QueueWriteBuffer {
+    buffer_id: wgc::id::StagingBufferId,
+    mapping: BufferMappedRange,
+}
+
+impl crate::BufferMappedRangeSlice for QueueWriteBuffer {
+    fn slice(&self) -> &[u8] {
+        self.mapping.slice()
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        self.mapping.slice_mut()
+    }
+}
@@ -0,0 +1,1161 @@
+//! Context picking the direct or the remote backend at runtime, depending on
+//! whether the instance was created with `Instance::new` or `Instance::connect`.
+//!
+//! Objects shared by both backends, like the `wgpu-core` IDs, are passed as they
+//! are. The others are an [`Id`], which says which backend they come from.
+
+use super::{direct, remote};
+use crate::{
+    AdapterInfo, BindGroupDescriptor, BindGroupLayoutDescriptor, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipelineDescriptor, DownlevelCapabilities, Features, Limits,
+    MapMode, PipelineCacheDescriptor, PipelineLayoutDescriptor, RenderBundleDescriptor,
+    RenderBundleEncoderDescriptor, RenderPipelineDescriptor, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderModuleDescriptorSpirV, SurfaceStatus, TextureDescriptor,
+    TextureFormat, TextureViewDescriptor,
+};
+
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut, Range},
+    os::unix::net::UnixStream,
+    pin::Pin,
+    sync::Arc,
+};
+
+#[derive(Debug)]
+pub enum Context {
+    Direct(Arc<direct::Context>),
+    Remote(Arc<remote::Context>),
+}
+
+/// Object of the direct or of the remote backend.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Id<D, R> {
+    Direct(D),
+    Remote(R),
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Object type of the context, which differs between the backends.
+macro_rules! either {
+    ($name:ident) => {
+        Id<<direct::Context as crate::Context>::$name, <remote::Context as crate::Context>::$name>
+    };
+}
+
+/// Evaluate `$body` with the backend `$context` of `$self`.
+///
+/// Within `$body`, `$id` gets the object of the backend out of an [`Id`], and
+/// `$wrap` makes an [`Id`] out of it.
+macro_rules! dispatch {
+    ($self:expr => |$context:ident, $id:ident, $wrap:ident| $body:expr) => {
+        match *$self {
+            Context::Direct(ref $context) => {
+                #[allow(unused_imports)]
+                use self::{direct_id as $id, Id::Direct as $wrap};
+                $body
+            }
+            Context::Remote(ref $context) => {
+                #[allow(unused_imports)]
+                use self::{remote_id as $id, Id::Remote as $wrap};
+                $body
+            }
+        }
+    };
+}
+
+fn mismatch() -> ! {
+    panic!("Objects of a direct and of a remote instance can't be used together")
+}
+
+pub(super) fn direct_id<D, R>(id: &Id<D, R>) -> &D {
+    match *id {
+        Id::Direct(ref id) => id,
+        Id::Remote(_) => mismatch(),
+    }
+}
+
+pub(super) fn remote_id<D, R>(id: &Id<D, R>) -> &R {
+    match *id {
+        Id::Direct(_) => mismatch(),
+        Id::Remote(ref id) => id,
+    }
+}
+
+impl<D, R> Id<D, R> {
+    fn into_direct(self) -> D {
+        match self {
+            Self::Direct(id) => id,
+            Self::Remote(_) => mismatch(),
+        }
+    }
+
+    fn into_remote(self) -> R {
+        match self {
+            Self::Direct(_) => mismatch(),
+            Self::Remote(id) => id,
+        }
+    }
+}
+
+impl<D: crate::BufferMappedRangeSlice, R: crate::BufferMappedRangeSlice>
+    crate::BufferMappedRangeSlice for Id<D, R>
+{
+    fn slice(&self) -> &[u8] {
+        match *self {
+            Self::Direct(ref mapping) => mapping.slice(),
+            Self::Remote(ref mapping) => mapping.slice(),
+        }
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        match *self {
+            Self::Direct(ref mut mapping) => mapping.slice_mut(),
+            Self::Remote(ref mut mapping) => mapping.slice_mut(),
+        }
+    }
+}
+
+/// Pass recorded with a `wgpu-core` encoder, like in both backends.
+///
+/// It is wrapped so that the encoding has an implementation for this context
+/// alone, and the object types of the pass don't need to be spelled out.
+#[derive(Debug)]
+pub(crate) struct Pass<P>(P);
+
+impl<P> Deref for Pass<P> {
+    type Target = P;
+
+    fn deref(&self) -> &P {
+        &self.0
+    }
+}
+
+impl<P> DerefMut for Pass<P> {
+    fn deref_mut(&mut self) -> &mut P {
+        &mut self.0
+    }
+}
+
+type Buffer = either!(BufferId);
+type ComputePass = Pass<wgc::command::ComputePass>;
+type RenderPass = Pass<wgc::command::RenderPass>;
+type RenderBundleEncoder = Pass<wgc::command::RenderBundleEncoder>;
+
+#[path = "pass_impl.rs"]
+mod pass_impl;
+
+fn buffer_id(buffer: &Buffer) -> wgc::id::BufferId {
+    match *buffer {
+        Id::Direct(ref buffer) => direct::buffer_id(buffer),
+        Id::Remote(ref buffer) => remote::buffer_id(buffer),
+    }
+}
+
+impl Context {
+    pub fn connect(stream: UnixStream) -> Self {
+        Self::Remote(Arc::new(remote::Context::connect(stream)))
+    }
+
+    /// Get the direct backend, for what only it supports.
+    fn direct(&self) -> &direct::Context {
+        match *self {
+            Self::Direct(ref context) => context,
+            Self::Remote(_) => panic!("This is not supported by the remote backend"),
+        }
+    }
+
+    pub unsafe fn from_hal_instance<A: wgc::hub::HalApi>(hal_instance: A::Instance) -> Self {
+        Self::Direct(Arc::new(direct::Context::from_hal_instance::<A>(
+            hal_instance,
+        )))
+    }
+
+    pub fn enumerate_adapters(&self, backends: wgt::Backends) -> Vec<either!(AdapterId)> {
+        dispatch!(self => |context, id, wrap| {
+            context.enumerate_adapters(backends).into_iter().map(wrap).collect()
+        })
+    }
+
+    pub unsafe fn create_adapter_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_adapter: hal::ExposedAdapter<A>,
+    ) -> either!(AdapterId) {
+        Id::Direct(self.direct().create_adapter_from_hal(hal_adapter))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub unsafe fn create_device_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        adapter: &either!(AdapterId),
+        hal_device: hal::OpenDevice<A>,
+        desc: &crate::DeviceDescriptor,
+        trace_dir: Option<&std::path::Path>,
+    ) -> Result<(either!(DeviceId), either!(QueueId)), crate::RequestDeviceError> {
+        self.direct()
+            .create_device_from_hal(direct_id(adapter), hal_device, desc, trace_dir)
+            .map(|(device, queue)| (Id::Direct(device), Id::Direct(queue)))
+    }
+
+    pub unsafe fn create_texture_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_texture: A::Texture,
+        device: &either!(DeviceId),
+        desc: &TextureDescriptor,
+    ) -> either!(TextureId) {
+        Id::Direct(
+            self.direct()
+                .create_texture_from_hal::<A>(hal_texture, direct_id(device), desc),
+        )
+    }
+
+    pub unsafe fn create_buffer_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        device: &either!(DeviceId),
+        desc: &crate::BufferDescriptor,
+    ) -> either!(BufferId) {
+        Id::Direct(
+            self.direct()
+                .create_buffer_from_hal::<A>(hal_buffer, direct_id(device), desc),
+        )
+    }
+
+    /// The objects of the remote backend have no hal counterpart, so
+    /// `hal_device_callback` gets `None` for them, like for the other `*_as_hal`.
+    pub unsafe fn device_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Device>) -> R, R>(
+        &self,
+        device: &either!(DeviceId),
+        hal_device_callback: F,
+    ) -> R {
+        match *self {
+            Self::Direct(ref context) => {
+                context.device_as_hal::<A, F, R>(direct_id(device), hal_device_callback)
+            }
+            Self::Remote(_) => hal_device_callback(None),
+        }
+    }
+
+    pub unsafe fn texture_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Texture>)>(
+        &self,
+        texture: &either!(TextureId),
+        hal_texture_callback: F,
+    ) {
+        match *self {
+            Self::Direct(ref context) => {
+                context.texture_as_hal::<A, F>(direct_id(texture), hal_texture_callback)
+            }
+            Self::Remote(_) => hal_texture_callback(None),
+        }
+    }
+
+    pub unsafe fn buffer_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Buffer>)>(
+        &self,
+        buffer: &either!(BufferId),
+        hal_buffer_callback: F,
+    ) {
+        match *self {
+            Self::Direct(ref context) => {
+                context.buffer_as_hal::<A, F>(direct_id(buffer), hal_buffer_callback)
+            }
+            Self::Remote(_) => hal_buffer_callback(None),
+        }
+    }
+
+    pub unsafe fn texture_view_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::TextureView>)>(
+        &self,
+        texture_view: &wgc::id::TextureViewId,
+        hal_texture_view_callback: F,
+    ) {
+        match *self {
+            Self::Direct(ref context) => {
+                context.texture_view_as_hal::<A, F>(texture_view, hal_texture_view_callback)
+            }
+            Self::Remote(_) => hal_texture_view_callback(None),
+        }
+    }
+
+    pub unsafe fn command_encoder_as_hal_mut<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &self,
+        encoder: &either!(CommandEncoderId),
+        hal_command_encoder_callback: F,
+    ) -> R {
+        match *self {
+            Self::Direct(ref context) => context.command_encoder_as_hal_mut::<A, F, R>(
+                direct_id(encoder),
+                hal_command_encoder_callback,
+            ),
+            Self::Remote(_) => hal_command_encoder_callback(None),
+        }
+    }
+
+    pub fn generate_report(&self) -> wgc::hub::GlobalReport {
+        self.direct().generate_report()
+    }
+
+    #[cfg(any(target_os = "ios", target_os = "macos"))]
+    pub unsafe fn create_surface_from_core_animation_layer(
+        &self,
+        layer: *mut std::ffi::c_void,
+    ) -> either!(SurfaceId) {
+        Id::Direct(
+            self.direct()
+                .create_surface_from_core_animation_layer(layer),
+        )
+    }
+}
+
+// Surfaces, pipeline caches and submission indices of the remote backend are `()`.
+#[allow(clippy::unit_arg)]
+impl crate::Context for Context {
+    type AdapterId = either!(AdapterId);
+    type DeviceId = either!(DeviceId);
+    type QueueId = either!(QueueId);
+    type ShaderModuleId = either!(ShaderModuleId);
+    type BindGroupLayoutId = wgc::id::BindGroupLayoutId;
+    type BindGroupId = wgc::id::BindGroupId;
+    type TextureViewId = wgc::id::TextureViewId;
+    type SamplerId = wgc::id::SamplerId;
+    type QuerySetId = wgc::id::QuerySetId;
+    type BufferId = Buffer;
+    type TextureId = either!(TextureId);
+    type PipelineLayoutId = wgc::id::PipelineLayoutId;
+    type RenderPipelineId = wgc::id::RenderPipelineId;
+    type ComputePipelineId = wgc::id::ComputePipelineId;
+    type PipelineCacheId = either!(PipelineCacheId);
+    type CommandEncoderId = either!(CommandEncoderId);
+    type ComputePassId = ComputePass;
+    type RenderPassId = RenderPass;
+    type CommandBufferId = either!(CommandBufferId);
+    type RenderBundleEncoderId = RenderBundleEncoder;
+    type RenderBundleId = wgc::id::RenderBundleId;
+    type SurfaceId = either!(SurfaceId);
+    type SubmissionIndex = either!(SubmissionIndex);
+
+    type SurfaceOutputDetail = either!(SurfaceOutputDetail);
+
+    type BufferMappedRange = either!(BufferMappedRange);
+    type QueueWriteBuffer = either!(QueueWriteBuffer);
+
+    type RequestAdapterFuture = BoxFuture<Option<Self::AdapterId>>;
+    type RequestDeviceFuture =
+        BoxFuture<Result<(Self::DeviceId, Self::QueueId), crate::RequestDeviceError>>;
+    type MapAsyncFuture = BoxFuture<Result<(), crate::BufferAsyncError>>;
+    type OnSubmittedWorkDoneFuture = BoxFuture<()>;
+    type PopErrorScopeFuture = BoxFuture<Option<crate::Error>>;
+    type CompilationInfoFuture = BoxFuture<wgt::CompilationInfo>;
+    type CreateRenderPipelineFuture = BoxFuture<Result<Self::RenderPipelineId, crate::Error>>;
+    type CreateComputePipelineFuture = BoxFuture<Result<Self::ComputePipelineId, crate::Error>>;
+
+    fn init(backends: wgt::Backends) -> Self {
+        Self::Direct(Arc::new(direct::Context::init(backends)))
+    }
+
+    fn instance_create_surface(
+        &self,
+        handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Self::SurfaceId {
+        dispatch!(self => |context, id, wrap| wrap(context.instance_create_surface(handle)))
+    }
+
+    fn instance_request_adapter(
+        &self,
+        options: &crate::RequestAdapterOptions,
+    ) -> Self::RequestAdapterFuture {
+        dispatch!(self => |context, id, wrap| {
+            let adapter = context.instance_request_adapter(options);
+            Box::pin(async move { adapter.await.map(wrap) })
+        })
+    }
+
+    fn instance_poll_all_devices(&self, force_wait: bool) {
+        dispatch!(self => |context, id, wrap| context.instance_poll_all_devices(force_wait))
+    }
+
+    fn adapter_request_device(
+        &self,
+        adapter: &Self::AdapterId,
+        desc: &crate::DeviceDescriptor,
+        trace_dir: Option<&std::path::Path>,
+    ) -> Self::RequestDeviceFuture {
+        dispatch!(self => |context, id, wrap| {
+            let device = context.adapter_request_device(id(adapter), desc, trace_dir);
+            Box::pin(async move {
+                device
+                    .await
+                    .map(|(device, queue)| (wrap(device), wrap(queue)))
+            })
+        })
+    }
+
+    fn adapter_is_surface_supported(
+        &self,
+        adapter: &Self::AdapterId,
+        surface: &Self::SurfaceId,
+    ) -> bool {
+        dispatch!(self => |context, id, wrap| {
+            context.adapter_is_surface_supported(id(adapter), id(surface))
+        })
+    }
+
+    fn adapter_features(&self, adapter: &Self::AdapterId) -> Features {
+        dispatch!(self => |context, id, wrap| context.adapter_features(id(adapter)))
+    }
+
+    fn adapter_limits(&self, adapter: &Self::AdapterId) -> Limits {
+        dispatch!(self => |context, id, wrap| context.adapter_limits(id(adapter)))
+    }
+
+    fn adapter_downlevel_properties(&self, adapter: &Self::AdapterId) -> DownlevelCapabilities {
+        dispatch!(self => |context, id, wrap| context.adapter_downlevel_properties(id(adapter)))
+    }
+
+    fn adapter_get_info(&self, adapter: &Self::AdapterId) -> AdapterInfo {
+        dispatch!(self => |context, id, wrap| context.adapter_get_info(id(adapter)))
+    }
+
+    fn adapter_get_texture_format_features(
+        &self,
+        adapter: &Self::AdapterId,
+        format: TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        dispatch!(self => |context, id, wrap| {
+            context.adapter_get_texture_format_features(id(adapter), format)
+        })
+    }
+
+    fn surface_get_preferred_format(
+        &self,
+        surface: &Self::SurfaceId,
+        adapter: &Self::AdapterId,
+    ) -> Option<TextureFormat> {
+        dispatch!(self => |context, id, wrap| {
+            context.surface_get_preferred_format(id(surface), id(adapter))
+        })
+    }
+
+    fn surface_configure(
+        &self,
+        surface: &Self::SurfaceId,
+        device: &Self::DeviceId,
+        config: &wgt::SurfaceConfiguration,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.surface_configure(id(surface), id(device), config)
+        })
+    }
+
+    fn surface_get_current_texture(
+        &self,
+        surface: &Self::SurfaceId,
+    ) -> (
+        Option<Self::TextureId>,
+        SurfaceStatus,
+        Self::SurfaceOutputDetail,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            let (texture, status, detail) = context.surface_get_current_texture(id(surface));
+            (texture.map(wrap), status, wrap(detail))
+        })
+    }
+
+    fn surface_present(&self, texture: &Self::TextureId, detail: &Self::SurfaceOutputDetail) {
+        dispatch!(self => |context, id, wrap| context.surface_present(id(texture), id(detail)))
+    }
+
+    fn surface_texture_discard(
+        &self,
+        texture: &Self::TextureId,
+        detail: &Self::SurfaceOutputDetail,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.surface_texture_discard(id(texture), id(detail))
+        })
+    }
+
+    fn device_features(&self, device: &Self::DeviceId) -> Features {
+        dispatch!(self => |context, id, wrap| context.device_features(id(device)))
+    }
+
+    fn device_limits(&self, device: &Self::DeviceId) -> Limits {
+        dispatch!(self => |context, id, wrap| context.device_limits(id(device)))
+    }
+
+    fn device_downlevel_properties(&self, device: &Self::DeviceId) -> DownlevelCapabilities {
+        dispatch!(self => |context, id, wrap| context.device_downlevel_properties(id(device)))
+    }
+
+    fn device_get_texture_format_features(
+        &self,
+        device: &Self::DeviceId,
+        format: TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        dispatch!(self => |context, id, wrap| {
+            context.device_get_texture_format_features(id(device), format)
+        })
+    }
+
+    fn device_memory_report(&self, device: &Self::DeviceId) -> wgt::MemoryReport {
+        dispatch!(self => |context, id, wrap| context.device_memory_report(id(device)))
+    }
+
+    fn device_create_shader_module(
+        &self,
+        device: &Self::DeviceId,
+        desc: &ShaderModuleDescriptor,
+        shader_bound_checks: wgt::ShaderBoundChecks,
+    ) -> Self::ShaderModuleId {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.device_create_shader_module(id(device), desc, shader_bound_checks))
+        })
+    }
+
+    unsafe fn device_create_shader_module_spirv(
+        &self,
+        device: &Self::DeviceId,
+        desc: &ShaderModuleDescriptorSpirV,
+    ) -> Self::ShaderModuleId {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.device_create_shader_module_spirv(id(device), desc))
+        })
+    }
+
+    fn device_create_bind_group_layout(
+        &self,
+        device: &Self::DeviceId,
+        desc: &BindGroupLayoutDescriptor,
+    ) -> Self::BindGroupLayoutId {
+        dispatch!(self => |context, id, wrap| {
+            context.device_create_bind_group_layout(id(device), desc)
+        })
+    }
+
+    fn device_create_bind_group(
+        &self,
+        device: &Self::DeviceId,
+        desc: &BindGroupDescriptor,
+    ) -> Self::BindGroupId {
+        dispatch!(self => |context, id, wrap| context.device_create_bind_group(id(device), desc))
+    }
+
+    fn device_create_pipeline_layout(
+        &self,
+        device: &Self::DeviceId,
+        desc: &PipelineLayoutDescriptor,
+    ) -> Self::PipelineLayoutId {
+        dispatch!(self => |context, id, wrap| {
+            context.device_create_pipeline_layout(id(device), desc)
+        })
+    }
+
+    fn device_create_render_pipeline(
+        &self,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::RenderPipelineId {
+        dispatch!(self => |context, id, wrap| {
+            context.device_create_render_pipeline(id(device), desc)
+        })
+    }
+
+    fn device_create_compute_pipeline(
+        &self,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::ComputePipelineId {
+        dispatch!(self => |context, id, wrap| {
+            context.device_create_compute_pipeline(id(device), desc)
+        })
+    }
+
+    fn device_create_render_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(Arc::clone(context).device_create_render_pipeline_async(id(device), desc))
+        })
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(Arc::clone(context).device_create_compute_pipeline_async(id(device), desc))
+        })
+    }
+
+    unsafe fn device_create_pipeline_cache(
+        &self,
+        device: &Self::DeviceId,
+        desc: &PipelineCacheDescriptor,
+    ) -> Self::PipelineCacheId {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.device_create_pipeline_cache(id(device), desc))
+        })
+    }
+
+    fn device_create_buffer(
+        &self,
+        device: &Self::DeviceId,
+        desc: &crate::BufferDescriptor,
+    ) -> Self::BufferId {
+        dispatch!(self => |context, id, wrap| wrap(context.device_create_buffer(id(device), desc)))
+    }
+
+    fn device_create_texture(
+        &self,
+        device: &Self::DeviceId,
+        desc: &TextureDescriptor,
+    ) -> Self::TextureId {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.device_create_texture(id(device), desc))
+        })
+    }
+
+    fn device_create_sampler(
+        &self,
+        device: &Self::DeviceId,
+        desc: &SamplerDescriptor,
+    ) -> Self::SamplerId {
+        dispatch!(self => |context, id, wrap| context.device_create_sampler(id(device), desc))
+    }
+
+    fn device_create_query_set(
+        &self,
+        device: &Self::DeviceId,
+        desc: &crate::QuerySetDescriptor,
+    ) -> Self::QuerySetId {
+        dispatch!(self => |context, id, wrap| context.device_create_query_set(id(device), desc))
+    }
+
+    fn device_create_command_encoder(
+        &self,
+        device: &Self::DeviceId,
+        desc: &CommandEncoderDescriptor,
+    ) -> Self::CommandEncoderId {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.device_create_command_encoder(id(device), desc))
+        })
+    }
+
+    fn device_create_render_bundle_encoder(
+        &self,
+        device: &Self::DeviceId,
+        desc: &RenderBundleEncoderDescriptor,
+    ) -> Self::RenderBundleEncoderId {
+        dispatch!(self => |context, id, wrap| {
+            Pass(context.device_create_render_bundle_encoder(id(device), desc))
+        })
+    }
+
+    fn device_destroy(&self, device: &Self::DeviceId) {
+        dispatch!(self => |context, id, wrap| context.device_destroy(id(device)))
+    }
+
+    fn device_drop(&self, device: &Self::DeviceId) {
+        dispatch!(self => |context, id, wrap| context.device_drop(id(device)))
+    }
+
+    fn device_poll(
+        &self,
+        device: &Self::DeviceId,
+        maintain: crate::Maintain,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), crate::PollError> {
+        dispatch!(self => |context, id, wrap| context.device_poll(id(device), maintain, timeout))
+    }
+
+    fn device_on_uncaptured_error(
+        &self,
+        device: &Self::DeviceId,
+        handler: impl crate::UncapturedErrorHandler,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.device_on_uncaptured_error(id(device), handler)
+        })
+    }
+
+    fn device_on_device_lost(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        dispatch!(self => |context, id, wrap| context.device_on_device_lost(id(device), callback))
+    }
+
+    fn device_push_error_scope(&self, device: &Self::DeviceId, filter: crate::ErrorFilter) {
+        dispatch!(self => |context, id, wrap| context.device_push_error_scope(id(device), filter))
+    }
+
+    fn device_pop_error_scope(&self, device: &Self::DeviceId) -> Self::PopErrorScopeFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(context.device_pop_error_scope(id(device)))
+        })
+    }
+
+    fn buffer_map_async(
+        &self,
+        buffer: &Self::BufferId,
+        mode: MapMode,
+        range: Range<wgt::BufferAddress>,
+    ) -> Self::MapAsyncFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(context.buffer_map_async(id(buffer), mode, range))
+        })
+    }
+
+    fn buffer_get_mapped_range(
+        &self,
+        buffer: &Self::BufferId,
+        sub_range: Range<wgt::BufferAddress>,
+    ) -> Self::BufferMappedRange {
+        dispatch!(self => |context, id, wrap| {
+            wrap(context.buffer_get_mapped_range(id(buffer), sub_range))
+        })
+    }
+
+    fn buffer_unmap(&self, buffer: &Self::BufferId) {
+        dispatch!(self => |context, id, wrap| context.buffer_unmap(id(buffer)))
+    }
+
+    fn texture_create_view(
+        &self,
+        texture: &Self::TextureId,
+        desc: &TextureViewDescriptor,
+    ) -> Self::TextureViewId {
+        dispatch!(self => |context, id, wrap| context.texture_create_view(id(texture), desc))
+    }
+
+    fn surface_drop(&self, surface: &Self::SurfaceId) {
+        dispatch!(self => |context, id, wrap| context.surface_drop(id(surface)))
+    }
+
+    fn adapter_drop(&self, adapter: &Self::AdapterId) {
+        dispatch!(self => |context, id, wrap| context.adapter_drop(id(adapter)))
+    }
+
+    fn buffer_destroy(&self, buffer: &Self::BufferId) {
+        dispatch!(self => |context, id, wrap| context.buffer_destroy(id(buffer)))
+    }
+
+    fn buffer_drop(&self, buffer: &Self::BufferId) {
+        dispatch!(self => |context, id, wrap| context.buffer_drop(id(buffer)))
+    }
+
+    fn texture_destroy(&self, texture: &Self::TextureId) {
+        dispatch!(self => |context, id, wrap| context.texture_destroy(id(texture)))
+    }
+
+    fn texture_drop(&self, texture: &Self::TextureId) {
+        dispatch!(self => |context, id, wrap| context.texture_drop(id(texture)))
+    }
+
+    fn texture_view_drop(&self, texture_view: &Self::TextureViewId) {
+        dispatch!(self => |context, id, wrap| context.texture_view_drop(texture_view))
+    }
+
+    fn sampler_drop(&self, sampler: &Self::SamplerId) {
+        dispatch!(self => |context, id, wrap| context.sampler_drop(sampler))
+    }
+
+    fn query_set_drop(&self, query_set: &Self::QuerySetId) {
+        dispatch!(self => |context, id, wrap| context.query_set_drop(query_set))
+    }
+
+    fn bind_group_drop(&self, bind_group: &Self::BindGroupId) {
+        dispatch!(self => |context, id, wrap| context.bind_group_drop(bind_group))
+    }
+
+    fn bind_group_layout_drop(&self, bind_group_layout: &Self::BindGroupLayoutId) {
+        dispatch!(self => |context, id, wrap| context.bind_group_layout_drop(bind_group_layout))
+    }
+
+    fn pipeline_layout_drop(&self, pipeline_layout: &Self::PipelineLayoutId) {
+        dispatch!(self => |context, id, wrap| context.pipeline_layout_drop(pipeline_layout))
+    }
+
+    fn shader_module_drop(&self, shader_module: &Self::ShaderModuleId) {
+        dispatch!(self => |context, id, wrap| context.shader_module_drop(id(shader_module)))
+    }
+
+    fn command_encoder_drop(&self, command_encoder: &Self::CommandEncoderId) {
+        dispatch!(self => |context, id, wrap| context.command_encoder_drop(id(command_encoder)))
+    }
+
+    fn command_buffer_drop(&self, command_buffer: &Self::CommandBufferId) {
+        dispatch!(self => |context, id, wrap| context.command_buffer_drop(id(command_buffer)))
+    }
+
+    fn render_bundle_drop(&self, render_bundle: &Self::RenderBundleId) {
+        dispatch!(self => |context, id, wrap| context.render_bundle_drop(render_bundle))
+    }
+
+    fn compute_pipeline_drop(&self, pipeline: &Self::ComputePipelineId) {
+        dispatch!(self => |context, id, wrap| context.compute_pipeline_drop(pipeline))
+    }
+
+    fn render_pipeline_drop(&self, pipeline: &Self::RenderPipelineId) {
+        dispatch!(self => |context, id, wrap| context.render_pipeline_drop(pipeline))
+    }
+
+    fn pipeline_cache_drop(&self, cache: &Self::PipelineCacheId) {
+        dispatch!(self => |context, id, wrap| context.pipeline_cache_drop(id(cache)))
+    }
+
+    fn pipeline_cache_get_data(&self, cache: &Self::PipelineCacheId) -> Option<Vec<u8>> {
+        dispatch!(self => |context, id, wrap| context.pipeline_cache_get_data(id(cache)))
+    }
+
+    fn shader_module_reflect(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        dispatch!(self => |context, id, wrap| context.shader_module_reflect(id(shader_module)))
+    }
+
+    fn shader_module_get_compilation_info(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Self::CompilationInfoFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(context.shader_module_get_compilation_info(id(shader_module)))
+        })
+    }
+
+    fn compute_pipeline_get_bind_group_layout(
+        &self,
+        pipeline: &Self::ComputePipelineId,
+        index: u32,
+    ) -> Self::BindGroupLayoutId {
+        dispatch!(self => |context, id, wrap| {
+            context.compute_pipeline_get_bind_group_layout(pipeline, index)
+        })
+    }
+
+    fn render_pipeline_get_bind_group_layout(
+        &self,
+        pipeline: &Self::RenderPipelineId,
+        index: u32,
+    ) -> Self::BindGroupLayoutId {
+        dispatch!(self => |context, id, wrap| {
+            context.render_pipeline_get_bind_group_layout(pipeline, index)
+        })
+    }
+
+    fn command_encoder_copy_buffer_to_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: &Self::BufferId,
+        source_offset: wgt::BufferAddress,
+        destination: &Self::BufferId,
+        destination_offset: wgt::BufferAddress,
+        copy_size: wgt::BufferAddress,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_copy_buffer_to_buffer(
+                id(encoder),
+                id(source),
+                source_offset,
+                id(destination),
+                destination_offset,
+                copy_size,
+            )
+        })
+    }
+
+    fn command_encoder_copy_buffer_to_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyBuffer,
+        destination: crate::ImageCopyTexture,
+        copy_size: wgt::Extent3d,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_copy_buffer_to_texture(
+                id(encoder),
+                source,
+                destination,
+                copy_size,
+            )
+        })
+    }
+
+    fn command_encoder_copy_texture_to_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyTexture,
+        destination: crate::ImageCopyBuffer,
+        copy_size: wgt::Extent3d,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_copy_texture_to_buffer(
+                id(encoder),
+                source,
+                destination,
+                copy_size,
+            )
+        })
+    }
+
+    fn command_encoder_copy_texture_to_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyTexture,
+        destination: crate::ImageCopyTexture,
+        copy_size: wgt::Extent3d,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_copy_texture_to_texture(
+                id(encoder),
+                source,
+                destination,
+                copy_size,
+            )
+        })
+    }
+
+    fn command_encoder_begin_compute_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        desc: &ComputePassDescriptor,
+    ) -> Self::ComputePassId {
+        dispatch!(self => |context, id, wrap| {
+            Pass(context.command_encoder_begin_compute_pass(id(encoder), desc))
+        })
+    }
+
+    fn command_encoder_end_compute_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        pass: &mut Self::ComputePassId,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_end_compute_pass(id(encoder), pass)
+        })
+    }
+
+    fn command_encoder_begin_render_pass<'a>(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        desc: &crate::RenderPassDescriptor<'a, '_>,
+    ) -> Self::RenderPassId {
+        dispatch!(self => |context, id, wrap| {
+            Pass(context.command_encoder_begin_render_pass(id(encoder), desc))
+        })
+    }
+
+    fn command_encoder_end_render_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        pass: &mut Self::RenderPassId,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_end_render_pass(id(encoder), pass)
+        })
+    }
+
+    fn command_encoder_finish(&self, encoder: Self::CommandEncoderId) -> Self::CommandBufferId {
+        match *self {
+            Self::Direct(ref context) => {
+                Id::Direct(context.command_encoder_finish(encoder.into_direct()))
+            }
+            Self::Remote(ref context) => {
+                Id::Remote(context.command_encoder_finish(encoder.into_remote()))
+            }
+        }
+    }
+
+    fn command_encoder_clear_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        texture: &crate::Texture,
+        subresource_range: &wgt::ImageSubresourceRange,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_clear_texture(id(encoder), texture, subresource_range)
+        })
+    }
+
+    fn command_encoder_clear_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        buffer: &crate::Buffer,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_clear_buffer(id(encoder), buffer, offset, size)
+        })
+    }
+
+    fn command_encoder_insert_debug_marker(&self, encoder: &Self::CommandEncoderId, label: &str) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_insert_debug_marker(id(encoder), label)
+        })
+    }
+
+    fn command_encoder_push_debug_group(&self, encoder: &Self::CommandEncoderId, label: &str) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_push_debug_group(id(encoder), label)
+        })
+    }
+
+    fn command_encoder_pop_debug_group(&self, encoder: &Self::CommandEncoderId) {
+        dispatch!(self => |context, id, wrap| context.command_encoder_pop_debug_group(id(encoder)))
+    }
+
+    fn command_encoder_write_timestamp(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        query_set: &Self::QuerySetId,
+        query_index: u32,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_write_timestamp(id(encoder), query_set, query_index)
+        })
+    }
+
+    fn command_encoder_resolve_query_set(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        query_set: &Self::QuerySetId,
+        first_query: u32,
+        query_count: u32,
+        destination: &Self::BufferId,
+        destination_offset: wgt::BufferAddress,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.command_encoder_resolve_query_set(
+                id(encoder),
+                query_set,
+                first_query,
+                query_count,
+                id(destination),
+                destination_offset,
+            )
+        })
+    }
+
+    fn render_bundle_encoder_finish(
+        &self,
+        encoder: Self::RenderBundleEncoderId,
+        desc: &RenderBundleDescriptor,
+    ) -> Self::RenderBundleId {
+        dispatch!(self => |context, id, wrap| context.render_bundle_encoder_finish(encoder.0, desc))
+    }
+
+    fn queue_write_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        data: &[u8],
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.queue_write_buffer(id(queue), id(buffer), offset, data)
+        })
+    }
+
+    fn queue_validate_write_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    ) -> Option<()> {
+        dispatch!(self => |context, id, wrap| {
+            context.queue_validate_write_buffer(id(queue), id(buffer), offset, size)
+        })
+    }
+
+    fn queue_create_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        size: wgt::BufferSize,
+    ) -> Option<Self::QueueWriteBuffer> {
+        dispatch!(self => |context, id, wrap| {
+            context.queue_create_staging_buffer(id(queue), size).map(wrap)
+        })
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: Self::QueueWriteBuffer,
+    ) {
+        match *self {
+            Self::Direct(ref context) => context.queue_write_staging_buffer(
+                direct_id(queue),
+                direct_id(buffer),
+                offset,
+                staging_buffer.into_direct(),
+            ),
+            Self::Remote(ref context) => context.queue_write_staging_buffer(
+                remote_id(queue),
+                remote_id(buffer),
+                offset,
+                staging_buffer.into_remote(),
+            ),
+        }
+    }
+
+    fn queue_write_texture(
+        &self,
+        queue: &Self::QueueId,
+        texture: crate::ImageCopyTexture,
+        data: &[u8],
+        data_layout: wgt::ImageDataLayout,
+        size: wgt::Extent3d,
+    ) {
+        dispatch!(self => |context, id, wrap| {
+            context.queue_write_texture(id(queue), texture, data, data_layout, size)
+        })
+    }
+
+    fn queue_submit<I: Iterator<Item = Self::CommandBufferId>>(
+        &self,
+        queue: &Self::QueueId,
+        command_buffers: I,
+    ) -> Self::SubmissionIndex {
+        match *self {
+            Self::Direct(ref context) => Id::Direct(
+                context.queue_submit(direct_id(queue), command_buffers.map(Id::into_direct)),
+            ),
+            Self::Remote(ref context) => Id::Remote(
+                context.queue_submit(remote_id(queue), command_buffers.map(Id::into_remote)),
+            ),
+        }
+    }
+
+    fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32 {
+        dispatch!(self => |context, id, wrap| context.queue_get_timestamp_period(id(queue)))
+    }
+
+    fn queue_on_submitted_work_done(
+        &self,
+        queue: &Self::QueueId,
+    ) -> Self::OnSubmittedWorkDoneFuture {
+        dispatch!(self => |context, id, wrap| {
+            Box::pin(context.queue_on_submitted_work_done(id(queue)))
+        })
+    }
+
+    fn device_start_capture(&self, device: &Self::DeviceId) {
+        dispatch!(self => |context, id, wrap| context.device_start_capture(id(device)))
+    }
+
+    fn device_stop_capture(&self, device: &Self::DeviceId) {
+        dispatch!(self => |context, id, wrap| context.device_stop_capture(id(device)))
+    }
+}
@@ -1,12 +1,26 @@
 #[cfg(all(target_arch = "wasm32", not(feature = "webgl")))]
 mod web;
 #[cfg(all(target_arch = "wasm32", not(feature = "webgl")))]
-pub(crate) use web::{BufferMappedRange, Context};
+pub(crate) use web::Context;
 
 #[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
 mod direct;
-#[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
-pub(crate) use direct::{BufferMappedRange, Context};
+#[cfg(all(
+    any(not(target_arch = "wasm32"), feature = "webgl"),
+    not(all(unix, feature = "remote"))
+))]
+pub(crate) use direct::Context;
+
+#[cfg(all(unix, feature = "remote"))]
+mod remote;
+
+#[cfg(all(unix, feature = "remote"))]
+mod dispatch;
+#[cfg(all(unix, feature = "remote"))]
+pub(crate) use dispatch::Context;
 
 #[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
 mod native_gpu_future;
+
+#[cfg(any(not(target_arch = "wasm32"), feature = "webgl"))]
+mod worker_pool;
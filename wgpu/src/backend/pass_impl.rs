@@ -0,0 +1,434 @@
+//! Pass encoding shared by the native backends, which record passes with the
+//! `wgpu-core` encoders and refer to buffers by their `wgpu-core` ID.
+//!
+//! The backend provides the `buffer_id` of its buffers, and its pass types, which
+//! are or dereference to the `wgpu-core` encoders.
+
+use super::{buffer_id, ComputePass, Context, RenderBundleEncoder, RenderPass};
+use smallvec::SmallVec;
+use std::convert::TryInto;
+use std::ops::Range;
+use wgc::command::{bundle_ffi::*, compute_ffi::*, render_ffi::*};
+
+impl crate::ComputePassInner<Context> for ComputePass {
+    fn set_pipeline(&mut self, pipeline: &wgc::id::ComputePipelineId) {
+        wgpu_compute_pass_set_pipeline(self, *pipeline)
+    }
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &wgc::id::BindGroupId,
+        offsets: &[wgt::DynamicOffset],
+    ) {
+        unsafe {
+            wgpu_compute_pass_set_bind_group(
+                self,
+                index,
+                *bind_group,
+                offsets.as_ptr(),
+                offsets.len(),
+            )
+        }
+    }
+    fn set_push_constants(&mut self, offset: u32, data: &[u8]) {
+        unsafe {
+            wgpu_compute_pass_set_push_constant(
+                self,
+                offset,
+                data.len().try_into().unwrap(),
+                data.as_ptr(),
+            )
+        }
+    }
+    fn insert_debug_marker(&mut self, label: &str) {
+        unsafe {
+            let label = std::ffi::CString::new(label).unwrap();
+            wgpu_compute_pass_insert_debug_marker(self, label.as_ptr(), 0);
+        }
+    }
+
+    fn push_debug_group(&mut self, group_label: &str) {
+        unsafe {
+            let label = std::ffi::CString::new(group_label).unwrap();
+            wgpu_compute_pass_push_debug_group(self, label.as_ptr(), 0);
+        }
+    }
+    fn pop_debug_group(&mut self) {
+        wgpu_compute_pass_pop_debug_group(self);
+    }
+
+    fn write_timestamp(&mut self, query_set: &wgc::id::QuerySetId, query_index: u32) {
+        wgpu_compute_pass_write_timestamp(self, *query_set, query_index)
+    }
+
+    fn begin_pipeline_statistics_query(
+        &mut self,
+        query_set: &wgc::id::QuerySetId,
+        query_index: u32,
+    ) {
+        wgpu_compute_pass_begin_pipeline_statistics_query(self, *query_set, query_index)
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        wgpu_compute_pass_end_pipeline_statistics_query(self)
+    }
+
+    fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        wgpu_compute_pass_dispatch(self, x, y, z)
+    }
+    fn dispatch_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+    ) {
+        wgpu_compute_pass_dispatch_indirect(self, buffer_id(indirect_buffer), indirect_offset)
+    }
+}
+
+impl crate::RenderInner<Context> for RenderPass {
+    fn set_pipeline(&mut self, pipeline: &wgc::id::RenderPipelineId) {
+        wgpu_render_pass_set_pipeline(self, *pipeline)
+    }
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &wgc::id::BindGroupId,
+        offsets: &[wgt::DynamicOffset],
+    ) {
+        unsafe {
+            wgpu_render_pass_set_bind_group(
+                self,
+                index,
+                *bind_group,
+                offsets.as_ptr(),
+                offsets.len(),
+            )
+        }
+    }
+    fn set_index_buffer(
+        &mut self,
+        buffer: &super::Buffer,
+        index_format: wgt::IndexFormat,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        wgc::command::RenderPass::set_index_buffer(
+            self,
+            buffer_id(buffer),
+            index_format,
+            offset,
+            size,
+        )
+    }
+    fn set_vertex_buffer(
+        &mut self,
+        slot: u32,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        wgpu_render_pass_set_vertex_buffer(self, slot, buffer_id(buffer), offset, size)
+    }
+    fn set_push_constants(&mut self, stages: wgt::ShaderStages, offset: u32, data: &[u8]) {
+        unsafe {
+            wgpu_render_pass_set_push_constants(
+                self,
+                stages,
+                offset,
+                data.len().try_into().unwrap(),
+                data.as_ptr(),
+            )
+        }
+    }
+    fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        wgpu_render_pass_draw(
+            self,
+            vertices.end - vertices.start,
+            instances.end - instances.start,
+            vertices.start,
+            instances.start,
+        )
+    }
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        wgpu_render_pass_draw_indexed(
+            self,
+            indices.end - indices.start,
+            instances.end - instances.start,
+            indices.start,
+            base_vertex,
+            instances.start,
+        )
+    }
+    fn draw_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+    ) {
+        wgpu_render_pass_draw_indirect(self, buffer_id(indirect_buffer), indirect_offset)
+    }
+    fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+    ) {
+        wgpu_render_pass_draw_indexed_indirect(self, buffer_id(indirect_buffer), indirect_offset)
+    }
+    fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+        count: u32,
+    ) {
+        wgpu_render_pass_multi_draw_indirect(
+            self,
+            buffer_id(indirect_buffer),
+            indirect_offset,
+            count,
+        )
+    }
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+        count: u32,
+    ) {
+        wgpu_render_pass_multi_draw_indexed_indirect(
+            self,
+            buffer_id(indirect_buffer),
+            indirect_offset,
+            count,
+        )
+    }
+    fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+        count_buffer: &super::Buffer,
+        count_buffer_offset: wgt::BufferAddress,
+        max_count: u32,
+    ) {
+        wgpu_render_pass_multi_draw_indirect_count(
+            self,
+            buffer_id(indirect_buffer),
+            indirect_offset,
+            buffer_id(count_buffer),
+            count_buffer_offset,
+            max_count,
+        )
+    }
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+        count_buffer: &super::Buffer,
+        count_buffer_offset: wgt::BufferAddress,
+        max_count: u32,
+    ) {
+        wgpu_render_pass_multi_draw_indexed_indirect_count(
+            self,
+            buffer_id(indirect_buffer),
+            indirect_offset,
+            buffer_id(count_buffer),
+            count_buffer_offset,
+            max_count,
+        )
+    }
+}
+
+impl crate::RenderPassInner<Context> for RenderPass {
+    fn set_blend_constant(&mut self, color: wgt::Color) {
+        wgpu_render_pass_set_blend_constant(self, &color)
+    }
+    fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        wgpu_render_pass_set_scissor_rect(self, x, y, width, height)
+    }
+    fn set_viewport(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        min_depth: f32,
+        max_depth: f32,
+    ) {
+        wgpu_render_pass_set_viewport(self, x, y, width, height, min_depth, max_depth)
+    }
+    fn set_stencil_reference(&mut self, reference: u32) {
+        wgpu_render_pass_set_stencil_reference(self, reference)
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        unsafe {
+            let label = std::ffi::CString::new(label).unwrap();
+            wgpu_render_pass_insert_debug_marker(self, label.as_ptr(), 0);
+        }
+    }
+
+    fn push_debug_group(&mut self, group_label: &str) {
+        unsafe {
+            let label = std::ffi::CString::new(group_label).unwrap();
+            wgpu_render_pass_push_debug_group(self, label.as_ptr(), 0);
+        }
+    }
+
+    fn pop_debug_group(&mut self) {
+        wgpu_render_pass_pop_debug_group(self);
+    }
+
+    fn write_timestamp(&mut self, query_set: &wgc::id::QuerySetId, query_index: u32) {
+        wgpu_render_pass_write_timestamp(self, *query_set, query_index)
+    }
+
+    fn begin_pipeline_statistics_query(
+        &mut self,
+        query_set: &wgc::id::QuerySetId,
+        query_index: u32,
+    ) {
+        wgpu_render_pass_begin_pipeline_statistics_query(self, *query_set, query_index)
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        wgpu_render_pass_end_pipeline_statistics_query(self)
+    }
+
+    fn execute_bundles<'a, I: Iterator<Item = &'a wgc::id::RenderBundleId>>(
+        &mut self,
+        render_bundles: I,
+    ) {
+        let temp_render_bundles = render_bundles.cloned().collect::<SmallVec<[_; 4]>>();
+        unsafe {
+            wgpu_render_pass_execute_bundles(
+                self,
+                temp_render_bundles.as_ptr(),
+                temp_render_bundles.len(),
+            )
+        }
+    }
+}
+
+impl crate::RenderInner<Context> for RenderBundleEncoder {
+    fn set_pipeline(&mut self, pipeline: &wgc::id::RenderPipelineId) {
+        wgpu_render_bundle_set_pipeline(self, *pipeline)
+    }
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &wgc::id::BindGroupId,
+        offsets: &[wgt::DynamicOffset],
+    ) {
+        unsafe {
+            wgpu_render_bundle_set_bind_group(
+                self,
+                index,
+                *bind_group,
+                offsets.as_ptr(),
+                offsets.len(),
+            )
+        }
+    }
+    fn set_index_buffer(
+        &mut self,
+        buffer: &super::Buffer,
+        index_format: wgt::IndexFormat,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        wgc::command::RenderBundleEncoder::set_index_buffer(
+            self,
+            buffer_id(buffer),
+            index_format,
+            offset,
+            size,
+        )
+    }
+    fn set_vertex_buffer(
+        &mut self,
+        slot: u32,
+        buffer: &super::Buffer,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        wgpu_render_bundle_set_vertex_buffer(self, slot, buffer_id(buffer), offset, size)
+    }
+
+    fn set_push_constants(&mut self, stages: wgt::ShaderStages, offset: u32, data: &[u8]) {
+        unsafe {
+            wgpu_render_bundle_set_push_constants(
+                self,
+                stages,
+                offset,
+                data.len().try_into().unwrap(),
+                data.as_ptr(),
+            )
+        }
+    }
+    fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        wgpu_render_bundle_draw(
+            self,
+            vertices.end - vertices.start,
+            instances.end - instances.start,
+            vertices.start,
+            instances.start,
+        )
+    }
+    fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        wgpu_render_bundle_draw_indexed(
+            self,
+            indices.end - indices.start,
+            instances.end - instances.start,
+            indices.start,
+            base_vertex,
+            instances.start,
+        )
+    }
+    fn draw_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+    ) {
+        wgpu_render_bundle_draw_indirect(self, buffer_id(indirect_buffer), indirect_offset)
+    }
+    fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &super::Buffer,
+        indirect_offset: wgt::BufferAddress,
+    ) {
+        wgpu_render_bundle_draw_indexed_indirect(self, buffer_id(indirect_buffer), indirect_offset)
+    }
+    fn multi_draw_indirect(
+        &mut self,
+        _indirect_buffer: &super::Buffer,
+        _indirect_offset: wgt::BufferAddress,
+        _count: u32,
+    ) {
+        unimplemented!()
+    }
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        _indirect_buffer: &super::Buffer,
+        _indirect_offset: wgt::BufferAddress,
+        _count: u32,
+    ) {
+        unimplemented!()
+    }
+    fn multi_draw_indirect_count(
+        &mut self,
+        _indirect_buffer: &super::Buffer,
+        _indirect_offset: wgt::BufferAddress,
+        _count_buffer: &super::Buffer,
+        _count_buffer_offset: wgt::BufferAddress,
+        _max_count: u32,
+    ) {
+        unimplemented!()
+    }
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        _indirect_buffer: &super::Buffer,
+        _indirect_offset: wgt::BufferAddress,
+        _count_buffer: &super::Buffer,
+        _count_buffer_offset: wgt::BufferAddress,
+        _max_count: u32,
+    ) {
+        unimplemented!()
+    }
+}
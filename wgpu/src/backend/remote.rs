@@ -0,0 +1,1731 @@
+use crate::{
+    AdapterInfo, BindGroupDescriptor, BindGroupLayoutDescriptor, BindingResource, BufferBinding,
+    CommandEncoderDescriptor, ComputePassDescriptor, ComputePipelineDescriptor,
+    DownlevelCapabilities, Features, Label, Limits, LoadOp, MapMode, Operations,
+    PipelineCacheDescriptor, PipelineLayoutDescriptor, RenderBundleEncoderDescriptor,
+    RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderModuleDescriptorSpirV, ShaderSource, SurfaceStatus, TextureDescriptor, TextureFormat,
+    TextureViewDescriptor,
+};
+
+use arrayvec::ArrayVec;
+use parking_lot::{Mutex, MutexGuard};
+use std::{
+    borrow::Cow::Borrowed,
+    error::Error,
+    fmt,
+    future::{ready, Ready},
+    mem,
+    ops::Range,
+    os::unix::net::UnixStream,
+    slice,
+    sync::Arc,
+};
+use wgc::id::TypedId;
+use wgpu_remote::{AdapterCapabilities, Client, HostMap};
+
+use super::{dispatch::remote_id, native_gpu_future};
+
+/// Context sending everything to a `wgpu_remote::Server` in another process.
+///
+/// There is a single adapter and device per connection. Validation errors are
+/// collected by the server, and only reach the error sink when polling or
+/// using error scopes.
+pub struct Context {
+    connection: Mutex<Connection>,
+    error_sink: ErrorSink,
+}
+
+/// Device lost callback, as given to `Device::on_device_lost`.
+type LostCallback = Box<dyn FnOnce(wgt::DeviceLostReason, String) + Send>;
+
+/// Message dropping a resource, which waits for the commands that may use it.
+type Release = Box<dyn FnOnce(&mut Client) -> Result<(), wgpu_remote::Error> + Send>;
+
+/// Mapping of a buffer, with the offset it starts at.
+type SharedMapping = Arc<Mutex<Option<(Mapping, wgt::BufferAddress)>>>;
+
+/// Mapping requested with `Buffer::map_async`, which is done on the next poll.
+struct PendingMap {
+    buffer: wgc::id::BufferId,
+    host: HostMap,
+    range: Range<wgt::BufferAddress>,
+    mapping: SharedMapping,
+    completion: native_gpu_future::GpuFutureCompletion<Result<(), crate::BufferAsyncError>>,
+}
+
+#[derive(Default)]
+struct Connection {
+    /// Socket to the server, until the device is requested on it.
+    stream: Option<UnixStream>,
+    adapter: Option<AdapterCapabilities>,
+    client: Option<Client>,
+    /// Set once the connection is closed, which loses the device.
+    lost: Option<(wgt::DeviceLostReason, String)>,
+    lost_callback: Option<LostCallback>,
+    /// Number of command encoders and buffers that are not submitted or dropped yet.
+    ///
+    /// Their commands only reach the server on submission, so the resources they use
+    /// are only dropped on the server once there are none.
+    recording: usize,
+    releases: Vec<Release>,
+    pending_maps: Vec<PendingMap>,
+}
+
+impl Connection {
+    /// Close the connection, returning what to notify once it's unlocked.
+    fn lose(&mut self, reason: wgt::DeviceLostReason, message: String) -> Lost {
+        self.client = None;
+        self.lost = Some((reason, message.clone()));
+        Lost {
+            callback: self
+                .lost_callback
+                .take()
+                .map(|callback| (callback, reason, message)),
+            pending_maps: mem::take(&mut self.pending_maps),
+        }
+    }
+}
+
+/// Notifications of a lost device.
+struct Lost {
+    callback: Option<(LostCallback, wgt::DeviceLostReason, String)>,
+    pending_maps: Vec<PendingMap>,
+}
+
+impl Lost {
+    fn notify(self) {
+        for map in self.pending_maps {
+            map.completion.complete(Err(crate::BufferAsyncError));
+        }
+        if let Some((callback, reason, message)) = self.callback {
+            callback(reason, message);
+        }
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context").field("type", &"Remote").finish()
+    }
+}
+
+/// Error reported by the server, which only keeps its description.
+#[derive(Debug)]
+struct ServerError(String);
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ServerError {}
+
+/// ID of resources created after the device is lost, which never reach the server.
+fn lost_id<I: TypedId>() -> I {
+    I::zip(0, 1, wgt::Backend::Empty)
+}
+
+impl Context {
+    pub fn connect(stream: UnixStream) -> Self {
+        Self {
+            connection: Mutex::new(Connection {
+                stream: Some(stream),
+                ..Connection::default()
+            }),
+            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
+        }
+    }
+
+    /// Get the adapter picked by the server, which is the only one.
+    fn adapter(&self) -> Option<AdapterCapabilities> {
+        let mut connection = self.connection.lock();
+        if connection.adapter.is_none() {
+            if let Some(ref mut stream) = connection.stream {
+                match Client::request_adapter(stream) {
+                    Ok(adapter) => connection.adapter = Some(adapter),
+                    Err(err) => log::error!("Error requesting the adapter: {}", err),
+                }
+            }
+        }
+        connection.adapter.clone()
+    }
+
+    pub fn enumerate_adapters(&self, _backends: wgt::Backends) -> Vec<AdapterCapabilities> {
+        self.adapter().into_iter().collect()
+    }
+
+    /// Run `fun` with the client, reporting an error if the device is lost.
+    fn with_client<R>(
+        &self,
+        fun: impl FnOnce(&mut Client) -> Result<R, wgpu_remote::Error>,
+    ) -> Option<R> {
+        let result = self.try_with_client(fun);
+        if result.is_none() {
+            self.report_lost();
+        }
+        result
+    }
+
+    fn report_lost(&self) {
+        let error = self.make_error(wgc::device::DeviceError::Lost);
+        self.error_sink.lock().handle_error(error);
+    }
+
+    /// Run `fun` with the client, unless the device is lost.
+    ///
+    /// A broken connection loses the device.
+    fn try_with_client<R>(
+        &self,
+        fun: impl FnOnce(&mut Client) -> Result<R, wgpu_remote::Error>,
+    ) -> Option<R> {
+        let mut connection = self.connection.lock();
+        let result = fun(connection.client.as_mut()?);
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                log::error!("Connection to the server is lost: {}", err);
+                self.lose(connection, wgt::DeviceLostReason::Unknown, err.to_string());
+                None
+            }
+        }
+    }
+
+    /// Close the connection, then notify the loss once nothing is locked.
+    fn lose(
+        &self,
+        mut connection: MutexGuard<Connection>,
+        reason: wgt::DeviceLostReason,
+        message: String,
+    ) {
+        let lost = connection.lose(reason, message);
+        drop(connection);
+        self.error_sink.lock().device_lost = true;
+        lost.notify();
+    }
+
+    /// Drop a resource on the server, once no recorded commands can use it.
+    fn release(
+        &self,
+        fun: impl FnOnce(&mut Client) -> Result<(), wgpu_remote::Error> + Send + 'static,
+    ) {
+        {
+            let mut connection = self.connection.lock();
+            if connection.recording != 0 {
+                connection.releases.push(Box::new(fun));
+                return;
+            }
+        }
+        self.try_with_client(fun);
+    }
+
+    /// Account for `count` command encoders or buffers being submitted or dropped.
+    fn finish_recording(&self, count: usize) {
+        let releases = {
+            let mut connection = self.connection.lock();
+            connection.recording -= count;
+            if connection.recording != 0 {
+                return;
+            }
+            mem::take(&mut connection.releases)
+        };
+        if !releases.is_empty() {
+            self.try_with_client(|client| {
+                releases.into_iter().try_for_each(|release| release(client))
+            });
+        }
+    }
+
+    fn make_error(&self, cause: impl Error + Send + 'static) -> crate::Error {
+        crate::Error::Validation {
+            description: format!("Validation Error\n\nCaused by:\n    {}\n", cause),
+            source: Box::new(cause),
+        }
+    }
+
+    fn handle_errors(&self, errors: Vec<String>) {
+        for message in errors {
+            let error = self.make_error(ServerError(message));
+            self.error_sink.lock().handle_error(error);
+        }
+    }
+
+    /// Do the pending mappings, then wait for the server to process everything so far,
+    /// and hand the validation errors to the error sink.
+    ///
+    /// Returns false if the device is lost.
+    fn sync(&self, wait: bool) -> bool {
+        self.map_pending();
+        self.sync_errors(wait)
+    }
+
+    fn sync_errors(&self, wait: bool) -> bool {
+        let errors =
+            self.try_with_client(|client| if wait { client.wait() } else { client.sync() });
+        match errors {
+            Some(errors) => {
+                self.handle_errors(errors);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run `fun`, and get the validation errors it causes instead of handing them
+    /// to the error sink. Returns `None` if the device is lost.
+    fn collect_errors<R>(
+        &self,
+        fun: impl FnOnce(&mut Client) -> Result<R, wgpu_remote::Error>,
+    ) -> Option<(R, Vec<String>)> {
+        self.sync_errors(false);
+        self.try_with_client(|client| {
+            let value = fun(client)?;
+            let errors = client.sync()?;
+            Ok((value, errors))
+        })
+    }
+
+    /// Map a range of a buffer, returning the reason if the server refuses to.
+    fn map(
+        &self,
+        buffer: wgc::id::BufferId,
+        host: HostMap,
+        range: Range<wgt::BufferAddress>,
+    ) -> Result<wgpu_remote::BufferMapping, Option<String>> {
+        let result = self.try_with_client(|client| match client.map_buffer(buffer, host, range) {
+            Ok(mapping) => Ok(Ok(mapping)),
+            Err(wgpu_remote::Error::Refused(reason)) => Ok(Err(reason)),
+            Err(err) => Err(err),
+        });
+        match result {
+            Some(Ok(mapping)) => Ok(mapping),
+            Some(Err(reason)) => Err(Some(reason)),
+            None => Err(None),
+        }
+    }
+
+    fn map_pending(&self) {
+        let pending_maps = mem::take(&mut self.connection.lock().pending_maps);
+        for map in pending_maps {
+            let offset = map.range.start;
+            let result = match self.map(map.buffer, map.host, map.range) {
+                Ok(mapping) => {
+                    *map.mapping.lock() = Some((Mapping::Shared(mapping), offset));
+                    Ok(())
+                }
+                Err(reason) => {
+                    if let Some(reason) = reason {
+                        // The errors of the previous calls come first.
+                        self.sync_errors(false);
+                        self.handle_errors(vec![reason]);
+                    }
+                    Err(crate::BufferAsyncError)
+                }
+            };
+            map.completion.complete(result);
+        }
+    }
+
+    /// Fail the pending mappings of a buffer, when it's unmapped or destroyed first.
+    fn abort_maps(&self, buffer: wgc::id::BufferId) {
+        let aborted: Vec<_> = {
+            let mut connection = self.connection.lock();
+            let (aborted, pending) = mem::take(&mut connection.pending_maps)
+                .into_iter()
+                .partition(|map| map.buffer == buffer);
+            connection.pending_maps = pending;
+            aborted
+        };
+        for map in aborted {
+            map.completion.complete(Err(crate::BufferAsyncError));
+        }
+    }
+
+    fn render_pipeline_descriptor<R>(
+        desc: &RenderPipelineDescriptor,
+        fun: impl FnOnce(&wgc::pipeline::RenderPipelineDescriptor) -> R,
+    ) -> R {
+        use wgc::pipeline as pipe;
+
+        let vertex_buffers: ArrayVec<_, { wgc::MAX_VERTEX_BUFFERS }> = desc
+            .vertex
+            .buffers
+            .iter()
+            .map(|vbuf| pipe::VertexBufferLayout {
+                array_stride: vbuf.array_stride,
+                step_mode: vbuf.step_mode,
+                attributes: Borrowed(vbuf.attributes),
+            })
+            .collect();
+
+        fun(&pipe::RenderPipelineDescriptor {
+            label: desc.label.map(Borrowed),
+            layout: Some(explicit_layout(desc.layout).id),
+            vertex: pipe::VertexState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: remote_id(&desc.vertex.module.id).id,
+                    entry_point: Borrowed(desc.vertex.entry_point),
+                },
+                buffers: Borrowed(&vertex_buffers),
+            },
+            primitive: desc.primitive,
+            depth_stencil: desc.depth_stencil.clone(),
+            multisample: desc.multisample,
+            fragment: desc.fragment.as_ref().map(|frag| pipe::FragmentState {
+                stage: pipe::ProgrammableStageDescriptor {
+                    module: remote_id(&frag.module.id).id,
+                    entry_point: Borrowed(frag.entry_point),
+                },
+                targets: Borrowed(frag.targets),
+            }),
+            multiview: desc.multiview,
+            // Pipeline caches are ignored.
+            cache: None,
+        })
+    }
+
+    fn compute_pipeline_descriptor<'a>(
+        desc: &ComputePipelineDescriptor<'a>,
+    ) -> wgc::pipeline::ComputePipelineDescriptor<'a> {
+        wgc::pipeline::ComputePipelineDescriptor {
+            label: desc.label.map(Borrowed),
+            layout: Some(explicit_layout(desc.layout).id),
+            stage: wgc::pipeline::ProgrammableStageDescriptor {
+                module: remote_id(&desc.module.id).id,
+                entry_point: Borrowed(desc.entry_point),
+            },
+            // Pipeline caches are ignored.
+            cache: None,
+        }
+    }
+}
+
+fn explicit_layout(layout: Option<&crate::PipelineLayout>) -> &crate::PipelineLayout {
+    layout.expect("Implicit pipeline layouts are not supported by the remote backend")
+}
+
+#[path = "pass_impl.rs"]
+mod pass_impl;
+
+type ComputePass = wgc::command::ComputePass;
+type RenderPass = wgc::command::RenderPass;
+type RenderBundleEncoder = wgc::command::RenderBundleEncoder;
+
+pub(super) fn buffer_id(buffer: &Buffer) -> wgc::id::BufferId {
+    buffer.id
+}
+
+fn map_buffer_copy_view(view: crate::ImageCopyBuffer) -> wgc::command::ImageCopyBuffer {
+    wgc::command::ImageCopyBuffer {
+        buffer: remote_id(&view.buffer.id).id,
+        layout: view.layout,
+    }
+}
+
+fn map_texture_copy_view(view: crate::ImageCopyTexture) -> wgc::command::ImageCopyTexture {
+    wgc::command::ImageCopyTexture {
+        texture: *remote_id(&view.texture.id),
+        mip_level: view.mip_level,
+        origin: view.origin,
+        aspect: view.aspect,
+    }
+}
+
+fn map_pass_channel<V: Copy + Default>(
+    ops: Option<&Operations<V>>,
+) -> wgc::command::PassChannel<V> {
+    match ops {
+        Some(&Operations {
+            load: LoadOp::Clear(clear_value),
+            store,
+        }) => wgc::command::PassChannel {
+            load_op: wgc::command::LoadOp::Clear,
+            store_op: if store {
+                wgc::command::StoreOp::Store
+            } else {
+                wgc::command::StoreOp::Discard
+            },
+            clear_value,
+            read_only: false,
+        },
+        Some(&Operations {
+            load: LoadOp::Load,
+            store,
+        }) => wgc::command::PassChannel {
+            load_op: wgc::command::LoadOp::Load,
+            store_op: if store {
+                wgc::command::StoreOp::Store
+            } else {
+                wgc::command::StoreOp::Discard
+            },
+            clear_value: V::default(),
+            read_only: false,
+        },
+        None => wgc::command::PassChannel {
+            load_op: wgc::command::LoadOp::Load,
+            store_op: wgc::command::StoreOp::Store,
+            clear_value: V::default(),
+            read_only: true,
+        },
+    }
+}
+
+#[derive(Debug)]
+pub struct Device {
+    id: wgc::id::DeviceId,
+    features: Features,
+    limits: Limits,
+    downlevel: DownlevelCapabilities,
+}
+
+#[derive(Debug)]
+pub struct Queue {
+    timestamp_period: f32,
+}
+
+/// Memory a buffer is mapped to.
+#[derive(Debug)]
+enum Mapping {
+    /// Memory shared with the server.
+    Shared(wgpu_remote::BufferMapping),
+    /// Memory of a buffer that couldn't be mapped at creation, which is never sent.
+    Local(Vec<u8>),
+}
+
+impl Mapping {
+    fn data_mut(&mut self) -> &mut [u8] {
+        match *self {
+            Self::Shared(ref mut mapping) => mapping.data_mut(),
+            Self::Local(ref mut data) => data,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Buffer {
+    id: wgc::id::BufferId,
+    size: wgt::BufferAddress,
+    usage: wgt::BufferUsages,
+    mapping: SharedMapping,
+}
+
+#[derive(Debug)]
+pub(crate) struct ShaderModule {
+    id: wgc::id::ShaderModuleId,
+    compilation_info: wgt::CompilationInfo,
+}
+
+#[derive(Debug)]
+pub(crate) struct CommandEncoder {
+    commands: Mutex<wgpu_remote::CommandEncoder>,
+}
+
+impl crate::Context for Context {
+    type AdapterId = AdapterCapabilities;
+    type DeviceId = Device;
+    type QueueId = Queue;
+    type ShaderModuleId = ShaderModule;
+    type BindGroupLayoutId = wgc::id::BindGroupLayoutId;
+    type BindGroupId = wgc::id::BindGroupId;
+    type TextureViewId = wgc::id::TextureViewId;
+    type SamplerId = wgc::id::SamplerId;
+    type QuerySetId = wgc::id::QuerySetId;
+    type BufferId = Buffer;
+    type TextureId = wgc::id::TextureId;
+    type PipelineLayoutId = wgc::id::PipelineLayoutId;
+    type RenderPipelineId = wgc::id::RenderPipelineId;
+    type ComputePipelineId = wgc::id::ComputePipelineId;
+    type PipelineCacheId = ();
+    type CommandEncoderId = CommandEncoder;
+    type ComputePassId = wgc::command::ComputePass;
+    type RenderPassId = wgc::command::RenderPass;
+    type CommandBufferId = wgpu_remote::CommandEncoder;
+    type RenderBundleEncoderId = wgc::command::RenderBundleEncoder;
+    type RenderBundleId = wgc::id::RenderBundleId;
+    type SurfaceId = ();
+    // Waiting for a submission waits for all of them.
+    type SubmissionIndex = ();
+
+    type SurfaceOutputDetail = ();
+
+    type BufferMappedRange = BufferMappedRange;
+    type QueueWriteBuffer = QueueWriteBuffer;
+
+    type RequestAdapterFuture = Ready<Option<Self::AdapterId>>;
+    #[allow(clippy::type_complexity)]
+    type RequestDeviceFuture =
+        Ready<Result<(Self::DeviceId, Self::QueueId), crate::RequestDeviceError>>;
+    type MapAsyncFuture = native_gpu_future::GpuFuture<Result<(), crate::BufferAsyncError>>;
+    type OnSubmittedWorkDoneFuture = Ready<()>;
+    type PopErrorScopeFuture = Ready<Option<crate::Error>>;
+    type CompilationInfoFuture = Ready<wgt::CompilationInfo>;
+    type CreateRenderPipelineFuture = Ready<Result<Self::RenderPipelineId, crate::Error>>;
+    type CreateComputePipelineFuture = Ready<Result<Self::ComputePipelineId, crate::Error>>;
+
+    fn init(_backends: wgt::Backends) -> Self {
+        // Without a connection, there are no adapters.
+        Self {
+            connection: Mutex::default(),
+            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
+        }
+    }
+
+    fn instance_create_surface(
+        &self,
+        _handle: &impl raw_window_handle::HasRawWindowHandle,
+    ) -> Self::SurfaceId {
+        panic!("Surfaces are not supported by the remote backend")
+    }
+
+    fn instance_request_adapter(
+        &self,
+        _options: &crate::RequestAdapterOptions,
+    ) -> Self::RequestAdapterFuture {
+        // The adapter is picked by the server.
+        ready(self.adapter())
+    }
+
+    fn instance_poll_all_devices(&self, force_wait: bool) {
+        self.sync(force_wait);
+    }
+
+    fn adapter_request_device(
+        &self,
+        _adapter: &Self::AdapterId,
+        desc: &crate::DeviceDescriptor,
+        trace_dir: Option<&std::path::Path>,
+    ) -> Self::RequestDeviceFuture {
+        if trace_dir.is_some() {
+            log::warn!("Tracing is not supported by the remote backend");
+        }
+        let stream = match self.connection.lock().stream.take() {
+            Some(stream) => stream,
+            None => {
+                log::error!(
+                    "Error in Adapter::request_device: the connection already has a device"
+                );
+                return ready(Err(crate::RequestDeviceError));
+            }
+        };
+        let client = match Client::connect(stream, &desc.map_label(|l| l.map(Borrowed))) {
+            Ok(client) => client,
+            Err(err) => {
+                log::error!("Error in Adapter::request_device: {}", err);
+                return ready(Err(crate::RequestDeviceError));
+            }
+        };
+        let info = client.device().clone();
+        self.connection.lock().client = Some(client);
+        let device = Device {
+            id: info.id,
+            features: info.features,
+            limits: info.limits,
+            downlevel: info.downlevel,
+        };
+        let queue = Queue {
+            timestamp_period: info.timestamp_period,
+        };
+        ready(Ok((device, queue)))
+    }
+
+    fn adapter_is_surface_supported(
+        &self,
+        _adapter: &Self::AdapterId,
+        _surface: &Self::SurfaceId,
+    ) -> bool {
+        false
+    }
+
+    fn adapter_features(&self, adapter: &Self::AdapterId) -> Features {
+        adapter.features
+    }
+
+    fn adapter_limits(&self, adapter: &Self::AdapterId) -> Limits {
+        adapter.limits.clone()
+    }
+
+    fn adapter_downlevel_properties(&self, adapter: &Self::AdapterId) -> DownlevelCapabilities {
+        adapter.downlevel.clone()
+    }
+
+    fn adapter_get_info(&self, adapter: &Self::AdapterId) -> AdapterInfo {
+        adapter.info.clone()
+    }
+
+    fn adapter_get_texture_format_features(
+        &self,
+        _adapter: &Self::AdapterId,
+        format: wgt::TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        format.describe().guaranteed_format_features
+    }
+
+    fn surface_get_preferred_format(
+        &self,
+        _surface: &Self::SurfaceId,
+        _adapter: &Self::AdapterId,
+    ) -> Option<TextureFormat> {
+        unreachable!()
+    }
+
+    fn surface_configure(
+        &self,
+        _surface: &Self::SurfaceId,
+        _device: &Self::DeviceId,
+        _config: &wgt::SurfaceConfiguration,
+    ) {
+        unreachable!()
+    }
+
+    fn surface_get_current_texture(
+        &self,
+        _surface: &Self::SurfaceId,
+    ) -> (
+        Option<Self::TextureId>,
+        SurfaceStatus,
+        Self::SurfaceOutputDetail,
+    ) {
+        unreachable!()
+    }
+
+    fn surface_present(&self, _texture: &Self::TextureId, _detail: &Self::SurfaceOutputDetail) {
+        unreachable!()
+    }
+
+    fn surface_texture_discard(
+        &self,
+        _texture: &Self::TextureId,
+        _detail: &Self::SurfaceOutputDetail,
+    ) {
+        unreachable!()
+    }
+
+    fn device_features(&self, device: &Self::DeviceId) -> Features {
+        device.features
+    }
+
+    fn device_limits(&self, device: &Self::DeviceId) -> Limits {
+        device.limits.clone()
+    }
+
+    fn device_downlevel_properties(&self, device: &Self::DeviceId) -> DownlevelCapabilities {
+        device.downlevel.clone()
+    }
+
+    fn device_get_texture_format_features(
+        &self,
+        _device: &Self::DeviceId,
+        format: wgt::TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        format.describe().guaranteed_format_features
+    }
+
+    fn device_memory_report(&self, _device: &Self::DeviceId) -> wgt::MemoryReport {
+        // The memory is only known to the server.
+        wgt::MemoryReport::default()
+    }
+
+    fn device_create_shader_module(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &ShaderModuleDescriptor,
+        _shader_bound_checks: wgt::ShaderBoundChecks,
+    ) -> Self::ShaderModuleId {
+        #[allow(clippy::infallible_destructuring_match)]
+        let code = match desc.source {
+            ShaderSource::Wgsl(ref code) => code,
+            #[cfg(any(feature = "spirv", feature = "glsl"))]
+            _ => panic!("Only WGSL shaders are supported by the remote backend"),
+        };
+        // The server always adds the bound checks.
+        let descriptor = wgc::pipeline::ShaderModuleDescriptor {
+            label: desc.label.map(Borrowed),
+            shader_bound_checks: wgt::ShaderBoundChecks::new(),
+        };
+        // The errors are needed for the compilation info.
+        let (id, errors) = self
+            .collect_errors(|client| client.create_shader_module(&descriptor, code))
+            .unwrap_or_else(|| {
+                self.report_lost();
+                (lost_id(), Vec::new())
+            });
+        let compilation_info = wgt::CompilationInfo {
+            messages: errors
+                .iter()
+                .map(|message| wgt::CompilationMessage {
+                    message: message.clone(),
+                    message_type: wgt::CompilationMessageType::Error,
+                    line_num: 0,
+                    line_pos: 0,
+                    offset: 0,
+                    length: 0,
+                })
+                .collect(),
+        };
+        self.handle_errors(errors);
+        ShaderModule {
+            id,
+            compilation_info,
+        }
+    }
+
+    unsafe fn device_create_shader_module_spirv(
+        &self,
+        _device: &Self::DeviceId,
+        _desc: &ShaderModuleDescriptorSpirV,
+    ) -> Self::ShaderModuleId {
+        panic!("Passing SPIR-V through is not supported by the remote backend")
+    }
+
+    fn device_create_bind_group_layout(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &BindGroupLayoutDescriptor,
+    ) -> Self::BindGroupLayoutId {
+        let descriptor = wgc::binding_model::BindGroupLayoutDescriptor {
+            label: desc.label.map(Borrowed),
+            entries: Borrowed(desc.entries),
+        };
+        self.with_client(|client| client.create_bind_group_layout(&descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_bind_group(
+        &self,
+        device: &Self::DeviceId,
+        desc: &BindGroupDescriptor,
+    ) -> Self::BindGroupId {
+        use wgc::binding_model as bm;
+
+        let mut arrayed_texture_views = Vec::new();
+        let mut arrayed_samplers = Vec::new();
+        if device.features.contains(Features::TEXTURE_BINDING_ARRAY) {
+            // gather all the array view IDs first
+            for entry in desc.entries.iter() {
+                if let BindingResource::TextureViewArray(array) = entry.resource {
+                    arrayed_texture_views.extend(array.iter().map(|view| view.id));
+                }
+                if let BindingResource::SamplerArray(array) = entry.resource {
+                    arrayed_samplers.extend(array.iter().map(|sampler| sampler.id));
+                }
+            }
+        }
+        let mut remaining_arrayed_texture_views = &arrayed_texture_views[..];
+        let mut remaining_arrayed_samplers = &arrayed_samplers[..];
+
+        let mut arrayed_buffer_bindings = Vec::new();
+        if device.features.contains(Features::BUFFER_BINDING_ARRAY) {
+            // gather all the buffers first
+            for entry in desc.entries.iter() {
+                if let BindingResource::BufferArray(array) = entry.resource {
+                    arrayed_buffer_bindings.extend(array.iter().map(|binding| bm::BufferBinding {
+                        buffer_id: remote_id(&binding.buffer.id).id,
+                        offset: binding.offset,
+                        size: binding.size,
+                    }));
+                }
+            }
+        }
+        let mut remaining_arrayed_buffer_bindings = &arrayed_buffer_bindings[..];
+
+        let entries = desc
+            .entries
+            .iter()
+            .map(|entry| bm::BindGroupEntry {
+                binding: entry.binding,
+                resource: match entry.resource {
+                    BindingResource::Buffer(BufferBinding {
+                        buffer,
+                        offset,
+                        size,
+                    }) => bm::BindingResource::Buffer(bm::BufferBinding {
+                        buffer_id: remote_id(&buffer.id).id,
+                        offset,
+                        size,
+                    }),
+                    BindingResource::BufferArray(array) => {
+                        let slice = &remaining_arrayed_buffer_bindings[..array.len()];
+                        remaining_arrayed_buffer_bindings =
+                            &remaining_arrayed_buffer_bindings[array.len()..];
+                        bm::BindingResource::BufferArray(Borrowed(slice))
+                    }
+                    BindingResource::Sampler(sampler) => bm::BindingResource::Sampler(sampler.id),
+                    BindingResource::SamplerArray(array) => {
+                        let slice = &remaining_arrayed_samplers[..array.len()];
+                        remaining_arrayed_samplers = &remaining_arrayed_samplers[array.len()..];
+                        bm::BindingResource::SamplerArray(Borrowed(slice))
+                    }
+                    BindingResource::TextureView(texture_view) => {
+                        bm::BindingResource::TextureView(texture_view.id)
+                    }
+                    BindingResource::TextureViewArray(array) => {
+                        let slice = &remaining_arrayed_texture_views[..array.len()];
+                        remaining_arrayed_texture_views =
+                            &remaining_arrayed_texture_views[array.len()..];
+                        bm::BindingResource::TextureViewArray(Borrowed(slice))
+                    }
+                },
+            })
+            .collect::<Vec<_>>();
+        let descriptor = bm::BindGroupDescriptor {
+            label: desc.label.as_ref().map(|label| Borrowed(&label[..])),
+            layout: desc.layout.id,
+            entries: Borrowed(&entries),
+        };
+        self.with_client(|client| client.create_bind_group(&descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_pipeline_layout(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &PipelineLayoutDescriptor,
+    ) -> Self::PipelineLayoutId {
+        let descriptor = wgc::binding_model::PipelineLayoutDescriptor {
+            label: desc.label.map(Borrowed),
+            bind_group_layouts: desc.bind_group_layouts.iter().map(|bgl| bgl.id).collect(),
+            push_constant_ranges: Borrowed(desc.push_constant_ranges),
+        };
+        self.with_client(|client| client.create_pipeline_layout(&descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_render_pipeline(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::RenderPipelineId {
+        Self::render_pipeline_descriptor(desc, |descriptor| {
+            self.with_client(|client| client.create_render_pipeline(descriptor))
+        })
+        .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_compute_pipeline(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::ComputePipelineId {
+        let descriptor = Self::compute_pipeline_descriptor(desc);
+        self.with_client(|client| client.create_compute_pipeline(&descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_render_pipeline_async(
+        self: Arc<Self>,
+        _device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        // The server creates pipelines right away, so this only waits for its errors.
+        let result = Self::render_pipeline_descriptor(desc, |descriptor| {
+            self.collect_errors(|client| client.create_render_pipeline(descriptor))
+        });
+        ready(match result {
+            Some((id, errors)) if !errors.is_empty() => {
+                self.release(move |client| client.drop_render_pipeline(id));
+                Err(self.make_error(ServerError(errors.join("\n"))))
+            }
+            Some((id, _)) => Ok(id),
+            None => Err(self.make_error(wgc::device::DeviceError::Lost)),
+        })
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: Arc<Self>,
+        _device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        // The server creates pipelines right away, so this only waits for its errors.
+        let descriptor = Self::compute_pipeline_descriptor(desc);
+        let result = self.collect_errors(|client| client.create_compute_pipeline(&descriptor));
+        ready(match result {
+            Some((id, errors)) if !errors.is_empty() => {
+                self.release(move |client| client.drop_compute_pipeline(id));
+                Err(self.make_error(ServerError(errors.join("\n"))))
+            }
+            Some((id, _)) => Ok(id),
+            None => Err(self.make_error(wgc::device::DeviceError::Lost)),
+        })
+    }
+
+    unsafe fn device_create_pipeline_cache(
+        &self,
+        _device: &Self::DeviceId,
+        _desc: &PipelineCacheDescriptor,
+    ) -> Self::PipelineCacheId {
+        // The data would go straight to the driver of the server, which can't trust it.
+    }
+
+    fn device_create_buffer(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &crate::BufferDescriptor<'_>,
+    ) -> Self::BufferId {
+        let id = self
+            .with_client(|client| client.create_buffer(&desc.map_label(|l| l.map(Borrowed))))
+            .unwrap_or_else(lost_id);
+        let mapping = if desc.mapped_at_creation {
+            // An invalid buffer still gets memory to write into, which is thrown away.
+            let mapping = match self.map(id, HostMap::Write, 0..desc.size) {
+                Ok(mapping) => Mapping::Shared(mapping),
+                Err(_) => Mapping::Local(vec![0; desc.size as usize]),
+            };
+            Some((mapping, 0))
+        } else {
+            None
+        };
+        Buffer {
+            id,
+            size: desc.size,
+            usage: desc.usage,
+            mapping: Arc::new(Mutex::new(mapping)),
+        }
+    }
+
+    fn device_create_texture(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &TextureDescriptor,
+    ) -> Self::TextureId {
+        self.with_client(|client| client.create_texture(&desc.map_label(|l| l.map(Borrowed))))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_sampler(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &SamplerDescriptor,
+    ) -> Self::SamplerId {
+        let descriptor = wgc::resource::SamplerDescriptor {
+            label: desc.label.map(Borrowed),
+            address_modes: [
+                desc.address_mode_u,
+                desc.address_mode_v,
+                desc.address_mode_w,
+            ],
+            mag_filter: desc.mag_filter,
+            min_filter: desc.min_filter,
+            mipmap_filter: desc.mipmap_filter,
+            lod_min_clamp: desc.lod_min_clamp,
+            lod_max_clamp: desc.lod_max_clamp,
+            compare: desc.compare,
+            anisotropy_clamp: desc.anisotropy_clamp,
+            border_color: desc.border_color,
+        };
+        self.with_client(|client| client.create_sampler(&descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_query_set(
+        &self,
+        _device: &Self::DeviceId,
+        desc: &wgt::QuerySetDescriptor<Label>,
+    ) -> Self::QuerySetId {
+        self.with_client(|client| client.create_query_set(&desc.map_label(|l| l.map(Borrowed))))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn device_create_command_encoder(
+        &self,
+        _device: &Self::DeviceId,
+        _desc: &CommandEncoderDescriptor,
+    ) -> Self::CommandEncoderId {
+        self.connection.lock().recording += 1;
+        CommandEncoder {
+            commands: Mutex::default(),
+        }
+    }
+
+    fn device_create_render_bundle_encoder(
+        &self,
+        device: &Self::DeviceId,
+        desc: &RenderBundleEncoderDescriptor,
+    ) -> Self::RenderBundleEncoderId {
+        let descriptor = wgc::command::RenderBundleEncoderDescriptor {
+            label: desc.label.map(Borrowed),
+            color_formats: Borrowed(desc.color_formats),
+            depth_stencil: desc.depth_stencil,
+            sample_count: desc.sample_count,
+            multiview: desc.multiview,
+        };
+        match wgc::command::RenderBundleEncoder::new(&descriptor, device.id, None) {
+            Ok(id) => id,
+            Err(e) => panic!("Error in Device::create_render_bundle_encoder: {}", e),
+        }
+    }
+
+    fn device_destroy(&self, _device: &Self::DeviceId) {
+        // Closing the connection makes the server drop everything.
+        let mut connection = self.connection.lock();
+        if connection.client.is_none() {
+            return;
+        }
+        self.lose(
+            connection,
+            wgt::DeviceLostReason::Destroyed,
+            "Device was destroyed".to_string(),
+        );
+    }
+
+    fn device_drop(&self, _device: &Self::DeviceId) {
+        self.try_with_client(|client| client.flush());
+    }
+
+    fn device_poll(
+        &self,
+        _device: &Self::DeviceId,
+        maintain: crate::Maintain,
+        _timeout: Option<std::time::Duration>,
+    ) -> Result<(), crate::PollError> {
+        // The server can't be interrupted, so there is no timeout.
+        let wait = !matches!(maintain, wgt::Maintain::Poll);
+        if self.sync(wait) {
+            Ok(())
+        } else {
+            Err(crate::PollError::DeviceLost)
+        }
+    }
+
+    fn device_on_uncaptured_error(
+        &self,
+        _device: &Self::DeviceId,
+        handler: impl crate::UncapturedErrorHandler,
+    ) {
+        let mut error_sink = self.error_sink.lock();
+        error_sink.uncaptured_handler = Box::new(handler);
+    }
+
+    fn device_on_device_lost(
+        &self,
+        _device: &Self::DeviceId,
+        callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        let mut connection = self.connection.lock();
+        match connection.lost {
+            Some((reason, ref message)) => {
+                let message = message.clone();
+                drop(connection);
+                callback(reason, message);
+            }
+            None => connection.lost_callback = Some(Box::new(callback)),
+        }
+    }
+
+    fn device_push_error_scope(&self, _device: &Self::DeviceId, filter: crate::ErrorFilter) {
+        // Errors of the previous calls don't belong to the new scope.
+        self.sync(false);
+        let mut error_sink = self.error_sink.lock();
+        error_sink.scopes.push(ErrorScope {
+            error: None,
+            filter,
+        });
+    }
+
+    fn device_pop_error_scope(&self, _device: &Self::DeviceId) -> Self::PopErrorScopeFuture {
+        self.sync(false);
+        let mut error_sink = self.error_sink.lock();
+        let scope = error_sink.scopes.pop().unwrap();
+        ready(scope.error)
+    }
+
+    fn buffer_map_async(
+        &self,
+        buffer: &Self::BufferId,
+        mode: MapMode,
+        range: Range<wgt::BufferAddress>,
+    ) -> Self::MapAsyncFuture {
+        let (future, completion) = native_gpu_future::new_gpu_future();
+        let mut connection = self.connection.lock();
+        if connection.client.is_none() {
+            drop(connection);
+            completion.complete(Err(crate::BufferAsyncError));
+            return future;
+        }
+        // Mapping waits for the server, so it's left to the next poll.
+        connection.pending_maps.push(PendingMap {
+            buffer: buffer.id,
+            host: match mode {
+                MapMode::Read => HostMap::Read,
+                MapMode::Write => HostMap::Write,
+            },
+            range,
+            mapping: Arc::clone(&buffer.mapping),
+            completion,
+        });
+        future
+    }
+
+    fn buffer_get_mapped_range(
+        &self,
+        buffer: &Self::BufferId,
+        sub_range: Range<wgt::BufferAddress>,
+    ) -> BufferMappedRange {
+        let mut guard = buffer.mapping.lock();
+        let (mapping, offset) = match *guard {
+            Some((ref mut mapping, offset)) => (mapping.data_mut(), offset),
+            None => panic!("Error in Buffer::get_mapped_range: the buffer is not mapped"),
+        };
+        let start = sub_range.start.wrapping_sub(offset) as usize;
+        let size = (sub_range.end - sub_range.start) as usize;
+        match mapping.get_mut(start..start.saturating_add(size)) {
+            Some(data) => BufferMappedRange {
+                ptr: data.as_mut_ptr(),
+                size,
+            },
+            None => panic!(
+                "Error in Buffer::get_mapped_range: {:?} is out of the mapped range",
+                sub_range
+            ),
+        }
+    }
+
+    fn buffer_unmap(&self, buffer: &Self::BufferId) {
+        self.abort_maps(buffer.id);
+        match buffer.mapping.lock().take() {
+            Some((Mapping::Shared(mapping), _)) => {
+                self.try_with_client(|client| client.unmap_buffer(mapping));
+            }
+            Some((Mapping::Local(_), _)) | None => {}
+        }
+    }
+
+    fn texture_create_view(
+        &self,
+        texture: &Self::TextureId,
+        desc: &TextureViewDescriptor,
+    ) -> Self::TextureViewId {
+        let descriptor = wgc::resource::TextureViewDescriptor {
+            label: desc.label.map(Borrowed),
+            format: desc.format,
+            dimension: desc.dimension,
+            range: wgt::ImageSubresourceRange {
+                aspect: desc.aspect,
+                base_mip_level: desc.base_mip_level,
+                mip_level_count: desc.mip_level_count,
+                base_array_layer: desc.base_array_layer,
+                array_layer_count: desc.array_layer_count,
+            },
+        };
+        self.with_client(|client| client.create_texture_view(*texture, &descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn surface_drop(&self, _surface: &Self::SurfaceId) {}
+
+    fn adapter_drop(&self, _adapter: &Self::AdapterId) {}
+
+    fn buffer_destroy(&self, buffer: &Self::BufferId) {
+        self.abort_maps(buffer.id);
+        buffer.mapping.lock().take();
+        self.try_with_client(|client| client.destroy_buffer(buffer.id));
+    }
+    fn buffer_drop(&self, buffer: &Self::BufferId) {
+        self.abort_maps(buffer.id);
+        let id = buffer.id;
+        self.release(move |client| client.drop_buffer(id));
+    }
+    fn texture_destroy(&self, texture: &Self::TextureId) {
+        self.try_with_client(|client| client.destroy_texture(*texture));
+    }
+    fn texture_drop(&self, texture: &Self::TextureId) {
+        let id = *texture;
+        self.release(move |client| client.drop_texture(id));
+    }
+    fn texture_view_drop(&self, texture_view: &Self::TextureViewId) {
+        let id = *texture_view;
+        self.release(move |client| client.drop_texture_view(id));
+    }
+    fn sampler_drop(&self, sampler: &Self::SamplerId) {
+        let id = *sampler;
+        self.release(move |client| client.drop_sampler(id));
+    }
+    fn query_set_drop(&self, query_set: &Self::QuerySetId) {
+        let id = *query_set;
+        self.release(move |client| client.drop_query_set(id));
+    }
+    fn bind_group_drop(&self, bind_group: &Self::BindGroupId) {
+        let id = *bind_group;
+        self.release(move |client| client.drop_bind_group(id));
+    }
+    fn bind_group_layout_drop(&self, bind_group_layout: &Self::BindGroupLayoutId) {
+        let id = *bind_group_layout;
+        self.release(move |client| client.drop_bind_group_layout(id));
+    }
+    fn pipeline_layout_drop(&self, pipeline_layout: &Self::PipelineLayoutId) {
+        let id = *pipeline_layout;
+        self.release(move |client| client.drop_pipeline_layout(id));
+    }
+    fn shader_module_drop(&self, shader_module: &Self::ShaderModuleId) {
+        let id = shader_module.id;
+        self.release(move |client| client.drop_shader_module(id));
+    }
+    fn pipeline_cache_drop(&self, _cache: &Self::PipelineCacheId) {}
+    fn pipeline_cache_get_data(&self, _cache: &Self::PipelineCacheId) -> Option<Vec<u8>> {
+        None
+    }
+    fn shader_module_reflect(
+        &self,
+        _shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        // The module is only known to the server.
+        None
+    }
+    fn shader_module_get_compilation_info(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Self::CompilationInfoFuture {
+        ready(shader_module.compilation_info.clone())
+    }
+    fn command_encoder_drop(&self, _command_encoder: &Self::CommandEncoderId) {
+        self.finish_recording(1);
+    }
+    fn command_buffer_drop(&self, _command_buffer: &Self::CommandBufferId) {
+        self.finish_recording(1);
+    }
+    fn render_bundle_drop(&self, render_bundle: &Self::RenderBundleId) {
+        let id = *render_bundle;
+        self.release(move |client| client.drop_render_bundle(id));
+    }
+    fn compute_pipeline_drop(&self, pipeline: &Self::ComputePipelineId) {
+        let id = *pipeline;
+        self.release(move |client| client.drop_compute_pipeline(id));
+    }
+    fn render_pipeline_drop(&self, pipeline: &Self::RenderPipelineId) {
+        let id = *pipeline;
+        self.release(move |client| client.drop_render_pipeline(id));
+    }
+
+    fn compute_pipeline_get_bind_group_layout(
+        &self,
+        _pipeline: &Self::ComputePipelineId,
+        _index: u32,
+    ) -> Self::BindGroupLayoutId {
+        panic!("Implicit pipeline layouts are not supported by the remote backend")
+    }
+    fn render_pipeline_get_bind_group_layout(
+        &self,
+        _pipeline: &Self::RenderPipelineId,
+        _index: u32,
+    ) -> Self::BindGroupLayoutId {
+        panic!("Implicit pipeline layouts are not supported by the remote backend")
+    }
+
+    fn command_encoder_copy_buffer_to_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: &Self::BufferId,
+        source_offset: wgt::BufferAddress,
+        destination: &Self::BufferId,
+        destination_offset: wgt::BufferAddress,
+        copy_size: wgt::BufferAddress,
+    ) {
+        encoder.commands.lock().copy_buffer_to_buffer(
+            source.id,
+            source_offset,
+            destination.id,
+            destination_offset,
+            copy_size,
+        );
+    }
+
+    fn command_encoder_copy_buffer_to_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyBuffer,
+        destination: crate::ImageCopyTexture,
+        copy_size: wgt::Extent3d,
+    ) {
+        encoder.commands.lock().copy_buffer_to_texture(
+            &map_buffer_copy_view(source),
+            &map_texture_copy_view(destination),
+            &copy_size,
+        );
+    }
+
+    fn command_encoder_copy_texture_to_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyTexture,
+        destination: crate::ImageCopyBuffer,
+        copy_size: wgt::Extent3d,
+    ) {
+        encoder.commands.lock().copy_texture_to_buffer(
+            &map_texture_copy_view(source),
+            &map_buffer_copy_view(destination),
+            &copy_size,
+        );
+    }
+
+    fn command_encoder_copy_texture_to_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        source: crate::ImageCopyTexture,
+        destination: crate::ImageCopyTexture,
+        copy_size: wgt::Extent3d,
+    ) {
+        encoder.commands.lock().copy_texture_to_texture(
+            &map_texture_copy_view(source),
+            &map_texture_copy_view(destination),
+            &copy_size,
+        );
+    }
+
+    fn command_encoder_write_timestamp(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        query_set: &Self::QuerySetId,
+        query_index: u32,
+    ) {
+        encoder
+            .commands
+            .lock()
+            .write_timestamp(*query_set, query_index);
+    }
+
+    fn command_encoder_resolve_query_set(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        query_set: &Self::QuerySetId,
+        first_query: u32,
+        query_count: u32,
+        destination: &Self::BufferId,
+        destination_offset: wgt::BufferAddress,
+    ) {
+        encoder.commands.lock().resolve_query_set(
+            *query_set,
+            first_query..first_query + query_count,
+            destination.id,
+            destination_offset,
+        );
+    }
+
+    fn command_encoder_begin_compute_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        desc: &ComputePassDescriptor,
+    ) -> Self::ComputePassId {
+        encoder
+            .commands
+            .lock()
+            .begin_compute_pass(&wgc::command::ComputePassDescriptor {
+                label: desc.label.map(Borrowed),
+            })
+    }
+
+    fn command_encoder_end_compute_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        pass: &mut Self::ComputePassId,
+    ) {
+        let mut commands = encoder.commands.lock();
+        let empty = commands.begin_compute_pass(&wgc::command::ComputePassDescriptor::default());
+        commands.end_compute_pass(mem::replace(pass, empty));
+    }
+
+    fn command_encoder_begin_render_pass<'a>(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        desc: &crate::RenderPassDescriptor<'a, '_>,
+    ) -> Self::RenderPassId {
+        let colors = desc
+            .color_attachments
+            .iter()
+            .map(|ca| wgc::command::RenderPassColorAttachment {
+                view: ca.view.id,
+                resolve_target: ca.resolve_target.map(|rt| rt.id),
+                channel: map_pass_channel(Some(&ca.ops)),
+            })
+            .collect::<ArrayVec<_, { wgc::MAX_COLOR_TARGETS }>>();
+
+        let depth_stencil = desc.depth_stencil_attachment.as_ref().map(|dsa| {
+            wgc::command::RenderPassDepthStencilAttachment {
+                view: dsa.view.id,
+                depth: map_pass_channel(dsa.depth_ops.as_ref()),
+                stencil: map_pass_channel(dsa.stencil_ops.as_ref()),
+            }
+        });
+
+        encoder
+            .commands
+            .lock()
+            .begin_render_pass(&wgc::command::RenderPassDescriptor {
+                label: desc.label.map(Borrowed),
+                color_attachments: Borrowed(&colors),
+                depth_stencil_attachment: depth_stencil.as_ref(),
+            })
+    }
+
+    fn command_encoder_end_render_pass(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        pass: &mut Self::RenderPassId,
+    ) {
+        let mut commands = encoder.commands.lock();
+        let empty = commands.begin_render_pass(&wgc::command::RenderPassDescriptor {
+            label: None,
+            color_attachments: Borrowed(&[]),
+            depth_stencil_attachment: None,
+        });
+        commands.end_render_pass(mem::replace(pass, empty));
+    }
+
+    fn command_encoder_finish(&self, encoder: Self::CommandEncoderId) -> Self::CommandBufferId {
+        encoder.commands.into_inner()
+    }
+
+    fn command_encoder_clear_texture(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        texture: &crate::Texture,
+        subresource_range: &wgt::ImageSubresourceRange,
+    ) {
+        encoder
+            .commands
+            .lock()
+            .clear_texture(*remote_id(&texture.id), subresource_range);
+    }
+
+    fn command_encoder_clear_buffer(
+        &self,
+        encoder: &Self::CommandEncoderId,
+        buffer: &crate::Buffer,
+        offset: wgt::BufferAddress,
+        size: Option<wgt::BufferSize>,
+    ) {
+        encoder
+            .commands
+            .lock()
+            .clear_buffer(remote_id(&buffer.id).id, offset, size);
+    }
+
+    fn command_encoder_insert_debug_marker(&self, encoder: &Self::CommandEncoderId, label: &str) {
+        encoder.commands.lock().insert_debug_marker(label);
+    }
+    fn command_encoder_push_debug_group(&self, encoder: &Self::CommandEncoderId, label: &str) {
+        encoder.commands.lock().push_debug_group(label);
+    }
+    fn command_encoder_pop_debug_group(&self, encoder: &Self::CommandEncoderId) {
+        encoder.commands.lock().pop_debug_group();
+    }
+
+    fn render_bundle_encoder_finish(
+        &self,
+        encoder: Self::RenderBundleEncoderId,
+        desc: &crate::RenderBundleDescriptor,
+    ) -> Self::RenderBundleId {
+        let descriptor = wgt::RenderBundleDescriptor {
+            label: desc.label.map(Borrowed),
+        };
+        self.with_client(|client| client.create_render_bundle(encoder, &descriptor))
+            .unwrap_or_else(lost_id)
+    }
+
+    fn queue_write_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        data: &[u8],
+    ) {
+        self.with_client(|client| client.write_buffer(buffer.id, offset, data));
+    }
+
+    fn queue_validate_write_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    ) -> Option<()> {
+        use wgc::command::{CopySide, TransferError};
+
+        // The data is only sent once it's written, so this can only check what the
+        // client knows about the buffer. The server validates the rest.
+        let size = size.get();
+        let cause = if !buffer.usage.contains(wgt::BufferUsages::COPY_DST) {
+            TransferError::MissingCopyDstUsageFlag(Some(buffer.id), None)
+        } else if size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            TransferError::UnalignedCopySize(size)
+        } else if offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+            TransferError::UnalignedBufferOffset(offset)
+        } else if offset.saturating_add(size) > buffer.size {
+            TransferError::BufferOverrun {
+                start_offset: offset,
+                end_offset: offset.saturating_add(size),
+                buffer_size: buffer.size,
+                side: CopySide::Destination,
+            }
+        } else {
+            return Some(());
+        };
+        // The errors of the previous calls come first.
+        self.sync_errors(false);
+        let error = self.make_error(wgc::device::queue::QueueWriteError::from(cause));
+        self.error_sink.lock().handle_error(error);
+        None
+    }
+
+    fn queue_create_staging_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        size: wgt::BufferSize,
+    ) -> Option<QueueWriteBuffer> {
+        Some(QueueWriteBuffer {
+            data: vec![0; size.get() as usize],
+        })
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    ) {
+        self.with_client(|client| client.write_buffer(buffer.id, offset, &staging_buffer.data));
+    }
+
+    fn queue_write_texture(
+        &self,
+        _queue: &Self::QueueId,
+        texture: crate::ImageCopyTexture,
+        data: &[u8],
+        data_layout: wgt::ImageDataLayout,
+        size: wgt::Extent3d,
+    ) {
+        self.with_client(|client| {
+            client.write_texture(&map_texture_copy_view(texture), data, &data_layout, &size)
+        });
+    }
+
+    fn queue_submit<I: Iterator<Item = Self::CommandBufferId>>(
+        &self,
+        _queue: &Self::QueueId,
+        command_buffers: I,
+    ) -> Self::SubmissionIndex {
+        let command_buffers = command_buffers.collect::<Vec<_>>();
+        let count = command_buffers.len();
+        self.with_client(|client| client.submit(command_buffers));
+        self.finish_recording(count);
+    }
+
+    fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32 {
+        queue.timestamp_period
+    }
+
+    fn queue_on_submitted_work_done(
+        &self,
+        _queue: &Self::QueueId,
+    ) -> Self::OnSubmittedWorkDoneFuture {
+        self.sync(true);
+        ready(())
+    }
+
+    fn device_start_capture(&self, _device: &Self::DeviceId) {}
+
+    fn device_stop_capture(&self, _device: &Self::DeviceId) {}
+}
+
+type ErrorSink = Arc<Mutex<ErrorSinkRaw>>;
+
+struct ErrorScope {
+    error: Option<crate::Error>,
+    filter: crate::ErrorFilter,
+}
+
+struct ErrorSinkRaw {
+    scopes: Vec<ErrorScope>,
+    uncaptured_handler: Box<dyn crate::UncapturedErrorHandler>,
+    /// Set once the device is lost. The lost callback reports the loss, so the
+    /// errors that follow it only reach the error scopes.
+    device_lost: bool,
+}
+
+impl ErrorSinkRaw {
+    fn new() -> ErrorSinkRaw {
+        ErrorSinkRaw {
+            scopes: Vec::new(),
+            uncaptured_handler: Box::from(default_error_handler),
+            device_lost: false,
+        }
+    }
+
+    fn handle_error(&mut self, err: crate::Error) {
+        let filter = match err {
+            crate::Error::OutOfMemory { .. } => crate::ErrorFilter::OutOfMemory,
+            crate::Error::Validation { .. } => crate::ErrorFilter::Validation,
+        };
+        match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.filter == filter)
+        {
+            Some(scope) => {
+                if scope.error.is_none() {
+                    scope.error = Some(err);
+                }
+            }
+            None if self.device_lost => {
+                log::debug!("Error on a lost device: {}", err);
+            }
+            None => {
+                (self.uncaptured_handler)(err);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ErrorSinkRaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ErrorSink")
+    }
+}
+
+fn default_error_handler(err: crate::Error) {
+    log::error!("Handling wgpu errors as fatal by default");
+    panic!("wgpu error: {}\n", err);
+}
+
+#[derive(Debug)]
+pub struct BufferMappedRange {
+    ptr: *mut u8,
+    size: usize,
+}
+
+unsafe impl Send for BufferMappedRange {}
+unsafe impl Sync for BufferMappedRange {}
+
+impl crate::BufferMappedRangeSlice for BufferMappedRange {
+    fn slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.size) }
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.size) }
+    }
+}
+
+impl Drop for BufferMappedRange {
+    fn drop(&mut self) {
+        // Intentionally left blank so that `BufferMappedRange` still
+        // implements `Drop`, to match the web backend
+    }
+}
+
+/// Data of `Queue::write_buffer_with`, which is sent when it's dropped.
+#[derive(Debug)]
+pub struct QueueWriteBuffer {
+    data: Vec<u8>,
+}
+
+impl crate::BufferMappedRangeSlice for QueueWriteBuffer {
+    fn slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
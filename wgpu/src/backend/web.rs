@@ -5,6 +5,7 @@ use std::{
     future::Future,
     ops::Range,
     pin::Pin,
+    sync::Arc,
     task::{self, Poll},
 };
 use wasm_bindgen::{prelude::*, JsCast};
@@ -909,6 +910,101 @@ fn map_map_mode(mode: crate::MapMode) -> u32 {
     }
 }
 
+fn map_render_pipeline_descriptor(
+    desc: &crate::RenderPipelineDescriptor,
+) -> web_sys::GpuRenderPipelineDescriptor {
+    let mut mapped_vertex_state =
+        web_sys::GpuVertexState::new(desc.vertex.entry_point, &desc.vertex.module.id.0);
+
+    let buffers = desc
+        .vertex
+        .buffers
+        .iter()
+        .map(|vbuf| {
+            let mapped_attributes = vbuf
+                .attributes
+                .iter()
+                .map(|attr| {
+                    web_sys::GpuVertexAttribute::new(
+                        map_vertex_format(attr.format),
+                        attr.offset as f64,
+                        attr.shader_location,
+                    )
+                })
+                .collect::<js_sys::Array>();
+
+            let mut mapped_vbuf =
+                web_sys::GpuVertexBufferLayout::new(vbuf.array_stride as f64, &mapped_attributes);
+            mapped_vbuf.step_mode(map_vertex_step_mode(vbuf.step_mode));
+            mapped_vbuf
+        })
+        .collect::<js_sys::Array>();
+
+    mapped_vertex_state.buffers(&buffers);
+
+    let mut mapped_desc = web_sys::GpuRenderPipelineDescriptor::new(&mapped_vertex_state);
+
+    if let Some(label) = desc.label {
+        mapped_desc.label(label);
+    }
+
+    if let Some(layout) = desc.layout {
+        mapped_desc.layout(&layout.id.0);
+    }
+
+    if let Some(ref depth_stencil) = desc.depth_stencil {
+        mapped_desc.depth_stencil(&map_depth_stencil_state(depth_stencil));
+    }
+
+    if let Some(ref frag) = desc.fragment {
+        let targets = frag
+            .targets
+            .iter()
+            .map(|target| {
+                let mapped_format = map_texture_format(target.format);
+                let mut mapped_color_state = web_sys::GpuColorTargetState::new(mapped_format);
+                if let Some(ref bs) = target.blend {
+                    let alpha = map_blend_component(&bs.alpha);
+                    let color = map_blend_component(&bs.color);
+                    let mapped_blend_state = web_sys::GpuBlendState::new(&alpha, &color);
+                    mapped_color_state.blend(&mapped_blend_state);
+                }
+                mapped_color_state.write_mask(target.write_mask.bits());
+                mapped_color_state
+            })
+            .collect::<js_sys::Array>();
+        let mapped_fragment_desc =
+            web_sys::GpuFragmentState::new(frag.entry_point, &frag.module.id.0, &targets);
+        mapped_desc.fragment(&mapped_fragment_desc);
+    }
+
+    let mut mapped_multisample = web_sys::GpuMultisampleState::new();
+    mapped_multisample.count(desc.multisample.count);
+    mapped_multisample.mask(desc.multisample.mask as u32);
+    mapped_multisample.alpha_to_coverage_enabled(desc.multisample.alpha_to_coverage_enabled);
+    mapped_desc.multisample(&mapped_multisample);
+
+    let mapped_primitive = map_primitive_state(&desc.primitive);
+    mapped_desc.primitive(&mapped_primitive);
+
+    mapped_desc
+}
+
+fn map_compute_pipeline_descriptor(
+    desc: &crate::ComputePipelineDescriptor,
+) -> web_sys::GpuComputePipelineDescriptor {
+    let mapped_compute_stage =
+        web_sys::GpuProgrammableStage::new(desc.entry_point, &desc.module.id.0);
+    let mut mapped_desc = web_sys::GpuComputePipelineDescriptor::new(&mapped_compute_stage);
+    if let Some(layout) = desc.layout {
+        mapped_desc.layout(&layout.id.0);
+    }
+    if let Some(label) = desc.label {
+        mapped_desc.label(label);
+    }
+    mapped_desc
+}
+
 type JsFutureResult = Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue>;
 
 fn future_request_adapter(result: JsFutureResult) -> Option<Sendable<web_sys::GpuAdapter>> {
@@ -945,6 +1041,52 @@ fn future_pop_error_scope(result: JsFutureResult) -> Option<crate::Error> {
     }
 }
 
+fn future_compilation_info(result: JsFutureResult) -> wgt::CompilationInfo {
+    let js_info = match result {
+        Ok(js_value) => web_sys::GpuCompilationInfo::from(js_value),
+        Err(_) => return wgt::CompilationInfo::default(),
+    };
+    let messages = js_info
+        .messages()
+        .iter()
+        .map(|js_value| {
+            let js_message = web_sys::GpuCompilationMessage::from(js_value);
+            wgt::CompilationMessage {
+                message: js_message.message(),
+                message_type: match js_message.type_() {
+                    web_sys::GpuCompilationMessageType::Error => wgt::CompilationMessageType::Error,
+                    web_sys::GpuCompilationMessageType::Warning => {
+                        wgt::CompilationMessageType::Warning
+                    }
+                    _ => wgt::CompilationMessageType::Info,
+                },
+                line_num: js_message.line_num() as u64,
+                line_pos: js_message.line_pos() as u64,
+                offset: js_message.offset() as u64,
+                length: js_message.length() as u64,
+            }
+        })
+        .collect();
+    wgt::CompilationInfo { messages }
+}
+
+fn future_create_pipeline<T: JsCast>(result: JsFutureResult) -> Result<Sendable<T>, crate::Error> {
+    match result {
+        Ok(js_value) => Ok(Sendable(js_value.unchecked_into())),
+        // The promise is rejected with a `GPUPipelineError`.
+        Err(js_error) => {
+            let description = match js_error.dyn_ref::<js_sys::Error>() {
+                Some(js_error) => js_error.message().into(),
+                None => format!("{:?}", js_error),
+            };
+            Err(crate::Error::Validation {
+                source: Box::from("<WebGPU Error>"),
+                description,
+            })
+        }
+    }
+}
+
 impl Context {
     pub fn instance_create_surface_from_canvas(
         &self,
@@ -984,6 +1126,7 @@ impl crate::Context for Context {
     type PipelineLayoutId = Sendable<web_sys::GpuPipelineLayout>;
     type RenderPipelineId = Sendable<web_sys::GpuRenderPipeline>;
     type ComputePipelineId = Sendable<web_sys::GpuComputePipeline>;
+    type PipelineCacheId = ();
     type CommandEncoderId = web_sys::GpuCommandEncoder;
     type ComputePassId = ComputePass;
     type RenderPassId = RenderPass;
@@ -991,9 +1134,13 @@ impl crate::Context for Context {
     type RenderBundleEncoderId = RenderBundleEncoder;
     type RenderBundleId = Sendable<web_sys::GpuRenderBundle>;
     type SurfaceId = Sendable<web_sys::GpuCanvasContext>;
+    type SubmissionIndex = ();
 
     type SurfaceOutputDetail = SurfaceOutputDetail;
 
+    type BufferMappedRange = BufferMappedRange;
+    type QueueWriteBuffer = QueueWriteBuffer;
+
     type RequestAdapterFuture = MakeSendFuture<
         wasm_bindgen_futures::JsFuture,
         fn(JsFutureResult) -> Option<Self::AdapterId>,
@@ -1010,6 +1157,16 @@ impl crate::Context for Context {
         MakeSendFuture<wasm_bindgen_futures::JsFuture, fn(JsFutureResult) -> ()>;
     type PopErrorScopeFuture =
         MakeSendFuture<wasm_bindgen_futures::JsFuture, fn(JsFutureResult) -> Option<crate::Error>>;
+    type CompilationInfoFuture =
+        MakeSendFuture<wasm_bindgen_futures::JsFuture, fn(JsFutureResult) -> wgt::CompilationInfo>;
+    type CreateRenderPipelineFuture = MakeSendFuture<
+        wasm_bindgen_futures::JsFuture,
+        fn(JsFutureResult) -> Result<Self::RenderPipelineId, crate::Error>,
+    >;
+    type CreateComputePipelineFuture = MakeSendFuture<
+        wasm_bindgen_futures::JsFuture,
+        fn(JsFutureResult) -> Result<Self::ComputePipelineId, crate::Error>,
+    >;
 
     fn init(_backends: wgt::Backends) -> Self {
         Context(web_sys::window().unwrap().navigator().gpu())
@@ -1247,6 +1404,19 @@ impl crate::Context for Context {
         wgt::DownlevelCapabilities::default()
     }
 
+    fn device_get_texture_format_features(
+        &self,
+        _device: &Self::DeviceId,
+        format: wgt::TextureFormat,
+    ) -> wgt::TextureFormatFeatures {
+        format.describe().guaranteed_format_features
+    }
+
+    fn device_memory_report(&self, _device: &Self::DeviceId) -> wgt::MemoryReport {
+        // WebGPU doesn't expose the memory of the device
+        wgt::MemoryReport::default()
+    }
+
     fn device_create_shader_module(
         &self,
         device: &Self::DeviceId,
@@ -1491,82 +1661,7 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &crate::RenderPipelineDescriptor,
     ) -> Self::RenderPipelineId {
-        let mut mapped_vertex_state =
-            web_sys::GpuVertexState::new(desc.vertex.entry_point, &desc.vertex.module.id.0);
-
-        let buffers = desc
-            .vertex
-            .buffers
-            .iter()
-            .map(|vbuf| {
-                let mapped_attributes = vbuf
-                    .attributes
-                    .iter()
-                    .map(|attr| {
-                        web_sys::GpuVertexAttribute::new(
-                            map_vertex_format(attr.format),
-                            attr.offset as f64,
-                            attr.shader_location,
-                        )
-                    })
-                    .collect::<js_sys::Array>();
-
-                let mut mapped_vbuf = web_sys::GpuVertexBufferLayout::new(
-                    vbuf.array_stride as f64,
-                    &mapped_attributes,
-                );
-                mapped_vbuf.step_mode(map_vertex_step_mode(vbuf.step_mode));
-                mapped_vbuf
-            })
-            .collect::<js_sys::Array>();
-
-        mapped_vertex_state.buffers(&buffers);
-
-        let mut mapped_desc = web_sys::GpuRenderPipelineDescriptor::new(&mapped_vertex_state);
-
-        if let Some(label) = desc.label {
-            mapped_desc.label(label);
-        }
-
-        if let Some(layout) = desc.layout {
-            mapped_desc.layout(&layout.id.0);
-        }
-
-        if let Some(ref depth_stencil) = desc.depth_stencil {
-            mapped_desc.depth_stencil(&map_depth_stencil_state(depth_stencil));
-        }
-
-        if let Some(ref frag) = desc.fragment {
-            let targets = frag
-                .targets
-                .iter()
-                .map(|target| {
-                    let mapped_format = map_texture_format(target.format);
-                    let mut mapped_color_state = web_sys::GpuColorTargetState::new(mapped_format);
-                    if let Some(ref bs) = target.blend {
-                        let alpha = map_blend_component(&bs.alpha);
-                        let color = map_blend_component(&bs.color);
-                        let mapped_blend_state = web_sys::GpuBlendState::new(&alpha, &color);
-                        mapped_color_state.blend(&mapped_blend_state);
-                    }
-                    mapped_color_state.write_mask(target.write_mask.bits());
-                    mapped_color_state
-                })
-                .collect::<js_sys::Array>();
-            let mapped_fragment_desc =
-                web_sys::GpuFragmentState::new(frag.entry_point, &frag.module.id.0, &targets);
-            mapped_desc.fragment(&mapped_fragment_desc);
-        }
-
-        let mut mapped_multisample = web_sys::GpuMultisampleState::new();
-        mapped_multisample.count(desc.multisample.count);
-        mapped_multisample.mask(desc.multisample.mask as u32);
-        mapped_multisample.alpha_to_coverage_enabled(desc.multisample.alpha_to_coverage_enabled);
-        mapped_desc.multisample(&mapped_multisample);
-
-        let mapped_primitive = map_primitive_state(&desc.primitive);
-        mapped_desc.primitive(&mapped_primitive);
-
+        let mapped_desc = map_render_pipeline_descriptor(desc);
         Sendable(device.0.create_render_pipeline(&mapped_desc))
     }
 
@@ -1575,18 +1670,44 @@ impl crate::Context for Context {
         device: &Self::DeviceId,
         desc: &crate::ComputePipelineDescriptor,
     ) -> Self::ComputePipelineId {
-        let mapped_compute_stage =
-            web_sys::GpuProgrammableStage::new(desc.entry_point, &desc.module.id.0);
-        let mut mapped_desc = web_sys::GpuComputePipelineDescriptor::new(&mapped_compute_stage);
-        if let Some(layout) = desc.layout {
-            mapped_desc.layout(&layout.id.0);
-        }
-        if let Some(label) = desc.label {
-            mapped_desc.label(label);
-        }
+        let mapped_desc = map_compute_pipeline_descriptor(desc);
         Sendable(device.0.create_compute_pipeline(&mapped_desc))
     }
 
+    fn device_create_render_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &crate::RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture {
+        let mapped_desc = map_render_pipeline_descriptor(desc);
+        let pipeline_promise = device.0.create_render_pipeline_async(&mapped_desc);
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(pipeline_promise),
+            future_create_pipeline,
+        )
+    }
+
+    fn device_create_compute_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &crate::ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture {
+        let mapped_desc = map_compute_pipeline_descriptor(desc);
+        let pipeline_promise = device.0.create_compute_pipeline_async(&mapped_desc);
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(pipeline_promise),
+            future_create_pipeline,
+        )
+    }
+
+    unsafe fn device_create_pipeline_cache(
+        &self,
+        _device: &Self::DeviceId,
+        _desc: &crate::PipelineCacheDescriptor,
+    ) -> Self::PipelineCacheId {
+        // Browsers cache pipelines on their own.
+    }
+
     fn device_create_buffer(
         &self,
         device: &Self::DeviceId,
@@ -1687,12 +1808,22 @@ impl crate::Context for Context {
         RenderBundleEncoder(device.0.create_render_bundle_encoder(&mapped_desc))
     }
 
+    fn device_destroy(&self, device: &Self::DeviceId) {
+        device.0.destroy();
+    }
+
     fn device_drop(&self, _device: &Self::DeviceId) {
         // Device is dropped automatically
     }
 
-    fn device_poll(&self, _device: &Self::DeviceId, _maintain: crate::Maintain) {
+    fn device_poll(
+        &self,
+        _device: &Self::DeviceId,
+        _maintain: crate::Maintain,
+        _timeout: Option<std::time::Duration>,
+    ) -> Result<(), crate::PollError> {
         // Device is polled automatically
+        Ok(())
     }
 
     fn device_on_uncaptured_error(
@@ -1711,6 +1842,22 @@ impl crate::Context for Context {
         f.forget();
     }
 
+    fn device_on_device_lost(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        let f = Closure::once(move |info: web_sys::GpuDeviceLostInfo| {
+            let reason = match info.reason().as_string().as_deref() {
+                Some("destroyed") => wgt::DeviceLostReason::Destroyed,
+                _ => wgt::DeviceLostReason::Unknown,
+            };
+            callback(reason, info.message());
+        });
+        let _ = device.0.lost().then(&f);
+        f.forget();
+    }
+
     fn device_push_error_scope(&self, device: &Self::DeviceId, filter: crate::ErrorFilter) {
         device.0.push_error_scope(match filter {
             crate::ErrorFilter::OutOfMemory => web_sys::GpuErrorFilter::OutOfMemory,
@@ -1864,6 +2011,30 @@ impl crate::Context for Context {
         // Dropped automatically
     }
 
+    fn pipeline_cache_drop(&self, _cache: &Self::PipelineCacheId) {}
+
+    fn pipeline_cache_get_data(&self, _cache: &Self::PipelineCacheId) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn shader_module_reflect(
+        &self,
+        _shader_module: &Self::ShaderModuleId,
+    ) -> Option<wgt::ShaderReflection> {
+        None
+    }
+
+    fn shader_module_get_compilation_info(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Self::CompilationInfoFuture {
+        let info_promise = shader_module.0.compilation_info();
+        MakeSendFuture::new(
+            wasm_bindgen_futures::JsFuture::from(info_promise),
+            future_compilation_info,
+        )
+    }
+
     fn compute_pipeline_get_bind_group_layout(
         &self,
         pipeline: &Self::ComputePipelineId,
@@ -2157,6 +2328,37 @@ impl crate::Context for Context {
             );
     }
 
+    fn queue_validate_write_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        _buffer: &Self::BufferId,
+        _offset: wgt::BufferAddress,
+        _size: wgt::BufferSize,
+    ) -> Option<()> {
+        // The browser validates the write once the data is written.
+        Some(())
+    }
+
+    fn queue_create_staging_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        size: wgt::BufferSize,
+    ) -> Option<QueueWriteBuffer> {
+        Some(QueueWriteBuffer(
+            vec![0; size.get() as usize].into_boxed_slice(),
+        ))
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    ) {
+        self.queue_write_buffer(queue, buffer, offset, &staging_buffer.0)
+    }
+
     fn queue_write_texture(
         &self,
         queue: &Self::QueueId,
@@ -2196,7 +2398,7 @@ impl crate::Context for Context {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    ) {
+    ) -> Self::SubmissionIndex {
         let temp_command_buffers = command_buffers.map(|i| i.0).collect::<js_sys::Array>();
 
         queue.0.submit(&temp_command_buffers);
@@ -2248,3 +2450,18 @@ impl Drop for BufferMappedRange {
         }
     }
 }
+
+/// There is no staging memory to write into on the web, so the data is written
+/// with `GPUQueue.writeBuffer` from this copy.
+#[derive(Debug)]
+pub struct QueueWriteBuffer(Box<[u8]>);
+
+impl crate::BufferMappedRangeSlice for QueueWriteBuffer {
+    fn slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
@@ -0,0 +1,52 @@
+use parking_lot::Mutex;
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Number of the worker threads.
+const WORKER_COUNT: usize = 4;
+
+/// Threads running the work that shouldn't block the caller, like pipeline compilation.
+///
+/// The threads are only started with the first job, and exit once the pool is dropped.
+#[derive(Default)]
+pub(crate) struct WorkerPool {
+    sender: Mutex<Option<mpsc::Sender<Job>>>,
+}
+
+impl WorkerPool {
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        // There are no threads to run the jobs on with WebGL.
+        if cfg!(target_arch = "wasm32") {
+            return job();
+        }
+        let mut sender = self.sender.lock();
+        let sender = sender.get_or_insert_with(Self::start);
+        // All the workers are gone if a job panicked on each of them.
+        if let Err(mpsc::SendError(job)) = sender.send(Box::new(job)) {
+            job();
+        }
+    }
+
+    fn start() -> mpsc::Sender<Job> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..WORKER_COUNT {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("wgpu worker {}", index))
+                .spawn(move || loop {
+                    let job = receiver.lock().recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(mpsc::RecvError) => break,
+                    }
+                })
+                .expect("Unable to start a worker thread");
+        }
+        sender
+    }
+}
@@ -20,29 +20,40 @@ use std::{
     ops::{Bound, Range, RangeBounds},
     sync::Arc,
     thread,
+    time::Duration,
 };
 
 use parking_lot::Mutex;
 
 pub use wgt::{
-    AdapterInfo, AddressMode, Backend, Backends, BindGroupLayoutEntry, BindingType, BlendComponent,
-    BlendFactor, BlendOperation, BlendState, BufferAddress, BufferBindingType, BufferSize,
-    BufferUsages, Color, ColorTargetState, ColorWrites, CommandBufferDescriptor, CompareFunction,
-    DepthBiasState, DepthStencilState, DeviceType, DownlevelCapabilities, DownlevelFlags,
-    DynamicOffset, Extent3d, Face, Features, FilterMode, FrontFace, ImageDataLayout,
-    ImageSubresourceRange, IndexFormat, Limits, MultisampleState, Origin3d,
-    PipelineStatisticsTypes, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
+    AdapterInfo, AddressMode, Backend, Backends, BindGroupLayoutEntry, BindingReflection,
+    BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState, BufferAddress,
+    BufferBindingType, BufferSize, BufferUsages, Color, ColorTargetState, ColorWrites,
+    CommandBufferDescriptor, CompareFunction, CompilationInfo, CompilationMessage,
+    CompilationMessageType, DepthBiasState, DepthStencilState, DeviceLostReason, DeviceType,
+    DownlevelCapabilities, DownlevelFlags, DynamicOffset, EntryPointReflection, Extent3d, Face,
+    Features, FilterMode, FragmentOutputReflection, FrontFace, ImageDataLayout,
+    ImageSubresourceRange, IndexFormat, Limits, MemoryHeapReport, MemoryReport, MultisampleState,
+    Origin3d, PipelineStatisticsTypes, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
     PrimitiveTopology, PushConstantRange, QueryType, RenderBundleDepthStencil, SamplerBindingType,
-    SamplerBorderColor, ShaderLocation, ShaderModel, ShaderStages, StencilFaceState,
-    StencilOperation, StencilState, StorageTextureAccess, SurfaceConfiguration, SurfaceStatus,
-    TextureAspect, TextureDimension, TextureFormat, TextureFormatFeatureFlags,
-    TextureFormatFeatures, TextureSampleType, TextureUsages, TextureViewDimension, VertexAttribute,
-    VertexFormat, VertexStepMode, COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT,
-    MAP_ALIGNMENT, PUSH_CONSTANT_ALIGNMENT, QUERY_RESOLVE_BUFFER_ALIGNMENT, QUERY_SET_MAX_QUERIES,
-    QUERY_SIZE, VERTEX_STRIDE_ALIGNMENT,
+    SamplerBorderColor, ShaderLocation, ShaderModel, ShaderReflection, ShaderScalarKind,
+    ShaderStages, StencilFaceState, StencilOperation, StencilState, StorageTextureAccess,
+    SurfaceConfiguration, SurfaceStatus, TextureAspect, TextureDimension, TextureFormat,
+    TextureFormatFeatureFlags, TextureFormatFeatures, TextureSampleType, TextureUsages,
+    TextureViewDimension, VertexAttribute, VertexFormat, VertexInputReflection, VertexStepMode,
+    COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT, MAP_ALIGNMENT, PUSH_CONSTANT_ALIGNMENT,
+    QUERY_RESOLVE_BUFFER_ALIGNMENT, QUERY_SET_MAX_QUERIES, QUERY_SIZE, VERTEX_STRIDE_ALIGNMENT,
 };
 
-use backend::{BufferMappedRange, Context as C};
+/// The hardware abstraction layer of the native backends, to interoperate with them through
+/// the `from_hal` and `as_hal` methods.
+#[cfg(not(target_arch = "wasm32"))]
+pub use ::hal;
+
+use backend::Context as C;
+
+type BufferMappedRange = <C as Context>::BufferMappedRange;
+type QueueWriteBuffer = <C as Context>::QueueWriteBuffer;
 
 /// Filter for error scopes.
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
@@ -177,6 +188,7 @@ trait Context: Debug + Send + Sized + Sync {
     type PipelineLayoutId: Debug + Send + Sync + 'static;
     type RenderPipelineId: Debug + Send + Sync + 'static;
     type ComputePipelineId: Debug + Send + Sync + 'static;
+    type PipelineCacheId: Debug + Send + Sync + 'static;
     type CommandEncoderId: Debug;
     type ComputePassId: Debug + ComputePassInner<Self>;
     type RenderPassId: Debug + RenderPassInner<Self>;
@@ -184,15 +196,22 @@ trait Context: Debug + Send + Sized + Sync {
     type RenderBundleEncoderId: Debug + RenderInner<Self>;
     type RenderBundleId: Debug + Send + Sync + 'static;
     type SurfaceId: Debug + Send + Sync + 'static;
+    type SubmissionIndex: Debug + Copy + Clone + Send + 'static;
 
     type SurfaceOutputDetail: Send;
 
+    type BufferMappedRange: BufferMappedRangeSlice + Debug;
+    type QueueWriteBuffer: BufferMappedRangeSlice + Debug;
+
     type RequestAdapterFuture: Future<Output = Option<Self::AdapterId>> + Send;
     type RequestDeviceFuture: Future<Output = Result<(Self::DeviceId, Self::QueueId), RequestDeviceError>>
         + Send;
     type MapAsyncFuture: Future<Output = Result<(), BufferAsyncError>> + Send;
     type OnSubmittedWorkDoneFuture: Future<Output = ()> + Send;
     type PopErrorScopeFuture: Future<Output = Option<Error>> + Send;
+    type CompilationInfoFuture: Future<Output = CompilationInfo> + Send;
+    type CreateRenderPipelineFuture: Future<Output = Result<Self::RenderPipelineId, Error>> + Send;
+    type CreateComputePipelineFuture: Future<Output = Result<Self::ComputePipelineId, Error>> + Send;
 
     fn init(backends: Backends) -> Self;
     fn instance_create_surface(
@@ -254,6 +273,12 @@ trait Context: Debug + Send + Sized + Sync {
     fn device_features(&self, device: &Self::DeviceId) -> Features;
     fn device_limits(&self, device: &Self::DeviceId) -> Limits;
     fn device_downlevel_properties(&self, device: &Self::DeviceId) -> DownlevelCapabilities;
+    fn device_get_texture_format_features(
+        &self,
+        device: &Self::DeviceId,
+        format: TextureFormat,
+    ) -> TextureFormatFeatures;
+    fn device_memory_report(&self, device: &Self::DeviceId) -> MemoryReport;
     fn device_create_shader_module(
         &self,
         device: &Self::DeviceId,
@@ -290,6 +315,21 @@ trait Context: Debug + Send + Sized + Sync {
         device: &Self::DeviceId,
         desc: &ComputePipelineDescriptor,
     ) -> Self::ComputePipelineId;
+    fn device_create_render_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &RenderPipelineDescriptor,
+    ) -> Self::CreateRenderPipelineFuture;
+    fn device_create_compute_pipeline_async(
+        self: Arc<Self>,
+        device: &Self::DeviceId,
+        desc: &ComputePipelineDescriptor,
+    ) -> Self::CreateComputePipelineFuture;
+    unsafe fn device_create_pipeline_cache(
+        &self,
+        device: &Self::DeviceId,
+        desc: &PipelineCacheDescriptor,
+    ) -> Self::PipelineCacheId;
     fn device_create_buffer(
         &self,
         device: &Self::DeviceId,
@@ -320,13 +360,24 @@ trait Context: Debug + Send + Sized + Sync {
         device: &Self::DeviceId,
         desc: &RenderBundleEncoderDescriptor,
     ) -> Self::RenderBundleEncoderId;
+    fn device_destroy(&self, device: &Self::DeviceId);
     fn device_drop(&self, device: &Self::DeviceId);
-    fn device_poll(&self, device: &Self::DeviceId, maintain: Maintain);
+    fn device_poll(
+        &self,
+        device: &Self::DeviceId,
+        maintain: Maintain,
+        timeout: Option<Duration>,
+    ) -> Result<(), PollError>;
     fn device_on_uncaptured_error(
         &self,
         device: &Self::DeviceId,
         handler: impl UncapturedErrorHandler,
     );
+    fn device_on_device_lost(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(DeviceLostReason, String) + Send + 'static,
+    );
     fn device_push_error_scope(&self, device: &Self::DeviceId, filter: ErrorFilter);
     fn device_pop_error_scope(&self, device: &Self::DeviceId) -> Self::PopErrorScopeFuture;
 
@@ -340,7 +391,7 @@ trait Context: Debug + Send + Sized + Sync {
         &self,
         buffer: &Self::BufferId,
         sub_range: Range<BufferAddress>,
-    ) -> BufferMappedRange;
+    ) -> Self::BufferMappedRange;
     fn buffer_unmap(&self, buffer: &Self::BufferId);
     fn texture_create_view(
         &self,
@@ -366,6 +417,16 @@ trait Context: Debug + Send + Sized + Sync {
     fn render_bundle_drop(&self, render_bundle: &Self::RenderBundleId);
     fn compute_pipeline_drop(&self, pipeline: &Self::ComputePipelineId);
     fn render_pipeline_drop(&self, pipeline: &Self::RenderPipelineId);
+    fn pipeline_cache_drop(&self, cache: &Self::PipelineCacheId);
+    fn pipeline_cache_get_data(&self, cache: &Self::PipelineCacheId) -> Option<Vec<u8>>;
+    fn shader_module_reflect(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Option<ShaderReflection>;
+    fn shader_module_get_compilation_info(
+        &self,
+        shader_module: &Self::ShaderModuleId,
+    ) -> Self::CompilationInfoFuture;
 
     fn compute_pipeline_get_bind_group_layout(
         &self,
@@ -477,6 +538,25 @@ trait Context: Debug + Send + Sized + Sync {
         offset: BufferAddress,
         data: &[u8],
     );
+    fn queue_validate_write_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: BufferAddress,
+        size: BufferSize,
+    ) -> Option<()>;
+    fn queue_create_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        size: BufferSize,
+    ) -> Option<Self::QueueWriteBuffer>;
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: BufferAddress,
+        staging_buffer: Self::QueueWriteBuffer,
+    );
     fn queue_write_texture(
         &self,
         queue: &Self::QueueId,
@@ -489,7 +569,7 @@ trait Context: Debug + Send + Sized + Sync {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    );
+    ) -> Self::SubmissionIndex;
     fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32;
     fn queue_on_submitted_work_done(
         &self,
@@ -543,15 +623,14 @@ pub struct Device {
     id: <C as Context>::DeviceId,
 }
 
-/// Passed to [`Device::poll`] to control if it should block or not. This has no effect on
+/// Identifier for a particular call to [`Queue::submit`]. Can be used
+/// with [`Maintain::WaitForSubmissionIndex`] to wait for that submission.
+#[derive(Debug, Copy, Clone)]
+pub struct SubmissionIndex(<C as Context>::SubmissionIndex);
+
+/// Passed to [`Device::poll`] to control if and how long it should block. This has no effect on
 /// the web.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum Maintain {
-    /// Block
-    Wait,
-    /// Don't block
-    Poll,
-}
+pub type Maintain = wgt::Maintain<SubmissionIndex>;
 
 /// The main purpose of this struct is to resolve mapped ranges (convert sizes
 /// to end points), and to ensure that the sub-ranges don't intersect.
@@ -644,6 +723,7 @@ pub struct Texture {
     context: Arc<C>,
     id: <C as Context>::TextureId,
     owned: bool,
+    descriptor: wgt::TextureDescriptor<()>,
 }
 
 /// Handle to a texture view.
@@ -683,6 +763,8 @@ impl Drop for Sampler {
 pub struct Surface {
     context: Arc<C>,
     id: <C as Context>::SurfaceId,
+    // Stores the latest configuration, to describe the textures of the surface.
+    config: Mutex<Option<SurfaceConfiguration>>,
 }
 
 impl Drop for Surface {
@@ -744,6 +826,25 @@ pub struct ShaderModule {
     id: <C as Context>::ShaderModuleId,
 }
 
+impl ShaderModule {
+    /// Returns the entry points of the module, with the resources and the inputs and outputs
+    /// they use.
+    ///
+    /// Returns `None` if the module is invalid, was created from SPIR-V passed through
+    /// to the backend, or on web.
+    pub fn reflect(&self) -> Option<ShaderReflection> {
+        self.context.shader_module_reflect(&self.id)
+    }
+
+    /// Returns the errors and warnings produced while compiling the module.
+    ///
+    /// The messages point at the WGSL source when possible. Modules created from other
+    /// sources only report errors, without a location.
+    pub fn get_compilation_info(&self) -> impl Future<Output = CompilationInfo> + Send {
+        self.context.shader_module_get_compilation_info(&self.id)
+    }
+}
+
 impl Drop for ShaderModule {
     fn drop(&mut self) {
         if !thread::panicking() {
@@ -1311,6 +1412,8 @@ pub struct RenderPipelineDescriptor<'a> {
     /// If the pipeline will be used with a multiview render pass, this indicates how many array
     /// layers the attachments will have.
     pub multiview: Option<NonZeroU32>,
+    /// The cache to look up and store the compiled shaders in, if any.
+    pub cache: Option<&'a PipelineCache>,
 }
 
 /// Describes the attachments of a compute pass.
@@ -1320,6 +1423,49 @@ pub struct ComputePassDescriptor<'a> {
     pub label: Label<'a>,
 }
 
+/// Handle to a pipeline cache.
+///
+/// A `PipelineCache` keeps the shaders translated for the backend, along with the
+/// native pipeline cache of the driver if there is one. Its data can be saved to disk
+/// with [`PipelineCache::get_data`] and passed to [`Device::create_pipeline_cache`]
+/// on the next run, to speed up the creation of the same pipelines.
+///
+/// This has no effect on web.
+#[derive(Debug)]
+pub struct PipelineCache {
+    context: Arc<C>,
+    id: <C as Context>::PipelineCacheId,
+}
+
+impl PipelineCache {
+    /// Returns the data of the cache, to be saved for a later run.
+    ///
+    /// Returns `None` if the cache is invalid, or on web.
+    pub fn get_data(&self) -> Option<Vec<u8>> {
+        self.context.pipeline_cache_get_data(&self.id)
+    }
+}
+
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.context.pipeline_cache_drop(&self.id);
+        }
+    }
+}
+
+/// Describes a [`PipelineCache`].
+#[derive(Clone, Debug)]
+pub struct PipelineCacheDescriptor<'a> {
+    /// Debug label of the pipeline cache. This will show up in graphics debuggers for easy identification.
+    pub label: Label<'a>,
+    /// Data returned by [`PipelineCache::get_data`] on a previous run, if any.
+    pub data: Option<&'a [u8]>,
+    /// If the data was produced by another version of wgpu, another backend, or is corrupted,
+    /// start with an empty cache instead of reporting an error.
+    pub fallback: bool,
+}
+
 /// Describes a compute pipeline.
 #[derive(Clone, Debug)]
 pub struct ComputePipelineDescriptor<'a> {
@@ -1332,6 +1478,8 @@ pub struct ComputePipelineDescriptor<'a> {
     /// The name of the entry point in the compiled shader. There must be a function that returns
     /// void with this name in the shader.
     pub entry_point: &'a str,
+    /// The cache to look up and store the compiled shader in, if any.
+    pub cache: Option<&'a PipelineCache>,
 }
 
 pub use wgt::ImageCopyBuffer as ImageCopyBufferBase;
@@ -1422,6 +1570,28 @@ impl Instance {
         }
     }
 
+    /// Create an new instance of wgpu, sending everything to a `wgpu_remote::Server`
+    /// on the other end of `stream`.
+    ///
+    /// The instance has a single adapter, picked by the server, and
+    /// [`Adapter::request_device`] can only be called once. Surfaces are not supported.
+    /// Validation errors are reported when the device is polled and when error scopes
+    /// are used, rather than right away.
+    ///
+    /// Its objects have no wgpu-hal counterpart: the `as_hal` methods give `None`, while
+    /// the `from_hal` ones and [`Instance::generate_report`] panic. They can't be used
+    /// together with the objects of other instances.
+    ///
+    /// # Arguments
+    ///
+    /// - `stream` - Unix socket connected to the server.
+    #[cfg(all(unix, feature = "remote"))]
+    pub fn connect(stream: std::os::unix::net::UnixStream) -> Self {
+        Self {
+            context: Arc::new(C::connect(stream)),
+        }
+    }
+
     /// Create an new instance of wgpu from a wgpu-hal instance.
     ///
     /// # Arguments
@@ -1497,6 +1667,7 @@ impl Instance {
         Surface {
             context: Arc::clone(&self.context),
             id: Context::instance_create_surface(&*self.context, window),
+            config: Mutex::new(None),
         }
     }
 
@@ -1510,7 +1681,11 @@ impl Instance {
         &self,
         layer: *mut std::ffi::c_void,
     ) -> Surface {
-        self.context.create_surface_from_core_animation_layer(layer)
+        Surface {
+            context: Arc::clone(&self.context),
+            id: self.context.create_surface_from_core_animation_layer(layer),
+            config: Mutex::new(None),
+        }
     }
 
     /// Creates a surface from a `web_sys::HtmlCanvasElement`.
@@ -1526,6 +1701,7 @@ impl Instance {
         Surface {
             context: Arc::clone(&self.context),
             id: self.context.instance_create_surface_from_canvas(canvas),
+            config: Mutex::new(None),
         }
     }
 
@@ -1544,6 +1720,7 @@ impl Instance {
             id: self
                 .context
                 .instance_create_surface_from_offscreen_canvas(canvas),
+            config: Mutex::new(None),
         }
     }
 
@@ -1674,9 +1851,26 @@ impl Adapter {
 impl Device {
     /// Check for resource cleanups and mapping callbacks.
     ///
+    /// With [`Maintain::WaitForSubmissionIndex`], only blocks until the given submission
+    /// is done, instead of all the submitted work.
+    ///
     /// no-op on the web, device is automatically polled.
     pub fn poll(&self, maintain: Maintain) {
-        Context::device_poll(&*self.context, &self.id, maintain);
+        match Context::device_poll(&*self.context, &self.id, maintain, None) {
+            // Lost devices are reported to the callback of `on_device_lost`.
+            Ok(()) | Err(PollError::DeviceLost) => {}
+            Err(error) => log::error!("Device::poll failed: {}", error),
+        }
+    }
+
+    /// Check for resource cleanups and mapping callbacks, waiting at most `timeout`.
+    ///
+    /// Returns [`PollError::Timeout`] if the work isn't done in time, and the device can
+    /// be polled again later.
+    ///
+    /// no-op on the web, device is automatically polled.
+    pub fn poll_timeout(&self, maintain: Maintain, timeout: Duration) -> Result<(), PollError> {
+        Context::device_poll(&*self.context, &self.id, maintain, Some(timeout))
     }
 
     /// List all features that may be used with this device.
@@ -1693,6 +1887,22 @@ impl Device {
         Context::device_limits(&*self.context, &self.id)
     }
 
+    /// Returns the features of a given texture format that can be used on this device.
+    ///
+    /// Unlike [`Adapter::get_texture_format_features`], this only includes the adapter specific
+    /// features if [`Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`] is enabled.
+    pub fn get_texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
+        Context::device_get_texture_format_features(&*self.context, &self.id, format)
+    }
+
+    /// Returns the memory allocated by this device for its buffers and textures.
+    ///
+    /// The driver budget is only reported by the backends that can query it. On the web,
+    /// the report is empty.
+    pub fn memory_report(&self) -> MemoryReport {
+        Context::device_memory_report(&*self.context, &self.id)
+    }
+
     /// Creates a shader module from either SPIR-V or WGSL source code.
     pub fn create_shader_module(&self, desc: &ShaderModuleDescriptor) -> ShaderModule {
         ShaderModule {
@@ -1749,6 +1959,19 @@ impl Device {
         }
     }
 
+    /// Creates a [`PipelineCache`], optionally from the data of a previous run.
+    ///
+    /// # Safety
+    ///
+    /// The data must have been returned by [`PipelineCache::get_data`]. The native
+    /// part of it is passed to the driver, and not every driver validates it properly.
+    pub unsafe fn create_pipeline_cache(&self, desc: &PipelineCacheDescriptor) -> PipelineCache {
+        PipelineCache {
+            context: Arc::clone(&self.context),
+            id: Context::device_create_pipeline_cache(&*self.context, &self.id, desc),
+        }
+    }
+
     /// Creates an empty [`CommandEncoder`].
     pub fn create_command_encoder(&self, desc: &CommandEncoderDescriptor) -> CommandEncoder {
         CommandEncoder {
@@ -1814,6 +2037,41 @@ impl Device {
         }
     }
 
+    /// Creates a [`RenderPipeline`] without blocking on the compilation of its shaders.
+    ///
+    /// Dropping the future doesn't cancel the creation, and the resources referenced by `desc`
+    /// are kept until it's done.
+    /// Unlike with [`Device::create_render_pipeline`], errors are returned by the future
+    /// instead of being reported to the error scopes.
+    pub fn create_render_pipeline_async<'a>(
+        &self,
+        desc: &RenderPipelineDescriptor<'a>,
+    ) -> impl Future<Output = Result<RenderPipeline, Error>> + Send + 'a {
+        let context = Arc::clone(&self.context);
+        let pipeline =
+            Context::device_create_render_pipeline_async(Arc::clone(&self.context), &self.id, desc);
+        async move { pipeline.await.map(|id| RenderPipeline { context, id }) }
+    }
+
+    /// Creates a [`ComputePipeline`] without blocking on the compilation of its shader.
+    ///
+    /// Dropping the future doesn't cancel the creation, and the resources referenced by `desc`
+    /// are kept until it's done.
+    /// Unlike with [`Device::create_compute_pipeline`], errors are returned by the future
+    /// instead of being reported to the error scopes.
+    pub fn create_compute_pipeline_async<'a>(
+        &self,
+        desc: &ComputePipelineDescriptor<'a>,
+    ) -> impl Future<Output = Result<ComputePipeline, Error>> + Send + 'a {
+        let context = Arc::clone(&self.context);
+        let pipeline = Context::device_create_compute_pipeline_async(
+            Arc::clone(&self.context),
+            &self.id,
+            desc,
+        );
+        async move { pipeline.await.map(|id| ComputePipeline { context, id }) }
+    }
+
     /// Creates a [`Buffer`].
     pub fn create_buffer(&self, desc: &BufferDescriptor) -> Buffer {
         let mut map_context = MapContext::new(desc.size);
@@ -1836,6 +2094,7 @@ impl Device {
             context: Arc::clone(&self.context),
             id: Context::device_create_texture(&*self.context, &self.id, desc),
             owned: true,
+            descriptor: desc.map_label(|_| ()),
         }
     }
 
@@ -1858,6 +2117,34 @@ impl Device {
                 .context
                 .create_texture_from_hal::<A>(hal_texture, &self.id, desc),
             owned: true,
+            descriptor: desc.map_label(|_| ()),
+        }
+    }
+
+    /// Creates a [`Buffer`] from a wgpu-hal Buffer.
+    ///
+    /// # Safety
+    ///
+    /// - `hal_buffer` must be created from this device internal handle
+    /// - `hal_buffer` must be created respecting `desc`
+    /// - `hal_buffer` must be initialized
+    /// - `desc.mapped_at_creation` must be `false`
+    ///
+    /// The buffer can't be mapped, so `desc.usage` can't contain [`BufferUsages::MAP_READ`] or
+    /// [`BufferUsages::MAP_WRITE`]. `hal_buffer` is destroyed if the creation fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn create_buffer_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        desc: &BufferDescriptor,
+    ) -> Buffer {
+        Buffer {
+            context: Arc::clone(&self.context),
+            id: self
+                .context
+                .create_buffer_from_hal::<A>(hal_buffer, &self.id, desc),
+            map_context: Mutex::new(MapContext::new(desc.size)),
+            usage: desc.usage,
         }
     }
 
@@ -1884,6 +2171,24 @@ impl Device {
         self.context.device_on_uncaptured_error(&self.id, handler);
     }
 
+    /// Set a callback for when the device is lost, with the reason and a message.
+    ///
+    /// Once the device is lost, polling and submitting work return errors instead of waiting
+    /// on the GPU. The callback is called right away if the device is already lost.
+    pub fn on_device_lost(&self, callback: impl FnOnce(DeviceLostReason, String) + Send + 'static) {
+        self.context.device_on_device_lost(&self.id, callback);
+    }
+
+    /// Destroy the device, freeing its buffers and textures right away.
+    ///
+    /// The device is lost with [`DeviceLostReason::Destroyed`]. Later operations on it,
+    /// or on its resources, report validation errors instead of doing any work.
+    /// Pending buffer mappings fail, and buffers that are currently mapped are only
+    /// freed when dropped.
+    pub fn destroy(&self) {
+        self.context.device_destroy(&self.id)
+    }
+
     /// Push an error scope.
     pub fn push_error_scope(&self, filter: ErrorFilter) {
         self.context.device_push_error_scope(&self.id, filter);
@@ -1940,6 +2245,26 @@ impl Display for RequestDeviceError {
 
 impl error::Error for RequestDeviceError {}
 
+/// Error returned by [`Device::poll_timeout`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PollError {
+    /// The work wasn't done before the timeout.
+    Timeout,
+    /// The device is lost, see [`Device::on_device_lost`].
+    DeviceLost,
+}
+
+impl Display for PollError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            PollError::Timeout => write!(f, "Timed out waiting for the device"),
+            PollError::DeviceLost => write!(f, "The device is lost"),
+        }
+    }
+}
+
+impl error::Error for PollError {}
+
 /// Error occurred when trying to async map a buffer.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct BufferAsyncError;
@@ -2011,6 +2336,49 @@ trait BufferMappedRangeSlice {
     fn slice_mut(&mut self) -> &mut [u8];
 }
 
+/// Write only view into the staging memory of a queue, returned by [`Queue::write_buffer_with`].
+///
+/// The data is copied into the target buffer when the view is dropped.
+#[derive(Debug)]
+pub struct QueueWriteBufferView<'a> {
+    queue: &'a Queue,
+    buffer: &'a Buffer,
+    offset: BufferAddress,
+    inner: Option<QueueWriteBuffer>,
+}
+
+impl std::ops::Deref for QueueWriteBufferView<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        panic!("QueueWriteBufferView is write-only!");
+    }
+}
+
+impl std::ops::DerefMut for QueueWriteBufferView<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.as_mut().unwrap().slice_mut()
+    }
+}
+
+impl AsMut<[u8]> for QueueWriteBufferView<'_> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.inner.as_mut().unwrap().slice_mut()
+    }
+}
+
+impl Drop for QueueWriteBufferView<'_> {
+    fn drop(&mut self) {
+        Context::queue_write_staging_buffer(
+            &*self.queue.context,
+            &self.queue.id,
+            &self.buffer.id,
+            self.offset,
+            self.inner.take().unwrap(),
+        );
+    }
+}
+
 /// Read only view into a mapped buffer.
 #[derive(Debug)]
 pub struct BufferView<'a> {
@@ -2086,6 +2454,21 @@ impl Drop for BufferViewMut<'_> {
 }
 
 impl Buffer {
+    /// Returns the inner hal Buffer using a callback. The hal buffer will be `None` if the
+    /// backend type argument does not match with this wgpu Buffer, or if it was destroyed
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal Buffer must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Buffer>)>(
+        &self,
+        hal_buffer_callback: F,
+    ) {
+        self.context
+            .buffer_as_hal::<A, F>(&self.id, hal_buffer_callback)
+    }
+
     /// Return the binding view of the entire buffer.
     pub fn as_entire_binding(&self) -> BindingResource {
         BindingResource::Buffer(self.as_entire_buffer_binding())
@@ -2244,6 +2627,23 @@ impl Drop for Texture {
     }
 }
 
+impl TextureView {
+    /// Returns the inner hal TextureView using a callback. The hal texture view will be `None`
+    /// if the backend type argument does not match with this wgpu TextureView
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal TextureView must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::TextureView>)>(
+        &self,
+        hal_texture_view_callback: F,
+    ) {
+        self.context
+            .texture_view_as_hal::<A, F>(&self.id, hal_texture_view_callback)
+    }
+}
+
 impl Drop for TextureView {
     fn drop(&mut self) {
         if !thread::panicking() {
@@ -2264,6 +2664,30 @@ impl CommandEncoder {
         }
     }
 
+    /// Returns the inner hal CommandEncoder using a callback, opened for recording. The hal
+    /// command encoder will be `None` if the backend type argument does not match with this
+    /// wgpu CommandEncoder, or if it is invalid
+    ///
+    /// # Safety
+    ///
+    /// - The raw command encoder must not be finished or reset
+    /// - The commands recorded through it aren't tracked: the resources they use must be in
+    ///   the state expected by the commands, and kept alive until they're done executing
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal_mut<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &mut self,
+        hal_command_encoder_callback: F,
+    ) -> R {
+        self.context.command_encoder_as_hal_mut::<A, F, R>(
+            self.id.as_ref().unwrap(),
+            hal_command_encoder_callback,
+        )
+    }
+
     /// Begins recording of a render pass.
     ///
     /// This function returns a [`RenderPass`] object which records a single render pass.
@@ -3089,6 +3513,29 @@ impl Queue {
         Context::queue_write_buffer(&*self.context, &self.id, &buffer.id, offset, data)
     }
 
+    /// Schedule a data write of `size` bytes into `buffer` starting at `offset`,
+    /// returning a view into the staging memory of the queue to write the data into.
+    ///
+    /// The view starts out zeroed. The data is copied into `buffer` once the view is dropped,
+    /// like with [`Queue::write_buffer`], but without copying it into the staging memory first.
+    ///
+    /// Returns `None` if the write is invalid, after reporting the error.
+    pub fn write_buffer_with<'a>(
+        &'a self,
+        buffer: &'a Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+    ) -> Option<QueueWriteBufferView<'a>> {
+        Context::queue_validate_write_buffer(&*self.context, &self.id, &buffer.id, offset, size)?;
+        let staging_buffer = Context::queue_create_staging_buffer(&*self.context, &self.id, size)?;
+        Some(QueueWriteBufferView {
+            queue: self,
+            buffer,
+            offset,
+            inner: Some(staging_buffer),
+        })
+    }
+
     /// Schedule a data write into `texture`.
     ///
     /// This method is intended to have low performance costs.
@@ -3107,14 +3554,20 @@ impl Queue {
     }
 
     /// Submits a series of finished command buffers for execution.
-    pub fn submit<I: IntoIterator<Item = CommandBuffer>>(&self, command_buffers: I) {
-        Context::queue_submit(
+    ///
+    /// Returns the index of the submission, to be waited on with [`Maintain::WaitForSubmissionIndex`].
+    pub fn submit<I: IntoIterator<Item = CommandBuffer>>(
+        &self,
+        command_buffers: I,
+    ) -> SubmissionIndex {
+        let raw = Context::queue_submit(
             &*self.context,
             &self.id,
             command_buffers
                 .into_iter()
                 .map(|mut comb| comb.id.take().unwrap()),
         );
+        SubmissionIndex(raw)
     }
 
     /// Gets the amount of nanoseconds each tick of a timestamp query represents.
@@ -3168,7 +3621,27 @@ impl Surface {
     /// - A old [`SurfaceTexture`] is still alive referencing an old surface.
     /// - Texture format requested is unsupported on the surface.
     pub fn configure(&self, device: &Device, config: &SurfaceConfiguration) {
-        Context::surface_configure(&*self.context, &self.id, &device.id, config)
+        Context::surface_configure(&*self.context, &self.id, &device.id, config);
+        *self.config.lock() = Some(config.clone());
+    }
+
+    /// Descriptor of the textures of the surface, once it's configured.
+    fn texture_descriptor(&self) -> Option<wgt::TextureDescriptor<()>> {
+        let config = self.config.lock();
+        let config = config.as_ref()?;
+        Some(wgt::TextureDescriptor {
+            label: (),
+            size: Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+        })
     }
 
     /// Returns the next texture to be presented by the swapchain for drawing.
@@ -3191,12 +3664,15 @@ impl Surface {
             SurfaceStatus::Lost => return Err(SurfaceError::Lost),
         };
 
+        // The backends only return a texture for a configured surface.
         texture_id
-            .map(|id| SurfaceTexture {
+            .zip(self.texture_descriptor())
+            .map(|(id, descriptor)| SurfaceTexture {
                 texture: Texture {
                     context: Arc::clone(&self.context),
                     id,
                     owned: false,
+                    descriptor,
                 },
                 suboptimal,
                 presented: false,
@@ -1,10 +1,20 @@
+use super::align_to;
 use crate::{
     Buffer, BufferAddress, BufferDescriptor, BufferSize, BufferUsages, BufferViewMut,
-    CommandEncoder, Device, MapMode,
+    CommandEncoder, Device, Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, MapMode,
+    TextureFormat,
 };
 use std::pin::Pin;
 use std::task::{self, Poll};
-use std::{future::Future, sync::mpsc};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
 
 // Given a vector of futures, poll each in parallel until all are ready.
 struct Join<F> {
@@ -38,33 +48,80 @@ impl<F: Future<Output = ()>> Future for Join<F> {
     }
 }
 
+/// The buffer of a chunk is being mapped.
+const CHUNK_PENDING: u8 = 0;
+/// The buffer of a chunk is mapped, and can be written to.
+const CHUNK_MAPPED: u8 = 1;
+/// The buffer of a chunk failed to map, or the future mapping it was dropped,
+/// so it's never going to be mapped.
+const CHUNK_FAILED: u8 = 2;
+
+/// A staging buffer, sub-allocated from the start.
 struct Chunk {
     buffer: Buffer,
     size: BufferAddress,
     offset: BufferAddress,
+    state: Arc<AtomicU8>,
+}
+
+/// Marks a chunk as failed if the future mapping it is dropped before it's done.
+struct PendingChunk(Arc<AtomicU8>);
+
+impl Drop for PendingChunk {
+    fn drop(&mut self) {
+        let _ = self.0.compare_exchange(
+            CHUNK_PENDING,
+            CHUNK_FAILED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+    }
+}
+
+/// Usage statistics of a [`StagingBelt`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StagingBeltStats {
+    /// Number of the sub-allocations made since the belt was created.
+    pub allocations: u64,
+    /// Bytes handed out by the sub-allocations, without the alignment padding.
+    pub allocated_bytes: BufferAddress,
+    /// Number of the staging buffers created since the belt was created.
+    pub buffers_created: u64,
+    /// Number of the staging buffers that the belt currently owns.
+    pub buffers: usize,
+    /// Total size of the staging buffers that the belt currently owns.
+    pub buffer_bytes: BufferAddress,
 }
 
 /// Staging belt is a machine that uploads data.
 ///
-/// Internally it uses a ring-buffer of staging buffers that are sub-allocated.
+/// Internally it uses a ring-buffer of staging buffers of `chunk_size` bytes,
+/// that are sub-allocated one after another.
 /// It has an advantage over `Queue.write_buffer` in a way that it returns a mutable slice,
 /// which you can fill to avoid an extra data copy.
 ///
+/// Once the uploads of a frame fit into the ring, no more staging buffers are created.
+/// Uploads larger than `chunk_size` get a staging buffer of their own, which is freed
+/// on `recall`.
+///
 /// Using a staging belt is slightly complicated, and generally goes as follows:
-/// - Write to buffers that need writing to using `write_buffer`.
+/// - Write to buffers and textures that need writing to using `write_buffer`
+///   and `write_texture`.
 /// - Call `finish`.
-/// - Submit all command encoders used with `write_buffer`.
-/// - Call `recall`
+/// - Submit all command encoders used with `write_buffer` and `write_texture`.
+/// - Call `recall`, and drive the returned future to completion.
 pub struct StagingBelt {
     chunk_size: BufferAddress,
-    /// Chunks that we are actively using for pending transfers at this moment.
-    active_chunks: Vec<Chunk>,
-    /// Chunks that have scheduled transfers already.
+    /// Chunk that we are sub-allocating from at this moment.
+    active_chunk: Option<Chunk>,
+    /// Chunks that are full, and waiting for `finish`.
     closed_chunks: Vec<Chunk>,
-    /// Chunks that are back from the GPU and ready to be used.
-    free_chunks: Vec<Chunk>,
-    sender: mpsc::Sender<Chunk>,
-    receiver: mpsc::Receiver<Chunk>,
+    /// Chunks given back by `recall`, in the order of submission.
+    /// The front one is the first to be mapped again.
+    ring: VecDeque<Chunk>,
+    allocations: u64,
+    allocated_bytes: BufferAddress,
+    buffers_created: u64,
 }
 
 impl StagingBelt {
@@ -74,17 +131,81 @@ impl StagingBelt {
     /// It's better when it's big, but ideally still 1-4 times less than
     /// the total amount of data uploaded per submission.
     pub fn new(chunk_size: BufferAddress) -> Self {
-        let (sender, receiver) = mpsc::channel();
         StagingBelt {
             chunk_size,
-            active_chunks: Vec::new(),
+            active_chunk: None,
             closed_chunks: Vec::new(),
-            free_chunks: Vec::new(),
-            sender,
-            receiver,
+            ring: VecDeque::new(),
+            allocations: 0,
+            allocated_bytes: 0,
+            buffers_created: 0,
         }
     }
 
+    /// Free the chunks that are never going to be mapped again.
+    fn drop_failed_chunks(&mut self) {
+        self.ring
+            .retain(|chunk| chunk.state.load(Ordering::Acquire) != CHUNK_FAILED);
+    }
+
+    /// Get the staging buffer to sub-allocate `size` bytes from next.
+    fn next_chunk(&mut self, size: BufferAddress, device: &Device) -> Chunk {
+        if size <= self.chunk_size {
+            self.drop_failed_chunks();
+            // The chunks are usually mapped in order, but one that is still being mapped
+            // doesn't hold back the ones after it.
+            let ready = self
+                .ring
+                .iter()
+                .position(|chunk| chunk.state.load(Ordering::Acquire) == CHUNK_MAPPED);
+            if let Some(index) = ready {
+                let mut chunk = self.ring.remove(index).unwrap();
+                chunk.offset = 0;
+                return chunk;
+            }
+        }
+
+        let size = self.chunk_size.max(size);
+        self.buffers_created += 1;
+        Chunk {
+            buffer: device.create_buffer(&BufferDescriptor {
+                label: Some("staging"),
+                size,
+                usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            }),
+            size,
+            offset: 0,
+            state: Arc::new(AtomicU8::new(CHUNK_MAPPED)),
+        }
+    }
+
+    /// Sub-allocate `size` bytes from the active chunk, returning the offset in it.
+    fn allocate(
+        &mut self,
+        size: BufferAddress,
+        alignment: BufferAddress,
+        device: &Device,
+    ) -> BufferAddress {
+        let fits = match self.active_chunk {
+            Some(ref chunk) => align_to(chunk.offset, alignment) + size <= chunk.size,
+            None => false,
+        };
+        if !fits {
+            let chunk = self.next_chunk(size, device);
+            if let Some(full) = self.active_chunk.replace(chunk) {
+                self.closed_chunks.push(full);
+            }
+        }
+
+        let chunk = self.active_chunk.as_mut().unwrap();
+        let offset = align_to(chunk.offset, alignment);
+        chunk.offset = offset + size;
+        self.allocations += 1;
+        self.allocated_bytes += size;
+        offset
+    }
+
     /// Allocate the staging belt slice of `size` to be uploaded into the `target` buffer
     /// at the specified offset.
     ///
@@ -98,47 +219,66 @@ impl StagingBelt {
         size: BufferSize,
         device: &Device,
     ) -> BufferViewMut {
-        let mut chunk = if let Some(index) = self
-            .active_chunks
-            .iter()
-            .position(|chunk| chunk.offset + size.get() <= chunk.size)
-        {
-            self.active_chunks.swap_remove(index)
-        } else if let Some(index) = self
-            .free_chunks
-            .iter()
-            .position(|chunk| size.get() <= chunk.size)
-        {
-            self.free_chunks.swap_remove(index)
-        } else {
-            let size = self.chunk_size.max(size.get());
-            Chunk {
-                buffer: device.create_buffer(&BufferDescriptor {
-                    label: Some("staging"),
-                    size,
-                    usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
-                    mapped_at_creation: true,
-                }),
-                size,
-                offset: 0,
-            }
-        };
+        let staging_offset = self.allocate(size.get(), crate::MAP_ALIGNMENT, device);
+        let buffer = &self.active_chunk.as_ref().unwrap().buffer;
+        encoder.copy_buffer_to_buffer(buffer, staging_offset, target, offset, size.get());
+        buffer
+            .slice(staging_offset..staging_offset + size.get())
+            .get_mapped_range_mut()
+    }
 
-        encoder.copy_buffer_to_buffer(&chunk.buffer, chunk.offset, target, offset, size.get());
-        let old_offset = chunk.offset;
-        chunk.offset += size.get();
-        let remainder = chunk.offset % crate::MAP_ALIGNMENT;
-        if remainder != 0 {
-            chunk.offset += crate::MAP_ALIGNMENT - remainder;
-        }
+    /// Allocate the staging belt slice for the `size` texels of a texture of the given
+    /// `format`, to be uploaded into the `target` texture.
+    ///
+    /// Returns the slice along with the number of bytes between the starts of the rows in it.
+    /// The rows are padded to [`COPY_BYTES_PER_ROW_ALIGNMENT`](crate::COPY_BYTES_PER_ROW_ALIGNMENT),
+    /// and the images of the layers follow each other without any padding rows.
+    /// For compressed formats, a row is a row of blocks, and `size` has to be
+    /// a multiple of the block dimensions.
+    ///
+    /// The upload will be placed into the provided command encoder. This encoder
+    /// must be submitted after `finish` is called and before `recall` is called.
+    pub fn write_texture(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        target: ImageCopyTexture,
+        format: TextureFormat,
+        size: Extent3d,
+        device: &Device,
+    ) -> (BufferViewMut, u32) {
+        let info = format.describe();
+        let block_width = info.block_dimensions.0 as u32;
+        let block_height = info.block_dimensions.1 as u32;
+        let rows_per_image = size.height / block_height;
+        let row_size = size.width / block_width * info.block_size as u32;
+        let bytes_per_row = align_to(
+            row_size as BufferAddress,
+            crate::COPY_BYTES_PER_ROW_ALIGNMENT as BufferAddress,
+        );
+        let total_size = bytes_per_row
+            * rows_per_image as BufferAddress
+            * size.depth_or_array_layers as BufferAddress;
 
-        self.active_chunks.push(chunk);
-        self.active_chunks
-            .last()
-            .unwrap()
-            .buffer
-            .slice(old_offset..old_offset + size.get())
-            .get_mapped_range_mut()
+        // Copies from buffers have to start at a texel block.
+        let alignment = crate::MAP_ALIGNMENT.max(info.block_size as BufferAddress);
+        let staging_offset = self.allocate(total_size, alignment, device);
+        let buffer = &self.active_chunk.as_ref().unwrap().buffer;
+        encoder.copy_buffer_to_texture(
+            ImageCopyBuffer {
+                buffer,
+                layout: ImageDataLayout {
+                    offset: staging_offset,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row as u32),
+                    rows_per_image: NonZeroU32::new(rows_per_image),
+                },
+            },
+            target,
+            size,
+        );
+        let view = buffer
+            .slice(staging_offset..staging_offset + total_size)
+            .get_mapped_range_mut();
+        (view, bytes_per_row as u32)
     }
 
     /// Prepare currently mapped buffers for use in a submission.
@@ -146,39 +286,66 @@ impl StagingBelt {
     /// At this point, all the partially used staging buffers are closed until
     /// the GPU is done copying the data from them.
     pub fn finish(&mut self) {
-        for chunk in self.active_chunks.drain(..) {
-            chunk.buffer.unmap();
+        if let Some(chunk) = self.active_chunk.take() {
             self.closed_chunks.push(chunk);
         }
+        for chunk in self.closed_chunks.iter() {
+            chunk.buffer.unmap();
+        }
     }
 
     /// Recall all of the closed buffers back to be reused.
     ///
     /// This has to be called after the command encoders written to `write_buffer` are submitted!
+    /// The buffers are only reused once the returned future is driven far enough to see
+    /// them mapped again. Dropping the future before it's done, or a failure to map them,
+    /// makes the belt free the buffers instead.
     pub fn recall(&mut self) -> impl Future<Output = ()> + Send {
-        while let Ok(mut chunk) = self.receiver.try_recv() {
-            chunk.offset = 0;
-            self.free_chunks.push(chunk);
-        }
-
-        let sender = &self.sender;
+        self.drop_failed_chunks();
+        let chunk_size = self.chunk_size;
+        let ring = &mut self.ring;
         let futures = self
             .closed_chunks
             .drain(..)
+            // Oversized buffers are only used once.
+            .filter(|chunk| chunk.size == chunk_size)
             .map(|chunk| {
-                let sender = sender.clone();
+                let state = Arc::clone(&chunk.state);
+                state.store(CHUNK_PENDING, Ordering::Release);
+                let pending = PendingChunk(Arc::clone(&state));
                 let async_buffer = chunk.buffer.slice(..).map_async(MapMode::Write);
+                ring.push_back(chunk);
 
                 Some(async move {
-                    // The result is ignored
-                    async_buffer.await.ok();
-
-                    // The only possible error is the other side disconnecting, which is fine
-                    let _ = sender.send(chunk);
+                    let _pending = pending;
+                    let result = match async_buffer.await {
+                        Ok(()) => CHUNK_MAPPED,
+                        Err(_) => CHUNK_FAILED,
+                    };
+                    state.store(result, Ordering::Release);
                 })
             })
             .collect::<Vec<_>>();
 
         Join { futures }
     }
+
+    /// Get the usage statistics of the belt.
+    pub fn stats(&self) -> StagingBeltStats {
+        let chunks = self
+            .active_chunk
+            .iter()
+            .chain(self.closed_chunks.iter())
+            .chain(self.ring.iter());
+        let (buffers, buffer_bytes) = chunks.fold((0, 0), |(count, bytes), chunk| {
+            (count + 1, bytes + chunk.size)
+        });
+        StagingBeltStats {
+            allocations: self.allocations,
+            allocated_bytes: self.allocated_bytes,
+            buffers_created: self.buffers_created,
+            buffers,
+            buffer_bytes,
+        }
+    }
 }
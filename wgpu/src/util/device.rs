@@ -1,5 +1,7 @@
 use std::num::NonZeroU32;
 
+use super::mipmap::MipmapPipeline;
+
 /// Describes a [Buffer](crate::Buffer) when allocating.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct BufferInitDescriptor<'a> {
@@ -12,6 +14,19 @@ pub struct BufferInitDescriptor<'a> {
     pub usage: crate::BufferUsages,
 }
 
+/// One image of a texture, uploaded by [`DeviceExt::create_texture_with_images`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureImage<'a> {
+    /// Mip level of the image.
+    pub mip_level: u32,
+    /// Array layer of the image. Must be 0 for 3D textures, where the image holds all the
+    /// depth slices of the mip level.
+    pub array_layer: u32,
+    /// Tightly packed rows of the image. For compressed formats, a row is a row of texel
+    /// blocks, and the image size is rounded up to whole blocks.
+    pub data: &'a [u8],
+}
+
 /// Utility methods not meant to be in the main API.
 pub trait DeviceExt {
     /// Creates a [Buffer](crate::Buffer) with data to initialize it.
@@ -35,6 +50,35 @@ pub trait DeviceExt {
         desc: &crate::TextureDescriptor,
         data: &[u8],
     ) -> crate::Texture;
+
+    /// Create a texture and upload the given images to it.
+    ///
+    /// Images can be given in any order and for any mip levels and array layers of the
+    /// texture. Their rows are padded as required by [`COPY_BYTES_PER_ROW_ALIGNMENT`], and
+    /// all of them are uploaded through a single staging buffer in one submission.
+    ///
+    /// If `generate_mipmaps` is set, the mip levels of each layer that have no image are
+    /// generated on the GPU from the level above them, the same way as [`generate_mipmaps`].
+    /// Otherwise these mip levels are left zeroed.
+    ///
+    /// Implicitly adds the `COPY_DST` usage, as well as the usages needed to generate
+    /// mipmaps.
+    ///
+    /// # Panics
+    ///
+    /// - If an image is out of the bounds of the texture.
+    /// - If the size of an image doesn't match its mip level.
+    /// - If `generate_mipmaps` is set, and [`generate_mipmaps`] doesn't support the texture.
+    ///
+    /// [`COPY_BYTES_PER_ROW_ALIGNMENT`]: crate::COPY_BYTES_PER_ROW_ALIGNMENT
+    /// [`generate_mipmaps`]: super::generate_mipmaps
+    fn create_texture_with_images(
+        &self,
+        queue: &crate::Queue,
+        desc: &crate::TextureDescriptor,
+        images: &[TextureImage],
+        generate_mipmaps: bool,
+    ) -> crate::Texture;
 }
 
 impl DeviceExt for crate::Device {
@@ -144,4 +188,157 @@ impl DeviceExt for crate::Device {
 
         texture
     }
+    fn create_texture_with_images(
+        &self,
+        queue: &crate::Queue,
+        desc: &crate::TextureDescriptor,
+        images: &[TextureImage],
+        generate_mipmaps: bool,
+    ) -> crate::Texture {
+        let mut desc = desc.to_owned();
+        desc.usage |= crate::TextureUsages::COPY_DST;
+        let mipmap_pipeline = if generate_mipmaps {
+            assert_eq!(
+                desc.dimension,
+                wgt::TextureDimension::D2,
+                "mipmaps can only be generated for 2D textures"
+            );
+            let pipeline =
+                MipmapPipeline::new(self, desc.format).unwrap_or_else(|err| panic!("{}", err));
+            desc.usage |= pipeline.usages();
+            Some(pipeline)
+        } else {
+            None
+        };
+        let texture = self.create_texture(&desc);
+
+        let format_info = desc.format.describe();
+        let block_width = format_info.block_dimensions.0 as u32;
+        let block_height = format_info.block_dimensions.1 as u32;
+        // Copies have to start at a texel block, and at a multiple of 4 bytes.
+        let alignment =
+            (format_info.block_size as crate::BufferAddress).max(crate::COPY_BUFFER_ALIGNMENT);
+
+        // Lay out every image in the staging buffer, with padded rows.
+        let mut copies = Vec::with_capacity(images.len());
+        let mut staging_size: crate::BufferAddress = 0;
+        for image in images {
+            assert!(
+                image.array_layer < desc.array_layer_count(),
+                "array layer {} is out of bounds",
+                image.array_layer
+            );
+            let mut mip_size = desc
+                .mip_level_size(image.mip_level)
+                .unwrap_or_else(|| panic!("mip level {} is out of bounds", image.mip_level));
+            if desc.dimension != wgt::TextureDimension::D3 {
+                mip_size.depth_or_array_layers = 1;
+            }
+            let mip_physical = mip_size.physical_size(desc.format);
+            let width_blocks = mip_physical.width / block_width;
+            let height_blocks = mip_physical.height / block_height;
+
+            let unpadded_bytes_per_row = width_blocks * format_info.block_size as u32;
+            let rows = height_blocks * mip_physical.depth_or_array_layers;
+            assert_eq!(
+                image.data.len(),
+                (unpadded_bytes_per_row * rows) as usize,
+                "image of mip level {} of array layer {} has the wrong size",
+                image.mip_level,
+                image.array_layer
+            );
+            let bytes_per_row = super::align_to(
+                unpadded_bytes_per_row as crate::BufferAddress,
+                crate::COPY_BYTES_PER_ROW_ALIGNMENT as crate::BufferAddress,
+            );
+
+            let offset = super::align_to(staging_size, alignment);
+            staging_size = offset + bytes_per_row * rows as crate::BufferAddress;
+            copies.push(ImageCopy {
+                offset,
+                unpadded_bytes_per_row: unpadded_bytes_per_row as usize,
+                bytes_per_row: bytes_per_row as u32,
+                rows_per_image: height_blocks,
+                size: mip_physical,
+            });
+        }
+
+        let mut encoder = self.create_command_encoder(&crate::CommandEncoderDescriptor {
+            label: Some("texture upload"),
+        });
+
+        if staging_size != 0 {
+            let staging = self.create_buffer(&crate::BufferDescriptor {
+                label: Some("texture upload"),
+                size: super::align_to(staging_size, crate::COPY_BUFFER_ALIGNMENT),
+                usage: crate::BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            });
+            {
+                let mut mapping = staging.slice(..).get_mapped_range_mut();
+                for (image, copy) in images.iter().zip(&copies) {
+                    if copy.unpadded_bytes_per_row == 0 {
+                        continue;
+                    }
+                    let rows = image.data.chunks(copy.unpadded_bytes_per_row);
+                    let padded_rows =
+                        mapping[copy.offset as usize..].chunks_mut(copy.bytes_per_row as usize);
+                    for (row, padded_row) in rows.zip(padded_rows) {
+                        padded_row[..row.len()].copy_from_slice(row);
+                    }
+                }
+            }
+            staging.unmap();
+
+            for (image, copy) in images.iter().zip(&copies) {
+                encoder.copy_buffer_to_texture(
+                    crate::ImageCopyBuffer {
+                        buffer: &staging,
+                        layout: crate::ImageDataLayout {
+                            offset: copy.offset,
+                            bytes_per_row: NonZeroU32::new(copy.bytes_per_row),
+                            rows_per_image: NonZeroU32::new(copy.rows_per_image),
+                        },
+                    },
+                    crate::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: image.mip_level,
+                        origin: crate::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: image.array_layer,
+                        },
+                        aspect: wgt::TextureAspect::All,
+                    },
+                    copy.size,
+                );
+            }
+        }
+
+        if let Some(pipeline) = mipmap_pipeline {
+            for array_layer in 0..desc.array_layer_count() {
+                for mip_level in 1..desc.mip_level_count {
+                    let given = images.iter().any(|image| {
+                        image.array_layer == array_layer && image.mip_level == mip_level
+                    });
+                    if !given {
+                        pipeline.generate(self, &mut encoder, &texture, array_layer, mip_level);
+                    }
+                }
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+
+        texture
+    }
+}
+
+/// Where an image of [`DeviceExt::create_texture_with_images`] sits in the staging buffer.
+struct ImageCopy {
+    offset: crate::BufferAddress,
+    unpadded_bytes_per_row: usize,
+    bytes_per_row: u32,
+    rows_per_image: u32,
+    size: crate::Extent3d,
 }
@@ -0,0 +1,272 @@
+use super::{checked_size, read_u32, ImageError, TextureFile};
+use crate::{Extent3d, TextureDimension, TextureFormat};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 128;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+const fn four_cc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+pub(super) fn load(bytes: &[u8]) -> Result<TextureFile, ImageError> {
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(ImageError::Invalid("not a DDS file"));
+    }
+
+    let flags = read_u32(bytes, 8)?;
+    let height = read_u32(bytes, 12)?.max(1);
+    let width = read_u32(bytes, 16)?.max(1);
+    let depth = read_u32(bytes, 24)?.max(1);
+    let mip_level_count = if flags & DDSD_MIPMAPCOUNT != 0 {
+        read_u32(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+    let pixel_flags = read_u32(bytes, 80)?;
+    let fourcc = read_u32(bytes, 84)?;
+    let caps2 = read_u32(bytes, 112)?;
+
+    let (format, dimension, layers, is_cube, data_offset) =
+        if pixel_flags & DDPF_FOURCC != 0 && fourcc == four_cc(b"DX10") {
+            let dxgi_format = read_u32(bytes, 128)?;
+            let dimension = match read_u32(bytes, 132)? {
+                2 => TextureDimension::D1,
+                3 => TextureDimension::D2,
+                4 => TextureDimension::D3,
+                _ => return Err(ImageError::Invalid("unknown DDS resource dimension")),
+            };
+            let is_cube = read_u32(bytes, 136)? & DDS_RESOURCE_MISC_TEXTURECUBE != 0;
+            let array_size = read_u32(bytes, 140)?.max(1);
+            let format = match dxgi_format_to_texture_format(dxgi_format) {
+                Some(format) => format,
+                None => return Err(ImageError::Unsupported("DXGI format of the DDS file")),
+            };
+            let layers = match (is_cube, array_size.checked_mul(6)) {
+                (false, _) => array_size,
+                (true, Some(layers)) => layers,
+                (true, None) => return Err(ImageError::Invalid("DDS array size is too large")),
+            };
+            (
+                format,
+                dimension,
+                layers,
+                is_cube,
+                HEADER_SIZE + DX10_HEADER_SIZE,
+            )
+        } else {
+            let format = if pixel_flags & DDPF_FOURCC != 0 {
+                fourcc_to_texture_format(fourcc)
+            } else if pixel_flags & (DDPF_RGB | DDPF_LUMINANCE) != 0 {
+                let masks = [
+                    read_u32(bytes, 92)?,
+                    read_u32(bytes, 96)?,
+                    read_u32(bytes, 100)?,
+                    read_u32(bytes, 104)?,
+                ];
+                masks_to_texture_format(read_u32(bytes, 88)?, masks)
+            } else {
+                None
+            };
+            let format = match format {
+                Some(format) => format,
+                None => return Err(ImageError::Unsupported("pixel format of the DDS file")),
+            };
+            let is_cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+            if is_cube && caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+                return Err(ImageError::Unsupported("DDS cube maps with missing faces"));
+            }
+            let dimension = if caps2 & DDSCAPS2_VOLUME != 0 {
+                TextureDimension::D3
+            } else {
+                TextureDimension::D2
+            };
+            let layers = if is_cube { 6 } else { 1 };
+            (format, dimension, layers, is_cube, HEADER_SIZE)
+        };
+
+    let size = match dimension {
+        TextureDimension::D1 => Extent3d {
+            width,
+            height: 1,
+            depth_or_array_layers: layers,
+        },
+        TextureDimension::D2 => Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        TextureDimension::D3 => {
+            if layers != 1 {
+                return Err(ImageError::Unsupported("arrays of 3D textures"));
+            }
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: depth,
+            }
+        }
+    };
+
+    let file = TextureFile {
+        size,
+        mip_level_count,
+        dimension,
+        is_cube,
+        format,
+        data: Vec::new(),
+    };
+
+    file.validate_counts(bytes.len())?;
+
+    // DDS files already store all the mip levels of a layer before the next layer.
+    let layer_size = (0..mip_level_count).try_fold(0usize, |size, mip_level| {
+        checked_size(size.checked_add(file.image_size(mip_level)?))
+    })?;
+    let data_size = checked_size(layer_size.checked_mul(file.layer_count() as usize))?;
+    match bytes.get(data_offset..checked_size(data_offset.checked_add(data_size))?) {
+        Some(data) => Ok(TextureFile {
+            data: data.to_vec(),
+            ..file
+        }),
+        None => Err(ImageError::Invalid("file is truncated")),
+    }
+}
+
+fn fourcc_to_texture_format(fourcc: u32) -> Option<TextureFormat> {
+    use TextureFormat as Tf;
+
+    Some(match fourcc {
+        x if x == four_cc(b"DXT1") => Tf::Bc1RgbaUnorm,
+        x if x == four_cc(b"DXT2") || x == four_cc(b"DXT3") => Tf::Bc2RgbaUnorm,
+        x if x == four_cc(b"DXT4") || x == four_cc(b"DXT5") => Tf::Bc3RgbaUnorm,
+        x if x == four_cc(b"ATI1") || x == four_cc(b"BC4U") => Tf::Bc4RUnorm,
+        x if x == four_cc(b"BC4S") => Tf::Bc4RSnorm,
+        x if x == four_cc(b"ATI2") || x == four_cc(b"BC5U") => Tf::Bc5RgUnorm,
+        x if x == four_cc(b"BC5S") => Tf::Bc5RgSnorm,
+        // Legacy `D3DFORMAT` values.
+        36 => Tf::Rgba16Unorm,
+        110 => Tf::Rgba16Snorm,
+        111 => Tf::R16Float,
+        112 => Tf::Rg16Float,
+        113 => Tf::Rgba16Float,
+        114 => Tf::R32Float,
+        115 => Tf::Rg32Float,
+        116 => Tf::Rgba32Float,
+        _ => return None,
+    })
+}
+
+fn masks_to_texture_format(bit_count: u32, masks: [u32; 4]) -> Option<TextureFormat> {
+    match (bit_count, masks) {
+        (32, [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]) => Some(TextureFormat::Rgba8Unorm),
+        (32, [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000]) => Some(TextureFormat::Bgra8Unorm),
+        (32, [0xFFFF, 0xFFFF_0000, 0, 0]) => Some(TextureFormat::Rg16Unorm),
+        (16, [0xFF, 0xFF00, 0, 0]) => Some(TextureFormat::Rg8Unorm),
+        (8, [0xFF, 0, 0, 0]) => Some(TextureFormat::R8Unorm),
+        _ => None,
+    }
+}
+
+fn dxgi_format_to_texture_format(dxgi_format: u32) -> Option<TextureFormat> {
+    use TextureFormat as Tf;
+
+    Some(match dxgi_format {
+        2 => Tf::Rgba32Float,
+        3 => Tf::Rgba32Uint,
+        4 => Tf::Rgba32Sint,
+        10 => Tf::Rgba16Float,
+        11 => Tf::Rgba16Unorm,
+        12 => Tf::Rgba16Uint,
+        13 => Tf::Rgba16Snorm,
+        14 => Tf::Rgba16Sint,
+        16 => Tf::Rg32Float,
+        17 => Tf::Rg32Uint,
+        18 => Tf::Rg32Sint,
+        24 => Tf::Rgb10a2Unorm,
+        26 => Tf::Rg11b10Float,
+        28 => Tf::Rgba8Unorm,
+        29 => Tf::Rgba8UnormSrgb,
+        30 => Tf::Rgba8Uint,
+        31 => Tf::Rgba8Snorm,
+        32 => Tf::Rgba8Sint,
+        34 => Tf::Rg16Float,
+        35 => Tf::Rg16Unorm,
+        36 => Tf::Rg16Uint,
+        37 => Tf::Rg16Snorm,
+        38 => Tf::Rg16Sint,
+        40 => Tf::Depth32Float,
+        41 => Tf::R32Float,
+        42 => Tf::R32Uint,
+        43 => Tf::R32Sint,
+        49 => Tf::Rg8Unorm,
+        50 => Tf::Rg8Uint,
+        51 => Tf::Rg8Snorm,
+        52 => Tf::Rg8Sint,
+        54 => Tf::R16Float,
+        56 => Tf::R16Unorm,
+        57 => Tf::R16Uint,
+        58 => Tf::R16Snorm,
+        59 => Tf::R16Sint,
+        61 => Tf::R8Unorm,
+        62 => Tf::R8Uint,
+        63 => Tf::R8Snorm,
+        64 => Tf::R8Sint,
+        67 => Tf::Rgb9e5Ufloat,
+        71 => Tf::Bc1RgbaUnorm,
+        72 => Tf::Bc1RgbaUnormSrgb,
+        74 => Tf::Bc2RgbaUnorm,
+        75 => Tf::Bc2RgbaUnormSrgb,
+        77 => Tf::Bc3RgbaUnorm,
+        78 => Tf::Bc3RgbaUnormSrgb,
+        80 => Tf::Bc4RUnorm,
+        81 => Tf::Bc4RSnorm,
+        83 => Tf::Bc5RgUnorm,
+        84 => Tf::Bc5RgSnorm,
+        87 => Tf::Bgra8Unorm,
+        91 => Tf::Bgra8UnormSrgb,
+        95 => Tf::Bc6hRgbUfloat,
+        96 => Tf::Bc6hRgbSfloat,
+        98 => Tf::Bc7RgbaUnorm,
+        99 => Tf::Bc7RgbaUnormSrgb,
+        // ASTC formats, as written by DirectXTex.
+        134 => Tf::Astc4x4RgbaUnorm,
+        135 => Tf::Astc4x4RgbaUnormSrgb,
+        138 => Tf::Astc5x4RgbaUnorm,
+        139 => Tf::Astc5x4RgbaUnormSrgb,
+        142 => Tf::Astc5x5RgbaUnorm,
+        143 => Tf::Astc5x5RgbaUnormSrgb,
+        146 => Tf::Astc6x5RgbaUnorm,
+        147 => Tf::Astc6x5RgbaUnormSrgb,
+        150 => Tf::Astc6x6RgbaUnorm,
+        151 => Tf::Astc6x6RgbaUnormSrgb,
+        154 => Tf::Astc8x5RgbaUnorm,
+        155 => Tf::Astc8x5RgbaUnormSrgb,
+        158 => Tf::Astc8x6RgbaUnorm,
+        159 => Tf::Astc8x6RgbaUnormSrgb,
+        162 => Tf::Astc8x8RgbaUnorm,
+        163 => Tf::Astc8x8RgbaUnormSrgb,
+        166 => Tf::Astc10x5RgbaUnorm,
+        167 => Tf::Astc10x5RgbaUnormSrgb,
+        170 => Tf::Astc10x6RgbaUnorm,
+        171 => Tf::Astc10x6RgbaUnormSrgb,
+        174 => Tf::Astc10x8RgbaUnorm,
+        175 => Tf::Astc10x8RgbaUnormSrgb,
+        178 => Tf::Astc10x10RgbaUnorm,
+        179 => Tf::Astc10x10RgbaUnormSrgb,
+        182 => Tf::Astc12x10RgbaUnorm,
+        183 => Tf::Astc12x10RgbaUnormSrgb,
+        186 => Tf::Astc12x12RgbaUnorm,
+        187 => Tf::Astc12x12RgbaUnormSrgb,
+        _ => return None,
+    })
+}
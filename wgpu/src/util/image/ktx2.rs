@@ -0,0 +1,218 @@
+use super::{checked_size, read_u32, read_u64, ImageError, TextureFile};
+use crate::{Extent3d, TextureDimension, TextureFormat};
+use std::convert::TryFrom;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const LEVEL_INDEX_OFFSET: usize = 80;
+
+pub(super) fn load(bytes: &[u8]) -> Result<TextureFile, ImageError> {
+    if bytes.len() < LEVEL_INDEX_OFFSET || bytes[..IDENTIFIER.len()] != IDENTIFIER {
+        return Err(ImageError::Invalid("not a KTX2 file"));
+    }
+
+    let vk_format = read_u32(bytes, 12)?;
+    let width = read_u32(bytes, 20)?;
+    let height = read_u32(bytes, 24)?;
+    let depth = read_u32(bytes, 28)?;
+    let layer_count = read_u32(bytes, 32)?.max(1);
+    let face_count = read_u32(bytes, 36)?;
+    // A level count of 0 means that only the base level is stored.
+    let level_count = read_u32(bytes, 40)?.max(1);
+    let supercompression = read_u32(bytes, 44)?;
+
+    if supercompression != 0 {
+        return Err(ImageError::Unsupported("supercompressed KTX2 files"));
+    }
+    let format = match vk_format_to_texture_format(vk_format) {
+        Some(format) => format,
+        None if vk_format == 0 => {
+            return Err(ImageError::Unsupported(
+                "KTX2 files without a Vulkan format",
+            ))
+        }
+        None => return Err(ImageError::Unsupported("Vulkan format of the KTX2 file")),
+    };
+    if face_count != 1 && face_count != 6 {
+        return Err(ImageError::Invalid("KTX2 face count must be 1 or 6"));
+    }
+    if width == 0 {
+        return Err(ImageError::Invalid("KTX2 width must not be 0"));
+    }
+
+    let dimension = match (height, depth) {
+        (0, 0) => TextureDimension::D1,
+        (_, 0) => TextureDimension::D2,
+        _ => TextureDimension::D3,
+    };
+    let size = match dimension {
+        TextureDimension::D3 => {
+            if layer_count != 1 || face_count != 1 {
+                return Err(ImageError::Unsupported("arrays of 3D textures"));
+            }
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: depth,
+            }
+        }
+        _ => Extent3d {
+            width,
+            height: height.max(1),
+            depth_or_array_layers: match layer_count.checked_mul(face_count) {
+                Some(count) => count,
+                None => return Err(ImageError::Invalid("KTX2 layer count is too large")),
+            },
+        },
+    };
+
+    let file = TextureFile {
+        size,
+        mip_level_count: level_count,
+        dimension,
+        is_cube: face_count == 6,
+        format,
+        data: Vec::new(),
+    };
+
+    file.validate_counts(bytes.len())?;
+    let level_index_end = LEVEL_INDEX_OFFSET + level_count as usize * 24;
+    if level_index_end > bytes.len() {
+        return Err(ImageError::Invalid("file is truncated"));
+    }
+
+    // Each level stores all of its layers and faces one after the other, but textures are
+    // uploaded one layer at a time with all of its mip levels.
+    let mut levels = Vec::new();
+    for level in 0..level_count {
+        let index = LEVEL_INDEX_OFFSET + level as usize * 24;
+        let offset = usize::try_from(read_u64(bytes, index)?);
+        let length = usize::try_from(read_u64(bytes, index + 8)?);
+        let (offset, length) = match (offset, length) {
+            (Ok(offset), Ok(length)) => (offset, length),
+            _ => return Err(ImageError::Invalid("file is truncated")),
+        };
+        let image_size = file.image_size(level)?;
+        if length != checked_size(image_size.checked_mul(file.layer_count() as usize))? {
+            return Err(ImageError::Invalid("KTX2 level has the wrong size"));
+        }
+        match bytes.get(offset..checked_size(offset.checked_add(length))?) {
+            Some(level_data) => levels.push((level_data, image_size)),
+            None => return Err(ImageError::Invalid("file is truncated")),
+        }
+    }
+
+    let mut data = Vec::with_capacity(levels.iter().map(|&(level, _)| level.len()).sum());
+    for layer in 0..file.layer_count() as usize {
+        for &(level_data, image_size) in levels.iter() {
+            data.extend_from_slice(&level_data[layer * image_size..(layer + 1) * image_size]);
+        }
+    }
+
+    Ok(TextureFile { data, ..file })
+}
+
+fn vk_format_to_texture_format(vk_format: u32) -> Option<TextureFormat> {
+    use TextureFormat as Tf;
+
+    Some(match vk_format {
+        9 => Tf::R8Unorm,
+        10 => Tf::R8Snorm,
+        13 => Tf::R8Uint,
+        14 => Tf::R8Sint,
+        16 => Tf::Rg8Unorm,
+        17 => Tf::Rg8Snorm,
+        20 => Tf::Rg8Uint,
+        21 => Tf::Rg8Sint,
+        37 => Tf::Rgba8Unorm,
+        38 => Tf::Rgba8Snorm,
+        41 => Tf::Rgba8Uint,
+        42 => Tf::Rgba8Sint,
+        43 => Tf::Rgba8UnormSrgb,
+        44 => Tf::Bgra8Unorm,
+        50 => Tf::Bgra8UnormSrgb,
+        64 => Tf::Rgb10a2Unorm,
+        70 => Tf::R16Unorm,
+        71 => Tf::R16Snorm,
+        74 => Tf::R16Uint,
+        75 => Tf::R16Sint,
+        76 => Tf::R16Float,
+        77 => Tf::Rg16Unorm,
+        78 => Tf::Rg16Snorm,
+        81 => Tf::Rg16Uint,
+        82 => Tf::Rg16Sint,
+        83 => Tf::Rg16Float,
+        91 => Tf::Rgba16Unorm,
+        92 => Tf::Rgba16Snorm,
+        95 => Tf::Rgba16Uint,
+        96 => Tf::Rgba16Sint,
+        97 => Tf::Rgba16Float,
+        98 => Tf::R32Uint,
+        99 => Tf::R32Sint,
+        100 => Tf::R32Float,
+        101 => Tf::Rg32Uint,
+        102 => Tf::Rg32Sint,
+        103 => Tf::Rg32Float,
+        107 => Tf::Rgba32Uint,
+        108 => Tf::Rgba32Sint,
+        109 => Tf::Rgba32Float,
+        122 => Tf::Rg11b10Float,
+        123 => Tf::Rgb9e5Ufloat,
+        126 => Tf::Depth32Float,
+        // BC1 without alpha is the same data as BC1 with alpha.
+        131 | 133 => Tf::Bc1RgbaUnorm,
+        132 | 134 => Tf::Bc1RgbaUnormSrgb,
+        135 => Tf::Bc2RgbaUnorm,
+        136 => Tf::Bc2RgbaUnormSrgb,
+        137 => Tf::Bc3RgbaUnorm,
+        138 => Tf::Bc3RgbaUnormSrgb,
+        139 => Tf::Bc4RUnorm,
+        140 => Tf::Bc4RSnorm,
+        141 => Tf::Bc5RgUnorm,
+        142 => Tf::Bc5RgSnorm,
+        143 => Tf::Bc6hRgbUfloat,
+        144 => Tf::Bc6hRgbSfloat,
+        145 => Tf::Bc7RgbaUnorm,
+        146 => Tf::Bc7RgbaUnormSrgb,
+        147 => Tf::Etc2Rgb8Unorm,
+        148 => Tf::Etc2Rgb8UnormSrgb,
+        149 => Tf::Etc2Rgb8A1Unorm,
+        150 => Tf::Etc2Rgb8A1UnormSrgb,
+        151 => Tf::Etc2Rgba8Unorm,
+        152 => Tf::Etc2Rgba8UnormSrgb,
+        153 => Tf::EacR11Unorm,
+        154 => Tf::EacR11Snorm,
+        155 => Tf::EacRg11Unorm,
+        156 => Tf::EacRg11Snorm,
+        157 => Tf::Astc4x4RgbaUnorm,
+        158 => Tf::Astc4x4RgbaUnormSrgb,
+        159 => Tf::Astc5x4RgbaUnorm,
+        160 => Tf::Astc5x4RgbaUnormSrgb,
+        161 => Tf::Astc5x5RgbaUnorm,
+        162 => Tf::Astc5x5RgbaUnormSrgb,
+        163 => Tf::Astc6x5RgbaUnorm,
+        164 => Tf::Astc6x5RgbaUnormSrgb,
+        165 => Tf::Astc6x6RgbaUnorm,
+        166 => Tf::Astc6x6RgbaUnormSrgb,
+        167 => Tf::Astc8x5RgbaUnorm,
+        168 => Tf::Astc8x5RgbaUnormSrgb,
+        169 => Tf::Astc8x6RgbaUnorm,
+        170 => Tf::Astc8x6RgbaUnormSrgb,
+        171 => Tf::Astc8x8RgbaUnorm,
+        172 => Tf::Astc8x8RgbaUnormSrgb,
+        173 => Tf::Astc10x5RgbaUnorm,
+        174 => Tf::Astc10x5RgbaUnormSrgb,
+        175 => Tf::Astc10x6RgbaUnorm,
+        176 => Tf::Astc10x6RgbaUnormSrgb,
+        177 => Tf::Astc10x8RgbaUnorm,
+        178 => Tf::Astc10x8RgbaUnormSrgb,
+        179 => Tf::Astc10x10RgbaUnorm,
+        180 => Tf::Astc10x10RgbaUnormSrgb,
+        181 => Tf::Astc12x10RgbaUnorm,
+        182 => Tf::Astc12x10RgbaUnormSrgb,
+        183 => Tf::Astc12x12RgbaUnorm,
+        184 => Tf::Astc12x12RgbaUnormSrgb,
+        _ => return None,
+    })
+}
@@ -0,0 +1,229 @@
+//! Loading textures from KTX2 and DDS files, and writing texture data to PNG and EXR files.
+//!
+//! Only available with the `image` feature.
+
+mod dds;
+mod ktx2;
+mod write;
+
+use std::{error, fmt, io};
+
+use super::DeviceExt;
+use crate::{
+    Device, Extent3d, Label, Queue, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDimension,
+};
+
+pub use write::{write_exr, write_png};
+
+/// Error returned when loading or writing image files.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The file is truncated or malformed.
+    Invalid(&'static str),
+    /// The file relies on something that isn't supported, like supercompression.
+    Unsupported(&'static str),
+    /// The texture format can't be written to the requested kind of file.
+    UnsupportedFormat(TextureFormat),
+    /// Writing the file failed.
+    Io(io::Error),
+    /// Encoding a PNG file failed.
+    Png(png::EncodingError),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ImageError::Invalid(reason) => write!(f, "Invalid image file: {}", reason),
+            ImageError::Unsupported(reason) => write!(f, "Unsupported image file: {}", reason),
+            ImageError::UnsupportedFormat(format) => {
+                write!(
+                    f,
+                    "Texture format {:?} can't be written to this file",
+                    format
+                )
+            }
+            ImageError::Io(ref error) => write!(f, "Failed to write image file: {}", error),
+            ImageError::Png(ref error) => write!(f, "Failed to encode PNG file: {}", error),
+        }
+    }
+}
+
+impl error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ImageError::Io(ref error) => Some(error),
+            ImageError::Png(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(error: io::Error) -> Self {
+        ImageError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(error: png::EncodingError) -> Self {
+        ImageError::Png(error)
+    }
+}
+
+/// Texture loaded from a KTX2 or DDS file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextureFile {
+    /// Size of the first mip level. For 1D and 2D textures, the depth is the number of
+    /// array layers, counting each face of cube maps.
+    pub size: Extent3d,
+    /// Number of mip levels in the file.
+    pub mip_level_count: u32,
+    /// Dimension of the texture.
+    pub dimension: TextureDimension,
+    /// Whether the array layers are the faces of cube maps, in groups of 6.
+    pub is_cube: bool,
+    /// Format of the texture.
+    pub format: TextureFormat,
+    /// All the images of the texture, in the order expected by
+    /// [`DeviceExt::create_texture_with_data`].
+    pub data: Vec<u8>,
+}
+
+impl TextureFile {
+    /// Load a texture from the contents of a KTX2 file.
+    ///
+    /// Supercompressed files aren't supported.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, ImageError> {
+        ktx2::load(bytes)
+    }
+
+    /// Load a texture from the contents of a DDS file.
+    ///
+    /// Files with a DX10 header are supported, as well as the FourCC codes of BC formats and
+    /// floating point formats, and the most common 8-bit RGBA layouts.
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, ImageError> {
+        dds::load(bytes)
+    }
+
+    /// Describe a texture matching the file.
+    pub fn descriptor<'a>(&self, label: Label<'a>, usage: TextureUsages) -> TextureDescriptor<'a> {
+        TextureDescriptor {
+            label,
+            size: self.size,
+            mip_level_count: self.mip_level_count,
+            sample_count: 1,
+            dimension: self.dimension,
+            format: self.format,
+            usage,
+        }
+    }
+
+    /// Dimension of a view of the whole texture.
+    pub fn view_dimension(&self) -> TextureViewDimension {
+        match self.dimension {
+            TextureDimension::D1 => TextureViewDimension::D1,
+            TextureDimension::D3 => TextureViewDimension::D3,
+            TextureDimension::D2 => match (self.is_cube, self.size.depth_or_array_layers) {
+                (true, 6) => TextureViewDimension::Cube,
+                (true, _) => TextureViewDimension::CubeArray,
+                (false, 1) => TextureViewDimension::D2,
+                (false, _) => TextureViewDimension::D2Array,
+            },
+        }
+    }
+
+    /// Create a texture with the contents of the file.
+    ///
+    /// The device needs the features required by the format of the file, see
+    /// [`TextureFormat::describe`].
+    pub fn create_texture(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        label: Label,
+        usage: TextureUsages,
+    ) -> Texture {
+        device.create_texture_with_data(queue, &self.descriptor(label, usage), &self.data)
+    }
+
+    /// Check the mip level and array layer counts read from a file of `file_size` bytes.
+    ///
+    /// Every mip level has at least one texel, and every layer at least one byte, which bounds
+    /// the counts before anything is allocated for them.
+    fn validate_counts(&self, file_size: usize) -> Result<(), ImageError> {
+        let max_dim = match self.dimension {
+            TextureDimension::D3 => self
+                .size
+                .width
+                .max(self.size.height)
+                .max(self.size.depth_or_array_layers),
+            _ => self.size.width.max(self.size.height),
+        };
+        // Not `Extent3d::max_mips`, which doesn't allow mip levels for 1D textures.
+        let max_mip_level_count = 32 - max_dim.leading_zeros();
+        if self.mip_level_count >= 32 || self.mip_level_count > max_mip_level_count {
+            return Err(ImageError::Invalid("too many mip levels for the size"));
+        }
+        if self.layer_count() as usize > file_size {
+            return Err(ImageError::Invalid("file is truncated"));
+        }
+        Ok(())
+    }
+
+    /// Size in bytes of one array layer of a mip level, for 3D textures all of its slices.
+    fn image_size(&self, mip_level: u32) -> Result<usize, ImageError> {
+        let format_info = self.format.describe();
+        let size = self
+            .size
+            .mip_level_size(mip_level, self.dimension == TextureDimension::D3);
+        let depth = match self.dimension {
+            TextureDimension::D3 => size.depth_or_array_layers,
+            _ => 1,
+        };
+        // `Extent3d::physical_size` would overflow when rounding sizes close to `u32::MAX`.
+        let blocks_wide = div_ceil(size.width, format_info.block_dimensions.0 as u32);
+        let blocks_high = div_ceil(size.height, format_info.block_dimensions.1 as u32);
+        checked_size(
+            (blocks_wide as usize)
+                .checked_mul(blocks_high as usize)
+                .and_then(|blocks| blocks.checked_mul(depth as usize))
+                .and_then(|blocks| blocks.checked_mul(format_info.block_size as usize)),
+        )
+    }
+
+    /// Number of array layers, counting cube map faces.
+    fn layer_count(&self) -> u32 {
+        match self.dimension {
+            TextureDimension::D3 => 1,
+            _ => self.size.depth_or_array_layers,
+        }
+    }
+}
+
+/// Divide rounding up, without overflowing. `value` must not be 0.
+fn div_ceil(value: u32, divisor: u32) -> u32 {
+    (value - 1) / divisor + 1
+}
+
+/// Turn a size computed with checked arithmetic into a result.
+///
+/// Only hostile or corrupted headers make sizes overflow.
+fn checked_size(size: Option<usize>) -> Result<usize, ImageError> {
+    size.ok_or(ImageError::Invalid("sizes in the header overflow"))
+}
+
+/// Read a little endian `u32` at `offset`.
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ImageError> {
+    match bytes.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(ImageError::Invalid("file is truncated")),
+    }
+}
+
+/// Read a little endian `u64` at `offset`.
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, ImageError> {
+    let low = read_u32(bytes, offset)? as u64;
+    let high = read_u32(bytes, offset + 4)? as u64;
+    Ok(low | high << 32)
+}
@@ -0,0 +1,165 @@
+use std::io::Write;
+
+use super::ImageError;
+use crate::TextureFormat;
+
+/// Write an image with tightly packed rows to a PNG file.
+///
+/// Supported formats are `R8Unorm`, `R16Unorm`, `Rgba16Unorm` and the 8-bit RGBA and BGRA
+/// formats. The values are written as they are, so sRGB formats keep their encoding, and
+/// linear formats are written as if they were sRGB.
+///
+/// This is the layout of the data returned by [`DownloadTexture`](crate::util::DownloadTexture)
+/// for a single 2D image.
+///
+/// # Panics
+///
+/// - If the size of `data` doesn't match `width` and `height`.
+pub fn write_png<W: Write>(
+    writer: W,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<(), ImageError> {
+    let (color, depth) = match format {
+        TextureFormat::R8Unorm => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        TextureFormat::R16Unorm => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => (png::ColorType::RGBA, png::BitDepth::Eight),
+        TextureFormat::Rgba16Unorm => (png::ColorType::RGBA, png::BitDepth::Sixteen),
+        _ => return Err(ImageError::UnsupportedFormat(format)),
+    };
+    check_size(format, width, height, data);
+
+    let data = match format {
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => data
+            .chunks(4)
+            .flat_map(|texel| [texel[2], texel[1], texel[0], texel[3]])
+            .collect(),
+        // PNG stores 16-bit samples as big endian.
+        TextureFormat::R16Unorm | TextureFormat::Rgba16Unorm => data
+            .chunks(2)
+            .flat_map(|sample| [sample[1], sample[0]])
+            .collect(),
+        _ => data.to_vec(),
+    };
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Write an image with tightly packed rows to an uncompressed OpenEXR file.
+///
+/// Supported formats are the 16 and 32-bit float formats, whose components are written to the
+/// `R`, `G`, `B` and `A` channels, and `Depth32Float`, which is written to the `Z` channel.
+///
+/// # Panics
+///
+/// - If the size of `data` doesn't match `width` and `height`.
+pub fn write_exr<W: Write>(
+    mut writer: W,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Result<(), ImageError> {
+    const HALF: u32 = 1;
+    const FLOAT: u32 = 2;
+
+    // Channels must be sorted by name, given with the index of their component in a texel.
+    let (pixel_type, channels): (u32, &[(&str, usize)]) = match format {
+        TextureFormat::R16Float => (HALF, &[("R", 0)]),
+        TextureFormat::Rg16Float => (HALF, &[("G", 1), ("R", 0)]),
+        TextureFormat::Rgba16Float => (HALF, &[("A", 3), ("B", 2), ("G", 1), ("R", 0)]),
+        TextureFormat::R32Float => (FLOAT, &[("R", 0)]),
+        TextureFormat::Rg32Float => (FLOAT, &[("G", 1), ("R", 0)]),
+        TextureFormat::Rgba32Float => (FLOAT, &[("A", 3), ("B", 2), ("G", 1), ("R", 0)]),
+        TextureFormat::Depth32Float => (FLOAT, &[("Z", 0)]),
+        _ => return Err(ImageError::UnsupportedFormat(format)),
+    };
+    check_size(format, width, height, data);
+
+    let component_size = if pixel_type == HALF { 2 } else { 4 };
+    let texel_size = format.describe().block_size as usize;
+    let row_size = width as usize * texel_size;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&0x0131_2F76u32.to_le_bytes());
+    // Version 2, single part scan line file.
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut channel_list = Vec::new();
+    for &(name, _) in channels {
+        channel_list.extend_from_slice(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&pixel_type.to_le_bytes());
+        // Not perceptually linear, 3 reserved bytes, and no subsampling.
+        channel_list.extend_from_slice(&[0; 4]);
+        channel_list.extend_from_slice(&1u32.to_le_bytes());
+        channel_list.extend_from_slice(&1u32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let mut window = Vec::new();
+    for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let attributes: [(&str, &str, &[u8]); 8] = [
+        ("channels", "chlist", &channel_list),
+        ("compression", "compression", &[0]),
+        ("dataWindow", "box2i", &window),
+        ("displayWindow", "box2i", &window),
+        ("lineOrder", "lineOrder", &[0]),
+        ("pixelAspectRatio", "float", &1f32.to_le_bytes()),
+        ("screenWindowCenter", "v2f", &[0; 8]),
+        ("screenWindowWidth", "float", &1f32.to_le_bytes()),
+    ];
+    for &(name, kind, value) in attributes.iter() {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        header.extend_from_slice(value);
+    }
+    header.push(0);
+
+    // Each line is a chunk made of its index, its size, and the line of each channel.
+    let chunk_size = 8 + row_size;
+    let first_chunk = header.len() + height as usize * 8;
+    for y in 0..height as usize {
+        header.extend_from_slice(&((first_chunk + y * chunk_size) as u64).to_le_bytes());
+    }
+    writer.write_all(&header)?;
+
+    let mut chunk = Vec::with_capacity(chunk_size);
+    for (y, row) in data.chunks(row_size).enumerate() {
+        chunk.clear();
+        chunk.extend_from_slice(&(y as u32).to_le_bytes());
+        chunk.extend_from_slice(&(row_size as u32).to_le_bytes());
+        for &(_, component) in channels {
+            let offset = component * component_size;
+            for texel in row.chunks(texel_size) {
+                chunk.extend_from_slice(&texel[offset..offset + component_size]);
+            }
+        }
+        writer.write_all(&chunk)?;
+    }
+    Ok(())
+}
+
+fn check_size(format: TextureFormat, width: u32, height: u32, data: &[u8]) {
+    let texel_size = format.describe().block_size as usize;
+    assert_eq!(
+        data.len(),
+        width as usize * height as usize * texel_size,
+        "Image data doesn't match its size"
+    );
+}
@@ -0,0 +1,492 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error, fmt,
+    num::NonZeroU32,
+};
+
+use crate::{
+    BindGroupLayout, CommandEncoder, ComputePipeline, Context, Device, RenderPipeline, Sampler,
+    Texture, TextureDimension, TextureFormat, TextureUsages,
+};
+
+/// Error returned by [`generate_mipmaps`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenerateMipmapsError {
+    /// The format is neither renderable and filterable, nor usable as a storage texture.
+    UnsupportedFormat(TextureFormat),
+    /// Only 2D textures, including arrays and cube maps, are supported.
+    UnsupportedDimension(TextureDimension),
+    /// Multisampled textures can't have mipmaps.
+    Multisampled,
+    /// The texture is missing usages needed to generate its mipmaps.
+    MissingUsages(TextureUsages),
+}
+
+impl fmt::Display for GenerateMipmapsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GenerateMipmapsError::UnsupportedFormat(format) => {
+                write!(f, "Mipmaps can't be generated for format {:?}", format)
+            }
+            GenerateMipmapsError::UnsupportedDimension(dimension) => write!(
+                f,
+                "Mipmaps can't be generated for textures of dimension {:?}",
+                dimension
+            ),
+            GenerateMipmapsError::Multisampled => {
+                write!(f, "Mipmaps can't be generated for multisampled textures")
+            }
+            GenerateMipmapsError::MissingUsages(usages) => write!(
+                f,
+                "Texture usages {:?} are needed to generate mipmaps",
+                usages
+            ),
+        }
+    }
+}
+
+impl error::Error for GenerateMipmapsError {}
+
+/// Generate all the mip levels of a texture from its first level.
+///
+/// The pipelines are built for this call only, see [`MipmapGenerator`] to reuse them.
+pub fn generate_mipmaps(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture: &Texture,
+) -> Result<(), GenerateMipmapsError> {
+    MipmapGenerator::new().generate(device, encoder, texture)
+}
+
+/// Generates the mip levels of textures, keeping the pipelines it builds for each format.
+///
+/// The pipelines belong to the device they are built with, so a generator is only
+/// to be used with a single device.
+#[derive(Debug, Default)]
+pub struct MipmapGenerator {
+    pipelines: HashMap<TextureFormat, MipmapPipeline>,
+}
+
+impl MipmapGenerator {
+    /// Create a generator without any pipeline, they are built on the first use of each format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate all the mip levels of a texture from its first level.
+    ///
+    /// Each array layer is handled separately, so this works for 2D arrays and cube maps.
+    /// Formats that are renderable and filterable are downsampled by rendering with linear
+    /// filtering. Other formats that can be used as storage textures are downsampled in compute
+    /// shaders instead, by averaging each 2x2 square of texels.
+    ///
+    /// The texture needs the `TEXTURE_BINDING` usage, as well as `RENDER_ATTACHMENT` for
+    /// renderable formats, or `STORAGE_BINDING` otherwise.
+    pub fn generate(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+    ) -> Result<(), GenerateMipmapsError> {
+        let desc = &texture.descriptor;
+        if desc.dimension != TextureDimension::D2 {
+            return Err(GenerateMipmapsError::UnsupportedDimension(desc.dimension));
+        }
+        if desc.sample_count > 1 {
+            return Err(GenerateMipmapsError::Multisampled);
+        }
+        let pipeline = self.pipeline(device, desc.format)?;
+        let missing_usages = pipeline.usages() - desc.usage;
+        if !missing_usages.is_empty() {
+            return Err(GenerateMipmapsError::MissingUsages(missing_usages));
+        }
+
+        for array_layer in 0..desc.array_layer_count() {
+            for mip_level in 1..desc.mip_level_count {
+                pipeline.generate(device, encoder, texture, array_layer, mip_level);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the pipeline generating mip levels of the given format, building it if needed.
+    fn pipeline(
+        &mut self,
+        device: &Device,
+        format: TextureFormat,
+    ) -> Result<&MipmapPipeline, GenerateMipmapsError> {
+        Ok(match self.pipelines.entry(format) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(MipmapPipeline::new(device, format)?),
+        })
+    }
+}
+
+/// Name of a format in WGSL storage texture types.
+fn storage_format_name(format: TextureFormat) -> Option<&'static str> {
+    use TextureFormat as Tf;
+    Some(match format {
+        Tf::R8Unorm => "r8unorm",
+        Tf::R8Snorm => "r8snorm",
+        Tf::R8Uint => "r8uint",
+        Tf::R8Sint => "r8sint",
+        Tf::R16Uint => "r16uint",
+        Tf::R16Sint => "r16sint",
+        Tf::R16Float => "r16float",
+        Tf::Rg8Unorm => "rg8unorm",
+        Tf::Rg8Snorm => "rg8snorm",
+        Tf::Rg8Uint => "rg8uint",
+        Tf::Rg8Sint => "rg8sint",
+        Tf::R32Uint => "r32uint",
+        Tf::R32Sint => "r32sint",
+        Tf::R32Float => "r32float",
+        Tf::Rg16Uint => "rg16uint",
+        Tf::Rg16Sint => "rg16sint",
+        Tf::Rg16Float => "rg16float",
+        Tf::Rgba8Unorm => "rgba8unorm",
+        Tf::Rgba8Snorm => "rgba8snorm",
+        Tf::Rgba8Uint => "rgba8uint",
+        Tf::Rgba8Sint => "rgba8sint",
+        Tf::Rgb10a2Unorm => "rgb10a2unorm",
+        Tf::Rg11b10Float => "rg11b10float",
+        Tf::Rg32Uint => "rg32uint",
+        Tf::Rg32Sint => "rg32sint",
+        Tf::Rg32Float => "rg32float",
+        Tf::Rgba16Uint => "rgba16uint",
+        Tf::Rgba16Sint => "rgba16sint",
+        Tf::Rgba16Float => "rgba16float",
+        Tf::Rgba32Uint => "rgba32uint",
+        Tf::Rgba32Sint => "rgba32sint",
+        Tf::Rgba32Float => "rgba32float",
+        _ => return None,
+    })
+}
+
+/// Compute shader averaging 2x2 texels of a mip level into the next one.
+///
+/// `{scalar}`, `{format}` and `{average}` are replaced for the texture format.
+const DOWNSAMPLE_SHADER: &str = "
+[[group(0), binding(0)]]
+var src: texture_2d<{scalar}>;
+[[group(0), binding(1)]]
+var dst: texture_storage_2d<{format}, write>;
+
+fn average(a: vec4<{scalar}>, b: vec4<{scalar}>, c: vec4<{scalar}>, d: vec4<{scalar}>) -> vec4<{scalar}> {
+{average}
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let coords = vec2<i32>(id.xy);
+    let size = textureDimensions(dst);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+    let last = textureDimensions(src) - vec2<i32>(1, 1);
+    let base = coords * 2;
+    textureStore(dst, coords, average(
+        textureLoad(src, base, 0),
+        textureLoad(src, min(base + vec2<i32>(1, 0), last), 0),
+        textureLoad(src, min(base + vec2<i32>(0, 1), last), 0),
+        textureLoad(src, min(base + vec2<i32>(1, 1), last), 0),
+    ));
+}
+";
+
+/// Body of `average` for floating point formats, scaling each texel so the sum can't overflow.
+const AVERAGE_FLOAT: &str = "    return a * 0.25 + b * 0.25 + c * 0.25 + d * 0.25;";
+
+/// Body of `average` for integer formats, where `{three}` is replaced for the scalar type.
+///
+/// The texels are divided before they are summed, so the sum can't overflow,
+/// and the remainders are added back. Shifting rounds down for signed integers too.
+const AVERAGE_INTEGER: &str = "    let two = vec4<u32>(2u, 2u, 2u, 2u);
+    let three = vec4<{scalar}>({three}, {three}, {three}, {three});
+    let remainders = (a & three) + (b & three) + (c & three) + (d & three);
+    return (a >> two) + (b >> two) + (c >> two) + (d >> two) + (remainders >> two);";
+
+const DOWNSAMPLE_WORKGROUP_SIZE: u32 = 8;
+
+#[derive(Debug)]
+enum MipmapPipelineKind {
+    Render {
+        pipeline: RenderPipeline,
+        sampler: Sampler,
+    },
+    Compute(ComputePipeline),
+}
+
+/// Renders or computes mip levels of a texture from the level above them.
+#[derive(Debug)]
+pub(crate) struct MipmapPipeline {
+    bind_group_layout: BindGroupLayout,
+    kind: MipmapPipelineKind,
+}
+
+impl MipmapPipeline {
+    pub(crate) fn new(
+        device: &Device,
+        format: TextureFormat,
+    ) -> Result<Self, GenerateMipmapsError> {
+        let features = device.get_texture_format_features(format);
+        let is_color_float = matches!(
+            format.describe().sample_type,
+            wgt::TextureSampleType::Float { .. }
+        );
+        let can_render = is_color_float
+            && features
+                .flags
+                .contains(wgt::TextureFormatFeatureFlags::FILTERABLE)
+            && features
+                .allowed_usages
+                .contains(TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT);
+        if can_render {
+            return Ok(Self::new_render(device, format));
+        }
+
+        let can_compute = Context::device_downlevel_properties(&*device.context, &device.id)
+            .flags
+            .contains(wgt::DownlevelFlags::COMPUTE_SHADERS)
+            && features
+                .allowed_usages
+                .contains(TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING);
+        match storage_format_name(format) {
+            Some(name) if can_compute => Ok(Self::new_compute(device, format, name)),
+            _ => Err(GenerateMipmapsError::UnsupportedFormat(format)),
+        }
+    }
+
+    fn new_render(device: &Device, format: TextureFormat) -> Self {
+        let shader = device.create_shader_module(&crate::include_wgsl!("blit.wgsl"));
+        // The layout is explicit, since not every backend supports implicit ones.
+        let bind_group_layout =
+            device.create_bind_group_layout(&crate::BindGroupLayoutDescriptor {
+                label: Some("mipmap blit"),
+                entries: &[
+                    wgt::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgt::ShaderStages::FRAGMENT,
+                        ty: wgt::BindingType::Texture {
+                            sample_type: wgt::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgt::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgt::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgt::ShaderStages::FRAGMENT,
+                        ty: wgt::BindingType::Sampler(wgt::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let layout = device.create_pipeline_layout(&crate::PipelineLayoutDescriptor {
+            label: Some("mipmap blit"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&crate::RenderPipelineDescriptor {
+            label: Some("mipmap blit"),
+            layout: Some(&layout),
+            vertex: crate::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(crate::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[format.into()],
+            }),
+            primitive: wgt::PrimitiveState {
+                topology: wgt::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgt::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&crate::SamplerDescriptor {
+            label: Some("mipmap blit"),
+            address_mode_u: wgt::AddressMode::ClampToEdge,
+            address_mode_v: wgt::AddressMode::ClampToEdge,
+            address_mode_w: wgt::AddressMode::ClampToEdge,
+            mag_filter: wgt::FilterMode::Linear,
+            min_filter: wgt::FilterMode::Linear,
+            mipmap_filter: wgt::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            bind_group_layout,
+            kind: MipmapPipelineKind::Render { pipeline, sampler },
+        }
+    }
+
+    fn new_compute(device: &Device, format: TextureFormat, format_name: &str) -> Self {
+        let (sample_type, scalar, average) = match format.describe().sample_type {
+            wgt::TextureSampleType::Uint => (
+                wgt::TextureSampleType::Uint,
+                "u32",
+                AVERAGE_INTEGER.replace("{three}", "3u"),
+            ),
+            wgt::TextureSampleType::Sint => (
+                wgt::TextureSampleType::Sint,
+                "i32",
+                AVERAGE_INTEGER.replace("{three}", "3"),
+            ),
+            _ => (
+                wgt::TextureSampleType::Float { filterable: false },
+                "f32",
+                AVERAGE_FLOAT.to_string(),
+            ),
+        };
+        let source = DOWNSAMPLE_SHADER
+            .replace("{average}", &average)
+            .replace("{scalar}", scalar)
+            .replace("{format}", format_name);
+        let shader = device.create_shader_module(&crate::ShaderModuleDescriptor {
+            label: Some("mipmap downsample"),
+            source: crate::ShaderSource::Wgsl(source.into()),
+        });
+
+        let bind_group_layout =
+            device.create_bind_group_layout(&crate::BindGroupLayoutDescriptor {
+                label: Some("mipmap downsample"),
+                entries: &[
+                    wgt::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgt::ShaderStages::COMPUTE,
+                        ty: wgt::BindingType::Texture {
+                            sample_type,
+                            view_dimension: wgt::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgt::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgt::ShaderStages::COMPUTE,
+                        ty: wgt::BindingType::StorageTexture {
+                            access: wgt::StorageTextureAccess::WriteOnly,
+                            format,
+                            view_dimension: wgt::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let layout = device.create_pipeline_layout(&crate::PipelineLayoutDescriptor {
+            label: Some("mipmap downsample"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&crate::ComputePipelineDescriptor {
+            label: Some("mipmap downsample"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: "cs_main",
+            cache: None,
+        });
+        Self {
+            bind_group_layout,
+            kind: MipmapPipelineKind::Compute(pipeline),
+        }
+    }
+
+    /// Usages a texture needs for its mip levels to be generated.
+    pub(crate) fn usages(&self) -> TextureUsages {
+        match self.kind {
+            MipmapPipelineKind::Render { .. } => {
+                TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT
+            }
+            MipmapPipelineKind::Compute(_) => {
+                TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING
+            }
+        }
+    }
+
+    /// Generate `mip_level` of `array_layer` from the previous mip level.
+    pub(crate) fn generate(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &Texture,
+        array_layer: u32,
+        mip_level: u32,
+    ) {
+        debug_assert_ne!(mip_level, 0);
+        let view = |mip_level| {
+            texture.create_view(&crate::TextureViewDescriptor {
+                label: Some("mipmap"),
+                format: None,
+                dimension: Some(wgt::TextureViewDimension::D2),
+                aspect: wgt::TextureAspect::All,
+                base_mip_level: mip_level,
+                mip_level_count: NonZeroU32::new(1),
+                base_array_layer: array_layer,
+                array_layer_count: NonZeroU32::new(1),
+            })
+        };
+        let source = view(mip_level - 1);
+        let target = view(mip_level);
+
+        let target_resource = match self.kind {
+            MipmapPipelineKind::Render { ref sampler, .. } => {
+                crate::BindingResource::Sampler(sampler)
+            }
+            MipmapPipelineKind::Compute(_) => crate::BindingResource::TextureView(&target),
+        };
+        let bind_group = device.create_bind_group(&crate::BindGroupDescriptor {
+            label: Some("mipmap"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                crate::BindGroupEntry {
+                    binding: 0,
+                    resource: crate::BindingResource::TextureView(&source),
+                },
+                crate::BindGroupEntry {
+                    binding: 1,
+                    resource: target_resource,
+                },
+            ],
+        });
+
+        match self.kind {
+            MipmapPipelineKind::Render { ref pipeline, .. } => {
+                let mut pass = encoder.begin_render_pass(&crate::RenderPassDescriptor {
+                    label: Some("mipmap blit"),
+                    color_attachments: &[crate::RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: crate::Operations {
+                            load: crate::LoadOp::Clear(wgt::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.draw(0..4, 0..1);
+            }
+            MipmapPipelineKind::Compute(ref pipeline) => {
+                let size = texture
+                    .descriptor
+                    .mip_level_size(mip_level)
+                    .expect("mip level is out of bounds");
+                let mut pass = encoder.begin_compute_pass(&crate::ComputePassDescriptor {
+                    label: Some("mipmap downsample"),
+                });
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch(
+                    (size.width - 1) / DOWNSAMPLE_WORKGROUP_SIZE + 1,
+                    (size.height - 1) / DOWNSAMPLE_WORKGROUP_SIZE + 1,
+                    1,
+                );
+            }
+        }
+    }
+}
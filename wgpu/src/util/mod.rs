@@ -3,21 +3,26 @@
 mod belt;
 mod device;
 mod encoder;
+#[cfg(feature = "image")]
+pub mod image;
 mod indirect;
 mod init;
+mod mipmap;
 
 use std::future::Future;
 use std::{
     borrow::Cow,
     mem::{align_of, size_of},
+    num::NonZeroU32,
     ptr::copy_nonoverlapping,
 };
 
-pub use belt::StagingBelt;
-pub use device::{BufferInitDescriptor, DeviceExt};
+pub use belt::{StagingBelt, StagingBeltStats};
+pub use device::{BufferInitDescriptor, DeviceExt, TextureImage};
 pub use encoder::RenderEncoder;
 pub use indirect::*;
 pub use init::*;
+pub use mipmap::{generate_mipmaps, GenerateMipmapsError, MipmapGenerator};
 
 /// Treat the given byte slice as a SPIR-V module.
 ///
@@ -69,6 +74,14 @@ pub fn make_spirv_raw(data: &[u8]) -> Cow<[u32]> {
     words
 }
 
+/// Round `value` up to a multiple of `alignment`.
+fn align_to(value: super::BufferAddress, alignment: super::BufferAddress) -> super::BufferAddress {
+    match value % alignment {
+        0 => value,
+        remainder => value + alignment - remainder,
+    }
+}
+
 /// CPU accessible buffer used to download data back from the GPU.
 pub struct DownloadBuffer(super::Buffer, super::BufferMappedRange);
 
@@ -113,3 +126,113 @@ impl std::ops::Deref for DownloadBuffer {
         super::BufferMappedRangeSlice::slice(&self.1)
     }
 }
+
+/// CPU accessible copy of a region of a texture, downloaded from the GPU.
+///
+/// Unlike copies to buffers, rows are tightly packed, without padding.
+pub struct DownloadTexture {
+    data: Vec<u8>,
+    bytes_per_row: u32,
+    rows_per_image: u32,
+}
+
+impl DownloadTexture {
+    /// Asynchronously read a region of a texture.
+    ///
+    /// The region starts at the origin of `texture`, and `size` covers array layers of 2D
+    /// textures, or depth slices of 3D textures. The data is laid out as by
+    /// [`CommandEncoder::copy_texture_to_buffer`], with the same restrictions:
+    ///
+    /// - Compressed formats are read as whole texel blocks.
+    /// - Depth and stencil are read a whole mip level at once, one aspect at a time. Only
+    ///   [`TextureFormat::Depth32Float`] can be read as depth, as 32-bit floats.
+    ///   [`TextureFormat::Depth24PlusStencil8`] can only be read as stencil, one byte per
+    ///   texel, and not on the GL backend. [`TextureFormat::Depth24Plus`] can't be read at all.
+    ///
+    /// [`CommandEncoder::copy_texture_to_buffer`]: super::CommandEncoder::copy_texture_to_buffer
+    /// [`TextureFormat::Depth32Float`]: super::TextureFormat::Depth32Float
+    /// [`TextureFormat::Depth24PlusStencil8`]: super::TextureFormat::Depth24PlusStencil8
+    /// [`TextureFormat::Depth24Plus`]: super::TextureFormat::Depth24Plus
+    pub fn read_texture(
+        device: &super::Device,
+        queue: &super::Queue,
+        texture: super::ImageCopyTexture,
+        size: super::Extent3d,
+    ) -> impl Future<Output = Result<Self, super::BufferAsyncError>> + Send {
+        let format = texture.texture.descriptor.format;
+        let format_info = format.describe();
+        let block_size = match (format, texture.aspect) {
+            (super::TextureFormat::Depth24PlusStencil8, super::TextureAspect::StencilOnly) => 1,
+            _ => format_info.block_size as u32,
+        };
+        let bytes_per_row = size.width / format_info.block_dimensions.0 as u32 * block_size;
+        let rows_per_image = size.height / format_info.block_dimensions.1 as u32;
+        let rows = rows_per_image * size.depth_or_array_layers;
+        let padded_bytes_per_row = align_to(
+            bytes_per_row.max(1) as super::BufferAddress,
+            super::COPY_BYTES_PER_ROW_ALIGNMENT as super::BufferAddress,
+        );
+
+        let download = device.create_buffer(&super::BufferDescriptor {
+            size: (padded_bytes_per_row * rows as super::BufferAddress)
+                .max(super::COPY_BUFFER_ALIGNMENT),
+            usage: super::BufferUsages::COPY_DST | super::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+            label: None,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&super::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            texture,
+            super::ImageCopyBuffer {
+                buffer: &download,
+                layout: super::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row as u32),
+                    rows_per_image: NonZeroU32::new(rows_per_image),
+                },
+            },
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let fut = download.slice(..).map_async(super::MapMode::Read);
+        async move {
+            fut.await?;
+            let mut data = Vec::with_capacity(bytes_per_row as usize * rows as usize);
+            {
+                let mapping = download.slice(..).get_mapped_range();
+                for row in mapping
+                    .chunks(padded_bytes_per_row as usize)
+                    .take(rows as usize)
+                {
+                    data.extend_from_slice(&row[..bytes_per_row as usize]);
+                }
+            }
+            download.unmap();
+            Ok(Self {
+                data,
+                bytes_per_row,
+                rows_per_image,
+            })
+        }
+    }
+
+    /// Number of bytes in a row of texel blocks.
+    pub fn bytes_per_row(&self) -> u32 {
+        self.bytes_per_row
+    }
+
+    /// Number of rows of texel blocks in an array layer or depth slice.
+    pub fn rows_per_image(&self) -> u32 {
+        self.rows_per_image
+    }
+}
+
+impl std::ops::Deref for DownloadTexture {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
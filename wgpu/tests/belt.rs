@@ -0,0 +1,212 @@
+use std::num::{NonZeroU32, NonZeroU64};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn read_buffer(ctx: &TestingContext, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    pollster::block_on(mapping).unwrap();
+    let data = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    data
+}
+
+#[test]
+fn belt_reuses_buffers() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let target = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 100 * 4,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 100 * 4,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut belt = wgpu::util::StagingBelt::new(256);
+        for frame in 0..3u8 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            for index in 0..100u64 {
+                belt.write_buffer(
+                    &mut encoder,
+                    &target,
+                    index * 4,
+                    NonZeroU64::new(4).unwrap(),
+                    &ctx.device,
+                )
+                .copy_from_slice(&[frame, index as u8, 0, 0]);
+            }
+            belt.finish();
+            encoder.copy_buffer_to_buffer(&target, 0, &readback, 0, 100 * 4);
+            ctx.queue.submit(Some(encoder.finish()));
+            let recall = belt.recall();
+            ctx.device.poll(wgpu::Maintain::Wait);
+            pollster::block_on(recall);
+
+            let data = read_buffer(&ctx, &readback);
+            for (index, value) in data.chunks(4).enumerate() {
+                assert_eq!(value, &[frame, index as u8, 0, 0]);
+            }
+        }
+
+        // Each write takes 8 bytes of a staging buffer.
+        let stats = belt.stats();
+        assert_eq!(stats.allocations, 300);
+        assert_eq!(stats.allocated_bytes, 300 * 4);
+        assert_eq!(stats.buffers_created, 4);
+        assert_eq!(stats.buffers, 4);
+        assert_eq!(stats.buffer_bytes, 4 * 256);
+    })
+}
+
+#[test]
+fn belt_write_texture() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let size = wgpu::Extent3d {
+            width: 3,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Uint,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        });
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 2 * 256,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut belt = wgpu::util::StagingBelt::new(1024);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        // Misalign the texture upload on purpose.
+        belt.write_buffer(
+            &mut encoder,
+            &readback,
+            0,
+            NonZeroU64::new(4).unwrap(),
+            &ctx.device,
+        );
+        let (mut view, bytes_per_row) = belt.write_texture(
+            &mut encoder,
+            texture.as_image_copy(),
+            wgpu::TextureFormat::Rgba8Uint,
+            size,
+            &ctx.device,
+        );
+        assert_eq!(bytes_per_row, 256);
+        for (row, data) in view.chunks_mut(bytes_per_row as usize).enumerate() {
+            for texel in 0..3 {
+                data[texel * 4..texel * 4 + 4].copy_from_slice(&[row as u8, texel as u8, 1, 2]);
+            }
+        }
+        drop(view);
+        belt.finish();
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(256),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let data = read_buffer(&ctx, &readback);
+        for row in 0..2 {
+            for texel in 0..3 {
+                let offset = row * 256 + texel * 4;
+                assert_eq!(&data[offset..offset + 4], &[row as u8, texel as u8, 1, 2]);
+            }
+        }
+    })
+}
+
+fn upload(ctx: &TestingContext, belt: &mut wgpu::util::StagingBelt, target: &wgpu::Buffer) {
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    belt.write_buffer(
+        &mut encoder,
+        target,
+        0,
+        NonZeroU64::new(4).unwrap(),
+        &ctx.device,
+    )
+    .copy_from_slice(&[1, 2, 3, 4]);
+    belt.finish();
+    ctx.queue.submit(Some(encoder.finish()));
+}
+
+#[test]
+fn belt_dropped_recall() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let target = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut belt = wgpu::util::StagingBelt::new(256);
+        upload(&ctx, &mut belt, &target);
+        drop(belt.recall());
+        ctx.device.poll(wgpu::Maintain::Wait);
+
+        // The buffer that was never seen mapped is replaced instead of being kept around.
+        upload(&ctx, &mut belt, &target);
+        let recall = belt.recall();
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(recall);
+        let stats = belt.stats();
+        assert_eq!(stats.buffers_created, 2);
+        assert_eq!(stats.buffers, 1);
+
+        // A buffer that is mapped again is reused.
+        upload(&ctx, &mut belt, &target);
+        assert_eq!(belt.stats().buffers_created, 2);
+    })
+}
+
+#[test]
+fn belt_failed_recall() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let target = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut belt = wgpu::util::StagingBelt::new(256);
+        upload(&ctx, &mut belt, &target);
+        let recall = belt.recall();
+        // Destroying the device makes the pending mappings fail.
+        ctx.device.destroy();
+        pollster::block_on(recall);
+        assert_eq!(belt.stats().buffers, 1);
+
+        // The buffers that failed to map are freed.
+        pollster::block_on(belt.recall());
+        assert_eq!(belt.stats().buffers, 0);
+    })
+}
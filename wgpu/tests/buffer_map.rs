@@ -0,0 +1,49 @@
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn buffer_map_at_offset() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let data = (0..=255).collect::<Vec<u8>>();
+
+        // Write through a mapping that starts in the middle of the buffer.
+        let upload = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 512,
+            usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let slice = upload.slice(256..);
+        let mapping = slice.map_async(wgpu::MapMode::Write);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        slice.get_mapped_range_mut()[128..192].copy_from_slice(&data[128..192]);
+        upload
+            .slice(448..)
+            .get_mapped_range_mut()
+            .copy_from_slice(&data[192..]);
+        upload.unmap();
+
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 512,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(&upload, 0, &readback, 0, 512);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        // Read through a mapping that starts in the middle of the buffer.
+        let slice = readback.slice(256..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        // The part of the mapping that wasn't written is zeroed.
+        assert_eq!(slice.get_mapped_range()[..128], [0; 128]);
+        assert_eq!(slice.get_mapped_range()[128..], data[128..]);
+        assert_eq!(readback.slice(320..384).get_mapped_range()[..], [0; 64]);
+        readback.unmap();
+    })
+}
@@ -109,6 +109,15 @@ impl TestParameters {
         self
     }
 
+    /// Mark the test as failing with the `remote` feature, for what the remote backend doesn't support.
+    pub fn remote_failure(self) -> Self {
+        if cfg!(all(unix, feature = "remote")) {
+            self.failure()
+        } else {
+            self
+        }
+    }
+
     /// Mark the test as always failing on a specific backend, equivilant to specific_failure(backend, None, None)
     pub fn backend_failure(mut self, backends: wgpu::Backends) -> Self {
         self.failures.push(FailureCase {
@@ -143,12 +152,32 @@ impl TestParameters {
         self
     }
 }
+#[cfg(not(all(unix, feature = "remote")))]
+fn initialize_instance(backend_bits: Backends) -> Instance {
+    Instance::new(backend_bits)
+}
+
+/// Run the tests through a server on another thread.
+#[cfg(all(unix, feature = "remote"))]
+fn initialize_instance(backend_bits: Backends) -> Instance {
+    let (client_stream, server_stream) = std::os::unix::net::UnixStream::pair().unwrap();
+    std::thread::spawn(move || {
+        let result =
+            wgpu_remote::Server::new(server_stream, backend_bits, wgt::PowerPreference::default())
+                .and_then(wgpu_remote::Server::run);
+        if let Err(err) = result {
+            log::error!("Server error: {}", err);
+        }
+    });
+    Instance::connect(client_stream)
+}
+
 pub fn initialize_test(parameters: TestParameters, test_function: impl FnOnce(TestingContext)) {
     // We don't actually care if it fails
     let _ = env_logger::try_init();
 
     let backend_bits = util::backend_bits_from_env().unwrap_or_else(Backends::all);
-    let instance = Instance::new(backend_bits);
+    let instance = initialize_instance(backend_bits);
     let adapter = pollster::block_on(util::initialize_adapter_from_env_or_default(
         &instance,
         backend_bits,
@@ -1,3 +1,8 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use crate::common::{initialize_test, TestParameters};
 
 #[test]
@@ -6,3 +11,118 @@ fn device_initialization() {
         // intentionally empty
     })
 }
+
+#[test]
+fn device_destroy() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let reason = Arc::new(Mutex::new(None));
+        let reason_clone = Arc::clone(&reason);
+        ctx.device.on_device_lost(move |reason, _message| {
+            *reason_clone.lock().unwrap() = Some(reason);
+        });
+
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mapping = buffer.slice(..).map_async(wgpu::MapMode::Read);
+
+        ctx.device.destroy();
+        assert_eq!(
+            *reason.lock().unwrap(),
+            Some(wgpu::DeviceLostReason::Destroyed)
+        );
+        assert!(pollster::block_on(mapping).is_err());
+
+        // Everything is an error from now on, but nothing panics.
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue.write_buffer(&buffer, 0, &[0; 16]);
+        let encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        ctx.queue.submit(Some(encoder.finish()));
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_some());
+        assert_eq!(
+            ctx.device
+                .poll_timeout(wgpu::Maintain::Wait, Duration::from_secs(1)),
+            Err(wgpu::PollError::DeviceLost)
+        );
+    })
+}
+
+#[test]
+fn device_lost_errors_are_not_uncaptured() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        ctx.device.destroy();
+
+        // The lost callback reports the loss, so the default handler doesn't panic.
+        let encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        ctx.queue.submit(Some(encoder.finish()));
+
+        // Mapping fails, without an error on top of it.
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let mapping = buffer.slice(..).map_async(wgpu::MapMode::Read);
+        assert!(pollster::block_on(mapping).is_err());
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+    })
+}
+
+#[test]
+fn device_memory_report() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let before = ctx.device.memory_report();
+
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 1024,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let report = ctx.device.memory_report();
+        assert!(!report.heaps.is_empty());
+        assert!(report.buffer_bytes() >= before.buffer_bytes() + 1024);
+        assert!(report.texture_bytes() >= before.texture_bytes() + 64 * 64 * 4);
+        assert!(report.block_bytes() >= report.buffer_bytes() + report.texture_bytes());
+        for heap in &report.heaps {
+            assert!(heap.used_bytes() <= heap.block_bytes);
+            assert!((0.0..=1.0).contains(&heap.fragmentation()));
+        }
+
+        drop(buffer);
+        drop(texture);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let after = ctx.device.memory_report();
+        assert_eq!(after.buffer_bytes(), before.buffer_bytes());
+        assert_eq!(after.texture_bytes(), before.texture_bytes());
+    })
+}
@@ -0,0 +1,205 @@
+use wgpu::util::{DeviceExt, DownloadTexture, TextureImage};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn download(
+    ctx: &TestingContext,
+    texture: wgpu::ImageCopyTexture,
+    size: wgpu::Extent3d,
+) -> DownloadTexture {
+    let download = DownloadTexture::read_texture(&ctx.device, &ctx.queue, texture, size);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    pollster::block_on(download).unwrap()
+}
+
+#[test]
+fn download_texture_layers_and_mips() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let desc = wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 3,
+                height: 2,
+                depth_or_array_layers: 2,
+            },
+            mip_level_count: 2,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Uint,
+            usage: wgpu::TextureUsages::COPY_SRC,
+        };
+        let layers = [
+            (0..24).collect::<Vec<u8>>(),
+            (100..124).collect::<Vec<u8>>(),
+        ];
+        let mip = vec![1, 2, 3, 4];
+        let texture = ctx.device.create_texture_with_images(
+            &ctx.queue,
+            &desc,
+            &[
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 0,
+                    data: &layers[0],
+                },
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 1,
+                    data: &layers[1],
+                },
+                TextureImage {
+                    mip_level: 1,
+                    array_layer: 1,
+                    data: &mip,
+                },
+            ],
+            false,
+        );
+
+        let all = download(&ctx, texture.as_image_copy(), desc.size);
+        assert_eq!(all.bytes_per_row(), 12);
+        assert_eq!(all.rows_per_image(), 2);
+        assert_eq!(&all[..24], &layers[0][..]);
+        assert_eq!(&all[24..], &layers[1][..]);
+
+        let mip1_layer1 = download(
+            &ctx,
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 1,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: 1 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        assert_eq!(&mip1_layer1[..], &mip[..]);
+    })
+}
+
+#[test]
+fn download_texture_depth_stencil() {
+    // GL can't copy stencil values to buffers.
+    initialize_test(
+        TestParameters::default().backend_failure(wgpu::Backends::GL),
+        |ctx| {
+            let size = wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            };
+            let create = |format| {
+                ctx.device.create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                })
+            };
+            let depth = create(wgpu::TextureFormat::Depth32Float);
+            let depth_stencil = create(wgpu::TextureFormat::Depth24PlusStencil8);
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            for (texture, stencil_ops) in [
+                (&depth, None),
+                (
+                    &depth_stencil,
+                    Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(7),
+                        store: true,
+                    }),
+                ),
+            ] {
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[],
+                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                        view: &view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(0.5),
+                            store: true,
+                        }),
+                        stencil_ops,
+                    }),
+                });
+            }
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let depth_data = download(
+                &ctx,
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::DepthOnly,
+                    ..depth.as_image_copy()
+                },
+                size,
+            );
+            assert_eq!(depth_data.bytes_per_row(), 16);
+            assert_eq!(depth_data.len(), 64);
+            for texel in depth_data.chunks(4) {
+                assert_eq!(
+                    f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]),
+                    0.5
+                );
+            }
+
+            let stencil_data = download(
+                &ctx,
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::StencilOnly,
+                    ..depth_stencil.as_image_copy()
+                },
+                size,
+            );
+            assert_eq!(stencil_data.bytes_per_row(), 4);
+            assert_eq!(&stencil_data[..], &[7; 16][..]);
+        },
+    )
+}
+
+#[test]
+fn download_texture_compressed() {
+    initialize_test(
+        TestParameters::default().features(wgpu::Features::TEXTURE_COMPRESSION_BC),
+        |ctx| {
+            // 2x2 blocks of 8 bytes.
+            let desc = wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: 8,
+                    height: 8,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Bc1RgbaUnorm,
+                usage: wgpu::TextureUsages::COPY_SRC,
+            };
+            let blocks = (0..32).collect::<Vec<u8>>();
+            let texture = ctx.device.create_texture_with_images(
+                &ctx.queue,
+                &desc,
+                &[TextureImage {
+                    mip_level: 0,
+                    array_layer: 0,
+                    data: &blocks,
+                }],
+                false,
+            );
+
+            let data = download(&ctx, texture.as_image_copy(), desc.size);
+            assert_eq!(data.bytes_per_row(), 16);
+            assert_eq!(data.rows_per_image(), 2);
+            assert_eq!(&data[..], &blocks[..]);
+        },
+    )
+}
@@ -0,0 +1,139 @@
+use wgpu::hal::{self, api::Cpu, CommandEncoder as _, Device as _};
+
+use crate::common::{initialize_test, TestParameters};
+
+/// The tests go through the hal objects of the CPU backend, which remote devices don't have.
+fn parameters() -> TestParameters {
+    TestParameters::default()
+        .specific_failure(
+            Some(wgpu::Backends::all() - wgpu::Backends::CPU),
+            None,
+            None,
+            true,
+        )
+        .remote_failure()
+}
+
+#[test]
+fn hal_buffer_round_trip() {
+    initialize_test(parameters(), |ctx| {
+        let hal_buffer = unsafe {
+            ctx.device.as_hal::<Cpu, _, _>(|device| {
+                device
+                    .unwrap()
+                    .create_buffer(&hal::BufferDescriptor {
+                        label: None,
+                        size: 16,
+                        usage: hal::BufferUses::COPY_SRC | hal::BufferUses::COPY_DST,
+                        memory_flags: hal::MemoryFlags::empty(),
+                    })
+                    .unwrap()
+            })
+        };
+        let buffer = unsafe {
+            ctx.device.create_buffer_from_hal::<Cpu>(
+                hal_buffer,
+                &wgpu::BufferDescriptor {
+                    label: None,
+                    size: 16,
+                    usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            )
+        };
+        let mut found = false;
+        unsafe { buffer.as_hal::<Cpu, _>(|hal_buffer| found = hal_buffer.is_some()) };
+        assert!(found);
+
+        let data = (0..16).collect::<Vec<u8>>();
+        ctx.queue.write_buffer(&buffer, 0, &data);
+
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        // Commands recorded through hal and through wgpu end up in the same command buffer.
+        let opened = unsafe {
+            encoder.as_hal_mut::<Cpu, _, _>(|hal_encoder| match hal_encoder {
+                Some(hal_encoder) => {
+                    hal_encoder.insert_debug_marker("hal");
+                    true
+                }
+                None => false,
+            })
+        };
+        assert!(opened);
+        encoder.copy_buffer_to_buffer(&buffer, 0, &readback, 0, 16);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        assert_eq!(*slice.get_mapped_range(), data[..]);
+    })
+}
+
+#[test]
+fn hal_buffer_map_usage() {
+    initialize_test(parameters(), |ctx| {
+        let hal_buffer = unsafe {
+            ctx.device.as_hal::<Cpu, _, _>(|device| {
+                device
+                    .unwrap()
+                    .create_buffer(&hal::BufferDescriptor {
+                        label: None,
+                        size: 16,
+                        usage: hal::BufferUses::MAP_READ | hal::BufferUses::COPY_DST,
+                        memory_flags: hal::MemoryFlags::empty(),
+                    })
+                    .unwrap()
+            })
+        };
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let _buffer = unsafe {
+            ctx.device.create_buffer_from_hal::<Cpu>(
+                hal_buffer,
+                &wgpu::BufferDescriptor {
+                    label: None,
+                    size: 16,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            )
+        };
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_some());
+    })
+}
+
+#[test]
+fn hal_texture_view() {
+    initialize_test(parameters(), |ctx| {
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut found = false;
+        unsafe { texture.as_hal::<Cpu, _>(|hal_texture| found = hal_texture.is_some()) };
+        assert!(found);
+        found = false;
+        unsafe { view.as_hal::<Cpu, _>(|hal_view| found = hal_view.is_some()) };
+        assert!(found);
+    })
+}
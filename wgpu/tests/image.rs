@@ -0,0 +1,304 @@
+use wgpu::util::image::{write_exr, write_png, ImageError, TextureFile};
+
+use crate::common::{initialize_test, TestParameters};
+use crate::texture_upload::read_image;
+
+const KTX2_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Build a KTX2 file from its header fields and the data of each level.
+fn ktx2(header: [u32; 9], levels: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = KTX2_IDENTIFIER.to_vec();
+    for value in header {
+        file.extend_from_slice(&value.to_le_bytes());
+    }
+    // Empty data format descriptor, key/value data and supercompression global data.
+    file.resize(80, 0);
+
+    let mut offset = 80 + levels.len() * 24;
+    for level in levels {
+        file.extend_from_slice(&(offset as u64).to_le_bytes());
+        file.extend_from_slice(&(level.len() as u64).to_le_bytes());
+        file.extend_from_slice(&(level.len() as u64).to_le_bytes());
+        offset += level.len();
+    }
+    for level in levels {
+        file.extend_from_slice(level);
+    }
+    file
+}
+
+/// Build a DDS file from its header, with the fields given as `(offset, value)`.
+fn dds(fields: &[(usize, u32)], dx10: bool, data: &[u8]) -> Vec<u8> {
+    let mut file = b"DDS ".to_vec();
+    file.resize(if dx10 { 148 } else { 128 }, 0);
+    for &(offset, value) in fields {
+        file[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    file.extend_from_slice(data);
+    file
+}
+
+#[test]
+fn image_ktx2_layers_and_mips() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let layer0_mip0 = [10; 16];
+        let layer1_mip0 = [20; 16];
+        let layer0_mip1 = [30; 4];
+        let layer1_mip1 = [40; 4];
+        // VK_FORMAT_R8G8B8A8_UNORM, 2x2, 2 layers, 2 levels.
+        let bytes = ktx2(
+            [37, 1, 2, 2, 0, 2, 1, 2, 0],
+            &[
+                [layer0_mip0, layer1_mip0].concat(),
+                [layer0_mip1, layer1_mip1].concat(),
+            ],
+        );
+
+        let file = TextureFile::from_ktx2(&bytes).unwrap();
+        assert_eq!(file.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(file.dimension, wgpu::TextureDimension::D2);
+        assert_eq!(file.mip_level_count, 2);
+        assert_eq!(file.view_dimension(), wgpu::TextureViewDimension::D2Array);
+        assert_eq!(
+            file.data,
+            [
+                &layer0_mip0[..],
+                &layer0_mip1[..],
+                &layer1_mip0[..],
+                &layer1_mip1[..]
+            ]
+            .concat()
+        );
+
+        let texture =
+            file.create_texture(&ctx.device, &ctx.queue, None, wgpu::TextureUsages::COPY_SRC);
+        assert_eq!(read_image(&ctx, &texture, 0, 1, 2, 2), layer1_mip0);
+        assert_eq!(read_image(&ctx, &texture, 1, 0, 1, 1), layer0_mip1);
+    })
+}
+
+#[test]
+fn image_ktx2_compressed_cube() {
+    // VK_FORMAT_BC1_RGBA_SRGB_BLOCK, 8x8 cube map, 2x2 blocks of 8 bytes per face.
+    let level = (0..6 * 32).map(|byte| byte as u8).collect::<Vec<_>>();
+    let file = TextureFile::from_ktx2(&ktx2(
+        [134, 1, 8, 8, 0, 0, 6, 1, 0],
+        std::slice::from_ref(&level),
+    ))
+    .unwrap();
+    assert_eq!(file.format, wgpu::TextureFormat::Bc1RgbaUnormSrgb);
+    assert_eq!(file.size.depth_or_array_layers, 6);
+    assert!(file.is_cube);
+    assert_eq!(file.view_dimension(), wgpu::TextureViewDimension::Cube);
+    assert_eq!(file.data, level);
+
+    // ASTC 6x6 blocks of 16 bytes.
+    let file =
+        TextureFile::from_ktx2(&ktx2([165, 1, 7, 6, 0, 0, 1, 1, 0], &[vec![0; 32]])).unwrap();
+    assert_eq!(file.format, wgpu::TextureFormat::Astc6x6RgbaUnorm);
+}
+
+#[test]
+fn image_ktx2_errors() {
+    assert!(matches!(
+        TextureFile::from_ktx2(b"not a texture"),
+        Err(ImageError::Invalid(_))
+    ));
+    // Zstandard supercompression.
+    assert!(matches!(
+        TextureFile::from_ktx2(&ktx2([37, 1, 1, 1, 0, 0, 1, 1, 2], &[vec![0; 4]])),
+        Err(ImageError::Unsupported(_))
+    ));
+    // The level is missing a texel.
+    assert!(matches!(
+        TextureFile::from_ktx2(&ktx2([37, 1, 2, 1, 0, 0, 1, 1, 0], &[vec![0; 4]])),
+        Err(ImageError::Invalid(_))
+    ));
+}
+
+#[test]
+fn image_ktx2_hostile_headers() {
+    let invalid =
+        |bytes: &[u8]| matches!(TextureFile::from_ktx2(bytes), Err(ImageError::Invalid(_)));
+    let levels = &[vec![0; 4]];
+
+    let bytes = ktx2([37, 1, 1, 1, 0, 0, 1, 1, 0], levels);
+    assert!(invalid(&bytes[..79]));
+    assert!(invalid(&bytes[..90]));
+    // More levels than 32 bit sizes allow, and than a 4x4 texture has.
+    assert!(invalid(&ktx2([37, 1, 1, 1, 0, 0, 1, u32::MAX, 0], levels)));
+    assert!(invalid(&ktx2([37, 1, 4, 4, 0, 0, 1, 4, 0], levels)));
+    // The level index of the two levels doesn't fit in the file.
+    assert!(invalid(&ktx2([37, 1, 2, 2, 0, 0, 1, 2, 0], levels)));
+    // Layer counts that overflow, or that the file can't hold.
+    assert!(invalid(&ktx2([37, 1, 1, 1, 0, u32::MAX, 6, 1, 0], levels)));
+    assert!(invalid(&ktx2([37, 1, 1, 1, 0, u32::MAX, 1, 1, 0], levels)));
+    // Sizes that overflow, and that used to overflow when rounded up to BC1 blocks.
+    assert!(invalid(&ktx2(
+        [37, 1, u32::MAX, u32::MAX, 0, 0, 1, 1, 0],
+        levels
+    )));
+    assert!(invalid(&ktx2(
+        [131, 1, u32::MAX, u32::MAX, 0, 0, 1, 1, 0],
+        levels
+    )));
+    assert!(invalid(&ktx2(
+        [37, 1, u32::MAX, u32::MAX, u32::MAX, 0, 1, 1, 0],
+        levels
+    )));
+    // The level ends past the end of the address space.
+    let mut bytes = ktx2([37, 1, 1, 1, 0, 0, 1, 1, 0], levels);
+    bytes[80..88].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(invalid(&bytes));
+}
+
+#[test]
+fn image_dds_legacy_and_dx10() {
+    // DXT5 4x4 with 3 mip levels of a single block each.
+    let data = (0..48).collect::<Vec<u8>>();
+    let bytes = dds(
+        &[
+            (8, 0x2_0000),
+            (12, 4),
+            (16, 4),
+            (28, 3),
+            (80, 0x4),
+            (84, u32::from_le_bytes(*b"DXT5")),
+        ],
+        false,
+        &data,
+    );
+    let file = TextureFile::from_dds(&bytes).unwrap();
+    assert_eq!(file.format, wgpu::TextureFormat::Bc3RgbaUnorm);
+    assert_eq!(file.mip_level_count, 3);
+    assert_eq!(file.data, data);
+
+    // DX10 R32Float 2x1 array of 2 layers.
+    let data = (0..16).collect::<Vec<u8>>();
+    let bytes = dds(
+        &[
+            (12, 1),
+            (16, 2),
+            (80, 0x4),
+            (84, u32::from_le_bytes(*b"DX10")),
+            (128, 41),
+            (132, 3),
+            (140, 2),
+        ],
+        true,
+        &data,
+    );
+    let file = TextureFile::from_dds(&bytes).unwrap();
+    assert_eq!(file.format, wgpu::TextureFormat::R32Float);
+    assert_eq!(
+        file.size,
+        wgpu::Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 2
+        }
+    );
+    assert_eq!(file.data, data);
+
+    // Truncated data.
+    assert!(matches!(
+        TextureFile::from_dds(&bytes[..bytes.len() - 1]),
+        Err(ImageError::Invalid(_))
+    ));
+}
+
+#[test]
+fn image_dds_hostile_headers() {
+    let invalid =
+        |bytes: &[u8]| matches!(TextureFile::from_dds(bytes), Err(ImageError::Invalid(_)));
+    let rgba8 = [
+        (80, 0x40),
+        (88, 32),
+        (92, 0xFF),
+        (96, 0xFF00),
+        (100, 0xFF_0000),
+        (104, 0xFF00_0000),
+    ];
+    let dds_rgba8 = |fields: &[(usize, u32)]| dds(&[&rgba8[..], fields].concat(), false, &[0; 4]);
+    let dds_dx10 = |fields: &[(usize, u32)]| {
+        let header = [(80, 0x4), (84, u32::from_le_bytes(*b"DX10")), (128, 28)];
+        dds(&[&header[..], fields].concat(), true, &[0; 4])
+    };
+
+    let bytes = dds_rgba8(&[(12, 1), (16, 1)]);
+    assert!(TextureFile::from_dds(&bytes).is_ok());
+    assert!(invalid(&bytes[..100]));
+    assert!(invalid(&dds_dx10(&[(132, 3)])[..140]));
+    // More mip levels than 32 bit sizes allow, and than a 4x4 texture has.
+    assert!(invalid(&dds_rgba8(&[(8, 0x2_0000), (28, u32::MAX)])));
+    assert!(invalid(&dds_rgba8(&[
+        (8, 0x2_0000),
+        (12, 4),
+        (16, 4),
+        (28, 4)
+    ])));
+    // Sizes that overflow, and slices that overflow the data size.
+    assert!(invalid(&dds_rgba8(&[(12, u32::MAX), (16, u32::MAX)])));
+    assert!(invalid(&dds_rgba8(&[
+        (12, u32::MAX),
+        (16, u32::MAX),
+        (24, u32::MAX),
+        (112, 0x20_0000)
+    ])));
+    // Array sizes that overflow once multiplied by the 6 faces, or that the file can't hold.
+    assert!(invalid(&dds_dx10(&[(132, 3), (136, 0x4), (140, u32::MAX)])));
+    assert!(invalid(&dds_dx10(&[(132, 3), (140, u32::MAX)])));
+}
+
+#[test]
+fn image_write_png() {
+    let mut bytes = Vec::new();
+    write_png(
+        &mut bytes,
+        wgpu::TextureFormat::Bgra8Unorm,
+        2,
+        1,
+        &[1, 2, 3, 4, 5, 6, 7, 8],
+    )
+    .unwrap();
+
+    let (info, mut reader) = png::Decoder::new(&bytes[..]).read_info().unwrap();
+    assert_eq!((info.width, info.height), (2, 1));
+    assert_eq!(info.color_type, png::ColorType::RGBA);
+    let mut pixels = vec![0; info.buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(pixels, [3, 2, 1, 4, 7, 6, 5, 8]);
+
+    assert!(matches!(
+        write_png(&mut bytes, wgpu::TextureFormat::Rgba32Float, 1, 1, &[0; 16]),
+        Err(ImageError::UnsupportedFormat(
+            wgpu::TextureFormat::Rgba32Float
+        ))
+    ));
+}
+
+#[test]
+fn image_write_exr() {
+    let mut bytes = Vec::new();
+    // Two texels with R and G components of 2 bytes each.
+    write_exr(
+        &mut bytes,
+        wgpu::TextureFormat::Rg16Float,
+        2,
+        1,
+        &[1, 2, 3, 4, 5, 6, 7, 8],
+    )
+    .unwrap();
+
+    assert_eq!(bytes[..8], [0x76, 0x2F, 0x31, 0x01, 2, 0, 0, 0]);
+    // The only chunk is at the end of the file, with the G channel before the R channel.
+    let chunk = &bytes[bytes.len() - 16..];
+    assert_eq!(chunk, [0, 0, 0, 0, 8, 0, 0, 0, 3, 4, 7, 8, 1, 2, 5, 6]);
+    let offset = bytes.len() as u64 - 16;
+    assert_eq!(
+        bytes[offset as usize - 8..offset as usize],
+        offset.to_le_bytes()
+    );
+}
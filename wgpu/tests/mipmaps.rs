@@ -0,0 +1,265 @@
+use wgpu::util::{DeviceExt, GenerateMipmapsError, MipmapGenerator, TextureImage};
+
+use crate::common::{initialize_test, TestParameters};
+use crate::texture_upload::read_image;
+
+fn texture_descriptor(
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    layers: u32,
+) -> wgpu::TextureDescriptor<'static> {
+    wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 3,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+    }
+}
+
+#[test]
+fn generate_mipmaps_cube_map() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let desc = texture_descriptor(
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            6,
+        );
+        let faces = (0..6u8)
+            .map(|face| [face * 40, 255 - face * 40, face, 255].repeat(16))
+            .collect::<Vec<_>>();
+        let images = faces
+            .iter()
+            .enumerate()
+            .map(|(face, data)| TextureImage {
+                mip_level: 0,
+                array_layer: face as u32,
+                data,
+            })
+            .collect::<Vec<_>>();
+        let texture = ctx
+            .device
+            .create_texture_with_images(&ctx.queue, &desc, &images, false);
+
+        // Generate twice, to go through the cached pipeline.
+        for _ in 0..2 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            wgpu::util::generate_mipmaps(&ctx.device, &mut encoder, &texture).unwrap();
+            ctx.queue.submit(Some(encoder.finish()));
+        }
+
+        for (face, data) in faces.iter().enumerate() {
+            assert_eq!(read_image(&ctx, &texture, 1, face as u32, 2, 2), data[..16]);
+            assert_eq!(read_image(&ctx, &texture, 2, face as u32, 1, 1), data[..4]);
+        }
+    })
+}
+
+#[test]
+fn generate_mipmaps_compute_fallback() {
+    initialize_test(
+        TestParameters::default().downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            // `R32Float` isn't filterable, but can be used as a storage texture.
+            let desc = texture_descriptor(
+                wgpu::TextureFormat::R32Float,
+                wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::STORAGE_BINDING,
+                1,
+            );
+            let texels = (0..16u8)
+                .flat_map(|texel| f32::from(texel).to_ne_bytes())
+                .collect::<Vec<_>>();
+            let texture = ctx.device.create_texture_with_images(
+                &ctx.queue,
+                &desc,
+                &[TextureImage {
+                    mip_level: 0,
+                    array_layer: 0,
+                    data: &texels,
+                }],
+                false,
+            );
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            wgpu::util::generate_mipmaps(&ctx.device, &mut encoder, &texture).unwrap();
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let read_floats = |mip_level, size| {
+                read_image(&ctx, &texture, mip_level, 0, size, size)
+                    .chunks(4)
+                    .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(read_floats(1, 2), [2.5, 4.5, 10.5, 12.5]);
+            assert_eq!(read_floats(2, 1), [7.5]);
+        },
+    )
+}
+
+fn generate_integer_mipmaps(
+    ctx: &crate::common::TestingContext,
+    generator: &mut MipmapGenerator,
+    format: wgpu::TextureFormat,
+    texels: &[[u8; 4]],
+) -> (Vec<u8>, Vec<u8>) {
+    let desc = texture_descriptor(
+        format,
+        wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::STORAGE_BINDING,
+        1,
+    );
+    let texture = ctx.device.create_texture_with_images(
+        &ctx.queue,
+        &desc,
+        &[TextureImage {
+            mip_level: 0,
+            array_layer: 0,
+            data: &texels.concat(),
+        }],
+        false,
+    );
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    generator
+        .generate(&ctx.device, &mut encoder, &texture)
+        .unwrap();
+    ctx.queue.submit(Some(encoder.finish()));
+    (
+        read_image(ctx, &texture, 1, 0, 2, 2),
+        read_image(ctx, &texture, 2, 0, 1, 1),
+    )
+}
+
+#[test]
+fn generate_mipmaps_integer() {
+    initialize_test(
+        TestParameters::default().downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let mut generator = MipmapGenerator::new();
+
+            // Values close to the limits would overflow if they were summed.
+            let texels = (0..16u32)
+                .map(|texel| (u32::MAX - texel).to_ne_bytes())
+                .collect::<Vec<_>>();
+            let (level1, level2) = generate_integer_mipmaps(
+                &ctx,
+                &mut generator,
+                wgpu::TextureFormat::R32Uint,
+                &texels,
+            );
+            let values = level1
+                .chunks(4)
+                .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                values,
+                [u32::MAX - 3, u32::MAX - 5, u32::MAX - 11, u32::MAX - 13]
+            );
+            assert_eq!(
+                u32::from_ne_bytes([level2[0], level2[1], level2[2], level2[3]]),
+                u32::MAX - 8
+            );
+
+            // Averages of signed values are rounded down.
+            let texels = (0..16i32)
+                .map(|texel| (i32::MIN + texel).to_ne_bytes())
+                .collect::<Vec<_>>();
+            let (level1, level2) = generate_integer_mipmaps(
+                &ctx,
+                &mut generator,
+                wgpu::TextureFormat::R32Sint,
+                &texels,
+            );
+            let values = level1
+                .chunks(4)
+                .map(|bytes| i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                values,
+                [i32::MIN + 2, i32::MIN + 4, i32::MIN + 10, i32::MIN + 12]
+            );
+            assert_eq!(
+                i32::from_ne_bytes([level2[0], level2[1], level2[2], level2[3]]),
+                i32::MIN + 7
+            );
+
+            // The pipeline built for the first texture is used again.
+            let levels = generate_integer_mipmaps(
+                &ctx,
+                &mut generator,
+                wgpu::TextureFormat::R32Sint,
+                &texels,
+            );
+            assert_eq!(levels, (level1, level2));
+        },
+    )
+}
+
+#[test]
+fn generate_mipmaps_errors() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let generate = |desc: &wgpu::TextureDescriptor| {
+            let texture = ctx.device.create_texture(desc);
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            wgpu::util::generate_mipmaps(&ctx.device, &mut encoder, &texture)
+        };
+
+        // `Rg8Uint` is neither filterable nor usable as a storage texture.
+        let desc = texture_descriptor(
+            wgpu::TextureFormat::Rg8Uint,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+            1,
+        );
+        assert_eq!(
+            generate(&desc),
+            Err(GenerateMipmapsError::UnsupportedFormat(
+                wgpu::TextureFormat::Rg8Uint
+            ))
+        );
+
+        let desc = wgpu::TextureDescriptor {
+            dimension: wgpu::TextureDimension::D3,
+            ..texture_descriptor(
+                wgpu::TextureFormat::Rgba8Unorm,
+                wgpu::TextureUsages::TEXTURE_BINDING,
+                1,
+            )
+        };
+        assert_eq!(
+            generate(&desc),
+            Err(GenerateMipmapsError::UnsupportedDimension(
+                wgpu::TextureDimension::D3
+            ))
+        );
+
+        let desc = texture_descriptor(
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureUsages::TEXTURE_BINDING,
+            1,
+        );
+        assert_eq!(
+            generate(&desc),
+            Err(GenerateMipmapsError::MissingUsages(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            ))
+        );
+    })
+}
@@ -0,0 +1,239 @@
+use std::borrow::Cow;
+
+use crate::common::{initialize_test, TestParameters};
+
+const SHADER: &str = "
+struct Data {
+    values: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> data: Data;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    data.values[0] = 1u;
+}
+";
+
+const RENDER_SHADER: &str = "
+struct Globals {
+    color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> globals: Globals;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> [[builtin(position)]] vec4<f32> {
+    let x = f32(i32(index) - 1);
+    let y = f32(i32(index & 1u) * 2 - 1);
+    return vec4<f32>(x, y, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return globals.color;
+}
+";
+
+#[test]
+fn create_compute_pipeline_async() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let pipeline = pollster::block_on(ctx.device.create_compute_pipeline_async(
+            &wgpu::ComputePipelineDescriptor {
+                label: Some("async"),
+                layout: None,
+                module: &module,
+                entry_point: "main",
+                cache: None,
+            },
+        ))
+        .unwrap();
+        // The implicit layout is available right away.
+        let _ = pipeline.get_bind_group_layout(0);
+    })
+}
+
+#[test]
+fn create_compute_pipeline_async_error() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = pollster::block_on(ctx.device.create_compute_pipeline_async(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                module: &module,
+                entry_point: "missing",
+                cache: None,
+            },
+        ));
+        assert!(matches!(result, Err(wgpu::Error::Validation { .. })));
+        // The error is returned instead of being reported.
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+    })
+}
+
+#[test]
+fn create_compute_pipeline_async_dropped_module() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        // Neither the pipeline nor the module are kept while the pipeline is being
+        // created, and the new module can't take the id of the old one until it's done.
+        drop(
+            ctx.device
+                .create_compute_pipeline_async(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &module,
+                    entry_point: "main",
+                    cache: None,
+                }),
+        );
+        drop(module);
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let pipeline = pollster::block_on(ctx.device.create_compute_pipeline_async(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                module: &module,
+                entry_point: "main",
+                cache: None,
+            },
+        ))
+        .unwrap();
+        let _ = pipeline.get_bind_group_layout(0);
+    })
+}
+
+#[test]
+fn render_pass_during_create_render_pipeline_async() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(RENDER_SHADER)),
+            });
+        let bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+        let layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let descriptor = wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[wgpu::TextureFormat::Rgba8Unorm.into()],
+            }),
+            multiview: None,
+            cache: None,
+        };
+        let pipeline = ctx.device.create_render_pipeline(&descriptor);
+        // The workers compile these while the frame below is recorded,
+        // which uses the same layouts.
+        let pending = (0..16)
+            .map(|_| ctx.device.create_render_pipeline_async(&descriptor))
+            .collect::<Vec<_>>();
+
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations::default(),
+                }],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        ctx.queue.submit(Some(encoder.finish()));
+
+        for pipeline in pending {
+            assert!(pollster::block_on(pipeline).is_ok());
+        }
+    })
+}
@@ -0,0 +1,71 @@
+use std::borrow::Cow;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+const SHADER: &str = "
+[[stage(compute), workgroup_size(1)]]
+fn main() {}
+";
+
+fn create_pipeline(ctx: &TestingContext, cache: &wgpu::PipelineCache) {
+    let module = ctx
+        .device
+        .create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+    let _ = ctx
+        .device
+        .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+            cache: Some(cache),
+        });
+}
+
+#[test]
+fn pipeline_cache_round_trip() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let cache = unsafe {
+            ctx.device
+                .create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                    label: Some("first run"),
+                    data: None,
+                    fallback: false,
+                })
+        };
+        create_pipeline(&ctx, &cache);
+        let data = cache.get_data().unwrap();
+
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let cache = unsafe {
+            ctx.device
+                .create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                    label: Some("second run"),
+                    data: Some(&data),
+                    fallback: false,
+                })
+        };
+        create_pipeline(&ctx, &cache);
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+    })
+}
+
+#[test]
+fn pipeline_cache_fallback() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let cache = unsafe {
+            ctx.device
+                .create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                    label: None,
+                    data: Some(b"not a pipeline cache"),
+                    fallback: true,
+                })
+        };
+        create_pipeline(&ctx, &cache);
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+    })
+}
@@ -0,0 +1,106 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn submit_copy(ctx: &TestingContext, buffer: &wgpu::Buffer) -> wgpu::SubmissionIndex {
+    let source = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 16,
+        usage: wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(&source, 0, buffer, 0, 16);
+    ctx.queue.submit(Some(encoder.finish()))
+}
+
+#[test]
+fn wait_for_submission() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let index = submit_copy(&ctx, &buffer);
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(index));
+        pollster::block_on(mapping).unwrap();
+    })
+}
+
+#[test]
+fn wait_for_older_submission() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let first = submit_copy(&ctx, &buffer);
+        let _second = submit_copy(&ctx, &buffer);
+        ctx.device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(first));
+        ctx.device.poll(wgpu::Maintain::Wait);
+    })
+}
+
+#[test]
+fn poll_timeout() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let lost = Arc::new(AtomicBool::new(false));
+        let lost_clone = Arc::clone(&lost);
+        ctx.device.on_device_lost(move |_reason, _message| {
+            lost_clone.store(true, Ordering::SeqCst);
+        });
+
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index = submit_copy(&ctx, &buffer);
+        ctx.device
+            .poll_timeout(
+                wgpu::Maintain::WaitForSubmissionIndex(index),
+                Duration::from_secs(1),
+            )
+            .unwrap();
+        assert!(!lost.load(Ordering::SeqCst));
+    })
+}
+
+#[test]
+fn wait_for_submission_of_other_device() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index = submit_copy(&ctx, &buffer);
+        let (device, _queue) = pollster::block_on(
+            ctx.adapter
+                .request_device(&wgpu::DeviceDescriptor::default(), None),
+        )
+        .unwrap();
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        device.poll(wgpu::Maintain::WaitForSubmissionIndex(index));
+        assert!(pollster::block_on(device.pop_error_scope()).is_some());
+    })
+}
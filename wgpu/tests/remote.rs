@@ -0,0 +1,121 @@
+//! Tests of what the remote backend emulates on top of the server.
+
+use std::borrow::Cow;
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn drop_before_submit() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let data = (0..16).collect::<Vec<u8>>();
+        let upload = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: true,
+        });
+        upload
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(&data);
+        upload.unmap();
+        let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // The commands only reach the server on submission, after the buffer is dropped.
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(&upload, 0, &readback, 0, 16);
+        drop(upload);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        assert_eq!(&slice.get_mapped_range()[..], &data[..]);
+    })
+}
+
+#[test]
+fn error_scopes() {
+    initialize_test(TestParameters::default(), |ctx| {
+        // Errors are reported by the server later on, but still reach their scope.
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let _ = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::empty(),
+            mapped_at_creation: false,
+        });
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_some());
+    })
+}
+
+#[test]
+fn create_compute_pipeline_async_error() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    "[[stage(compute), workgroup_size(1)]]\nfn main() {}\n",
+                )),
+            });
+        let layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[],
+                push_constant_ranges: &[],
+            });
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = pollster::block_on(ctx.device.create_compute_pipeline_async(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "missing",
+                cache: None,
+            },
+        ));
+        assert!(matches!(result, Err(wgpu::Error::Validation { .. })));
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_none());
+
+        let result = pollster::block_on(ctx.device.create_compute_pipeline_async(
+            &wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&layout),
+                module: &module,
+                entry_point: "main",
+                cache: None,
+            },
+        ));
+        assert!(result.is_ok());
+    })
+}
+
+#[test]
+fn unmap_pending() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        // Mappings are done on the next poll, unless they're aborted first.
+        let mapping = buffer.slice(..).map_async(wgpu::MapMode::Read);
+        buffer.unmap();
+        ctx.device.poll(wgpu::Maintain::Wait);
+        assert!(pollster::block_on(mapping).is_err());
+    })
+}
@@ -1,9 +1,26 @@
 // All files containing tests
 mod common;
 
+mod belt;
+mod buffer_map;
 mod clear_texture;
 mod device;
+mod download_texture;
 mod example_wgsl;
+#[cfg(feature = "cpu")]
+mod hal_interop;
+#[cfg(feature = "image")]
+mod image;
 mod instance;
+mod mipmaps;
+mod pipeline_async;
+mod pipeline_cache;
+mod poll;
+#[cfg(all(unix, feature = "remote"))]
+mod remote;
+mod shader_compilation_info;
+mod shader_reflection;
+mod texture_upload;
 mod vertex_indices;
+mod write_buffer_with;
 mod zero_init_texture_after_discard;
@@ -0,0 +1,69 @@
+use std::borrow::Cow;
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+fn compile(ctx: &TestingContext, source: &str) -> wgpu::CompilationInfo {
+    ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = ctx
+        .device
+        .create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+        });
+    let error = pollster::block_on(ctx.device.pop_error_scope());
+    let info = pollster::block_on(module.get_compilation_info());
+    assert_eq!(error.is_some(), !info.messages.is_empty());
+    info
+}
+
+#[test]
+fn valid_shader() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let info = compile(
+            &ctx,
+            "[[stage(compute), workgroup_size(1)]]\nfn main() {}\n",
+        );
+        assert_eq!(info, wgpu::CompilationInfo::default());
+    })
+}
+
+#[test]
+fn parsing_error() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let info = compile(&ctx, "fn main() {\n    let x = 1 +;\n}\n");
+        assert_eq!(
+            info.messages,
+            [wgpu::CompilationMessage {
+                message: "expected expression, found ';'".to_string(),
+                message_type: wgpu::CompilationMessageType::Error,
+                line_num: 2,
+                line_pos: 16,
+                offset: 27,
+                length: 0,
+            }]
+        );
+    })
+}
+
+#[test]
+fn validation_error() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        // Positions are in UTF-16 code units, so 'é' counts once.
+        let source = "// é\nfn f() -> u32 {\n    return 1.0;\n}\n";
+        let info = compile(&ctx, source);
+
+        let error = &info.messages[0];
+        assert_eq!(error.message_type, wgpu::CompilationMessageType::Error);
+        assert!(error
+            .message
+            .contains("does not match the function return value"));
+        assert_eq!((error.line_num, error.line_pos), (3, 11));
+        assert_eq!((error.offset, error.length), (31, 4));
+
+        assert!(info.messages.len() > 1);
+        for message in &info.messages[1..] {
+            assert_eq!(message.message_type, wgpu::CompilationMessageType::Info);
+            assert_ne!(message.line_num, 0);
+        }
+    })
+}
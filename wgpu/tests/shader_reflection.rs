@@ -0,0 +1,152 @@
+use std::{borrow::Cow, num::NonZeroU64};
+
+use crate::common::{initialize_test, TestParameters};
+
+const SHADER: &str = "
+struct Globals {
+    transform: mat4x4<f32>;
+};
+[[group(0), binding(0)]] var<uniform> globals: Globals;
+[[group(1), binding(1)]] var color_texture: texture_2d<f32>;
+[[group(1), binding(0)]] var color_sampler: sampler;
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(1)]] uv: vec2<f32>,
+    [[location(0)]] position: vec3<f32>,
+    [[location(2)]] id: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = globals.transform * vec4<f32>(position, f32(id));
+    out.uv = uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(color_texture, color_sampler, in.uv);
+}
+
+struct Data {
+    values: array<u32>;
+};
+[[group(0), binding(0)]] var<storage, read_write> data: Data;
+
+[[stage(compute), workgroup_size(8, 4)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    data.values[id.x] = id.y;
+}
+";
+
+#[test]
+fn shader_reflection() {
+    initialize_test(TestParameters::default().remote_failure(), |ctx| {
+        let module = ctx
+            .device
+            .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+        let reflection = module.reflect().unwrap();
+
+        let names = reflection
+            .entry_points
+            .iter()
+            .map(|ep| ep.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["cs_main", "fs_main", "vs_main"]);
+
+        let vs = reflection
+            .entry_point("vs_main", wgpu::ShaderStages::VERTEX)
+            .unwrap();
+        assert_eq!(
+            vs.bindings,
+            [wgpu::BindingReflection {
+                group: 0,
+                binding: 0,
+                ty: Some(wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(64),
+                }),
+            }]
+        );
+        assert_eq!(
+            vs.vertex_inputs,
+            [
+                wgpu::VertexInputReflection {
+                    location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexInputReflection {
+                    location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexInputReflection {
+                    location: 2,
+                    format: wgpu::VertexFormat::Uint32,
+                },
+            ]
+        );
+        assert!(vs.fragment_outputs.is_empty());
+
+        let fs = reflection
+            .entry_point("fs_main", wgpu::ShaderStages::FRAGMENT)
+            .unwrap();
+        assert_eq!(
+            fs.bindings,
+            [
+                wgpu::BindingReflection {
+                    group: 1,
+                    binding: 0,
+                    ty: Some(wgpu::BindingType::Sampler(
+                        wgpu::SamplerBindingType::Filtering
+                    )),
+                },
+                wgpu::BindingReflection {
+                    group: 1,
+                    binding: 1,
+                    ty: Some(wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    }),
+                },
+            ]
+        );
+        assert!(fs.vertex_inputs.is_empty());
+        assert_eq!(
+            fs.fragment_outputs,
+            [wgpu::FragmentOutputReflection {
+                location: 0,
+                kind: wgpu::ShaderScalarKind::Float,
+                components: 4,
+            }]
+        );
+
+        let cs = reflection
+            .entry_point("cs_main", wgpu::ShaderStages::COMPUTE)
+            .unwrap();
+        assert_eq!(
+            cs.bindings,
+            [wgpu::BindingReflection {
+                group: 0,
+                binding: 0,
+                ty: Some(wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(4),
+                }),
+            }]
+        );
+        assert_eq!(cs.workgroup_size, [8, 4, 1]);
+        assert!(reflection
+            .entry_point("cs_main", wgpu::ShaderStages::VERTEX)
+            .is_none());
+    })
+}
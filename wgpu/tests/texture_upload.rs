@@ -0,0 +1,167 @@
+use std::num::NonZeroU32;
+
+use wgpu::util::{DeviceExt, TextureImage};
+
+use crate::common::{initialize_test, TestParameters, TestingContext};
+
+/// Read back tightly packed rows of 4 byte texels of a 2D texture image.
+pub fn read_image(
+    ctx: &TestingContext,
+    texture: &wgpu::Texture,
+    mip_level: u32,
+    array_layer: u32,
+    width: u32,
+    height: u32,
+) -> Vec<u8> {
+    let readback = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 256 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: array_layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(256),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    ctx.device.poll(wgpu::Maintain::Wait);
+    pollster::block_on(mapping).unwrap();
+    let data = slice.get_mapped_range();
+    data.chunks(256)
+        .flat_map(|row| row[..width as usize * 4].iter().copied())
+        .collect()
+}
+
+#[test]
+fn texture_upload_layers_and_mips() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let desc = wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 3,
+                height: 2,
+                depth_or_array_layers: 2,
+            },
+            mip_level_count: 2,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Uint,
+            usage: wgpu::TextureUsages::COPY_SRC,
+        };
+        let image = |seed: u8, texels: u8| {
+            (0..texels)
+                .flat_map(|texel| [seed, texel, 1, 2])
+                .collect::<Vec<_>>()
+        };
+        let layer0_mip0 = image(10, 6);
+        let layer1_mip0 = image(20, 6);
+        let layer1_mip1 = image(30, 1);
+
+        // Out of order on purpose, and without an image for mip 1 of layer 0.
+        let texture = ctx.device.create_texture_with_images(
+            &ctx.queue,
+            &desc,
+            &[
+                TextureImage {
+                    mip_level: 1,
+                    array_layer: 1,
+                    data: &layer1_mip1,
+                },
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 0,
+                    data: &layer0_mip0,
+                },
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 1,
+                    data: &layer1_mip0,
+                },
+            ],
+            false,
+        );
+
+        assert_eq!(read_image(&ctx, &texture, 0, 0, 3, 2), layer0_mip0);
+        assert_eq!(read_image(&ctx, &texture, 0, 1, 3, 2), layer1_mip0);
+        assert_eq!(read_image(&ctx, &texture, 1, 0, 1, 1), [0; 4]);
+        assert_eq!(read_image(&ctx, &texture, 1, 1, 1, 1), layer1_mip1);
+    })
+}
+
+#[test]
+fn texture_upload_generates_mipmaps() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let desc = wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 2,
+            },
+            mip_level_count: 3,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_SRC,
+        };
+        let red = [255, 0, 0, 255].repeat(16);
+        let green = [0, 255, 0, 255].repeat(16);
+        let blue = [0, 0, 255, 255].repeat(4);
+
+        // Mip 2 of layer 1 comes from the given mip 1 rather than from mip 0.
+        let texture = ctx.device.create_texture_with_images(
+            &ctx.queue,
+            &desc,
+            &[
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 0,
+                    data: &red,
+                },
+                TextureImage {
+                    mip_level: 0,
+                    array_layer: 1,
+                    data: &green,
+                },
+                TextureImage {
+                    mip_level: 1,
+                    array_layer: 1,
+                    data: &blue,
+                },
+            ],
+            true,
+        );
+
+        assert_eq!(read_image(&ctx, &texture, 1, 0, 2, 2), red[..16]);
+        assert_eq!(read_image(&ctx, &texture, 2, 0, 1, 1), red[..4]);
+        assert_eq!(read_image(&ctx, &texture, 1, 1, 2, 2), blue);
+        assert_eq!(read_image(&ctx, &texture, 2, 1, 1, 1), blue[..4]);
+    })
+}
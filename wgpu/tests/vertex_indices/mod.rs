@@ -78,6 +78,7 @@ fn pulling_common(
                 }],
             }),
             multiview: None,
+            cache: None,
         });
 
     let dummy = ctx
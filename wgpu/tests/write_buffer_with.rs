@@ -0,0 +1,38 @@
+use std::num::NonZeroU64;
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn write_buffer_with() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        ctx.queue
+            .write_buffer_with(&buffer, 4, NonZeroU64::new(8).unwrap())
+            .unwrap()
+            .copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        ctx.queue.submit(None);
+
+        let slice = buffer.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        assert_eq!(
+            &slice.get_mapped_range()[..],
+            &[0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0, 0, 0]
+        );
+        buffer.unmap();
+
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let view = ctx
+            .queue
+            .write_buffer_with(&buffer, 12, NonZeroU64::new(8).unwrap());
+        assert!(view.is_none());
+        assert!(pollster::block_on(ctx.device.pop_error_scope()).is_some());
+    })
+}